@@ -0,0 +1,28 @@
+use std::env::current_dir;
+
+use cosmwasm_schema::{export_schema, schema_for, write_api};
+
+use pfc_steak::hub::{
+    BondReceipt, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ReceiveMsg, SudoMsg,
+    UnbondReceipt, WithdrawReceipt,
+};
+
+fn main() {
+    write_api! {
+        instantiate: InstantiateMsg,
+        execute: ExecuteMsg,
+        query: QueryMsg,
+        migrate: MigrateMsg,
+        sudo: SudoMsg,
+    }
+
+    // Not part of an entry point's message: sent inside `Cw20ReceiveMsg::msg`, or returned as the
+    // `data` of bonds, unbonds and withdrawals
+    let mut out_dir = current_dir().unwrap();
+    out_dir.push("schema");
+    out_dir.push("raw");
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
+    export_schema(&schema_for!(BondReceipt), &out_dir);
+    export_schema(&schema_for!(UnbondReceipt), &out_dir);
+    export_schema(&schema_for!(WithdrawReceipt), &out_dir);
+}
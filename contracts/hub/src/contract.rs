@@ -1,25 +1,28 @@
 use cosmwasm_std::{
-    entry_point, from_binary, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Reply,
-    Response, StdError, StdResult, Uint128,
+    entry_point, from_binary, to_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo,
+    Order, Reply, Response, StdError, StdResult, Storage, Uint128,
 };
 use cw20::Cw20ReceiveMsg;
 
 use pfc_steak::hub::{
     CallbackMsg, ExecuteMsg, FeeType, InstantiateMsg, MigrateMsg, QueryMsg, ReceiveMsg,
+    ReceiveMsgKind, SudoMsg,
 };
 
-use crate::helpers::{get_denom_balance, unwrap_reply};
-use crate::migrations::ConfigV100;
+use crate::debug::with_breadcrumbs;
+use crate::helpers::{assert_message_cap, get_denom_balance, unwrap_reply};
+use crate::migrations::{
+    backfill_batches, count_previous_batches, key_batches_by_denom, ConfigV100,
+};
 use crate::state::State;
-use crate::{execute, queries};
+use crate::types::ReplyContext;
+use crate::{execute, queries, treasury};
 use cw2::{get_contract_version, set_contract_version, ContractVersion};
 
 /// Contract name that is used for migration.
 pub const CONTRACT_NAME: &str = "steak-hub";
 /// Contract version that is used for migration.
 pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
-pub const REPLY_INSTANTIATE_TOKEN: u64 = 1;
-pub const REPLY_REGISTER_RECEIVED_COINS: u64 = 2;
 
 #[entry_point]
 pub fn instantiate(
@@ -34,10 +37,68 @@ pub fn instantiate(
 
 #[entry_point]
 pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+    with_breadcrumbs(deps, |mut deps| {
+        let res = dispatch_execute(deps.branch(), env, info, msg)?;
+        assert_message_cap(deps.storage, &res)?;
+        Ok(res)
+    })
+}
+
+fn dispatch_execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
     let api = deps.api;
     match msg {
         ExecuteMsg::Receive(cw20_msg) => receive(deps, env, info, cw20_msg),
-        ExecuteMsg::Bond { receiver } => execute::bond(
+        ExecuteMsg::Bond {
+            receiver,
+            referrer,
+            min_usteak_out,
+        } => execute::bond(
+            deps,
+            env,
+            info.sender.clone(),
+            receiver
+                .map(|s| api.addr_validate(&s))
+                .transpose()?
+                .unwrap_or(info.sender),
+            referrer.map(|s| api.addr_validate(&s)).transpose()?,
+            info.funds,
+            min_usteak_out,
+        ),
+        ExecuteMsg::BondMany {
+            receivers,
+            referrer,
+            min_usteak_out,
+        } => execute::bond_many(
+            deps,
+            env,
+            info.sender,
+            receivers
+                .into_iter()
+                .map(|(receiver, amount)| Ok((api.addr_validate(&receiver)?, amount)))
+                .collect::<StdResult<_>>()?,
+            referrer.map(|s| api.addr_validate(&s)).transpose()?,
+            info.funds,
+            min_usteak_out,
+        ),
+        ExecuteMsg::ClaimReferralRewards {} => {
+            execute::claim_referral_rewards(deps, env, info.sender)
+        }
+        ExecuteMsg::ClaimVestedFees {} => execute::claim_vested_fees(deps, env, info.sender),
+        ExecuteMsg::ReleaseFeeEscrow {} => execute::release_fee_escrow(deps),
+        ExecuteMsg::ClassifyFunds {
+            amount,
+            classification,
+        } => execute::classify_funds(deps, env, info.sender, amount, classification),
+        ExecuteMsg::RetryTokenInstantiate {} => execute::retry_token_instantiate(deps, info.sender),
+        ExecuteMsg::BondOnBehalf { original_sender } => {
+            execute::bond_on_behalf(deps, env, info.sender, original_sender, info.funds)
+        }
+        ExecuteMsg::BondTokenizedShares { receiver } => execute::bond_tokenized_shares(
             deps,
             env,
             receiver
@@ -46,7 +107,7 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
                 .unwrap_or(info.sender),
             info.funds,
         ),
-        ExecuteMsg::WithdrawUnbonded { receiver } => execute::withdraw_unbonded(
+        ExecuteMsg::WithdrawUnbonded { receiver, amount } => execute::withdraw_unbonded(
             deps,
             env,
             info.sender.clone(),
@@ -54,32 +115,74 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
                 .map(|s| api.addr_validate(&s))
                 .transpose()?
                 .unwrap_or(info.sender),
+            amount,
         ),
         ExecuteMsg::WithdrawUnbondedAdmin { address } => {
             execute::withdraw_unbonded_admin(deps, env, info.sender, api.addr_validate(&address)?)
         }
+        ExecuteMsg::CreateUnbondOrder {
+            shares_per_epoch,
+            epochs,
+        } => execute::create_unbond_order(
+            deps,
+            env,
+            info.sender,
+            info.funds,
+            shares_per_epoch,
+            epochs,
+        ),
+        ExecuteMsg::CancelUnbondOrder {} => execute::cancel_unbond_order(deps, env, info.sender),
+        ExecuteMsg::QueueUnbond {
+            receiver,
+            min_native_out,
+        } => {
+            let receiver = receiver
+                .map(|s| deps.api.addr_validate(&s))
+                .transpose()?
+                .unwrap_or_else(|| info.sender.clone());
+            execute::queue_unbond_native(
+                deps,
+                env,
+                info.sender,
+                receiver,
+                info.funds,
+                min_native_out,
+            )
+        }
+        ExecuteMsg::MigrateToken { new_backend } => {
+            execute::migrate_token(deps, env, info.sender, new_backend)
+        }
+        ExecuteMsg::UpdateTokenAdmin { admin } => {
+            execute::update_token_admin(deps, info.sender, admin)
+        }
+        ExecuteMsg::MigrateTokenContract { new_code_id, msg } => {
+            execute::migrate_token_contract(deps, env, info.sender, new_code_id, msg)
+        }
         ExecuteMsg::AddValidator { validator } => {
             execute::add_validator(deps, info.sender, validator)
         }
-        ExecuteMsg::RemoveValidator { validator } => {
-            execute::remove_validator(deps, env, info.sender, validator)
-        }
         ExecuteMsg::RemoveValidatorEx { validator } => {
             execute::remove_validator_ex(deps, env, info.sender, validator)
         }
+        ExecuteMsg::ReplaceValidator { old, new } => {
+            execute::replace_validator(deps, env, info.sender, old, new)
+        }
         ExecuteMsg::TransferOwnership { new_owner } => {
             execute::transfer_ownership(deps, info.sender, new_owner)
         }
         ExecuteMsg::AcceptOwnership {} => execute::accept_ownership(deps, info.sender),
+        ExecuteMsg::ProposeChange { change } => {
+            execute::propose_change(deps, env, info.sender, change)
+        }
+        ExecuteMsg::AcceptChange { id } => execute::accept_change(deps, env, info.sender, id),
+        ExecuteMsg::CancelChange { id } => execute::cancel_change(deps, info.sender, id),
         ExecuteMsg::Harvest {} => execute::harvest(deps, env, info.sender),
         ExecuteMsg::Rebalance { minimum } => execute::rebalance(deps, env, minimum),
-        ExecuteMsg::Reconcile {} => execute::reconcile(deps, env),
-        ExecuteMsg::SubmitBatch {} => execute::submit_batch(deps, env),
-        ExecuteMsg::TransferFeeAccount {
-            fee_account_type,
-            new_fee_account,
-        } => execute::transfer_fee_account(deps, info.sender, fee_account_type, new_fee_account),
-        ExecuteMsg::UpdateFee { new_fee } => execute::update_fee(deps, info.sender, new_fee),
+        ExecuteMsg::ContinueRebalance {} => execute::continue_rebalance(deps, env),
+        ExecuteMsg::Reconcile {} => execute::reconcile(deps, env, info.sender),
+        ExecuteMsg::SubmitBatch { exclude } => {
+            execute::submit_batch(deps, env, info.sender, exclude)
+        }
         ExecuteMsg::Callback(callback_msg) => callback(deps, env, info, callback_msg),
         ExecuteMsg::PauseValidator { validator } => {
             execute::pause_validator(deps, env, info.sender, validator)
@@ -87,15 +190,189 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
         ExecuteMsg::UnPauseValidator { validator } => {
             execute::unpause_validator(deps, env, info.sender, validator)
         }
+        ExecuteMsg::SuspendValidator { validator, until } => {
+            execute::suspend_validator(deps, env, info.sender, validator, until)
+        }
+        ExecuteMsg::ReinstateValidator { validator } => {
+            execute::reinstate_validator(deps, env, info.sender, validator)
+        }
+        ExecuteMsg::SetSlashLimit { slash_limit } => {
+            execute::set_slash_limit(deps, info.sender, slash_limit)
+        }
         ExecuteMsg::SetUnbondPeriod { unbond_period } => {
             execute::set_unbond_period(deps, env, info.sender, unbond_period)
         }
+        ExecuteMsg::SetMinReinvestAmount {
+            min_reinvest_amount,
+        } => execute::set_min_reinvest_amount(deps, info.sender, min_reinvest_amount),
+        ExecuteMsg::SetMinBondAmount { min_bond_amount } => {
+            execute::set_min_bond_amount(deps, info.sender, min_bond_amount)
+        }
+        ExecuteMsg::SetMaxMessages { max_messages } => {
+            execute::set_max_messages(deps, info.sender, max_messages)
+        }
+        ExecuteMsg::SetMinRedelegation { min_redelegation } => {
+            execute::set_min_redelegation(deps, info.sender, min_redelegation)
+        }
+        ExecuteMsg::SetMaxRebaseBps { max_rebase_bps } => {
+            execute::set_max_rebase_bps(deps, info.sender, max_rebase_bps)
+        }
+        ExecuteMsg::SetMaxHarvestStaleness {
+            max_harvest_staleness,
+        } => execute::set_max_harvest_staleness(deps, info.sender, max_harvest_staleness),
+        ExecuteMsg::SetSubmitDeadlineBuffer {
+            submit_deadline_buffer,
+        } => execute::set_submit_deadline_buffer(deps, info.sender, submit_deadline_buffer),
+        ExecuteMsg::SetHarvestGranter { granter } => {
+            execute::set_harvest_granter(deps, info.sender, granter)
+        }
+        ExecuteMsg::SetReferralBps { referral_bps } => {
+            execute::set_referral_bps(deps, info.sender, referral_bps)
+        }
+        ExecuteMsg::SetRestakingAdapter { adapter } => {
+            execute::set_restaking_adapter(deps, info.sender, adapter)
+        }
+        ExecuteMsg::RegisterHook {
+            event_kind,
+            contract,
+        } => execute::register_hook(deps, info.sender, event_kind, api.addr_validate(&contract)?),
+        ExecuteMsg::DeregisterHook {
+            event_kind,
+            contract,
+        } => execute::deregister_hook(deps, info.sender, event_kind, api.addr_validate(&contract)?),
+        ExecuteMsg::SetMeasureDeposits { enabled } => {
+            execute::set_measure_deposits(deps, info.sender, enabled)
+        }
+        ExecuteMsg::SetMinDelegationPerValidator { min_delegation } => {
+            execute::set_min_delegation_per_validator(deps, info.sender, min_delegation)
+        }
+        ExecuteMsg::SetBondRateLimit { limit } => {
+            execute::set_bond_rate_limit(deps, info.sender, limit)
+        }
+        ExecuteMsg::SetMinHarvestReward { min_reward } => {
+            execute::set_min_harvest_reward(deps, info.sender, min_reward)
+        }
+        ExecuteMsg::SetMinSelfDelegation {
+            min_self_delegation,
+        } => execute::set_min_self_delegation(deps, info.sender, min_self_delegation),
+        ExecuteMsg::CheckValidators {} => execute::check_validators(deps, env),
+        ExecuteMsg::SetMaxBatchExclusions { max_exclusions } => {
+            execute::set_max_batch_exclusions(deps, info.sender, max_exclusions)
+        }
+        ExecuteMsg::SetForbiddenDepositDenoms { denoms } => {
+            execute::set_forbidden_deposit_denoms(deps, info.sender, denoms)
+        }
+        ExecuteMsg::SetTokenAdmin { admin } => {
+            execute::set_token_admin(deps, env, info.sender, admin)
+        }
+        ExecuteMsg::AbsorbDelegation { validator, amount } => {
+            execute::absorb_delegation(deps, env, info.sender, validator, amount)
+        }
+        ExecuteMsg::SetSwapRoute { denom, route } => {
+            execute::set_swap_route(deps, info.sender, denom, route)
+        }
+        ExecuteMsg::FundRewardCampaign { start, end } => {
+            execute::fund_reward_campaign(deps, env, info.sender, info.funds, start, end)
+        }
+        ExecuteMsg::SwapRewards { denom, min_out } => {
+            execute::swap_rewards(deps, env, denom, min_out)
+        }
+        ExecuteMsg::ReportRestaked { amount } => {
+            execute::report_restaked(deps, env, info.sender, amount)
+        }
+        ExecuteMsg::SetBatchRollover { batch_rollover } => {
+            execute::set_batch_rollover(deps, info.sender, batch_rollover)
+        }
+        ExecuteMsg::SetFeeVesting { fee_vesting } => {
+            execute::set_fee_vesting(deps, info.sender, fee_vesting)
+        }
+        ExecuteMsg::UpdateFeeExemptions { add, remove } => execute::update_fee_exemptions(
+            deps,
+            info.sender,
+            add.iter()
+                .map(|holder| api.addr_validate(holder))
+                .collect::<StdResult<_>>()?,
+            remove
+                .iter()
+                .map(|holder| api.addr_validate(holder))
+                .collect::<StdResult<_>>()?,
+        ),
+        ExecuteMsg::SetMinUnbondShares { min_unbond_shares } => {
+            execute::set_min_unbond_shares(deps, info.sender, min_unbond_shares)
+        }
+        ExecuteMsg::SetPriorityUnbonders { addresses } => {
+            execute::set_priority_unbonders(deps, info.sender, addresses)
+        }
+        ExecuteMsg::SetDenomDecimals { decimals } => {
+            execute::set_denom_decimals(deps, info.sender, decimals)
+        }
+        ExecuteMsg::SetValidatorCap { validator, cap } => {
+            execute::set_validator_cap(deps, info.sender, validator, cap)
+        }
+        ExecuteMsg::SetValidatorDifficulty {
+            validator,
+            difficulty,
+        } => execute::set_validator_difficulty(deps, info.sender, validator, difficulty),
+        ExecuteMsg::SetStakingProtoUrls { urls } => {
+            execute::set_staking_proto_urls(deps, info.sender, urls)
+        }
+        ExecuteMsg::SetIcs20Contract {
+            ics20_contract,
+            channel,
+        } => execute::set_ics20_contract(deps, info.sender, ics20_contract, channel),
+        ExecuteMsg::SetRatePublisher { rate_publisher } => {
+            execute::set_rate_publisher(deps, info.sender, rate_publisher)
+        }
+        ExecuteMsg::SetTreasury { treasury } => treasury::set_treasury(deps, info.sender, treasury),
+        ExecuteMsg::ProvideLiquidity {} => treasury::provide_liquidity(deps, env, info.sender),
+        ExecuteMsg::TransferTreasuryLp { recipient, amount } => {
+            treasury::transfer_treasury_lp(deps, info.sender, recipient, amount)
+        }
+        ExecuteMsg::SetAcceptedToken {
+            token,
+            receive_msgs,
+        } => execute::set_accepted_token(deps, info.sender, token, receive_msgs),
+        ExecuteMsg::SetHarvestMode { mode } => execute::set_harvest_mode(deps, info.sender, mode),
+        ExecuteMsg::SetReconcileMode { mode } => {
+            execute::set_reconcile_mode(deps, info.sender, mode)
+        }
+        ExecuteMsg::SetRemainderPolicy { policy } => {
+            execute::set_remainder_policy(deps, info.sender, policy)
+        }
+        ExecuteMsg::SetFeeSplitInterface { interface } => {
+            execute::set_fee_split_interface(deps, info.sender, interface)
+        }
+        ExecuteMsg::SetGuardian { guardian } => execute::set_guardian(deps, info.sender, guardian),
+        ExecuteMsg::SetCapabilities { capabilities } => {
+            execute::set_capabilities(deps, info.sender, capabilities)
+        }
+        ExecuteMsg::SetExchangeRateEpsilon { epsilon } => {
+            execute::set_exchange_rate_epsilon(deps, info.sender, epsilon)
+        }
+        ExecuteMsg::AcknowledgeSlash { validator } => {
+            execute::acknowledge_slash(deps, env, info.sender, validator)
+        }
         ExecuteMsg::UpdateEntropy { entropy } => {
             execute::update_entropy(deps, env, info.sender, entropy)
         }
         ExecuteMsg::SubmitProof { nonce, validator } => {
             execute::submit_proof(deps, env, info.sender, nonce, validator)
         }
+        ExecuteMsg::SetMiningParams {
+            difficulty,
+            entropy,
+            enabled,
+            proof_cooldown_blocks,
+            power_normalization,
+        } => execute::set_mining_params(
+            deps,
+            info.sender,
+            difficulty,
+            entropy,
+            enabled,
+            proof_cooldown_blocks,
+            power_normalization,
+        ),
     }
 }
 
@@ -106,26 +383,42 @@ fn receive(
     cw20_msg: Cw20ReceiveMsg,
 ) -> StdResult<Response> {
     let api = deps.api;
-    match from_binary(&cw20_msg.msg)? {
-        ReceiveMsg::QueueUnbond { receiver } => {
-            let state = State::default();
+    let receive_msg: ReceiveMsg = from_binary(&cw20_msg.msg)?;
 
-            let steak_token = state.steak_token.load(deps.storage)?;
-            if info.sender != steak_token {
-                return Err(StdError::generic_err(format!(
-                    "expecting Steak token, received {}",
-                    info.sender
-                )));
-            }
+    let state = State::default();
+    state.assert_token_accepted(deps.storage, &info.sender, receive_msg.kind())?;
 
-            execute::queue_unbond(
-                deps,
-                env,
-                api.addr_validate(&receiver.unwrap_or(cw20_msg.sender))?,
-                cw20_msg.amount,
-            )
+    match receive_msg {
+        ReceiveMsg::QueueUnbond {
+            receiver,
+            min_native_out,
+        } => {
+            // The amount is counted as Steak shares, so no other token may stand in for it,
+            // whatever the registry allows
+            assert_steak_token(deps.storage, &info.sender)?;
+            let sender = api.addr_validate(&cw20_msg.sender)?;
+            let receiver = receiver
+                .map(|s| api.addr_validate(&s))
+                .transpose()?
+                .unwrap_or_else(|| sender.clone());
+            execute::queue_unbond_cw20(deps, env, sender, receiver, cw20_msg.amount, min_native_out)
         }
+        ReceiveMsg::SwapToken {} => {
+            let holder = api.addr_validate(&cw20_msg.sender)?;
+            execute::swap_token(deps, env, info.sender, holder, cw20_msg.amount)
+        }
+    }
+}
+
+fn assert_steak_token(storage: &dyn Storage, token: &Addr) -> StdResult<()> {
+    let steak_token = State::default().steak_token.load(storage)?;
+    if *token != steak_token {
+        return Err(StdError::generic_err(format!(
+            "expecting Steak token, received {}",
+            token
+        )));
     }
+    Ok(())
 }
 
 fn callback(
@@ -145,27 +438,107 @@ fn callback(
     }
 }
 
+#[entry_point]
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> StdResult<Response> {
+    match msg {
+        SudoMsg::Tick {} => execute::tick(deps, env),
+    }
+}
+
 #[entry_point]
 pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> StdResult<Response> {
-    match reply.id {
-        1 => execute::register_steak_token(deps, unwrap_reply(reply)?),
-        REPLY_REGISTER_RECEIVED_COINS => {
+    with_breadcrumbs(deps, |deps| dispatch_reply(deps, env, reply))
+}
+
+fn dispatch_reply(deps: DepsMut, env: Env, reply: Reply) -> StdResult<Response> {
+    // Submessages register what their reply is for when they are created; see
+    // `State::register_reply`
+    let state = State::default();
+    let context = state
+        .reply_contexts
+        .may_load(deps.storage, reply.id)?
+        .ok_or_else(|| StdError::generic_err(format!("invalid reply id: {}", reply.id)))?;
+    state.reply_contexts.remove(deps.storage, reply.id);
+
+    match context {
+        ReplyContext::InstantiateToken => execute::register_steak_token(deps, reply.result),
+        ReplyContext::RegisterReceivedCoins => {
             execute::register_received_coins(deps, env, unwrap_reply(reply)?.events)
         }
-        id => Err(StdError::generic_err(format!(
-            "invalid reply id: {}; must be 1-2",
-            id
-        ))),
+        ReplyContext::Undelegate {
+            batch_id,
+            validator,
+        } => execute::finish_undelegation(
+            deps,
+            env,
+            batch_id,
+            validator,
+            unwrap_reply(reply)?.events,
+        ),
+        ReplyContext::Tick { action } => execute::finish_tick_action(action, reply.result),
+        ReplyContext::RestakingHook => execute::finish_restaking_hook(reply.result),
+        ReplyContext::Hook { contract } => execute::finish_hook(contract, reply.result),
+        ReplyContext::FeeSplitDeposit { amount } => {
+            execute::finish_fee_split_deposit(deps, amount, reply.result)
+        }
+        ReplyContext::RedeemTokenizedShares {
+            receiver,
+            validator,
+            delegation_before,
+        } => execute::finish_tokenized_shares_bond(
+            deps,
+            env,
+            receiver,
+            validator,
+            delegation_before,
+            unwrap_reply(reply)?.events,
+        ),
     }
 }
 
 #[entry_point]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::Config {} => to_binary(&queries::config(deps)?),
+        QueryMsg::Config {} => to_binary(&queries::config(deps, env)?),
         QueryMsg::State {} => to_binary(&queries::state(deps, env)?),
+        QueryMsg::PendingChanges { start_after, limit } => {
+            to_binary(&queries::pending_changes(deps, env, start_after, limit)?)
+        }
+        QueryMsg::Info {} => to_binary(&queries::info(deps, env)?),
+        QueryMsg::Schedule {} => to_binary(&queries::schedule(deps, env)?),
+        QueryMsg::StakingProtoUrls {} => to_binary(&queries::staking_proto_urls(deps)?),
+        QueryMsg::Counters {} => to_binary(&queries::counters(deps)?),
+        QueryMsg::Metrics {} => to_binary(&queries::metrics(deps, env)?),
+        QueryMsg::DenomMetadata {} => to_binary(&queries::denom_metadata(deps)?),
+        QueryMsg::ExchangeRateFloor {} => to_binary(&queries::exchange_rate_floor(deps)?),
+        QueryMsg::FeeLedger { start_after, limit } => {
+            to_binary(&queries::fee_ledger(deps, start_after, limit)?)
+        }
+        QueryMsg::SupplyCheckpoints { start_after, limit } => {
+            to_binary(&queries::supply_checkpoints(deps, start_after, limit)?)
+        }
+        QueryMsg::SupplyCheckpointAt { height } => {
+            to_binary(&queries::supply_checkpoint_at(deps, height)?)
+        }
+        QueryMsg::ReferralStats { referrer } => {
+            to_binary(&queries::referral_stats(deps, referrer)?)
+        }
+        QueryMsg::VestedFees { account } => to_binary(&queries::vested_fees(deps, env, account)?),
+        QueryMsg::Multi { queries } => {
+            let responses = queries
+                .into_iter()
+                .map(|msg| match msg {
+                    QueryMsg::Multi { .. } => {
+                        Err(StdError::generic_err("multi queries cannot be nested"))
+                    }
+                    msg => query(deps, env.clone(), msg),
+                })
+                .collect::<StdResult<Vec<Binary>>>()?;
+            to_binary(&responses)
+        }
         QueryMsg::PendingBatch {} => to_binary(&queries::pending_batch(deps)?),
         QueryMsg::PreviousBatch(id) => to_binary(&queries::previous_batch(deps, id)?),
+        QueryMsg::ReplayBatch { id } => to_binary(&queries::replay_batch(deps, id)?),
         QueryMsg::PreviousBatches { start_after, limit } => {
             to_binary(&queries::previous_batches(deps, start_after, limit)?)
         }
@@ -189,10 +562,61 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             start_after,
             limit,
         )?),
+        QueryMsg::UnbondRequestsBySender {
+            sender,
+            start_after,
+            limit,
+        } => to_binary(&queries::unbond_requests_by_sender(
+            deps,
+            sender,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::UnbondRequestCalls {
+            id,
+            user,
+            start_after,
+            limit,
+        } => to_binary(&queries::unbond_request_calls(
+            deps,
+            id,
+            user,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::WithdrawableAmount { user } => {
+            to_binary(&queries::withdrawable_amount(deps, env, user)?)
+        }
+        QueryMsg::RecentClaims { user } => to_binary(&queries::recent_claims(deps, user)?),
+        QueryMsg::UnbondEta { user } => to_binary(&queries::unbond_eta(deps, env, user)?),
+        QueryMsg::UnbondOrder { user } => to_binary(&queries::unbond_order(deps, user)?),
         QueryMsg::MinerParams {} => to_binary(&queries::miner_params(deps)?),
         QueryMsg::ValidatorMiningPowers { start_after, limit } => {
             to_binary(&queries::validator_mining_powers(deps, start_after, limit)?)
         }
+        QueryMsg::ValidatorSummary { validator } => {
+            to_binary(&queries::validator_summary(deps, env, validator)?)
+        }
+        QueryMsg::ValidatorNetApr { validator } => {
+            to_binary(&queries::validator_net_apr(deps, validator)?)
+        }
+        QueryMsg::ValidatorCaps { start_after, limit } => {
+            to_binary(&queries::validator_caps(deps, start_after, limit)?)
+        }
+        QueryMsg::ValidatorDifficulties { start_after, limit } => {
+            to_binary(&queries::validator_difficulties(deps, start_after, limit)?)
+        }
+        QueryMsg::AcceptedTokens { start_after, limit } => {
+            to_binary(&queries::accepted_tokens(deps, start_after, limit)?)
+        }
+        QueryMsg::RewardCampaigns { start_after, limit } => {
+            to_binary(&queries::reward_campaigns(deps, start_after, limit)?)
+        }
+        QueryMsg::Hooks { event_kind } => to_binary(&queries::hooks(deps, event_kind)?),
+        QueryMsg::SwapRoutes { start_after, limit } => {
+            to_binary(&queries::swap_routes(deps, start_after, limit)?)
+        }
+        QueryMsg::Treasury {} => to_binary(&queries::treasury(deps, env)?),
     }
 }
 
@@ -294,6 +718,36 @@ pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> StdResult<Response>
             ))
         }
     }
+
+    // hubs instantiated before the token registry existed only know the Steak token implicitly
+    let state = State::default();
+    let steak_token = state.steak_token.load(deps.storage)?;
+    if !state.accepted_tokens.has(deps.storage, &steak_token) {
+        state.accepted_tokens.save(
+            deps.storage,
+            &steak_token,
+            &vec![ReceiveMsgKind::QueueUnbond],
+        )?;
+    }
+
+    // validators removed before their mining power was purged on removal still count towards the
+    // total, skewing every other validator's target delegation
+    let validators = state.validators.load(deps.storage)?;
+    let ghosts = state
+        .validator_mining_powers
+        .keys(deps.storage, None, None, Order::Ascending)
+        .filter(|validator| match validator {
+            Ok(validator) => !validators.contains(validator),
+            Err(_) => true,
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    for validator in ghosts {
+        state.remove_mining_power(deps.storage, &validator)?;
+    }
+
+    let batches_rekeyed = key_batches_by_denom(deps.storage)?;
+    let batches_backfilled = backfill_batches(deps.storage)?;
+    count_previous_batches(deps.storage)?;
     /*
     let state = State::default();
 
@@ -301,11 +755,23 @@ pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> StdResult<Response>
     state.fee_rate.save(deps.storage,&Decimal::from_ratio(10u32,100u32))?;
 
      */
+    // the running totals are meant to be monotonic for the contract's whole life. No migration step
+    // rewrites them, so they carry over as long as what's stored still reads as this version's
+    // `Counters`; refuse to migrate rather than have the next bump start them over from zero
+    state.counters.may_load(deps.storage).map_err(|err| {
+        StdError::generic_err(format!(
+            "stored counters don't match this version's schema: {}",
+            err
+        ))
+    })?;
+
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::new()
         .add_attribute("previous_contract_name", &contract_version.contract)
         .add_attribute("previous_contract_version", &contract_version.version)
         .add_attribute("new_contract_name", CONTRACT_NAME)
-        .add_attribute("new_contract_version", CONTRACT_VERSION))
+        .add_attribute("new_contract_version", CONTRACT_VERSION)
+        .add_attribute("batches_rekeyed", batches_rekeyed.to_string())
+        .add_attribute("batches_backfilled", batches_backfilled.to_string()))
 }
@@ -0,0 +1,100 @@
+//! Execution breadcrumbs for profiling heavy paths on mainnet forks. Built with the `debug-events`
+//! feature, every execute and reply adds `debug_*` attributes counting the storage reads and writes
+//! it made and the submessages it sent. Without the feature, nothing is added
+
+use cosmwasm_std::{DepsMut, Response, StdResult};
+
+#[cfg(feature = "debug-events")]
+use std::cell::Cell;
+
+#[cfg(feature = "debug-events")]
+use cosmwasm_std::{Order, Record, Storage};
+
+/// Storage counting the reads and writes made through it. Each record a range yields counts as
+/// one read
+#[cfg(feature = "debug-events")]
+pub struct CountingStorage<'a> {
+    inner: &'a mut dyn Storage,
+    reads: Cell<u64>,
+    writes: u64,
+}
+
+#[cfg(feature = "debug-events")]
+impl<'a> CountingStorage<'a> {
+    pub fn new(inner: &'a mut dyn Storage) -> Self {
+        Self {
+            inner,
+            reads: Cell::new(0),
+            writes: 0,
+        }
+    }
+
+    pub fn reads(&self) -> u64 {
+        self.reads.get()
+    }
+
+    pub fn writes(&self) -> u64 {
+        self.writes
+    }
+}
+
+#[cfg(feature = "debug-events")]
+impl<'a> Storage for CountingStorage<'a> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.reads.set(self.reads.get() + 1);
+        self.inner.get(key)
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        let reads = &self.reads;
+        Box::new(
+            self.inner
+                .range(start, end, order)
+                .inspect(move |_| reads.set(reads.get() + 1)),
+        )
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.writes += 1;
+        self.inner.set(key, value)
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.writes += 1;
+        self.inner.remove(key)
+    }
+}
+
+/// Run an entry point, adding the `debug_*` attributes to its response
+#[cfg(feature = "debug-events")]
+pub fn with_breadcrumbs<F>(deps: DepsMut, f: F) -> StdResult<Response>
+where
+    F: FnOnce(DepsMut) -> StdResult<Response>,
+{
+    let mut storage = CountingStorage::new(deps.storage);
+    let res = f(DepsMut {
+        storage: &mut storage,
+        api: deps.api,
+        querier: deps.querier,
+    })?;
+
+    let submessages = res.messages.len();
+    Ok(res
+        .add_attribute("debug_storage_reads", storage.reads().to_string())
+        .add_attribute("debug_storage_writes", storage.writes().to_string())
+        .add_attribute("debug_submessages", submessages.to_string()))
+}
+
+/// Run an entry point as is
+#[cfg(not(feature = "debug-events"))]
+pub fn with_breadcrumbs<F>(deps: DepsMut, f: F) -> StdResult<Response>
+where
+    F: FnOnce(DepsMut) -> StdResult<Response>,
+{
+    f(deps)
+}
@@ -1,43 +1,136 @@
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::ops::Mul;
 use std::str::FromStr;
 
 use cosmwasm_std::{
-    to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Decimal256, DepsMut, Env, Event, Order,
-    Response, StdError, StdResult, Storage, SubMsg, SubMsgResponse, Uint128, Uint64, WasmMsg,
+    to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256, Deps, DepsMut, Env,
+    Event, Order, QuerierWrapper, Response, StdError, StdResult, Storage, SubMsg, SubMsgResponse,
+    SubMsgResult, Uint128, Uint64, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, MinterResponse};
-use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
+use cw20_base::msg::{
+    InstantiateMarketingInfo as Cw20InstantiateMarketingInfo, InstantiateMsg as Cw20InstantiateMsg,
+};
 use sha2::{Digest, Sha256};
 
-use crate::contract::{REPLY_INSTANTIATE_TOKEN, REPLY_REGISTER_RECEIVED_COINS};
+use pfc_steak::chains::ChainPreset;
 use pfc_steak::hub::{
-    Batch, CallbackMsg, ExecuteMsg, FeeType, InstantiateMsg, PendingBatch, UnbondRequest,
+    Batch, BatchRollover, BondRateLimit, BondReceipt, BridgeProtocol, CallbackMsg, Capabilities,
+    ConfigChange, ExecuteMsg, FeeLedgerEntry, FeeSplitInterface, FeeType, FeeVesting,
+    FundsClassification, HarvestMode, HookEventKind, InstantiateMsg, MiningPowerNormalization,
+    OperationRecord, PendingBatch, PendingChange, RatePublisher, ReceiveMsgKind, RecentClaim,
+    ReconcileMode, RemainderPolicy, RestakingAdapter, RestakingHookMsg, RewardCampaign,
+    StakingProtoUrls, SteakHookMsg, SupplyCheckpoint, SwapRoute, TokenBackend, UnbondCall,
+    UnbondOrder, UnbondReceipt, UnbondRequest, UnbondRequestSource, WithdrawReceipt,
 };
 use pfc_steak::DecimalCheckedOps;
 
 use crate::helpers::{
-    get_denom_balance, parse_received_fund, query_cw20_total_supply, query_delegation,
-    query_delegations,
+    compute_reconciled_batches, derive_intermediate_sender, format_denom_amount, get_denom_balance,
+    measure_received_fund, native_due_unreconciled, native_owed, parse_received_fund,
+    parse_rfc3339_seconds, query_bonded_delegations, query_chain_unbonding_time,
+    query_cw20_balance, query_delegation, query_delegations, query_draining_delegations,
+    query_pending_rewards, query_self_delegation, query_usteak_balance, query_usteak_supply,
 };
 use crate::math::{
     compute_mint_amount, compute_redelegations_for_rebalancing, compute_redelegations_for_removal,
-    compute_target_delegation_from_mining_power, compute_unbond_amount, compute_undelegations,
-    reconcile_batches,
+    compute_target_delegation_from_mining_power, compute_unbond_amount,
+    compute_undelegations_with_floor, select_validator_for_deposit,
 };
 use crate::state::State;
-use crate::types::{Coins, Delegation, RewardWithdrawal};
+use crate::treasury::accrue_treasury_share;
+use crate::types::{
+    burn_msg, create_denom_msg, fee_split_deposit_msg, mint_msg, rate_publication_msg,
+    swap_exact_amount_in_msg, token_factory_denom, Coins, Delegation, Ics20TransferMsg,
+    RatePayload, Redelegation, ReplyContext, RewardWithdrawal, SharesRedemption, ToCosmosMsg,
+    VestingTranche,
+};
 
 // minimum amount of time it should take to mine a block (20 seconds)
 pub const TARGET_MINING_DURATION_FLOOR_SECONDS: u64 = 20u64;
 // maximum amount of time it should take to mine a block (5 minutes)
 pub const TARGET_MINING_DURATION_CEILING_SECONDS: u64 = 300u64;
+// highest mining difficulty the owner may set; a hex-encoded SHA-256 digest has 64 characters
+pub const MAX_MINING_DIFFICULTY: u64 = 64u64;
+// largest number of decimals supported for the bond denom (e.g. 18-decimal EVM-style denoms)
+pub const MAX_DENOM_DECIMALS: u8 = 18u8;
+/// Longest label the Steak token may be instantiated with
+pub const MAX_TOKEN_LABEL_LENGTH: usize = 128;
+/// Number of per-call records kept for an unbonding request before further calls are merged
+pub const MAX_UNBOND_CALLS_PER_REQUEST: u32 = 20u32;
+/// Number of redelegations sent by a single `Rebalance`, `ContinueRebalance` or `RemoveValidator`,
+/// to stay within the block gas limit; the rest wait in `State::pending_rebalance`
+pub const MAX_REDELEGATIONS_PER_TX: usize = 10;
+/// Number of contracts that may be notified of each kind of event
+pub const MAX_HOOKS_PER_EVENT_KIND: usize = 10;
+/// Number of reward campaigns that may run at once, as every harvest goes through all of them
+pub const MAX_REWARD_CAMPAIGNS: usize = 32;
+/// Number of unbond orders that may be open at once, as every batch submission goes through all
+/// of them
+pub const MAX_UNBOND_ORDERS: usize = 100;
+/// Number of a user's own batches `WithdrawUnbonded` reconciles before paying out
+pub const MAX_AUTO_RECONCILED_BATCHES: usize = 10;
+/// Number of addresses that may be exempt from the protocol fee, as every harvest queries the
+/// Steak balance of each
+pub const MAX_FEE_EXEMPTIONS: usize = 20;
 
 //--------------------------------------------------------------------------------------------------
 // Instantiation
 //--------------------------------------------------------------------------------------------------
 
+/// Catch a hub being instantiated with settings of another chain than the one it is deployed to
+fn assert_chain_preset(preset: ChainPreset, msg: &InstantiateMsg) -> StdResult<()> {
+    let config = preset.config();
+    if msg.denom != config.denom {
+        return Err(StdError::generic_err(format!(
+            "denom {} doesn't match the {} of {}",
+            msg.denom, config.denom, config.chain_id
+        )));
+    }
+    if msg.unbond_period != config.unbond_period {
+        return Err(StdError::generic_err(format!(
+            "unbond period {} doesn't match the {} of {}",
+            msg.unbond_period, config.unbond_period, config.chain_id
+        )));
+    }
+    if let Some(validator) = msg
+        .validators
+        .iter()
+        .find(|v| !preset.is_valoper_address(v))
+    {
+        return Err(StdError::generic_err(format!(
+            "{} is not a validator address on {}",
+            validator, config.chain_id
+        )));
+    }
+    Ok(())
+}
+
+/// Labels show up in explorers and `wasmd` queries, so keep them to one printable line
+fn validate_token_label(label: Option<String>) -> StdResult<String> {
+    let label = match label {
+        Some(label) => label.trim().to_string(),
+        None => return Ok("steak_token".to_string()),
+    };
+    if label.is_empty() {
+        return Err(StdError::generic_err("token label can't be empty"));
+    }
+    if label.chars().count() > MAX_TOKEN_LABEL_LENGTH {
+        return Err(StdError::generic_err(format!(
+            "token label can't be longer than {} characters",
+            MAX_TOKEN_LABEL_LENGTH
+        )));
+    }
+    if label.chars().any(char::is_control) {
+        return Err(StdError::generic_err(
+            "token label can't contain control characters",
+        ));
+    }
+    Ok(label)
+}
+
 pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> StdResult<Response> {
     let state = State::default();
 
@@ -48,8 +141,20 @@ pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> StdResult<Re
     if msg.fee_amount > msg.max_fee_amount {
         return Err(StdError::generic_err("fee can not exceed max fee"));
     }
-    let fee_type = FeeType::from_str(&msg.fee_account_type)
-        .map_err(|_| StdError::generic_err("Invalid Fee type: Wallet or FeeSplit only"))?;
+    let denom_decimals = msg.denom_decimals.unwrap_or(6);
+    if denom_decimals > MAX_DENOM_DECIMALS {
+        return Err(StdError::generic_err(format!(
+            "decimals cannot exceed {}",
+            MAX_DENOM_DECIMALS
+        )));
+    }
+    if let Some(preset) = msg.chain {
+        assert_chain_preset(preset, &msg)?;
+        state.chain_preset.save(deps.storage, &preset)?;
+        state
+            .staking_proto_urls
+            .save(deps.storage, &preset.staking_proto_urls())?;
+    }
 
     state
         .owner
@@ -60,20 +165,25 @@ pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> StdResult<Re
     state.unlocked_coins.save(deps.storage, &vec![])?;
     state.prev_denom.save(deps.storage, &Uint128::zero())?;
     state.denom.save(deps.storage, &msg.denom)?;
+    state.denom_decimals.save(deps.storage, &denom_decimals)?;
     state.max_fee_rate.save(deps.storage, &msg.max_fee_amount)?;
     state.fee_rate.save(deps.storage, &msg.fee_amount)?;
-    state.fee_account_type.save(deps.storage, &fee_type)?;
+    state
+        .fee_account_type
+        .save(deps.storage, &msg.fee_account_type)?;
 
     state
         .fee_account
         .save(deps.storage, &deps.api.addr_validate(&msg.fee_account)?)?;
 
-    state.pending_batch.save(
+    state.pending_batches.save(
         deps.storage,
+        &msg.denom,
         &PendingBatch {
             id: 1,
             usteak_to_burn: Uint128::zero(),
             est_unbond_start_time: env.block.time.seconds() + msg.epoch_period,
+            rollovers: 0,
         },
     )?;
     state
@@ -106,50 +216,119 @@ pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> StdResult<Re
         .total_mining_power
         .save(deps.storage, &Uint128::zero())?;
 
-    Ok(Response::new().add_submessage(SubMsg::reply_on_success(
-        CosmosMsg::Wasm(WasmMsg::Instantiate {
-            admin: Some(msg.owner), // use the owner as admin for now; can be changed later by a `MsgUpdateAdmin`
-            code_id: msg.cw20_code_id,
-            msg: to_binary(&Cw20InstantiateMsg {
-                name: msg.name,
-                symbol: msg.symbol,
-                decimals: msg.decimals,
-                initial_balances: vec![],
-                mint: Some(MinterResponse {
-                    minter: env.contract.address.into(),
-                    cap: None,
-                }),
-                marketing: msg.marketing,
-            })?,
-            funds: vec![],
-            label: msg.label.unwrap_or_else(|| "steak_token".to_string()),
-        }),
-        REPLY_INSTANTIATE_TOKEN,
-    )))
+    let label = validate_token_label(msg.label)?;
+    // Unless told otherwise, the hub administers the token, so the owner can upgrade it through
+    // `MigrateTokenContract`
+    let token_admin = match msg.token_admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => env.contract.address.clone(),
+    };
+    state.token_admin.save(deps.storage, &token_admin)?;
+
+    let token_instantiate_msg = WasmMsg::Instantiate {
+        admin: Some(token_admin.into()),
+        code_id: msg.cw20_code_id,
+        msg: to_binary(&Cw20InstantiateMsg {
+            name: msg.name,
+            symbol: msg.symbol,
+            decimals: msg.decimals,
+            initial_balances: vec![],
+            mint: Some(MinterResponse {
+                minter: env.contract.address.into(),
+                cap: None,
+            }),
+            marketing: msg.marketing.map(|info| Cw20InstantiateMarketingInfo {
+                project: info.project,
+                description: info.description,
+                marketing: info.marketing,
+                logo: info.logo,
+            }),
+        })?,
+        funds: vec![],
+        label,
+    };
+    state
+        .token_instantiate_msg
+        .save(deps.storage, &token_instantiate_msg)?;
+
+    Ok(Response::new().add_submessage(token_instantiate_submsg(
+        deps.storage,
+        token_instantiate_msg,
+    )?))
 }
 
-pub fn register_steak_token(deps: DepsMut, response: SubMsgResponse) -> StdResult<Response> {
-    let state = State::default();
+/// Instantiate the Steak token. The hub is left without one rather than failing if this does, so
+/// the owner can try again with `RetryTokenInstantiate`
+fn token_instantiate_submsg(
+    storage: &mut dyn Storage,
+    token_instantiate_msg: WasmMsg,
+) -> StdResult<SubMsg> {
+    Ok(SubMsg::reply_always(
+        token_instantiate_msg,
+        State::default().register_reply(storage, ReplyContext::InstantiateToken)?,
+    ))
+}
 
-    let event = response
-        .events
-        .iter()
-        .find(|event| event.ty == "instantiate")
-        .ok_or_else(|| StdError::generic_err("cannot find `instantiate` event"))?;
+pub fn register_steak_token(deps: DepsMut, result: SubMsgResult) -> StdResult<Response> {
+    let state = State::default();
 
-    let contract_addr_str = &event
-        .attributes
-        .iter()
-        .find(|attr| attr.key == "_contract_address")
-        .ok_or_else(|| StdError::generic_err("cannot find `_contract_address` attribute"))?
-        .value;
+    let contract_addr = result
+        .into_result()
+        .map_err(StdError::generic_err)
+        .and_then(|response| {
+            let event = response
+                .events
+                .iter()
+                .find(|event| event.ty == "instantiate")
+                .ok_or_else(|| StdError::generic_err("cannot find `instantiate` event"))?;
+
+            let contract_addr_str = &event
+                .attributes
+                .iter()
+                .find(|attr| attr.key == "_contract_address")
+                .ok_or_else(|| StdError::generic_err("cannot find `_contract_address` attribute"))?
+                .value;
+
+            deps.api.addr_validate(contract_addr_str)
+        });
+    let contract_addr = match contract_addr {
+        Ok(contract_addr) => contract_addr,
+        Err(err) => {
+            let event = Event::new("steakhub/token_instantiation_failed")
+                .add_attribute("error", err.to_string());
+            return Ok(Response::new().add_event(event));
+        }
+    };
 
-    let contract_addr = deps.api.addr_validate(contract_addr_str)?;
     state.steak_token.save(deps.storage, &contract_addr)?;
+    state.token_instantiate_msg.remove(deps.storage);
+    state.accepted_tokens.save(
+        deps.storage,
+        &contract_addr,
+        &vec![ReceiveMsgKind::QueueUnbond],
+    )?;
 
     Ok(Response::new())
 }
 
+pub fn retry_token_instantiate(deps: DepsMut, sender: Addr) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let token_instantiate_msg = state
+        .token_instantiate_msg
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("steak token is already instantiated"))?;
+
+    Ok(Response::new()
+        .add_submessage(token_instantiate_submsg(
+            deps.storage,
+            token_instantiate_msg,
+        )?)
+        .add_attribute("action", "steakhub/retry_token_instantiate"))
+}
+
 //--------------------------------------------------------------------------------------------------
 // Bonding and harvesting logics
 //--------------------------------------------------------------------------------------------------
@@ -158,832 +337,5459 @@ pub fn register_steak_token(deps: DepsMut, response: SubMsgResponse) -> StdResul
 /// they all have the same amount of delegation. This is however quite gas-expensive: $1.5 cost in
 /// the case of 15 validators.
 ///
-/// To save gas for users, now we simply delegate all deposited Native Token to the validator with the
+/// To save gas for users, now we simply delegate all deposited Native Token to the validator
+/// furthest below its target delegation, which short of mining power or caps is the one with the
 /// smallest amount of delegation. If delegations become severely unbalance as a result of this
 /// (e.g. when a single user makes a very big deposit), anyone can invoke `ExecuteMsg::Rebalance`
 /// to balance the delegations.
-pub fn bond(deps: DepsMut, env: Env, receiver: Addr, funds: Vec<Coin>) -> StdResult<Response> {
+pub fn bond(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    receiver: Addr,
+    referrer: Option<Addr>,
+    funds: Vec<Coin>,
+    min_usteak_out: Option<Uint128>,
+) -> StdResult<Response> {
+    if let Some(referrer) = &referrer {
+        if *referrer == sender || *referrer == receiver {
+            return Err(StdError::generic_err("cannot refer yourself"));
+        }
+    }
+    let (response, _) = bond_internal(deps, env, receiver, referrer, funds, min_usteak_out)?;
+    Ok(response)
+}
+
+/// Bond a single deposit for several receivers, each minted Steak for its share of the deposit at
+/// the same exchange rate. The deposit is delegated in one go, like a bond. With `min_usteak_out`,
+/// the bond fails rather than mint less Steak than that in total
+pub fn bond_many(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    receivers: Vec<(Addr, Uint128)>,
+    referrer: Option<Addr>,
+    funds: Vec<Coin>,
+    min_usteak_out: Option<Uint128>,
+) -> StdResult<Response> {
+    if let Some(referrer) = &referrer {
+        if *referrer == sender || receivers.iter().any(|(receiver, _)| receiver == referrer) {
+            return Err(StdError::generic_err("cannot refer yourself"));
+        }
+    }
+    let amount_to_bond = measure_deposit(deps.as_ref(), &env, &funds)?;
+    if receivers.is_empty() {
+        return Err(StdError::generic_err("no receivers specified"));
+    }
+    let amount_split = receivers
+        .iter()
+        .try_fold(Uint128::zero(), |total, (_, amount)| {
+            total.checked_add(*amount)
+        })?;
+    if amount_split != amount_to_bond {
+        return Err(StdError::generic_err(format!(
+            "receiver amounts add up to {} but {} was deposited",
+            amount_split, amount_to_bond
+        )));
+    }
+
+    let (response, receipts) = bond_deposit(deps, &env, receivers, referrer, min_usteak_out)?;
+    Ok(response
+        .set_data(to_binary(&receipts)?)
+        .add_attribute("action", "steakhub/bond_many"))
+}
+
+/// Bond the deposit and mint Steak to `receiver`, returning the response along with the amount of
+/// Steak minted
+pub(crate) fn bond_internal(
+    deps: DepsMut,
+    env: Env,
+    receiver: Addr,
+    referrer: Option<Addr>,
+    funds: Vec<Coin>,
+    min_usteak_out: Option<Uint128>,
+) -> StdResult<(Response, Uint128)> {
+    let amount_to_bond = measure_deposit(deps.as_ref(), &env, &funds)?;
+    let (response, receipts) = bond_deposit(
+        deps,
+        &env,
+        vec![(receiver, amount_to_bond)],
+        referrer,
+        min_usteak_out,
+    )?;
+    Ok((
+        response
+            .set_data(to_binary(&receipts[0])?)
+            .add_attribute("action", "steakhub/bond"),
+        receipts[0].usteak_minted,
+    ))
+}
+
+/// The amount of Native Token deposited for bonding, once bonds are checked to be open to it
+fn measure_deposit(deps: Deps, env: &Env, funds: &[Coin]) -> StdResult<Uint128> {
     let state = State::default();
+    assert_capability(deps.storage, |c| c.bond, "bonding")?;
+    assert_harvest_fresh(deps.storage, env)?;
+    assert_no_derivative_deposit(deps.storage, funds)?;
     let denom = state.denom.load(deps.storage)?;
-    let amount_to_bond = parse_received_fund(&funds, &denom)?;
-    let steak_token = state.steak_token.load(deps.storage)?;
-    let validators = state.validators_active.load(deps.storage)?;
+    measure_received_fund(deps, env, funds, &denom)
+}
+
+/// Delegate a deposit and mint Steak to each of `receivers` for the amount of it set against them,
+/// all at the same exchange rate. The amount bonded counts towards the referral volume of
+/// `referrer`, if any. Returns the response, without data or action, along with the receipts
+fn bond_deposit(
+    deps: DepsMut,
+    env: &Env,
+    receivers: Vec<(Addr, Uint128)>,
+    referrer: Option<Addr>,
+    min_usteak_out: Option<Uint128>,
+) -> StdResult<(Response, Vec<BondReceipt>)> {
+    let state = State::default();
+    let denom = state.denom.load(deps.storage)?;
+    let amount_to_bond: Uint128 = receivers.iter().map(|(_, amount)| *amount).sum();
+    let min_bond_amount = state
+        .min_bond_amount
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if amount_to_bond < min_bond_amount {
+        return Err(StdError::generic_err(format!(
+            "bond amount {} is below the minimum of {}",
+            amount_to_bond, min_bond_amount
+        )));
+    }
+    let validators = state.load_validators_active(deps.storage, env.block.time.seconds())?;
 
-    // Query the current delegations made to validators, and find the validator with the smallest
-    // delegated amount through a linear search
-    // The code for linear search is a bit uglier than using `sort_by` but cheaper: O(n) vs O(n * log(n))
+    // Delegate to the validator furthest below its target, as reinvesting does
     let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
-    let mut validator = &delegations[0].validator;
-    let mut amount = delegations[0].amount;
-    for d in &delegations[1..] {
-        if d.amount < amount {
-            validator = &d.validator;
-            amount = d.amount;
-        }
-    }
-    let new_delegation = Delegation {
-        validator: validator.clone(),
-        amount: amount_to_bond.u128(),
-        denom: denom.clone(),
+    let active_bonded: u128 = delegations.iter().map(|d| d.amount).sum();
+    let validator = select_validator_for_deposit(
+        &delegations,
+        |d| compute_validator_target(deps.storage, &d.validator, active_bonded, delegations.len()),
+        |d| {
+            state
+                .validator_caps
+                .may_load(deps.storage, d.validator.clone())
+        },
+    )?;
+    let new_delegation = Delegation::new(&validator, amount_to_bond.u128(), &denom);
+
+    // Query the current supply of Steak and compute the amounts to mint
+    let usteak_supply = query_usteak_supply(&deps.querier, deps.storage)?;
+    let bonded_delegations =
+        query_bonded_delegations(&deps.querier, deps.storage, &env.contract.address, &denom)?;
+    let native_bonded: u128 = bonded_delegations.iter().map(|d| d.amount).sum();
+    assert_exchange_rate_floor(deps.storage, native_bonded, usteak_supply)?;
+    let exchange_rate = if usteak_supply.is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(native_bonded, usteak_supply)
     };
-
-    // Query the current supply of Steak and compute the amount to mint
-    let usteak_supply = query_cw20_total_supply(&deps.querier, &steak_token)?;
-    let usteak_to_mint = compute_mint_amount(usteak_supply, amount_to_bond, &delegations);
     state.prev_denom.save(
         deps.storage,
         &get_denom_balance(&deps.querier, env.contract.address.clone(), denom.clone())?,
     )?;
 
+    let proto_urls = state.load_staking_proto_urls(deps.storage)?;
     let delegate_submsg = SubMsg::reply_on_success(
-        new_delegation.to_cosmos_msg(env.contract.address.to_string())?,
-        REPLY_REGISTER_RECEIVED_COINS,
+        new_delegation.to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+        state.register_reply(deps.storage, ReplyContext::RegisterReceivedCoins)?,
     );
 
-    let mint_msg: CosmosMsg = CosmosMsg::Wasm(WasmMsg::Execute {
+    let mut mint_msgs = vec![];
+    let mut receipts = vec![];
+    let mut events = vec![];
+    let mut hooks = vec![];
+    for (receiver, amount) in receivers {
+        let usteak_to_mint = compute_mint_amount(usteak_supply, amount, &bonded_delegations);
+        if usteak_to_mint.is_zero() {
+            return Err(StdError::generic_err(format!(
+                "bond amount {} for {} is too small to mint any steak",
+                amount, receiver
+            )));
+        }
+        if receiver != env.contract.address {
+            state.add_staker(deps.storage, &receiver)?;
+            record_bond_window(deps.storage, env, &receiver, amount)?;
+        }
+        mint_msgs.extend(mint_usteak_msgs(
+            deps.storage,
+            &env.contract.address,
+            &receiver,
+            usteak_to_mint,
+        )?);
+
+        let mut event = Event::new("steakhub/bonded")
+            .add_attribute("time", env.block.time.seconds().to_string())
+            .add_attribute("height", env.block.height.to_string())
+            .add_attribute("receiver", &receiver)
+            .add_attribute("denom_bonded", &denom)
+            .add_attribute("denom_amount", amount)
+            .add_attribute("usteak_minted", usteak_to_mint);
+        if let Some(referrer) = &referrer {
+            event = event.add_attribute("referrer", referrer);
+        }
+        events.push(event);
+        let receipt = BondReceipt {
+            receiver: receiver.into(),
+            native_bonded: amount,
+            usteak_minted: usteak_to_mint,
+            exchange_rate,
+        };
+        hooks.extend(hook_submsgs(
+            deps.storage,
+            HookEventKind::Bond,
+            &SteakHookMsg::Bonded {
+                receipt: receipt.clone(),
+            },
+        )?);
+        receipts.push(receipt);
+    }
+
+    // The exchange rate may have moved since the bond was simulated
+    if let Some(min_usteak_out) = min_usteak_out {
+        let usteak_minted: Uint128 = receipts.iter().map(|r| r.usteak_minted).sum();
+        if usteak_minted < min_usteak_out {
+            return Err(StdError::generic_err(format!(
+                "bond would mint {} usteak, below the minimum of {}",
+                usteak_minted, min_usteak_out
+            )));
+        }
+    }
+    if let Some(referrer) = &referrer {
+        record_referral(deps.storage, referrer, amount_to_bond)?;
+    }
+
+    state.bump_counters(deps.storage, amount_to_bond, Uint128::zero(), 0)?;
+
+    let restaking_hook = restaking_hook_submsg(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        amount_to_bond.u128(),
+        0,
+    )?;
+
+    Ok((
+        Response::new()
+            .add_submessage(delegate_submsg)
+            .add_messages(mint_msgs)
+            .add_submessages(restaking_hook)
+            .add_submessages(hooks)
+            .add_events(events),
+        receipts,
+    ))
+}
+
+/// Entry point for `ibc_hooks`: the deposit arrives through an ICS-20 transfer whose memo invokes
+/// this message. The Steak is minted to the hub itself, then forwarded to the cw20-ics20 contract
+/// to be sent back to the depositor on the origin chain.
+///
+/// `ibc_hooks` executes the memo from a sender derived from the channel and the original sender,
+/// so checking it against the one derived here proves the deposit came from `original_sender`
+/// over the configured channel. Rate limits apply to that derived sender
+pub fn bond_on_behalf(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    original_sender: String,
+    funds: Vec<Coin>,
+) -> StdResult<Response> {
+    let state = State::default();
+    let ics20_contract = state
+        .ics20_contract
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("bonding through IBC is not enabled"))?;
+    let channel = state.ics20_channel.load(deps.storage)?;
+    if original_sender.is_empty() {
+        return Err(StdError::generic_err("original sender must be provided"));
+    }
+    let bech32_prefix = env
+        .contract
+        .address
+        .as_str()
+        .rsplit_once('1')
+        .map(|(prefix, _)| prefix)
+        .ok_or_else(|| StdError::generic_err("hub address is not bech32"))?;
+    let intermediate_sender = derive_intermediate_sender(&channel, &original_sender, bech32_prefix);
+    if sender.as_str() != intermediate_sender {
+        return Err(StdError::generic_err(format!(
+            "sender {} is not the IBC hooks sender for {} over {}",
+            sender, original_sender, channel
+        )));
+    }
+    // The cw20-ics20 contract can only send the cw20 token back
+    if state.usteak_denom.may_load(deps.storage)?.is_some() {
+        return Err(StdError::generic_err(
+            "bonding through IBC is not supported once the token has been migrated",
+        ));
+    }
+    let steak_token = state.steak_token.load(deps.storage)?;
+    let denom = state.denom.load(deps.storage)?;
+    record_bond_window(
+        deps.storage,
+        &env,
+        &sender,
+        parse_received_fund(&funds, &denom)?,
+    )?;
+
+    let (response, usteak_minted) = bond_internal(
+        deps,
+        env.clone(),
+        env.contract.address.clone(),
+        None,
+        funds,
+        None,
+    )?;
+
+    let send_msg = CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: steak_token.into(),
-        msg: to_binary(&Cw20ExecuteMsg::Mint {
-            recipient: receiver.to_string(),
-            amount: usteak_to_mint,
+        msg: to_binary(&Cw20ExecuteMsg::Send {
+            contract: ics20_contract.into(),
+            amount: usteak_minted,
+            msg: to_binary(&Ics20TransferMsg {
+                channel: channel.clone(),
+                remote_address: original_sender.clone(),
+                timeout: None,
+            })?,
         })?,
         funds: vec![],
     });
 
-    let event = Event::new("steakhub/bonded")
+    let event = Event::new("steakhub/bonded_on_behalf")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("original_sender", original_sender)
+        .add_attribute("channel", channel)
+        .add_attribute("usteak_minted", usteak_minted);
+
+    Ok(response.add_message(send_msg).add_event(event))
+}
+
+/// Redeem the liquid staking module share tokens sent along into a delegation from the hub. The
+/// Steak is minted in the reply, once the delegation is known: the share tokens may be worth less
+/// than their face value if the validator was slashed since they were tokenized
+pub fn bond_tokenized_shares(
+    deps: DepsMut,
+    env: Env,
+    receiver: Addr,
+    funds: Vec<Coin>,
+) -> StdResult<Response> {
+    let state = State::default();
+    assert_capability(deps.storage, |c| c.bond, "bonding")?;
+    assert_harvest_fresh(deps.storage, &env)?;
+    if funds.len() != 1 {
+        return Err(StdError::generic_err(format!(
+            "must deposit exactly one coin; received {}",
+            funds.len()
+        )));
+    }
+    let shares = &funds[0];
+    if shares.amount.is_zero() {
+        return Err(StdError::generic_err("deposit amount must be non-zero"));
+    }
+
+    // Tokenized shares are denominated `{validator}/{record_id}`
+    let validator = match shares.denom.rsplit_once('/') {
+        Some((validator, record_id))
+            if !validator.is_empty() && record_id.parse::<u64>().is_ok() =>
+        {
+            validator
+        }
+        _ => {
+            return Err(StdError::generic_err(format!(
+                "{} is not a tokenized shares denom",
+                shares.denom
+            )))
+        }
+    };
+    let validators = state.load_validators_active(deps.storage, env.block.time.seconds())?;
+    if !validators.iter().any(|v| v == validator) {
+        return Err(StdError::generic_err(format!(
+            "validator {} is not active",
+            validator
+        )));
+    }
+
+    let denom = state.denom.load(deps.storage)?;
+    let delegation_before =
+        query_delegation(&deps.querier, validator, &env.contract.address, &denom)?.amount;
+
+    let proto_urls = state.load_staking_proto_urls(deps.storage)?;
+    let redeem_submsg = SubMsg::reply_on_success(
+        SharesRedemption::new(shares.amount.u128(), &shares.denom)
+            .to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+        state.register_reply(
+            deps.storage,
+            ReplyContext::RedeemTokenizedShares {
+                receiver,
+                validator: validator.to_string(),
+                delegation_before: Uint128::new(delegation_before),
+            },
+        )?,
+    );
+
+    Ok(Response::new()
+        .add_submessage(redeem_submsg)
+        .add_attribute("action", "steakhub/bond_tokenized_shares"))
+}
+
+/// Mint Steak for the delegation the share tokens were redeemed into. Redeeming withdraws the
+/// rewards of the hub's existing delegation to the validator, which are registered as well
+pub fn finish_tokenized_shares_bond(
+    mut deps: DepsMut,
+    env: Env,
+    receiver: Addr,
+    validator: String,
+    delegation_before: Uint128,
+    events: Vec<Event>,
+) -> StdResult<Response> {
+    let response = register_received_coins(deps.branch(), env.clone(), events)?;
+
+    let state = State::default();
+    let denom = state.denom.load(deps.storage)?;
+    // The validator may have been paused since the redemption was sent, or its suspension may have
+    // run out
+    let validators = state.load_validators_active(deps.storage, env.block.time.seconds())?;
+    if !validators.contains(&validator) {
+        return Err(StdError::generic_err(format!(
+            "validator {} is not active",
+            validator
+        )));
+    }
+    let mut delegations =
+        query_bonded_delegations(&deps.querier, deps.storage, &env.contract.address, &denom)?;
+
+    // Put the validator's delegation back to what it was, so the exchange rate is the one from
+    // before the redemption
+    let delegation = delegations
+        .iter_mut()
+        .find(|d| d.validator == validator)
+        .ok_or_else(|| StdError::generic_err(format!("validator {} is not active", validator)))?;
+    let amount_bonded = Uint128::new(delegation.amount).checked_sub(delegation_before)?;
+    delegation.amount = delegation_before.u128();
+
+    let min_bond_amount = state
+        .min_bond_amount
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if amount_bonded < min_bond_amount {
+        return Err(StdError::generic_err(format!(
+            "bond amount {} is below the minimum of {}",
+            amount_bonded, min_bond_amount
+        )));
+    }
+
+    let usteak_supply = query_usteak_supply(&deps.querier, deps.storage)?;
+    let native_bonded: u128 = delegations.iter().map(|d| d.amount).sum();
+    assert_exchange_rate_floor(deps.storage, native_bonded, usteak_supply)?;
+    let usteak_to_mint = compute_mint_amount(usteak_supply, amount_bonded, &delegations);
+    if usteak_to_mint.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "bond amount {} is too small to mint any steak",
+            amount_bonded
+        )));
+    }
+    if receiver != env.contract.address {
+        state.add_staker(deps.storage, &receiver)?;
+        record_bond_window(deps.storage, &env, &receiver, amount_bonded)?;
+    }
+    state.bump_counters(deps.storage, amount_bonded, Uint128::zero(), 0)?;
+
+    let mint_msgs = mint_usteak_msgs(
+        deps.storage,
+        &env.contract.address,
+        &receiver,
+        usteak_to_mint,
+    )?;
+    // The redemption has gone through already, so the delegations include the bonded amount
+    let restaking_hook =
+        restaking_hook_submsg(deps.storage, &deps.querier, &env.contract.address, 0, 0)?;
+
+    let receipt = BondReceipt {
+        receiver: receiver.to_string(),
+        native_bonded: amount_bonded,
+        usteak_minted: usteak_to_mint,
+        exchange_rate: if usteak_supply.is_zero() {
+            Decimal::one()
+        } else {
+            Decimal::from_ratio(native_bonded, usteak_supply)
+        },
+    };
+
+    let hooks = hook_submsgs(
+        deps.storage,
+        HookEventKind::Bond,
+        &SteakHookMsg::Bonded {
+            receipt: receipt.clone(),
+        },
+    )?;
+
+    let event = Event::new("steakhub/bonded_tokenized_shares")
         .add_attribute("time", env.block.time.seconds().to_string())
         .add_attribute("height", env.block.height.to_string())
         .add_attribute("receiver", receiver)
-        .add_attribute("denom_bonded", denom)
-        .add_attribute("denom_amount", amount_to_bond)
+        .add_attribute("validator", validator)
+        .add_attribute("denom_amount", amount_bonded)
         .add_attribute("usteak_minted", usteak_to_mint);
 
-    Ok(Response::new()
-        .add_submessage(delegate_submsg)
-        .add_message(mint_msg)
-        .add_event(event)
-        .add_attribute("action", "steakhub/bond"))
+    Ok(response
+        .set_data(to_binary(&receipt)?)
+        .add_messages(mint_msgs)
+        .add_submessages(restaking_hook)
+        .add_submessages(hooks)
+        .add_event(event))
 }
 
 pub fn harvest(deps: DepsMut, env: Env, sender: Addr) -> StdResult<Response> {
-    if sender != env.contract.address {
+    let state = State::default();
+    assert_capability(deps.storage, |c| c.harvest, "harvesting")?;
+    // with mining disabled there is no proof to trigger the harvest, so anyone may call it
+    if sender != env.contract.address && state.is_mining_enabled(deps.storage)? {
         return Err(StdError::generic_err(
             "only the contract itself can harvest rewards for DPOW",
         ));
     }
-    let state = State::default();
+    state.last_harvester.save(
+        deps.storage,
+        &OperationRecord {
+            address: sender.to_string(),
+            height: env.block.height,
+        },
+    )?;
     let denom = state.denom.load(deps.storage)?;
     state.prev_denom.save(
         deps.storage,
-        &get_denom_balance(&deps.querier, env.contract.address.clone(), denom)?,
+        &get_denom_balance(&deps.querier, env.contract.address.clone(), denom.clone())?,
     )?;
+    state
+        .last_harvest_time
+        .save(deps.storage, &env.block.time.seconds())?;
+    state.bump_counters(deps.storage, Uint128::zero(), Uint128::zero(), 1)?;
+
+    // Without `coin_received` events, the rewards are found by comparing balances in the callback
+    let harvest_mode = state
+        .harvest_mode
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if harvest_mode == HarvestMode::BalanceDiff {
+        state.harvest_balances.save(
+            deps.storage,
+            &deps.querier.query_all_balances(&env.contract.address)?,
+        )?;
+    }
 
-    let withdraw_submsgs = deps
+    // When the hub stakes through an intermediate account, that account's rewards are withdrawn
+    // through its authz grant, and paid to the hub as its withdraw address
+    let harvest_granter = state.harvest_granter.may_load(deps.storage)?;
+    let delegator = harvest_granter
+        .clone()
+        .unwrap_or_else(|| env.contract.address.clone());
+
+    // Withdrawing from a validator with next to nothing pending costs more gas than it brings in.
+    // Chains refusing the rewards query have every validator withdrawn from
+    let min_harvest_reward = state
+        .min_harvest_reward
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let pending_rewards = query_pending_rewards(&deps.querier, &delegator, &denom);
+    let (delegations, skipped): (Vec<_>, Vec<_>) = deps
         .querier
-        .query_all_delegations(&env.contract.address)?
+        .query_all_delegations(&delegator)?
+        .into_iter()
+        .partition(|d| match &pending_rewards {
+            Some(rewards) => rewards
+                .get(&d.validator)
+                .map_or(false, |reward| *reward > min_harvest_reward),
+            None => true,
+        });
+
+    let proto_urls = state.load_staking_proto_urls(deps.storage)?;
+    let withdraw_submsgs = delegations
         .into_iter()
         .map(|d| -> StdResult<SubMsg> {
-            Ok(SubMsg::reply_on_success(
-                RewardWithdrawal {
-                    validator: d.validator,
-                }
-                .to_cosmos_msg(env.contract.address.to_string())?,
-                REPLY_REGISTER_RECEIVED_COINS,
-            ))
+            let withdrawal = RewardWithdrawal {
+                validator: d.validator,
+            };
+            let msg = match &harvest_granter {
+                Some(granter) => withdrawal.to_authz_exec_msg(
+                    granter.to_string(),
+                    env.contract.address.to_string(),
+                    &proto_urls,
+                )?,
+                None => withdrawal.to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+            };
+            Ok(match harvest_mode {
+                HarvestMode::CoinReceivedEvents => SubMsg::reply_on_success(
+                    msg,
+                    state.register_reply(deps.storage, ReplyContext::RegisterReceivedCoins)?,
+                ),
+                HarvestMode::BalanceDiff => SubMsg::new(msg),
+            })
         })
         .collect::<StdResult<Vec<SubMsg>>>()?;
 
     let callback_msg = CallbackMsg::Reinvest {}.into_cosmos_msg(&env.contract.address)?;
 
-    Ok(Response::new()
+    let mut response = Response::new()
         .add_submessages(withdraw_submsgs)
-        .add_message(callback_msg)
+        .add_message(callback_msg);
+    if !skipped.is_empty() {
+        response = response.add_attribute("validators_skipped", skipped.len().to_string());
+    }
+    Ok(response
+        .add_attribute("sender", sender)
         .add_attribute("action", "steakhub/harvest"))
 }
 
-/// NOTE:
-/// 1. When delegation Native denom here, we don't need to use a `SubMsg` to handle the received coins,
-/// because we have already withdrawn all claimable staking rewards previously in the same atomic
-/// execution.
-/// 2. Same as with `bond`, in the latest implementation we only delegate staking rewards with the
-/// validator that has the smallest delegation amount.
-pub fn reinvest(deps: DepsMut, env: Env) -> StdResult<Response> {
+/// Send whichever of `Reconcile`, `SubmitBatch` and `Harvest` are due, each as a submessage to the
+/// hub itself. Failures are caught in the reply, so a tick never fails: the chain's clock module
+/// may stop calling contracts whose sudo fails
+///
+/// Harvesting is only due while mining is disabled (otherwise proofs trigger it), once an epoch
+/// period has passed since the last harvest
+pub fn tick(deps: DepsMut, env: Env) -> StdResult<Response> {
     let state = State::default();
+    let current_time = env.block.time.seconds();
+
     let denom = state.denom.load(deps.storage)?;
-    let fee = state.fee_rate.load(deps.storage)?;
 
-    let validators = state.validators_active.load(deps.storage)?;
-    let prev_coin = state.prev_denom.load(deps.storage)?;
-    let current_coin =
-        get_denom_balance(&deps.querier, env.contract.address.clone(), denom.clone())?;
+    let mut actions: Vec<(&str, ExecuteMsg)> = vec![];
 
-    if current_coin <= prev_coin {
-        return Err(StdError::generic_err("no rewards"));
+    let mut needs_reconcile = false;
+    for item in state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(false.into())
+        .range(deps.storage, None, None, Order::Ascending)
+    {
+        let ((batch_denom, _), batch) = item?;
+        if batch_denom == denom && current_time > batch.est_unbond_end_time {
+            needs_reconcile = true;
+            break;
+        }
+    }
+    if needs_reconcile {
+        actions.push(("reconcile", ExecuteMsg::Reconcile {}));
     }
-    let amount_to_bond = current_coin.saturating_sub(prev_coin);
-    let mut unlocked_coins = state.unlocked_coins.load(deps.storage)?;
 
-    /*
+    // Slices of unbond orders are only added to the batch at submission
+    let pending_batch = state.pending_batches.load(deps.storage, &denom)?;
+    let has_unbond_orders = state
+        .unbond_orders
+        .keys_raw(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    if current_time >= pending_batch.est_unbond_start_time
+        && (!pending_batch.usteak_to_burn.is_zero() || has_unbond_orders)
+    {
+        actions.push(("submit_batch", ExecuteMsg::SubmitBatch { exclude: vec![] }));
+    }
 
-        if unlocked_coins.is_empty() {
-            return Err(StdError::generic_err("no rewards"));
+    if !state.is_mining_enabled(deps.storage)? {
+        let epoch_period = state.epoch_period.load(deps.storage)?;
+        let harvest_due = state
+            .last_harvest_time
+            .may_load(deps.storage)?
+            .map_or(true, |last| current_time >= last + epoch_period);
+        if harvest_due {
+            actions.push(("harvest", ExecuteMsg::Harvest {}));
         }
-        let amount_to_bond = unlocked_coins
+    }
+
+    let event = Event::new("steakhub/tick").add_attribute(
+        "actions",
+        actions
             .iter()
-            .find(|coin| coin.denom == denom)
-            .ok_or_else(|| StdError::generic_err("no native amount available to be bonded"))?
-            .amount;
-    */
-    let total_mining_power = state
-        .total_mining_power
-        .may_load(deps.storage)?
-        .unwrap_or_default();
-    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
-    let total_bonded = delegations.iter().fold(0u128, |acc, d| acc + d.amount);
-    let mut validator = &delegations[0].validator;
-    let validator_mining_power = state
-        .validator_mining_powers
-        .may_load(deps.storage, validator.to_string())?
-        .unwrap_or_default();
-    let target_delegation = compute_target_delegation_from_mining_power(
-        total_bonded.into(),
-        validator_mining_power,
-        total_mining_power,
-    )?;
-    println!(
-        "total mining power: {} total bonded: {}",
-        total_mining_power, total_bonded
+            .map(|(action, _)| *action)
+            .collect::<Vec<_>>()
+            .join(","),
     );
 
-    let mut cmp = target_delegation.u128().cmp(&delegations[0].amount);
-    let mut diff = if cmp.is_gt() {
-        target_delegation.u128().abs_diff(delegations[0].amount)
-    } else {
-        0u128
+    let submsgs = actions
+        .into_iter()
+        .map(|(action, msg)| {
+            Ok(SubMsg::reply_always(
+                WasmMsg::Execute {
+                    contract_addr: env.contract.address.to_string(),
+                    msg: to_binary(&msg)?,
+                    funds: vec![],
+                },
+                state.register_reply(
+                    deps.storage,
+                    ReplyContext::Tick {
+                        action: action.to_string(),
+                    },
+                )?,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(Response::new()
+        .add_submessages(submsgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/tick"))
+}
+
+/// Swallow the failure of an action sent by `tick`, leaving a trace of it in the events
+pub fn finish_tick_action(action: String, result: SubMsgResult) -> StdResult<Response> {
+    match result.into_result() {
+        Ok(_) => Ok(Response::new()),
+        Err(error) => {
+            let event = Event::new("steakhub/tick_failed")
+                .add_attribute("tick_action", action)
+                .add_attribute("error", error);
+            Ok(Response::new().add_event(event))
+        }
+    }
+}
+
+/// Tell the restaking adapter, if one is configured, how much will be bonded once the transaction
+/// goes through: the current delegations plus `added`, minus `removed`. The adapter is not to hold
+/// the hub up, so its errors are only reported
+fn restaking_hook_submsg(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    contract_addr: &Addr,
+    added: u128,
+    removed: u128,
+) -> StdResult<Option<SubMsg>> {
+    let state = State::default();
+    let adapter = match state.restaking_adapter.may_load(storage)? {
+        Some(adapter) => adapter,
+        None => return Ok(None),
     };
-    println!(
-        "validator: {} amount: {} target: {} diff: {}",
-        validator,
-        delegations[0].amount,
-        target_delegation.u128(),
-        diff
-    );
 
-    for d in &delegations[1..] {
-        let current_validator_mining_power = state
-            .validator_mining_powers
-            .may_load(deps.storage, d.validator.to_string())?
-            .unwrap_or_default();
-        let current_td = compute_target_delegation_from_mining_power(
-            total_bonded.into(),
-            current_validator_mining_power,
-            total_mining_power,
-        )?;
-        let current_diff = current_td.u128().abs_diff(d.amount);
-        println!(
-            "validator: {} amount: {} target: {} diff: {}",
-            d.validator,
-            d.amount,
-            current_td.u128(),
-            current_diff
-        );
-        let current_cmp = current_td.u128().cmp(&d.amount);
-        // if there is a bigger gap to fill with the current validator, use it
-        if current_cmp > cmp || (current_cmp.is_gt() && current_diff > diff) {
-            validator = &d.validator;
-            diff = current_diff;
-            cmp = current_cmp;
+    let denom = state.denom.load(storage)?;
+    let delegations = query_bonded_delegations(querier, storage, contract_addr, &denom)?;
+    let native_bonded: u128 = delegations.iter().map(|d| d.amount).sum();
+    let total_bonded = Uint128::new(native_bonded)
+        .checked_add(Uint128::new(added))?
+        .checked_sub(Uint128::new(removed))?;
+    let msg = RestakingHookMsg::BondedAmountChanged {
+        total_bonded,
+        max_restaked: total_bonded.multiply_ratio(adapter.max_restaked_bps, 10_000u128),
+    };
+
+    Ok(Some(SubMsg::reply_always(
+        WasmMsg::Execute {
+            contract_addr: adapter.contract,
+            msg: to_binary(&msg)?,
+            funds: vec![],
+        },
+        state.register_reply(storage, ReplyContext::RestakingHook)?,
+    )))
+}
+
+/// Notify the contracts registered for `event_kind` of `msg`
+fn hook_submsgs(
+    storage: &mut dyn Storage,
+    event_kind: HookEventKind,
+    msg: &SteakHookMsg,
+) -> StdResult<Vec<SubMsg>> {
+    let state = State::default();
+    let contracts = state
+        .hooks
+        .may_load(storage, event_kind.as_str())?
+        .unwrap_or_default();
+
+    contracts
+        .into_iter()
+        .map(|contract| {
+            Ok(SubMsg::reply_always(
+                WasmMsg::Execute {
+                    contract_addr: contract.to_string(),
+                    msg: to_binary(msg)?,
+                    funds: vec![],
+                },
+                state.register_reply(storage, ReplyContext::Hook { contract })?,
+            ))
+        })
+        .collect()
+}
+
+pub fn finish_hook(contract: Addr, result: SubMsgResult) -> StdResult<Response> {
+    match result.into_result() {
+        Ok(_) => Ok(Response::new()),
+        Err(error) => {
+            let event = Event::new("steakhub/hook_failed")
+                .add_attribute("contract", contract)
+                .add_attribute("error", error);
+            Ok(Response::new().add_event(event))
+        }
+    }
+}
+
+pub fn finish_restaking_hook(result: SubMsgResult) -> StdResult<Response> {
+    match result.into_result() {
+        Ok(_) => Ok(Response::new()),
+        Err(error) => {
+            let event = Event::new("steakhub/restaking_hook_failed").add_attribute("error", error);
+            Ok(Response::new().add_event(event))
         }
     }
+}
+
+/// NOTE:
+/// 1. When delegation Native denom here, we don't need to use a `SubMsg` to handle the received coins,
+/// because we have already withdrawn all claimable staking rewards previously in the same atomic
+/// execution.
+/// 2. Staking rewards are delegated to a single active validator: the one furthest below the target
+/// that rebalancing works towards (see `compute_validator_target`), so paused validators and
+/// validators at their cap are skipped.
+pub fn reinvest(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let state = State::default();
+    let denom = state.denom.load(deps.storage)?;
+    let fee = state.fee_rate.load(deps.storage)?;
+
+    register_harvested_balances(deps.storage, &deps.querier, &env.contract.address)?;
+
+    let validators = state.load_validators_active(deps.storage, env.block.time.seconds())?;
+    let prev_coin = state.prev_denom.load(deps.storage)?;
+    let current_coin =
+        get_denom_balance(&deps.querier, env.contract.address.clone(), denom.clone())?;
+
+    // native held back by a previous reinvest for being below `min_reinvest_amount`
+    let carry = state
+        .reinvest_carry
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    // reward campaigns funded by third parties, delegated without a fee
+    let campaign_rewards =
+        state.releasable_campaign_rewards(deps.storage, env.block.time.seconds())?;
+
+    if current_coin <= prev_coin && carry.is_zero() && campaign_rewards.is_zero() {
+        return Err(StdError::generic_err("no rewards"));
+    }
+    let amount_to_bond = current_coin.saturating_sub(prev_coin) + carry;
+    let mut unlocked_coins = Coins(state.unlocked_coins.load(deps.storage)?);
+
+    /*
+
+        if unlocked_coins.is_empty() {
+            return Err(StdError::generic_err("no rewards"));
+        }
+        let amount_to_bond = unlocked_coins
+            .iter()
+            .find(|coin| coin.denom == denom)
+            .ok_or_else(|| StdError::generic_err("no native amount available to be bonded"))?
+            .amount;
+    */
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
+    let active_bonded: u128 = delegations.iter().map(|d| d.amount).sum();
+    let total_bonded: u128 =
+        query_bonded_delegations(&deps.querier, deps.storage, &env.contract.address, &denom)?
+            .iter()
+            .map(|d| d.amount)
+            .sum();
+    let usteak_supply = query_usteak_supply(&deps.querier, deps.storage)?;
+    assert_exchange_rate_floor(deps.storage, total_bonded, usteak_supply)?;
+
+    // A large reward event would otherwise jump the exchange rate in a single block. Bond no more
+    // than `max_rebase_bps` of the current stake, and drip the rest out over the next reinvests
+    let max_rebase_bps = state.max_rebase_bps.may_load(deps.storage)?.flatten();
+    let amount_deferred = match max_rebase_bps {
+        Some(bps) if total_bonded > 0 => {
+            let max_amount = Uint128::new(total_bonded).multiply_ratio(bps, 10_000u64);
+            amount_to_bond.saturating_sub(max_amount)
+        }
+        _ => Uint128::zero(),
+    };
+    let amount_to_bond = amount_to_bond - amount_deferred;
+
+    // The bridge's fee comes out of the rewards. Rewards too small to pay it skip publishing
+    let rate_publisher = state.rate_publisher.may_load(deps.storage)?;
+    let publication_fee = match &rate_publisher {
+        Some(publisher) if publisher.fee <= amount_to_bond => publisher.fee,
+        _ => Uint128::zero(),
+    };
+    let amount_to_bond = amount_to_bond - publication_fee;
+
+    let validator = select_validator_for_deposit(
+        &delegations,
+        |d| compute_validator_target(deps.storage, &d.validator, active_bonded, delegations.len()),
+        |d| {
+            state
+                .validator_caps
+                .may_load(deps.storage, d.validator.clone())
+        },
+    )?;
     let fee_amount = if fee.is_zero() {
         Uint128::zero()
     } else {
         fee.checked_mul_uint(amount_to_bond)?
     };
+    // Exempt holders, e.g. protocol-owned liquidity, get back the fee taken on their share of the
+    // rewards: it is delegated along with the rest, and minted to them as Steak. Everyone else
+    // pays the full fee
+    let mut fee_rebates = vec![];
+    if !fee_amount.is_zero() && !usteak_supply.is_zero() {
+        for holder in state
+            .fee_exemptions
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+        {
+            let usteak_held = query_usteak_balance(&deps.querier, deps.storage, &holder)?;
+            let rebate = fee_amount.multiply_ratio(usteak_held, usteak_supply);
+            if !rebate.is_zero() {
+                fee_rebates.push((holder, rebate));
+            }
+        }
+    }
+    let fee_rebated: Uint128 = fee_rebates.iter().map(|(_, rebate)| *rebate).sum();
+    let fee_amount = fee_amount - fee_rebated;
     let amount_to_bond_minus_fees = amount_to_bond.saturating_sub(fee_amount);
 
-    let new_delegation = Delegation::new(validator, amount_to_bond_minus_fees.u128(), &denom);
+    // Delegating dust wastes gas and creates tiny delegation entries. Carry the rewards over in
+    // `reinvest_carry` and pick them up again at the next reinvest
+    let min_reinvest_amount = state
+        .min_reinvest_amount
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if amount_to_bond_minus_fees + campaign_rewards < min_reinvest_amount {
+        let amount_carried = amount_to_bond + amount_deferred + publication_fee;
+        state.reinvest_carry.save(deps.storage, &amount_carried)?;
+
+        let event = Event::new("steakhub/reinvest_skipped")
+            .add_attribute("time", env.block.time.seconds().to_string())
+            .add_attribute("height", env.block.height.to_string())
+            .add_attribute("denom", &denom)
+            .add_attribute("amount_carried", amount_carried)
+            .add_attribute("min_reinvest_amount", min_reinvest_amount);
+
+        return Ok(Response::new()
+            .add_event(event)
+            .add_attribute("action", "steakhub/reinvest"));
+    }
+    state.reinvest_carry.save(deps.storage, &amount_deferred)?;
+    state.release_campaign_rewards(deps.storage, env.block.time.seconds())?;
+    let referral_kickback = accrue_referral_kickback(deps.storage, fee_amount, total_bonded)?;
+    let treasury_share = accrue_treasury_share(deps.storage, fee_amount - referral_kickback)?;
+    let fee_amount_to_account = fee_amount - referral_kickback - treasury_share;
+
+    // A fee taken in Steak is delegated along with the rest of the rewards
+    let fee_vesting = state.fee_vesting.may_load(deps.storage)?;
+    let amount_to_delegate = if fee_vesting.is_some() {
+        amount_to_bond_minus_fees + campaign_rewards + fee_amount_to_account
+    } else {
+        amount_to_bond_minus_fees + campaign_rewards
+    };
+
+    let new_delegation = Delegation::new(&validator, amount_to_delegate.u128(), &denom);
+    let proto_urls = state.load_staking_proto_urls(deps.storage)?;
+    let restaking_hook = restaking_hook_submsg(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        amount_to_delegate.u128(),
+        0,
+    )?;
+
+    let native_unlocked = unlocked_coins.find(&denom);
+    unlocked_coins.sub(&native_unlocked)?;
+    state.unlocked_coins.save(deps.storage, &unlocked_coins.0)?;
+
+    // Native rewards received since the last reinvest, and rewards in other denoms still waiting
+    // to be swapped
+    let mut gross_rewards = vec![Coin::new(
+        current_coin.saturating_sub(prev_coin).u128(),
+        &denom,
+    )];
+    gross_rewards.extend(unlocked_coins.0.iter().cloned());
 
-    unlocked_coins.retain(|coin| coin.denom != denom);
-    state.unlocked_coins.save(deps.storage, &unlocked_coins)?;
+    // Running totals reported by `QueryMsg::State`
+    state
+        .last_reinvest_amount
+        .save(deps.storage, &amount_to_delegate)?;
+    let total_fees_collected = state
+        .total_fees_collected
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    state
+        .total_fees_collected
+        .save(deps.storage, &(total_fees_collected + fee_amount))?;
+    let total_rewards_compounded = state
+        .total_rewards_compounded
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    state.total_rewards_compounded.save(
+        deps.storage,
+        &(total_rewards_compounded + amount_to_bond_minus_fees),
+    )?;
 
-    let event = Event::new("steakhub/harvested")
+    let mut event = Event::new("steakhub/harvested")
         .add_attribute("time", env.block.time.seconds().to_string())
         .add_attribute("height", env.block.height.to_string())
         .add_attribute("denom", &denom)
         .add_attribute("fees_deducted", fee_amount)
-        .add_attribute("denom_bonded", amount_to_bond_minus_fees);
+        .add_attribute("denom_bonded", amount_to_delegate);
+    if !amount_deferred.is_zero() {
+        event = event.add_attribute("amount_deferred", amount_deferred);
+    }
+    if !referral_kickback.is_zero() {
+        event = event.add_attribute("referral_kickback", referral_kickback);
+    }
+    if !campaign_rewards.is_zero() {
+        event = event.add_attribute("campaign_rewards", campaign_rewards);
+    }
+    if !treasury_share.is_zero() {
+        event = event.add_attribute("treasury_share", treasury_share);
+    }
+    if !fee_rebated.is_zero() {
+        event = event.add_attribute("fee_rebated", fee_rebated);
+    }
 
-    if fee_amount > Uint128::zero() {
-        let fee_account = state.fee_account.load(deps.storage)?;
-        let fee_type = state.fee_account_type.load(deps.storage)?;
+    // Steak minted against native delegated along with the rewards is priced at the exchange rate
+    // the rewards alone bring the hub to
+    let usteak_for = |amount: Uint128| {
+        if total_bonded == 0 {
+            amount
+        } else {
+            usteak_supply.multiply_ratio(
+                amount,
+                Uint128::new(total_bonded) + amount_to_bond_minus_fees - fee_rebated
+                    + campaign_rewards,
+            )
+        }
+    };
+
+    // A fee taken in Steak mints the fee account its share of the stake once the rewards are
+    // delegated. Burning it means never minting it
+    let fee_type = state.fee_account_type.load(deps.storage)?;
+    let usteak_to_vest =
+        if fee_vesting.is_none() || fee_amount_to_account.is_zero() || fee_type == FeeType::Burn {
+            Uint128::zero()
+        } else {
+            usteak_for(fee_amount_to_account)
+        };
+
+    let mut usteak_rebated = Uint128::zero();
+    let mut rebate_msgs = vec![];
+    let mut rebate_events = vec![];
+    for (holder, rebate) in fee_rebates {
+        let usteak_to_mint = usteak_for(rebate);
+        usteak_rebated += usteak_to_mint;
+        rebate_msgs.extend(mint_usteak_msgs(
+            deps.storage,
+            &env.contract.address,
+            &holder,
+            usteak_to_mint,
+        )?);
+        rebate_events.push(
+            Event::new("steakhub/fee_rebated")
+                .add_attribute("holder", holder)
+                .add_attribute("native_rebated", rebate)
+                .add_attribute("usteak_minted", usteak_to_mint),
+        );
+    }
+    state.record_supply_checkpoint(
+        deps.storage,
+        supply_checkpoint(
+            &env,
+            "harvest",
+            Uint128::new(total_bonded) + amount_to_delegate,
+            usteak_supply + usteak_to_vest + usteak_rebated,
+        ),
+    )?;
 
-        let send_msgs = match fee_type {
-            FeeType::Wallet => vec![CosmosMsg::Bank(BankMsg::Send {
-                to_address: fee_account.to_string(),
-                amount: vec![Coin::new(fee_amount.into(), &denom)],
-            })],
-            FeeType::FeeSplit => {
-                let msg = pfc_fee_split::fee_split_msg::ExecuteMsg::Deposit { flush: false };
+    // Everything a dashboard needs to work out the APR of the epoch, without querying the hub
+    let exchange_rate = if usteak_supply.is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(
+            Uint128::new(total_bonded) + amount_to_delegate,
+            usteak_supply + usteak_to_vest + usteak_rebated,
+        )
+    };
+    let summary = Event::new("steakhub/reinvest_summary")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute(
+            "gross_rewards",
+            gross_rewards
+                .iter()
+                .map(|coin| coin.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+        .add_attribute("fee", fee_amount)
+        .add_attribute("net_delegated", amount_to_delegate)
+        .add_attribute("validators", &validator)
+        .add_attribute("exchange_rate", exchange_rate.to_string());
+
+    let rate_publication = match &rate_publisher {
+        Some(publisher) if publication_fee == publisher.fee => {
+            event = event.add_attribute("publication_fee", publication_fee);
+            vec![rate_publication_msg(
+                publisher,
+                env.contract.address.to_string(),
+                &denom,
+                &RatePayload {
+                    exchange_rate,
+                    supply: usteak_supply + usteak_to_vest + usteak_rebated,
+                    total_bonded: Uint128::new(total_bonded) + amount_to_delegate,
+                    time: env.block.time.seconds(),
+                },
+                env.block.time,
+                env.block.height,
+            )?]
+        }
+        _ => vec![],
+    };
+
+    if fee_amount_to_account > Uint128::zero() {
+        let fee_account = state.fee_account.load(deps.storage)?;
 
-                vec![msg.into_cosmos_msg(fee_account, vec![Coin::new(fee_amount.into(), &denom)])?]
+        let (fee_paid, fee_denom, send_msgs) = match fee_vesting {
+            // Delegated along with the rewards, with no Steak minted for it
+            Some(_) if fee_type == FeeType::Burn => (fee_amount_to_account, denom.clone(), vec![]),
+            Some(fee_vesting) => {
+                event = event.add_attribute("usteak_vesting", usteak_to_vest);
+
+                let usteak = match state.usteak_denom.may_load(deps.storage)? {
+                    Some(usteak_denom) => usteak_denom,
+                    None => state.steak_token.load(deps.storage)?.into(),
+                };
+                let msgs = vest_fee(
+                    deps.storage,
+                    &env,
+                    &fee_account,
+                    usteak_to_vest,
+                    &fee_vesting,
+                )?;
+                (
+                    usteak_to_vest,
+                    usteak,
+                    msgs.into_iter().map(SubMsg::new).collect(),
+                )
+            }
+            None => {
+                let msg = fee_payment_msg(
+                    deps.storage,
+                    &fee_type,
+                    &fee_account,
+                    Coin::new(fee_amount_to_account.into(), &denom),
+                )?;
+                let msgs = match fee_type {
+                    FeeType::Wallet | FeeType::Burn => vec![SubMsg::new(msg)],
+                    // A fee-split contract rejecting the deposit, e.g. while it is paused or being
+                    // migrated, mustn't hold up compounding. The fees are kept in escrow instead
+                    FeeType::FeeSplit => vec![SubMsg::reply_always(
+                        msg,
+                        state.register_reply(
+                            deps.storage,
+                            ReplyContext::FeeSplitDeposit {
+                                amount: fee_amount_to_account,
+                            },
+                        )?,
+                    )],
+                };
+                (fee_amount_to_account, denom.clone(), msgs)
             }
         };
+        state.record_fee(
+            deps.storage,
+            FeeLedgerEntry {
+                id: 0,
+                source: "reinvest".to_string(),
+                amount: fee_paid,
+                denom: fee_denom,
+                destination: fee_account,
+                fee_type,
+                height: env.block.height,
+            },
+        )?;
+
         Ok(Response::new()
-            .add_message(new_delegation.to_cosmos_msg(env.contract.address.to_string())?)
-            .add_messages(send_msgs)
+            .add_message(
+                new_delegation.to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+            )
+            .add_submessages(send_msgs)
+            .add_messages(rebate_msgs)
+            .add_messages(rate_publication)
+            .add_submessages(restaking_hook)
             .add_event(event)
+            .add_events(rebate_events)
+            .add_event(summary)
             .add_attribute("action", "steakhub/reinvest"))
     } else {
         Ok(Response::new()
-            .add_message(new_delegation.to_cosmos_msg(env.contract.address.to_string())?)
+            .add_message(
+                new_delegation.to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+            )
+            .add_messages(rebate_msgs)
+            .add_messages(rate_publication)
+            .add_submessages(restaking_hook)
             .add_event(event)
+            .add_events(rebate_events)
+            .add_event(summary)
             .add_attribute("action", "steakhub/reinvest"))
     }
 }
 
-/// NOTE: a `SubMsgResponse` may contain multiple coin-receiving events, must handle them individually
-pub fn register_received_coins(
+/// Message paying `fee` to the fee account, however it takes them
+fn fee_payment_msg(
+    storage: &dyn Storage,
+    fee_type: &FeeType,
+    fee_account: &Addr,
+    fee: Coin,
+) -> StdResult<CosmosMsg> {
+    match fee_type {
+        FeeType::Wallet => Ok(CosmosMsg::Bank(BankMsg::Send {
+            to_address: fee_account.to_string(),
+            amount: vec![fee],
+        })),
+        FeeType::FeeSplit => {
+            let interface = State::default()
+                .fee_split_interface
+                .may_load(storage)?
+                .unwrap_or_default();
+            fee_split_deposit_msg(interface, fee_account.to_string(), vec![fee])
+        }
+        FeeType::Burn => Ok(CosmosMsg::Bank(BankMsg::Burn { amount: vec![fee] })),
+    }
+}
+
+pub fn finish_fee_split_deposit(
     deps: DepsMut,
-    env: Env,
-    mut events: Vec<Event>,
+    amount: Uint128,
+    result: SubMsgResult,
 ) -> StdResult<Response> {
-    events.retain(|event| event.ty == "coin_received");
-    if events.is_empty() {
-        return Ok(Response::new());
+    let state = State::default();
+
+    match result.into_result() {
+        Ok(_) => Ok(Response::new()),
+        Err(error) => {
+            let fee_escrow = state.fee_escrow.may_load(deps.storage)?.unwrap_or_default();
+            state
+                .fee_escrow
+                .save(deps.storage, &(fee_escrow + amount))?;
+
+            let event = Event::new("steakhub/fee_deposit_failed")
+                .add_attribute("amount", amount)
+                .add_attribute("fee_escrow", fee_escrow + amount)
+                .add_attribute("error", error);
+            Ok(Response::new().add_event(event))
+        }
     }
+}
 
-    let mut received_coins = Coins(vec![]);
-    for event in &events {
-        received_coins.add_many(&parse_coin_receiving_event(&env, event)?)?;
+pub fn release_fee_escrow(deps: DepsMut) -> StdResult<Response> {
+    let state = State::default();
+
+    let fee_escrow = state.fee_escrow.may_load(deps.storage)?.unwrap_or_default();
+    if fee_escrow.is_zero() {
+        return Err(StdError::generic_err("no fees in escrow"));
     }
+    state.fee_escrow.save(deps.storage, &Uint128::zero())?;
+
+    // The fees were entered in the fee ledger when they were taken. If the deposit fails again,
+    // the whole release does and the fees stay in escrow
+    let denom = state.denom.load(deps.storage)?;
+    let fee_account = state.fee_account.load(deps.storage)?;
+    let fee_type = state.fee_account_type.load(deps.storage)?;
+    let msg = fee_payment_msg(
+        deps.storage,
+        &fee_type,
+        &fee_account,
+        Coin::new(fee_escrow.u128(), &denom),
+    )?;
+
+    let event = Event::new("steakhub/fee_escrow_released")
+        .add_attribute("fee_account", fee_account)
+        .add_attribute("amount", fee_escrow);
+
+    Ok(Response::new()
+        .add_message(msg)
+        .add_event(event)
+        .add_attribute("action", "steakhub/release_fee_escrow"))
+}
 
+/// Mint `amount` of Steak to the hub, and start vesting it to `beneficiary` on `fee_vesting`
+fn vest_fee(
+    storage: &mut dyn Storage,
+    env: &Env,
+    beneficiary: &Addr,
+    amount: Uint128,
+    fee_vesting: &FeeVesting,
+) -> StdResult<Vec<CosmosMsg>> {
     let state = State::default();
-    state
-        .unlocked_coins
-        .update(deps.storage, |coins| -> StdResult<_> {
-            let mut coins = Coins(coins);
-            coins.add_many(&received_coins)?;
-            Ok(coins.0)
-        })?;
 
-    Ok(Response::new().add_attribute("action", "steakhub/register_received_coins"))
+    let id = state
+        .next_vesting_tranche_id
+        .may_load(storage)?
+        .unwrap_or_default();
+    state.next_vesting_tranche_id.save(storage, &(id + 1))?;
+
+    let start = env.block.time.seconds();
+    state.vesting_tranches.save(
+        storage,
+        (beneficiary, id),
+        &VestingTranche {
+            amount,
+            claimed: Uint128::zero(),
+            start,
+            cliff_end: start + fee_vesting.cliff,
+            end: start + fee_vesting.duration,
+        },
+    )?;
+
+    mint_usteak_msgs(
+        storage,
+        &env.contract.address,
+        &env.contract.address,
+        amount,
+    )
 }
 
-fn parse_coin_receiving_event(env: &Env, event: &Event) -> StdResult<Coins> {
-    let receiver = &event
-        .attributes
-        .iter()
-        .find(|attr| attr.key == "receiver")
-        .ok_or_else(|| StdError::generic_err("cannot find `receiver` attribute"))?
-        .value;
+/// Set aside the referrers' share of a protocol fee: `referral_bps` of the fee earned on the stake
+/// they brought in, split among them by volume. Returns the amount set aside, which stays with the
+/// hub until claimed
+fn accrue_referral_kickback(
+    storage: &mut dyn Storage,
+    fee_amount: Uint128,
+    total_bonded: u128,
+) -> StdResult<Uint128> {
+    let state = State::default();
+    let referral_bps = state.referral_bps.may_load(storage)?.unwrap_or_default();
+    let referral_volume = state.referral_volume.may_load(storage)?.unwrap_or_default();
+    if referral_bps == 0 || referral_volume.is_zero() || total_bonded == 0 {
+        return Ok(Uint128::zero());
+    }
 
-    let amount_str = &event
-        .attributes
-        .iter()
-        .find(|attr| attr.key == "amount")
-        .ok_or_else(|| StdError::generic_err("cannot find `amount` attribute"))?
-        .value;
+    // Referral volume only ever grows, so it may exceed the stake that is still bonded
+    let referred_stake = referral_volume.min(Uint128::new(total_bonded));
+    let kickback = fee_amount
+        .multiply_ratio(referral_bps, 10_000u64)
+        .multiply_ratio(referred_stake, total_bonded);
+    if kickback.is_zero() {
+        return Ok(kickback);
+    }
 
-    let amount = if *receiver == env.contract.address {
-        Coins::from_str(amount_str)?
-    } else {
-        Coins(vec![])
+    let index = state.referral_index.may_load(storage)?.unwrap_or_default()
+        + Decimal::from_ratio(kickback, referral_volume);
+    state.referral_index.save(storage, &index)?;
+    let unclaimed = state
+        .referral_unclaimed
+        .may_load(storage)?
+        .unwrap_or_default();
+    state
+        .referral_unclaimed
+        .save(storage, &(unclaimed + kickback))?;
+    Ok(kickback)
+}
+
+/// Add `amount` to what was bonded for `receiver` in its current rate limit window, refusing to go
+/// over the limit
+fn record_bond_window(
+    storage: &mut dyn Storage,
+    env: &Env,
+    receiver: &Addr,
+    amount: Uint128,
+) -> StdResult<()> {
+    let state = State::default();
+
+    let limit = match state.bond_rate_limit.may_load(storage)? {
+        Some(limit) => limit,
+        None => return Ok(()),
     };
+    let mut window = state
+        .bond_windows
+        .may_load(storage, receiver)?
+        .unwrap_or_default()
+        .at(env.block.time.seconds(), limit.window_secs);
+    window.bonded = window.bonded.checked_add(amount)?;
+    if window.bonded > limit.max_bond_per_window {
+        return Err(StdError::generic_err(format!(
+            "{} would have {} bonded in the window, above the limit of {}",
+            receiver, window.bonded, limit.max_bond_per_window
+        )));
+    }
+    state.bond_windows.save(storage, receiver, &window)
+}
 
-    Ok(amount)
+/// Add `amount` to the volume of `referrer`, settling the kickbacks it accrued at its old volume
+fn record_referral(storage: &mut dyn Storage, referrer: &Addr, amount: Uint128) -> StdResult<()> {
+    let state = State::default();
+    let mut record = state.load_referrer(storage, referrer)?;
+    record.volume += amount;
+    state.referrers.save(storage, referrer, &record)?;
+
+    let referral_volume = state.referral_volume.may_load(storage)?.unwrap_or_default();
+    state
+        .referral_volume
+        .save(storage, &(referral_volume + amount))
 }
 
-//--------------------------------------------------------------------------------------------------
-// Unbonding logics
-//--------------------------------------------------------------------------------------------------
+pub fn claim_referral_rewards(deps: DepsMut, env: Env, sender: Addr) -> StdResult<Response> {
+    let state = State::default();
+    let mut record = state.load_referrer(deps.storage, &sender)?;
+    if record.unclaimed.is_zero() {
+        return Err(StdError::generic_err("no referral rewards to claim"));
+    }
+    let amount = record.unclaimed;
+    record.unclaimed = Uint128::zero();
+    state.referrers.save(deps.storage, &sender, &record)?;
 
-pub fn queue_unbond(
-    deps: DepsMut,
-    env: Env,
-    receiver: Addr,
-    usteak_to_burn: Uint128,
-) -> StdResult<Response> {
+    let unclaimed = state
+        .referral_unclaimed
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    state
+        .referral_unclaimed
+        .save(deps.storage, &unclaimed.saturating_sub(amount))?;
+
+    let denom = state.denom.load(deps.storage)?;
+    let send_msg = BankMsg::Send {
+        to_address: sender.to_string(),
+        amount: vec![Coin::new(amount.u128(), &denom)],
+    };
+
+    let event = Event::new("steakhub/referral_rewards_claimed")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("referrer", sender)
+        .add_attribute("amount", amount);
+
+    Ok(Response::new()
+        .add_message(send_msg)
+        .add_event(event)
+        .add_attribute("action", "steakhub/claim_referral_rewards"))
+}
+
+pub fn claim_vested_fees(deps: DepsMut, env: Env, sender: Addr) -> StdResult<Response> {
     let state = State::default();
+    let now = env.block.time.seconds();
 
-    let mut pending_batch = state.pending_batch.load(deps.storage)?;
-    pending_batch.usteak_to_burn += usteak_to_burn;
-    state.pending_batch.save(deps.storage, &pending_batch)?;
+    let tranches = state
+        .vesting_tranches
+        .prefix(&sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
 
-    state.unbond_requests.update(
-        deps.storage,
-        (pending_batch.id, &receiver),
-        |x| -> StdResult<_> {
-            let mut request = x.unwrap_or_else(|| UnbondRequest {
-                id: pending_batch.id,
-                user: receiver.clone(),
-                shares: Uint128::zero(),
-            });
-            request.shares += usteak_to_burn;
-            Ok(request)
-        },
-    )?;
+    let mut amount = Uint128::zero();
+    for (id, mut tranche) in tranches {
+        let claimable = tranche.claimable(now);
+        if claimable.is_zero() {
+            continue;
+        }
+        amount += claimable;
+        tranche.claimed += claimable;
+        if tranche.claimed == tranche.amount {
+            state.vesting_tranches.remove(deps.storage, (&sender, id));
+        } else {
+            state
+                .vesting_tranches
+                .save(deps.storage, (&sender, id), &tranche)?;
+        }
+    }
+    if amount.is_zero() {
+        return Err(StdError::generic_err("no vested fees to claim"));
+    }
 
-    let mut msgs: Vec<CosmosMsg> = vec![];
+    let transfer_msg = transfer_usteak_msg(deps.storage, &sender, amount)?;
+
+    let event = Event::new("steakhub/vested_fees_claimed")
+        .add_attribute("time", now.to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("account", sender)
+        .add_attribute("usteak_claimed", amount);
+
+    Ok(Response::new()
+        .add_message(transfer_msg)
+        .add_event(event)
+        .add_attribute("action", "steakhub/claim_vested_fees"))
+}
+
+/// Refuse to go on if rewards were last harvested more than `max_harvest_staleness` ago: until they
+/// are reinvested, the exchange rate understates the stake, and Steak would be minted or redeemed
+/// at the wrong price
+fn assert_harvest_fresh(storage: &dyn Storage, env: &Env) -> StdResult<()> {
+    let state = State::default();
+    let max_harvest_staleness = match state.max_harvest_staleness.may_load(storage)?.flatten() {
+        Some(max_harvest_staleness) => max_harvest_staleness,
+        None => return Ok(()),
+    };
+    if let Some(last_harvest_time) = state.last_harvest_time.may_load(storage)? {
+        if env.block.time.seconds() > last_harvest_time + max_harvest_staleness {
+            return Err(StdError::generic_err(format!(
+                "exchange rate is stale: last harvest at {} is more than {} seconds ago",
+                last_harvest_time, max_harvest_staleness
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Refuse to go on if the owner halted `operation`, as reported by `capability`
+fn assert_capability(
+    storage: &dyn Storage,
+    capability: fn(&Capabilities) -> bool,
+    operation: &str,
+) -> StdResult<()> {
+    let capabilities = State::default()
+        .capabilities
+        .may_load(storage)?
+        .unwrap_or_default();
+    if !capability(&capabilities) {
+        return Err(StdError::generic_err(format!("{} is paused", operation)));
+    }
+    Ok(())
+}
+
+/// Refuse deposits of the hub's own Steak or of other liquid staking derivatives. Bonding them
+/// would back Steak with Steak, and the hub's accounting can't tell them apart from Native Token
+fn assert_no_derivative_deposit(storage: &dyn Storage, funds: &[Coin]) -> StdResult<()> {
+    let state = State::default();
+    let usteak_denom = state.usteak_denom.may_load(storage)?;
+    let forbidden_denoms = state
+        .forbidden_deposit_denoms
+        .may_load(storage)?
+        .unwrap_or_default();
+
+    for coin in funds {
+        if usteak_denom.as_ref() == Some(&coin.denom) {
+            return Err(StdError::generic_err(format!(
+                "{} is this hub's own Steak and can't be bonded",
+                coin.denom
+            )));
+        }
+        if forbidden_denoms.contains(&coin.denom) {
+            return Err(StdError::generic_err(format!(
+                "{} is a liquid staking derivative and can't be bonded",
+                coin.denom
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Native Token per Steak at the current delegations and supply
+fn query_exchange_rate(deps: Deps, env: &Env) -> StdResult<Decimal> {
+    let state = State::default();
+    let denom = state.denom.load(deps.storage)?;
+    let delegations =
+        query_bonded_delegations(&deps.querier, deps.storage, &env.contract.address, &denom)?;
+    let native_bonded: u128 = delegations.iter().map(|d| d.amount).sum();
+    let usteak_supply = query_usteak_supply(&deps.querier, deps.storage)?;
+    Ok(if usteak_supply.is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(native_bonded, usteak_supply)
+    })
+}
+
+/// Refuse to go on if the exchange rate dropped by more than the epsilon since it was last
+/// observed, as minting or unbonding at the lower rate would lock in the loss for holders. Once a
+/// guardian has acknowledged the drop (a genuine slash), the lower rate is accepted as the new
+/// reference
+fn assert_exchange_rate_floor(
+    storage: &mut dyn Storage,
+    native_bonded: u128,
+    usteak_supply: Uint128,
+) -> StdResult<()> {
+    let state = State::default();
+
+    if usteak_supply.is_zero() {
+        return Ok(());
+    }
+    let exchange_rate = Decimal::from_ratio(native_bonded, usteak_supply);
+
+    if let Some(last_exchange_rate) = state.last_exchange_rate.may_load(storage)? {
+        let epsilon = state.load_exchange_rate_epsilon(storage)?;
+        let floor = last_exchange_rate * (Decimal::one() - epsilon);
+        if exchange_rate < floor {
+            if !state.slash_acknowledged.may_load(storage)?.unwrap_or(false) {
+                return Err(StdError::generic_err(format!(
+                    "exchange rate dropped from {} to {}; a guardian must acknowledge the slash",
+                    last_exchange_rate, exchange_rate
+                )));
+            }
+            state.slash_acknowledged.save(storage, &false)?;
+        }
+    }
+    state.last_exchange_rate.save(storage, &exchange_rate)
+}
+
+/// Amount of native a validator should hold: its share of `total_delegated` by mining power, or an
+/// even share when mining is disabled or no mining power has been recorded yet, limited by its cap.
+/// Reinvesting and rebalancing both target these amounts, so that native is never reinvested into a
+/// validator that rebalancing would then move it away from
+pub(crate) fn compute_validator_target(
+    storage: &dyn Storage,
+    validator: &str,
+    total_delegated: u128,
+    validator_count: usize,
+) -> StdResult<Uint128> {
+    let state = State::default();
+    let total_mining_power = state
+        .total_mining_power
+        .may_load(storage)?
+        .unwrap_or_default();
+
+    let target = if state.is_mining_enabled(storage)? && !total_mining_power.is_zero() {
+        compute_target_delegation_from_mining_power(
+            total_delegated.into(),
+            state
+                .validator_mining_powers
+                .may_load(storage, validator.to_string())?
+                .unwrap_or_default(),
+            total_mining_power,
+        )?
+    } else {
+        Uint128::new(total_delegated / validator_count.max(1) as u128)
+    };
+
+    Ok(
+        match state
+            .validator_caps
+            .may_load(storage, validator.to_string())?
+        {
+            Some(cap) => target.min(cap),
+            None => target,
+        },
+    )
+}
+
+/// NOTE: a `SubMsgResponse` may contain multiple coin-receiving events, must handle them individually
+pub fn register_received_coins(
+    deps: DepsMut,
+    env: Env,
+    mut events: Vec<Event>,
+) -> StdResult<Response> {
+    events.retain(|event| event.ty == "coin_received");
+    if events.is_empty() {
+        return Ok(Response::new());
+    }
+
+    let mut received_coins = Coins(vec![]);
+    for event in &events {
+        received_coins.add_many(&parse_coin_receiving_event(&env, event)?)?;
+    }
+
+    let state = State::default();
+    state
+        .unlocked_coins
+        .update(deps.storage, |coins| -> StdResult<_> {
+            let mut coins = Coins(coins);
+            coins.add_many(&received_coins)?;
+            Ok(coins.0)
+        })?;
+
+    Ok(Response::new().add_attribute("action", "steakhub/register_received_coins"))
+}
+
+/// Record when the staking module says batch `batch_id`'s undelegation from `validator` completes,
+/// and make the latest of those times the batch's end, in place of the estimate from the
+/// configured unbonding period. Chains that don't report it keep the estimate
+pub fn finish_undelegation(
+    mut deps: DepsMut,
+    env: Env,
+    batch_id: u64,
+    validator: String,
+    events: Vec<Event>,
+) -> StdResult<Response> {
+    let completion_time = events
+        .iter()
+        .filter(|event| event.ty == "unbond")
+        .flat_map(|event| &event.attributes)
+        .find(|attr| attr.key == "completion_time")
+        .map(|attr| parse_rfc3339_seconds(&attr.value))
+        .transpose()?;
+
+    let response = register_received_coins(deps.branch(), env, events)?;
+
+    let completion_time = match completion_time {
+        Some(time) => time,
+        None => return Ok(response),
+    };
+
+    let state = State::default();
+    state
+        .unbond_completion_times
+        .save(deps.storage, (batch_id, &validator), &completion_time)?;
+    let est_unbond_end_time = state
+        .unbond_completion_times
+        .prefix(batch_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, time)| time))
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .max()
+        .unwrap_or(completion_time);
+
+    let denom = state.denom.load(deps.storage)?;
+    let mut batch = state
+        .previous_batches
+        .load(deps.storage, (&denom, batch_id))?;
+    batch.est_unbond_end_time = est_unbond_end_time;
+    state.save_previous_batch(deps.storage, &denom, &batch)?;
+
+    Ok(response.add_event(
+        Event::new("steakhub/unbond_completion_recorded")
+            .add_attribute("id", batch_id.to_string())
+            .add_attribute("validator", validator)
+            .add_attribute("completion_time", completion_time.to_string())
+            .add_attribute("est_unbond_end_time", est_unbond_end_time.to_string()),
+    ))
+}
+
+/// In `HarvestMode::BalanceDiff`, add whatever the hub's balances grew by since the harvest started
+/// to `unlocked_coins`, as `register_received_coins` would have from the events
+fn register_harvested_balances(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    contract_addr: &Addr,
+) -> StdResult<()> {
+    let state = State::default();
+
+    let prev_balances = match state.harvest_balances.may_load(storage)? {
+        Some(balances) => Coins(balances),
+        None => return Ok(()),
+    };
+    state.harvest_balances.remove(storage);
+
+    let mut received_coins = Coins(querier.query_all_balances(contract_addr)?);
+    received_coins.saturating_sub_many(&prev_balances);
+
+    state
+        .unlocked_coins
+        .update(storage, |coins| -> StdResult<_> {
+            let mut coins = Coins(coins);
+            coins.add_many(&received_coins)?;
+            Ok(coins.0)
+        })?;
+    Ok(())
+}
+
+fn parse_coin_receiving_event(env: &Env, event: &Event) -> StdResult<Coins> {
+    let receiver = &event
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "receiver")
+        .ok_or_else(|| StdError::generic_err("cannot find `receiver` attribute"))?
+        .value;
+
+    let amount_str = &event
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "amount")
+        .ok_or_else(|| StdError::generic_err("cannot find `amount` attribute"))?
+        .value;
+
+    let amount = if *receiver == env.contract.address {
+        Coins::from_str(amount_str)?
+    } else {
+        Coins(vec![])
+    };
+
+    Ok(amount)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Unbonding logics
+//--------------------------------------------------------------------------------------------------
+
+pub fn queue_unbond(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    receiver: Addr,
+    usteak_to_burn: Uint128,
+    min_native_out: Option<Uint128>,
+) -> StdResult<Response> {
+    let state = State::default();
+    assert_capability(deps.storage, |c| c.unbond, "unbonding")?;
+    assert_harvest_fresh(deps.storage, &env)?;
+
+    let priority = is_priority_unbond(deps.storage, &sender, &receiver)?;
+    let min_unbond_shares = state
+        .min_unbond_shares
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if usteak_to_burn < min_unbond_shares && !priority {
+        return Err(StdError::generic_err(format!(
+            "unbond amount {} is below the minimum of {}",
+            usteak_to_burn, min_unbond_shares
+        )));
+    }
+
+    let exchange_rate = query_exchange_rate(deps.as_ref(), &env)?;
+    let denom = state.denom.load(deps.storage)?;
+    let mut pending_batch = state.pending_batches.load(deps.storage, &denom)?;
+    let (call_index, request_shares) = add_to_pending_batch(
+        deps.storage,
+        &mut pending_batch,
+        &sender,
+        &receiver,
+        usteak_to_burn,
+        env.block.time.seconds(),
+        exchange_rate,
+    )?;
+    state
+        .pending_batches
+        .save(deps.storage, &denom, &pending_batch)?;
+    // Calls to the same request add up their minimums, as they do their shares
+    if let Some(min_native_out) = min_native_out {
+        state.unbond_min_outs.update(
+            deps.storage,
+            (pending_batch.id, &receiver),
+            |min| -> StdResult<_> { Ok(min.unwrap_or_default() + min_native_out) },
+        )?;
+    }
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
     if env.block.time.seconds() >= pending_batch.est_unbond_start_time {
         msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: env.contract.address.into(),
-            msg: to_binary(&ExecuteMsg::SubmitBatch {})?,
+            msg: to_binary(&ExecuteMsg::SubmitBatch { exclude: vec![] })?,
             funds: vec![],
         }));
     }
 
-    let event = Event::new("steakhub/unbond_queued")
-        .add_attribute("time", env.block.time.seconds().to_string())
-        .add_attribute("height", env.block.height.to_string())
-        .add_attribute("id", pending_batch.id.to_string())
-        .add_attribute("receiver", receiver)
-        .add_attribute("usteak_to_burn", usteak_to_burn);
+    let receipt = UnbondReceipt {
+        batch_id: pending_batch.id,
+        receiver: receiver.to_string(),
+        usteak_to_burn,
+        request_shares,
+        est_unbond_start_time: pending_batch.est_unbond_start_time,
+    };
+
+    let mut event = Event::new("steakhub/unbond_queued")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("id", pending_batch.id.to_string())
+        .add_attribute("sender", sender)
+        .add_attribute("receiver", receiver)
+        .add_attribute("usteak_to_burn", usteak_to_burn)
+        .add_attribute("call_index", call_index.to_string())
+        .add_attribute("request_shares", request_shares);
+    if let Some(min_native_out) = min_native_out {
+        event = event.add_attribute("min_native_out", min_native_out);
+    }
+    if priority {
+        event = event.add_attribute("priority", "true");
+    }
+
+    Ok(Response::new()
+        .set_data(to_binary(&receipt)?)
+        .add_messages(msgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/queue_unbond"))
+}
+
+/// Whether a request is queued by a priority unbonder for itself. Requests queued for them by
+/// others don't count, lest anyone keep small batches from being rolled over
+fn is_priority_unbond(storage: &dyn Storage, sender: &Addr, receiver: &Addr) -> StdResult<bool> {
+    let priority_unbonders = State::default()
+        .priority_unbonders
+        .may_load(storage)?
+        .unwrap_or_default();
+    Ok(sender == receiver && priority_unbonders.contains(sender))
+}
+
+/// Queue cw20 Steak sent through the `Receive` hook. Once the token has been migrated, the hub
+/// swaps it first, so that every batch is burned in the token factory denom
+pub fn queue_unbond_cw20(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    receiver: Addr,
+    usteak_to_burn: Uint128,
+    min_native_out: Option<Uint128>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    let swap_msgs = match state.usteak_denom.may_load(deps.storage)? {
+        Some(usteak_denom) => swap_cw20_msgs(
+            deps.storage,
+            &env.contract.address,
+            &usteak_denom,
+            usteak_to_burn,
+        )?,
+        None => vec![],
+    };
+
+    let mut res = queue_unbond(deps, env, sender, receiver, usteak_to_burn, min_native_out)?;
+    res.messages
+        .splice(0..0, swap_msgs.into_iter().map(SubMsg::new));
+    Ok(res)
+}
+
+/// Queue the token factory Steak sent along
+pub fn queue_unbond_native(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    receiver: Addr,
+    funds: Vec<Coin>,
+    min_native_out: Option<Uint128>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    let usteak_denom = state.usteak_denom.may_load(deps.storage)?.ok_or_else(|| {
+        StdError::generic_err("the token has not been migrated; send the cw20 token instead")
+    })?;
+    let usteak_to_burn = parse_received_fund(&funds, &usteak_denom)?;
+
+    queue_unbond(deps, env, sender, receiver, usteak_to_burn, min_native_out)
+}
+
+/// Add `shares` sent by `sender` to `user`'s unbonding request in the pending batch, queued at
+/// `exchange_rate`.
+///
+/// Returns the index of the call record that was written, and the request's new total shares
+fn add_to_pending_batch(
+    storage: &mut dyn Storage,
+    pending_batch: &mut PendingBatch,
+    sender: &Addr,
+    user: &Addr,
+    shares: Uint128,
+    time: u64,
+    exchange_rate: Decimal,
+) -> StdResult<(u32, Uint128)> {
+    let state = State::default();
+
+    pending_batch.usteak_to_burn += shares;
+    let mut request = state
+        .load_unbond_request(storage, pending_batch.id, user)?
+        .unwrap_or_else(|| UnbondRequest {
+            id: pending_batch.id,
+            user: user.clone(),
+            shares: Uint128::zero(),
+        });
+    // Calls to the same request average their rates, weighted by their shares. Requests queued
+    // before rates were recorded count at the rate of the call
+    let queued_exchange_rate = match state
+        .unbond_queue_rates
+        .may_load(storage, (pending_batch.id, user))?
+    {
+        Some(rate) if !request.shares.is_zero() => {
+            let total_shares = request.shares + shares;
+            rate * Decimal::from_ratio(request.shares, total_shares)
+                + exchange_rate * Decimal::from_ratio(shares, total_shares)
+        }
+        _ => exchange_rate,
+    };
+    state
+        .unbond_queue_rates
+        .save(storage, (pending_batch.id, user), &queued_exchange_rate)?;
+    request.shares += shares;
+    state.save_unbond_request(storage, &request)?;
+    state.unbond_request_sources.update(
+        storage,
+        (pending_batch.id, user, sender),
+        |x| -> StdResult<_> {
+            let mut source = x.unwrap_or_else(|| UnbondRequestSource {
+                id: pending_batch.id,
+                sender: sender.clone(),
+                receiver: user.clone(),
+                shares: Uint128::zero(),
+            });
+            source.shares += shares;
+            Ok(source)
+        },
+    )?;
+
+    let last_call = state
+        .unbond_calls
+        .prefix((pending_batch.id, user))
+        .range(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map(|(_, call)| call);
+    let call = match last_call {
+        Some(mut call) if call.index + 1 >= MAX_UNBOND_CALLS_PER_REQUEST => {
+            call.sender = sender.clone();
+            call.shares += shares;
+            call.time = time;
+            call.calls += 1;
+            call
+        }
+        last_call => UnbondCall {
+            index: last_call.map(|call| call.index + 1).unwrap_or(0),
+            sender: sender.clone(),
+            shares,
+            time,
+            calls: 1,
+        },
+    };
+    state
+        .unbond_calls
+        .save(storage, (pending_batch.id, user, call.index), &call)?;
+
+    Ok((call.index, request.shares))
+}
+
+pub fn create_unbond_order(
+    deps: DepsMut,
+    env: Env,
+    user: Addr,
+    funds: Vec<Coin>,
+    shares_per_epoch: Uint128,
+    epochs: u32,
+) -> StdResult<Response> {
+    let state = State::default();
+    assert_capability(deps.storage, |c| c.unbond, "unbonding")?;
+
+    if shares_per_epoch.is_zero() || epochs == 0 {
+        return Err(StdError::generic_err(
+            "shares per epoch and epochs must be greater than zero",
+        ));
+    }
+    // Each slice is queued like a `QueueUnbond` of its own, and held to the same minimum
+    let min_unbond_shares = state
+        .min_unbond_shares
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if shares_per_epoch < min_unbond_shares {
+        return Err(StdError::generic_err(format!(
+            "shares per epoch {} is below the minimum of {}",
+            shares_per_epoch, min_unbond_shares
+        )));
+    }
+    if state.unbond_orders.has(deps.storage, &user) {
+        return Err(StdError::generic_err("user already has an unbond order"));
+    }
+    if state
+        .unbond_orders
+        .keys(deps.storage, None, None, Order::Ascending)
+        .count()
+        >= MAX_UNBOND_ORDERS
+    {
+        return Err(StdError::generic_err(format!(
+            "no more than {} unbond orders may be open at once",
+            MAX_UNBOND_ORDERS
+        )));
+    }
+    let usteak_to_escrow = shares_per_epoch.checked_mul(Uint128::from(epochs))?;
+
+    // Once the token has been migrated, the escrow is sent along instead of pulled with an allowance
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    match state.usteak_denom.may_load(deps.storage)? {
+        Some(usteak_denom) => {
+            let usteak_received = parse_received_fund(&funds, &usteak_denom)?;
+            if usteak_received != usteak_to_escrow {
+                return Err(StdError::generic_err(format!(
+                    "must send exactly {}{}; received {}{}",
+                    usteak_to_escrow, usteak_denom, usteak_received, usteak_denom
+                )));
+            }
+        }
+        None => {
+            let steak_token = state.steak_token.load(deps.storage)?;
+            msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: steak_token.into(),
+                msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                    owner: user.to_string(),
+                    recipient: env.contract.address.to_string(),
+                    amount: usteak_to_escrow,
+                })?,
+                funds: vec![],
+            }));
+        }
+    }
+
+    state.unbond_orders.save(
+        deps.storage,
+        &user,
+        &UnbondOrder {
+            user: user.clone(),
+            shares_per_epoch,
+            epochs_remaining: epochs,
+            last_batch_id: None,
+        },
+    )?;
+
+    let event = Event::new("steakhub/unbond_order_created")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("user", user)
+        .add_attribute("shares_per_epoch", shares_per_epoch)
+        .add_attribute("epochs", epochs.to_string());
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/create_unbond_order"))
+}
+
+pub fn cancel_unbond_order(deps: DepsMut, env: Env, user: Addr) -> StdResult<Response> {
+    let state = State::default();
+
+    let order = state
+        .unbond_orders
+        .may_load(deps.storage, &user)?
+        .ok_or_else(|| StdError::generic_err("user has no unbond order"))?;
+    state.unbond_orders.remove(deps.storage, &user);
+    let usteak_to_return = order
+        .shares_per_epoch
+        .checked_mul(Uint128::from(order.epochs_remaining))?;
+
+    let transfer_msg = transfer_usteak_msg(deps.storage, &user, usteak_to_return)?;
+
+    let event = Event::new("steakhub/unbond_order_cancelled")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("user", user)
+        .add_attribute("usteak_returned", usteak_to_return);
+
+    Ok(Response::new()
+        .add_message(transfer_msg)
+        .add_event(event)
+        .add_attribute("action", "steakhub/cancel_unbond_order"))
+}
+
+pub fn submit_batch(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    exclude: Vec<String>,
+) -> StdResult<Response> {
+    let state = State::default();
+    assert_capability(deps.storage, |c| c.submit_batch, "batch submission")?;
+    let denom = state.denom.load(deps.storage)?;
+    let validators = state.validators.load(deps.storage)?;
+    let max_batch_exclusions = state
+        .max_batch_exclusions
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let mut exclude = exclude;
+    exclude.sort();
+    exclude.dedup();
+    if exclude.len() > max_batch_exclusions as usize {
+        return Err(StdError::generic_err(format!(
+            "can't exclude more than {} validators from a batch",
+            max_batch_exclusions
+        )));
+    }
+    if let Some(validator) = exclude.iter().find(|v| !validators.contains(v)) {
+        return Err(StdError::generic_err(format!(
+            "validator {} is not whitelisted",
+            validator
+        )));
+    }
+    if exclude.len() == validators.len() {
+        return Err(StdError::generic_err(
+            "can't exclude every validator from a batch",
+        ));
+    }
+    let unbond_period = state.unbond_period.load(deps.storage)?;
+    let submit_deadline_buffer = state
+        .submit_deadline_buffer
+        .may_load(deps.storage)?
+        .flatten();
+    let mut pending_batch = state.pending_batches.load(deps.storage, &denom)?;
+
+    let current_time = env.block.time.seconds();
+    if current_time < pending_batch.est_unbond_start_time {
+        return Err(StdError::generic_err(format!(
+            "batch can only be submitted for unbonding after {}",
+            pending_batch.est_unbond_start_time
+        )));
+    }
+    // Past the deadline, the guardian takes over from keepers. Ticks from the chain's clock are
+    // sent by the hub itself and always go through
+    if let Some(buffer) = submit_deadline_buffer {
+        if current_time > pending_batch.est_unbond_start_time + buffer
+            && sender != env.contract.address
+        {
+            state.assert_guardian(deps.storage, &sender)?;
+        }
+    }
+
+    // Cache the chain's unbonding time for `UnbondEta`, which as a query can't store it itself
+    if state.chain_unbonding_time.may_load(deps.storage)?.is_none() {
+        if let Some(time) = query_chain_unbonding_time(&deps.querier) {
+            state.chain_unbonding_time.save(deps.storage, &time)?;
+        }
+    }
+
+    // Queue the next slice of each unbond order into the batch. The usteak is already held by the
+    // hub in escrow, so it is burned along with the rest of the batch. All orders are processed at
+    // once, which `MAX_UNBOND_ORDERS` keeps within the gas limit. A batch rolled or held over to
+    // the next epoch already has its slice from each order
+    let orders = state
+        .unbond_orders
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((_, order)) => (order.last_batch_id != Some(pending_batch.id)).then(|| Ok(order)),
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let exchange_rate = if orders.is_empty() {
+        Decimal::one()
+    } else {
+        query_exchange_rate(deps.as_ref(), &env)?
+    };
+    for mut order in orders {
+        add_to_pending_batch(
+            deps.storage,
+            &mut pending_batch,
+            &order.user,
+            &order.user,
+            order.shares_per_epoch,
+            env.block.time.seconds(),
+            exchange_rate,
+        )?;
+        order.epochs_remaining -= 1;
+        order.last_batch_id = Some(pending_batch.id);
+        if order.epochs_remaining == 0 {
+            state.unbond_orders.remove(deps.storage, &order.user);
+        } else {
+            state
+                .unbond_orders
+                .save(deps.storage, &order.user, &order)?;
+        }
+    }
+
+    // Hold a batch too small to be worth submitting over to the next epoch, within limits, unless
+    // a priority unbonder is waiting on it
+    let batch_rollover = state.batch_rollover.may_load(deps.storage)?;
+    if let Some(rollover) = batch_rollover {
+        let has_priority_request = state
+            .priority_unbonders
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            .iter()
+            .any(|user| {
+                state
+                    .unbond_request_sources
+                    .has(deps.storage, (pending_batch.id, user, user))
+            });
+        if pending_batch.usteak_to_burn < rollover.min_shares
+            && pending_batch.rollovers < rollover.max_rollovers
+            && !has_priority_request
+        {
+            let epoch_period = state.epoch_period.load(deps.storage)?;
+            pending_batch.rollovers += 1;
+            pending_batch.est_unbond_start_time = current_time + epoch_period;
+            state
+                .pending_batches
+                .save(deps.storage, &denom, &pending_batch)?;
+
+            let event = Event::new("steakhub/batch_rolled_over")
+                .add_attribute("id", pending_batch.id.to_string())
+                .add_attribute("usteak_to_burn", pending_batch.usteak_to_burn)
+                .add_attribute("min_shares", rollover.min_shares)
+                .add_attribute("rollovers", pending_batch.rollovers.to_string());
+
+            return Ok(Response::new()
+                .add_event(event)
+                .add_event(submit_window_event(&pending_batch, submit_deadline_buffer))
+                .add_attribute("action", "steakhub/roll_over_batch"));
+        }
+    }
+
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
+    // Stake still waiting to be moved off removed validators is bonded all the same
+    let draining =
+        query_draining_delegations(&deps.querier, deps.storage, &env.contract.address, &denom)?;
+    let bonded_delegations = [&delegations[..], &draining[..]].concat();
+    let usteak_supply = query_usteak_supply(&deps.querier, deps.storage)?;
+
+    // Requests that would now be submitted for less than their minimum, e.g. after a slash since
+    // they were queued, are held over to the next batch
+    let min_outs = state
+        .unbond_min_outs
+        .prefix(pending_batch.id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut held_over = vec![];
+    for (user, min_native_out) in &min_outs {
+        let request = state
+            .load_unbond_request(deps.storage, pending_batch.id, user)?
+            .ok_or_else(|| StdError::generic_err(format!("no unbonding request for {}", user)))?;
+        let native_out = compute_unbond_amount(usteak_supply, request.shares, &bonded_delegations);
+        if native_out < *min_native_out {
+            held_over.push((request, native_out, *min_native_out));
+        }
+    }
+    let usteak_held_over: Uint128 = held_over.iter().map(|(request, ..)| request.shares).sum();
+    let epoch_period = state.epoch_period.load(deps.storage)?;
+    let held_over_events = held_over
+        .iter()
+        .map(|(request, native_out, min_native_out)| {
+            Event::new("steakhub/unbond_held_over")
+                .add_attribute("id", request.id.to_string())
+                .add_attribute("user", request.user.to_string())
+                .add_attribute("shares", request.shares)
+                .add_attribute("native_out", *native_out)
+                .add_attribute("min_native_out", *min_native_out)
+        })
+        .collect::<Vec<_>>();
+
+    // With every request held over, the batch as a whole waits for the next epoch
+    if !held_over.is_empty() && usteak_held_over == pending_batch.usteak_to_burn {
+        pending_batch.est_unbond_start_time = current_time + epoch_period;
+        state
+            .pending_batches
+            .save(deps.storage, &denom, &pending_batch)?;
+
+        return Ok(Response::new()
+            .add_events(held_over_events)
+            .add_event(submit_window_event(&pending_batch, submit_deadline_buffer))
+            .add_attribute("action", "steakhub/hold_over_batch"));
+    }
+    for (request, ..) in &held_over {
+        move_unbond_request(deps.storage, request, pending_batch.id + 1)?;
+    }
+    for (user, _) in &min_outs {
+        state
+            .unbond_min_outs
+            .remove(deps.storage, (pending_batch.id, user));
+    }
+    pending_batch.usteak_to_burn -= usteak_held_over;
+
+    let amount_to_bond = compute_unbond_amount(
+        usteak_supply,
+        pending_batch.usteak_to_burn,
+        &bonded_delegations,
+    );
+
+    // Restaked stake can't be unbonded before the restaking adapter releases it
+    let native_bonded: u128 = bonded_delegations.iter().map(|d| d.amount).sum();
+    let restaked_native = state
+        .restaked_native
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if Uint128::new(native_bonded).checked_sub(amount_to_bond)? < restaked_native {
+        return Err(StdError::generic_err(format!(
+            "unbonding {} would leave less bonded than the {} restaked",
+            amount_to_bond, restaked_native
+        )));
+    }
+    // Only the validators left after the exclusions are unbonded from
+    let unbondable_delegations = delegations
+        .iter()
+        .filter(|d| !exclude.contains(&d.validator))
+        .cloned()
+        .collect::<Vec<_>>();
+    let native_unbondable: u128 = unbondable_delegations.iter().map(|d| d.amount).sum();
+    if native_unbondable < amount_to_bond.u128() {
+        return Err(StdError::generic_err(format!(
+            "validators left after exclusions only have {} delegated, can't unbond {}",
+            native_unbondable, amount_to_bond
+        )));
+    }
+    let remainder_offset = state.next_remainder_offset(deps.storage)?;
+    let min_delegation = state
+        .min_delegation_per_validator
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let new_undelegations = compute_undelegations_with_floor(
+        amount_to_bond,
+        &unbondable_delegations,
+        &denom,
+        remainder_offset,
+        min_delegation,
+    );
+
+    // Snapshot the rates in effect at submission, so the batch can be audited later on
+    let fee_rate = state.fee_rate.load(deps.storage)?;
+    assert_exchange_rate_floor(deps.storage, native_bonded, usteak_supply)?;
+    let exchange_rate = if usteak_supply.is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(native_bonded, usteak_supply)
+    };
+
+    // NOTE: Regarding the `amount_unclaimed` value
+    //
+    // If validators misbehave and get slashed during the unbonding period, the contract can receive
+    // LESS Native Token than `amount_to_unbond` when unbonding finishes!
+    //
+    // In this case, users who invokes `withdraw_unbonded` will have their txs failed as the contract
+    // does not have enough Native Token balance.
+    //
+    // I don't have a solution for this... other than to manually fund contract with the slashed amount.
+    state.save_previous_batch(
+        deps.storage,
+        &denom,
+        &Batch {
+            id: pending_batch.id,
+            reconciled: false,
+            total_shares: pending_batch.usteak_to_burn,
+            amount_unclaimed: amount_to_bond,
+            est_unbond_end_time: current_time + unbond_period,
+            fee_rate: Some(fee_rate),
+            exchange_rate: Some(exchange_rate),
+            submitter: Some(sender.clone()),
+            shortfalls: vec![],
+            backfilled: false,
+        },
+    )?;
+
+    let next_batch = PendingBatch {
+        id: pending_batch.id + 1,
+        usteak_to_burn: usteak_held_over,
+        est_unbond_start_time: current_time + epoch_period,
+        rollovers: 0,
+    };
+    state
+        .pending_batches
+        .save(deps.storage, &denom, &next_batch)?;
+    state.bump_counters(deps.storage, Uint128::zero(), amount_to_bond, 0)?;
+    state.record_supply_checkpoint(
+        deps.storage,
+        supply_checkpoint(
+            &env,
+            "batch",
+            Uint128::new(native_bonded) - amount_to_bond,
+            usteak_supply - pending_batch.usteak_to_burn,
+        ),
+    )?;
+    state.prev_denom.save(
+        deps.storage,
+        &get_denom_balance(&deps.querier, env.contract.address.clone(), denom)?,
+    )?;
+
+    let proto_urls = state.load_staking_proto_urls(deps.storage)?;
+    let undelegate_submsgs = new_undelegations
+        .iter()
+        .map(|d| {
+            Ok(SubMsg::reply_on_success(
+                d.to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+                state.register_reply(
+                    deps.storage,
+                    ReplyContext::Undelegate {
+                        batch_id: pending_batch.id,
+                        validator: d.validator.clone(),
+                    },
+                )?,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let burn_msg = burn_usteak_msg(
+        deps.storage,
+        &env.contract.address,
+        pending_batch.usteak_to_burn,
+    )?;
+
+    let normalization_event = normalize_mining_powers(deps.storage, &validators)?;
+    let restaking_hook = restaking_hook_submsg(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        0,
+        amount_to_bond.u128(),
+    )?;
+
+    let hooks = hook_submsgs(
+        deps.storage,
+        HookEventKind::BatchSubmission,
+        &SteakHookMsg::BatchSubmitted {
+            id: pending_batch.id,
+            native_unbonded: amount_to_bond,
+            usteak_burned: pending_batch.usteak_to_burn,
+            est_unbond_end_time: current_time + unbond_period,
+        },
+    )?;
+    state.last_batch_submitter.save(
+        deps.storage,
+        &OperationRecord {
+            address: sender.to_string(),
+            height: env.block.height,
+        },
+    )?;
+
+    let mut event = Event::new("steakhub/unbond_submitted")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("sender", sender)
+        .add_attribute("id", pending_batch.id.to_string())
+        .add_attribute("native_unbonded", amount_to_bond)
+        .add_attribute("usteak_burned", pending_batch.usteak_to_burn)
+        .add_attribute("fee_rate", fee_rate.to_string())
+        .add_attribute("exchange_rate", exchange_rate.to_string());
+    if !exclude.is_empty() {
+        event = event.add_attribute("validators_excluded", exclude.join(","));
+    }
+
+    Ok(Response::new()
+        .add_submessages(undelegate_submsgs)
+        .add_message(burn_msg)
+        .add_submessages(restaking_hook)
+        .add_submessages(hooks)
+        .add_event(event)
+        .add_events(held_over_events)
+        .add_events(normalization_event)
+        .add_event(submit_window_event(&next_batch, submit_deadline_buffer))
+        .add_attribute("action", "steakhub/unbond"))
+}
+
+/// Move an unbonding request of the pending batch, along with where its shares came from and its
+/// minimum, to the batch `id`
+fn move_unbond_request(
+    storage: &mut dyn Storage,
+    request: &UnbondRequest,
+    id: u64,
+) -> StdResult<()> {
+    let state = State::default();
+
+    let queued_exchange_rate = state
+        .unbond_queue_rates
+        .may_load(storage, (request.id, &request.user))?;
+    state.remove_unbond_request(storage, request.id, &request.user)?;
+    if let Some(rate) = queued_exchange_rate {
+        state
+            .unbond_queue_rates
+            .save(storage, (id, &request.user), &rate)?;
+    }
+    state.save_unbond_request(
+        storage,
+        &UnbondRequest {
+            id,
+            user: request.user.clone(),
+            shares: request.shares,
+        },
+    )?;
+
+    let sources = state
+        .unbond_request_sources
+        .prefix((request.id, &request.user))
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (sender, mut source) in sources {
+        state
+            .unbond_request_sources
+            .remove(storage, (request.id, &request.user, &sender))?;
+        source.id = id;
+        state
+            .unbond_request_sources
+            .save(storage, (id, &request.user, &sender), &source)?;
+    }
+
+    let calls = state
+        .unbond_calls
+        .prefix((request.id, &request.user))
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (index, call) in calls {
+        state
+            .unbond_calls
+            .remove(storage, (request.id, &request.user, index));
+        state
+            .unbond_calls
+            .save(storage, (id, &request.user, index), &call)?;
+    }
+
+    if let Some(min_native_out) = state
+        .unbond_min_outs
+        .may_load(storage, (request.id, &request.user))?
+    {
+        state
+            .unbond_min_outs
+            .remove(storage, (request.id, &request.user));
+        state
+            .unbond_min_outs
+            .save(storage, (id, &request.user), &min_native_out)?;
+    }
+
+    Ok(())
+}
+
+/// The supply checkpoint for the hub's stake and Steak supply right after `source` changed them
+fn supply_checkpoint(
+    env: &Env,
+    source: &str,
+    native_bonded: Uint128,
+    usteak_supply: Uint128,
+) -> SupplyCheckpoint {
+    SupplyCheckpoint {
+        id: 0,
+        height: env.block.height,
+        time: env.block.time.seconds(),
+        source: source.to_string(),
+        usteak_supply,
+        native_bonded,
+        exchange_rate: if usteak_supply.is_zero() {
+            Decimal::one()
+        } else {
+            Decimal::from_ratio(native_bonded, usteak_supply)
+        },
+    }
+}
+
+/// Announce when a pending batch may be submitted, so keepers can schedule it
+fn submit_window_event(batch: &PendingBatch, submit_deadline_buffer: Option<u64>) -> Event {
+    let mut event = Event::new("steakhub/submit_window_scheduled")
+        .add_attribute("id", batch.id.to_string())
+        .add_attribute("window_start", batch.est_unbond_start_time.to_string());
+    if let Some(buffer) = submit_deadline_buffer {
+        event = event.add_attribute(
+            "window_end",
+            (batch.est_unbond_start_time + buffer).to_string(),
+        );
+    }
+    event
+}
+
+/// Scale down or reset the validators' mining power at the end of an epoch, as configured, so it
+/// reflects recent work and doesn't grow without bound
+fn normalize_mining_powers(
+    storage: &mut dyn Storage,
+    validators: &[String],
+) -> StdResult<Option<Event>> {
+    let state = State::default();
+    let normalization = state
+        .mining_power_normalization
+        .may_load(storage)?
+        .unwrap_or_default();
+
+    let total_mining_power = match &normalization {
+        MiningPowerNormalization::Disabled => return Ok(None),
+        MiningPowerNormalization::Scale { factor } => {
+            let mining_powers = state
+                .validator_mining_powers
+                .range(storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?;
+            let mut total = Uint128::zero();
+            for (validator, mining_power) in mining_powers {
+                let mining_power = mining_power * *factor;
+                state
+                    .validator_mining_powers
+                    .save(storage, validator, &mining_power)?;
+                total += mining_power;
+            }
+            total
+        }
+        MiningPowerNormalization::Reset { baseline } => {
+            for validator in validators {
+                state
+                    .validator_mining_powers
+                    .save(storage, validator.clone(), baseline)?;
+            }
+            *baseline * Uint128::from(validators.len() as u128)
+        }
+    };
+    let previous_total = state
+        .total_mining_power
+        .may_load(storage)?
+        .unwrap_or_default();
+    state
+        .total_mining_power
+        .save(storage, &total_mining_power)?;
+
+    Ok(Some(
+        Event::new("steakhub/mining_power_normalized")
+            .add_attribute("previous_total_mining_power", previous_total)
+            .add_attribute("total_mining_power", total_mining_power),
+    ))
+}
+
+pub fn reconcile(deps: DepsMut, env: Env, sender: Addr) -> StdResult<Response> {
+    let state = State::default();
+    state.last_reconciler.save(
+        deps.storage,
+        &OperationRecord {
+            address: sender.to_string(),
+            height: env.block.height,
+        },
+    )?;
+
+    let denom = state.denom.load(deps.storage)?;
+    // Set aside what the hub was sent beyond what it owes first, so that it doesn't hide a shortfall
+    let native_parked = park_unexpected_native(deps.storage, &deps.querier, &env)?;
+    let (batches, native_to_deduct) = compute_reconciled_batches(deps.as_ref(), &env, None)?;
+    for batch in &batches {
+        state.save_previous_batch(deps.storage, &denom, batch)?;
+    }
+
+    let hooks = if batches.is_empty() {
+        vec![]
+    } else {
+        hook_submsgs(
+            deps.storage,
+            HookEventKind::Reconciliation,
+            &SteakHookMsg::BatchesReconciled {
+                ids: batches.iter().map(|b| b.id).collect(),
+            },
+        )?
+    };
+
+    let ids = batches
+        .iter()
+        .map(|b| b.id.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let mut event = Event::new("steakhub/reconciled")
+        .add_attribute("sender", sender)
+        .add_attribute("ids", ids)
+        .add_attribute("native_deducted", native_to_deduct.to_string());
+    if !native_parked.is_zero() {
+        event = event.add_attribute("native_parked", native_parked);
+    }
+
+    Ok(Response::new()
+        .add_submessages(hooks)
+        .add_event(event)
+        .add_attribute("action", "steakhub/reconcile"))
+}
+
+/// Park the Native Token the hub holds beyond what it owes to batches, unlocked rewards,
+/// referrers, reward campaigns, the fee account, the treasury and the next reinvest, e.g. a bank
+/// transfer to the hub, in `donations` until the owner classifies it. Otherwise it would hide a shortfall from the
+/// next reconciliation. Returns the amount parked
+fn park_unexpected_native(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+) -> StdResult<Uint128> {
+    let state = State::default();
+    let denom = state.denom.load(storage)?;
+
+    // Unbondings can complete ahead of the estimated end time, so what batches not yet reconciled
+    // are due may already be here. It's owed all the same, up to what the hub holds
+    let native_due_batches = native_due_unreconciled(storage, &denom, env.block.time.seconds())?;
+    let balance = querier.query_balance(&env.contract.address, &denom)?.amount;
+    let native_owed = native_owed(storage, &denom, native_due_batches.min(balance))?;
+
+    let donations = state.donations.may_load(storage)?.unwrap_or_default();
+    let native_parked = balance.saturating_sub(native_owed);
+    if !native_parked.is_zero() {
+        state
+            .donations
+            .save(storage, &(donations + native_parked))?;
+    }
+    Ok(native_parked)
+}
+
+pub fn classify_funds(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    amount: Uint128,
+    classification: FundsClassification,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let donations = state.donations.may_load(deps.storage)?.unwrap_or_default();
+    if amount.is_zero() || amount > donations {
+        return Err(StdError::generic_err(format!(
+            "{} of Native Token is awaiting classification, can't classify {}",
+            donations, amount
+        )));
+    }
+    state.donations.save(deps.storage, &(donations - amount))?;
+
+    let denom = state.denom.load(deps.storage)?;
+    let event = Event::new("steakhub/funds_classified").add_attribute("amount", amount);
+    let (msgs, event) = match classification {
+        FundsClassification::AsRewards => {
+            let carry = state
+                .reinvest_carry
+                .may_load(deps.storage)?
+                .unwrap_or_default();
+            state.reinvest_carry.save(deps.storage, &(carry + amount))?;
+            (vec![], event.add_attribute("classification", "as_rewards"))
+        }
+        FundsClassification::AsDonation => {
+            // Like a bond, to the validator with the smallest delegation, but minting nothing
+            let validators =
+                state.load_validators_active(deps.storage, env.block.time.seconds())?;
+            let delegations =
+                query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
+            let validator = delegations
+                .iter()
+                .min_by_key(|d| d.amount)
+                .ok_or_else(|| StdError::generic_err("no active validators"))?
+                .validator
+                .clone();
+            let proto_urls = state.load_staking_proto_urls(deps.storage)?;
+            let delegate_submsg = SubMsg::reply_on_success(
+                Delegation::new(&validator, amount.u128(), &denom)
+                    .to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+                state.register_reply(deps.storage, ReplyContext::RegisterReceivedCoins)?,
+            );
+            (
+                vec![delegate_submsg],
+                event
+                    .add_attribute("classification", "as_donation")
+                    .add_attribute("validator", validator),
+            )
+        }
+        FundsClassification::Refund { recipient } => {
+            let recipient = deps.api.addr_validate(&recipient)?;
+            let send_msg = BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin::new(amount.u128(), &denom)],
+            };
+            (
+                vec![SubMsg::new(send_msg)],
+                event
+                    .add_attribute("classification", "refund")
+                    .add_attribute("recipient", recipient),
+            )
+        }
+    };
+
+    Ok(Response::new()
+        .add_submessages(msgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/classify_funds"))
+}
+
+/// Reconcile the batches `ids` of `user` that have finished unbonding, as `reconcile` would, for
+/// `withdraw_unbonded`. Returns the hook messages and the event
+fn reconcile_own_batches(
+    deps: DepsMut,
+    env: &Env,
+    user: &Addr,
+    ids: &[u64],
+) -> StdResult<(Vec<SubMsg>, Vec<Event>)> {
+    let state = State::default();
+    let denom = state.denom.load(deps.storage)?;
+
+    let (batches, native_to_deduct) = compute_reconciled_batches(deps.as_ref(), env, Some(ids))?;
+    if batches.is_empty() {
+        return Ok((vec![], vec![]));
+    }
+    for batch in &batches {
+        state.save_previous_batch(deps.storage, &denom, batch)?;
+    }
+    state.last_reconciler.save(
+        deps.storage,
+        &OperationRecord {
+            address: user.to_string(),
+            height: env.block.height,
+        },
+    )?;
+
+    let hooks = hook_submsgs(
+        deps.storage,
+        HookEventKind::Reconciliation,
+        &SteakHookMsg::BatchesReconciled {
+            ids: batches.iter().map(|b| b.id).collect(),
+        },
+    )?;
+
+    let event = Event::new("steakhub/reconciled")
+        .add_attribute("sender", user)
+        .add_attribute(
+            "ids",
+            batches
+                .iter()
+                .map(|b| b.id.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+        .add_attribute("native_deducted", native_to_deduct.to_string());
+
+    Ok((hooks, vec![event]))
+}
+
+pub fn withdraw_unbonded_admin(
+    deps: DepsMut,
+    env: Env,
+    user: Addr,
+    receiver: Addr,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &user)?;
+
+    withdraw_unbonded(deps, env, receiver.clone(), receiver, None)
+}
+
+pub fn withdraw_unbonded(
+    mut deps: DepsMut,
+    env: Env,
+    user: Addr,
+    receiver: Addr,
+    amount: Option<Uint128>,
+) -> StdResult<Response> {
+    let state = State::default();
+    assert_capability(deps.storage, |c| c.withdraw, "withdrawing")?;
+    let denom = state.denom.load(deps.storage)?;
+    let current_time = env.block.time.seconds();
+
+    // NOTE: If the user has too many unclaimed requests, this may not fit in the WASM memory...
+    // However, this is practically never going to happen. Who would create hundreds of unbonding
+    // requests and never claim them?
+    state.migrate_unbond_requests(deps.storage, &user)?;
+    let requests = state.load_unbond_requests_by_user(deps.storage, &user, None, None)?;
+
+    // If any of the user's batches has finished unbonding but has not been reconciled yet,
+    // reconcile it first, so that the user does not depend on someone else invoking it. Only the
+    // user's own batches are touched, and only so many of them; `ExecuteMsg::Reconcile` sees to
+    // the rest
+    let mut expired_ids: Vec<u64> = vec![];
+    for request in &requests {
+        if expired_ids.len() >= MAX_AUTO_RECONCILED_BATCHES {
+            break;
+        }
+        if let Some(batch) = state
+            .previous_batches
+            .may_load(deps.storage, (&denom, request.id))?
+        {
+            if !batch.reconciled && batch.est_unbond_end_time < current_time {
+                expired_ids.push(batch.id);
+            }
+        }
+    }
+    let (reconcile_hooks, reconcile_events) = if expired_ids.is_empty() {
+        (vec![], vec![])
+    } else {
+        reconcile_own_batches(deps.branch(), &env, &user, &expired_ids)?
+    };
+
+    // NOTE: Native in the following batches are withdrawn it the batch:
+    // - is a _previous_ batch, not a _pending_ batch
+    // - is reconciled
+    // - has finished unbonding
+    //
+    // For a partial withdrawal, the batch that would take the total over `amount` is only claimed in
+    // part: the request keeps the shares that were not claimed, and the batch keeps the native
+    // backing them. Rounding goes in favour of the shares left behind, so the refund may fall short
+    // of `amount` by a few units
+    let mut total_native_to_refund = Uint128::zero();
+    let mut ids: Vec<u64> = vec![];
+    let mut claimed_in_part = false;
+    for request in &requests {
+        if amount.map_or(false, |amount| total_native_to_refund >= amount) {
+            break;
+        }
+        if let Ok(mut batch) = state
+            .previous_batches
+            .load(deps.storage, (&denom, request.id))
+        {
+            if batch.reconciled && batch.est_unbond_end_time < current_time {
+                let mut shares_to_claim = request.shares;
+                let mut native_to_refund = batch
+                    .amount_unclaimed
+                    .multiply_ratio(request.shares, batch.total_shares);
+                if let Some(amount) = amount {
+                    let native_remaining = amount - total_native_to_refund;
+                    if native_to_refund > native_remaining {
+                        claimed_in_part = true;
+                        shares_to_claim = request
+                            .shares
+                            .multiply_ratio(native_remaining, native_to_refund);
+                        native_to_refund = batch
+                            .amount_unclaimed
+                            .multiply_ratio(shares_to_claim, batch.total_shares);
+                    }
+                }
+                if shares_to_claim.is_zero() {
+                    break;
+                }
+
+                ids.push(request.id);
+
+                total_native_to_refund += native_to_refund;
+                batch.total_shares -= shares_to_claim;
+                batch.amount_unclaimed -= native_to_refund;
+
+                if batch.total_shares.is_zero() {
+                    state.remove_previous_batch(deps.storage, &denom, request.id)?;
+                    let validators = state
+                        .unbond_completion_times
+                        .prefix(request.id)
+                        .keys(deps.storage, None, None, Order::Ascending)
+                        .collect::<StdResult<Vec<_>>>()?;
+                    for validator in validators {
+                        state
+                            .unbond_completion_times
+                            .remove(deps.storage, (request.id, &validator));
+                    }
+                } else {
+                    state.save_previous_batch(deps.storage, &denom, &batch)?;
+                }
+
+                if shares_to_claim < request.shares {
+                    state.save_unbond_request(
+                        deps.storage,
+                        &UnbondRequest {
+                            id: request.id,
+                            user: user.clone(),
+                            shares: request.shares - shares_to_claim,
+                        },
+                    )?;
+                    continue;
+                }
+
+                state.remove_unbond_request(deps.storage, request.id, &user)?;
+                let senders = state
+                    .unbond_request_sources
+                    .prefix((request.id, &user))
+                    .keys(deps.storage, None, None, Order::Ascending)
+                    .collect::<StdResult<Vec<_>>>()?;
+                for sender in senders {
+                    state
+                        .unbond_request_sources
+                        .remove(deps.storage, (request.id, &user, &sender))?;
+                }
+                let call_indexes = state
+                    .unbond_calls
+                    .prefix((request.id, &user))
+                    .keys(deps.storage, None, None, Order::Ascending)
+                    .collect::<StdResult<Vec<_>>>()?;
+                for index in call_indexes {
+                    state
+                        .unbond_calls
+                        .remove(deps.storage, (request.id, &user, index));
+                }
+            }
+        }
+    }
+
+    if total_native_to_refund.is_zero() {
+        // Most likely a retry of a withdrawal that already went through
+        let last_claim = state
+            .recent_claims
+            .prefix(&user)
+            .range(deps.storage, None, None, Order::Descending)
+            .next()
+            .transpose()?;
+        return Err(StdError::generic_err(match last_claim {
+            Some((_, claim)) => format!(
+                "withdrawable amount is zero; claim {} already withdrew {} at height {}",
+                claim.id, claim.amount, claim.height
+            ),
+            None => "withdrawable amount is zero".to_string(),
+        }));
+    }
+    if let Some(amount) = amount {
+        if !claimed_in_part && total_native_to_refund < amount {
+            return Err(StdError::generic_err(format!(
+                "withdrawable amount is {}, less than the {} requested",
+                total_native_to_refund, amount
+            )));
+        }
+    }
+
+    // A user who holds no Steak and has no unbonding requests left is no longer counted as a staker
+    if state.stakers.has(deps.storage, &user) {
+        let has_requests = !state
+            .load_unbond_requests_by_user(deps.storage, &user, None, Some(1))?
+            .is_empty();
+        if !has_requests && query_usteak_balance(&deps.querier, deps.storage, &user)?.is_zero() {
+            state.remove_staker(deps.storage, &user)?;
+        }
+    }
+
+    let claim_id = state.record_claim(
+        deps.storage,
+        &user,
+        RecentClaim {
+            id: 0,
+            receiver: receiver.to_string(),
+            amount: total_native_to_refund,
+            height: env.block.height,
+        },
+    )?;
+
+    let refund_msg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: receiver.clone().into(),
+        amount: vec![Coin::new(total_native_to_refund.u128(), &denom)],
+    });
+
+    let event = Event::new("steakhub/unbonded_withdrawn")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("claim_id", claim_id.to_string())
+        .add_attribute(
+            "ids",
+            ids.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+        .add_attribute("user", user)
+        .add_attribute("receiver", &receiver)
+        .add_attribute("amount_refunded", total_native_to_refund);
+
+    let receipt = WithdrawReceipt {
+        claim_id,
+        receiver: receiver.into(),
+        native_withdrawn: total_native_to_refund,
+        batch_ids: ids,
+    };
+
+    Ok(Response::new()
+        .set_data(to_binary(&receipt)?)
+        .add_message(refund_msg)
+        .add_submessages(reconcile_hooks)
+        .add_events(reconcile_events)
+        .add_event(event)
+        .add_attribute("action", "steakhub/withdraw_unbonded"))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Token migration logics
+//--------------------------------------------------------------------------------------------------
+
+/// Messages minting `amount` of Steak to `recipient`. Once the token has been migrated, the denom
+/// is minted to the hub and sent on
+fn mint_usteak_msgs(
+    storage: &mut dyn Storage,
+    contract_addr: &Addr,
+    recipient: &Addr,
+    amount: Uint128,
+) -> StdResult<Vec<CosmosMsg>> {
+    let state = State::default();
+
+    let usteak_denom = match state.usteak_denom.may_load(storage)? {
+        Some(usteak_denom) => usteak_denom,
+        None => {
+            let steak_token = state.steak_token.load(storage)?;
+            return Ok(vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: steak_token.into(),
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            })]);
+        }
+    };
+
+    state
+        .usteak_denom_supply
+        .update(storage, |supply| -> StdResult<_> {
+            Ok(supply.checked_add(amount)?)
+        })?;
+    let mut msgs = vec![mint_msg(contract_addr.to_string(), &usteak_denom, amount)?];
+    if recipient != contract_addr {
+        msgs.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin::new(amount.u128(), usteak_denom)],
+        }));
+    }
+    Ok(msgs)
+}
+
+/// Message burning `amount` of Steak held by the hub
+fn burn_usteak_msg(
+    storage: &mut dyn Storage,
+    contract_addr: &Addr,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    let state = State::default();
+
+    match state.usteak_denom.may_load(storage)? {
+        Some(usteak_denom) => {
+            state
+                .usteak_denom_supply
+                .update(storage, |supply| -> StdResult<_> {
+                    Ok(supply.checked_sub(amount)?)
+                })?;
+            burn_msg(contract_addr.to_string(), &usteak_denom, amount)
+        }
+        None => {
+            let steak_token = state.steak_token.load(storage)?;
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: steak_token.into(),
+                msg: to_binary(&Cw20ExecuteMsg::Burn { amount })?,
+                funds: vec![],
+            }))
+        }
+    }
+}
+
+/// Message sending `amount` of Steak held by the hub to `recipient`. Escrow made before the token
+/// was migrated has been swapped along with the rest of the hub's
+fn transfer_usteak_msg(
+    storage: &dyn Storage,
+    recipient: &Addr,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    let state = State::default();
+
+    match state.usteak_denom.may_load(storage)? {
+        Some(usteak_denom) => Ok(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin::new(amount.u128(), usteak_denom)],
+        })),
+        None => {
+            let steak_token = state.steak_token.load(storage)?;
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: steak_token.into(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            }))
+        }
+    }
+}
+
+/// Messages burning `amount` of cw20 Steak held by the hub, and minting as much of the token
+/// factory denom to the hub in its place, leaving the total supply unchanged
+fn swap_cw20_msgs(
+    storage: &mut dyn Storage,
+    contract_addr: &Addr,
+    usteak_denom: &str,
+    amount: Uint128,
+) -> StdResult<Vec<CosmosMsg>> {
+    let state = State::default();
+
+    let steak_token = state.steak_token.load(storage)?;
+    state
+        .usteak_denom_supply
+        .update(storage, |supply| -> StdResult<_> {
+            Ok(supply.checked_add(amount)?)
+        })?;
+
+    Ok(vec![
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: steak_token.into(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn { amount })?,
+            funds: vec![],
+        }),
+        mint_msg(contract_addr.to_string(), usteak_denom, amount)?,
+    ])
+}
+
+/// Move the Steak token to a token factory denom. The cw20 token is frozen in the sense that the
+/// hub never mints it again; what is left of it can only shrink, as holders swap or unbond it
+pub fn migrate_token(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    new_backend: TokenBackend,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    if state.usteak_denom.may_load(deps.storage)?.is_some() {
+        return Err(StdError::generic_err("the token has already been migrated"));
+    }
+    let subdenom = match new_backend {
+        TokenBackend::Cw20 => {
+            return Err(StdError::generic_err(
+                "the token already uses the cw20 backend",
+            ));
+        }
+        TokenBackend::TokenFactory { subdenom } => subdenom,
+    };
+
+    let usteak_denom = token_factory_denom(env.contract.address.as_str(), &subdenom);
+    state.usteak_denom.save(deps.storage, &usteak_denom)?;
+    state
+        .usteak_denom_supply
+        .save(deps.storage, &Uint128::zero())?;
+
+    // Holders swap through the `Receive` hook, alongside queuing unbonds
+    let steak_token = state.steak_token.load(deps.storage)?;
+    let mut receive_msgs = state
+        .accepted_tokens
+        .may_load(deps.storage, &steak_token)?
+        .unwrap_or_default();
+    if !receive_msgs.contains(&ReceiveMsgKind::SwapToken) {
+        receive_msgs.push(ReceiveMsgKind::SwapToken);
+    }
+    state
+        .accepted_tokens
+        .save(deps.storage, &steak_token, &receive_msgs)?;
+
+    // The hub's own cw20, escrowed for the pending batch and unbond orders, is swapped right away
+    let mut msgs = vec![create_denom_msg(
+        env.contract.address.to_string(),
+        subdenom,
+    )?];
+    let usteak_escrowed = query_cw20_balance(&deps.querier, &steak_token, &env.contract.address)?;
+    if !usteak_escrowed.is_zero() {
+        msgs.extend(swap_cw20_msgs(
+            deps.storage,
+            &env.contract.address,
+            &usteak_denom,
+            usteak_escrowed,
+        )?);
+    }
+
+    let event = Event::new("steakhub/token_migrated")
+        .add_attribute("usteak_denom", usteak_denom)
+        .add_attribute("usteak_escrowed", usteak_escrowed);
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/migrate_token"))
+}
+
+/// Hand the cw20 Steak token's admin rights to `admin`. The hub can only do so while it is the
+/// token's admin, which it is from instantiation
+pub fn update_token_admin(deps: DepsMut, sender: Addr, admin: String) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let admin = deps.api.addr_validate(&admin)?;
+    let steak_token = state.steak_token.load(deps.storage)?;
+
+    let update_admin_msg = CosmosMsg::Wasm(WasmMsg::UpdateAdmin {
+        contract_addr: steak_token.to_string(),
+        admin: admin.to_string(),
+    });
+
+    let event = Event::new("steak/update_token_admin")
+        .add_attribute("steak_token", steak_token)
+        .add_attribute("admin", admin);
+
+    Ok(Response::new()
+        .add_message(update_admin_msg)
+        .add_event(event)
+        .add_attribute("action", "steakhub/update_token_admin"))
+}
+
+/// Migrate the cw20 Steak token contract to `new_code_id`, with the hub as its admin
+/// The hub can only migrate the Steak token, or hand it to another admin, while it administers it
+fn assert_hub_administers_token(deps: Deps, env: &Env) -> StdResult<()> {
+    let token_admin = State::default().token_admin.may_load(deps.storage)?;
+    match token_admin {
+        Some(admin) if admin != env.contract.address => Err(StdError::generic_err(format!(
+            "steak token is administered by {}, not the hub",
+            admin
+        ))),
+        _ => Ok(()),
+    }
+}
+
+pub fn migrate_token_contract(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    new_code_id: u64,
+    msg: Binary,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    assert_hub_administers_token(deps.as_ref(), &env)?;
+    let steak_token = state.steak_token.load(deps.storage)?;
+
+    let migrate_msg = CosmosMsg::Wasm(WasmMsg::Migrate {
+        contract_addr: steak_token.to_string(),
+        new_code_id,
+        msg,
+    });
+
+    let event = Event::new("steak/migrate_token_contract")
+        .add_attribute("steak_token", steak_token)
+        .add_attribute("new_code_id", new_code_id.to_string());
+
+    Ok(Response::new()
+        .add_message(migrate_msg)
+        .add_event(event)
+        .add_attribute("action", "steakhub/migrate_token_contract"))
+}
+
+/// Swap cw20 Steak sent through the `Receive` hook for the token factory denom. `token` is the cw20
+/// that was sent, which must be the Steak token: the hub burns as much of its own Steak
+pub fn swap_token(
+    deps: DepsMut,
+    env: Env,
+    token: Addr,
+    holder: Addr,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    let steak_token = state.steak_token.load(deps.storage)?;
+    if token != steak_token {
+        return Err(StdError::generic_err(format!(
+            "expecting Steak token, received {}",
+            token
+        )));
+    }
+    let usteak_denom = state
+        .usteak_denom
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("the token has not been migrated"))?;
+
+    let mut msgs = swap_cw20_msgs(deps.storage, &env.contract.address, &usteak_denom, amount)?;
+    msgs.push(CosmosMsg::Bank(BankMsg::Send {
+        to_address: holder.to_string(),
+        amount: vec![Coin::new(amount.u128(), &usteak_denom)],
+    }));
+
+    let event = Event::new("steakhub/token_swapped")
+        .add_attribute("holder", holder)
+        .add_attribute("amount", amount);
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/swap_token"))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Ownership and management logics
+//--------------------------------------------------------------------------------------------------
+
+pub fn rebalance(deps: DepsMut, env: Env, minimum: Option<Uint128>) -> StdResult<Response> {
+    let state = State::default();
+    // Callers may skip more moves than the owner asked for, but not fewer
+    let minimum = state
+        .min_redelegation
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .max(minimum.unwrap_or_default());
+    let denom = state.denom.load(deps.storage)?;
+    let validators = state.validators.load(deps.storage)?;
+    let validators_active = state.load_validators_active(deps.storage, env.block.time.seconds())?;
+
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
+
+    let total_delegated_amount = delegations.iter().fold(0u128, |acc, d| acc + d.amount);
+
+    let validator_count = validators_active.len();
+    let remainder_offset = state.next_remainder_offset(deps.storage)?;
+
+    let new_redelegations = compute_redelegations_for_rebalancing(
+        validators_active,
+        &delegations,
+        minimum,
+        remainder_offset,
+        |d| {
+            compute_validator_target(
+                deps.storage,
+                &d.validator,
+                total_delegated_amount,
+                validator_count,
+            )
+        },
+    )?;
+
+    send_redelegations(deps, env, denom, new_redelegations)
+}
+
+pub fn continue_rebalance(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let state = State::default();
+    let denom = state.denom.load(deps.storage)?;
+    let validators = state.validators.load(deps.storage)?;
+    let validators_active = state.load_validators_active(deps.storage, env.block.time.seconds())?;
+
+    let pending_rebalance = state
+        .pending_rebalance
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if pending_rebalance.is_empty() {
+        return Err(StdError::generic_err("no rebalance in progress"));
+    }
+
+    // Delegations may have changed since the plan was made, so each redelegation is capped at what
+    // its source still has, and those towards validators that left the active set are dropped
+    let mut delegated: HashMap<String, u128> =
+        query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?
+            .into_iter()
+            .map(|d| (d.validator, d.amount))
+            .collect();
+    // Redelegations left over by `RemoveValidator` come from a validator no longer whitelisted
+    for rd in &pending_rebalance {
+        if !delegated.contains_key(&rd.src) {
+            let delegation =
+                query_delegation(&deps.querier, &rd.src, &env.contract.address, &denom)?;
+            delegated.insert(rd.src.clone(), delegation.amount);
+        }
+    }
+    let redelegations = pending_rebalance
+        .into_iter()
+        .filter(|rd| validators_active.contains(&rd.dst))
+        .filter_map(|mut rd| {
+            let available = delegated.entry(rd.src.clone()).or_default();
+            rd.amount = rd.amount.min(*available);
+            *available -= rd.amount;
+            if rd.amount > 0 {
+                Some(rd)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    send_redelegations(deps, env, denom, redelegations)
+}
+
+/// Number of redelegations one execute may send: `MAX_REDELEGATIONS_PER_TX`, or fewer if the owner
+/// capped the messages per execute lower
+fn redelegations_per_tx(storage: &dyn Storage) -> StdResult<usize> {
+    let state = State::default();
+    Ok(match state.max_messages.may_load(storage)? {
+        Some(max_messages) => MAX_REDELEGATIONS_PER_TX.min(max_messages as usize),
+        None => MAX_REDELEGATIONS_PER_TX,
+    })
+}
+
+/// Send the first `redelegations_per_tx` redelegations, saving the rest for `ContinueRebalance`
+fn send_redelegations(
+    deps: DepsMut,
+    env: Env,
+    denom: String,
+    mut redelegations: Vec<Redelegation>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    let per_tx = redelegations_per_tx(deps.storage)?;
+    let pending_rebalance = redelegations.split_off(per_tx.min(redelegations.len()));
+    if pending_rebalance.is_empty() {
+        state.pending_rebalance.remove(deps.storage);
+    } else {
+        state
+            .pending_rebalance
+            .save(deps.storage, &pending_rebalance)?;
+    }
+
+    state.prev_denom.save(
+        deps.storage,
+        &get_denom_balance(&deps.querier, env.contract.address.clone(), denom)?,
+    )?;
+
+    let proto_urls = state.load_staking_proto_urls(deps.storage)?;
+    let redelegate_submsgs = redelegations
+        .iter()
+        .map(|rd| {
+            Ok(SubMsg::reply_on_success(
+                rd.to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+                state.register_reply(deps.storage, ReplyContext::RegisterReceivedCoins)?,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let amount: u128 = redelegations.iter().map(|rd| rd.amount).sum();
+
+    let event = Event::new("steakhub/rebalanced")
+        .add_attribute("amount_moved", amount.to_string())
+        .add_attribute("redelegations_pending", pending_rebalance.len().to_string());
+
+    Ok(Response::new()
+        .add_submessages(redelegate_submsgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/rebalance"))
+}
+
+/// Refuse to whitelist a validator that self-delegates less than the owner requires, if anything
+fn assert_min_self_delegation(deps: Deps, validator: &str) -> StdResult<()> {
+    let min_self_delegation = State::default()
+        .min_self_delegation
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if !min_self_delegation.is_zero() {
+        let self_delegation = query_self_delegation(&deps.querier, validator)?;
+        if self_delegation < min_self_delegation {
+            return Err(StdError::generic_err(format!(
+                "validator {} self-delegates {}, below the minimum of {}",
+                validator, self_delegation, min_self_delegation
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub fn add_validator(deps: DepsMut, sender: Addr, validator: String) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    assert_min_self_delegation(deps.as_ref(), &validator)?;
+
+    state.validators.update(deps.storage, |mut validators| {
+        if validators.contains(&validator) {
+            return Err(StdError::generic_err("validator is already whitelisted"));
+        }
+        validators.push(validator.clone());
+        Ok(validators)
+    })?;
+
+    let mut validators_active = state.validators_active.load(deps.storage)?;
+    if !validators_active.contains(&validator) {
+        validators_active.push(validator.clone());
+    }
+    state
+        .validators_active
+        .save(deps.storage, &validators_active)?;
+    let event = Event::new("steakhub/validator_added").add_attribute("validator", validator);
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/add_validator"))
+}
+
+fn remove_validator(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    validator: String,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let denom = state.denom.load(deps.storage)?;
+
+    let validators = state.validators.update(deps.storage, |mut validators| {
+        if !validators.contains(&validator) {
+            return Err(StdError::generic_err(
+                "validator is not already whitelisted",
+            ));
+        }
+        validators.retain(|v| *v != validator);
+        Ok(validators)
+    })?;
+    let mut validators_active = state.validators_active.load(deps.storage)?;
+    validators_active.retain(|v| *v != validator);
+    state
+        .validators_active
+        .save(deps.storage, &validators_active)?;
+    state
+        .validator_suspensions
+        .remove(deps.storage, validator.clone());
+    state
+        .validator_mining_difficulties
+        .remove(deps.storage, validator.clone());
+    let mining_power_removed = state.remove_mining_power(deps.storage, &validator)?;
+    let total_mining_power = state
+        .total_mining_power
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
+    let delegation_to_remove =
+        query_delegation(&deps.querier, &validator, &env.contract.address, &denom)?;
+    let remainder_offset = state.next_remainder_offset(deps.storage)?;
+    let mut new_redelegations = compute_redelegations_for_removal(
+        &delegation_to_remove,
+        &delegations,
+        &denom,
+        remainder_offset,
+    );
+
+    // Across a large validator set, the redelegations may not fit in one execute. The rest are
+    // sent by `ContinueRebalance`, ahead of whatever rebalance was already in progress
+    let per_tx = redelegations_per_tx(deps.storage)?;
+    let mut redelegations_pending =
+        new_redelegations.split_off(per_tx.min(new_redelegations.len()));
+    if !redelegations_pending.is_empty() {
+        redelegations_pending.extend(
+            state
+                .pending_rebalance
+                .may_load(deps.storage)?
+                .unwrap_or_default(),
+        );
+        state
+            .pending_rebalance
+            .save(deps.storage, &redelegations_pending)?;
+    }
+
+    state.prev_denom.save(
+        deps.storage,
+        &get_denom_balance(&deps.querier, env.contract.address.clone(), denom)?,
+    )?;
+
+    let proto_urls = state.load_staking_proto_urls(deps.storage)?;
+    let redelegate_submsgs = new_redelegations
+        .iter()
+        .map(|d| {
+            Ok(SubMsg::reply_on_success(
+                d.to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+                state.register_reply(deps.storage, ReplyContext::RegisterReceivedCoins)?,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut event = Event::new("steak/validator_removed")
+        .add_attribute("validator", validator)
+        .add_attribute("mining_power_removed", mining_power_removed)
+        .add_attribute("total_mining_power", total_mining_power);
+    if !redelegations_pending.is_empty() {
+        event = event.add_attribute(
+            "redelegations_pending",
+            redelegations_pending.len().to_string(),
+        );
+    }
+
+    Ok(Response::new()
+        .add_submessages(redelegate_submsgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/remove_validator"))
+}
+
+pub fn replace_validator(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    old: String,
+    new: String,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    assert_min_self_delegation(deps.as_ref(), &new)?;
+    let denom = state.denom.load(deps.storage)?;
+
+    state.validators.update(deps.storage, |mut validators| {
+        if validators.contains(&new) {
+            return Err(StdError::generic_err("validator is already whitelisted"));
+        }
+        match validators.iter_mut().find(|v| **v == old) {
+            Some(v) => *v = new.clone(),
+            None => {
+                return Err(StdError::generic_err(
+                    "validator is not already whitelisted",
+                ))
+            }
+        }
+        Ok(validators)
+    })?;
+    state
+        .validators_active
+        .update(deps.storage, |mut validators| -> StdResult<_> {
+            if let Some(v) = validators.iter_mut().find(|v| **v == old) {
+                *v = new.clone();
+            }
+            Ok(validators)
+        })?;
+
+    // Everything the hub keeps per validator carries over to the new address
+    let mining_power = state
+        .validator_mining_powers
+        .may_load(deps.storage, old.clone())?;
+    if let Some(mining_power) = mining_power {
+        state
+            .validator_mining_powers
+            .remove(deps.storage, old.clone());
+        state
+            .validator_mining_powers
+            .save(deps.storage, new.clone(), &mining_power)?;
+    }
+    if let Some(cap) = state.validator_caps.may_load(deps.storage, old.clone())? {
+        state.validator_caps.remove(deps.storage, old.clone());
+        state.validator_caps.save(deps.storage, new.clone(), &cap)?;
+    }
+    if let Some(difficulty) = state
+        .validator_mining_difficulties
+        .may_load(deps.storage, old.clone())?
+    {
+        state
+            .validator_mining_difficulties
+            .remove(deps.storage, old.clone());
+        state
+            .validator_mining_difficulties
+            .save(deps.storage, new.clone(), &difficulty)?;
+    }
+    if let Some(incidents) = state.slash_incidents.may_load(deps.storage, old.clone())? {
+        state.slash_incidents.remove(deps.storage, old.clone());
+        state
+            .slash_incidents
+            .save(deps.storage, new.clone(), &incidents)?;
+    }
+    if let Some(until) = state
+        .validator_suspensions
+        .may_load(deps.storage, old.clone())?
+    {
+        state
+            .validator_suspensions
+            .remove(deps.storage, old.clone());
+        state
+            .validator_suspensions
+            .save(deps.storage, new.clone(), &until)?;
+    }
+    // The old validator's stake moves to the new one, so does its part in a rebalance in progress
+    if let Some(mut pending_rebalance) = state.pending_rebalance.may_load(deps.storage)? {
+        for rd in pending_rebalance.iter_mut() {
+            if rd.src == old {
+                rd.src = new.clone();
+            }
+            if rd.dst == old {
+                rd.dst = new.clone();
+            }
+        }
+        state
+            .pending_rebalance
+            .save(deps.storage, &pending_rebalance)?;
+    }
+
+    let delegation = query_delegation(&deps.querier, &old, &env.contract.address, &denom)?;
+    let mut redelegate_submsgs = vec![];
+    if delegation.amount > 0 {
+        state.prev_denom.save(
+            deps.storage,
+            &get_denom_balance(&deps.querier, env.contract.address.clone(), denom.clone())?,
+        )?;
+        let proto_urls = state.load_staking_proto_urls(deps.storage)?;
+        redelegate_submsgs.push(SubMsg::reply_on_success(
+            Redelegation::new(&old, &new, delegation.amount, &denom)
+                .to_cosmos_msg(env.contract.address.to_string(), &proto_urls)?,
+            state.register_reply(deps.storage, ReplyContext::RegisterReceivedCoins)?,
+        ));
+    }
+
+    let event = Event::new("steak/validator_replaced")
+        .add_attribute("old", old)
+        .add_attribute("new", new)
+        .add_attribute("redelegated", delegation.amount.to_string())
+        .add_attribute("mining_power", mining_power.unwrap_or_default());
+
+    Ok(Response::new()
+        .add_submessages(redelegate_submsgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/replace_validator"))
+}
+
+pub fn remove_validator_ex(
+    deps: DepsMut,
+    _env: Env,
+    sender: Addr,
+    validator: String,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    state.validators.update(deps.storage, |mut validators| {
+        if !validators.contains(&validator) {
+            return Err(StdError::generic_err(
+                "validator is not already whitelisted",
+            ));
+        }
+        validators.retain(|v| *v != validator);
+        Ok(validators)
+    })?;
+    state
+        .validators_active
+        .update(deps.storage, |mut validators| -> StdResult<_> {
+            validators.retain(|v| *v != validator);
+            Ok(validators)
+        })?;
+    state
+        .validator_suspensions
+        .remove(deps.storage, validator.clone());
+    state
+        .validator_mining_difficulties
+        .remove(deps.storage, validator.clone());
+    let mining_power_removed = state.remove_mining_power(deps.storage, &validator)?;
+    let total_mining_power = state
+        .total_mining_power
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    let event = Event::new("steak/validator_removed_ex")
+        .add_attribute("validator", validator)
+        .add_attribute("mining_power_removed", mining_power_removed)
+        .add_attribute("total_mining_power", total_mining_power);
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/remove_validator_ex"))
+}
+
+pub fn pause_validator(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    validator: String,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    // Pausing a suspended validator clears the suspension, so that it stays out once it would end
+    let mut validators_active =
+        state.load_validators_active(deps.storage, env.block.time.seconds())?;
+    let suspended = state
+        .validator_suspensions
+        .has(deps.storage, validator.clone());
+    if !validators_active.contains(&validator) && !suspended {
+        return Err(StdError::generic_err(
+            "validator is not already whitelisted",
+        ));
+    }
+    validators_active.retain(|v| *v != validator);
+    state
+        .validators_active
+        .save(deps.storage, &validators_active)?;
+    state
+        .validator_suspensions
+        .remove(deps.storage, validator.clone());
+
+    let event = Event::new("steak/pause_validator").add_attribute("validator", validator);
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/pause_validator"))
+}
+
+pub fn unpause_validator(
+    deps: DepsMut,
+    _env: Env,
+    sender: Addr,
+    validator: String,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let mut validators_active = state.validators_active.load(deps.storage)?;
+    if !validators_active.contains(&validator) {
+        validators_active.push(validator.clone());
+    }
+    state
+        .validators_active
+        .save(deps.storage, &validators_active)?;
+    state
+        .validator_suspensions
+        .remove(deps.storage, validator.clone());
+
+    let event = Event::new("steak/unpause_validator").add_attribute("validator", validator);
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/unpause_validator"))
+}
+
+pub fn suspend_validator(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    validator: String,
+    until: u64,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    if until <= env.block.time.seconds() {
+        return Err(StdError::generic_err(format!(
+            "suspension must end after the current time {}",
+            env.block.time.seconds()
+        )));
+    }
+    let suspended = state
+        .validator_suspensions
+        .has(deps.storage, validator.clone());
+    state
+        .validators_active
+        .update(deps.storage, |mut validators| {
+            if !validators.contains(&validator) && !suspended {
+                return Err(StdError::generic_err(format!(
+                    "validator {} is not active",
+                    validator
+                )));
+            }
+            validators.retain(|v| *v != validator);
+            Ok(validators)
+        })?;
+    state
+        .validator_suspensions
+        .save(deps.storage, validator.clone(), &until)?;
+
+    let event = Event::new("steak/suspend_validator")
+        .add_attribute("validator", validator)
+        .add_attribute("until", until.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/suspend_validator"))
+}
+pub fn set_unbond_period(
+    deps: DepsMut,
+    _env: Env,
+    sender: Addr,
+    unbond_period: u64,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state.unbond_period.save(deps.storage, &unbond_period)?;
+    let event = Event::new("steak/set_unbond_period")
+        .add_attribute("unbond_period", format!("{}", unbond_period));
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_unbond_period"))
+}
+
+pub fn set_min_reinvest_amount(
+    deps: DepsMut,
+    sender: Addr,
+    min_reinvest_amount: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state
+        .min_reinvest_amount
+        .save(deps.storage, &min_reinvest_amount)?;
+    let decimals = state.load_denom_decimals(deps.storage)?;
+    let event = Event::new("steak/set_min_reinvest_amount")
+        .add_attribute("min_reinvest_amount", min_reinvest_amount)
+        .add_attribute(
+            "whole_units",
+            format_denom_amount(min_reinvest_amount, decimals),
+        );
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_min_reinvest_amount"))
+}
+
+pub fn set_max_messages(
+    deps: DepsMut,
+    sender: Addr,
+    max_messages: Option<u32>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let event = match max_messages {
+        Some(0) => {
+            return Err(StdError::generic_err(
+                "maximum message count must be at least 1",
+            ))
+        }
+        Some(max_messages) => {
+            state.max_messages.save(deps.storage, &max_messages)?;
+            Event::new("steak/set_max_messages")
+                .add_attribute("max_messages", max_messages.to_string())
+        }
+        None => {
+            state.max_messages.remove(deps.storage);
+            Event::new("steak/set_max_messages").add_attribute("max_messages", "none")
+        }
+    };
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_max_messages"))
+}
+
+pub fn set_min_redelegation(
+    deps: DepsMut,
+    sender: Addr,
+    min_redelegation: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state
+        .min_redelegation
+        .save(deps.storage, &min_redelegation)?;
+
+    let decimals = state.load_denom_decimals(deps.storage)?;
+    let event = Event::new("steak/set_min_redelegation")
+        .add_attribute("min_redelegation", min_redelegation)
+        .add_attribute(
+            "whole_units",
+            format_denom_amount(min_redelegation, decimals),
+        );
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_min_redelegation"))
+}
+
+pub fn set_min_bond_amount(
+    deps: DepsMut,
+    sender: Addr,
+    min_bond_amount: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state.min_bond_amount.save(deps.storage, &min_bond_amount)?;
+    let decimals = state.load_denom_decimals(deps.storage)?;
+    let event = Event::new("steak/set_min_bond_amount")
+        .add_attribute("min_bond_amount", min_bond_amount)
+        .add_attribute(
+            "whole_units",
+            format_denom_amount(min_bond_amount, decimals),
+        );
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_min_bond_amount"))
+}
+
+pub fn set_max_rebase_bps(
+    deps: DepsMut,
+    sender: Addr,
+    max_rebase_bps: Option<u64>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    if max_rebase_bps == Some(0) {
+        return Err(StdError::generic_err(
+            "max rebase must be positive; use none to remove the limit",
+        ));
+    }
+    state.max_rebase_bps.save(deps.storage, &max_rebase_bps)?;
+
+    let event = Event::new("steak/set_max_rebase_bps").add_attribute(
+        "max_rebase_bps",
+        max_rebase_bps.map_or_else(|| "none".to_string(), |bps| bps.to_string()),
+    );
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_max_rebase_bps"))
+}
+
+pub fn set_max_harvest_staleness(
+    deps: DepsMut,
+    sender: Addr,
+    max_harvest_staleness: Option<u64>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    if max_harvest_staleness == Some(0) {
+        return Err(StdError::generic_err(
+            "max harvest staleness must be positive; use none to remove the limit",
+        ));
+    }
+    state
+        .max_harvest_staleness
+        .save(deps.storage, &max_harvest_staleness)?;
+
+    let event = Event::new("steak/set_max_harvest_staleness").add_attribute(
+        "max_harvest_staleness",
+        max_harvest_staleness.map_or_else(|| "none".to_string(), |secs| secs.to_string()),
+    );
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_max_harvest_staleness"))
+}
+
+pub fn set_submit_deadline_buffer(
+    deps: DepsMut,
+    sender: Addr,
+    submit_deadline_buffer: Option<u64>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state
+        .submit_deadline_buffer
+        .save(deps.storage, &submit_deadline_buffer)?;
+
+    let event = Event::new("steak/set_submit_deadline_buffer").add_attribute(
+        "submit_deadline_buffer",
+        submit_deadline_buffer.map_or_else(|| "none".to_string(), |secs| secs.to_string()),
+    );
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_submit_deadline_buffer"))
+}
+
+pub fn set_harvest_granter(
+    deps: DepsMut,
+    sender: Addr,
+    granter: Option<String>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let event = match granter {
+        Some(granter) => {
+            let granter = deps.api.addr_validate(&granter)?;
+            state.harvest_granter.save(deps.storage, &granter)?;
+            Event::new("steak/set_harvest_granter").add_attribute("granter", granter)
+        }
+        None => {
+            state.harvest_granter.remove(deps.storage);
+            Event::new("steak/set_harvest_granter").add_attribute("granter", "none")
+        }
+    };
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_harvest_granter"))
+}
+
+pub fn set_referral_bps(deps: DepsMut, sender: Addr, referral_bps: u64) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    if referral_bps > 10_000 {
+        return Err(StdError::generic_err("referral bps must not exceed 10000"));
+    }
+    state.referral_bps.save(deps.storage, &referral_bps)?;
+
+    let event = Event::new("steak/set_referral_bps")
+        .add_attribute("referral_bps", referral_bps.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_referral_bps"))
+}
+
+pub fn set_fee_vesting(
+    deps: DepsMut,
+    sender: Addr,
+    fee_vesting: Option<FeeVesting>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let mut event = Event::new("steak/set_fee_vesting");
+    match fee_vesting {
+        Some(fee_vesting) => {
+            if fee_vesting.duration == 0 || fee_vesting.cliff > fee_vesting.duration {
+                return Err(StdError::generic_err(
+                    "fee vesting duration must be positive and not shorter than the cliff",
+                ));
+            }
+            state.fee_vesting.save(deps.storage, &fee_vesting)?;
+            event = event
+                .add_attribute("cliff", fee_vesting.cliff.to_string())
+                .add_attribute("duration", fee_vesting.duration.to_string());
+        }
+        None => {
+            state.fee_vesting.remove(deps.storage);
+            event = event.add_attribute("fee_vesting", "none");
+        }
+    }
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_fee_vesting"))
+}
+
+pub fn set_restaking_adapter(
+    deps: DepsMut,
+    sender: Addr,
+    adapter: Option<RestakingAdapter>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let mut event = Event::new("steak/set_restaking_adapter");
+    match adapter {
+        Some(adapter) => {
+            if adapter.max_restaked_bps > 10_000 {
+                return Err(StdError::generic_err(
+                    "max restaked share can't be more than 10000 bps",
+                ));
+            }
+            let adapter = RestakingAdapter {
+                contract: deps.api.addr_validate(&adapter.contract)?.into(),
+                ..adapter
+            };
+            state.restaking_adapter.save(deps.storage, &adapter)?;
+            event = event
+                .add_attribute("contract", &adapter.contract)
+                .add_attribute("max_restaked_bps", adapter.max_restaked_bps.to_string());
+        }
+        None => {
+            // Without an adapter, nothing can be restaked
+            state.restaking_adapter.remove(deps.storage);
+            state.restaked_native.remove(deps.storage);
+            event = event.add_attribute("restaking_adapter", "none");
+        }
+    }
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_restaking_adapter"))
+}
+
+pub fn report_restaked(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    let adapter = state.restaking_adapter.may_load(deps.storage)?;
+    let adapter = match adapter {
+        Some(adapter) if sender == adapter.contract => adapter,
+        _ => {
+            return Err(StdError::generic_err(
+                "unauthorized: sender is not the restaking adapter",
+            ))
+        }
+    };
+
+    let denom = state.denom.load(deps.storage)?;
+    let validators = state.validators.load(deps.storage)?;
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
+    let native_bonded: u128 = delegations.iter().map(|d| d.amount).sum();
+    let max_restaked =
+        Uint128::new(native_bonded).multiply_ratio(adapter.max_restaked_bps, 10_000u128);
+    if amount > max_restaked {
+        return Err(StdError::generic_err(format!(
+            "restaked amount {} is above the cap of {}",
+            amount, max_restaked
+        )));
+    }
+    state.restaked_native.save(deps.storage, &amount)?;
+
+    let event = Event::new("steakhub/restaked_reported").add_attribute("restaked_native", amount);
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/report_restaked"))
+}
+
+pub fn absorb_delegation(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    validator: String,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    if amount.is_zero() {
+        return Err(StdError::generic_err("nothing to absorb"));
+    }
+    let validators = state.validators.load(deps.storage)?;
+    if !validators.contains(&validator) {
+        return Err(StdError::generic_err(format!(
+            "validator {} is not whitelisted",
+            validator
+        )));
+    }
+
+    let denom = state.denom.load(deps.storage)?;
+    let mut delegations =
+        query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
+    // A validator the hub doesn't delegate to comes back with nothing delegated
+    let delegation = delegations
+        .iter_mut()
+        .find(|d| d.validator == validator && d.amount > 0)
+        .ok_or_else(|| {
+            StdError::generic_err(format!("no delegation to {} to absorb", validator))
+        })?;
+    if delegation.amount < amount.u128() {
+        return Err(StdError::generic_err(format!(
+            "the hub only delegates {} to {}, can't absorb {}",
+            delegation.amount, validator, amount
+        )));
+    }
+    // From here on, `delegations` are what the hub would hold without the external delegation
+    delegation.amount -= amount.u128();
+
+    let usteak_supply = query_usteak_supply(&deps.querier, deps.storage)?;
+    let native_bonded: u128 = delegations.iter().map(|d| d.amount).sum();
+    if !usteak_supply.is_zero() {
+        if let Some(last_exchange_rate) = state.last_exchange_rate.may_load(deps.storage)? {
+            let exchange_rate = Decimal::from_ratio(native_bonded, usteak_supply);
+            if exchange_rate < last_exchange_rate {
+                return Err(StdError::generic_err(format!(
+                    "absorbing {} would put the exchange rate at {}, below the last observed {}",
+                    amount, exchange_rate, last_exchange_rate
+                )));
+            }
+        }
+    }
+    let usteak_to_mint = compute_mint_amount(usteak_supply, amount, &delegations);
+
+    let absorbed_native = state
+        .absorbed_native
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    state
+        .absorbed_native
+        .save(deps.storage, &absorbed_native.checked_add(amount)?)?;
+
+    let mint_msgs = mint_usteak_msgs(deps.storage, &env.contract.address, &sender, usteak_to_mint)?;
+
+    let event = Event::new("steakhub/delegation_absorbed")
+        .add_attribute("validator", validator)
+        .add_attribute("amount", amount)
+        .add_attribute("usteak_minted", usteak_to_mint);
+
+    Ok(Response::new()
+        .add_messages(mint_msgs)
+        .add_event(event)
+        .add_attribute("action", "steakhub/absorb_delegation"))
+}
+
+pub fn set_swap_route(
+    deps: DepsMut,
+    sender: Addr,
+    denom: String,
+    route: Option<SwapRoute>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let native_denom = state.denom.load(deps.storage)?;
+    if denom == native_denom {
+        return Err(StdError::generic_err(
+            "rewards in the native denom need no swap",
+        ));
+    }
+    let mut event = Event::new("steak/set_swap_route").add_attribute("denom", &denom);
+    match route {
+        Some(route) => {
+            if route.hops.last().map(|hop| &hop.token_out_denom) != Some(&native_denom) {
+                return Err(StdError::generic_err(format!(
+                    "swap route must end in {}",
+                    native_denom
+                )));
+            }
+            if route.min_rate.is_zero() {
+                return Err(StdError::generic_err(
+                    "swap route needs a positive minimum rate",
+                ));
+            }
+            let pool_ids: Vec<String> = route
+                .hops
+                .iter()
+                .map(|hop| hop.pool_id.to_string())
+                .collect();
+            event = event
+                .add_attribute("pool_ids", pool_ids.join(","))
+                .add_attribute("min_rate", route.min_rate.to_string());
+            state.swap_routes.save(deps.storage, &denom, &route)?;
+        }
+        None => {
+            state.swap_routes.remove(deps.storage, &denom);
+            event = event.add_attribute("route", "none");
+        }
+    }
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_swap_route"))
+}
+
+pub fn fund_reward_campaign(
+    deps: DepsMut,
+    env: Env,
+    funder: Addr,
+    funds: Vec<Coin>,
+    start: u64,
+    end: u64,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    let denom = state.denom.load(deps.storage)?;
+    let amount = parse_received_fund(&funds, &denom)?;
+    if start < env.block.time.seconds() {
+        return Err(StdError::generic_err(
+            "reward campaign can't start in the past",
+        ));
+    }
+    if end <= start {
+        return Err(StdError::generic_err(
+            "reward campaign must end after it starts",
+        ));
+    }
+    if state
+        .reward_campaigns
+        .keys(deps.storage, None, None, Order::Ascending)
+        .count()
+        >= MAX_REWARD_CAMPAIGNS
+    {
+        return Err(StdError::generic_err(format!(
+            "no more than {} reward campaigns may run at once",
+            MAX_REWARD_CAMPAIGNS
+        )));
+    }
+
+    let id = state
+        .next_reward_campaign_id
+        .may_load(deps.storage)?
+        .unwrap_or(1);
+    state
+        .next_reward_campaign_id
+        .save(deps.storage, &(id + 1))?;
+    state.reward_campaigns.save(
+        deps.storage,
+        id,
+        &RewardCampaign {
+            id,
+            funder: funder.clone(),
+            amount,
+            released: Uint128::zero(),
+            start,
+            end,
+        },
+    )?;
+    let escrow = state
+        .reward_campaign_escrow
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    state
+        .reward_campaign_escrow
+        .save(deps.storage, &escrow.checked_add(amount)?)?;
+
+    let event = Event::new("steakhub/reward_campaign_funded")
+        .add_attribute("id", id.to_string())
+        .add_attribute("funder", funder)
+        .add_attribute("amount", amount)
+        .add_attribute("start", start.to_string())
+        .add_attribute("end", end.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/fund_reward_campaign"))
+}
+
+pub fn swap_rewards(
+    deps: DepsMut,
+    env: Env,
+    denom: String,
+    min_out: Option<Uint128>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    let route = state
+        .swap_routes
+        .may_load(deps.storage, &denom)?
+        .ok_or_else(|| StdError::generic_err(format!("no swap route for {}", denom)))?;
+
+    let mut unlocked_coins = Coins(state.unlocked_coins.load(deps.storage)?);
+    let token_in = unlocked_coins.find(&denom);
+    if token_in.amount.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "no {} rewards to swap",
+            denom
+        )));
+    }
+    let min_out = (token_in.amount * route.min_rate).max(min_out.unwrap_or_default());
+
+    // The Native Token coming out of the swap raises the hub's balance, which the next reinvest
+    // delegates
+    unlocked_coins.sub(&token_in)?;
+    state.unlocked_coins.save(deps.storage, &unlocked_coins.0)?;
+
+    let swap_msg = swap_exact_amount_in_msg(
+        env.contract.address.to_string(),
+        &route.hops,
+        &token_in,
+        min_out,
+    )?;
+
+    let event = Event::new("steakhub/rewards_swapped")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("denom", denom)
+        .add_attribute("amount", token_in.amount)
+        .add_attribute("min_out", min_out);
+
+    Ok(Response::new()
+        .add_message(swap_msg)
+        .add_event(event)
+        .add_attribute("action", "steakhub/swap_rewards"))
+}
+
+pub fn set_batch_rollover(
+    deps: DepsMut,
+    sender: Addr,
+    batch_rollover: Option<BatchRollover>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let mut event = Event::new("steak/set_batch_rollover");
+    match batch_rollover {
+        Some(batch_rollover) => {
+            if batch_rollover.min_shares.is_zero() || batch_rollover.max_rollovers == 0 {
+                return Err(StdError::generic_err(
+                    "batch rollover needs a positive minimum and number of rollovers",
+                ));
+            }
+            state.batch_rollover.save(deps.storage, &batch_rollover)?;
+            event = event
+                .add_attribute("min_shares", batch_rollover.min_shares)
+                .add_attribute("max_rollovers", batch_rollover.max_rollovers.to_string());
+        }
+        None => {
+            state.batch_rollover.remove(deps.storage);
+            event = event.add_attribute("batch_rollover", "none");
+        }
+    }
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_batch_rollover"))
+}
+
+pub fn set_min_delegation_per_validator(
+    deps: DepsMut,
+    sender: Addr,
+    min_delegation: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state
+        .min_delegation_per_validator
+        .save(deps.storage, &min_delegation)?;
+
+    let decimals = state.load_denom_decimals(deps.storage)?;
+    let event = Event::new("steak/set_min_delegation_per_validator")
+        .add_attribute("min_delegation", min_delegation)
+        .add_attribute("whole_units", format_denom_amount(min_delegation, decimals));
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_min_delegation_per_validator"))
+}
+
+pub fn set_bond_rate_limit(
+    deps: DepsMut,
+    sender: Addr,
+    limit: Option<BondRateLimit>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let mut event = Event::new("steak/set_bond_rate_limit");
+    match limit {
+        Some(limit) => {
+            if limit.window_secs == 0 {
+                return Err(StdError::generic_err(
+                    "bond rate limit window can't be empty",
+                ));
+            }
+            state.bond_rate_limit.save(deps.storage, &limit)?;
+            let decimals = state.load_denom_decimals(deps.storage)?;
+            event = event
+                .add_attribute("max_bond_per_window", limit.max_bond_per_window)
+                .add_attribute(
+                    "whole_units",
+                    format_denom_amount(limit.max_bond_per_window, decimals),
+                )
+                .add_attribute("window_secs", limit.window_secs.to_string());
+        }
+        None => {
+            state.bond_rate_limit.remove(deps.storage);
+            event = event.add_attribute("bond_rate_limit", "none");
+        }
+    }
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_bond_rate_limit"))
+}
+
+pub fn set_min_harvest_reward(
+    deps: DepsMut,
+    sender: Addr,
+    min_reward: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state.min_harvest_reward.save(deps.storage, &min_reward)?;
+
+    let decimals = state.load_denom_decimals(deps.storage)?;
+    let event = Event::new("steak/set_min_harvest_reward")
+        .add_attribute("min_reward", min_reward)
+        .add_attribute("whole_units", format_denom_amount(min_reward, decimals));
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_min_harvest_reward"))
+}
+
+pub fn set_min_self_delegation(
+    deps: DepsMut,
+    sender: Addr,
+    min_self_delegation: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state
+        .min_self_delegation
+        .save(deps.storage, &min_self_delegation)?;
+
+    let decimals = state.load_denom_decimals(deps.storage)?;
+    let event = Event::new("steak/set_min_self_delegation")
+        .add_attribute("min_self_delegation", min_self_delegation)
+        .add_attribute(
+            "whole_units",
+            format_denom_amount(min_self_delegation, decimals),
+        );
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_min_self_delegation"))
+}
+
+/// Deactivate the validators whose operators no longer self-delegate `min_self_delegation`. They
+/// stay whitelisted, keeping their delegations, until the owner removes them or unpauses them
+pub fn check_validators(deps: DepsMut, env: Env) -> StdResult<Response> {
+    let state = State::default();
+
+    let min_self_delegation = state
+        .min_self_delegation
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if min_self_delegation.is_zero() {
+        return Err(StdError::generic_err("no minimum self-delegation is set"));
+    }
+
+    let mut validators_active =
+        state.load_validators_active(deps.storage, env.block.time.seconds())?;
+    let mut deactivated = vec![];
+    for validator in &validators_active {
+        if query_self_delegation(&deps.querier, validator)? < min_self_delegation {
+            deactivated.push(validator.clone());
+        }
+    }
+    validators_active.retain(|v| !deactivated.contains(v));
+    if validators_active.is_empty() {
+        return Err(StdError::generic_err(
+            "can't deactivate every validator; remove or replace some of them instead",
+        ));
+    }
+    state
+        .validators_active
+        .save(deps.storage, &validators_active)?;
+
+    // A suspended validator that falls short would otherwise come back once its suspension ends
+    let suspended = state
+        .validator_suspensions
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for validator in suspended {
+        if query_self_delegation(&deps.querier, &validator)? < min_self_delegation {
+            state
+                .validator_suspensions
+                .remove(deps.storage, validator.clone());
+            deactivated.push(validator);
+        }
+    }
+
+    let event = Event::new("steakhub/validators_checked")
+        .add_attribute("min_self_delegation", min_self_delegation)
+        .add_attribute("deactivated", deactivated.join(","));
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/check_validators"))
+}
+
+pub fn register_hook(
+    deps: DepsMut,
+    sender: Addr,
+    event_kind: HookEventKind,
+    contract: Addr,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let mut contracts = state
+        .hooks
+        .may_load(deps.storage, event_kind.as_str())?
+        .unwrap_or_default();
+    if contracts.contains(&contract) {
+        return Err(StdError::generic_err(format!(
+            "{} is already notified of {} events",
+            contract,
+            event_kind.as_str()
+        )));
+    }
+    if contracts.len() >= MAX_HOOKS_PER_EVENT_KIND {
+        return Err(StdError::generic_err(format!(
+            "no more than {} contracts may be notified of {} events",
+            MAX_HOOKS_PER_EVENT_KIND,
+            event_kind.as_str()
+        )));
+    }
+    contracts.push(contract.clone());
+    state
+        .hooks
+        .save(deps.storage, event_kind.as_str(), &contracts)?;
+
+    let event = Event::new("steak/register_hook")
+        .add_attribute("event_kind", event_kind.as_str())
+        .add_attribute("contract", contract);
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/register_hook"))
+}
+
+pub fn deregister_hook(
+    deps: DepsMut,
+    sender: Addr,
+    event_kind: HookEventKind,
+    contract: Addr,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let mut contracts = state
+        .hooks
+        .may_load(deps.storage, event_kind.as_str())?
+        .unwrap_or_default();
+    if !contracts.contains(&contract) {
+        return Err(StdError::generic_err(format!(
+            "{} is not notified of {} events",
+            contract,
+            event_kind.as_str()
+        )));
+    }
+    contracts.retain(|c| *c != contract);
+    if contracts.is_empty() {
+        state.hooks.remove(deps.storage, event_kind.as_str());
+    } else {
+        state
+            .hooks
+            .save(deps.storage, event_kind.as_str(), &contracts)?;
+    }
+
+    let event = Event::new("steak/deregister_hook")
+        .add_attribute("event_kind", event_kind.as_str())
+        .add_attribute("contract", contract);
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/deregister_hook"))
+}
+
+pub fn set_forbidden_deposit_denoms(
+    deps: DepsMut,
+    sender: Addr,
+    denoms: Vec<String>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let denom = state.denom.load(deps.storage)?;
+    if denoms.contains(&denom) {
+        return Err(StdError::generic_err(format!(
+            "{} is the Native Token and can't be forbidden",
+            denom
+        )));
+    }
+    let mut denoms = denoms;
+    denoms.sort();
+    denoms.dedup();
+    state.forbidden_deposit_denoms.save(deps.storage, &denoms)?;
+
+    let event =
+        Event::new("steak/set_forbidden_deposit_denoms").add_attribute("denoms", denoms.join(","));
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_forbidden_deposit_denoms"))
+}
+
+pub fn set_token_admin(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    admin: String,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    assert_hub_administers_token(deps.as_ref(), &env)?;
+    let steak_token = state.steak_token.load(deps.storage)?;
+    let admin = deps.api.addr_validate(&admin)?;
+    state.token_admin.save(deps.storage, &admin)?;
+
+    let update_admin_msg = CosmosMsg::Wasm(WasmMsg::UpdateAdmin {
+        contract_addr: steak_token.to_string(),
+        admin: admin.to_string(),
+    });
+
+    let event = Event::new("steak/set_token_admin")
+        .add_attribute("steak_token", steak_token)
+        .add_attribute("admin", admin);
+
+    Ok(Response::new()
+        .add_message(update_admin_msg)
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_token_admin"))
+}
+
+pub fn set_max_batch_exclusions(
+    deps: DepsMut,
+    sender: Addr,
+    max_exclusions: u32,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state
+        .max_batch_exclusions
+        .save(deps.storage, &max_exclusions)?;
+
+    let event = Event::new("steak/set_max_batch_exclusions")
+        .add_attribute("max_exclusions", max_exclusions.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_max_batch_exclusions"))
+}
+
+pub fn set_measure_deposits(deps: DepsMut, sender: Addr, enabled: bool) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state.measure_deposits.save(deps.storage, &enabled)?;
+
+    let event =
+        Event::new("steak/set_measure_deposits").add_attribute("enabled", enabled.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_measure_deposits"))
+}
+
+pub fn set_min_unbond_shares(
+    deps: DepsMut,
+    sender: Addr,
+    min_unbond_shares: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    state
+        .min_unbond_shares
+        .save(deps.storage, &min_unbond_shares)?;
+    let event = Event::new("steak/set_min_unbond_shares")
+        .add_attribute("min_unbond_shares", min_unbond_shares);
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_min_unbond_shares"))
+}
+
+pub fn set_priority_unbonders(
+    deps: DepsMut,
+    sender: Addr,
+    addresses: Vec<String>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let mut priority_unbonders = addresses
+        .iter()
+        .map(|address| deps.api.addr_validate(address))
+        .collect::<StdResult<Vec<_>>>()?;
+    priority_unbonders.sort();
+    priority_unbonders.dedup();
+    state
+        .priority_unbonders
+        .save(deps.storage, &priority_unbonders)?;
+
+    let event = Event::new("steak/set_priority_unbonders").add_attribute(
+        "addresses",
+        priority_unbonders
+            .iter()
+            .map(Addr::as_str)
+            .collect::<Vec<_>>()
+            .join(","),
+    );
 
     Ok(Response::new()
-        .add_messages(msgs)
         .add_event(event)
-        .add_attribute("action", "steakhub/queue_unbond"))
+        .add_attribute("action", "steakhub/set_priority_unbonders"))
 }
 
-pub fn submit_batch(deps: DepsMut, env: Env) -> StdResult<Response> {
+pub fn set_denom_decimals(deps: DepsMut, sender: Addr, decimals: u8) -> StdResult<Response> {
     let state = State::default();
-    let denom = state.denom.load(deps.storage)?;
-    let steak_token = state.steak_token.load(deps.storage)?;
-    let validators = state.validators.load(deps.storage)?;
-    let unbond_period = state.unbond_period.load(deps.storage)?;
-    let pending_batch = state.pending_batch.load(deps.storage)?;
 
-    let current_time = env.block.time.seconds();
-    if current_time < pending_batch.est_unbond_start_time {
+    state.assert_owner(deps.storage, &sender)?;
+    if decimals > MAX_DENOM_DECIMALS {
         return Err(StdError::generic_err(format!(
-            "batch can only be submitted for unbonding after {}",
-            pending_batch.est_unbond_start_time
+            "decimals cannot exceed {}",
+            MAX_DENOM_DECIMALS
         )));
     }
-
-    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
-    let usteak_supply = query_cw20_total_supply(&deps.querier, &steak_token)?;
-
-    let amount_to_bond =
-        compute_unbond_amount(usteak_supply, pending_batch.usteak_to_burn, &delegations);
-    let new_undelegations = compute_undelegations(amount_to_bond, &delegations, &denom);
-
-    // NOTE: Regarding the `amount_unclaimed` value
-    //
-    // If validators misbehave and get slashed during the unbonding period, the contract can receive
-    // LESS Native Token than `amount_to_unbond` when unbonding finishes!
-    //
-    // In this case, users who invokes `withdraw_unbonded` will have their txs failed as the contract
-    // does not have enough Native Token balance.
-    //
-    // I don't have a solution for this... other than to manually fund contract with the slashed amount.
-    state.previous_batches.save(
-        deps.storage,
-        pending_batch.id,
-        &Batch {
-            id: pending_batch.id,
-            reconciled: false,
-            total_shares: pending_batch.usteak_to_burn,
-            amount_unclaimed: amount_to_bond,
-            est_unbond_end_time: current_time + unbond_period,
-        },
-    )?;
-
-    let epoch_period = state.epoch_period.load(deps.storage)?;
-    state.pending_batch.save(
-        deps.storage,
-        &PendingBatch {
-            id: pending_batch.id + 1,
-            usteak_to_burn: Uint128::zero(),
-            est_unbond_start_time: current_time + epoch_period,
-        },
-    )?;
-    state.prev_denom.save(
-        deps.storage,
-        &get_denom_balance(&deps.querier, env.contract.address.clone(), denom)?,
-    )?;
-
-    let undelegate_submsgs = new_undelegations
-        .iter()
-        .map(|d| {
-            Ok(SubMsg::reply_on_success(
-                d.to_cosmos_msg(env.contract.address.to_string())?,
-                REPLY_REGISTER_RECEIVED_COINS,
-            ))
-        })
-        .collect::<StdResult<Vec<_>>>()?;
-
-    let burn_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: steak_token.into(),
-        msg: to_binary(&Cw20ExecuteMsg::Burn {
-            amount: pending_batch.usteak_to_burn,
-        })?,
-        funds: vec![],
-    });
-
-    let event = Event::new("steakhub/unbond_submitted")
-        .add_attribute("time", env.block.time.seconds().to_string())
-        .add_attribute("height", env.block.height.to_string())
-        .add_attribute("id", pending_batch.id.to_string())
-        .add_attribute("native_unbonded", amount_to_bond)
-        .add_attribute("usteak_burned", pending_batch.usteak_to_burn);
+    state.denom_decimals.save(deps.storage, &decimals)?;
+    let event =
+        Event::new("steak/set_denom_decimals").add_attribute("decimals", decimals.to_string());
 
     Ok(Response::new()
-        .add_submessages(undelegate_submsgs)
-        .add_message(burn_msg)
         .add_event(event)
-        .add_attribute("action", "steakhub/unbond"))
+        .add_attribute("action", "steakhub/set_denom_decimals"))
 }
 
-pub fn reconcile(deps: DepsMut, env: Env) -> StdResult<Response> {
+pub fn set_staking_proto_urls(
+    deps: DepsMut,
+    sender: Addr,
+    urls: StakingProtoUrls,
+) -> StdResult<Response> {
     let state = State::default();
-    let current_time = env.block.time.seconds();
-
-    // Load batches that have not been reconciled
-    let all_batches = state
-        .previous_batches
-        .idx
-        .reconciled
-        .prefix(false.into())
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|item| {
-            let (_, v) = item?;
-            Ok(v)
-        })
-        .collect::<StdResult<Vec<_>>>()?;
 
-    let mut batches = all_batches
-        .into_iter()
-        .filter(|b| current_time > b.est_unbond_end_time)
-        .collect::<Vec<_>>();
+    state.assert_owner(deps.storage, &sender)?;
+    for url in [
+        &urls.delegate,
+        &urls.undelegate,
+        &urls.redelegate,
+        &urls.withdraw_rewards,
+        &urls.redeem_tokens,
+        &urls.authz_exec,
+    ] {
+        if !url.starts_with('/') {
+            return Err(StdError::generic_err(format!(
+                "invalid type url `{}`: must start with `/`",
+                url
+            )));
+        }
+    }
+    state.staking_proto_urls.save(deps.storage, &urls)?;
 
-    let native_expected_received: Uint128 = batches.iter().map(|b| b.amount_unclaimed).sum();
-    let denom = state.denom.load(deps.storage)?;
-    let unlocked_coins = state.unlocked_coins.load(deps.storage)?;
+    let event = Event::new("steak/set_staking_proto_urls")
+        .add_attribute("delegate", urls.delegate)
+        .add_attribute("undelegate", urls.undelegate)
+        .add_attribute("redelegate", urls.redelegate)
+        .add_attribute("withdraw_rewards", urls.withdraw_rewards)
+        .add_attribute("redeem_tokens", urls.redeem_tokens)
+        .add_attribute("authz_exec", urls.authz_exec);
 
-    let native_expected_unlocked = Coins(unlocked_coins).find(&denom).amount;
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_staking_proto_urls"))
+}
 
-    let native_expected = native_expected_received + native_expected_unlocked;
-    let native_actual = deps
-        .querier
-        .query_balance(&env.contract.address, &denom)?
-        .amount;
+pub fn set_validator_cap(
+    deps: DepsMut,
+    sender: Addr,
+    validator: String,
+    cap: Option<Uint128>,
+) -> StdResult<Response> {
+    let state = State::default();
 
-    let native_to_deduct = native_expected
-        .checked_sub(native_actual)
-        .unwrap_or_else(|_| Uint128::zero());
-    if !native_to_deduct.is_zero() {
-        reconcile_batches(&mut batches, native_expected - native_actual);
+    state.assert_owner(deps.storage, &sender)?;
+    if !state.validators.load(deps.storage)?.contains(&validator) {
+        return Err(StdError::generic_err(
+            "validator is not already whitelisted",
+        ));
     }
+    let event = match cap {
+        Some(cap) => {
+            state
+                .validator_caps
+                .save(deps.storage, validator.clone(), &cap)?;
+            Event::new("steak/set_validator_cap")
+                .add_attribute("validator", validator)
+                .add_attribute("cap", cap)
+        }
+        None => {
+            state.validator_caps.remove(deps.storage, validator.clone());
+            Event::new("steak/set_validator_cap")
+                .add_attribute("validator", validator)
+                .add_attribute("cap", "none")
+        }
+    };
 
-    for batch in batches.iter_mut() {
-        batch.reconciled = true;
-        state.previous_batches.save(deps.storage, batch.id, batch)?;
-    }
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_validator_cap"))
+}
 
-    let ids = batches
-        .iter()
-        .map(|b| b.id.to_string())
-        .collect::<Vec<_>>()
-        .join(",");
+pub fn set_validator_difficulty(
+    deps: DepsMut,
+    sender: Addr,
+    validator: String,
+    difficulty: Option<Uint64>,
+) -> StdResult<Response> {
+    let state = State::default();
 
-    let event = Event::new("steakhub/reconciled")
-        .add_attribute("ids", ids)
-        .add_attribute("native_deducted", native_to_deduct.to_string());
+    state.assert_owner(deps.storage, &sender)?;
+    if !state.validators.load(deps.storage)?.contains(&validator) {
+        return Err(StdError::generic_err(
+            "validator is not already whitelisted",
+        ));
+    }
+    let event = match difficulty {
+        Some(difficulty) => {
+            if difficulty.is_zero() || difficulty.u64() > MAX_MINING_DIFFICULTY {
+                return Err(StdError::generic_err(format!(
+                    "difficulty must be between 1 and {}",
+                    MAX_MINING_DIFFICULTY
+                )));
+            }
+            state.validator_mining_difficulties.save(
+                deps.storage,
+                validator.clone(),
+                &difficulty,
+            )?;
+            Event::new("steak/set_validator_difficulty")
+                .add_attribute("validator", validator)
+                .add_attribute("difficulty", difficulty)
+        }
+        None => {
+            state
+                .validator_mining_difficulties
+                .remove(deps.storage, validator.clone());
+            Event::new("steak/set_validator_difficulty")
+                .add_attribute("validator", validator)
+                .add_attribute("difficulty", "none")
+        }
+    };
 
     Ok(Response::new()
         .add_event(event)
-        .add_attribute("action", "steakhub/reconcile"))
+        .add_attribute("action", "steakhub/set_validator_difficulty"))
 }
-pub fn withdraw_unbonded_admin(
+
+pub fn set_ics20_contract(
     deps: DepsMut,
-    env: Env,
-    user: Addr,
-    receiver: Addr,
+    sender: Addr,
+    ics20_contract: Option<String>,
+    channel: Option<String>,
 ) -> StdResult<Response> {
     let state = State::default();
 
-    state.assert_owner(deps.storage, &user)?;
+    state.assert_owner(deps.storage, &sender)?;
+    let event = match (ics20_contract, channel) {
+        (Some(ics20_contract), Some(channel)) if !channel.is_empty() => {
+            let ics20_contract = deps.api.addr_validate(&ics20_contract)?;
+            state.ics20_contract.save(deps.storage, &ics20_contract)?;
+            state.ics20_channel.save(deps.storage, &channel)?;
+            Event::new("steak/set_ics20_contract")
+                .add_attribute("ics20_contract", ics20_contract)
+                .add_attribute("channel", channel)
+        }
+        (None, None) => {
+            state.ics20_contract.remove(deps.storage);
+            state.ics20_channel.remove(deps.storage);
+            Event::new("steak/set_ics20_contract").add_attribute("ics20_contract", "none")
+        }
+        _ => {
+            return Err(StdError::generic_err(
+                "ics20 contract and channel must be set together",
+            ))
+        }
+    };
 
-    withdraw_unbonded(deps, env, receiver.clone(), receiver)
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_ics20_contract"))
 }
 
-pub fn withdraw_unbonded(
+pub fn set_rate_publisher(
     deps: DepsMut,
-    env: Env,
-    user: Addr,
-    receiver: Addr,
+    sender: Addr,
+    rate_publisher: Option<RatePublisher>,
 ) -> StdResult<Response> {
     let state = State::default();
-    let denom = state.denom.load(deps.storage)?;
-    let current_time = env.block.time.seconds();
 
-    // NOTE: If the user has too many unclaimed requests, this may not fit in the WASM memory...
-    // However, this is practically never going to happen. Who would create hundreds of unbonding
-    // requests and never claim them?
-    let requests = state
-        .unbond_requests
-        .idx
-        .user
-        .prefix(user.to_string())
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|item| {
-            let (_, v) = item?;
-            Ok(v)
-        })
-        .collect::<StdResult<Vec<_>>>()?;
+    state.assert_owner(deps.storage, &sender)?;
+    let event = match rate_publisher {
+        Some(rate_publisher) => {
+            let protocol = match &rate_publisher.protocol {
+                BridgeProtocol::Wormhole { core_bridge } => {
+                    deps.api.addr_validate(core_bridge)?;
+                    "wormhole"
+                }
+                BridgeProtocol::Axelar {
+                    channel,
+                    destination_chain,
+                    destination_address,
+                } => {
+                    if channel.is_empty()
+                        || destination_chain.is_empty()
+                        || destination_address.is_empty()
+                    {
+                        return Err(StdError::generic_err(
+                            "axelar needs a channel, destination chain and destination address",
+                        ));
+                    }
+                    // The message rides on an IBC transfer, which can't be of zero tokens
+                    if rate_publisher.fee.is_zero() {
+                        return Err(StdError::generic_err("axelar needs a non-zero fee"));
+                    }
+                    "axelar"
+                }
+            };
+            state.rate_publisher.save(deps.storage, &rate_publisher)?;
+            Event::new("steak/set_rate_publisher")
+                .add_attribute("protocol", protocol)
+                .add_attribute("fee", rate_publisher.fee)
+        }
+        None => {
+            state.rate_publisher.remove(deps.storage);
+            Event::new("steak/set_rate_publisher").add_attribute("protocol", "none")
+        }
+    };
 
-    // NOTE: Native in the following batches are withdrawn it the batch:
-    // - is a _previous_ batch, not a _pending_ batch
-    // - is reconciled
-    // - has finished unbonding
-    // If not sure whether the batches have been reconciled, the user should first invoke `ExecuteMsg::Reconcile`
-    // before withdrawing.
-    let mut total_native_to_refund = Uint128::zero();
-    let mut ids: Vec<String> = vec![];
-    for request in &requests {
-        if let Ok(mut batch) = state.previous_batches.load(deps.storage, request.id) {
-            if batch.reconciled && batch.est_unbond_end_time < current_time {
-                let native_to_refund = batch
-                    .amount_unclaimed
-                    .multiply_ratio(request.shares, batch.total_shares);
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_rate_publisher"))
+}
 
-                ids.push(request.id.to_string());
+pub fn set_accepted_token(
+    deps: DepsMut,
+    sender: Addr,
+    token: String,
+    receive_msgs: Vec<ReceiveMsgKind>,
+) -> StdResult<Response> {
+    let state = State::default();
 
-                total_native_to_refund += native_to_refund;
-                batch.total_shares -= request.shares;
-                batch.amount_unclaimed -= native_to_refund;
+    state.assert_owner(deps.storage, &sender)?;
+    let token = deps.api.addr_validate(&token)?;
+    if receive_msgs.is_empty() {
+        state.accepted_tokens.remove(deps.storage, &token);
+    } else {
+        state
+            .accepted_tokens
+            .save(deps.storage, &token, &receive_msgs)?;
+    }
 
-                if batch.total_shares.is_zero() {
-                    state.previous_batches.remove(deps.storage, request.id)?;
-                } else {
-                    state
-                        .previous_batches
-                        .save(deps.storage, batch.id, &batch)?;
-                }
+    let event = Event::new("steak/set_accepted_token")
+        .add_attribute("token", token)
+        .add_attribute(
+            "receive_msgs",
+            receive_msgs
+                .iter()
+                .map(|kind| format!("{:?}", kind))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
 
-                state
-                    .unbond_requests
-                    .remove(deps.storage, (request.id, &user))?;
-            }
-        }
-    }
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_accepted_token"))
+}
 
-    if total_native_to_refund.is_zero() {
-        return Err(StdError::generic_err("withdrawable amount is zero"));
-    }
+pub fn set_harvest_mode(deps: DepsMut, sender: Addr, mode: HarvestMode) -> StdResult<Response> {
+    let state = State::default();
 
-    let refund_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: receiver.clone().into(),
-        amount: vec![Coin::new(total_native_to_refund.u128(), &denom)],
-    });
+    state.assert_owner(deps.storage, &sender)?;
+    state.harvest_mode.save(deps.storage, &mode)?;
 
-    let event = Event::new("steakhub/unbonded_withdrawn")
-        .add_attribute("time", env.block.time.seconds().to_string())
-        .add_attribute("height", env.block.height.to_string())
-        .add_attribute("ids", ids.join(","))
-        .add_attribute("user", user)
-        .add_attribute("receiver", receiver)
-        .add_attribute("amount_refunded", total_native_to_refund);
+    let event = Event::new("steak/set_harvest_mode").add_attribute("mode", format!("{:?}", mode));
 
     Ok(Response::new()
-        .add_message(refund_msg)
         .add_event(event)
-        .add_attribute("action", "steakhub/withdraw_unbonded"))
+        .add_attribute("action", "steakhub/set_harvest_mode"))
 }
 
-//--------------------------------------------------------------------------------------------------
-// Ownership and management logics
-//--------------------------------------------------------------------------------------------------
-
-pub fn rebalance(deps: DepsMut, env: Env, minimum: Uint128) -> StdResult<Response> {
+pub fn set_reconcile_mode(deps: DepsMut, sender: Addr, mode: ReconcileMode) -> StdResult<Response> {
     let state = State::default();
-    let denom = state.denom.load(deps.storage)?;
-    let validators = state.validators.load(deps.storage)?;
-    let validators_active = state.validators_active.load(deps.storage)?;
-
-    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
 
-    let total_delegated_amount = delegations.iter().fold(0u128, |acc, d| acc + d.amount);
-
-    let total_mining_power = state.total_mining_power.load(deps.storage)?;
+    state.assert_owner(deps.storage, &sender)?;
+    state.reconcile_mode.save(deps.storage, &mode)?;
 
-    let new_redelegations =
-        compute_redelegations_for_rebalancing(validators_active, &delegations, minimum, |d| {
-            compute_target_delegation_from_mining_power(
-                total_delegated_amount.into(),
-                state
-                    .validator_mining_powers
-                    .may_load(deps.storage, d.validator.clone())?
-                    .unwrap_or_default(),
-                total_mining_power,
-            )
-        })?;
+    let event = Event::new("steak/set_reconcile_mode").add_attribute("mode", format!("{:?}", mode));
 
-    state.prev_denom.save(
-        deps.storage,
-        &get_denom_balance(&deps.querier, env.contract.address.clone(), denom)?,
-    )?;
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_reconcile_mode"))
+}
 
-    let redelegate_submsgs = new_redelegations
-        .iter()
-        .map(|rd| {
-            Ok(SubMsg::reply_on_success(
-                rd.to_cosmos_msg(env.contract.address.to_string())?,
-                REPLY_REGISTER_RECEIVED_COINS,
-            ))
-        })
-        .collect::<StdResult<Vec<_>>>()?;
+pub fn set_remainder_policy(
+    deps: DepsMut,
+    sender: Addr,
+    policy: RemainderPolicy,
+) -> StdResult<Response> {
+    let state = State::default();
 
-    let amount: u128 = new_redelegations.iter().map(|rd| rd.amount).sum();
+    state.assert_owner(deps.storage, &sender)?;
+    state.remainder_policy.save(deps.storage, &policy)?;
 
-    let event = Event::new("steakhub/rebalanced").add_attribute("amount_moved", amount.to_string());
+    let event =
+        Event::new("steak/set_remainder_policy").add_attribute("policy", format!("{:?}", policy));
 
     Ok(Response::new()
-        .add_submessages(redelegate_submsgs)
         .add_event(event)
-        .add_attribute("action", "steakhub/rebalance"))
+        .add_attribute("action", "steakhub/set_remainder_policy"))
 }
 
-pub fn add_validator(deps: DepsMut, sender: Addr, validator: String) -> StdResult<Response> {
+pub fn set_fee_split_interface(
+    deps: DepsMut,
+    sender: Addr,
+    interface: FeeSplitInterface,
+) -> StdResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
+    state.fee_split_interface.save(deps.storage, &interface)?;
 
-    state.validators.update(deps.storage, |mut validators| {
-        if validators.contains(&validator) {
-            return Err(StdError::generic_err("validator is already whitelisted"));
-        }
-        validators.push(validator.clone());
-        Ok(validators)
-    })?;
-
-    let mut validators_active = state.validators_active.load(deps.storage)?;
-    if !validators_active.contains(&validator) {
-        validators_active.push(validator.clone());
-    }
-    state
-        .validators_active
-        .save(deps.storage, &validators_active)?;
-    let event = Event::new("steakhub/validator_added").add_attribute("validator", validator);
+    let event = Event::new("steak/set_fee_split_interface")
+        .add_attribute("interface", format!("{:?}", interface));
 
     Ok(Response::new()
         .add_event(event)
-        .add_attribute("action", "steakhub/add_validator"))
+        .add_attribute("action", "steakhub/set_fee_split_interface"))
 }
 
-pub fn remove_validator(
+pub fn set_capabilities(
     deps: DepsMut,
-    env: Env,
     sender: Addr,
-    validator: String,
+    capabilities: Capabilities,
 ) -> StdResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
-    let denom = state.denom.load(deps.storage)?;
-
-    let validators = state.validators.update(deps.storage, |mut validators| {
-        if !validators.contains(&validator) {
-            return Err(StdError::generic_err(
-                "validator is not already whitelisted",
-            ));
-        }
-        validators.retain(|v| *v != validator);
-        Ok(validators)
-    })?;
-    let mut validators_active = state.validators_active.load(deps.storage)?;
-    if !validators_active.contains(&validator) {
-        validators_active.push(validator.clone());
-    }
-    state
-        .validators_active
-        .save(deps.storage, &validators_active)?;
+    state.capabilities.save(deps.storage, &capabilities)?;
 
-    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
-    let delegation_to_remove =
-        query_delegation(&deps.querier, &validator, &env.contract.address, &denom)?;
-    let new_redelegations =
-        compute_redelegations_for_removal(&delegation_to_remove, &delegations, &denom);
+    let event = Event::new("steak/set_capabilities")
+        .add_attribute("bond", capabilities.bond.to_string())
+        .add_attribute("unbond", capabilities.unbond.to_string())
+        .add_attribute("withdraw", capabilities.withdraw.to_string())
+        .add_attribute("harvest", capabilities.harvest.to_string())
+        .add_attribute("submit_batch", capabilities.submit_batch.to_string());
 
-    state.prev_denom.save(
-        deps.storage,
-        &get_denom_balance(&deps.querier, env.contract.address.clone(), denom)?,
-    )?;
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_capabilities"))
+}
 
-    let redelegate_submsgs = new_redelegations
-        .iter()
-        .map(|d| {
-            Ok(SubMsg::reply_on_success(
-                d.to_cosmos_msg(env.contract.address.to_string())?,
-                REPLY_REGISTER_RECEIVED_COINS,
-            ))
-        })
-        .collect::<StdResult<Vec<_>>>()?;
+pub fn set_guardian(deps: DepsMut, sender: Addr, guardian: Option<String>) -> StdResult<Response> {
+    let state = State::default();
 
-    let event = Event::new("steak/validator_removed").add_attribute("validator", validator);
+    state.assert_owner(deps.storage, &sender)?;
+    let event = match guardian {
+        Some(guardian) => {
+            let guardian = deps.api.addr_validate(&guardian)?;
+            state.guardian.save(deps.storage, &guardian)?;
+            Event::new("steak/set_guardian").add_attribute("guardian", guardian)
+        }
+        None => {
+            state.guardian.remove(deps.storage);
+            Event::new("steak/set_guardian").add_attribute("guardian", "none")
+        }
+    };
 
     Ok(Response::new()
-        .add_submessages(redelegate_submsgs)
         .add_event(event)
-        .add_attribute("action", "steakhub/remove_validator"))
+        .add_attribute("action", "steakhub/set_guardian"))
 }
 
-pub fn remove_validator_ex(
+pub fn set_exchange_rate_epsilon(
     deps: DepsMut,
-    _env: Env,
     sender: Addr,
-    validator: String,
+    epsilon: Decimal,
 ) -> StdResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
+    if epsilon >= Decimal::one() {
+        return Err(StdError::generic_err("epsilon must be less than 1"));
+    }
+    state.exchange_rate_epsilon.save(deps.storage, &epsilon)?;
 
-    state.validators.update(deps.storage, |mut validators| {
-        if !validators.contains(&validator) {
-            return Err(StdError::generic_err(
-                "validator is not already whitelisted",
-            ));
-        }
-        validators.retain(|v| *v != validator);
-        Ok(validators)
-    })?;
-
-    let event = Event::new("steak/validator_removed_ex").add_attribute("validator", validator);
+    let event =
+        Event::new("steak/set_exchange_rate_epsilon").add_attribute("epsilon", epsilon.to_string());
 
     Ok(Response::new()
         .add_event(event)
-        .add_attribute("action", "steakhub/remove_validator_ex"))
+        .add_attribute("action", "steakhub/set_exchange_rate_epsilon"))
 }
 
-pub fn pause_validator(
+pub fn acknowledge_slash(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     sender: Addr,
-    validator: String,
+    validator: Option<String>,
 ) -> StdResult<Response> {
     let state = State::default();
 
-    state.assert_owner(deps.storage, &sender)?;
+    state.assert_guardian(deps.storage, &sender)?;
+    state.slash_acknowledged.save(deps.storage, &true)?;
 
-    state
-        .validators_active
-        .update(deps.storage, |mut validators| {
-            if !validators.contains(&validator) {
-                return Err(StdError::generic_err(
-                    "validator is not already whitelisted",
-                ));
-            }
-            validators.retain(|v| *v != validator);
-            Ok(validators)
-        })?;
+    let mut event = Event::new("steak/slash_acknowledged").add_attribute("guardian", sender);
+    let mut res = Response::new();
 
-    let event = Event::new("steak/pause_validator").add_attribute("validator", validator);
+    if let Some(validator) = validator {
+        if !state.validators.load(deps.storage)?.contains(&validator) {
+            return Err(StdError::generic_err(format!(
+                "validator {} is not whitelisted",
+                validator
+            )));
+        }
+        let slash_incidents = state.slash_incidents.update(
+            deps.storage,
+            validator.clone(),
+            |incidents| -> StdResult<_> { Ok(incidents.unwrap_or_default() + 1) },
+        )?;
+        event = event
+            .add_attribute("validator", &validator)
+            .add_attribute("slash_incidents", slash_incidents.to_string());
+
+        // Repeat offenders stop receiving new delegations until the owner reinstates them
+        let slash_limit = state.slash_limit.may_load(deps.storage)?.flatten();
+        let mut validators_active =
+            state.load_validators_active(deps.storage, env.block.time.seconds())?;
+        if slash_limit.map_or(false, |limit| slash_incidents >= limit)
+            && validators_active.contains(&validator)
+        {
+            validators_active.retain(|v| *v != validator);
+            state
+                .validators_active
+                .save(deps.storage, &validators_active)?;
+            res = res.add_event(
+                Event::new("steak/validator_penalized")
+                    .add_attribute("validator", &validator)
+                    .add_attribute("slash_incidents", slash_incidents.to_string()),
+            );
+        }
+    }
 
-    Ok(Response::new()
+    Ok(res
         .add_event(event)
-        .add_attribute("action", "steakhub/pause_validator"))
+        .add_attribute("action", "steakhub/acknowledge_slash"))
 }
 
-pub fn unpause_validator(
+pub fn set_slash_limit(
     deps: DepsMut,
-    _env: Env,
     sender: Addr,
-    validator: String,
+    slash_limit: Option<u32>,
 ) -> StdResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
-    let mut validators_active = state.validators_active.load(deps.storage)?;
-    if !validators_active.contains(&validator) {
-        validators_active.push(validator.clone());
+    if slash_limit == Some(0) {
+        return Err(StdError::generic_err(
+            "slash limit must be positive; use none to never deactivate validators",
+        ));
     }
-    state
-        .validators_active
-        .save(deps.storage, &validators_active)?;
+    state.slash_limit.save(deps.storage, &slash_limit)?;
 
-    let event = Event::new("steak/unpause_validator").add_attribute("validator", validator);
+    let event = Event::new("steak/set_slash_limit").add_attribute(
+        "slash_limit",
+        slash_limit.map_or_else(|| "none".to_string(), |limit| limit.to_string()),
+    );
 
     Ok(Response::new()
         .add_event(event)
-        .add_attribute("action", "steakhub/unpause_validator"))
+        .add_attribute("action", "steakhub/set_slash_limit"))
 }
-pub fn set_unbond_period(
+
+pub fn reinstate_validator(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     sender: Addr,
-    unbond_period: u64,
+    validator: String,
 ) -> StdResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
-    state.unbond_period.save(deps.storage, &unbond_period)?;
-    let event = Event::new("steak/set_unbond_period")
-        .add_attribute("unbond_period", format!("{}", unbond_period));
+    if !state.validators.load(deps.storage)?.contains(&validator) {
+        return Err(StdError::generic_err(format!(
+            "validator {} is not whitelisted",
+            validator
+        )));
+    }
+
+    state
+        .slash_incidents
+        .remove(deps.storage, validator.clone());
+    // A validator still suspended comes back once the suspension ends
+    let mut validators_active =
+        state.load_validators_active(deps.storage, env.block.time.seconds())?;
+    let suspended = state
+        .validator_suspensions
+        .has(deps.storage, validator.clone());
+    if !validators_active.contains(&validator) && !suspended {
+        validators_active.push(validator.clone());
+    }
+    state
+        .validators_active
+        .save(deps.storage, &validators_active)?;
+
+    let event = Event::new("steak/validator_reinstated").add_attribute("validator", validator);
 
     Ok(Response::new()
         .add_event(event)
-        .add_attribute("action", "steakhub/set_unbond_period"))
+        .add_attribute("action", "steakhub/reinstate_validator"))
 }
 
 pub fn transfer_ownership(deps: DepsMut, sender: Addr, new_owner: String) -> StdResult<Response> {
@@ -1021,25 +5827,150 @@ pub fn accept_ownership(deps: DepsMut, sender: Addr) -> StdResult<Response> {
         .add_attribute("action", "steakhub/transfer_ownership"))
 }
 
+pub fn propose_change(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    change: ConfigChange,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    if let ConfigChange::Timelock { timelock } = &change {
+        if timelock.expiry == 0 {
+            return Err(StdError::generic_err("timelock expiry must be above zero"));
+        }
+    }
+
+    // Changes that can no longer be accepted are dropped as new ones come in
+    let proposed_at = env.block.time.seconds();
+    let expired = state
+        .pending_changes
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref()
+                .map_or(true, |(_, change)| change.expires_at < proposed_at)
+        })
+        .map(|item| item.map(|(id, _)| id))
+        .collect::<StdResult<Vec<_>>>()?;
+    for id in &expired {
+        state.pending_changes.remove(deps.storage, *id);
+    }
+
+    let id = state.next_change_id.may_load(deps.storage)?.unwrap_or(1);
+    state.next_change_id.save(deps.storage, &(id + 1))?;
+
+    let timelock = state
+        .change_timelock
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let accept_after = proposed_at + timelock.delay;
+    let pending_change = PendingChange {
+        id,
+        change,
+        proposed_at,
+        accept_after,
+        expires_at: accept_after + timelock.expiry,
+    };
+    state
+        .pending_changes
+        .save(deps.storage, id, &pending_change)?;
+
+    let mut event = Event::new("steakhub/change_proposed")
+        .add_attribute("id", id.to_string())
+        .add_attribute("change", format!("{:?}", pending_change.change))
+        .add_attribute("accept_after", accept_after.to_string())
+        .add_attribute("expires_at", pending_change.expires_at.to_string());
+    if !expired.is_empty() {
+        event = event.add_attribute("expired_dropped", expired.len().to_string());
+    }
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/propose_change"))
+}
+
+pub fn accept_change(deps: DepsMut, env: Env, sender: Addr, id: u64) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let pending_change = state
+        .pending_changes
+        .may_load(deps.storage, id)?
+        .ok_or_else(|| StdError::generic_err(format!("change {} not found", id)))?;
+    if env.block.time.seconds() < pending_change.accept_after {
+        return Err(StdError::generic_err(format!(
+            "change {} can only be accepted after {}",
+            id, pending_change.accept_after
+        )));
+    }
+    if env.block.time.seconds() > pending_change.expires_at {
+        return Err(StdError::generic_err(format!("change {} has expired", id)));
+    }
+    state.pending_changes.remove(deps.storage, id);
+
+    let response = match pending_change.change {
+        ConfigChange::FeeAccount {
+            fee_account_type,
+            new_fee_account,
+        } => transfer_fee_account(deps, sender, fee_account_type, new_fee_account)?,
+        ConfigChange::FeeRate { new_fee } => update_fee(deps, sender, new_fee)?,
+        ConfigChange::RemoveValidator { validator } => {
+            remove_validator(deps, env, sender, validator)?
+        }
+        ConfigChange::Timelock { timelock } => {
+            state.change_timelock.save(deps.storage, &timelock)?;
+            Response::new()
+                .add_event(
+                    Event::new("steak/set_change_timelock")
+                        .add_attribute("delay", timelock.delay.to_string())
+                        .add_attribute("expiry", timelock.expiry.to_string()),
+                )
+                .add_attribute("action", "steakhub/set_change_timelock")
+        }
+    };
+
+    let event = Event::new("steakhub/change_accepted").add_attribute("id", id.to_string());
+
+    Ok(response.add_event(event))
+}
+
+pub fn cancel_change(deps: DepsMut, sender: Addr, id: u64) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    if !state.pending_changes.has(deps.storage, id) {
+        return Err(StdError::generic_err(format!("change {} not found", id)));
+    }
+    state.pending_changes.remove(deps.storage, id);
+
+    let event = Event::new("steakhub/change_cancelled").add_attribute("id", id.to_string());
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/cancel_change"))
+}
+
 fn transfer_fee_account_internal(
     deps: DepsMut,
-    fee_account_type: String,
+    fee_account_type: FeeType,
     new_fee_account: String,
 ) -> StdResult<()> {
     let state = State::default();
-    let fee_type = FeeType::from_str(&fee_account_type)
-        .map_err(|_| StdError::generic_err("Invalid Fee type: Wallet or FeeSplit only"))?;
-    state.fee_account_type.save(deps.storage, &fee_type)?;
+    state
+        .fee_account_type
+        .save(deps.storage, &fee_account_type)?;
     state
         .fee_account
         .save(deps.storage, &deps.api.addr_validate(&new_fee_account)?)?;
     Ok(())
 }
 
-pub fn transfer_fee_account(
+fn transfer_fee_account(
     deps: DepsMut,
     sender: Addr,
-    fee_account_type: String,
+    fee_account_type: FeeType,
     new_fee_account: String,
 ) -> StdResult<Response> {
     let state = State::default();
@@ -1060,7 +5991,7 @@ pub fn change_denom(deps: DepsMut, sender: Addr, new_denom: String) -> StdResult
     Ok(Response::new().add_attribute("action", "steakhub/change_denom"))
 }
 
-pub fn update_fee(deps: DepsMut, sender: Addr, new_fee: Decimal) -> StdResult<Response> {
+fn update_fee(deps: DepsMut, sender: Addr, new_fee: Decimal) -> StdResult<Response> {
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
@@ -1074,6 +6005,135 @@ pub fn update_fee(deps: DepsMut, sender: Addr, new_fee: Decimal) -> StdResult<Re
     Ok(Response::new().add_attribute("action", "steakhub/update_fee"))
 }
 
+pub fn update_fee_exemptions(
+    deps: DepsMut,
+    sender: Addr,
+    add: Vec<Addr>,
+    remove: Vec<Addr>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let mut exemptions = state
+        .fee_exemptions
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    exemptions.retain(|holder| !remove.contains(holder));
+    for holder in add {
+        if !exemptions.contains(&holder) {
+            exemptions.push(holder);
+        }
+    }
+    if exemptions.len() > MAX_FEE_EXEMPTIONS {
+        return Err(StdError::generic_err(format!(
+            "at most {} addresses can be exempt from the fee",
+            MAX_FEE_EXEMPTIONS
+        )));
+    }
+    state.fee_exemptions.save(deps.storage, &exemptions)?;
+
+    let event = Event::new("steak/update_fee_exemptions").add_attribute(
+        "exemptions",
+        exemptions
+            .iter()
+            .map(|holder| holder.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+    );
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/update_fee_exemptions"))
+}
+
+pub fn set_mining_params(
+    deps: DepsMut,
+    sender: Addr,
+    difficulty: Option<Uint64>,
+    entropy: Option<String>,
+    enabled: Option<bool>,
+    proof_cooldown_blocks: Option<u64>,
+    power_normalization: Option<MiningPowerNormalization>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let mut events = vec![];
+    if let Some(difficulty) = difficulty {
+        if difficulty.is_zero() || difficulty.u64() > MAX_MINING_DIFFICULTY {
+            return Err(StdError::generic_err(format!(
+                "difficulty must be between 1 and {}",
+                MAX_MINING_DIFFICULTY
+            )));
+        }
+        let previous = state.miner_difficulty.load(deps.storage)?;
+        state.miner_difficulty.save(deps.storage, &difficulty)?;
+        events.push(
+            Event::new("steak/difficulty_retargeted")
+                .add_attribute("previous_difficulty", previous)
+                .add_attribute("difficulty", difficulty),
+        );
+    }
+    if let Some(entropy) = entropy {
+        if entropy.is_empty() {
+            return Err(StdError::generic_err("entropy cannot be empty"));
+        }
+        // Like any other contribution, it only takes effect with the next accepted proof, so the
+        // owner can't pick the entropy miners are working against
+        let next_entropy = mix_entropy_draft(deps.storage, &entropy)?;
+        events.push(
+            Event::new("steak/entropy_rotated").add_attribute("miner_entropy_draft", next_entropy),
+        );
+    }
+    if let Some(enabled) = enabled {
+        state.mining_enabled.save(deps.storage, &enabled)?;
+        events
+            .push(Event::new("steak/mining_enabled").add_attribute("enabled", enabled.to_string()));
+    }
+    if let Some(proof_cooldown_blocks) = proof_cooldown_blocks {
+        state
+            .proof_cooldown_blocks
+            .save(deps.storage, &proof_cooldown_blocks)?;
+        events.push(
+            Event::new("steak/set_proof_cooldown")
+                .add_attribute("proof_cooldown_blocks", proof_cooldown_blocks.to_string()),
+        );
+    }
+    if let Some(power_normalization) = power_normalization {
+        let mut event = Event::new("steak/set_mining_power_normalization");
+        match &power_normalization {
+            MiningPowerNormalization::Disabled => {
+                event = event.add_attribute("normalization", "disabled");
+            }
+            MiningPowerNormalization::Scale { factor } => {
+                if factor.is_zero() || *factor >= Decimal::one() {
+                    return Err(StdError::generic_err(
+                        "mining power scale factor must be between 0 and 1, exclusive",
+                    ));
+                }
+                event = event
+                    .add_attribute("normalization", "scale")
+                    .add_attribute("factor", factor.to_string());
+            }
+            MiningPowerNormalization::Reset { baseline } => {
+                event = event
+                    .add_attribute("normalization", "reset")
+                    .add_attribute("baseline", *baseline);
+            }
+        }
+        state
+            .mining_power_normalization
+            .save(deps.storage, &power_normalization)?;
+        events.push(event);
+    }
+
+    Ok(Response::new()
+        .add_events(events)
+        .add_attribute("action", "steakhub/set_mining_params"))
+}
+
 // update entropy execute function
 pub fn update_entropy(
     deps: DepsMut,
@@ -1081,23 +6141,7 @@ pub fn update_entropy(
     _sender: Addr,
     entropy: String,
 ) -> StdResult<Response> {
-    let state = State::default();
-
-    let next_entropy =
-        state
-            .miner_entropy_draft
-            .update(deps.storage, |entropy_draft| -> StdResult<String> {
-                // use sha2 to hash the entropy
-                let mut hasher = Sha256::new();
-                hasher.update(entropy_draft);
-                hasher.update(entropy);
-                let result = hasher.finalize();
-                // convert the hash to a hex string
-                let entropy_hash = hex::encode(result);
-                // convert bytes to string
-                let entropy_hash = String::from_utf8(entropy_hash.as_bytes().to_vec())?;
-                Ok(entropy_hash)
-            })?;
+    let next_entropy = mix_entropy_draft(deps.storage, &entropy)?;
 
     update_difficulty(deps.storage, env.block.time.seconds(), false)?;
 
@@ -1106,6 +6150,27 @@ pub fn update_entropy(
         .add_attribute("miner_entropy_draft", next_entropy))
 }
 
+/// Hash `entropy` into the draft that becomes the miner entropy at the next accepted proof,
+/// returning the new draft
+fn mix_entropy_draft(storage: &mut dyn Storage, entropy: &str) -> StdResult<String> {
+    let state = State::default();
+
+    state
+        .miner_entropy_draft
+        .update(storage, |entropy_draft| -> StdResult<String> {
+            // use sha2 to hash the entropy
+            let mut hasher = Sha256::new();
+            hasher.update(entropy_draft);
+            hasher.update(entropy);
+            let result = hasher.finalize();
+            // convert the hash to a hex string
+            let entropy_hash = hex::encode(result);
+            // convert bytes to string
+            let entropy_hash = String::from_utf8(entropy_hash.as_bytes().to_vec())?;
+            Ok(entropy_hash)
+        })
+}
+
 pub fn create_difficulty_prefix(difficulty: Uint64) -> String {
     // validate difficulty
     let mut difficulty_string = String::new();
@@ -1198,14 +6263,46 @@ pub fn submit_proof(
     validator_address: String,
 ) -> StdResult<Response> {
     let state = State::default();
+    if !state.is_mining_enabled(deps.storage)? {
+        return Err(StdError::generic_err("mining is disabled"));
+    }
     let validator = deps
         .querier
         .query_validator(validator_address)?
         .ok_or_else(|| StdError::generic_err("validator address not found in staking module"))?;
+    if !state
+        .load_validators_active(deps.storage, env.block.time.seconds())?
+        .contains(&validator.address)
+    {
+        return Err(StdError::generic_err(
+            "validator is not whitelisted or is paused",
+        ));
+    }
+    // A single miner may not submit proofs back to back and take over the mining power
+    let proof_cooldown_blocks = state
+        .proof_cooldown_blocks
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if let Some(last_proof_height) = state.last_proof_height.may_load(deps.storage, &sender)? {
+        let next_proof_height = last_proof_height + proof_cooldown_blocks;
+        if env.block.height < next_proof_height {
+            return Err(StdError::generic_err(format!(
+                "miner must wait until block {} to submit another proof",
+                next_proof_height
+            )));
+        }
+    }
     let miner_entropy = state.miner_entropy.load(deps.storage)?;
     let miner_entropy_draft = state.miner_entropy_draft.load(deps.storage)?;
     let fee_account_type = state.fee_account_type.load(deps.storage)?;
-    let difficulty = state.miner_difficulty.load(deps.storage)?;
+    // Validators with their own difficulty ignore the global one
+    let difficulty = match state
+        .validator_mining_difficulties
+        .may_load(deps.storage, validator.address.clone())?
+    {
+        Some(difficulty) => difficulty,
+        None => state.miner_difficulty.load(deps.storage)?,
+    };
     let miner_last_mined_block = state
         .miner_last_mined_block
         .load(deps.storage)
@@ -1272,6 +6369,9 @@ pub fn submit_proof(
     state
         .miner_last_mined_block
         .save(deps.storage, &env.block.height.into())?;
+    state
+        .last_proof_height
+        .save(deps.storage, &sender, &env.block.height)?;
 
     // set fee account
     if fee_account_type != FeeType::Wallet {
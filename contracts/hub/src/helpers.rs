@@ -1,18 +1,243 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::str::FromStr;
 
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::PageRequest;
+use cosmos_sdk_proto::cosmos::base::v1beta1::DecCoin;
+use cosmos_sdk_proto::cosmos::distribution::v1beta1::{
+    QueryDelegationTotalRewardsRequest, QueryDelegationTotalRewardsResponse,
+    QueryParamsRequest as QueryDistributionParamsRequest,
+    QueryParamsResponse as QueryDistributionParamsResponse,
+};
+use cosmos_sdk_proto::cosmos::mint::v1beta1::{
+    QueryAnnualProvisionsRequest, QueryAnnualProvisionsResponse,
+};
+use cosmos_sdk_proto::cosmos::staking::v1beta1::{
+    QueryDelegatorUnbondingDelegationsRequest, QueryDelegatorUnbondingDelegationsResponse,
+    QueryParamsRequest, QueryParamsResponse, QueryPoolRequest, QueryPoolResponse,
+    QueryValidatorRequest, QueryValidatorResponse,
+};
 use cosmwasm_std::{
-    Addr, BalanceResponse, BankQuery, Coin, CosmosMsg, QuerierWrapper, QueryRequest, Reply,
-    StdError, StdResult, SubMsgResponse, Uint128,
+    to_vec, Addr, BalanceResponse, BankQuery, Binary, Coin, ContractResult, CosmosMsg, Decimal,
+    Deps, Empty, Env, Order, QuerierWrapper, QueryRequest, Reply, Response, StdError, StdResult,
+    Storage, SubMsgResponse, SystemResult, Uint128,
 };
 use cw20::{Cw20QueryMsg, TokenInfoResponse};
+use sha2::{Digest, Sha256};
 
-use crate::types::Delegation;
+use pfc_steak::hub::{Batch, BatchShortfall, ReconcileMode};
+
+use crate::math::reconcile_batches;
+use crate::state::State;
+use crate::types::{Coins, Delegation};
 
 /// Unwrap a `Reply` object to extract the response
 pub(crate) fn unwrap_reply(reply: Reply) -> StdResult<SubMsgResponse> {
     reply.result.into_result().map_err(StdError::generic_err)
 }
 
+/// The batches that finished unbonding but have not been reconciled, as `reconcile` would leave
+/// them, along with the amount of native deducted from them to cover any shortfall (e.g. slashing).
+/// With `only`, just those batches are reconciled, and deducted no more than their share of the
+/// shortfall
+pub(crate) fn compute_reconciled_batches(
+    deps: Deps,
+    env: &Env,
+    only: Option<&[u64]>,
+) -> StdResult<(Vec<Batch>, Uint128)> {
+    let state = State::default();
+    let current_time = env.block.time.seconds();
+    let denom = state.denom.load(deps.storage)?;
+
+    // Load batches that have not been reconciled
+    let all_batches = state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(false.into())
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok(((batch_denom, _), v)) => (batch_denom == denom).then(|| Ok(v)),
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut batches = all_batches
+        .into_iter()
+        .filter(|b| current_time > b.est_unbond_end_time)
+        .collect::<Vec<_>>();
+
+    // A batch may still be unbonding past its estimated end, e.g. if the chain's unbonding time
+    // grew, and deducting its amount from the others would take it for a shortfall. Leave out the
+    // batches with an undelegation the staking module still holds. Those submitted before
+    // completion times were recorded can only go by the estimate
+    let reconcile_mode = state
+        .reconcile_mode
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if reconcile_mode == ReconcileMode::UnbondingEntries {
+        let entries =
+            query_unbonding_entries(&deps.querier, &env.contract.address).ok_or_else(|| {
+                StdError::generic_err("chain doesn't allow querying unbonding delegations")
+            })?;
+        let mut received = vec![];
+        for batch in batches {
+            let completion_times = state
+                .unbond_completion_times
+                .prefix(batch.id)
+                .range(deps.storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?;
+            if !completion_times.iter().any(|entry| entries.contains(entry)) {
+                received.push(batch);
+            }
+        }
+        batches = received;
+    }
+    if batches.is_empty() {
+        return Ok((batches, Uint128::zero()));
+    }
+
+    let native_expected_received: Uint128 = batches.iter().map(|b| b.amount_unclaimed).sum();
+    let native_expected = native_owed(deps.storage, &denom, native_expected_received)?;
+    let native_actual = deps
+        .querier
+        .query_balance(&env.contract.address, &denom)?
+        .amount;
+
+    let mut native_to_deduct = native_expected.saturating_sub(native_actual);
+
+    // With `only`, the other batches are left as they are, for a later reconciliation to take
+    // their share of the shortfall; the hub still counts what they're due as owed until then
+    if let Some(ids) = only {
+        let batch_count = batches.len();
+        batches.retain(|b| ids.contains(&b.id));
+        native_to_deduct =
+            native_to_deduct.multiply_ratio(batches.len() as u128, batch_count as u128);
+    }
+
+    if !batches.is_empty() && !native_to_deduct.is_zero() {
+        let amounts_before: Vec<Uint128> = batches.iter().map(|b| b.amount_unclaimed).collect();
+        reconcile_batches(&mut batches, native_to_deduct);
+        for (batch, amount_before) in batches.iter_mut().zip(amounts_before) {
+            let amount = amount_before - batch.amount_unclaimed;
+            if !amount.is_zero() {
+                batch.shortfalls.push(BatchShortfall {
+                    time: current_time,
+                    amount,
+                });
+            }
+        }
+    }
+
+    for batch in batches.iter_mut() {
+        batch.reconciled = true;
+    }
+
+    Ok((batches, native_to_deduct))
+}
+
+/// Native Token the hub holds on someone's behalf: batches of `denom` reconciled but not yet
+/// withdrawn from, what's due to batches not reconciled yet as the caller counts it, unlocked coins,
+/// and everything in escrow, donations included. Whatever the hub holds beyond this, it wasn't
+/// expecting
+pub(crate) fn native_owed(
+    storage: &dyn Storage,
+    denom: &str,
+    native_due_batches: Uint128,
+) -> StdResult<Uint128> {
+    let state = State::default();
+
+    let native_owed_batches = state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(true.into())
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|((batch_denom, _), batch)| match batch_denom == denom {
+                true => batch.amount_unclaimed,
+                false => Uint128::zero(),
+            })
+        })
+        .sum::<StdResult<Uint128>>()?;
+
+    Ok(native_owed_batches
+        + native_due_batches
+        + Coins(state.unlocked_coins.load(storage)?).get(denom)
+        + state
+            .referral_unclaimed
+            .may_load(storage)?
+            .unwrap_or_default()
+        + state
+            .reward_campaign_escrow
+            .may_load(storage)?
+            .unwrap_or_default()
+        + state.fee_escrow.may_load(storage)?.unwrap_or_default()
+        + state.donations.may_load(storage)?.unwrap_or_default()
+        + state.treasury_escrow.may_load(storage)?.unwrap_or_default()
+        + state.reinvest_carry.may_load(storage)?.unwrap_or_default())
+}
+
+/// Native Token owed to batches of `denom` not reconciled yet that have finished unbonding: past
+/// their estimated end, or with every undelegation the staking module reported complete by `time`.
+/// Batches still unbonding are owed nothing yet
+pub(crate) fn native_due_unreconciled(
+    storage: &dyn Storage,
+    denom: &str,
+    time: u64,
+) -> StdResult<Uint128> {
+    let state = State::default();
+
+    let batches = state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(false.into())
+        .range(storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok(((batch_denom, _), batch)) => (batch_denom == denom).then(|| Ok(batch)),
+            Err(err) => Some(Err(err)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut native_due = Uint128::zero();
+    for batch in batches {
+        let due = if time > batch.est_unbond_end_time {
+            true
+        } else {
+            let completion_times = state
+                .unbond_completion_times
+                .prefix(batch.id)
+                .range(storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, completion_time)| completion_time))
+                .collect::<StdResult<Vec<_>>>()?;
+            !completion_times.is_empty() && completion_times.iter().all(|t| *t <= time)
+        };
+        if due {
+            native_due += batch.amount_unclaimed;
+        }
+    }
+    Ok(native_due)
+}
+
+/// Refuse a response sending more messages than the owner allows a single execute to. The
+/// staking module charges gas per message, so an execute past the cap risks never fitting in a
+/// block; better to fail up front than to leave the hub with an operation nobody can carry out
+pub(crate) fn assert_message_cap(storage: &dyn Storage, res: &Response) -> StdResult<()> {
+    let state = State::default();
+
+    match state.max_messages.may_load(storage)? {
+        Some(max_messages) if res.messages.len() > max_messages as usize => {
+            Err(StdError::generic_err(format!(
+                "execute would send {} messages, more than the maximum of {}",
+                res.messages.len(),
+                max_messages
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Query the total supply of a CW20 token
 pub(crate) fn query_cw20_total_supply(
     querier: &QuerierWrapper,
@@ -23,6 +248,51 @@ pub(crate) fn query_cw20_total_supply(
     Ok(token_info.total_supply)
 }
 
+/// Query the total supply of Steak: the cw20 token, plus the token factory denom once the token
+/// has been migrated. Holders swap at their own pace, so both may be outstanding at once
+pub(crate) fn query_usteak_supply(
+    querier: &QuerierWrapper,
+    storage: &dyn Storage,
+) -> StdResult<Uint128> {
+    let state = State::default();
+    let steak_token = state.steak_token.load(storage)?;
+    let denom_supply = state
+        .usteak_denom_supply
+        .may_load(storage)?
+        .unwrap_or_default();
+    Ok(query_cw20_total_supply(querier, &steak_token)? + denom_supply)
+}
+
+/// Query the Steak balance of an account, in either form
+pub(crate) fn query_usteak_balance(
+    querier: &QuerierWrapper,
+    storage: &dyn Storage,
+    account: &Addr,
+) -> StdResult<Uint128> {
+    let state = State::default();
+    let steak_token = state.steak_token.load(storage)?;
+    let mut balance = query_cw20_balance(querier, &steak_token, account)?;
+    if let Some(usteak_denom) = state.usteak_denom.may_load(storage)? {
+        balance += querier.query_balance(account, usteak_denom)?.amount;
+    }
+    Ok(balance)
+}
+
+/// Query the CW20 token balance of an account
+pub(crate) fn query_cw20_balance(
+    querier: &QuerierWrapper,
+    token_addr: &Addr,
+    account: &Addr,
+) -> StdResult<Uint128> {
+    let res: cw20::BalanceResponse = querier.query_wasm_smart(
+        token_addr,
+        &Cw20QueryMsg::Balance {
+            address: account.to_string(),
+        },
+    )?;
+    Ok(res.balance)
+}
+
 /// Query the amounts of Native Token a staker is delegating to a specific validator
 pub(crate) fn query_delegation(
     querier: &QuerierWrapper,
@@ -53,6 +323,363 @@ pub(crate) fn query_delegations(
         .collect()
 }
 
+/// Query the delegations to validators removed from the whitelist but not yet drained by
+/// `ContinueRebalance`, which count towards the Native Token bonded alongside `validators`
+pub(crate) fn query_draining_delegations(
+    querier: &QuerierWrapper,
+    storage: &dyn Storage,
+    delegator_addr: &Addr,
+    denom: &str,
+) -> StdResult<Vec<Delegation>> {
+    let draining = State::default().load_validators_draining(storage)?;
+    query_delegations(querier, &draining, delegator_addr, denom)
+}
+
+/// Query every delegation backing Steak: those to the whitelisted validators, paused or not, and to
+/// removed validators still being drained. The exchange rate is always computed over these
+pub(crate) fn query_bonded_delegations(
+    querier: &QuerierWrapper,
+    storage: &dyn Storage,
+    delegator_addr: &Addr,
+    denom: &str,
+) -> StdResult<Vec<Delegation>> {
+    let validators = State::default().validators.load(storage)?;
+    let mut delegations = query_delegations(querier, &validators, delegator_addr, denom)?;
+    delegations.extend(query_draining_delegations(
+        querier,
+        storage,
+        delegator_addr,
+        denom,
+    )?);
+    Ok(delegations)
+}
+
+/// `amount` of the bond denom in whole units, e.g. `1.5` for 1500000 of a 6-decimal denom, as
+/// minimums, thresholds and fees are shown in events
+pub(crate) fn format_denom_amount(amount: Uint128, decimals: u8) -> String {
+    let unit = 10u128.pow(decimals as u32);
+    let whole = amount.u128() / unit;
+    let fraction = amount.u128() % unit;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let fraction = format!("{:0width$}", fraction, width = decimals as usize);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Path of the staking module's gRPC query for a single validator
+pub(crate) const VALIDATOR_QUERY_PATH: &str = "/cosmos.staking.v1beta1.Query/Validator";
+
+/// Query the total amount of Native Token bonded to a validator, and whether it is jailed. The
+/// CosmWasm staking query doesn't expose either, so this goes through a Stargate query, which not
+/// every chain allows; `None` if the query is refused or the validator isn't found
+pub(crate) fn query_validator_tokens(
+    querier: &QuerierWrapper,
+    validator: &str,
+) -> Option<(Uint128, bool)> {
+    let mut data = Vec::new();
+    prost::Message::encode(
+        &QueryValidatorRequest {
+            validator_addr: validator.to_string(),
+        },
+        &mut data,
+    )
+    .ok()?;
+    let request: QueryRequest<Empty> = QueryRequest::Stargate {
+        path: VALIDATOR_QUERY_PATH.to_string(),
+        data: Binary(data),
+    };
+    let response = match querier.raw_query(&to_vec(&request).ok()?) {
+        SystemResult::Ok(ContractResult::Ok(response)) => response,
+        _ => return None,
+    };
+    let validator = <QueryValidatorResponse as prost::Message>::decode(response.as_slice())
+        .ok()?
+        .validator?;
+    Some((Uint128::from_str(&validator.tokens).ok()?, validator.jailed))
+}
+
+/// Path of the staking module's gRPC query for its parameters
+pub(crate) const STAKING_PARAMS_QUERY_PATH: &str = "/cosmos.staking.v1beta1.Query/Params";
+
+/// Query the staking module's unbonding time, in seconds, which may differ from the hub's
+/// configured unbond period. `None` if the chain doesn't allow the Stargate query
+pub(crate) fn query_chain_unbonding_time(querier: &QuerierWrapper) -> Option<u64> {
+    let mut data = Vec::new();
+    prost::Message::encode(&QueryParamsRequest {}, &mut data).ok()?;
+    let request: QueryRequest<Empty> = QueryRequest::Stargate {
+        path: STAKING_PARAMS_QUERY_PATH.to_string(),
+        data: Binary(data),
+    };
+    let response = match querier.raw_query(&to_vec(&request).ok()?) {
+        SystemResult::Ok(ContractResult::Ok(response)) => response,
+        _ => return None,
+    };
+    let unbonding_time = <QueryParamsResponse as prost::Message>::decode(response.as_slice())
+        .ok()?
+        .params?
+        .unbonding_time?;
+    u64::try_from(unbonding_time.seconds).ok()
+}
+
+/// Path of the mint module's gRPC query for the tokens minted per year at the current inflation
+pub(crate) const ANNUAL_PROVISIONS_QUERY_PATH: &str = "/cosmos.mint.v1beta1.Query/AnnualProvisions";
+
+/// Path of the distribution module's gRPC query for its parameters
+pub(crate) const DISTRIBUTION_PARAMS_QUERY_PATH: &str = "/cosmos.distribution.v1beta1.Query/Params";
+
+/// Path of the staking module's gRPC query for the amounts of bonded and unbonded tokens
+pub(crate) const STAKING_POOL_QUERY_PATH: &str = "/cosmos.staking.v1beta1.Query/Pool";
+
+/// Send a Stargate query and decode its response. `None` if the chain refuses the query
+fn query_stargate<T: prost::Message + Default>(
+    querier: &QuerierWrapper,
+    path: &str,
+    request: &impl prost::Message,
+) -> Option<T> {
+    let request: QueryRequest<Empty> = QueryRequest::Stargate {
+        path: path.to_string(),
+        data: Binary(request.encode_to_vec()),
+    };
+    let response = match querier.raw_query(&to_vec(&request).ok()?) {
+        SystemResult::Ok(ContractResult::Ok(response)) => response,
+        _ => return None,
+    };
+    T::decode(response.as_slice()).ok()
+}
+
+/// Staking rewards paid per year per token bonded, before validators take their commission: the
+/// annual provisions left after the community tax, over the bonded tokens. Fees and other
+/// rewards are left out. `None` if the chain doesn't allow the Stargate queries, e.g. because it
+/// has no mint module
+pub(crate) fn query_staking_apr(querier: &QuerierWrapper) -> Option<Decimal> {
+    let annual_provisions: QueryAnnualProvisionsResponse = query_stargate(
+        querier,
+        ANNUAL_PROVISIONS_QUERY_PATH,
+        &QueryAnnualProvisionsRequest {},
+    )?;
+    let annual_provisions =
+        parse_sdk_whole_units(std::str::from_utf8(&annual_provisions.annual_provisions).ok()?)?;
+    let distribution_params: QueryDistributionParamsResponse = query_stargate(
+        querier,
+        DISTRIBUTION_PARAMS_QUERY_PATH,
+        &QueryDistributionParamsRequest {},
+    )?;
+    let community_tax = parse_sdk_decimal(&distribution_params.params?.community_tax)?;
+    let pool: QueryPoolResponse =
+        query_stargate(querier, STAKING_POOL_QUERY_PATH, &QueryPoolRequest {})?;
+    let bonded_tokens = Uint128::from_str(&pool.pool?.bonded_tokens).ok()?;
+
+    if bonded_tokens.is_zero() || community_tax > Decimal::one() {
+        return None;
+    }
+    let staking_rewards = annual_provisions * (Decimal::one() - community_tax);
+    Some(Decimal::from_ratio(staking_rewards, bonded_tokens))
+}
+
+/// Decimal from a gRPC response. The SDK encodes decimals as integers scaled by 10^18, but some
+/// chains send them with a decimal point
+fn parse_sdk_decimal(s: &str) -> Option<Decimal> {
+    if s.contains('.') {
+        Decimal::from_str(s).ok()
+    } else {
+        Some(Decimal::raw(u128::from_str(s).ok()?))
+    }
+}
+
+/// Decimal from a gRPC response, truncated to whole units, for amounts too large for `Decimal`
+fn parse_sdk_whole_units(s: &str) -> Option<Uint128> {
+    let whole = match s.split_once('.') {
+        Some((whole, _)) => whole,
+        None => &s[..s.len().saturating_sub(18)],
+    };
+    if whole.is_empty() {
+        Some(Uint128::zero())
+    } else {
+        Uint128::from_str(whole).ok()
+    }
+}
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+fn bech32_polymod(values: impl Iterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn bech32_hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|b| b >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|b| b & 31))
+}
+
+/// Encode 5-bit words under `hrp`, appending the checksum
+fn bech32_encode(hrp: &str, payload: &[u8]) -> String {
+    let checksum = bech32_polymod(
+        bech32_hrp_expand(hrp)
+            .chain(payload.iter().copied())
+            .chain([0u8; 6].iter().copied()),
+    ) ^ 1;
+    let mut address = format!("{}1", hrp);
+    for value in payload {
+        address.push(BECH32_CHARSET[*value as usize] as char);
+    }
+    for i in 0..6 {
+        address.push(BECH32_CHARSET[((checksum >> (5 * (5 - i))) & 31) as usize] as char);
+    }
+    address
+}
+
+/// Account address of a validator's operator, e.g. `juno1...` for `junovaloper1...`: the same
+/// bytes under the chain's account prefix. The hub doesn't link a bech32 library, so the checksum
+/// is computed here
+pub(crate) fn operator_account_address(validator: &str) -> StdResult<String> {
+    let invalid =
+        || StdError::generic_err(format!("{} is not a validator operator address", validator));
+    let (hrp, data) = validator.rsplit_once('1').ok_or_else(invalid)?;
+    let account_hrp = hrp
+        .strip_suffix("valoper")
+        .filter(|prefix| !prefix.is_empty())
+        .ok_or_else(invalid)?;
+    let values = data
+        .bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|x| *x == c).map(|i| i as u8))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(invalid)?;
+    if values.len() < 6 || bech32_polymod(bech32_hrp_expand(hrp).chain(values.iter().copied())) != 1
+    {
+        return Err(invalid());
+    }
+
+    Ok(bech32_encode(account_hrp, &values[..values.len() - 6]))
+}
+
+/// Query the amount of Native Token a validator's operator delegates to it
+pub(crate) fn query_self_delegation(
+    querier: &QuerierWrapper,
+    validator: &str,
+) -> StdResult<Uint128> {
+    let operator = operator_account_address(validator)?;
+    Ok(querier
+        .query_delegation(operator, validator)?
+        .map(|delegation| delegation.amount.amount)
+        .unwrap_or_default())
+}
+
+/// Path of the staking module's gRPC query for a delegator's unbonding delegations
+pub(crate) const DELEGATOR_UNBONDING_DELEGATIONS_QUERY_PATH: &str =
+    "/cosmos.staking.v1beta1.Query/DelegatorUnbondingDelegations";
+
+/// Query the validator and completion time, in seconds, of each of `delegator`'s unbonding
+/// delegation entries that hasn't matured yet. `None` if the chain doesn't allow the Stargate query
+pub(crate) fn query_unbonding_entries(
+    querier: &QuerierWrapper,
+    delegator: &Addr,
+) -> Option<Vec<(String, u64)>> {
+    let mut entries = vec![];
+    let mut key = vec![];
+    loop {
+        let mut data = Vec::new();
+        prost::Message::encode(
+            &QueryDelegatorUnbondingDelegationsRequest {
+                delegator_addr: delegator.to_string(),
+                pagination: Some(PageRequest {
+                    key,
+                    ..Default::default()
+                }),
+            },
+            &mut data,
+        )
+        .ok()?;
+        let request: QueryRequest<Empty> = QueryRequest::Stargate {
+            path: DELEGATOR_UNBONDING_DELEGATIONS_QUERY_PATH.to_string(),
+            data: Binary(data),
+        };
+        let response = match querier.raw_query(&to_vec(&request).ok()?) {
+            SystemResult::Ok(ContractResult::Ok(response)) => response,
+            _ => return None,
+        };
+        let response = <QueryDelegatorUnbondingDelegationsResponse as prost::Message>::decode(
+            response.as_slice(),
+        )
+        .ok()?;
+        for unbonding in response.unbonding_responses {
+            for entry in unbonding.entries {
+                let completion_time = u64::try_from(entry.completion_time?.seconds).ok()?;
+                entries.push((unbonding.validator_address.clone(), completion_time));
+            }
+        }
+        match response.pagination {
+            Some(pagination) if !pagination.next_key.is_empty() => key = pagination.next_key,
+            _ => return Some(entries),
+        }
+    }
+}
+
+/// Path of the distribution module's gRPC query for the rewards pending on a delegator's delegations
+pub(crate) const DELEGATION_TOTAL_REWARDS_QUERY_PATH: &str =
+    "/cosmos.distribution.v1beta1.Query/DelegationTotalRewards";
+
+/// Query the rewards in `denom` pending on each of `delegator`'s delegations, in whole units, by
+/// validator. `None` if the chain doesn't allow the Stargate query
+pub(crate) fn query_pending_rewards(
+    querier: &QuerierWrapper,
+    delegator: &Addr,
+    denom: &str,
+) -> Option<HashMap<String, Uint128>> {
+    let mut data = Vec::new();
+    prost::Message::encode(
+        &QueryDelegationTotalRewardsRequest {
+            delegator_address: delegator.to_string(),
+        },
+        &mut data,
+    )
+    .ok()?;
+    let request: QueryRequest<Empty> = QueryRequest::Stargate {
+        path: DELEGATION_TOTAL_REWARDS_QUERY_PATH.to_string(),
+        data: Binary(data),
+    };
+    let response = match querier.raw_query(&to_vec(&request).ok()?) {
+        SystemResult::Ok(ContractResult::Ok(response)) => response,
+        _ => return None,
+    };
+    let response =
+        <QueryDelegationTotalRewardsResponse as prost::Message>::decode(response.as_slice())
+            .ok()?;
+    response
+        .rewards
+        .into_iter()
+        .map(|r| {
+            let amount = match r.reward.iter().find(|coin| coin.denom == denom) {
+                Some(coin) => parse_dec_coin_amount(coin)?,
+                None => Uint128::zero(),
+            };
+            Some((r.validator_address, amount))
+        })
+        .collect()
+}
+
+/// Amount of a `DecCoin` from a gRPC response, truncated to whole units. The SDK encodes decimals
+/// there as integers scaled by 10^18, but some chains send them with a decimal point
+fn parse_dec_coin_amount(coin: &DecCoin) -> Option<Uint128> {
+    if coin.amount.contains('.') {
+        Some(Uint128::new(1) * Decimal::from_str(&coin.amount).ok()?)
+    } else {
+        Some(Uint128::from_str(&coin.amount).ok()? / Uint128::new(1_000_000_000_000_000_000))
+    }
+}
+
 /// `cosmwasm_std::Coin` does not implement `FromStr`, so we have do it ourselves
 ///
 /// Parsing the string with regex doesn't work, because the resulting binary would be too big for
@@ -103,6 +730,114 @@ pub(crate) fn parse_received_fund(funds: &[Coin], denom: &str) -> StdResult<Uint
     Ok(fund.amount)
 }
 
+/// Parse the deposit of `denom`, and when deposits are measured, credit no more than what the
+/// contract's balance shows to have actually arrived: the balance beyond the Native Token the hub
+/// already owes to unlocked coins, referral rewards and finished unbonding batches. Any surplus
+/// (e.g. rewards withdrawn but not yet registered) can't inflate the deposit past `info.funds`
+pub(crate) fn measure_received_fund(
+    deps: Deps,
+    env: &Env,
+    funds: &[Coin],
+    denom: &str,
+) -> StdResult<Uint128> {
+    let state = State::default();
+    let amount_sent = parse_received_fund(funds, denom)?;
+    if !state
+        .measure_deposits
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+    {
+        return Ok(amount_sent);
+    }
+
+    let native_due_batches =
+        native_due_unreconciled(deps.storage, denom, env.block.time.seconds())?;
+    let native_owed = native_owed(deps.storage, denom, native_due_batches)?;
+
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, denom)?
+        .amount;
+    let amount_received = balance.saturating_sub(native_owed).min(amount_sent);
+    if amount_received.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "none of the {} deposit reached the contract",
+            denom
+        )));
+    }
+
+    Ok(amount_received)
+}
+
+/// Parse an RFC 3339 timestamp, as the staking module reports an undelegation's completion time in
+/// (e.g. `2022-08-01T12:34:56.789Z`), into seconds since the Unix epoch. Fractions of a second are
+/// dropped.
+///
+/// Like `parse_coin`, this is done by hand to keep a date library out of the binary.
+pub(crate) fn parse_rfc3339_seconds(s: &str) -> StdResult<u64> {
+    let invalid = || StdError::generic_err(format!("failed to parse timestamp: {}", s));
+    let number = |range: std::ops::Range<usize>| -> StdResult<i64> {
+        s.get(range)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(invalid)
+    };
+
+    let bytes = s.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't' | b' ')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return Err(invalid());
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return Err(invalid());
+    }
+
+    let mut rest = &s[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return Err(invalid());
+        }
+        rest = &fraction[digits..];
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return Err(invalid()),
+            };
+            let offset = s.len() - 6;
+            sign * (number(offset + 1..offset + 3)? * 3600 + number(offset + 4..offset + 6)? * 60)
+        }
+        _ => return Err(invalid()),
+    };
+
+    // Days since the epoch of the civil date; see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second - offset;
+    u64::try_from(seconds).map_err(|_| invalid())
+}
+
 pub fn get_denom_balance(
     querier: &QuerierWrapper,
     account_addr: Addr,
@@ -126,3 +861,37 @@ pub(crate) fn proto_encode<M: prost::Message>(msg: M, type_url: String) -> StdRe
         value: cosmwasm_std::Binary(bytes),
     })
 }
+
+/// Prefix `ibc_hooks` hashes into the address of the sender it executes wasm memos from
+const IBC_HOOKS_SENDER_PREFIX: &str = "ibc-wasm-hook-intermediary";
+
+/// The sender `ibc_hooks` executes the memo of an ICS-20 transfer from, as in its
+/// `DeriveIntermediateSender`: `sha256(sha256(IBC_HOOKS_SENDER_PREFIX) ++ "{channel}/{original_sender}")`
+/// under the chain's account prefix. `channel` is the one on this chain the transfer arrived over
+pub(crate) fn derive_intermediate_sender(
+    channel: &str,
+    original_sender: &str,
+    bech32_prefix: &str,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(Sha256::digest(IBC_HOOKS_SENDER_PREFIX.as_bytes()));
+    hasher.update(format!("{}/{}", channel, original_sender).as_bytes());
+
+    // Regroup the hash into 5-bit words, padding the last one with zeros
+    let mut payload = vec![];
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    for byte in hasher.finalize() {
+        acc = (acc << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            payload.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        payload.push(((acc << (5 - bits)) & 31) as u8);
+    }
+
+    bech32_encode(bech32_prefix, &payload)
+}
@@ -1,11 +1,13 @@
 #[cfg(not(feature = "library"))]
 pub mod contract;
 
+pub mod debug;
 pub mod execute;
 pub mod helpers;
 pub mod math;
 pub mod queries;
 pub mod state;
+pub mod treasury;
 pub mod types;
 
 mod migrations;
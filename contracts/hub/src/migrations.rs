@@ -1,8 +1,10 @@
-use crate::state::{State, BATCH_KEY_V101};
+use crate::state::{
+    State, BATCH_KEY_RECONCILED_V101, BATCH_KEY_V101, BATCH_KEY_V102, PENDING_BATCH_KEY_V101,
+};
 use crate::types::BooleanKey;
-use cosmwasm_std::{Addr, Order, QuerierWrapper, StdError, StdResult, Storage, Uint128};
-use cw_storage_plus::{Index, IndexList, IndexedMap, MultiIndex};
-use pfc_steak::hub::Batch;
+use cosmwasm_std::{Addr, Decimal, Order, QuerierWrapper, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use pfc_steak::hub::{Batch, PendingBatch};
 
 use crate::helpers::get_denom_balance;
 use serde::{Deserialize, Serialize};
@@ -50,9 +52,10 @@ impl ConfigV100 {
                 IndexedMap::new(BATCH_KEY_V100, pb_indexes_v100);
             let state = State::default();
             let denom = state.denom.load(storage)?;
-            state
-                .prev_denom
-                .save(storage, &get_denom_balance(querier, contract_addr, denom)?)?;
+            state.prev_denom.save(
+                storage,
+                &get_denom_balance(querier, contract_addr, denom.clone())?,
+            )?;
 
             let old_batches = old
                 .range(storage, None, None, Order::Ascending)
@@ -72,8 +75,16 @@ impl ConfigV100 {
                             total_shares: v.total_shares,
                             amount_unclaimed: v.native_token_unclaimed,
                             est_unbond_end_time: v.est_unbond_end_time,
+                            fee_rate: None,
+                            exchange_rate: None,
+                            submitter: None,
+                            shortfalls: vec![],
+                            backfilled: false,
                         };
-                        state.previous_batches.save(storage, v.id, &batch).unwrap();
+                        state
+                            .previous_batches
+                            .save(storage, (&denom, v.id), &batch)
+                            .unwrap();
                     }
                     //  Ok(v)
                 });
@@ -96,3 +107,119 @@ impl<'a> IndexList<BatchV100> for PreviousBatchesIndexesV100<'a> {
         Box::new(v.into_iter())
     }
 }
+
+pub(crate) struct PreviousBatchesIndexesV101<'a> {
+    // pk goes to second tuple element
+    pub reconciled: MultiIndex<'a, BooleanKey, Batch, Vec<u8>>,
+}
+
+impl<'a> IndexList<Batch> for PreviousBatchesIndexesV101<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Batch>> + '_> {
+        let v: Vec<&dyn Index<Batch>> = vec![&self.reconciled];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Move the pending batch and the previous batches stored before batches were keyed by denom
+/// under the hub's denom. Returns the number of previous batches moved
+pub fn key_batches_by_denom(storage: &mut dyn Storage) -> StdResult<u64> {
+    if BATCH_KEY_V101 == BATCH_KEY_V102 {
+        return Err(StdError::generic_err(
+            "STEAK: Migration Failed. Batch keys are the same",
+        ));
+    }
+    let state = State::default();
+    let denom = state.denom.load(storage)?;
+
+    let old_pending_batch: Item<PendingBatch> = Item::new(PENDING_BATCH_KEY_V101);
+    if let Some(pending_batch) = old_pending_batch.may_load(storage)? {
+        state
+            .pending_batches
+            .save(storage, &denom, &pending_batch)?;
+        old_pending_batch.remove(storage);
+    }
+
+    let old: IndexedMap<'_, u64, Batch, PreviousBatchesIndexesV101<'_>> = IndexedMap::new(
+        BATCH_KEY_V101,
+        PreviousBatchesIndexesV101 {
+            reconciled: MultiIndex::new(
+                |d: &Batch| d.reconciled.into(),
+                BATCH_KEY_V101,
+                BATCH_KEY_RECONCILED_V101,
+            ),
+        },
+    );
+    let old_batches = old
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let count = old_batches.len() as u64;
+    for (id, batch) in old_batches {
+        state.previous_batches.save(storage, (&denom, id), &batch)?;
+        old.remove(storage, id)?;
+    }
+    Ok(count)
+}
+
+/// Fill in the snapshots missing from batches submitted before they were recorded, marking them as
+/// backfilled. The fee rate is taken to be the current one. The exchange rate is what the batch
+/// pays per share, which for a batch not reconciled yet is the rate it was submitted at. Returns
+/// the number of batches backfilled
+pub fn backfill_batches(storage: &mut dyn Storage) -> StdResult<u64> {
+    let state = State::default();
+    let fee_rate = state.fee_rate.load(storage)?;
+    let denom = state.denom.load(storage)?;
+
+    let legacy_batches = state
+        .previous_batches
+        .prefix(&denom)
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, batch)) => {
+                !batch.backfilled && (batch.fee_rate.is_none() || batch.exchange_rate.is_none())
+            }
+            Err(_) => true,
+        })
+        .map(|item| item.map(|(_, batch)| batch))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let count = legacy_batches.len() as u64;
+    for mut batch in legacy_batches {
+        batch.fee_rate = batch.fee_rate.or(Some(fee_rate));
+        if batch.exchange_rate.is_none() && !batch.total_shares.is_zero() {
+            batch.exchange_rate = Some(Decimal::from_ratio(
+                batch.amount_unclaimed,
+                batch.total_shares,
+            ));
+        }
+        batch.backfilled = true;
+        state
+            .previous_batches
+            .save(storage, (&denom, batch.id), &batch)?;
+    }
+    Ok(count)
+}
+
+/// Count the previous batches of the bond denom and the Native Token they hold unclaimed, which
+/// hubs keep track of as batches come and go since `QueryMsg::Metrics` stopped going through them
+pub fn count_previous_batches(storage: &mut dyn Storage) -> StdResult<u64> {
+    let state = State::default();
+    let denom = state.denom.load(storage)?;
+
+    let mut num_batches = 0u64;
+    let mut native_unclaimed = Uint128::zero();
+    for item in state
+        .previous_batches
+        .prefix(&denom)
+        .range(storage, None, None, Order::Ascending)
+    {
+        let (_, batch) = item?;
+        num_batches += 1;
+        native_unclaimed += batch.amount_unclaimed;
+    }
+    state.num_previous_batches.save(storage, &num_batches)?;
+    state
+        .batches_native_unclaimed
+        .save(storage, &native_unclaimed)?;
+    Ok(num_batches)
+}
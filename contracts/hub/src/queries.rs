@@ -1,18 +1,31 @@
-use cosmwasm_std::{Addr, Decimal, Deps, Env, Order, StdResult, Uint128};
-use cw_storage_plus::{Bound, CwIntKey};
+use cosmwasm_std::{Addr, Decimal, Deps, Env, Order, StdError, StdResult, Uint128};
+use cw_storage_plus::{Bound, PrimaryKey};
+
+use cw2::get_contract_version;
 
 use pfc_steak::hub::{
-    Batch, ConfigResponse, MinerParamsResponse, PendingBatch, StateResponse,
-    UnbondRequestsByBatchResponseItem, UnbondRequestsByUserResponseItem, ValidatorMiningPower,
+    AcceptedTokenResponse, Batch, ChainProfile, ConfigResponse, Counters, DenomMetadataResponse,
+    ExchangeRateFloorResponse, FeeLedgerEntry, HookEventKind, InfoResponse, MetricsResponse,
+    MinerParamsResponse, PendingBatch, PendingChange, RecentClaim, ReferralStatsResponse,
+    ReplayBatchResponse, RewardCampaign, ScheduleResponse, StakingProtoUrls, StateResponse,
+    SupplyCheckpoint, SwapRouteResponse, TreasuryResponse, UnbondCall, UnbondEntitlement,
+    UnbondEtaResponse, UnbondEtaResponseItem, UnbondOrder, UnbondRequestsByBatchResponseItem,
+    UnbondRequestsBySenderResponseItem, UnbondRequestsByUserResponseItem, ValidatorCapResponse,
+    ValidatorDifficultyResponse, ValidatorMiningPower, ValidatorNetAprResponse,
+    ValidatorSummaryResponse, VestedFeesResponse, WithdrawableAmountResponse,
 };
 
-use crate::helpers::{query_cw20_total_supply, query_delegations};
+use crate::execute::compute_validator_target;
+use crate::helpers::{
+    compute_reconciled_batches, query_chain_unbonding_time, query_cw20_balance, query_delegations,
+    query_draining_delegations, query_staking_apr, query_usteak_supply, query_validator_tokens,
+};
 use crate::state::State;
 
 const MAX_LIMIT: u32 = 30;
 const DEFAULT_LIMIT: u32 = 10;
 
-pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
+pub fn config(deps: Deps, env: Env) -> StdResult<ConfigResponse> {
     let state = State::default();
     Ok(ConfigResponse {
         owner: state.owner.load(deps.storage)?.into(),
@@ -21,6 +34,7 @@ pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
             .may_load(deps.storage)?
             .map(|addr| addr.into()),
         steak_token: state.steak_token.load(deps.storage)?.into(),
+        usteak_denom: state.usteak_denom.may_load(deps.storage)?,
         epoch_period: state.epoch_period.load(deps.storage)?,
         unbond_period: state.unbond_period.load(deps.storage)?,
         denom: state.denom.load(deps.storage)?,
@@ -28,7 +42,119 @@ pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
         fee_account: state.fee_account.load(deps.storage)?.to_string(),
         fee_rate: state.fee_rate.load(deps.storage)?,
         max_fee_rate: state.max_fee_rate.load(deps.storage)?,
+        fee_exemptions: state
+            .fee_exemptions
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|holder| holder.into())
+            .collect(),
         validators: state.validators.load(deps.storage)?,
+        min_reinvest_amount: state
+            .min_reinvest_amount
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        min_bond_amount: state
+            .min_bond_amount
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        min_unbond_shares: state
+            .min_unbond_shares
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        max_rebase_bps: state.max_rebase_bps.may_load(deps.storage)?.flatten(),
+        max_harvest_staleness: state
+            .max_harvest_staleness
+            .may_load(deps.storage)?
+            .flatten(),
+        submit_deadline_buffer: state
+            .submit_deadline_buffer
+            .may_load(deps.storage)?
+            .flatten(),
+        harvest_granter: state
+            .harvest_granter
+            .may_load(deps.storage)?
+            .map(|addr| addr.into()),
+        ics20_contract: state
+            .ics20_contract
+            .may_load(deps.storage)?
+            .map(|addr| addr.into()),
+        ics20_channel: state.ics20_channel.may_load(deps.storage)?,
+        harvest_mode: state
+            .harvest_mode
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        remainder_policy: state
+            .remainder_policy
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        fee_split_interface: state
+            .fee_split_interface
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        slash_limit: state.slash_limit.may_load(deps.storage)?.flatten(),
+        referral_bps: state
+            .referral_bps
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        fee_vesting: state.fee_vesting.may_load(deps.storage)?,
+        batch_rollover: state.batch_rollover.may_load(deps.storage)?,
+        restaking_adapter: state.restaking_adapter.may_load(deps.storage)?,
+        measure_deposits: state
+            .measure_deposits
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        min_delegation_per_validator: state
+            .min_delegation_per_validator
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        bond_rate_limit: state.bond_rate_limit.may_load(deps.storage)?,
+        min_harvest_reward: state
+            .min_harvest_reward
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        min_self_delegation: state
+            .min_self_delegation
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        max_batch_exclusions: state
+            .max_batch_exclusions
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        forbidden_deposit_denoms: state
+            .forbidden_deposit_denoms
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        token_admin: state
+            .token_admin
+            .may_load(deps.storage)?
+            .unwrap_or(env.contract.address)
+            .into(),
+        capabilities: state
+            .capabilities
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        reconcile_mode: state
+            .reconcile_mode
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        rate_publisher: state.rate_publisher.may_load(deps.storage)?,
+        max_messages: state.max_messages.may_load(deps.storage)?,
+        min_redelegation: state
+            .min_redelegation
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        priority_unbonders: state
+            .priority_unbonders
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        change_timelock: state
+            .change_timelock
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
     })
 }
 
@@ -36,12 +162,13 @@ pub fn state(deps: Deps, env: Env) -> StdResult<StateResponse> {
     let state = State::default();
 
     let denom = state.denom.load(deps.storage)?;
-    let steak_token = state.steak_token.load(deps.storage)?;
-    let total_usteak = query_cw20_total_supply(&deps.querier, &steak_token)?;
+    let total_usteak = query_usteak_supply(&deps.querier, deps.storage)?;
 
     let validators = state.validators.load(deps.storage)?;
     let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
-    let total_native: u128 = delegations.iter().map(|d| d.amount).sum();
+    let draining =
+        query_draining_delegations(&deps.querier, deps.storage, &env.contract.address, &denom)?;
+    let total_native: u128 = delegations.iter().chain(&draining).map(|d| d.amount).sum();
 
     let exchange_rate = if total_usteak.is_zero() {
         Decimal::one()
@@ -54,17 +181,301 @@ pub fn state(deps: Deps, env: Env) -> StdResult<StateResponse> {
         total_native: Uint128::new(total_native),
         exchange_rate,
         unlocked_coins: state.unlocked_coins.load(deps.storage)?,
+        last_harvest_time: state.last_harvest_time.may_load(deps.storage)?,
+        last_reinvest_amount: state
+            .last_reinvest_amount
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        total_fees_collected: state
+            .total_fees_collected
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        total_rewards_compounded: state
+            .total_rewards_compounded
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        restaked_native: state
+            .restaked_native
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        absorbed_native: state
+            .absorbed_native
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        fee_escrow: state.fee_escrow.may_load(deps.storage)?.unwrap_or_default(),
+        donations: state.donations.may_load(deps.storage)?.unwrap_or_default(),
+        last_harvester: state.last_harvester.may_load(deps.storage)?,
+        last_reconciler: state.last_reconciler.may_load(deps.storage)?,
+        last_batch_submitter: state.last_batch_submitter.may_load(deps.storage)?,
+    })
+}
+
+pub fn denom_metadata(deps: Deps) -> StdResult<DenomMetadataResponse> {
+    let state = State::default();
+    let decimals = state.load_denom_decimals(deps.storage)?;
+
+    Ok(DenomMetadataResponse {
+        denom: state.denom.load(deps.storage)?,
+        decimals,
+        unit: Uint128::new(10u128.pow(decimals as u32)),
+    })
+}
+
+pub fn exchange_rate_floor(deps: Deps) -> StdResult<ExchangeRateFloorResponse> {
+    let state = State::default();
+
+    Ok(ExchangeRateFloorResponse {
+        last_exchange_rate: state.last_exchange_rate.may_load(deps.storage)?,
+        epsilon: state.load_exchange_rate_epsilon(deps.storage)?,
+        guardian: state.guardian.may_load(deps.storage)?.map(String::from),
+        slash_acknowledged: state
+            .slash_acknowledged
+            .may_load(deps.storage)?
+            .unwrap_or(false),
+    })
+}
+
+/// Git commit the code was built from, passed in through the `GIT_COMMIT` environment variable
+const GIT_COMMIT: Option<&str> = option_env!("GIT_COMMIT");
+
+pub fn info(deps: Deps, env: Env) -> StdResult<InfoResponse> {
+    let state = State::default();
+    let contract_version = get_contract_version(deps.storage)?;
+
+    let mut features = vec![];
+    if cfg!(feature = "backtraces") {
+        features.push("backtraces".to_string());
+    }
+    if cfg!(feature = "cranelift") {
+        features.push("cranelift".to_string());
+    }
+    if cfg!(feature = "debug-events") {
+        features.push("debug-events".to_string());
+    }
+
+    let (token_backend, usteak) = match state.usteak_denom.may_load(deps.storage)? {
+        Some(usteak_denom) => ("token_factory", usteak_denom),
+        None => ("cw20", state.steak_token.load(deps.storage)?.into()),
+    };
+
+    Ok(InfoResponse {
+        contract: contract_version.contract,
+        version: contract_version.version,
+        git_commit: GIT_COMMIT.map(String::from),
+        features,
+        token_backend: token_backend.to_string(),
+        usteak,
+        chain_profile: ChainProfile {
+            chain_id: env.block.chain_id,
+            denom: state.denom.load(deps.storage)?,
+            unbond_period: state.unbond_period.load(deps.storage)?,
+            staking_proto_urls: state.load_staking_proto_urls(deps.storage)?,
+            preset: state.chain_preset.may_load(deps.storage)?,
+        },
+    })
+}
+
+pub fn schedule(deps: Deps, env: Env) -> StdResult<ScheduleResponse> {
+    let state = State::default();
+    let current_time = env.block.time.seconds();
+    let denom = state.denom.load(deps.storage)?;
+
+    let pending_batch = state.pending_batches.load(deps.storage, &denom)?;
+    let submit_deadline_buffer = state
+        .submit_deadline_buffer
+        .may_load(deps.storage)?
+        .flatten();
+
+    let reconciliation_time = state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(false.into())
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|((batch_denom, _), batch)| (batch_denom, batch.est_unbond_end_time)))
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|(batch_denom, _)| *batch_denom == denom)
+        .map(|(_, est_unbond_end_time)| est_unbond_end_time)
+        .min();
+
+    let max_harvest_staleness = state
+        .max_harvest_staleness
+        .may_load(deps.storage)?
+        .flatten();
+    let harvest_deadline = match max_harvest_staleness {
+        Some(staleness) => state
+            .last_harvest_time
+            .may_load(deps.storage)?
+            .map(|time| time + staleness),
+        None => None,
+    };
+
+    let suspension_end = state
+        .validator_suspensions
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, until)| until))
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .min();
+
+    let config_change_expiry = state
+        .pending_changes
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, change)| change.expires_at))
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|expires_at| *expires_at >= current_time)
+        .min();
+
+    Ok(ScheduleResponse {
+        pending_batch_id: pending_batch.id,
+        batch_submission_time: pending_batch.est_unbond_start_time,
+        batch_submission_deadline: submit_deadline_buffer
+            .map(|buffer| pending_batch.est_unbond_start_time + buffer),
+        reconciliation_time,
+        harvest_deadline,
+        suspension_end,
+        config_change_expiry,
+    })
+}
+
+pub fn staking_proto_urls(deps: Deps) -> StdResult<StakingProtoUrls> {
+    let state = State::default();
+    state.load_staking_proto_urls(deps.storage)
+}
+
+pub fn counters(deps: Deps) -> StdResult<Counters> {
+    let state = State::default();
+    state.load_counters(deps.storage)
+}
+
+pub fn metrics(deps: Deps, env: Env) -> StdResult<MetricsResponse> {
+    let state = State::default();
+    let StateResponse {
+        total_usteak,
+        total_native,
+        ..
+    } = self::state(deps, env)?;
+    let denom = state.denom.load(deps.storage)?;
+    let pending_batch = state.pending_batches.load(deps.storage, &denom)?;
+
+    Ok(MetricsResponse {
+        total_native,
+        total_usteak,
+        num_stakers: state
+            .num_stakers
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        num_validators: state.validators.load(deps.storage)?.len() as u64,
+        pending_batch_id: pending_batch.id,
+        pending_usteak_to_burn: pending_batch.usteak_to_burn,
+        pending_est_unbond_start_time: pending_batch.est_unbond_start_time,
+        num_previous_batches: state
+            .num_previous_batches
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        native_unclaimed: state
+            .batches_native_unclaimed
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
     })
 }
 
 pub fn pending_batch(deps: Deps) -> StdResult<PendingBatch> {
     let state = State::default();
-    state.pending_batch.load(deps.storage)
+    let denom = state.denom.load(deps.storage)?;
+    state.pending_batches.load(deps.storage, &denom)
 }
 
 pub fn previous_batch(deps: Deps, id: u64) -> StdResult<Batch> {
     let state = State::default();
-    state.previous_batches.load(deps.storage, id)
+    let denom = state.denom.load(deps.storage)?;
+    state.previous_batches.load(deps.storage, (&denom, id))
+}
+
+pub fn replay_batch(deps: Deps, id: u64) -> StdResult<ReplayBatchResponse> {
+    let state = State::default();
+
+    let denom = state.denom.load(deps.storage)?;
+    let requests = state.load_unbond_requests_by_batch(deps.storage, id, None, None)?;
+
+    let mut mismatches = vec![];
+    // A batch is removed once all of its shares are withdrawn
+    let batch = match state
+        .previous_batches
+        .may_load(deps.storage, (&denom, id))?
+    {
+        Some(batch) => batch,
+        None if requests.is_empty() => {
+            return Err(StdError::generic_err(format!(
+                "batch {} was not submitted or has been fully withdrawn",
+                id
+            )));
+        }
+        None => {
+            mismatches.push(format!(
+                "batch is gone but {} requests remain",
+                requests.len()
+            ));
+            Batch {
+                id,
+                reconciled: false,
+                total_shares: Uint128::zero(),
+                amount_unclaimed: Uint128::zero(),
+                est_unbond_end_time: 0,
+                fee_rate: None,
+                exchange_rate: None,
+                submitter: None,
+                shortfalls: vec![],
+                backfilled: false,
+            }
+        }
+    };
+
+    let mut request_shares = Uint128::zero();
+    let mut amount_entitled = Uint128::zero();
+    let mut entitlements = vec![];
+    for request in requests {
+        // Same computation as `WithdrawUnbonded`
+        let amount = if batch.total_shares.is_zero() {
+            Uint128::zero()
+        } else {
+            batch
+                .amount_unclaimed
+                .multiply_ratio(request.shares, batch.total_shares)
+        };
+        request_shares += request.shares;
+        amount_entitled += amount;
+        entitlements.push(UnbondEntitlement {
+            user: request.user.into(),
+            shares: request.shares,
+            amount,
+        });
+    }
+
+    if request_shares != batch.total_shares {
+        mismatches.push(format!(
+            "requests hold {} shares but the batch records {}",
+            request_shares, batch.total_shares
+        ));
+    }
+    if amount_entitled > batch.amount_unclaimed {
+        mismatches.push(format!(
+            "requests are entitled to {} but the batch has {} unclaimed",
+            amount_entitled, batch.amount_unclaimed
+        ));
+    }
+
+    Ok(ReplayBatchResponse {
+        id,
+        total_shares: batch.total_shares,
+        amount_unclaimed: batch.amount_unclaimed,
+        request_shares,
+        amount_entitled,
+        entitlements,
+        mismatches,
+    })
 }
 
 pub fn previous_batches(
@@ -73,12 +484,14 @@ pub fn previous_batches(
     limit: Option<u32>,
 ) -> StdResult<Vec<Batch>> {
     let state = State::default();
+    let denom = state.denom.load(deps.storage)?;
 
     let start = start_after.map(Bound::exclusive);
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
 
     state
         .previous_batches
+        .prefix(&denom)
         .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
@@ -96,19 +509,79 @@ pub fn unbond_requests_by_batch(
 ) -> StdResult<Vec<UnbondRequestsByBatchResponseItem>> {
     let state = State::default();
 
-    let addr: Addr;
+    let start = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .load_unbond_requests_by_batch(deps.storage, id, start.as_ref(), Some(limit))?
+        .into_iter()
+        .map(|request| {
+            let queued_exchange_rate = state
+                .unbond_queue_rates
+                .may_load(deps.storage, (request.id, &request.user))?;
+            Ok(UnbondRequestsByBatchResponseItem {
+                queued_exchange_rate,
+                ..request.into()
+            })
+        })
+        .collect()
+}
+
+pub fn unbond_requests_by_user(
+    deps: Deps,
+    user: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<UnbondRequestsByUserResponseItem>> {
+    let state = State::default();
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .load_unbond_requests_by_user(
+            deps.storage,
+            &Addr::unchecked(user),
+            start_after,
+            Some(limit),
+        )?
+        .into_iter()
+        .map(|request| {
+            let queued_exchange_rate = state
+                .unbond_queue_rates
+                .may_load(deps.storage, (request.id, &request.user))?;
+            Ok(UnbondRequestsByUserResponseItem {
+                queued_exchange_rate,
+                ..request.into()
+            })
+        })
+        .collect()
+}
+
+pub fn unbond_requests_by_sender(
+    deps: Deps,
+    sender: String,
+    start_after: Option<(u64, String)>,
+    limit: Option<u32>,
+) -> StdResult<Vec<UnbondRequestsBySenderResponseItem>> {
+    let state = State::default();
+
+    let sender_addr = deps.api.addr_validate(&sender)?;
     let start = match start_after {
         None => None,
-        Some(addr_str) => {
-            addr = deps.api.addr_validate(&addr_str)?;
-            Some(Bound::exclusive(&addr))
+        Some((id, receiver)) => {
+            let receiver = deps.api.addr_validate(&receiver)?;
+            Some(Bound::exclusive((id, &receiver, &sender_addr).joined_key()))
         }
     };
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
 
     state
-        .unbond_requests
-        .prefix(id)
+        .unbond_request_sources
+        .idx
+        .sender
+        .prefix(sender)
         .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
@@ -118,36 +591,136 @@ pub fn unbond_requests_by_batch(
         .collect()
 }
 
-pub fn unbond_requests_by_user(
+pub fn unbond_request_calls(
     deps: Deps,
+    id: u64,
     user: String,
-    start_after: Option<u64>,
+    start_after: Option<u32>,
     limit: Option<u32>,
-) -> StdResult<Vec<UnbondRequestsByUserResponseItem>> {
+) -> StdResult<Vec<UnbondCall>> {
     let state = State::default();
 
-    let start = start_after.map(|id| {
-        let mut key = vec![0u8, 8u8]; // when `u64` are used as keys, they are prefixed with the length, which is [0, 8]
-        key.extend(id.to_cw_bytes());
-        Bound::exclusive(key)
-    });
+    let user_addr = deps.api.addr_validate(&user)?;
+    let start = start_after.map(Bound::exclusive);
     let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
 
     state
-        .unbond_requests
-        .idx
-        .user
-        .prefix(user)
+        .unbond_calls
+        .prefix((id, &user_addr))
         .range(deps.storage, start, None, Order::Ascending)
         .take(limit)
         .map(|item| {
             let (_, v) = item?;
-            Ok(v.into())
+            Ok(v)
+        })
+        .collect()
+}
+
+pub fn withdrawable_amount(
+    deps: Deps,
+    env: Env,
+    user: String,
+) -> StdResult<WithdrawableAmountResponse> {
+    let state = State::default();
+    let current_time = env.block.time.seconds();
+    let denom = state.denom.load(deps.storage)?;
+
+    // Batches that `WithdrawUnbonded` would reconcile before paying out
+    let (reconciled_batches, _) = compute_reconciled_batches(deps, &env, None)?;
+
+    let mut amount = Uint128::zero();
+    let mut ids: Vec<u64> = vec![];
+    for request in
+        state.load_unbond_requests_by_user(deps.storage, &Addr::unchecked(user), None, None)?
+    {
+        let batch = match reconciled_batches.iter().find(|b| b.id == request.id) {
+            Some(batch) => Some(batch.clone()),
+            None => state
+                .previous_batches
+                .may_load(deps.storage, (&denom, request.id))?,
+        };
+        if let Some(batch) = batch {
+            if batch.reconciled && batch.est_unbond_end_time < current_time {
+                amount += batch
+                    .amount_unclaimed
+                    .multiply_ratio(request.shares, batch.total_shares);
+                ids.push(request.id);
+            }
+        }
+    }
+
+    Ok(WithdrawableAmountResponse { amount, ids })
+}
+
+pub fn recent_claims(deps: Deps, user: String) -> StdResult<Vec<RecentClaim>> {
+    let state = State::default();
+
+    state
+        .recent_claims
+        .prefix(&Addr::unchecked(user))
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
         })
         .collect()
 }
 
+pub fn unbond_eta(deps: Deps, env: Env, user: String) -> StdResult<UnbondEtaResponse> {
+    let state = State::default();
+    let current_time = env.block.time.seconds();
+    let unbond_period = state.unbond_period.load(deps.storage)?;
+    let denom = state.denom.load(deps.storage)?;
+    let pending_batch = state.pending_batches.load(deps.storage, &denom)?;
+
+    // Batches already submitted were estimated with the configured period; if the chain takes
+    // longer, they finish late by the difference
+    let chain_unbonding_time = match state.chain_unbonding_time.may_load(deps.storage)? {
+        Some(time) => Some(time),
+        None => query_chain_unbonding_time(&deps.querier),
+    };
+    let unbond_time = chain_unbonding_time.map_or(unbond_period, |time| time.max(unbond_period));
+    let delay = unbond_time - unbond_period;
+
+    let batches = state
+        .load_unbond_requests_by_user(deps.storage, &Addr::unchecked(user), None, None)?
+        .into_iter()
+        .map(|request| {
+            if request.id == pending_batch.id {
+                // A batch is submitted at the earliest at its start time, or right away if overdue
+                let start_time = pending_batch.est_unbond_start_time.max(current_time);
+                return Ok(UnbondEtaResponseItem {
+                    id: request.id,
+                    shares: request.shares,
+                    submitted: false,
+                    est_unbond_end_time: start_time + unbond_time,
+                });
+            }
+            let batch = state
+                .previous_batches
+                .load(deps.storage, (&denom, request.id))?;
+            Ok(UnbondEtaResponseItem {
+                id: request.id,
+                shares: request.shares,
+                submitted: true,
+                est_unbond_end_time: batch.est_unbond_end_time + delay,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(UnbondEtaResponse {
+        unbond_time,
+        batches,
+    })
+}
+
 // query function for entropy
+pub fn unbond_order(deps: Deps, user: String) -> StdResult<Option<UnbondOrder>> {
+    let state = State::default();
+    let user = deps.api.addr_validate(&user)?;
+    state.unbond_orders.may_load(deps.storage, &user)
+}
+
 pub fn miner_params(deps: Deps) -> StdResult<MinerParamsResponse> {
     let state = State::default();
     let entropy = state.miner_entropy.load(deps.storage)?;
@@ -156,6 +729,15 @@ pub fn miner_params(deps: Deps) -> StdResult<MinerParamsResponse> {
     Ok(MinerParamsResponse {
         entropy,
         difficulty,
+        enabled: state.is_mining_enabled(deps.storage)?,
+        proof_cooldown_blocks: state
+            .proof_cooldown_blocks
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        power_normalization: state
+            .mining_power_normalization
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
     })
 }
 
@@ -182,3 +764,380 @@ pub fn validator_mining_powers(
         })
         .collect()
 }
+
+pub fn validator_summary(
+    deps: Deps,
+    env: Env,
+    validator: String,
+) -> StdResult<ValidatorSummaryResponse> {
+    let state = State::default();
+    let denom = state.denom.load(deps.storage)?;
+    let validators = state.validators.load(deps.storage)?;
+    // A suspension that has ended is only lifted by the next transaction that needs the active set,
+    // so count the validator as active already, unless it was penalized for repeated slashing
+    let mut suspended_until = state
+        .validator_suspensions
+        .may_load(deps.storage, validator.clone())?;
+    let mut validators_active = state.validators_active.load(deps.storage)?;
+    if suspended_until.map_or(false, |until| until <= env.block.time.seconds()) {
+        suspended_until = None;
+        let penalized = match state.slash_limit.may_load(deps.storage)?.flatten() {
+            Some(limit) => {
+                state
+                    .slash_incidents
+                    .may_load(deps.storage, validator.clone())?
+                    .unwrap_or_default()
+                    >= limit
+            }
+            None => false,
+        };
+        if !penalized {
+            validators_active.push(validator.clone());
+        }
+    }
+
+    if !validators.contains(&validator) {
+        return Err(StdError::generic_err(format!(
+            "validator {} is not whitelisted",
+            validator
+        )));
+    }
+
+    let delegations = query_delegations(&deps.querier, &validators, &env.contract.address, &denom)?;
+    let total_delegated: u128 = delegations.iter().map(|d| d.amount).sum();
+    let delegation = delegations
+        .iter()
+        .find(|d| d.validator == validator)
+        .map_or(0, |d| d.amount);
+    let target_delegation = compute_validator_target(
+        deps.storage,
+        &validator,
+        total_delegated,
+        validators_active.len(),
+    )?;
+
+    let staking_validator = deps.querier.query_validator(&validator)?;
+    let tokens = query_validator_tokens(&deps.querier, &validator);
+
+    Ok(ValidatorSummaryResponse {
+        active: validators_active.contains(&validator),
+        delegation: Uint128::new(delegation),
+        target_delegation,
+        cap: state
+            .validator_caps
+            .may_load(deps.storage, validator.clone())?,
+        mining_power: state
+            .validator_mining_powers
+            .may_load(deps.storage, validator.clone())?
+            .unwrap_or_default(),
+        commission: staking_validator.as_ref().map(|v| v.commission),
+        max_commission: staking_validator.as_ref().map(|v| v.max_commission),
+        total_tokens: tokens.map(|(tokens, _)| tokens),
+        jailed: tokens.map(|(_, jailed)| jailed),
+        slash_incidents: state
+            .slash_incidents
+            .may_load(deps.storage, validator.clone())?
+            .unwrap_or_default(),
+        suspended_until,
+        validator,
+    })
+}
+
+pub fn validator_net_apr(deps: Deps, validator: String) -> StdResult<ValidatorNetAprResponse> {
+    let state = State::default();
+    let validators = state.validators.load(deps.storage)?;
+    if !validators.contains(&validator) {
+        return Err(StdError::generic_err(format!(
+            "validator {} is not whitelisted",
+            validator
+        )));
+    }
+
+    let commission = deps
+        .querier
+        .query_validator(&validator)?
+        .map(|v| v.commission);
+    let staking_apr = query_staking_apr(&deps.querier);
+    let net_apr = match (staking_apr, commission) {
+        (Some(staking_apr), Some(commission)) if commission <= Decimal::one() => {
+            Some(staking_apr * (Decimal::one() - commission))
+        }
+        _ => None,
+    };
+
+    Ok(ValidatorNetAprResponse {
+        validator,
+        commission,
+        staking_apr,
+        net_apr,
+    })
+}
+
+pub fn validator_caps(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ValidatorCapResponse>> {
+    let state = State::default();
+
+    let start = start_after.map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .validator_caps
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (validator, cap) = item?;
+            Ok(ValidatorCapResponse { validator, cap })
+        })
+        .collect()
+}
+
+pub fn validator_difficulties(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ValidatorDifficultyResponse>> {
+    let state = State::default();
+
+    let start = start_after.map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .validator_mining_difficulties
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (validator, difficulty) = item?;
+            Ok(ValidatorDifficultyResponse {
+                validator,
+                difficulty,
+            })
+        })
+        .collect()
+}
+
+pub fn hooks(deps: Deps, event_kind: HookEventKind) -> StdResult<Vec<String>> {
+    let state = State::default();
+
+    Ok(state
+        .hooks
+        .may_load(deps.storage, event_kind.as_str())?
+        .unwrap_or_default()
+        .into_iter()
+        .map(String::from)
+        .collect())
+}
+
+pub fn reward_campaigns(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<RewardCampaign>> {
+    let state = State::default();
+
+    let start = start_after.map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .reward_campaigns
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, campaign)| campaign))
+        .collect()
+}
+
+pub fn swap_routes(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<SwapRouteResponse>> {
+    let state = State::default();
+
+    let start = start_after.as_deref().map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .swap_routes
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (denom, route) = item?;
+            Ok(SwapRouteResponse { denom, route })
+        })
+        .collect()
+}
+
+pub fn treasury(deps: Deps, env: Env) -> StdResult<TreasuryResponse> {
+    let state = State::default();
+
+    let treasury = state.treasury.may_load(deps.storage)?;
+    let lp_tokens = match &treasury {
+        Some(treasury) => query_cw20_balance(
+            &deps.querier,
+            &Addr::unchecked(&treasury.lp_token),
+            &env.contract.address,
+        )?,
+        None => Uint128::zero(),
+    };
+
+    Ok(TreasuryResponse {
+        treasury,
+        native_escrow: state
+            .treasury_escrow
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        native_provided: state
+            .treasury_native_provided
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        usteak_provided: state
+            .treasury_usteak_provided
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        lp_tokens,
+    })
+}
+
+pub fn pending_changes(
+    deps: Deps,
+    env: Env,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<PendingChange>> {
+    let state = State::default();
+    let current_time = env.block.time.seconds();
+
+    let start = start_after.map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .pending_changes
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, change)) => change.expires_at >= current_time,
+            Err(_) => true,
+        })
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+pub fn fee_ledger(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<FeeLedgerEntry>> {
+    let state = State::default();
+
+    let start = start_after.map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .fee_ledger
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+pub fn supply_checkpoints(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<SupplyCheckpoint>> {
+    let state = State::default();
+
+    let start = start_after.map(Bound::exclusive);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .supply_checkpoints
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+pub fn supply_checkpoint_at(deps: Deps, height: u64) -> StdResult<Option<SupplyCheckpoint>> {
+    let state = State::default();
+    state.load_supply_checkpoint_at(deps.storage, height)
+}
+
+pub fn referral_stats(deps: Deps, referrer: String) -> StdResult<ReferralStatsResponse> {
+    let state = State::default();
+    let record = state.load_referrer(deps.storage, &deps.api.addr_validate(&referrer)?)?;
+
+    Ok(ReferralStatsResponse {
+        referrer,
+        volume: record.volume,
+        earned: record.earned,
+        unclaimed: record.unclaimed,
+    })
+}
+
+pub fn vested_fees(deps: Deps, env: Env, account: String) -> StdResult<VestedFeesResponse> {
+    let state = State::default();
+    let addr = deps.api.addr_validate(&account)?;
+    let now = env.block.time.seconds();
+
+    let mut unclaimed = Uint128::zero();
+    let mut claimable = Uint128::zero();
+    for item in
+        state
+            .vesting_tranches
+            .prefix(&addr)
+            .range(deps.storage, None, None, Order::Ascending)
+    {
+        let (_, tranche) = item?;
+        unclaimed += tranche.amount - tranche.claimed;
+        claimable += tranche.claimable(now);
+    }
+
+    Ok(VestedFeesResponse {
+        account,
+        unclaimed,
+        claimable,
+    })
+}
+
+pub fn accepted_tokens(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<AcceptedTokenResponse>> {
+    let state = State::default();
+
+    let addr: Addr;
+    let start = match start_after {
+        None => None,
+        Some(addr_str) => {
+            addr = deps.api.addr_validate(&addr_str)?;
+            Some(Bound::exclusive(&addr))
+        }
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    state
+        .accepted_tokens
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (token, receive_msgs) = item?;
+            Ok(AcceptedTokenResponse {
+                token: token.into(),
+                receive_msgs,
+            })
+        })
+        .collect()
+}
@@ -1,11 +1,30 @@
-use cosmwasm_std::{Addr, Coin, Decimal, StdError, StdResult, Storage, Uint128, Uint64};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use cosmwasm_std::{
+    Addr, Coin, Decimal, Empty, Order, StdError, StdResult, Storage, Uint128, Uint64, WasmMsg,
+};
+use cw_storage_plus::{Bound, CwIntKey, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
-use pfc_steak::hub::{Batch, FeeType, PendingBatch, UnbondRequest};
+use pfc_steak::chains::ChainPreset;
+use pfc_steak::hub::{
+    Batch, BatchRollover, BondRateLimit, Capabilities, ChangeTimelock, Counters, FeeLedgerEntry,
+    FeeSplitInterface, FeeType, FeeVesting, HarvestMode, MiningPowerNormalization, OperationRecord,
+    PendingBatch, PendingChange, RatePublisher, ReceiveMsgKind, RecentClaim, ReconcileMode,
+    RemainderPolicy, RestakingAdapter, RewardCampaign, StakingProtoUrls, SupplyCheckpoint,
+    SwapRoute, TreasuryConfig, UnbondCall, UnbondOrder, UnbondRequest, UnbondRequestSource,
+};
 
-use crate::types::BooleanKey;
+use crate::types::{BondWindow, BooleanKey, Redelegation, Referrer, ReplyContext, VestingTranche};
 pub(crate) const BATCH_KEY_V101: &str = "previous_batches_101";
 pub(crate) const BATCH_KEY_RECONCILED_V101: &str = "previous_batches__reconciled_101";
+pub(crate) const BATCH_KEY_V102: &str = "batches";
+pub(crate) const BATCH_KEY_RECONCILED_V102: &str = "batches__reconciled";
+pub(crate) const PENDING_BATCH_KEY_V101: &str = "pending_batch";
+
+/// Number of fee payouts kept in the fee ledger
+pub const FEE_LEDGER_CAPACITY: u64 = 100;
+/// Number of withdrawals kept for each user
+pub const RECENT_CLAIMS_CAPACITY: usize = 5;
+/// Number of supply checkpoints kept; at one harvest and one batch a day, about a year and a half
+pub const SUPPLY_CHECKPOINT_CAPACITY: u64 = 1000;
 
 pub(crate) struct State<'a> {
     /// Account who can call certain privileged functions
@@ -19,6 +38,8 @@ pub(crate) struct State<'a> {
     pub fee_rate: Item<'a, Decimal>,
     /// Maximum fee rate
     pub max_fee_rate: Item<'a, Decimal>,
+    /// Holders whose share of the rewards bears no protocol fee
+    pub fee_exemptions: Item<'a, Vec<Addr>>,
     /// denom to accept
     pub denom: Item<'a, String>,
     /// Address of the Steak token
@@ -32,13 +53,32 @@ pub(crate) struct State<'a> {
 
     /// Coins that can be reinvested
     pub unlocked_coins: Item<'a, Vec<Coin>>,
-    /// The current batch of unbonding requests queded to be executed
-    pub pending_batch: Item<'a, PendingBatch>,
+    /// The current batch of unbonding requests queded to be executed, keyed by the denom unbonded
+    pub pending_batches: Map<'a, &'a str, PendingBatch>,
 
-    /// Previous batches that have started unbonding but not yet finished
-    pub previous_batches: IndexedMap<'a, u64, Batch, PreviousBatchesIndexes<'a>>,
-    /// Users' shares in unbonding batches
-    pub unbond_requests: IndexedMap<'a, (u64, &'a Addr), UnbondRequest, UnbondRequestsIndexes<'a>>,
+    /// Previous batches that have started unbonding but not yet finished, keyed by (denom, batch
+    /// id). Unbonding requests are keyed by batch id alone, which holds while the hub unbonds a
+    /// single denom
+    pub previous_batches: IndexedMap<'a, (&'a str, u64), Batch, PreviousBatchesIndexes<'a>>,
+    /// Users' shares in unbonding batches, keyed by (batch id, user). Use `load_unbond_request`
+    /// and friends, which also see requests still in the legacy layout
+    pub unbond_requests: Map<'a, (u64, &'a Addr), Uint128>,
+    /// Batch IDs of each user's unbonding requests, keyed by (user, batch id)
+    pub unbond_request_ids: Map<'a, (&'a Addr, u64), Empty>,
+    /// Unbonding requests written before the compact layout, each storing its key again in the
+    /// value and in a secondary index. They move over the next time they are written to
+    pub legacy_unbond_requests:
+        IndexedMap<'a, (u64, &'a Addr), UnbondRequest, UnbondRequestsIndexes<'a>>,
+    /// Who queued the shares of each unbonding request, keyed by (batch id, receiver, sender)
+    pub unbond_request_sources: IndexedMap<
+        'a,
+        (u64, &'a Addr, &'a Addr),
+        UnbondRequestSource,
+        UnbondRequestSourcesIndexes<'a>,
+    >,
+    /// When each validator reported the undelegation of a batch will complete, keyed by
+    /// (batch id, validator)
+    pub unbond_completion_times: Map<'a, (u64, &'a str), u64>,
     pub validators_active: Item<'a, Vec<String>>,
     /// coins in 'denom' held before reinvest was called.
     pub prev_denom: Item<'a, Uint128>,
@@ -54,8 +94,226 @@ pub(crate) struct State<'a> {
     pub miner_last_mined_block: Item<'a, Uint64>,
     // mining power by validator (map of validator address to time weighted mining power)
     pub validator_mining_powers: Map<'a, String, Uint128>,
+    /// Mining difficulty of proofs for a validator, overriding `miner_difficulty`
+    pub validator_mining_difficulties: Map<'a, String, Uint64>,
     // total mining power
     pub total_mining_power: Item<'a, Uint128>,
+    // whether the mining subsystem is in use
+    pub mining_enabled: Item<'a, bool>,
+    /// Harvested native below this amount (after fees) is not delegated, but carried over
+    pub min_reinvest_amount: Item<'a, Uint128>,
+    /// Harvested native held back by `min_reinvest_amount`, to be added to the next reinvest
+    pub reinvest_carry: Item<'a, Uint128>,
+    /// Largest increase of the bonded amount a single reinvest may make, in basis points; the
+    /// excess is added to `reinvest_carry`. `None` means no limit
+    pub max_rebase_bps: Item<'a, Option<u64>>,
+    /// Seconds after the last harvest past which deposits and unbonding requests are refused.
+    /// `None` means no limit
+    pub max_harvest_staleness: Item<'a, Option<u64>>,
+    /// Seconds after the pending batch's estimated start time during which anyone may submit it;
+    /// past it only the guardian may. `None` means no deadline
+    pub submit_deadline_buffer: Item<'a, Option<u64>>,
+    /// Account whose delegations are harvested through an authz grant to the hub, when the hub
+    /// stakes through an intermediate account
+    pub harvest_granter: Item<'a, Addr>,
+    /// Deposits below this amount are refused by `Bond`
+    pub min_bond_amount: Item<'a, Uint128>,
+    /// Steak amounts below this are refused by `QueueUnbond`
+    pub min_unbond_shares: Item<'a, Uint128>,
+    /// Accounts whose unbonding requests for themselves skip `min_unbond_shares` and keep the
+    /// pending batch from being rolled over
+    pub priority_unbonders: Item<'a, Vec<Addr>>,
+    /// CW20 tokens accepted by the `Receive` hook, and the `ReceiveMsg` variants each may use
+    pub accepted_tokens: Map<'a, &'a Addr, Vec<ReceiveMsgKind>>,
+    /// cw20-ics20 contract used to send Steak back to depositors bonding through IBC hooks
+    pub ics20_contract: Item<'a, Addr>,
+    /// IBC channel deposits through IBC hooks are accepted from and the Steak sent back over
+    pub ics20_channel: Item<'a, String>,
+    /// Accounts that have been minted Steak and have not yet exited, used to estimate the number
+    /// of stakers
+    pub stakers: Map<'a, &'a Addr, Empty>,
+    /// Number of entries in `stakers`
+    pub num_stakers: Item<'a, u64>,
+    /// Number of `previous_batches` of the bond denom
+    pub num_previous_batches: Item<'a, u64>,
+    /// Native Token the `previous_batches` of the bond denom hold unclaimed
+    pub batches_native_unclaimed: Item<'a, Uint128>,
+    /// Number of decimals of the bond denom
+    pub denom_decimals: Item<'a, u8>,
+    /// Recurring unbond orders, whose usteak is held in escrow by the hub
+    pub unbond_orders: Map<'a, &'a Addr, UnbondOrder>,
+    /// Per-call records of each unbonding request, keyed by (batch id, user, index)
+    pub unbond_calls: Map<'a, (u64, &'a Addr, u32), UnbondCall>,
+    /// Least Native Token each request of the pending batch must be submitted for, keyed by
+    /// (batch id, user); see `QueueUnbond`'s `min_native_out`
+    pub unbond_min_outs: Map<'a, (u64, &'a Addr), Uint128>,
+    /// Config changes proposed by the owner, awaiting acceptance
+    pub pending_changes: Map<'a, u64, PendingChange>,
+    /// ID to be assigned to the next proposed change
+    pub next_change_id: Item<'a, u64>,
+    /// How long proposed changes wait before they can be accepted, and for how long after
+    pub change_timelock: Item<'a, ChangeTimelock>,
+    /// Type URLs of the Stargate messages sent to the staking and distribution modules
+    pub staking_proto_urls: Item<'a, StakingProtoUrls>,
+    /// Chain preset the hub was instantiated with
+    pub chain_preset: Item<'a, ChainPreset>,
+    /// Maximum amount of native to be delegated to a validator
+    pub validator_caps: Map<'a, String, Uint128>,
+    /// Account allowed to acknowledge slashes
+    pub guardian: Item<'a, Addr>,
+    /// Exchange rate observed by the last bond, reinvest or batch submission
+    pub last_exchange_rate: Item<'a, Decimal>,
+    /// Largest relative drop of the exchange rate that is let through without an acknowledgement
+    pub exchange_rate_epsilon: Item<'a, Decimal>,
+    /// Whether the guardian has acknowledged the next drop of the exchange rate
+    pub slash_acknowledged: Item<'a, bool>,
+    /// The last `FEE_LEDGER_CAPACITY` fee payouts, keyed by entry ID
+    pub fee_ledger: Map<'a, u64, FeeLedgerEntry>,
+    /// ID to be assigned to the next fee ledger entry
+    pub next_fee_ledger_id: Item<'a, u64>,
+    /// The last `SUPPLY_CHECKPOINT_CAPACITY` supply checkpoints, keyed by checkpoint ID
+    pub supply_checkpoints: Map<'a, u64, SupplyCheckpoint>,
+    /// ID to be assigned to the next supply checkpoint
+    pub next_supply_checkpoint_id: Item<'a, u64>,
+    /// The last `RECENT_CLAIMS_CAPACITY` withdrawals of each user, keyed by (user, claim ID)
+    pub recent_claims: Map<'a, (&'a Addr, u64), RecentClaim>,
+    /// ID to be assigned to the next claim
+    pub next_claim_id: Item<'a, u64>,
+    /// How the rewards received by a harvest are found
+    pub harvest_mode: Item<'a, HarvestMode>,
+    /// Bank balances of the hub before the reward withdrawals of an ongoing harvest
+    pub harvest_balances: Item<'a, Vec<Coin>>,
+    /// Time of the last harvest, in seconds
+    pub last_harvest_time: Item<'a, u64>,
+    /// Native Token delegated by the last reinvest
+    pub last_reinvest_amount: Item<'a, Uint128>,
+    /// Protocol fees taken from rewards so far, in Native Token
+    pub total_fees_collected: Item<'a, Uint128>,
+    /// Rewards delegated back so far, net of fees
+    pub total_rewards_compounded: Item<'a, Uint128>,
+    /// What each outstanding reply is for, keyed by reply ID
+    pub reply_contexts: Map<'a, u64, ReplyContext>,
+    /// Which validators receive the remainder when an amount can't be split evenly among them
+    pub remainder_policy: Item<'a, RemainderPolicy>,
+    /// Position of the first validator to receive the remainder of the next operation, under
+    /// `RemainderPolicy::RoundRobin`
+    pub remainder_cursor: Item<'a, u64>,
+    /// Interface of the fee-split contract, when the fee account is one
+    pub fee_split_interface: Item<'a, FeeSplitInterface>,
+    /// Redelegations of the last `Rebalance` that did not fit in its transaction, to be sent by
+    /// `ContinueRebalance`
+    pub pending_rebalance: Item<'a, Vec<Redelegation>>,
+    /// Slashing incidents acknowledged against each validator since it was last reinstated
+    pub slash_incidents: Map<'a, String, u32>,
+    /// Number of slashing incidents after which a validator is deactivated. `None` means never
+    pub slash_limit: Item<'a, Option<u32>>,
+    /// When each suspended validator re-enters the active set, in seconds
+    pub validator_suspensions: Map<'a, String, u64>,
+    /// The staking module's unbonding time, in seconds, cached by the first batch submission that
+    /// could query it
+    pub chain_unbonding_time: Item<'a, u64>,
+    /// Token factory denom of the Steak token, once it has been migrated off cw20
+    pub usteak_denom: Item<'a, String>,
+    /// Supply of `usteak_denom`. The hub is its only minter and burner, so it keeps count itself
+    pub usteak_denom_supply: Item<'a, Uint128>,
+    /// Share of the protocol fee paid back to referrers, in basis points
+    pub referral_bps: Item<'a, u64>,
+    /// Referral record of each address that referred a bond
+    pub referrers: Map<'a, &'a Addr, Referrer>,
+    /// Sum of the volumes of all referrers
+    pub referral_volume: Item<'a, Uint128>,
+    /// Kickbacks accrued per unit of referral volume since the program started
+    pub referral_index: Item<'a, Decimal>,
+    /// Kickbacks accrued but not claimed yet. They are held by the hub, so they are set aside when
+    /// reconciling
+    pub referral_unclaimed: Item<'a, Uint128>,
+    /// Number of blocks a miner must wait after an accepted proof before submitting another
+    pub proof_cooldown_blocks: Item<'a, u64>,
+    /// Height of the last accepted proof of each miner
+    pub last_proof_height: Map<'a, &'a Addr, u64>,
+    /// How mining power is normalized at each batch submission
+    pub mining_power_normalization: Item<'a, MiningPowerNormalization>,
+    /// Vesting schedule of the reinvest fee. When set, the fee is taken in Steak instead of Native
+    /// Token
+    pub fee_vesting: Item<'a, FeeVesting>,
+    /// Steak fees vesting to each account, by tranche ID. The Steak is held by the hub until
+    /// claimed
+    pub vesting_tranches: Map<'a, (&'a Addr, u64), VestingTranche>,
+    /// ID of the next vesting tranche
+    pub next_vesting_tranche_id: Item<'a, u64>,
+    /// When pending batches too small to be worth submitting are held over to the next epoch
+    pub batch_rollover: Item<'a, BatchRollover>,
+    /// Contract notified of changes to the bonded amount, which may restake part of it
+    pub restaking_adapter: Item<'a, RestakingAdapter>,
+    /// Native Token the restaking adapter last reported as restaked
+    pub restaked_native: Item<'a, Uint128>,
+    /// Whether deposits are measured from the contract's balance rather than `info.funds`
+    pub measure_deposits: Item<'a, bool>,
+    /// Running totals of bonds, unbonds and harvests
+    pub counters: Item<'a, Counters>,
+    /// Native Token delegated to the hub from outside and absorbed so far
+    pub absorbed_native: Item<'a, Uint128>,
+    /// Fees in Native Token held after the fee-split contract rejected them
+    pub fee_escrow: Item<'a, Uint128>,
+    /// Native Token found at reconciliation beyond what the hub owes, e.g. a bank transfer to the
+    /// hub, parked until the owner classifies it
+    pub donations: Item<'a, Uint128>,
+    /// Least Native Token a validator's operator must delegate to it; zero if not set
+    pub min_self_delegation: Item<'a, Uint128>,
+    /// Bridge the exchange rate is published through on each reinvest
+    pub rate_publisher: Item<'a, RatePublisher>,
+    /// Protocol-owned liquidity settings
+    pub treasury: Item<'a, TreasuryConfig>,
+    /// Native Token set aside from fees for the treasury to provide as liquidity
+    pub treasury_escrow: Item<'a, Uint128>,
+    /// Native Token the treasury deposited into its pair
+    pub treasury_native_provided: Item<'a, Uint128>,
+    /// Steak the treasury deposited into its pair
+    pub treasury_usteak_provided: Item<'a, Uint128>,
+    /// Most messages a single execute may send
+    pub max_messages: Item<'a, u32>,
+    /// How far from its target a validator's delegation may be before a rebalance moves it; zero
+    /// if not set
+    pub min_redelegation: Item<'a, Uint128>,
+    /// Account that triggered the last harvest
+    pub last_harvester: Item<'a, OperationRecord>,
+    /// Account that triggered the last reconciliation
+    pub last_reconciler: Item<'a, OperationRecord>,
+    /// Account that triggered the last batch submission
+    pub last_batch_submitter: Item<'a, OperationRecord>,
+    /// Contracts notified of each kind of event, keyed by `HookEventKind::as_str`
+    pub hooks: Map<'a, &'a str, Vec<Addr>>,
+    /// Message instantiating the Steak token, kept until it succeeds so it can be retried
+    pub token_instantiate_msg: Item<'a, WasmMsg>,
+    /// Native Token per Steak when each unbonding request was queued, averaged over its calls,
+    /// keyed by (batch id, user)
+    pub unbond_queue_rates: Map<'a, (u64, &'a Addr), Decimal>,
+    /// Swap module routes converting reward denoms to Native Token, by reward denom
+    pub swap_routes: Map<'a, &'a str, SwapRoute>,
+    /// Least Native Token unbonding leaves delegated to a validator
+    pub min_delegation_per_validator: Item<'a, Uint128>,
+    /// How much Native Token may be bonded for a single receiver per window
+    pub bond_rate_limit: Item<'a, BondRateLimit>,
+    /// Native Token bonded for each receiver in its current rate limit window
+    pub bond_windows: Map<'a, &'a Addr, BondWindow>,
+    /// Pending rewards a validator must have above for a harvest to withdraw from it
+    pub min_harvest_reward: Item<'a, Uint128>,
+    /// How many validators `SubmitBatch` may be asked to leave out of a batch
+    pub max_batch_exclusions: Item<'a, u32>,
+    /// Denoms of liquid staking derivatives `Bond` refuses
+    pub forbidden_deposit_denoms: Item<'a, Vec<String>>,
+    /// Wasm admin of the Steak token; the hub itself if not set
+    pub token_admin: Item<'a, Addr>,
+    /// Operations the owner has halted or resumed; everything is enabled if not set
+    pub capabilities: Item<'a, Capabilities>,
+    /// How `Reconcile` finds the batches whose unbonded Native Token has been received
+    pub reconcile_mode: Item<'a, ReconcileMode>,
+    /// Reward campaigns not paid out in full yet
+    pub reward_campaigns: Map<'a, u64, RewardCampaign>,
+    /// ID of the next reward campaign
+    pub next_reward_campaign_id: Item<'a, u64>,
+    /// Native Token held for reward campaigns and not released yet
+    pub reward_campaign_escrow: Item<'a, Uint128>,
 }
 
 impl Default for State<'static> {
@@ -63,8 +321,8 @@ impl Default for State<'static> {
         let pb_indexes = PreviousBatchesIndexes {
             reconciled: MultiIndex::new(
                 |d: &Batch| d.reconciled.into(),
-                BATCH_KEY_V101,
-                BATCH_KEY_RECONCILED_V101,
+                BATCH_KEY_V102,
+                BATCH_KEY_RECONCILED_V102,
             ),
         };
         let ubr_indexes = UnbondRequestsIndexes {
@@ -74,21 +332,34 @@ impl Default for State<'static> {
                 "unbond_requests__user",
             ),
         };
+        let ubrs_indexes = UnbondRequestSourcesIndexes {
+            sender: MultiIndex::new(
+                |d: &UnbondRequestSource| d.sender.clone().into(),
+                "unbond_request_sources",
+                "unbond_request_sources__sender",
+            ),
+        };
         Self {
             owner: Item::new("owner"),
             new_owner: Item::new("new_owner"),
             fee_account: Item::new("fee_account"),
             fee_rate: Item::new("fee_rate"),
             max_fee_rate: Item::new("max_fee_rate"),
+            fee_exemptions: Item::new("fee_exemptions"),
             denom: Item::new("denom"),
             steak_token: Item::new("steak_token"),
             epoch_period: Item::new("epoch_period"),
             unbond_period: Item::new("unbond_period"),
             validators: Item::new("validators"),
             unlocked_coins: Item::new("unlocked_coins"),
-            pending_batch: Item::new("pending_batch"),
-            previous_batches: IndexedMap::new(BATCH_KEY_V101, pb_indexes),
-            unbond_requests: IndexedMap::new("unbond_requests", ubr_indexes),
+            pending_batches: Map::new("pending_batches"),
+            previous_batches: IndexedMap::new(BATCH_KEY_V102, pb_indexes),
+            // namespaces are repeated in every key, so these two are kept short
+            unbond_requests: Map::new("ubr"),
+            unbond_request_ids: Map::new("ubr_ids"),
+            legacy_unbond_requests: IndexedMap::new("unbond_requests", ubr_indexes),
+            unbond_request_sources: IndexedMap::new("unbond_request_sources", ubrs_indexes),
+            unbond_completion_times: Map::new("unbond_completion_times"),
             validators_active: Item::new("validators_active"),
             prev_denom: Item::new("prev_denom"),
             fee_account_type: Item::new("fee_account_type"),
@@ -98,7 +369,108 @@ impl Default for State<'static> {
             miner_last_mined_timestamp: Item::new("miner_last_mined_timestamp"),
             miner_last_mined_block: Item::new("miner_last_mined_block"),
             validator_mining_powers: Map::new("validator_mining_powers"),
+            validator_mining_difficulties: Map::new("validator_mining_difficulties"),
             total_mining_power: Item::new("total_mining_power"),
+            mining_enabled: Item::new("mining_enabled"),
+            min_reinvest_amount: Item::new("min_reinvest_amount"),
+            min_bond_amount: Item::new("min_bond_amount"),
+            min_unbond_shares: Item::new("min_unbond_shares"),
+            priority_unbonders: Item::new("priority_unbonders"),
+            reinvest_carry: Item::new("reinvest_carry"),
+            max_rebase_bps: Item::new("max_rebase_bps"),
+            max_harvest_staleness: Item::new("max_harvest_staleness"),
+            submit_deadline_buffer: Item::new("submit_deadline_buffer"),
+            harvest_granter: Item::new("harvest_granter"),
+            accepted_tokens: Map::new("accepted_tokens"),
+            ics20_contract: Item::new("ics20_contract"),
+            ics20_channel: Item::new("ics20_channel"),
+            stakers: Map::new("stakers"),
+            num_stakers: Item::new("num_stakers"),
+            num_previous_batches: Item::new("num_previous_batches"),
+            batches_native_unclaimed: Item::new("batches_native_unclaimed"),
+            denom_decimals: Item::new("denom_decimals"),
+            unbond_orders: Map::new("unbond_orders"),
+            unbond_calls: Map::new("unbond_calls"),
+            unbond_min_outs: Map::new("unbond_min_outs"),
+            pending_changes: Map::new("pending_changes"),
+            next_change_id: Item::new("next_change_id"),
+            change_timelock: Item::new("change_timelock"),
+            staking_proto_urls: Item::new("staking_proto_urls"),
+            chain_preset: Item::new("chain_preset"),
+            validator_caps: Map::new("validator_caps"),
+            guardian: Item::new("guardian"),
+            last_exchange_rate: Item::new("last_exchange_rate"),
+            exchange_rate_epsilon: Item::new("exchange_rate_epsilon"),
+            slash_acknowledged: Item::new("slash_acknowledged"),
+            fee_ledger: Map::new("fee_ledger"),
+            next_fee_ledger_id: Item::new("next_fee_ledger_id"),
+            supply_checkpoints: Map::new("supply_checkpoints"),
+            next_supply_checkpoint_id: Item::new("next_supply_checkpoint_id"),
+            recent_claims: Map::new("recent_claims"),
+            next_claim_id: Item::new("next_claim_id"),
+            harvest_mode: Item::new("harvest_mode"),
+            harvest_balances: Item::new("harvest_balances"),
+            last_harvest_time: Item::new("last_harvest_time"),
+            last_reinvest_amount: Item::new("last_reinvest_amount"),
+            total_fees_collected: Item::new("total_fees_collected"),
+            total_rewards_compounded: Item::new("total_rewards_compounded"),
+            reply_contexts: Map::new("reply_contexts"),
+            remainder_policy: Item::new("remainder_policy"),
+            remainder_cursor: Item::new("remainder_cursor"),
+            fee_split_interface: Item::new("fee_split_interface"),
+            pending_rebalance: Item::new("pending_rebalance"),
+            slash_incidents: Map::new("slash_incidents"),
+            slash_limit: Item::new("slash_limit"),
+            validator_suspensions: Map::new("validator_suspensions"),
+            chain_unbonding_time: Item::new("chain_unbonding_time"),
+            usteak_denom: Item::new("usteak_denom"),
+            usteak_denom_supply: Item::new("usteak_denom_supply"),
+            referral_bps: Item::new("referral_bps"),
+            referrers: Map::new("referrers"),
+            referral_volume: Item::new("referral_volume"),
+            referral_index: Item::new("referral_index"),
+            referral_unclaimed: Item::new("referral_unclaimed"),
+            proof_cooldown_blocks: Item::new("proof_cooldown_blocks"),
+            last_proof_height: Map::new("last_proof_height"),
+            mining_power_normalization: Item::new("mining_power_normalization"),
+            fee_vesting: Item::new("fee_vesting"),
+            vesting_tranches: Map::new("vesting_tranches"),
+            next_vesting_tranche_id: Item::new("next_vesting_tranche_id"),
+            batch_rollover: Item::new("batch_rollover"),
+            restaking_adapter: Item::new("restaking_adapter"),
+            restaked_native: Item::new("restaked_native"),
+            measure_deposits: Item::new("measure_deposits"),
+            counters: Item::new("counters"),
+            absorbed_native: Item::new("absorbed_native"),
+            fee_escrow: Item::new("fee_escrow"),
+            donations: Item::new("donations"),
+            min_self_delegation: Item::new("min_self_delegation"),
+            rate_publisher: Item::new("rate_publisher"),
+            treasury: Item::new("treasury"),
+            treasury_escrow: Item::new("treasury_escrow"),
+            treasury_native_provided: Item::new("treasury_native_provided"),
+            treasury_usteak_provided: Item::new("treasury_usteak_provided"),
+            max_messages: Item::new("max_messages"),
+            min_redelegation: Item::new("min_redelegation"),
+            last_harvester: Item::new("last_harvester"),
+            last_reconciler: Item::new("last_reconciler"),
+            last_batch_submitter: Item::new("last_batch_submitter"),
+            hooks: Map::new("hooks"),
+            token_instantiate_msg: Item::new("token_instantiate_msg"),
+            unbond_queue_rates: Map::new("unbond_queue_rates"),
+            swap_routes: Map::new("swap_routes"),
+            min_delegation_per_validator: Item::new("min_delegation_per_validator"),
+            bond_rate_limit: Item::new("bond_rate_limit"),
+            bond_windows: Map::new("bond_windows"),
+            min_harvest_reward: Item::new("min_harvest_reward"),
+            max_batch_exclusions: Item::new("max_batch_exclusions"),
+            forbidden_deposit_denoms: Item::new("forbidden_deposit_denoms"),
+            token_admin: Item::new("token_admin"),
+            capabilities: Item::new("capabilities"),
+            reconcile_mode: Item::new("reconcile_mode"),
+            reward_campaigns: Map::new("reward_campaigns"),
+            next_reward_campaign_id: Item::new("next_reward_campaign_id"),
+            reward_campaign_escrow: Item::new("reward_campaign_escrow"),
         }
     }
 }
@@ -112,11 +484,589 @@ impl<'a> State<'a> {
             Err(StdError::generic_err("unauthorized: sender is not owner"))
         }
     }
+
+    /// The guardian acknowledges slashes; until one is set, the owner does
+    pub fn assert_guardian(&self, storage: &dyn Storage, sender: &Addr) -> StdResult<()> {
+        match self.guardian.may_load(storage)? {
+            Some(guardian) if *sender == guardian => Ok(()),
+            Some(_) => Err(StdError::generic_err(
+                "unauthorized: sender is not guardian",
+            )),
+            None => self.assert_owner(storage, sender),
+        }
+    }
+
+    /// Largest relative drop of the exchange rate let through, 0.1% unless set by the owner
+    pub fn load_exchange_rate_epsilon(&self, storage: &dyn Storage) -> StdResult<Decimal> {
+        Ok(self
+            .exchange_rate_epsilon
+            .may_load(storage)?
+            .unwrap_or_else(|| Decimal::permille(1)))
+    }
+
+    /// Allocate a reply ID for a submessage and store what the reply is for; see `contract::reply`.
+    ///
+    /// Contexts are removed as their replies come in, and a failed submessage reverts the whole
+    /// transaction, so none outlive the transaction that registered them. IDs are therefore only
+    /// unique within a transaction, and start again from 1 in the next one
+    pub fn register_reply(
+        &self,
+        storage: &mut dyn Storage,
+        context: ReplyContext,
+    ) -> StdResult<u64> {
+        let id = self
+            .reply_contexts
+            .keys(storage, None, None, Order::Descending)
+            .next()
+            .transpose()?
+            .map_or(1, |id| id + 1);
+        self.reply_contexts.save(storage, id, &context)?;
+        Ok(id)
+    }
+
+    /// Position of the first validator to receive the remainder of a delegation operation. Under
+    /// `RemainderPolicy::RoundRobin`, each call moves the cursor on by one validator
+    pub fn next_remainder_offset(&self, storage: &mut dyn Storage) -> StdResult<usize> {
+        match self.remainder_policy.may_load(storage)?.unwrap_or_default() {
+            RemainderPolicy::FirstValidators => Ok(0),
+            RemainderPolicy::RoundRobin => {
+                let cursor = self.remainder_cursor.may_load(storage)?.unwrap_or(0);
+                self.remainder_cursor
+                    .save(storage, &cursor.wrapping_add(1))?;
+                Ok(cursor as usize)
+            }
+        }
+    }
+
+    /// Append a fee payout to the ledger, dropping the oldest entry once it is full
+    pub fn record_fee(
+        &self,
+        storage: &mut dyn Storage,
+        mut entry: FeeLedgerEntry,
+    ) -> StdResult<()> {
+        let id = self.next_fee_ledger_id.may_load(storage)?.unwrap_or(0);
+        entry.id = id;
+        self.fee_ledger.save(storage, id, &entry)?;
+        if id >= FEE_LEDGER_CAPACITY {
+            self.fee_ledger.remove(storage, id - FEE_LEDGER_CAPACITY);
+        }
+        self.next_fee_ledger_id.save(storage, &(id + 1))
+    }
+
+    /// Native Token of the reward campaigns due for release at `time` but not released yet
+    pub fn releasable_campaign_rewards(
+        &self,
+        storage: &dyn Storage,
+        time: u64,
+    ) -> StdResult<Uint128> {
+        self.reward_campaigns
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, campaign)| campaign.vested(time) - campaign.released))
+            .sum()
+    }
+
+    /// Release the Native Token of the reward campaigns due by `time`, dropping the campaigns paid
+    /// out in full. Returns the amount released
+    pub fn release_campaign_rewards(
+        &self,
+        storage: &mut dyn Storage,
+        time: u64,
+    ) -> StdResult<Uint128> {
+        let campaigns = self
+            .reward_campaigns
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, campaign)| campaign))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let mut released = Uint128::zero();
+        for mut campaign in campaigns {
+            let vested = campaign.vested(time);
+            released += vested - campaign.released;
+            if vested == campaign.amount {
+                self.reward_campaigns.remove(storage, campaign.id);
+            } else {
+                campaign.released = vested;
+                self.reward_campaigns
+                    .save(storage, campaign.id, &campaign)?;
+            }
+        }
+
+        let escrow = self
+            .reward_campaign_escrow
+            .may_load(storage)?
+            .unwrap_or_default();
+        self.reward_campaign_escrow
+            .save(storage, &escrow.checked_sub(released)?)?;
+        Ok(released)
+    }
+
+    /// Append a supply checkpoint, dropping the oldest one once `SUPPLY_CHECKPOINT_CAPACITY` are kept
+    pub fn record_supply_checkpoint(
+        &self,
+        storage: &mut dyn Storage,
+        mut checkpoint: SupplyCheckpoint,
+    ) -> StdResult<()> {
+        let id = self
+            .next_supply_checkpoint_id
+            .may_load(storage)?
+            .unwrap_or(0);
+        checkpoint.id = id;
+        self.supply_checkpoints.save(storage, id, &checkpoint)?;
+        if id >= SUPPLY_CHECKPOINT_CAPACITY {
+            self.supply_checkpoints
+                .remove(storage, id - SUPPLY_CHECKPOINT_CAPACITY);
+        }
+        self.next_supply_checkpoint_id.save(storage, &(id + 1))
+    }
+
+    /// Record a withdrawal by `user`, dropping their oldest one once `RECENT_CLAIMS_CAPACITY` are
+    /// kept. Returns the ID assigned to it
+    pub fn record_claim(
+        &self,
+        storage: &mut dyn Storage,
+        user: &Addr,
+        mut claim: RecentClaim,
+    ) -> StdResult<u64> {
+        let id = self.next_claim_id.may_load(storage)?.unwrap_or(0);
+        claim.id = id;
+        self.recent_claims.save(storage, (user, id), &claim)?;
+        self.next_claim_id.save(storage, &(id + 1))?;
+
+        let ids = self
+            .recent_claims
+            .prefix(user)
+            .keys(storage, None, None, Order::Descending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for old_id in ids.into_iter().skip(RECENT_CLAIMS_CAPACITY) {
+            self.recent_claims.remove(storage, (user, old_id));
+        }
+        Ok(id)
+    }
+
+    /// The last supply checkpoint recorded at or before `height`. Checkpoints are recorded in
+    /// height order, so this is a binary search over the ones kept
+    pub fn load_supply_checkpoint_at(
+        &self,
+        storage: &dyn Storage,
+        height: u64,
+    ) -> StdResult<Option<SupplyCheckpoint>> {
+        let next_id = self
+            .next_supply_checkpoint_id
+            .may_load(storage)?
+            .unwrap_or(0);
+        let mut low = next_id.saturating_sub(SUPPLY_CHECKPOINT_CAPACITY);
+        let mut high = next_id;
+        let mut found = None;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let checkpoint = self.supply_checkpoints.load(storage, mid)?;
+            if checkpoint.height <= height {
+                found = Some(checkpoint);
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Number of decimals of the bond denom. Contracts instantiated before this was stored assumed
+    /// 6 decimals
+    pub fn load_denom_decimals(&self, storage: &dyn Storage) -> StdResult<u8> {
+        Ok(self.denom_decimals.may_load(storage)?.unwrap_or(6))
+    }
+
+    /// Type URLs of the Stargate messages to send, falling back to the ones the hub was written for
+    pub fn load_staking_proto_urls(&self, storage: &dyn Storage) -> StdResult<StakingProtoUrls> {
+        Ok(self
+            .staking_proto_urls
+            .may_load(storage)?
+            .unwrap_or_default())
+    }
+
+    /// The unbonding request of `user` in batch `id`, if there is one
+    pub fn load_unbond_request(
+        &self,
+        storage: &dyn Storage,
+        id: u64,
+        user: &Addr,
+    ) -> StdResult<Option<UnbondRequest>> {
+        if let Some(shares) = self.unbond_requests.may_load(storage, (id, user))? {
+            return Ok(Some(UnbondRequest {
+                id,
+                user: user.clone(),
+                shares,
+            }));
+        }
+        self.legacy_unbond_requests.may_load(storage, (id, user))
+    }
+
+    /// Save an unbonding request in the compact layout, moving it out of the legacy one
+    pub fn save_unbond_request(
+        &self,
+        storage: &mut dyn Storage,
+        request: &UnbondRequest,
+    ) -> StdResult<()> {
+        self.legacy_unbond_requests
+            .remove(storage, (request.id, &request.user))?;
+        self.unbond_requests
+            .save(storage, (request.id, &request.user), &request.shares)?;
+        self.unbond_request_ids
+            .save(storage, (&request.user, request.id), &Empty {})
+    }
+
+    pub fn remove_unbond_request(
+        &self,
+        storage: &mut dyn Storage,
+        id: u64,
+        user: &Addr,
+    ) -> StdResult<()> {
+        self.legacy_unbond_requests.remove(storage, (id, user))?;
+        self.unbond_requests.remove(storage, (id, user));
+        self.unbond_request_ids.remove(storage, (user, id));
+        self.unbond_queue_rates.remove(storage, (id, user));
+        Ok(())
+    }
+
+    /// Move all of `user`'s unbonding requests still in the legacy layout to the compact one
+    pub fn migrate_unbond_requests(&self, storage: &mut dyn Storage, user: &Addr) -> StdResult<()> {
+        let legacy = self
+            .legacy_unbond_requests
+            .idx
+            .user
+            .prefix(user.to_string())
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| {
+                let (_, v) = item?;
+                Ok(v)
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        for request in &legacy {
+            self.save_unbond_request(storage, request)?;
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` of `user`'s unbonding requests, by ascending batch ID after `start_after`
+    pub fn load_unbond_requests_by_user(
+        &self,
+        storage: &dyn Storage,
+        user: &Addr,
+        start_after: Option<u64>,
+        limit: Option<usize>,
+    ) -> StdResult<Vec<UnbondRequest>> {
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut requests = self
+            .unbond_request_ids
+            .prefix(user)
+            .keys(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|id| {
+                let id = id?;
+                Ok(UnbondRequest {
+                    id,
+                    user: user.clone(),
+                    shares: self.unbond_requests.load(storage, (id, user))?,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let legacy_start = start_after.map(|id| {
+            let mut key = vec![0u8, 8u8]; // when `u64` are used as keys, they are prefixed with the length, which is [0, 8]
+            key.extend(id.to_cw_bytes());
+            Bound::exclusive(key)
+        });
+        for item in self
+            .legacy_unbond_requests
+            .idx
+            .user
+            .prefix(user.to_string())
+            .range(storage, legacy_start, None, Order::Ascending)
+            .take(limit)
+        {
+            let (_, v) = item?;
+            requests.push(v);
+        }
+
+        requests.sort_by_key(|r| r.id);
+        requests.truncate(limit);
+        Ok(requests)
+    }
+
+    /// Up to `limit` of the unbonding requests in batch `id`, by ascending user address after
+    /// `start_after`
+    pub fn load_unbond_requests_by_batch(
+        &self,
+        storage: &dyn Storage,
+        id: u64,
+        start_after: Option<&Addr>,
+        limit: Option<usize>,
+    ) -> StdResult<Vec<UnbondRequest>> {
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut requests = self
+            .unbond_requests
+            .prefix(id)
+            .range(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|item| {
+                let (user, shares) = item?;
+                Ok(UnbondRequest { id, user, shares })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for item in self
+            .legacy_unbond_requests
+            .prefix(id)
+            .range(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+        {
+            let (_, v) = item?;
+            requests.push(v);
+        }
+
+        requests.sort_by(|a, b| a.user.cmp(&b.user));
+        requests.truncate(limit);
+        Ok(requests)
+    }
+
+    /// Running totals of the hub's activity, all zero until something happens
+    pub fn load_counters(&self, storage: &dyn Storage) -> StdResult<Counters> {
+        Ok(self.counters.may_load(storage)?.unwrap_or_default())
+    }
+
+    /// Add to the running totals. They only ever grow, so an overflow is an error rather than a
+    /// wrap back to zero
+    pub fn bump_counters(
+        &self,
+        storage: &mut dyn Storage,
+        bonded: Uint128,
+        unbonded: Uint128,
+        harvests: u64,
+    ) -> StdResult<()> {
+        let mut counters = self.load_counters(storage)?;
+        counters.total_bonded = counters.total_bonded.checked_add(bonded)?;
+        counters.total_unbonded = counters.total_unbonded.checked_add(unbonded)?;
+        counters.total_harvests = counters
+            .total_harvests
+            .checked_add(harvests)
+            .ok_or_else(|| StdError::generic_err("harvest counter overflow"))?;
+        self.counters.save(storage, &counters)
+    }
+
+    /// Validators removed from the whitelist whose stake still waits in `pending_rebalance` to be
+    /// redelegated. They're no longer in `validators`, but what they hold still backs Steak
+    pub fn load_validators_draining(&self, storage: &dyn Storage) -> StdResult<Vec<String>> {
+        let validators = self.validators.load(storage)?;
+        let mut draining: Vec<String> = vec![];
+        for rd in self
+            .pending_rebalance
+            .may_load(storage)?
+            .unwrap_or_default()
+        {
+            if !validators.contains(&rd.src) && !draining.contains(&rd.src) {
+                draining.push(rd.src);
+            }
+        }
+        Ok(draining)
+    }
+
+    /// The validators receiving new delegations at `time`, after putting back those whose suspension
+    /// has ended. Validators removed or penalized in the meantime stay out
+    pub fn load_validators_active(
+        &self,
+        storage: &mut dyn Storage,
+        time: u64,
+    ) -> StdResult<Vec<String>> {
+        let mut validators_active = self.validators_active.load(storage)?;
+        let ended = self
+            .validator_suspensions
+            .range(storage, None, None, Order::Ascending)
+            .filter(|item| item.as_ref().map_or(true, |(_, until)| *until <= time))
+            .map(|item| item.map(|(validator, _)| validator))
+            .collect::<StdResult<Vec<_>>>()?;
+        if ended.is_empty() {
+            return Ok(validators_active);
+        }
+
+        let validators = self.validators.load(storage)?;
+        let slash_limit = self.slash_limit.may_load(storage)?.flatten();
+        for validator in ended {
+            self.validator_suspensions
+                .remove(storage, validator.clone());
+            let penalized = match slash_limit {
+                Some(limit) => {
+                    self.slash_incidents
+                        .may_load(storage, validator.clone())?
+                        .unwrap_or_default()
+                        >= limit
+                }
+                None => false,
+            };
+            if validators.contains(&validator)
+                && !penalized
+                && !validators_active.contains(&validator)
+            {
+                validators_active.push(validator);
+            }
+        }
+        self.validators_active.save(storage, &validators_active)?;
+
+        Ok(validators_active)
+    }
+
+    /// Whether the mining subsystem is in use. It is enabled unless the owner turned it off
+    pub fn is_mining_enabled(&self, storage: &dyn Storage) -> StdResult<bool> {
+        Ok(self.mining_enabled.may_load(storage)?.unwrap_or(true))
+    }
+
+    /// Delete a validator's mining power and subtract it from the total, returning the amount removed
+    pub fn remove_mining_power(
+        &self,
+        storage: &mut dyn Storage,
+        validator: &str,
+    ) -> StdResult<Uint128> {
+        let mining_power = self
+            .validator_mining_powers
+            .may_load(storage, validator.to_string())?
+            .unwrap_or_default();
+        if !mining_power.is_zero() {
+            let total_mining_power = self
+                .total_mining_power
+                .may_load(storage)?
+                .unwrap_or_default();
+            self.total_mining_power
+                .save(storage, &total_mining_power.saturating_sub(mining_power))?;
+        }
+        self.validator_mining_powers
+            .remove(storage, validator.to_string());
+        Ok(mining_power)
+    }
+
+    /// Save a previous batch of the bond denom, keeping the batch count and the Native Token held
+    /// unclaimed up to date
+    pub fn save_previous_batch(
+        &self,
+        storage: &mut dyn Storage,
+        denom: &str,
+        batch: &Batch,
+    ) -> StdResult<()> {
+        let previous = self.previous_batches.may_load(storage, (denom, batch.id))?;
+        self.previous_batches
+            .save(storage, (denom, batch.id), batch)?;
+        self.update_batch_totals(storage, previous.as_ref(), Some(batch))
+    }
+
+    /// Remove a previous batch of the bond denom, keeping the batch count and the Native Token held
+    /// unclaimed up to date
+    pub fn remove_previous_batch(
+        &self,
+        storage: &mut dyn Storage,
+        denom: &str,
+        id: u64,
+    ) -> StdResult<()> {
+        let previous = self.previous_batches.may_load(storage, (denom, id))?;
+        self.previous_batches.remove(storage, (denom, id))?;
+        self.update_batch_totals(storage, previous.as_ref(), None)
+    }
+
+    fn update_batch_totals(
+        &self,
+        storage: &mut dyn Storage,
+        before: Option<&Batch>,
+        after: Option<&Batch>,
+    ) -> StdResult<()> {
+        let mut num_batches = self
+            .num_previous_batches
+            .may_load(storage)?
+            .unwrap_or_default();
+        let mut native_unclaimed = self
+            .batches_native_unclaimed
+            .may_load(storage)?
+            .unwrap_or_default();
+        if let Some(batch) = before {
+            num_batches = num_batches.saturating_sub(1);
+            native_unclaimed = native_unclaimed.saturating_sub(batch.amount_unclaimed);
+        }
+        if let Some(batch) = after {
+            num_batches += 1;
+            native_unclaimed += batch.amount_unclaimed;
+        }
+        self.num_previous_batches.save(storage, &num_batches)?;
+        self.batches_native_unclaimed
+            .save(storage, &native_unclaimed)
+    }
+
+    /// Start tracking `staker`, unless it is already tracked
+    pub fn add_staker(&self, storage: &mut dyn Storage, staker: &Addr) -> StdResult<()> {
+        if !self.stakers.has(storage, staker) {
+            self.stakers.save(storage, staker, &Empty {})?;
+            let num_stakers = self.num_stakers.may_load(storage)?.unwrap_or_default();
+            self.num_stakers.save(storage, &(num_stakers + 1))?;
+        }
+        Ok(())
+    }
+
+    /// Stop tracking `staker`, if it is tracked
+    pub fn remove_staker(&self, storage: &mut dyn Storage, staker: &Addr) -> StdResult<()> {
+        if self.stakers.has(storage, staker) {
+            self.stakers.remove(storage, staker);
+            let num_stakers = self.num_stakers.may_load(storage)?.unwrap_or_default();
+            self.num_stakers
+                .save(storage, &num_stakers.saturating_sub(1))?;
+        }
+        Ok(())
+    }
+
+    /// Referral record of `referrer`, with the kickbacks accrued so far credited
+    pub fn load_referrer(&self, storage: &dyn Storage, referrer: &Addr) -> StdResult<Referrer> {
+        let mut record = self
+            .referrers
+            .may_load(storage, referrer)?
+            .unwrap_or_default();
+        record.settle(self.referral_index.may_load(storage)?.unwrap_or_default());
+        Ok(record)
+    }
+
+    pub fn assert_token_accepted(
+        &self,
+        storage: &dyn Storage,
+        token: &Addr,
+        kind: ReceiveMsgKind,
+    ) -> StdResult<()> {
+        let receive_msgs = self
+            .accepted_tokens
+            .may_load(storage, token)?
+            .ok_or_else(|| StdError::generic_err(format!("token {} is not accepted", token)))?;
+        if receive_msgs.contains(&kind) {
+            Ok(())
+        } else {
+            Err(StdError::generic_err(format!(
+                "token {} cannot be used for {:?}",
+                token, kind
+            )))
+        }
+    }
 }
 
 pub(crate) struct PreviousBatchesIndexes<'a> {
-    // pk goes to second tuple element
-    pub reconciled: MultiIndex<'a, BooleanKey, Batch, Vec<u8>>,
+    // pk goes to second tuple element. Batches of every denom share the index; the denom is read
+    // back from the pk
+    pub reconciled: MultiIndex<'a, BooleanKey, Batch, (String, u64)>,
 }
 
 impl<'a> IndexList<Batch> for PreviousBatchesIndexes<'a> {
@@ -137,3 +1087,15 @@ impl<'a> IndexList<UnbondRequest> for UnbondRequestsIndexes<'a> {
         Box::new(v.into_iter())
     }
 }
+
+pub(crate) struct UnbondRequestSourcesIndexes<'a> {
+    // pk goes to third tuple element
+    pub sender: MultiIndex<'a, String, UnbondRequestSource, Vec<u8>>,
+}
+
+impl<'a> IndexList<UnbondRequestSource> for UnbondRequestSourcesIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<UnbondRequestSource>> + '_> {
+        let v: Vec<&dyn Index<UnbondRequestSource>> = vec![&self.sender];
+        Box::new(v.into_iter())
+    }
+}
@@ -1,12 +1,29 @@
 use std::collections::HashMap;
 
+use cosmos_sdk_proto::cosmos::base::v1beta1::DecCoin;
+use cosmos_sdk_proto::cosmos::distribution::v1beta1::{
+    DelegationDelegatorReward, Params as DistributionParams, QueryDelegationTotalRewardsResponse,
+    QueryParamsResponse as QueryDistributionParamsResponse,
+};
+use cosmos_sdk_proto::cosmos::mint::v1beta1::QueryAnnualProvisionsResponse;
+use cosmos_sdk_proto::cosmos::staking::v1beta1::{
+    Params as StakingParams, Pool, QueryDelegatorUnbondingDelegationsResponse, QueryParamsResponse,
+    QueryPoolResponse, QueryValidatorRequest, QueryValidatorResponse, UnbondingDelegation,
+    UnbondingDelegationEntry, Validator as ProtoValidator,
+};
 use cosmwasm_std::testing::{BankQuerier, StakingQuerier, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, from_slice, Addr, Coin, Decimal, Empty, FullDelegation, Querier, QuerierResult,
-    QueryRequest, SystemError, Validator, WasmQuery,
+    from_binary, from_slice, Addr, Binary, Coin, ContractResult, Decimal, Empty, FullDelegation,
+    Querier, QuerierResult, QueryRequest, SystemError, SystemResult, Validator, WasmQuery,
 };
 use cw20::Cw20QueryMsg;
+use prost::Message;
 
+use crate::helpers::{
+    ANNUAL_PROVISIONS_QUERY_PATH, DELEGATION_TOTAL_REWARDS_QUERY_PATH,
+    DELEGATOR_UNBONDING_DELEGATIONS_QUERY_PATH, DISTRIBUTION_PARAMS_QUERY_PATH,
+    STAKING_PARAMS_QUERY_PATH, STAKING_POOL_QUERY_PATH, VALIDATOR_QUERY_PATH,
+};
 use crate::types::Delegation;
 
 use super::cw20_querier::Cw20Querier;
@@ -17,6 +34,24 @@ pub(super) struct CustomQuerier {
     pub cw20_querier: Cw20Querier,
     pub bank_querier: BankQuerier,
     pub staking_querier: StakingQuerier,
+    /// Total tokens bonded to each validator, answering the Stargate validator query. The query is
+    /// refused while this is empty, like on chains that don't allow it
+    pub validator_tokens: HashMap<String, u128>,
+    /// The staking module's unbonding time, answering the Stargate params query. The query is
+    /// refused while this is `None`
+    pub unbonding_time: Option<u64>,
+    /// Rewards in `uxyz` pending on each of the hub's delegations, answering the Stargate
+    /// distribution query. The query is refused while this is empty
+    pub pending_rewards: HashMap<String, u128>,
+    /// Validator and completion time of each of the hub's unbonding delegation entries, answering
+    /// the Stargate unbonding delegations query. The query is refused while this is `None`
+    pub unbonding_entries: Option<Vec<(String, u64)>>,
+    /// Annual provisions, community tax and bonded tokens, answering the Stargate mint,
+    /// distribution params and staking pool queries. The queries are refused while this is `None`
+    pub staking_economics: Option<(u128, Decimal, u128)>,
+    /// Commission rate of each validator, zero if not set. Takes effect at the next
+    /// `set_staking_delegations`
+    pub commissions: HashMap<String, Decimal>,
 }
 
 impl Querier for CustomQuerier {
@@ -63,10 +98,15 @@ impl CustomQuerier {
     }
 
     pub fn set_staking_delegations(&mut self, delegations: &[Delegation]) {
+        self.set_staking_delegations_of(MOCK_CONTRACT_ADDR, delegations)
+    }
+
+    /// Delegations made by an account other than the hub, such as an intermediate staking account
+    pub fn set_staking_delegations_of(&mut self, delegator: &str, delegations: &[Delegation]) {
         let fds = delegations
             .iter()
             .map(|d| FullDelegation {
-                delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                delegator: Addr::unchecked(delegator),
                 validator: d.validator.clone(),
                 amount: Coin::new(d.amount, "native_token"),
                 can_redelegate: Coin::new(0, "native_token"),
@@ -77,7 +117,11 @@ impl CustomQuerier {
             .iter()
             .map(|d| Validator {
                 address: d.validator.clone(),
-                commission: Decimal::zero(),
+                commission: self
+                    .commissions
+                    .get(&d.validator)
+                    .copied()
+                    .unwrap_or_default(),
                 max_commission: Decimal::zero(),
                 max_change_rate: Decimal::zero(),
             })
@@ -99,6 +143,125 @@ impl CustomQuerier {
 
             QueryRequest::Staking(query) => self.staking_querier.query(query),
 
+            QueryRequest::Stargate { path, data }
+                if path == VALIDATOR_QUERY_PATH && !self.validator_tokens.is_empty() =>
+            {
+                let validator_addr = QueryValidatorRequest::decode(data.as_slice())
+                    .unwrap()
+                    .validator_addr;
+                let response = QueryValidatorResponse {
+                    validator: self.validator_tokens.get(&validator_addr).map(|tokens| {
+                        ProtoValidator {
+                            operator_address: validator_addr.clone(),
+                            tokens: tokens.to_string(),
+                            ..Default::default()
+                        }
+                    }),
+                };
+                SystemResult::Ok(ContractResult::Ok(Binary(response.encode_to_vec())))
+            }
+
+            QueryRequest::Stargate { path, .. }
+                if path == STAKING_PARAMS_QUERY_PATH && self.unbonding_time.is_some() =>
+            {
+                let response = QueryParamsResponse {
+                    params: Some(StakingParams {
+                        unbonding_time: self.unbonding_time.map(|seconds| prost_types::Duration {
+                            seconds: seconds as i64,
+                            nanos: 0,
+                        }),
+                        ..Default::default()
+                    }),
+                };
+                SystemResult::Ok(ContractResult::Ok(Binary(response.encode_to_vec())))
+            }
+
+            QueryRequest::Stargate { path, .. }
+                if path == ANNUAL_PROVISIONS_QUERY_PATH && self.staking_economics.is_some() =>
+            {
+                let (annual_provisions, _, _) = self.staking_economics.unwrap();
+                // Decimals are sent as integers scaled by 10^18, as the SDK does
+                let response = QueryAnnualProvisionsResponse {
+                    annual_provisions: format!("{}000000000000000000", annual_provisions)
+                        .into_bytes(),
+                };
+                SystemResult::Ok(ContractResult::Ok(Binary(response.encode_to_vec())))
+            }
+
+            QueryRequest::Stargate { path, .. }
+                if path == DISTRIBUTION_PARAMS_QUERY_PATH && self.staking_economics.is_some() =>
+            {
+                let (_, community_tax, _) = self.staking_economics.unwrap();
+                let response = QueryDistributionParamsResponse {
+                    params: Some(DistributionParams {
+                        community_tax: community_tax.atomics().to_string(),
+                        ..Default::default()
+                    }),
+                };
+                SystemResult::Ok(ContractResult::Ok(Binary(response.encode_to_vec())))
+            }
+
+            QueryRequest::Stargate { path, .. }
+                if path == STAKING_POOL_QUERY_PATH && self.staking_economics.is_some() =>
+            {
+                let (_, _, bonded_tokens) = self.staking_economics.unwrap();
+                let response = QueryPoolResponse {
+                    pool: Some(Pool {
+                        bonded_tokens: bonded_tokens.to_string(),
+                        ..Default::default()
+                    }),
+                };
+                SystemResult::Ok(ContractResult::Ok(Binary(response.encode_to_vec())))
+            }
+
+            QueryRequest::Stargate { path, .. }
+                if path == DELEGATION_TOTAL_REWARDS_QUERY_PATH
+                    && !self.pending_rewards.is_empty() =>
+            {
+                // Decimals are sent as integers scaled by 10^18, as the SDK does
+                let response = QueryDelegationTotalRewardsResponse {
+                    rewards: self
+                        .pending_rewards
+                        .iter()
+                        .map(|(validator, amount)| DelegationDelegatorReward {
+                            validator_address: validator.clone(),
+                            reward: vec![DecCoin {
+                                denom: "uxyz".to_string(),
+                                amount: format!("{}000000000000000000", amount),
+                            }],
+                        })
+                        .collect(),
+                    total: vec![],
+                };
+                SystemResult::Ok(ContractResult::Ok(Binary(response.encode_to_vec())))
+            }
+
+            QueryRequest::Stargate { path, .. }
+                if path == DELEGATOR_UNBONDING_DELEGATIONS_QUERY_PATH
+                    && self.unbonding_entries.is_some() =>
+            {
+                let response = QueryDelegatorUnbondingDelegationsResponse {
+                    unbonding_responses: self
+                        .unbonding_entries
+                        .iter()
+                        .flatten()
+                        .map(|(validator, completion_time)| UnbondingDelegation {
+                            delegator_address: MOCK_CONTRACT_ADDR.to_string(),
+                            validator_address: validator.clone(),
+                            entries: vec![UnbondingDelegationEntry {
+                                completion_time: Some(prost_types::Timestamp {
+                                    seconds: *completion_time as i64,
+                                    nanos: 0,
+                                }),
+                                ..Default::default()
+                            }],
+                        })
+                        .collect(),
+                    pagination: None,
+                };
+                SystemResult::Ok(ContractResult::Ok(Binary(response.encode_to_vec())))
+            }
+
             _ => err_unsupported_query(request),
         }
     }
@@ -0,0 +1,187 @@
+//! Builders for the state most tests start from, and a runner that executes a sequence of messages
+//! while moving the block time forward
+
+use cosmwasm_std::testing::{mock_info, MockApi, MockStorage};
+use cosmwasm_std::{from_binary, Addr, Env, OwnedDeps, Response, StdResult, Storage, Uint128};
+use serde::de::DeserializeOwned;
+
+use pfc_steak::hub::{Batch, ExecuteMsg, QueryMsg, UnbondRequest};
+
+use crate::contract::{execute, query};
+use crate::state::State;
+use crate::types::Delegation;
+
+use super::custom_querier::CustomQuerier;
+use super::helpers::mock_env_at_timestamp;
+
+/// A submitted batch, not yet reconciled and with no snapshots, unless set otherwise
+pub(super) struct BatchBuilder {
+    batch: Batch,
+}
+
+impl BatchBuilder {
+    pub fn new(id: u64) -> Self {
+        Self {
+            batch: Batch {
+                id,
+                reconciled: false,
+                total_shares: Uint128::zero(),
+                amount_unclaimed: Uint128::zero(),
+                est_unbond_end_time: 0,
+                fee_rate: None,
+                exchange_rate: None,
+                submitter: None,
+                shortfalls: vec![],
+                backfilled: false,
+            },
+        }
+    }
+
+    pub fn reconciled(mut self) -> Self {
+        self.batch.reconciled = true;
+        self
+    }
+
+    pub fn shares(mut self, total_shares: u128) -> Self {
+        self.batch.total_shares = Uint128::new(total_shares);
+        self
+    }
+
+    pub fn unclaimed(mut self, amount_unclaimed: u128) -> Self {
+        self.batch.amount_unclaimed = Uint128::new(amount_unclaimed);
+        self
+    }
+
+    pub fn unbond_end_time(mut self, est_unbond_end_time: u64) -> Self {
+        self.batch.est_unbond_end_time = est_unbond_end_time;
+        self
+    }
+
+    pub fn build(self) -> Batch {
+        self.batch
+    }
+
+    /// Save the batch among the previous batches, and return it
+    pub fn save(self, storage: &mut dyn Storage) -> StdResult<Batch> {
+        let state = State::default();
+        let denom = state.denom.load(storage)?;
+        state
+            .previous_batches
+            .save(storage, (&denom, self.batch.id), &self.batch)?;
+        Ok(self.batch)
+    }
+}
+
+pub(super) struct UnbondRequestBuilder {
+    request: UnbondRequest,
+}
+
+impl UnbondRequestBuilder {
+    pub fn new(id: u64, user: &str) -> Self {
+        Self {
+            request: UnbondRequest {
+                id,
+                user: Addr::unchecked(user),
+                shares: Uint128::zero(),
+            },
+        }
+    }
+
+    pub fn shares(mut self, shares: u128) -> Self {
+        self.request.shares = Uint128::new(shares);
+        self
+    }
+
+    pub fn build(self) -> UnbondRequest {
+        self.request
+    }
+
+    /// Save the request, and return it
+    pub fn save(self, storage: &mut dyn Storage) -> StdResult<UnbondRequest> {
+        State::default().save_unbond_request(storage, &self.request)?;
+        Ok(self.request)
+    }
+}
+
+/// The hub's delegations, in Native Token
+#[derive(Default)]
+pub(super) struct DelegationSetBuilder {
+    delegations: Vec<Delegation>,
+}
+
+impl DelegationSetBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn delegate(mut self, validator: &str, amount: u128) -> Self {
+        self.delegations
+            .push(Delegation::new(validator, amount, "uxyz"));
+        self
+    }
+
+    /// Delegate `total` across `validators` as evenly as possible, the first validators taking
+    /// the remainder
+    pub fn split(mut self, validators: &[&str], total: u128) -> Self {
+        let count = validators.len() as u128;
+        for (i, validator) in validators.iter().enumerate() {
+            let extra = if (i as u128) < total % count { 1 } else { 0 };
+            self = self.delegate(validator, total / count + extra);
+        }
+        self
+    }
+
+    /// Have the querier report these delegations as the hub's
+    pub fn apply(self, querier: &mut CustomQuerier) {
+        querier.set_staking_delegations(&self.delegations);
+    }
+}
+
+/// A step of a `Scenario`
+pub(super) enum Step {
+    /// Move the block time forward by this many seconds
+    Advance(u64),
+    /// Execute a message, which must succeed
+    Execute(&'static str, ExecuteMsg),
+}
+
+/// Tests driven through a sequence of messages over time, instead of hand-built state
+pub(super) struct Scenario {
+    pub deps: OwnedDeps<MockStorage, MockApi, CustomQuerier>,
+    pub time: u64,
+}
+
+impl Scenario {
+    pub fn new(deps: OwnedDeps<MockStorage, MockApi, CustomQuerier>, time: u64) -> Self {
+        Self { deps, time }
+    }
+
+    pub fn env(&self) -> Env {
+        mock_env_at_timestamp(self.time)
+    }
+
+    pub fn advance(&mut self, seconds: u64) {
+        self.time += seconds;
+    }
+
+    pub fn execute(&mut self, sender: &str, msg: ExecuteMsg) -> StdResult<Response> {
+        let env = self.env();
+        execute(self.deps.as_mut(), env, mock_info(sender, &[]), msg)
+    }
+
+    /// Run the steps in order, returning the responses of the messages executed
+    pub fn run(&mut self, steps: Vec<Step>) -> Vec<Response> {
+        let mut responses = vec![];
+        for step in steps {
+            match step {
+                Step::Advance(seconds) => self.advance(seconds),
+                Step::Execute(sender, msg) => responses.push(self.execute(sender, msg).unwrap()),
+            }
+        }
+        responses
+    }
+
+    pub fn query<T: DeserializeOwned>(&self, msg: QueryMsg) -> T {
+        from_binary(&query(self.deps.as_ref(), self.env(), msg).unwrap()).unwrap()
+    }
+}
@@ -1,13 +1,13 @@
-use cosmwasm_std::testing::{mock_env, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, Addr, BlockInfo, ContractInfo, Deps, Env, OwnedDeps, QuerierResult, SystemError,
-    SystemResult, Timestamp,
+    from_binary, Addr, BlockInfo, ContractInfo, Deps, Env, OwnedDeps, QuerierResult, Response,
+    StdResult, SystemError, SystemResult, Timestamp,
 };
 use serde::de::DeserializeOwned;
 
-use pfc_steak::hub::QueryMsg;
+use pfc_steak::hub::{ChangeTimelock, ConfigChange, ExecuteMsg, QueryMsg};
 
-use crate::contract::query;
+use crate::contract::{execute, query};
 
 use super::custom_querier::CustomQuerier;
 
@@ -44,3 +44,33 @@ pub(super) fn mock_env_at_timestamp(timestamp: u64) -> Env {
 pub(super) fn query_helper<T: DeserializeOwned>(deps: Deps, msg: QueryMsg) -> T {
     from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
 }
+
+/// Propose `change` as `sender`, and accept it at `env` once the default timelock delay has passed
+pub(super) fn apply_change(
+    deps: &mut OwnedDeps<MockStorage, MockApi, CustomQuerier>,
+    env: Env,
+    sender: &str,
+    change: ConfigChange,
+) -> StdResult<Response> {
+    let mut proposal_env = env.clone();
+    proposal_env.block.time =
+        Timestamp::from_seconds(env.block.time.seconds() - ChangeTimelock::default().delay);
+    let res = execute(
+        deps.as_mut(),
+        proposal_env,
+        mock_info(sender, &[]),
+        ExecuteMsg::ProposeChange { change },
+    )?;
+    let id = res.events[0]
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "id")
+        .map(|attr| attr.value.parse().unwrap())
+        .unwrap();
+    execute(
+        deps.as_mut(),
+        env,
+        mock_info(sender, &[]),
+        ExecuteMsg::AcceptChange { id },
+    )
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use cosmwasm_std::Uint128;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::math::{
+    compute_redelegations_for_rebalancing, compute_redelegations_for_removal,
+    compute_target_delegation_from_mining_power, compute_undelegations,
+};
+use crate::types::{Delegation, Redelegation};
+
+/// Largest validator set generated, a little above the active set size of most chains
+const MAX_VALIDATORS: usize = 150;
+/// Largest amount delegated to a single validator
+const MAX_DELEGATION: u128 = 1_000_000_000_000;
+
+fn delegations_from(amounts: &[u128]) -> Vec<Delegation> {
+    amounts
+        .iter()
+        .enumerate()
+        .map(|(i, amount)| Delegation::new(&format!("validator{}", i), *amount, "uxyz"))
+        .collect()
+}
+
+fn amounts_strategy() -> impl Strategy<Value = Vec<u128>> {
+    vec(0..=MAX_DELEGATION, 1..=MAX_VALIDATORS)
+}
+
+/// Delegated amount of each validator once `redelegations` are carried out
+fn apply_redelegations(
+    delegations: &[Delegation],
+    redelegations: &[Redelegation],
+) -> HashMap<String, u128> {
+    let mut amounts: HashMap<String, u128> = delegations
+        .iter()
+        .map(|d| (d.validator.clone(), d.amount))
+        .collect();
+    for r in redelegations {
+        *amounts.get_mut(&r.src).unwrap() -= r.amount;
+        *amounts.get_mut(&r.dst).unwrap() += r.amount;
+    }
+    amounts
+}
+
+fn assert_well_formed(redelegations: &[Redelegation]) -> Result<(), TestCaseError> {
+    for r in redelegations {
+        prop_assert!(r.amount > 0, "empty redelegation {:?}", r);
+        prop_assert_ne!(&r.src, &r.dst, "redelegation to self {:?}", r);
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn undelegations_conserve_the_unbonded_amount(
+        amounts in amounts_strategy(),
+        unbond_ratio in 0u128..=1000,
+        remainder_offset in 0usize..1000,
+    ) {
+        let delegations = delegations_from(&amounts);
+        let native_staked: u128 = amounts.iter().sum();
+        let native_to_unbond = native_staked * unbond_ratio / 1000;
+
+        let undelegations = compute_undelegations(
+            Uint128::new(native_to_unbond),
+            &delegations,
+            "uxyz",
+            remainder_offset,
+        );
+
+        let native_undelegated: u128 = undelegations.iter().map(|u| u.amount).sum();
+        prop_assert_eq!(native_undelegated, native_to_unbond);
+
+        // Nobody is undelegated from twice, by more than they have, or below the even split
+        let native_per_validator = (native_staked - native_to_unbond) / amounts.len() as u128;
+        let mut seen: Vec<&str> = vec![];
+        for u in &undelegations {
+            prop_assert!(u.amount > 0);
+            prop_assert!(!seen.contains(&u.validator.as_str()));
+            seen.push(&u.validator);
+
+            let d = delegations.iter().find(|d| d.validator == u.validator).unwrap();
+            prop_assert!(u.amount <= d.amount);
+            prop_assert!(d.amount - u.amount >= native_per_validator);
+        }
+    }
+
+    #[test]
+    fn redelegations_for_removal_move_the_whole_delegation(
+        amounts in vec(0..=MAX_DELEGATION, 2..=MAX_VALIDATORS),
+        remainder_offset in 0usize..1000,
+    ) {
+        let delegations = delegations_from(&amounts);
+        let (remaining, removed) = delegations.split_at(delegations.len() - 1);
+        let delegation_to_remove = &removed[0];
+
+        let redelegations = compute_redelegations_for_removal(
+            delegation_to_remove,
+            remaining,
+            "uxyz",
+            remainder_offset,
+        );
+        assert_well_formed(&redelegations)?;
+
+        let native_redelegated: u128 = redelegations.iter().map(|r| r.amount).sum();
+        prop_assert_eq!(native_redelegated, delegation_to_remove.amount);
+
+        // Redelegations only fill validators up to the even split
+        let native_staked: u128 = amounts.iter().sum();
+        let native_per_validator = native_staked / remaining.len() as u128;
+        let new_amounts = apply_redelegations(&delegations, &redelegations);
+        for r in &redelegations {
+            prop_assert_eq!(&r.src, &delegation_to_remove.validator);
+            prop_assert!(new_amounts[&r.dst] <= native_per_validator + 1);
+        }
+    }
+
+    #[test]
+    fn rebalancing_converges_in_one_pass(
+        amounts in amounts_strategy(),
+        remainder_offset in 0usize..1000,
+    ) {
+        let delegations = delegations_from(&amounts);
+        let validators_active: Vec<String> =
+            delegations.iter().map(|d| d.validator.clone()).collect();
+        let native_staked: u128 = amounts.iter().sum();
+        let native_per_validator = native_staked / amounts.len() as u128;
+
+        let redelegations = compute_redelegations_for_rebalancing(
+            validators_active.clone(),
+            &delegations,
+            Uint128::zero(),
+            remainder_offset,
+            |_| Ok(native_per_validator.into()),
+        )
+        .unwrap();
+        assert_well_formed(&redelegations)?;
+
+        // Every validator ends up on the even split, so a second pass has nothing left to move
+        let new_amounts = apply_redelegations(&delegations, &redelegations);
+        let rebalanced: Vec<Delegation> = delegations
+            .iter()
+            .map(|d| Delegation::new(&d.validator, new_amounts[&d.validator], "uxyz"))
+            .collect();
+        for d in &rebalanced {
+            prop_assert!(d.amount == native_per_validator || d.amount == native_per_validator + 1);
+        }
+
+        let redelegations = compute_redelegations_for_rebalancing(
+            validators_active,
+            &rebalanced,
+            Uint128::zero(),
+            remainder_offset,
+            |_| Ok(native_per_validator.into()),
+        )
+        .unwrap();
+        prop_assert_eq!(redelegations, vec![]);
+    }
+
+    #[test]
+    fn rebalancing_moves_only_excess_to_active_validators(
+        amounts_and_active in vec((0..=MAX_DELEGATION, any::<bool>()), 1..=MAX_VALIDATORS),
+        min_difference in 0u128..1_000_000,
+        remainder_offset in 0usize..1000,
+    ) {
+        let amounts: Vec<u128> = amounts_and_active.iter().map(|(amount, _)| *amount).collect();
+        let delegations = delegations_from(&amounts);
+        let mut validators_active: Vec<String> = delegations
+            .iter()
+            .zip(&amounts_and_active)
+            .filter(|(_, (_, active))| *active)
+            .map(|(d, _)| d.validator.clone())
+            .collect();
+        if validators_active.is_empty() {
+            validators_active.push(delegations[0].validator.clone());
+        }
+        let native_staked: u128 = amounts.iter().sum();
+        let native_per_validator = native_staked / validators_active.len() as u128;
+
+        let redelegations = compute_redelegations_for_rebalancing(
+            validators_active.clone(),
+            &delegations,
+            Uint128::new(min_difference),
+            remainder_offset,
+            |d| {
+                Ok(if validators_active.contains(&d.validator) {
+                    native_per_validator.into()
+                } else {
+                    Uint128::zero()
+                })
+            },
+        )
+        .unwrap();
+        assert_well_formed(&redelegations)?;
+        prop_assert!(redelegations.len() <= 2 * delegations.len());
+
+        // Each source gives away no more than its excess over the target, plus the remainder unit
+        let new_amounts = apply_redelegations(&delegations, &redelegations);
+        for r in &redelegations {
+            prop_assert!(validators_active.contains(&r.dst));
+            prop_assert!(new_amounts[&r.dst] <= native_per_validator + 1);
+            let src_target = if validators_active.contains(&r.src) {
+                native_per_validator
+            } else {
+                0
+            };
+            prop_assert!(new_amounts[&r.src] >= src_target);
+        }
+    }
+
+    #[test]
+    fn mining_power_targets_split_the_total(
+        mining_powers in vec(0u128..1_000_000_000_000_000_000_000, 1..=MAX_VALIDATORS),
+        total_delegated in 0..=(MAX_DELEGATION * MAX_VALIDATORS as u128),
+    ) {
+        let total_mining_power: u128 = mining_powers.iter().sum();
+        prop_assume!(total_mining_power > 0);
+
+        let mut total_target = 0u128;
+        for mining_power in &mining_powers {
+            let target = compute_target_delegation_from_mining_power(
+                Uint128::new(total_delegated),
+                Uint128::new(*mining_power),
+                Uint128::new(total_mining_power),
+            )
+            .unwrap();
+            prop_assert!(target.u128() <= total_delegated);
+            total_target += target.u128();
+        }
+
+        // Each target is rounded down, losing less than two units
+        prop_assert!(total_target <= total_delegated);
+        prop_assert!(total_delegated - total_target < 2 * mining_powers.len() as u128);
+
+        let err = compute_target_delegation_from_mining_power(
+            Uint128::new(total_delegated),
+            Uint128::new(total_mining_power + 1),
+            Uint128::new(total_mining_power),
+        );
+        prop_assert!(err.is_err());
+    }
+}
@@ -1,4 +1,6 @@
 mod custom_querier;
 mod cw20_querier;
+mod fixtures;
 mod helpers;
+mod math_properties;
 mod tests;
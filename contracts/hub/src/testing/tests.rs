@@ -2,34 +2,67 @@ use std::io::Read;
 use std::ops::Mul;
 use std::str::FromStr;
 
+use cosmos_sdk_proto::cosmos::authz::v1beta1::MsgExec;
+use cosmos_sdk_proto::cosmos::distribution::v1beta1::MsgWithdrawDelegatorReward;
 use cosmos_sdk_proto::cosmos::staking::v1beta1::{MsgDelegate, MsgUndelegate};
 use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Event, Order, OwnedDeps,
-    Reply, ReplyOn, StdError, SubMsg, SubMsgResponse, Uint128, Uint64, WasmMsg,
+    attr, from_binary, from_slice, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal,
+    DepsMut, Empty, Event, Order, OverflowError, OverflowOperation, OwnedDeps, Reply, ReplyOn,
+    StdError, StdResult, SubMsg, SubMsgResponse, Uint128, Uint64, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, MinterResponse};
 use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
+use cw_storage_plus::{Item, Map};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
+use pfc_steak::chains::ChainPreset;
 use pfc_steak::hub::{
-    Batch, CallbackMsg, ConfigResponse, ExecuteMsg, InstantiateMsg, PendingBatch, QueryMsg,
-    ReceiveMsg, StateResponse, UnbondRequest, UnbondRequestsByBatchResponseItem,
-    UnbondRequestsByUserResponseItem,
+    AcceptedTokenResponse, Batch, BatchRollover, BatchShortfall, BondRateLimit, BondReceipt,
+    BridgeProtocol, CallbackMsg, Capabilities, ChainProfile, ChangeTimelock, ConfigChange,
+    ConfigResponse, Counters, DenomMetadataResponse, ExchangeRateFloorResponse, ExecuteMsg,
+    FeeLedgerEntry, FeeSplitInterface, FeeType, FeeVesting, FundsClassification, HarvestMode,
+    HookEventKind, InfoResponse, InstantiateMsg, MetricsResponse, MinerParamsResponse,
+    MiningPowerNormalization, OperationRecord, PendingBatch, PendingChange, QueryMsg,
+    RatePublisher, ReceiveMsg, ReceiveMsgKind, RecentClaim, ReconcileMode, ReferralStatsResponse,
+    RemainderPolicy, ReplayBatchResponse, RestakingAdapter, RestakingHookMsg, RewardCampaign,
+    ScheduleResponse, StakingProtoUrls, StateResponse, SteakHookMsg, SudoMsg, SupplyCheckpoint,
+    SwapHop, SwapRoute, SwapRouteResponse, TokenBackend, TreasuryConfig, TreasuryResponse,
+    UnbondCall, UnbondEntitlement, UnbondEtaResponse, UnbondEtaResponseItem, UnbondOrder,
+    UnbondReceipt, UnbondRequest, UnbondRequestsByBatchResponseItem,
+    UnbondRequestsBySenderResponseItem, UnbondRequestsByUserResponseItem, ValidatorCapResponse,
+    ValidatorDifficultyResponse, ValidatorNetAprResponse, ValidatorSummaryResponse,
+    VestedFeesResponse, WithdrawReceipt, WithdrawableAmountResponse,
 };
 
-use crate::contract::{
-    execute, instantiate, reply, REPLY_INSTANTIATE_TOKEN, REPLY_REGISTER_RECEIVED_COINS,
+use crate::contract::{execute, instantiate, migrate, query, reply, sudo, CONTRACT_VERSION};
+use crate::execute::{
+    MAX_FEE_EXEMPTIONS, MAX_REDELEGATIONS_PER_TX, MAX_UNBOND_CALLS_PER_REQUEST, MAX_UNBOND_ORDERS,
+};
+use crate::helpers::{
+    operator_account_address, parse_coin, parse_received_fund, parse_rfc3339_seconds,
 };
-use crate::helpers::{parse_coin, parse_received_fund};
 use crate::math::{
     compute_redelegations_for_rebalancing, compute_redelegations_for_removal,
     compute_target_delegation_from_mining_power, compute_undelegations,
+    compute_undelegations_with_floor,
+};
+use crate::migrations::count_previous_batches;
+use crate::state::{
+    State, BATCH_KEY_V101, FEE_LEDGER_CAPACITY, PENDING_BATCH_KEY_V101, RECENT_CLAIMS_CAPACITY,
+    SUPPLY_CHECKPOINT_CAPACITY,
+};
+use crate::types::{
+    burn_msg, create_denom_msg, mint_msg, swap_exact_amount_in_msg, Asset, AssetInfo,
+    AxelarGmpMemo, Coins, Delegation, FeeSplitV2ExecuteMsg, Ics20TransferMsg, MsgTransfer,
+    PairExecuteMsg, RatePayload, Redelegation, ReplyContext, RewardWithdrawal, SharesRedemption,
+    ToCosmosMsg, Undelegation, WormholeExecuteMsg,
 };
-use crate::state::State;
-use crate::types::{Coins, Delegation, Redelegation, RewardWithdrawal, Undelegation};
 
 use super::custom_querier::CustomQuerier;
-use super::helpers::{mock_dependencies, mock_env_at_timestamp, query_helper};
+use super::fixtures::{BatchBuilder, DelegationSetBuilder, Scenario, Step, UnbondRequestBuilder};
+use super::helpers::{apply_change, mock_dependencies, mock_env_at_timestamp, query_helper};
 
 //--------------------------------------------------------------------------------------------------
 // Test setup
@@ -48,11 +81,12 @@ fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
             name: "Steak Token".to_string(),
             symbol: "STEAK".to_string(),
             denom: "uxyz".to_string(),
-            fee_account_type: "Wallet".to_string(),
+            fee_account_type: FeeType::Wallet,
             fee_account: "the_fee_man".to_string(),
             fee_amount: Decimal::from_ratio(10_u128, 100_u128), //10%
             max_fee_amount: Decimal::from_ratio(20_u128, 100_u128), //20%
             decimals: 6,
+            denom_decimals: None,
             epoch_period: 259200,   // 3 * 24 * 60 * 60 = 3 days
             unbond_period: 1814400, // 21 * 24 * 60 * 60 = 21 days
             validators: vec![
@@ -62,6 +96,8 @@ fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
             ],
             label: None,
             marketing: None,
+            chain: None,
+            token_admin: None,
         },
     )
     .unwrap();
@@ -69,9 +105,9 @@ fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
     assert_eq!(res.messages.len(), 1);
     assert_eq!(
         res.messages[0],
-        SubMsg::reply_on_success(
+        SubMsg::reply_always(
             CosmosMsg::Wasm(WasmMsg::Instantiate {
-                admin: Some("larry".to_string()),
+                admin: Some(MOCK_CONTRACT_ADDR.to_string()),
                 code_id: 69420,
                 msg: to_binary(&Cw20InstantiateMsg {
                     name: "Steak Token".to_string(),
@@ -88,7 +124,7 @@ fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
                 funds: vec![],
                 label: "steak_token".to_string(),
             }),
-            REPLY_INSTANTIATE_TOKEN
+            1
         )
     );
 
@@ -100,7 +136,7 @@ fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
         deps.as_mut(),
         mock_env_at_timestamp(10000),
         Reply {
-            id: REPLY_INSTANTIATE_TOKEN,
+            id: 1,
             result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
                 events: vec![event],
                 data: None,
@@ -128,11 +164,12 @@ fn setup_test_fee_split() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
             name: "Steak Token".to_string(),
             symbol: "STEAK".to_string(),
             denom: "uxyz".to_string(),
-            fee_account_type: "FeeSplit".to_string(),
+            fee_account_type: FeeType::FeeSplit,
             fee_account: "fee_split_contract".to_string(),
             fee_amount: Decimal::from_ratio(10_u128, 100_u128), //10%
             max_fee_amount: Decimal::from_ratio(20_u128, 100_u128), //20%
             decimals: 6,
+            denom_decimals: None,
             epoch_period: 259200,   // 3 * 24 * 60 * 60 = 3 days
             unbond_period: 1814400, // 21 * 24 * 60 * 60 = 21 days
             validators: vec![
@@ -142,6 +179,8 @@ fn setup_test_fee_split() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
             ],
             label: None,
             marketing: None,
+            chain: None,
+            token_admin: None,
         },
     )
     .unwrap();
@@ -149,9 +188,9 @@ fn setup_test_fee_split() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
     assert_eq!(res.messages.len(), 1);
     assert_eq!(
         res.messages[0],
-        SubMsg::reply_on_success(
+        SubMsg::reply_always(
             CosmosMsg::Wasm(WasmMsg::Instantiate {
-                admin: Some("larry".to_string()),
+                admin: Some(MOCK_CONTRACT_ADDR.to_string()),
                 code_id: 69420,
                 msg: to_binary(&Cw20InstantiateMsg {
                     name: "Steak Token".to_string(),
@@ -168,7 +207,7 @@ fn setup_test_fee_split() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
                 funds: vec![],
                 label: "steak_token".to_string(),
             }),
-            REPLY_INSTANTIATE_TOKEN
+            1
         )
     );
 
@@ -180,7 +219,7 @@ fn setup_test_fee_split() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
         deps.as_mut(),
         mock_env_at_timestamp(10000),
         Reply {
-            id: REPLY_INSTANTIATE_TOKEN,
+            id: 1,
             result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
                 events: vec![event],
                 data: None,
@@ -210,6 +249,7 @@ fn proper_instantiation() {
             owner: "larry".to_string(),
             new_owner: None,
             steak_token: "steak_token".to_string(),
+            usteak_denom: None,
             epoch_period: 259200,
             unbond_period: 1814400,
             denom: "uxyz".to_string(),
@@ -217,11 +257,44 @@ fn proper_instantiation() {
             fee_account: "the_fee_man".to_string(),
             fee_rate: Decimal::from_ratio(10_u128, 100_u128),
             max_fee_rate: Decimal::from_ratio(20_u128, 100_u128),
+            fee_exemptions: vec![],
             validators: vec![
                 "alice".to_string(),
                 "bob".to_string(),
                 "charlie".to_string()
-            ]
+            ],
+            min_reinvest_amount: Uint128::zero(),
+            min_bond_amount: Uint128::zero(),
+            min_unbond_shares: Uint128::zero(),
+            max_rebase_bps: None,
+            max_harvest_staleness: None,
+            submit_deadline_buffer: None,
+            harvest_granter: None,
+            ics20_contract: None,
+            ics20_channel: None,
+            harvest_mode: HarvestMode::CoinReceivedEvents,
+            remainder_policy: RemainderPolicy::FirstValidators,
+            fee_split_interface: FeeSplitInterface::V1,
+            slash_limit: None,
+            referral_bps: 0,
+            fee_vesting: None,
+            batch_rollover: None,
+            restaking_adapter: None,
+            measure_deposits: false,
+            min_delegation_per_validator: Uint128::zero(),
+            bond_rate_limit: None,
+            min_harvest_reward: Uint128::zero(),
+            min_self_delegation: Uint128::zero(),
+            max_batch_exclusions: 0,
+            forbidden_deposit_denoms: vec![],
+            token_admin: MOCK_CONTRACT_ADDR.to_string(),
+            capabilities: Capabilities::default(),
+            reconcile_mode: ReconcileMode::BalanceDiff,
+            rate_publisher: None,
+            max_messages: None,
+            min_redelegation: Uint128::zero(),
+            priority_unbonders: vec![],
+            change_timelock: ChangeTimelock::default(),
         }
     );
 
@@ -233,6 +306,17 @@ fn proper_instantiation() {
             total_native: Uint128::zero(),
             exchange_rate: Decimal::one(),
             unlocked_coins: vec![],
+            last_harvest_time: None,
+            last_reinvest_amount: Uint128::zero(),
+            total_fees_collected: Uint128::zero(),
+            total_rewards_compounded: Uint128::zero(),
+            restaked_native: Uint128::zero(),
+            absorbed_native: Uint128::zero(),
+            fee_escrow: Uint128::zero(),
+            donations: Uint128::zero(),
+            last_harvester: None,
+            last_reconciler: None,
+            last_batch_submitter: None,
         },
     );
 
@@ -243,6 +327,17 @@ fn proper_instantiation() {
             id: 1,
             usteak_to_burn: Uint128::zero(),
             est_unbond_start_time: 269200, // 10,000 + 259,200
+            rollovers: 0,
+        },
+    );
+
+    let res: DenomMetadataResponse = query_helper(deps.as_ref(), QueryMsg::DenomMetadata {});
+    assert_eq!(
+        res,
+        DenomMetadataResponse {
+            denom: "uxyz".to_string(),
+            decimals: 6,
+            unit: Uint128::new(1000000),
         },
     );
     let deps_fee_split = setup_test_fee_split();
@@ -254,6 +349,7 @@ fn proper_instantiation() {
             owner: "larry".to_string(),
             new_owner: None,
             steak_token: "steak_token".to_string(),
+            usteak_denom: None,
             epoch_period: 259200,
             unbond_period: 1814400,
             denom: "uxyz".to_string(),
@@ -261,1316 +357,1129 @@ fn proper_instantiation() {
             fee_account: "fee_split_contract".to_string(),
             fee_rate: Decimal::from_ratio(10_u128, 100_u128),
             max_fee_rate: Decimal::from_ratio(20_u128, 100_u128),
+            fee_exemptions: vec![],
             validators: vec![
                 "alice".to_string(),
                 "bob".to_string(),
                 "charlie".to_string()
-            ]
+            ],
+            min_reinvest_amount: Uint128::zero(),
+            min_bond_amount: Uint128::zero(),
+            min_unbond_shares: Uint128::zero(),
+            max_rebase_bps: None,
+            max_harvest_staleness: None,
+            submit_deadline_buffer: None,
+            harvest_granter: None,
+            ics20_contract: None,
+            ics20_channel: None,
+            harvest_mode: HarvestMode::CoinReceivedEvents,
+            remainder_policy: RemainderPolicy::FirstValidators,
+            fee_split_interface: FeeSplitInterface::V1,
+            slash_limit: None,
+            referral_bps: 0,
+            fee_vesting: None,
+            batch_rollover: None,
+            restaking_adapter: None,
+            measure_deposits: false,
+            min_delegation_per_validator: Uint128::zero(),
+            bond_rate_limit: None,
+            min_harvest_reward: Uint128::zero(),
+            min_self_delegation: Uint128::zero(),
+            max_batch_exclusions: 0,
+            forbidden_deposit_denoms: vec![],
+            token_admin: MOCK_CONTRACT_ADDR.to_string(),
+            capabilities: Capabilities::default(),
+            reconcile_mode: ReconcileMode::BalanceDiff,
+            rate_publisher: None,
+            max_messages: None,
+            min_redelegation: Uint128::zero(),
+            priority_unbonders: vec![],
+            change_timelock: ChangeTimelock::default(),
         }
     );
 }
-
 #[test]
-fn bonding() {
-    let mut deps = setup_test();
-    let env = mock_env();
-    // Bond when no delegation has been made
-    // In this case, the full deposit simply goes to the first validator
-    let res = execute(
+fn retrying_token_instantiation() {
+    let mut deps = mock_dependencies();
+
+    let res = instantiate(
         deps.as_mut(),
-        env.clone(),
-        mock_info("user_1", &[Coin::new(1000000, "uxyz")]),
-        ExecuteMsg::Bond { receiver: None },
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        InstantiateMsg {
+            cw20_code_id: 69420,
+            owner: "larry".to_string(),
+            name: "Steak Token".to_string(),
+            symbol: "STEAK".to_string(),
+            denom: "uxyz".to_string(),
+            fee_account_type: FeeType::Wallet,
+            fee_account: "the_fee_man".to_string(),
+            fee_amount: Decimal::from_ratio(10_u128, 100_u128),
+            max_fee_amount: Decimal::from_ratio(20_u128, 100_u128),
+            decimals: 6,
+            denom_decimals: None,
+            epoch_period: 259200,
+            unbond_period: 1814400,
+            validators: vec!["alice".to_string()],
+            label: None,
+            marketing: None,
+            chain: None,
+            token_admin: None,
+        },
     )
     .unwrap();
+    let instantiate_msg = res.messages[0].msg.clone();
 
-    assert_eq!(res.messages.len(), 2);
-    assert_eq!(
-        res.messages[0],
-        SubMsg::reply_on_success(
-            Delegation::new("alice", 1000000, "uxyz")
-                .to_cosmos_msg(env.contract.address.to_string())
-                .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS
-        )
-    );
-    assert_eq!(
-        res.messages[1],
-        SubMsg {
-            id: 0,
-            msg: CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "steak_token".to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Mint {
-                    recipient: "user_1".to_string(),
-                    amount: Uint128::new(1000000)
-                })
-                .unwrap(),
-                funds: vec![]
-            }),
-            gas_limit: None,
-            reply_on: ReplyOn::Never,
-        }
-    );
-
-    // Bond when there are existing delegations, and Native Token:Steak exchange rate is >1
-    // Previously user 1 delegated 1,000,000 native_token. We assume we have accumulated 2.5% yield at 1025000 staked
-    deps.querier.set_staking_delegations(&[
-        Delegation::new("alice", 341667, "uxyz"),
-        Delegation::new("bob", 341667, "uxyz"),
-        Delegation::new("charlie", 341666, "uxyz"),
-    ]);
-    deps.querier.set_cw20_total_supply("steak_token", 1000000);
-
-    // Charlie has the smallest amount of delegation, so the full deposit goes to him
-    let res = execute(
+    // The token failing to instantiate leaves the hub without one, rather than failing as well
+    let res = reply(
         deps.as_mut(),
-        mock_env(),
-        mock_info("user_2", &[Coin::new(12345, "uxyz")]),
-        ExecuteMsg::Bond {
-            receiver: Some("user_3".to_string()),
+        mock_env_at_timestamp(10000),
+        Reply {
+            id: 1,
+            result: cosmwasm_std::SubMsgResult::Err("code id not found".to_string()),
         },
     )
     .unwrap();
-
-    assert_eq!(res.messages.len(), 2);
     assert_eq!(
-        res.messages[0],
-        SubMsg::reply_on_success(
-            Delegation::new("charlie", 12345, "uxyz")
-                .to_cosmos_msg(env.contract.address.to_string())
-                .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS
-        )
+        res.events,
+        vec![Event::new("steakhub/token_instantiation_failed")
+            .add_attribute("error", "Generic error: code id not found")]
     );
+    let state = State::default();
     assert_eq!(
-        res.messages[1],
-        SubMsg {
-            id: 0,
-            msg: CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "steak_token".to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Mint {
-                    recipient: "user_3".to_string(),
-                    amount: Uint128::new(12043)
-                })
-                .unwrap(),
-                funds: vec![]
-            }),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
-        }
+        state.steak_token.may_load(deps.as_ref().storage).unwrap(),
+        None
     );
 
-    // Check the state after bonding
-    deps.querier.set_staking_delegations(&[
-        Delegation::new("alice", 341667, "uxyz"),
-        Delegation::new("bob", 341667, "uxyz"),
-        Delegation::new("charlie", 354011, "uxyz"),
-    ]);
-    deps.querier.set_cw20_total_supply("steak_token", 1012043);
-
-    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::RetryTokenInstantiate {},
+    )
+    .unwrap_err();
     assert_eq!(
-        res,
-        StateResponse {
-            total_usteak: Uint128::new(1012043),
-            total_native: Uint128::new(1037345),
-            exchange_rate: Decimal::from_ratio(1037345u128, 1012043u128),
-            unlocked_coins: vec![],
-        }
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
     );
-}
-
-#[test]
-fn harvesting() {
-    let mut deps = setup_test();
-
-    // Assume users have bonded a total of 1,000,000 native_token and minted the same amount of usteak
-    deps.querier.set_staking_delegations(&[
-        Delegation::new("alice", 341667, "uxyz"),
-        Delegation::new("bob", 341667, "uxyz"),
-        Delegation::new("charlie", 341666, "uxyz"),
-    ]);
-    deps.querier.set_cw20_total_supply("steak_token", 1000000);
 
-    let harvest_env = mock_env();
     let res = execute(
         deps.as_mut(),
-        harvest_env.clone(),
-        mock_info(&harvest_env.contract.address.to_string(), &[]),
-        ExecuteMsg::Harvest {},
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::RetryTokenInstantiate {},
     )
     .unwrap();
+    assert_eq!(res.messages, vec![SubMsg::reply_always(instantiate_msg, 1)]);
 
-    assert_eq!(res.messages.len(), 4);
-    assert_eq!(
-        res.messages[0],
-        SubMsg::reply_on_success(
-            RewardWithdrawal {
-                validator: "alice".to_string(),
-            }
-            .to_cosmos_msg(harvest_env.contract.address.to_string())
-            .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS,
-        )
-    );
-    assert_eq!(
-        res.messages[1],
-        SubMsg::reply_on_success(
-            RewardWithdrawal {
-                validator: "bob".to_string(),
-            }
-            .to_cosmos_msg(harvest_env.contract.address.to_string())
-            .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS,
-        )
-    );
-    assert_eq!(
-        res.messages[2],
-        SubMsg::reply_on_success(
-            RewardWithdrawal {
-                validator: "charlie".to_string(),
-            }
-            .to_cosmos_msg(harvest_env.contract.address.to_string())
-            .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS,
-        )
-    );
-    assert_eq!(
-        res.messages[3],
-        SubMsg {
-            id: 0,
-            msg: CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
-                msg: to_binary(&ExecuteMsg::Callback(CallbackMsg::Reinvest {})).unwrap(),
-                funds: vec![]
+    // A reply missing the contract's address is no better
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+                events: vec![Event::new("instantiate").add_attribute("code_id", "69420")],
+                data: None,
             }),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
-        }
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![
+            Event::new("steakhub/token_instantiation_failed").add_attribute(
+                "error",
+                "Generic error: cannot find `_contract_address` attribute"
+            )
+        ]
     );
-}
-
-#[test]
-fn registering_unlocked_coins() {
-    let mut deps = setup_test();
-    let state = State::default();
-
-    // After withdrawing staking rewards, we parse the `coin_received` event to find the received amounts
-    let event = Event::new("coin_received")
-        .add_attribute("receiver", MOCK_CONTRACT_ADDR.to_string())
-        .add_attribute("amount", "123ukrw,234uxyz,345uusd,69420ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B");
 
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::RetryTokenInstantiate {},
+    )
+    .unwrap();
     reply(
         deps.as_mut(),
         mock_env(),
         Reply {
-            id: 2,
+            id: 1,
             result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
-                events: vec![event],
+                events: vec![Event::new("instantiate")
+                    .add_attribute("code_id", "69420")
+                    .add_attribute("_contract_address", "steak_token")],
                 data: None,
             }),
         },
     )
     .unwrap();
+    assert_eq!(
+        state.steak_token.load(deps.as_ref().storage).unwrap(),
+        Addr::unchecked("steak_token")
+    );
 
-    // Unlocked coins in contract state should have been updated
-    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::RetryTokenInstantiate {},
+    )
+    .unwrap_err();
     assert_eq!(
-        unlocked_coins,
-        vec![
-            Coin::new(123, "ukrw"),
-            Coin::new(234, "uxyz"),
-            Coin::new(345, "uusd"),
-            Coin::new(
-                69420,
-                "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
-            ),
-        ]
+        err,
+        StdError::generic_err("steak token is already instantiated")
     );
 }
-
 #[test]
-fn reinvesting() {
-    let mut deps = setup_test();
-    let state = State::default();
+fn instantiating_with_token_label_and_admin() {
+    let msg = InstantiateMsg {
+        cw20_code_id: 69420,
+        owner: "larry".to_string(),
+        name: "Steak Token".to_string(),
+        symbol: "STEAK".to_string(),
+        denom: "uxyz".to_string(),
+        fee_account_type: FeeType::Wallet,
+        fee_account: "the_fee_man".to_string(),
+        fee_amount: Decimal::from_ratio(10_u128, 100_u128),
+        max_fee_amount: Decimal::from_ratio(20_u128, 100_u128),
+        decimals: 6,
+        denom_decimals: None,
+        epoch_period: 259200,
+        unbond_period: 1814400,
+        validators: vec!["alice".to_string()],
+        label: Some("  steak_token_hub_7 ".to_string()),
+        marketing: None,
+        chain: None,
+        token_admin: Some("deployer".to_string()),
+    };
 
-    deps.querier.set_staking_delegations(&[
-        Delegation::new("alice", 333334, "uxyz"),
-        Delegation::new("bob", 333333, "uxyz"),
-        Delegation::new("charlie", 333333, "uxyz"),
-    ]);
-    state
-        .prev_denom
-        .save(deps.as_mut().storage, &Uint128::from(0_u32))
-        .unwrap();
-    deps.querier
-        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
-
-    // After the swaps, `unlocked_coins` should contain only uxyz and unknown denoms
-    state
-        .unlocked_coins
-        .save(
-            deps.as_mut().storage,
-            &vec![
-                Coin::new(234, "uxyz"),
-                Coin::new(
-                    69420,
-                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
-                ),
-            ],
+    for (label, error) in [
+        ("   ", "token label can't be empty"),
+        (
+            "steak\ntoken",
+            "token label can't contain control characters",
+        ),
+    ] {
+        let err = instantiate(
+            mock_dependencies().as_mut(),
+            mock_env_at_timestamp(10000),
+            mock_info("deployer", &[]),
+            InstantiateMsg {
+                label: Some(label.to_string()),
+                ..msg.clone()
+            },
         )
-        .unwrap();
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err(error));
+    }
+    let err = instantiate(
+        mock_dependencies().as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        InstantiateMsg {
+            label: Some("s".repeat(129)),
+            ..msg.clone()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("token label can't be longer than 128 characters")
+    );
 
-    let modifier = 1_000_000_000_000_000_000_u128;
+    let mut deps = mock_dependencies();
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        msg,
+    )
+    .unwrap();
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Instantiate { admin, label, .. }) => {
+            assert_eq!(admin, &Some("deployer".to_string()));
+            assert_eq!(label, "steak_token_hub_7");
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+    reply(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        Reply {
+            id: 1,
+            result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+                events: vec![Event::new("instantiate")
+                    .add_attribute("code_id", "69420")
+                    .add_attribute("_contract_address", "steak_token")],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
 
-    state
-        .total_mining_power
-        .save(deps.as_mut().storage, &Uint128::from(15_u128.mul(modifier)))
-        .unwrap();
+    // The hub can neither migrate a token it doesn't administer nor hand it to someone else
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.token_admin, "deployer".to_string());
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::MigrateTokenContract {
+            new_code_id: 69421,
+            msg: to_binary(&Empty {}).unwrap(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("steak token is administered by deployer, not the hub")
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetTokenAdmin {
+            admin: "multisig".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("steak token is administered by deployer, not the hub")
+    );
+}
 
-    state
-        .validator_mining_powers
-        .save(
-            deps.as_mut().storage,
-            "alice".to_string(),
-            &5_u128.mul(modifier).into(),
-        )
-        .unwrap();
-    state
-        .validator_mining_powers
-        .save(
-            deps.as_mut().storage,
-            "bob".to_string(),
-            &5_u128.mul(modifier).into(),
-        )
-        .unwrap();
-    state
-        .validator_mining_powers
-        .save(
-            deps.as_mut().storage,
-            "charlie".to_string(),
-            &5_u128.mul(modifier).into(),
-        )
-        .unwrap();
+#[test]
+fn setting_token_admin() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetTokenAdmin {
+            admin: "multisig".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
 
-    let env = mock_env();
-    // Bob has the smallest amount of delegations, so all proceeds go to him
     let res = execute(
         deps.as_mut(),
-        env.clone(),
-        mock_info(MOCK_CONTRACT_ADDR, &[]),
-        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetTokenAdmin {
+            admin: "multisig".to_string(),
+        },
     )
     .unwrap();
-
-    // decode first message as to MsgUndelegate
-    let decoded_message =
-        if let CosmosMsg::Stargate { type_url, value } = res.messages[0].msg.clone() {
-            // assert_eq!(type_url, "/liquidstaking.staking.v1beta1.MsgDelegate");
-            let msg_decoded: MsgDelegate = prost::Message::decode(value.as_slice()).unwrap();
-            // assert_eq!(msg_decoded.validator_address, "bob");
-            Some(msg_decoded)
-        } else {
-            None
-        };
-    // decode all messages to MsgUndelegate and transpose as result
-    let decoded_messages = res
-        .messages
-        .iter()
-        .map(|msg| {
-            if let CosmosMsg::Stargate { type_url, value } = msg.msg.clone() {
-                // assert_eq!(type_url, "/liquidstaking.staking.v1beta1.MsgDelegate");
-                let msg_decoded: MsgDelegate = prost::Message::decode(value.as_slice()).unwrap();
-                // assert_eq!(msg_decoded.validator_address, "bob");
-                Some(msg_decoded)
-            } else {
-                None
-            }
-        })
-        .filter(Option::is_some)
-        .collect::<Option<Vec<MsgDelegate>>>()
-        .unwrap();
-
-    assert_eq!(res.messages.len(), 2);
     assert_eq!(
-        res.messages[0],
-        SubMsg {
-            id: 0,
-            msg: Delegation::new("bob", 234 - 23, "uxyz")
-                .to_cosmos_msg(env.contract.address.to_string())
-                .unwrap(),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
-        },
-        "bob"
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::UpdateAdmin {
+            contract_addr: "steak_token".to_string(),
+            admin: "multisig".to_string(),
+        }))]
     );
-    let send_msg = BankMsg::Send {
-        to_address: "the_fee_man".into(),
-        amount: vec![Coin::new(23u128, "uxyz")],
+    assert!(res
+        .attributes
+        .contains(&attr("action", "steakhub/set_token_admin")));
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.token_admin, "multisig".to_string());
+}
+
+#[test]
+fn instantiating_with_chain_preset() {
+    let msg = InstantiateMsg {
+        cw20_code_id: 69420,
+        owner: "larry".to_string(),
+        name: "Steak Token".to_string(),
+        symbol: "STEAK".to_string(),
+        denom: "uwhale".to_string(),
+        fee_account_type: FeeType::Wallet,
+        fee_account: "the_fee_man".to_string(),
+        fee_amount: Decimal::from_ratio(10_u128, 100_u128),
+        max_fee_amount: Decimal::from_ratio(20_u128, 100_u128),
+        decimals: 6,
+        denom_decimals: None,
+        epoch_period: 259200,
+        unbond_period: 1814400,
+        validators: vec![
+            "migaloovaloper1alice".to_string(),
+            "migaloovaloper1bob".to_string(),
+        ],
+        label: None,
+        marketing: None,
+        chain: Some(ChainPreset::Migaloo),
+        token_admin: None,
     };
+
+    let mut deps = mock_dependencies();
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        InstantiateMsg {
+            denom: "ujuno".to_string(),
+            ..msg.clone()
+        },
+    )
+    .unwrap_err();
     assert_eq!(
-        res.messages[1],
-        SubMsg {
-            id: 0,
-            msg: CosmosMsg::Bank(send_msg),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
+        err,
+        StdError::generic_err("denom ujuno doesn't match the uwhale of migaloo-1")
+    );
+
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        InstantiateMsg {
+            unbond_period: 2419200,
+            ..msg.clone()
         },
-        "fee"
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unbond period 2419200 doesn't match the 1814400 of migaloo-1")
     );
 
-    // Storage should have been updated
-    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        InstantiateMsg {
+            validators: vec!["junovaloper1alice".to_string()],
+            ..msg.clone()
+        },
+    )
+    .unwrap_err();
     assert_eq!(
-        unlocked_coins,
-        vec![Coin::new(
-            69420,
-            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
-        )],
-        "unlocked_coins"
+        err,
+        StdError::generic_err("junovaloper1alice is not a validator address on migaloo-1")
     );
+
+    instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        msg,
+    )
+    .unwrap();
+
+    let res: StakingProtoUrls = query_helper(deps.as_ref(), QueryMsg::StakingProtoUrls {});
+    assert_eq!(res, ChainPreset::Migaloo.staking_proto_urls());
+    assert_eq!(res.delegate, "/cosmos.staking.v1beta1.MsgDelegate");
+
+    let preset = State::default()
+        .chain_preset
+        .load(deps.as_ref().storage)
+        .unwrap();
+    assert_eq!(preset, ChainPreset::Migaloo);
 }
 
 #[test]
-fn reinvesting_with_mining() {
+fn setting_denom_decimals() {
     let mut deps = setup_test();
-    let state = State::default();
 
-    deps.querier.set_staking_delegations(&[
-        Delegation::new("alice", 333334, "uxyz"),
-        Delegation::new("bob", 333333, "uxyz"),
-        Delegation::new("charlie", 333333, "uxyz"),
-    ]);
-    state
-        .prev_denom
-        .save(deps.as_mut().storage, &Uint128::from(0_u32))
-        .unwrap();
-    deps.querier
-        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetDenomDecimals { decimals: 18 },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
 
-    // After the swaps, `unlocked_coins` should contain only uxyz and unknown denoms
-    state
-        .unlocked_coins
-        .save(
-            deps.as_mut().storage,
-            &vec![
-                Coin::new(234, "uxyz"),
-                Coin::new(
-                    69420,
-                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
-                ),
-            ],
-        )
-        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetDenomDecimals { decimals: 19 },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("decimals cannot exceed 18"));
 
-    let modifier = 1_000_000_000_000_000_000_u128;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetDenomDecimals { decimals: 18 },
+    )
+    .unwrap();
 
-    state
-        .total_mining_power
-        .save(deps.as_mut().storage, &Uint128::from(15_u128.mul(modifier)))
-        .unwrap();
+    let res: DenomMetadataResponse = query_helper(deps.as_ref(), QueryMsg::DenomMetadata {});
+    assert_eq!(res.decimals, 18);
+    assert_eq!(res.unit, Uint128::new(1_000_000_000_000_000_000));
 
-    state
-        .validator_mining_powers
-        .save(
-            deps.as_mut().storage,
-            "alice".to_string(),
-            &4_u128.mul(modifier).into(),
-        )
-        .unwrap();
-    state
-        .validator_mining_powers
-        .save(
-            deps.as_mut().storage,
-            "bob".to_string(),
-            &4_u128.mul(modifier).into(),
-        )
-        .unwrap();
-    state
-        .validator_mining_powers
-        .save(
-            deps.as_mut().storage,
-            "charlie".to_string(),
-            &7_u128.mul(modifier).into(),
-        )
-        .unwrap();
+    // Minimums are shown in whole units of the bond denom, not of the Steak token
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMinBondAmount {
+            min_bond_amount: Uint128::new(1_500_000_000_000_000_000),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.events[0].attributes[1], attr("whole_units", "1.5"));
+}
 
+#[test]
+fn bonding() {
+    let mut deps = setup_test();
     let env = mock_env();
-    // Bob has the smallest amount of delegations, so all proceeds go to him
+    // Bond when no delegation has been made
+    // In this case, the full deposit simply goes to the first validator
     let res = execute(
         deps.as_mut(),
         env.clone(),
-        mock_info(MOCK_CONTRACT_ADDR, &[]),
-        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+        mock_info("user_1", &[Coin::new(1000000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
     )
     .unwrap();
 
-    // decode first message as to MsgUndelegate
-    let decoded_message =
-        if let CosmosMsg::Stargate { type_url, value } = res.messages[0].msg.clone() {
-            // assert_eq!(type_url, "/liquidstaking.staking.v1beta1.MsgDelegate");
-            let msg_decoded: MsgDelegate = prost::Message::decode(value.as_slice()).unwrap();
-            // assert_eq!(msg_decoded.validator_address, "bob");
-            Some(msg_decoded)
-        } else {
-            None
-        };
-    // decode all messages to MsgUndelegate and transpose as result
-    let decoded_messages = res
-        .messages
-        .iter()
-        .map(|msg| {
-            if let CosmosMsg::Stargate { type_url, value } = msg.msg.clone() {
-                // assert_eq!(type_url, "/liquidstaking.staking.v1beta1.MsgDelegate");
-                let msg_decoded: MsgDelegate = prost::Message::decode(value.as_slice()).unwrap();
-                // assert_eq!(msg_decoded.validator_address, "bob");
-                Some(msg_decoded)
-            } else {
-                None
-            }
-        })
-        .filter(Option::is_some)
-        .collect::<Option<Vec<MsgDelegate>>>()
-        .unwrap();
-
     assert_eq!(res.messages.len(), 2);
     assert_eq!(
         res.messages[0],
-        SubMsg {
-            id: 0,
-            msg: Delegation::new("charlie", 234 - 23, "uxyz")
-                .to_cosmos_msg(env.contract.address.to_string())
+        SubMsg::reply_on_success(
+            Delegation::new("alice", 1000000, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
                 .unwrap(),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
-        },
-        "charlie"
+            1
+        )
     );
-    let send_msg = BankMsg::Send {
-        to_address: "the_fee_man".into(),
-        amount: vec![Coin::new(23u128, "uxyz")],
-    };
     assert_eq!(
         res.messages[1],
         SubMsg {
             id: 0,
-            msg: CosmosMsg::Bank(send_msg),
+            msg: CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "steak_token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: "user_1".to_string(),
+                    amount: Uint128::new(1000000)
+                })
+                .unwrap(),
+                funds: vec![]
+            }),
             gas_limit: None,
-            reply_on: ReplyOn::Never
-        },
-        "fee"
-    );
-
-    // Storage should have been updated
-    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
-    assert_eq!(
-        unlocked_coins,
-        vec![Coin::new(
-            69420,
-            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
-        )],
-        "unlocked_coins"
+            reply_on: ReplyOn::Never,
+        }
     );
-}
 
-#[test]
-fn reinvesting_fee_split() {
-    let mut deps = setup_test_fee_split();
-    let state = State::default();
-    let env = mock_env();
+    // Bond when there are existing delegations, and Native Token:Steak exchange rate is >1
+    // Previously user 1 delegated 1,000,000 native_token. We assume we have accumulated 2.5% yield at 1025000 staked
     deps.querier.set_staking_delegations(&[
-        Delegation::new("alice", 333334, "uxyz"),
-        Delegation::new("bob", 333333, "uxyz"),
-        Delegation::new("charlie", 333333, "uxyz"),
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
     ]);
-    state
-        .prev_denom
-        .save(deps.as_mut().storage, &Uint128::from(0_u32))
-        .unwrap();
-    deps.querier
-        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
-
-    // After the swaps, `unlocked_coins` should contain only uxyz and unknown denoms
-    state
-        .unlocked_coins
-        .save(
-            deps.as_mut().storage,
-            &vec![
-                Coin::new(234, "uxyz"),
-                Coin::new(
-                    69420,
-                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
-                ),
-            ],
-        )
-        .unwrap();
-
-    let modifier = 1_000_000_000_000_000_000_u128;
-
-    state
-        .total_mining_power
-        .save(deps.as_mut().storage, &Uint128::from(15_u128.mul(modifier)))
-        .unwrap();
-
-    state
-        .validator_mining_powers
-        .save(
-            deps.as_mut().storage,
-            "alice".to_string(),
-            &1_u128.mul(modifier).into(),
-        )
-        .unwrap();
-    state
-        .validator_mining_powers
-        .save(
-            deps.as_mut().storage,
-            "bob".to_string(),
-            &12_u128.mul(modifier).into(),
-        )
-        .unwrap();
-    state
-        .validator_mining_powers
-        .save(
-            deps.as_mut().storage,
-            "charlie".to_string(),
-            &2_u128.mul(modifier).into(),
-        )
-        .unwrap();
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
 
-    // Bob has the smallest amount of delegations, so all proceeds go to him
+    // Charlie has the smallest amount of delegation, so the full deposit goes to him
     let res = execute(
         deps.as_mut(),
-        env.clone(),
-        mock_info(MOCK_CONTRACT_ADDR, &[]),
-        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+        mock_env(),
+        mock_info("user_2", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: Some("user_3".to_string()),
+            referrer: None,
+            min_usteak_out: None,
+        },
     )
     .unwrap();
 
     assert_eq!(res.messages.len(), 2);
     assert_eq!(
         res.messages[0],
-        SubMsg {
-            id: 0,
-            msg: Delegation::new("bob", 234 - 23, "uxyz")
-                .to_cosmos_msg(env.contract.address.to_string())
+        SubMsg::reply_on_success(
+            Delegation::new("charlie", 12345, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
                 .unwrap(),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
-        }
+            2
+        )
     );
-    let send_msg = pfc_fee_split::fee_split_msg::ExecuteMsg::Deposit { flush: false };
-
     assert_eq!(
         res.messages[1],
         SubMsg {
             id: 0,
-            msg: send_msg
-                .into_cosmos_msg("fee_split_contract", vec![Coin::new(23u128, "uxyz")])
+            msg: CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "steak_token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: "user_3".to_string(),
+                    amount: Uint128::new(12043)
+                })
                 .unwrap(),
+                funds: vec![]
+            }),
             gas_limit: None,
             reply_on: ReplyOn::Never
         }
     );
-
-    // Storage should have been updated
-    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
     assert_eq!(
-        unlocked_coins,
-        vec![Coin::new(
-            69420,
-            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
-        )],
+        from_binary::<BondReceipt>(&res.data.unwrap()).unwrap(),
+        BondReceipt {
+            receiver: "user_3".to_string(),
+            native_bonded: Uint128::new(12345),
+            usteak_minted: Uint128::new(12043),
+            exchange_rate: Decimal::from_ratio(1025000u128, 1000000u128),
+        }
     );
-}
 
-#[test]
-fn queuing_unbond() {
-    let mut deps = setup_test();
-    let state = State::default();
+    // Check the state after bonding
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 354011, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
 
-    // Only Steak token is accepted for unbonding requests
-    let err = execute(
-        deps.as_mut(),
-        mock_env(),
-        mock_info("random_token", &[]),
-        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
-            sender: "hacker".to_string(),
-            amount: Uint128::new(69420),
-            msg: to_binary(&ReceiveMsg::QueueUnbond { receiver: None }).unwrap(),
-        }),
-    )
-    .unwrap_err();
-
-    assert_eq!(
-        err,
-        StdError::generic_err("expecting Steak token, received random_token")
-    );
-
-    // User 1 creates an unbonding request before `est_unbond_start_time` is reached. The unbond
-    // request is saved, but not the pending batch is not submitted for unbonding
-    let res = execute(
-        deps.as_mut(),
-        mock_env_at_timestamp(12345), // est_unbond_start_time = 269200
-        mock_info("steak_token", &[]),
-        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
-            sender: "user_1".to_string(),
-            amount: Uint128::new(23456),
-            msg: to_binary(&ReceiveMsg::QueueUnbond { receiver: None }).unwrap(),
-        }),
-    )
-    .unwrap();
-
-    assert_eq!(res.messages.len(), 0);
-
-    // User 2 creates an unbonding request after `est_unbond_start_time` is reached. The unbond
-    // request is saved, and the pending is automatically submitted for unbonding
-    let res = execute(
-        deps.as_mut(),
-        mock_env_at_timestamp(269201), // est_unbond_start_time = 269200
-        mock_info("steak_token", &[]),
-        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
-            sender: "user_2".to_string(),
-            amount: Uint128::new(69420),
-            msg: to_binary(&ReceiveMsg::QueueUnbond {
-                receiver: Some("user_3".to_string()),
-            })
-            .unwrap(),
-        }),
-    )
-    .unwrap();
-
-    assert_eq!(res.messages.len(), 1);
-    assert_eq!(
-        res.messages[0],
-        SubMsg {
-            id: 0,
-            msg: CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
-                msg: to_binary(&ExecuteMsg::SubmitBatch {}).unwrap(),
-                funds: vec![]
-            }),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
-        }
-    );
-
-    // The users' unbonding requests should have been saved
-    let ubr1 = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
-        .unwrap();
-    let ubr2 = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_3")))
-        .unwrap();
-
-    assert_eq!(
-        ubr1,
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(23456)
-        }
-    );
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
     assert_eq!(
-        ubr2,
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_3"),
-            shares: Uint128::new(69420)
+        res,
+        StateResponse {
+            total_usteak: Uint128::new(1012043),
+            total_native: Uint128::new(1037345),
+            exchange_rate: Decimal::from_ratio(1037345u128, 1012043u128),
+            unlocked_coins: vec![],
+            last_harvest_time: None,
+            last_reinvest_amount: Uint128::zero(),
+            total_fees_collected: Uint128::zero(),
+            total_rewards_compounded: Uint128::zero(),
+            restaked_native: Uint128::zero(),
+            absorbed_native: Uint128::zero(),
+            fee_escrow: Uint128::zero(),
+            donations: Uint128::zero(),
+            last_harvester: None,
+            last_reconciler: None,
+            last_batch_submitter: None,
         }
     );
 
-    // Pending batch should have been updated
-    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
+    // Both receivers are counted as stakers
+    let res: MetricsResponse = query_helper(deps.as_ref(), QueryMsg::Metrics {});
     assert_eq!(
-        pending_batch,
-        PendingBatch {
-            id: 1,
-            usteak_to_burn: Uint128::new(92876), // 23,456 + 69,420
-            est_unbond_start_time: 269200
+        res,
+        MetricsResponse {
+            total_native: Uint128::new(1037345),
+            total_usteak: Uint128::new(1012043),
+            num_stakers: 2,
+            num_validators: 3,
+            pending_batch_id: 1,
+            pending_usteak_to_burn: Uint128::zero(),
+            pending_est_unbond_start_time: 269200,
+            num_previous_batches: 0,
+            native_unclaimed: Uint128::zero(),
         }
     );
 }
-
 #[test]
-fn submitting_batch() {
+fn bonding_refuses_derivatives() {
     let mut deps = setup_test();
-    let state = State::default();
-
-    // native_token bonded: 1,037,345
-    // usteak supply: 1,012,043
-    // native_token per ustake: 1.025
-    deps.querier.set_staking_delegations(&[
-        Delegation::new("alice", 345782, "uxyz"),
-        Delegation::new("bob", 345782, "uxyz"),
-        Delegation::new("charlie", 345781, "uxyz"),
-    ]);
-    deps.querier.set_cw20_total_supply("steak_token", 1012043);
-
-    // We continue from the contract state at the end of the last test
-    let unbond_requests = vec![
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(23456),
-        },
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_3"),
-            shares: Uint128::new(69420),
-        },
-    ];
-
-    for unbond_request in &unbond_requests {
-        state
-            .unbond_requests
-            .save(
-                deps.as_mut().storage,
-                (
-                    unbond_request.id,
-                    &Addr::unchecked(unbond_request.user.clone()),
-                ),
-                unbond_request,
-            )
-            .unwrap();
-    }
 
-    state
-        .pending_batch
+    State::default()
+        .usteak_denom
         .save(
             deps.as_mut().storage,
-            &PendingBatch {
-                id: 1,
-                usteak_to_burn: Uint128::new(92876), // 23,456 + 69,420
-                est_unbond_start_time: 269200,
-            },
+            &"factory/cosmos2contract/steak".to_string(),
         )
         .unwrap();
 
-    // Anyone can invoke `submit_batch`. Here we continue from the previous test and assume it is
-    // invoked automatically as user 2 submits the unbonding request
-    //
-    // usteak to burn: 23,456 + 69,420 = 92,876
-    // native_token to unbond: 1,037,345 * 92,876 / 1,012,043 = 95,197
-    //
-    // Target: (1,037,345 - 95,197) / 3 = 314,049
-    // Remainer: 1
-    // Alice:   345,782 - (314,049 + 1) = 31,732
-    // Bob:     345,782 - (314,049 + 0) = 31,733
-    // Charlie: 345,781 - (314,049 + 0) = 31,732
-    let env_at_ts = mock_env_at_timestamp(269201);
-    let res = execute(
+    let err = execute(
         deps.as_mut(),
-        env_at_ts.clone(),
-        mock_info(MOCK_CONTRACT_ADDR, &[]),
-        ExecuteMsg::SubmitBatch {},
+        mock_env(),
+        mock_info(
+            "user_1",
+            &[Coin::new(1000, "factory/cosmos2contract/steak")],
+        ),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
     )
-    .unwrap();
-
-    assert_eq!(res.messages.len(), 4);
-    assert_eq!(
-        res.messages[0],
-        SubMsg::reply_on_success(
-            Undelegation::new("alice", 31732, "uxyz")
-                .to_cosmos_msg(env_at_ts.contract.address.to_string())
-                .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS
-        )
-    );
-    assert_eq!(
-        res.messages[1],
-        SubMsg::reply_on_success(
-            Undelegation::new("bob", 31733, "uxyz")
-                .to_cosmos_msg(env_at_ts.contract.address.to_string())
-                .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS
-        )
-    );
+    .unwrap_err();
     assert_eq!(
-        res.messages[2],
-        SubMsg::reply_on_success(
-            Undelegation::new("charlie", 31732, "uxyz")
-                .to_cosmos_msg(env_at_ts.contract.address.to_string())
-                .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS
+        err,
+        StdError::generic_err(
+            "factory/cosmos2contract/steak is this hub's own Steak and can't be bonded"
         )
     );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetForbiddenDepositDenoms {
+            denoms: vec!["uxyz".to_string()],
+        },
+    )
+    .unwrap_err();
     assert_eq!(
-        res.messages[3],
-        SubMsg {
-            id: 0,
-            msg: CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: "steak_token".to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Burn {
-                    amount: Uint128::new(92876)
-                })
-                .unwrap(),
-                funds: vec![]
-            }),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
-        }
+        err,
+        StdError::generic_err("uxyz is the Native Token and can't be forbidden")
     );
 
-    // A new pending batch should have been created
-    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetForbiddenDepositDenoms {
+            denoms: vec!["factory/other_hub/ampxyz".to_string()],
+        },
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
     assert_eq!(
-        pending_batch,
-        PendingBatch {
-            id: 2,
-            usteak_to_burn: Uint128::zero(),
-            est_unbond_start_time: 528401 // 269,201 + 259,200
-        }
+        res.forbidden_deposit_denoms,
+        vec!["factory/other_hub/ampxyz".to_string()]
     );
 
-    // Previous batch should have been updated
-    let previous_batch = state
-        .previous_batches
-        .load(deps.as_ref().storage, 1u64)
-        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000, "factory/other_hub/ampxyz")]),
+        ExecuteMsg::BondMany {
+            receivers: vec![("user_1".to_string(), Uint128::new(1000))],
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap_err();
     assert_eq!(
-        previous_batch,
-        Batch {
-            id: 1,
-            reconciled: false,
-            total_shares: Uint128::new(92876),
-            amount_unclaimed: Uint128::new(95197),
-            est_unbond_end_time: 2083601 // 269,201 + 1,814,400
-        }
+        err,
+        StdError::generic_err(
+            "factory/other_hub/ampxyz is a liquid staking derivative and can't be bonded"
+        )
     );
 }
-
 #[test]
-fn reconciling() {
+fn pausing_operations() {
     let mut deps = setup_test();
-    let state = State::default();
 
-    let previous_batches = vec![
-        Batch {
-            id: 1,
-            reconciled: true,
-            total_shares: Uint128::new(92876),
-            amount_unclaimed: Uint128::new(95197), // 1.025 Native Token per Steak
-            est_unbond_end_time: 10000,
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetCapabilities {
+            capabilities: Capabilities::default(),
         },
-        Batch {
-            id: 2,
-            reconciled: false,
-            total_shares: Uint128::new(1345),
-            amount_unclaimed: Uint128::new(1385), // 1.030 Native Token per Steak
-            est_unbond_end_time: 20000,
-        },
-        Batch {
-            id: 3,
-            reconciled: false,
-            total_shares: Uint128::new(1456),
-            amount_unclaimed: Uint128::new(1506), // 1.035 Native Token per Steak
-            est_unbond_end_time: 30000,
-        },
-        Batch {
-            id: 4,
-            reconciled: false,
-            total_shares: Uint128::new(1567),
-            amount_unclaimed: Uint128::new(1629), // 1.040 Native Token per Steak
-            est_unbond_end_time: 40000,           // not yet finished unbonding, ignored
-        },
-    ];
-
-    for previous_batch in &previous_batches {
-        state
-            .previous_batches
-            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
-            .unwrap();
-    }
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
 
-    state
-        .unlocked_coins
-        .save(
-            deps.as_mut().storage,
-            &vec![
-                Coin::new(10000, "uxyz"),
-                Coin::new(234, "ukrw"),
-                Coin::new(345, "uusd"),
-                Coin::new(
-                    69420,
-                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
-                ),
-            ],
-        )
-        .unwrap();
+    // Halting bonds leaves users free to exit
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetCapabilities {
+            capabilities: Capabilities {
+                bond: false,
+                ..Capabilities::default()
+            },
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert!(!res.capabilities.bond);
+    assert!(res.capabilities.unbond);
 
-    deps.querier.set_bank_balances(&[
-        Coin::new(12345, "uxyz"),
-        Coin::new(234, "ukrw"),
-        Coin::new(345, "uusd"),
-        Coin::new(
-            69420,
-            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
-        ),
-    ]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("bonding is paused"));
+
+    let queue_unbond = ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+        sender: "user_1".to_string(),
+        amount: Uint128::new(500),
+        msg: to_binary(&ReceiveMsg::QueueUnbond {
+            receiver: None,
+            min_native_out: None,
+        })
+        .unwrap(),
+    });
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("steak_token", &[]),
+        queue_unbond.clone(),
+    )
+    .unwrap();
 
     execute(
         deps.as_mut(),
-        mock_env_at_timestamp(35000),
-        mock_info("worker", &[]),
-        ExecuteMsg::Reconcile {},
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetCapabilities {
+            capabilities: Capabilities {
+                bond: true,
+                unbond: false,
+                withdraw: false,
+                harvest: false,
+                submit_batch: false,
+            },
+        },
     )
     .unwrap();
 
-    // Expected received: batch 2 + batch 3 = 1385 + 1506 = 2891
-    // Expected unlocked: 10000
-    // Expected: 12891
-    // Actual: 12345
-    // Shortfall: 12891 - 12345 = 456
-    //
-    // native_token per batch: 546 / 2 = 273
-    // remainder: 0
-    // batch 2: 1385 - 273 = 1112
-    // batch 3: 1506 - 273 = 1233
-    let batch = state
-        .previous_batches
-        .load(deps.as_ref().storage, 2u64)
-        .unwrap();
-    assert_eq!(
-        batch,
-        Batch {
-            id: 2,
-            reconciled: true,
-            total_shares: Uint128::new(1345),
-            amount_unclaimed: Uint128::new(1112), // 1385 - 273
-            est_unbond_end_time: 20000,
-        }
-    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("steak_token", &[]),
+        queue_unbond,
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("unbonding is paused"));
 
-    let batch = state
-        .previous_batches
-        .load(deps.as_ref().storage, 3u64)
-        .unwrap();
-    assert_eq!(
-        batch,
-        Batch {
-            id: 3,
-            reconciled: true,
-            total_shares: Uint128::new(1456),
-            amount_unclaimed: Uint128::new(1233), // 1506 - 273
-            est_unbond_end_time: 30000,
-        }
-    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            amount: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("withdrawing is paused"));
 
-    // Batches 1 and 4 should not have changed
-    let batch = state
-        .previous_batches
-        .load(deps.as_ref().storage, 1u64)
-        .unwrap();
-    assert_eq!(batch, previous_batches[0]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("harvesting is paused"));
 
-    let batch = state
-        .previous_batches
-        .load(deps.as_ref().storage, 4u64)
-        .unwrap();
-    assert_eq!(batch, previous_batches[3]);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("batch submission is paused"));
 }
 
 #[test]
-fn withdrawing_unbonded() {
+fn notifying_registered_hooks() {
     let mut deps = setup_test();
-    let state = State::default();
 
-    // We simulate a most general case:
-    // - batches 1 and 2 have finished unbonding
-    // - batch 3 have been submitted for unbonding but have not finished
-    // - batch 4 is still pending
-    let unbond_requests = vec![
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(23456),
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::RegisterHook {
+            event_kind: HookEventKind::Bond,
+            contract: "indexer".to_string(),
         },
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_3"),
-            shares: Uint128::new(69420),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::RegisterHook {
+            event_kind: HookEventKind::Bond,
+            contract: "indexer".to_string(),
         },
-        UnbondRequest {
-            id: 2,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(34567),
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::RegisterHook {
+            event_kind: HookEventKind::Bond,
+            contract: "indexer".to_string(),
         },
-        UnbondRequest {
-            id: 3,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(45678),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("indexer is already notified of bond events")
+    );
+
+    let res: Vec<String> = query_helper(
+        deps.as_ref(),
+        QueryMsg::Hooks {
+            event_kind: HookEventKind::Bond,
         },
-        UnbondRequest {
-            id: 4,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(56789),
+    );
+    assert_eq!(res, vec!["indexer".to_string()]);
+    let res: Vec<String> = query_helper(
+        deps.as_ref(),
+        QueryMsg::Hooks {
+            event_kind: HookEventKind::Reconciliation,
         },
-    ];
-
-    for unbond_request in &unbond_requests {
-        state
-            .unbond_requests
-            .save(
-                deps.as_mut().storage,
-                (
-                    unbond_request.id,
-                    &Addr::unchecked(unbond_request.user.clone()),
-                ),
-                unbond_request,
-            )
-            .unwrap();
-    }
+    );
+    assert_eq!(res, Vec::<String>::new());
 
-    let previous_batches = vec![
-        Batch {
-            id: 1,
-            reconciled: true,
-            total_shares: Uint128::new(92876),
-            amount_unclaimed: Uint128::new(95197), // 1.025 Native Token per Steak
-            est_unbond_end_time: 10000,
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
         },
-        Batch {
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[2],
+        SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: "indexer".to_string(),
+                msg: to_binary(&SteakHookMsg::Bonded {
+                    receipt: BondReceipt {
+                        receiver: "user_1".to_string(),
+                        native_bonded: Uint128::new(1000000),
+                        usteak_minted: Uint128::new(1000000),
+                        exchange_rate: Decimal::one(),
+                    },
+                })
+                .unwrap(),
+                funds: vec![],
+            },
+            2
+        )
+    );
+
+    // A contract failing to handle the notification doesn't hold up the bond
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
             id: 2,
-            reconciled: true,
-            total_shares: Uint128::new(34567),
-            amount_unclaimed: Uint128::new(35604), // 1.030 Native Token per Steak
-            est_unbond_end_time: 20000,
+            result: cosmwasm_std::SubMsgResult::Err("unknown variant".to_string()),
         },
-        Batch {
-            id: 3,
-            reconciled: false, // finished unbonding, but not reconciled; ignored
-            total_shares: Uint128::new(45678),
-            amount_unclaimed: Uint128::new(47276), // 1.035 Native Token per Steak
-            est_unbond_end_time: 20000,
-        },
-        Batch {
-            id: 4,
-            reconciled: true,
-            total_shares: Uint128::new(56789),
-            amount_unclaimed: Uint128::new(59060), // 1.040 Native Token per Steak
-            est_unbond_end_time: 30000, // reconciled, but not yet finished unbonding; ignored
-        },
-    ];
-
-    for previous_batch in &previous_batches {
-        state
-            .previous_batches
-            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
-            .unwrap();
-    }
-
-    state
-        .pending_batch
-        .save(
-            deps.as_mut().storage,
-            &PendingBatch {
-                id: 4,
-                usteak_to_burn: Uint128::new(56789),
-                est_unbond_start_time: 100000,
-            },
-        )
-        .unwrap();
-
-    // Attempt to withdraw before any batch has completed unbonding. Should error
-    let err = execute(
-        deps.as_mut(),
-        mock_env_at_timestamp(5000),
-        mock_info("user_1", &[]),
-        ExecuteMsg::WithdrawUnbonded { receiver: None },
     )
-    .unwrap_err();
-
-    assert_eq!(err, StdError::generic_err("withdrawable amount is zero"));
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/hook_failed")
+            .add_attribute("contract", "indexer")
+            .add_attribute("error", "unknown variant")]
+    );
 
-    // Attempt to withdraw once batches 1 and 2 have finished unbonding, but 3 has not yet
-    //
-    // Withdrawable from batch 1: 95,197 * 23,456 / 92,876 = 24,042
-    // Withdrawable from batch 2: 35,604
-    // Total withdrawable: 24,042 + 35,604 = 59,646
-    //
-    // Batch 1 should be updated:
-    // Total shares: 92,876 - 23,456 = 69,420
-    // Unclaimed native_token: 95,197 - 24,042 = 71,155
-    //
-    // Batch 2 is completely withdrawn, should be purged from storage
-    let res = execute(
+    execute(
         deps.as_mut(),
-        mock_env_at_timestamp(25000),
-        mock_info("user_1", &[]),
-        ExecuteMsg::WithdrawUnbonded { receiver: None },
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::DeregisterHook {
+            event_kind: HookEventKind::Bond,
+            contract: "indexer".to_string(),
+        },
     )
     .unwrap();
 
-    assert_eq!(res.messages.len(), 1);
-    assert_eq!(
-        res.messages[0],
-        SubMsg {
-            id: 0,
-            msg: CosmosMsg::Bank(BankMsg::Send {
-                to_address: "user_1".to_string(),
-                amount: vec![Coin::new(59646, "uxyz")]
-            }),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
-        }
-    );
-
-    // Previous batches should have been updated
-    let batch = state
-        .previous_batches
-        .load(deps.as_ref().storage, 1u64)
-        .unwrap();
-    assert_eq!(
-        batch,
-        Batch {
-            id: 1,
-            reconciled: true,
-            total_shares: Uint128::new(69420),
-            amount_unclaimed: Uint128::new(71155),
-            est_unbond_end_time: 10000,
-        }
+    let res: Vec<String> = query_helper(
+        deps.as_ref(),
+        QueryMsg::Hooks {
+            event_kind: HookEventKind::Bond,
+        },
     );
+    assert_eq!(res, Vec::<String>::new());
 
-    let err = state
-        .previous_batches
-        .load(deps.as_ref().storage, 2u64)
-        .unwrap_err();
-    assert_eq!(err, StdError::not_found("pfc_steak::hub::Batch"));
-
-    // User 1's unbond requests in batches 1 and 2 should have been deleted
-    let err1 = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
-        .unwrap_err();
-    let err2 = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
-        .unwrap_err();
-
-    assert_eq!(err1, StdError::not_found("pfc_steak::hub::UnbondRequest"));
-    assert_eq!(err2, StdError::not_found("pfc_steak::hub::UnbondRequest"));
-    // User 3 attempt to withdraw; also specifying a receiver
-    let res = execute(
+    let err = execute(
         deps.as_mut(),
-        mock_env_at_timestamp(25000),
-        mock_info("user_3", &[]),
-        ExecuteMsg::WithdrawUnbonded {
-            receiver: Some("user_2".to_string()),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::DeregisterHook {
+            event_kind: HookEventKind::Bond,
+            contract: "indexer".to_string(),
         },
     )
-    .unwrap();
-
-    assert_eq!(res.messages.len(), 1);
+    .unwrap_err();
     assert_eq!(
-        res.messages[0],
-        SubMsg {
-            id: 0,
-            msg: CosmosMsg::Bank(BankMsg::Send {
-                to_address: "user_2".to_string(),
-                amount: vec![Coin::new(71155, "uxyz")]
-            }),
-            gas_limit: None,
-            reply_on: ReplyOn::Never
-        }
+        err,
+        StdError::generic_err("indexer is not notified of bond events")
     );
-
-    // Batch 1 and user 2's unbonding request should have been purged from storage
-    let err = state
-        .previous_batches
-        .load(deps.as_ref().storage, 1u64)
-        .unwrap_err();
-    assert_eq!(err, StdError::not_found("pfc_steak::hub::Batch"));
-
-    let err = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_3")))
-        .unwrap_err();
-
-    assert_eq!(err, StdError::not_found("pfc_steak::hub::UnbondRequest"));
 }
 
 #[test]
-fn adding_validator() {
+fn bonding_with_minimum_out() {
     let mut deps = setup_test();
-    let state = State::default();
+
+    // 1.025 native_token per usteak: 12,345 native_token mint 12,043 usteak
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
 
     let err = execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("jake", &[]),
-        ExecuteMsg::AddValidator {
-            validator: "dave".to_string(),
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: Some(Uint128::new(12044)),
         },
     )
     .unwrap_err();
-
     assert_eq!(
         err,
-        StdError::generic_err("unauthorized: sender is not owner")
+        StdError::generic_err("bond would mint 12043 usteak, below the minimum of 12044")
     );
 
-    let err = execute(
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: Some(Uint128::new(12043)),
+        },
+    )
+    .unwrap();
+    let receipt: BondReceipt = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(receipt.usteak_minted, Uint128::new(12043));
+}
+#[test]
+fn bonding_with_rate_limit() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    execute(
         deps.as_mut(),
         mock_env(),
         mock_info("larry", &[]),
-        ExecuteMsg::AddValidator {
-            validator: "alice".to_string(),
+        ExecuteMsg::SetBondRateLimit {
+            limit: Some(BondRateLimit {
+                max_bond_per_window: Uint128::new(20000),
+                window_secs: 3600,
+            }),
         },
     )
-    .unwrap_err();
+    .unwrap();
+
+    let bond = |deps: DepsMut, timestamp: u64| {
+        execute(
+            deps,
+            mock_env_at_timestamp(timestamp),
+            mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+            ExecuteMsg::Bond {
+                receiver: None,
+                referrer: None,
+                min_usteak_out: None,
+            },
+        )
+    };
+
+    bond(deps.as_mut(), 10000).unwrap();
 
+    let err = bond(deps.as_mut(), 13599).unwrap_err();
     assert_eq!(
         err,
-        StdError::generic_err("validator is already whitelisted")
+        StdError::generic_err(
+            "user_1 would have 24690 bonded in the window, above the limit of 20000"
+        )
     );
 
-    let res = execute(
+    // Another receiver has a window of its own
+    execute(
         deps.as_mut(),
-        mock_env(),
-        mock_info("larry", &[]),
-        ExecuteMsg::AddValidator {
-            validator: "dave".to_string(),
+        mock_env_at_timestamp(13599),
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: Some("user_2".to_string()),
+            referrer: None,
+            min_usteak_out: None,
         },
     )
     .unwrap();
 
-    assert_eq!(res.messages.len(), 0);
-
-    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    // Once the window is over, the next bond starts a new one
+    bond(deps.as_mut(), 13600).unwrap();
+    let err = bond(deps.as_mut(), 13601).unwrap_err();
     assert_eq!(
-        validators,
-        vec![
-            String::from("alice"),
-            String::from("bob"),
-            String::from("charlie"),
-            String::from("dave")
-        ],
+        err,
+        StdError::generic_err(
+            "user_1 would have 24690 bonded in the window, above the limit of 20000"
+        )
     );
 }
 
 #[test]
-fn removing_validator() {
+fn absorbing_external_delegation() {
     let mut deps = setup_test();
     let state = State::default();
 
+    // 1.025 native_token per usteak, after which the community pool delegates 102,500 to bob in
+    // the hub's name
     deps.querier.set_staking_delegations(&[
         Delegation::new("alice", 341667, "uxyz"),
-        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("bob", 444167, "uxyz"),
         Delegation::new("charlie", 341666, "uxyz"),
     ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    state
+        .last_exchange_rate
+        .save(deps.as_mut().storage, &Decimal::from_ratio(41u128, 40u128))
+        .unwrap();
 
     let err = execute(
         deps.as_mut(),
         mock_env(),
         mock_info("jake", &[]),
-        ExecuteMsg::RemoveValidator {
-            validator: "charlie".to_string(),
+        ExecuteMsg::AbsorbDelegation {
+            validator: "bob".to_string(),
+            amount: Uint128::new(102500),
         },
     )
     .unwrap_err();
-
     assert_eq!(
         err,
         StdError::generic_err("unauthorized: sender is not owner")
@@ -1580,652 +1489,10055 @@ fn removing_validator() {
         deps.as_mut(),
         mock_env(),
         mock_info("larry", &[]),
-        ExecuteMsg::RemoveValidator {
-            validator: "dave".to_string(),
+        ExecuteMsg::AbsorbDelegation {
+            validator: "alice".to_string(),
+            amount: Uint128::new(341668),
         },
     )
     .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("the hub only delegates 341667 to alice, can't absorb 341668")
+    );
 
+    // Claiming one more than was delegated from outside would take it from holders
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AbsorbDelegation {
+            validator: "bob".to_string(),
+            amount: Uint128::new(102501),
+        },
+    )
+    .unwrap_err();
     assert_eq!(
         err,
-        StdError::generic_err("validator is not already whitelisted")
+        StdError::generic_err(
+            "absorbing 102501 would put the exchange rate at 1.024999, below the last observed 1.025"
+        )
     );
 
-    // Target: (341667 + 341667 + 341666) / 2 = 512500
-    // Remainder: 0
-    // Alice:   512500 + 0 - 341667 = 170833
-    // Bob:     512500 + 0 - 341667 = 170833
-    let env = mock_env();
     let res = execute(
         deps.as_mut(),
-        env.clone(),
+        mock_env(),
         mock_info("larry", &[]),
-        ExecuteMsg::RemoveValidator {
-            validator: "charlie".to_string(),
+        ExecuteMsg::AbsorbDelegation {
+            validator: "bob".to_string(),
+            amount: Uint128::new(102500),
         },
     )
     .unwrap();
-
-    assert_eq!(res.messages.len(), 2);
-    assert_eq!(
-        res.messages[0],
-        SubMsg::reply_on_success(
-            Redelegation::new("charlie", "alice", 170833, "uxyz")
-                .to_cosmos_msg(env.contract.address.to_string())
-                .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS
-        ),
-    );
     assert_eq!(
-        res.messages[1],
-        SubMsg::reply_on_success(
-            Redelegation::new("charlie", "bob", 170833, "uxyz")
-                .to_cosmos_msg(env.contract.address.to_string())
-                .unwrap(),
-            REPLY_REGISTER_RECEIVED_COINS
-        ),
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "larry".to_string(),
+                amount: Uint128::new(100000)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))]
     );
 
-    let validators = state.validators.load(deps.as_ref().storage).unwrap();
-    assert_eq!(validators, vec![String::from("alice"), String::from("bob")],);
-}
-
-#[test]
-fn transferring_ownership() {
-    let mut deps = setup_test();
-    let state = State::default();
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(res.absorbed_native, Uint128::new(102500));
 
+    // A whitelisted validator the hub doesn't delegate to has nothing to absorb
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 444167, "uxyz"),
+    ]);
     let err = execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("jake", &[]),
-        ExecuteMsg::TransferOwnership {
-            new_owner: "jake".to_string(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AbsorbDelegation {
+            validator: "charlie".to_string(),
+            amount: Uint128::new(1),
         },
     )
     .unwrap_err();
-
     assert_eq!(
         err,
-        StdError::generic_err("unauthorized: sender is not owner")
+        StdError::generic_err("no delegation to charlie to absorb")
     );
+}
+#[test]
+fn swapping_rewards_through_swap_module() {
+    let mut deps = setup_test();
+    let state = State::default();
 
-    let res = execute(
+    state
+        .unlocked_coins
+        .save(
+            deps.as_mut().storage,
+            &vec![Coin::new(123, "uxyz"), Coin::new(1000, "uatom")],
+        )
+        .unwrap();
+
+    let route = SwapRoute {
+        hops: vec![
+            SwapHop {
+                pool_id: 1,
+                token_out_denom: "uosmo".to_string(),
+            },
+            SwapHop {
+                pool_id: 7,
+                token_out_denom: "uxyz".to_string(),
+            },
+        ],
+        min_rate: Decimal::from_ratio(3u128, 2u128),
+    };
+
+    let err = execute(
         deps.as_mut(),
         mock_env(),
         mock_info("larry", &[]),
-        ExecuteMsg::TransferOwnership {
-            new_owner: "jake".to_string(),
+        ExecuteMsg::SetSwapRoute {
+            denom: "uatom".to_string(),
+            route: Some(SwapRoute {
+                hops: route.hops[..1].to_vec(),
+                ..route.clone()
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("swap route must end in uxyz"));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::SwapRewards {
+            denom: "uatom".to_string(),
+            min_out: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("no swap route for uatom"));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetSwapRoute {
+            denom: "uatom".to_string(),
+            route: Some(route.clone()),
         },
     )
     .unwrap();
 
-    assert_eq!(res.messages.len(), 0);
+    let res: Vec<SwapRouteResponse> = query_helper(
+        deps.as_ref(),
+        QueryMsg::SwapRoutes {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![SwapRouteResponse {
+            denom: "uatom".to_string(),
+            route: route.clone(),
+        }]
+    );
 
-    let owner = state.owner.load(deps.as_ref().storage).unwrap();
-    assert_eq!(owner, Addr::unchecked("larry"));
+    // The route's minimum rate asks for 1,500 uxyz; a caller may ask for more
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::SwapRewards {
+            denom: "uatom".to_string(),
+            min_out: Some(Uint128::new(1600)),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(
+            swap_exact_amount_in_msg(
+                MOCK_CONTRACT_ADDR.to_string(),
+                &route.hops,
+                &Coin::new(1000, "uatom"),
+                Uint128::new(1600),
+            )
+            .unwrap()
+        )]
+    );
+    assert_eq!(
+        state.unlocked_coins.load(deps.as_ref().storage).unwrap(),
+        vec![Coin::new(123, "uxyz")]
+    );
 
     let err = execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("pumpkin", &[]),
-        ExecuteMsg::AcceptOwnership {},
+        mock_info("anyone", &[]),
+        ExecuteMsg::SwapRewards {
+            denom: "uatom".to_string(),
+            min_out: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("no uatom rewards to swap"));
+}
+
+#[test]
+fn measuring_deposits() {
+    let mut deps = setup_test();
+    let state = State::default();
+    let env = mock_env();
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMeasureDeposits { enabled: true },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert!(res.measure_deposits);
+
+    // The contract holds 500 uxyz of unlocked rewards, and a 12,345 uxyz deposit was taxed down to
+    // 11,000 on the way in
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(500, "uxyz")])
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(11500u128, "uxyz")]);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_success(
+            Delegation::new("alice", 11000, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            1
+        )
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "user_1".to_string(),
+                amount: Uint128::new(11000)
+            })
+            .unwrap(),
+            funds: vec![]
+        })
+    );
+
+    // Nothing beyond what the hub already owes arrived
+    deps.querier
+        .set_bank_balances(&[Coin::new(500u128, "uxyz")]);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
     )
     .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("none of the uxyz deposit reached the contract")
+    );
 
+    // Neither does the hub mistake what reinvest carried over, or the Native Token of a batch that
+    // finished unbonding early but isn't reconciled yet, for a deposit
+    state
+        .reinvest_carry
+        .save(deps.as_mut().storage, &Uint128::new(100))
+        .unwrap();
+    BatchBuilder::new(1)
+        .shares(1000)
+        .unclaimed(1025)
+        .unbond_end_time(env.block.time.seconds() + 10000)
+        .save(deps.as_mut().storage)
+        .unwrap();
+    state
+        .unbond_completion_times
+        .save(
+            deps.as_mut().storage,
+            (1, "alice"),
+            &(env.block.time.seconds() - 10),
+        )
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(500u128 + 100 + 1025, "uxyz")]);
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap_err();
     assert_eq!(
         err,
-        StdError::generic_err("unauthorized: sender is not new owner")
+        StdError::generic_err("none of the uxyz deposit reached the contract")
     );
+}
+
+#[cfg(feature = "debug-events")]
+#[test]
+fn emitting_debug_breadcrumbs() {
+    let mut deps = setup_test();
 
     let res = execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("jake", &[]),
-        ExecuteMsg::AcceptOwnership {},
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
     )
     .unwrap();
 
-    assert_eq!(res.messages.len(), 0);
-
-    let owner = state.owner.load(deps.as_ref().storage).unwrap();
-    assert_eq!(owner, Addr::unchecked("jake"));
+    let attribute = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .parse::<u64>()
+            .unwrap()
+    };
+    assert!(attribute("debug_storage_reads") > 0);
+    assert!(attribute("debug_storage_writes") > 0);
+    assert_eq!(attribute("debug_submessages"), res.messages.len() as u64);
 }
 
 #[test]
-fn splitting_fees() {
+fn rejecting_dust() {
     let mut deps = setup_test();
 
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    // At an exchange rate above 1, a 1 unit deposit would mint nothing
     let err = execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("jake", &[]),
-        ExecuteMsg::TransferFeeAccount {
-            fee_account_type: "Wallet".to_string(),
-            new_fee_account: "charlie".to_string(),
+        mock_info("user_1", &[Coin::new(1, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
         },
     )
     .unwrap_err();
-
     assert_eq!(
         err,
-        StdError::generic_err("unauthorized: sender is not owner")
+        StdError::generic_err("bond amount 1 for user_1 is too small to mint any steak")
     );
 
+    for msg in [
+        ExecuteMsg::SetMinBondAmount {
+            min_bond_amount: Uint128::new(1000),
+        },
+        ExecuteMsg::SetMinUnbondShares {
+            min_unbond_shares: Uint128::new(500),
+        },
+    ] {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("jake", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("unauthorized: sender is not owner")
+        );
+
+        execute(deps.as_mut(), mock_env(), mock_info("larry", &[]), msg).unwrap();
+    }
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.min_bond_amount, Uint128::new(1000));
+    assert_eq!(res.min_unbond_shares, Uint128::new(500));
+
     let err = execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("larry", &[]),
-        ExecuteMsg::TransferFeeAccount {
-            fee_account_type: "xxxx".to_string(),
-            new_fee_account: "charlie".to_string(),
+        mock_info("user_1", &[Coin::new(999, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
         },
     )
     .unwrap_err();
-
     assert_eq!(
         err,
-        StdError::generic_err("Invalid Fee type: Wallet or FeeSplit only")
+        StdError::generic_err("bond amount 999 is below the minimum of 1000")
     );
 
     execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("larry", &[]),
-        ExecuteMsg::TransferFeeAccount {
-            fee_account_type: "Wallet".to_string(),
-            new_fee_account: "charlie".to_string(),
+        mock_info("user_1", &[Coin::new(1000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
         },
     )
     .unwrap();
-    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(499),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
     assert_eq!(
-        res,
-        ConfigResponse {
-            owner: "larry".to_string(),
-            new_owner: None,
-            steak_token: "steak_token".to_string(),
-            epoch_period: 259200,
-            unbond_period: 1814400,
-            denom: "uxyz".to_string(),
-            fee_type: "Wallet".to_string(),
-            fee_account: "charlie".to_string(),
-            fee_rate: Decimal::from_ratio(10_u128, 100_u128),
-            max_fee_rate: Decimal::from_ratio(20_u128, 100_u128),
-            validators: vec![
-                "alice".to_string(),
-                "bob".to_string(),
-                "charlie".to_string()
-            ]
-        }
+        err,
+        StdError::generic_err("unbond amount 499 is below the minimum of 500")
     );
 
     execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("larry", &[]),
-        ExecuteMsg::TransferFeeAccount {
-            fee_account_type: "FeeSplit".to_string(),
-            new_fee_account: "contract".to_string(),
-        },
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(500),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
     )
     .unwrap();
-    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
-    assert_eq!(
-        res,
-        ConfigResponse {
-            owner: "larry".to_string(),
-            new_owner: None,
-            steak_token: "steak_token".to_string(),
-            epoch_period: 259200,
-            unbond_period: 1814400,
-            denom: "uxyz".to_string(),
-            fee_type: "FeeSplit".to_string(),
-            fee_account: "contract".to_string(),
-            fee_rate: Decimal::from_ratio(10_u128, 100_u128),
-            max_fee_rate: Decimal::from_ratio(20_u128, 100_u128),
-            validators: vec![
-                "alice".to_string(),
-                "bob".to_string(),
-                "charlie".to_string()
-            ]
-        }
-    );
 }
 
 #[test]
-fn submit_proof() {
+fn bonding_for_many() {
     let mut deps = setup_test();
-    let state = State::default();
-    let miner_entropy =
-        "df5c2d1c1e799c13e81ef0d24acdb338e9da760af9afcd1bfbde40d61fed8996".to_string();
-    let miner_address = "joe1gh9nds8amsy33ewpt97gj4n99436hftz2zl79q".to_string();
-    let nonce = Uint64::from(121063160u64);
+
     deps.querier.set_staking_delegations(&[
         Delegation::new("alice", 341667, "uxyz"),
         Delegation::new("bob", 341667, "uxyz"),
         Delegation::new("charlie", 341666, "uxyz"),
     ]);
-    state
-        .miner_entropy
-        .save(deps.as_mut().storage, &miner_entropy)
-        .unwrap();
-    state
-        .miner_difficulty
-        .save(deps.as_mut().storage, &Uint64::new(5))
-        .unwrap();
-    let res = execute(
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let err = execute(
         deps.as_mut(),
         mock_env(),
-        mock_info(&miner_address.to_string(), &[]),
-        ExecuteMsg::SubmitProof {
-            nonce,
-            validator: "alice".to_string(),
+        mock_info("payroll", &[Coin::new(30000, "uxyz")]),
+        ExecuteMsg::BondMany {
+            receivers: vec![
+                ("user_1".to_string(), Uint128::new(10000)),
+                ("user_2".to_string(), Uint128::new(10000)),
+            ],
+            referrer: None,
+            min_usteak_out: None,
         },
     )
-    .unwrap();
-}
-
-//--------------------------------------------------------------------------------------------------
-// Queries
-//--------------------------------------------------------------------------------------------------
-
-#[test]
-fn querying_previous_batches() {
-    let mut deps = mock_dependencies();
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("receiver amounts add up to 20000 but 30000 was deposited")
+    );
 
-    let batches = vec![
-        Batch {
-            id: 1,
-            reconciled: false,
-            total_shares: Uint128::new(123),
-            amount_unclaimed: Uint128::new(678),
-            est_unbond_end_time: 10000,
-        },
-        Batch {
-            id: 2,
-            reconciled: true,
-            total_shares: Uint128::new(234),
-            amount_unclaimed: Uint128::new(789),
-            est_unbond_end_time: 15000,
-        },
-        Batch {
-            id: 3,
-            reconciled: false,
-            total_shares: Uint128::new(345),
-            amount_unclaimed: Uint128::new(890),
-            est_unbond_end_time: 20000,
-        },
-        Batch {
-            id: 4,
-            reconciled: true,
-            total_shares: Uint128::new(456),
-            amount_unclaimed: Uint128::new(999),
-            est_unbond_end_time: 25000,
+    // The whole deposit goes to Charlie, and each receiver is minted at the same exchange rate
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("payroll", &[Coin::new(30000, "uxyz")]),
+        ExecuteMsg::BondMany {
+            receivers: vec![
+                ("user_1".to_string(), Uint128::new(10000)),
+                ("user_2".to_string(), Uint128::new(20000)),
+            ],
+            referrer: None,
+            min_usteak_out: None,
         },
-    ];
-
-    let state = State::default();
-    for batch in &batches {
-        state
-            .previous_batches
-            .save(deps.as_mut().storage, batch.id, batch)
-            .unwrap();
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_success(
+            Delegation::new("charlie", 30000, "uxyz")
+                .to_cosmos_msg(MOCK_CONTRACT_ADDR.to_string(), &StakingProtoUrls::default())
+                .unwrap(),
+            1
+        )
+    );
+    for (msg, (recipient, amount)) in res.messages[1..]
+        .iter()
+        .zip([("user_1", 9756u128), ("user_2", 19512)])
+    {
+        assert_eq!(
+            msg,
+            &SubMsg::new(WasmMsg::Execute {
+                contract_addr: "steak_token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: recipient.to_string(),
+                    amount: Uint128::new(amount),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
     }
+    let receipts: Vec<BondReceipt> = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(receipts.len(), 2);
+    assert_eq!(
+        receipts[1],
+        BondReceipt {
+            receiver: "user_2".to_string(),
+            native_bonded: Uint128::new(20000),
+            usteak_minted: Uint128::new(19512),
+            exchange_rate: Decimal::from_ratio(1025000u128, 1000000u128),
+        }
+    );
 
-    // Querying a single batch
-    let res: Batch = query_helper(deps.as_ref(), QueryMsg::PreviousBatch(1));
-    assert_eq!(res, batches[0].clone());
-
-    let res: Batch = query_helper(deps.as_ref(), QueryMsg::PreviousBatch(2));
-    assert_eq!(res, batches[1].clone());
-
-    // Query multiple batches
-    let res: Vec<Batch> = query_helper(
-        deps.as_ref(),
-        QueryMsg::PreviousBatches {
-            start_after: None,
-            limit: None,
+    // Like a bond, a referrer counts the whole deposit, and the minimum holds against the total
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("payroll", &[Coin::new(30000, "uxyz")]),
+        ExecuteMsg::BondMany {
+            receivers: vec![
+                ("user_1".to_string(), Uint128::new(10000)),
+                ("user_2".to_string(), Uint128::new(20000)),
+            ],
+            referrer: Some("user_2".to_string()),
+            min_usteak_out: None,
         },
-    );
-    assert_eq!(res, batches);
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("cannot refer yourself"));
 
-    let res: Vec<Batch> = query_helper(
-        deps.as_ref(),
-        QueryMsg::PreviousBatches {
-            start_after: Some(1),
-            limit: None,
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("payroll", &[Coin::new(30000, "uxyz")]),
+        ExecuteMsg::BondMany {
+            receivers: vec![
+                ("user_1".to_string(), Uint128::new(10000)),
+                ("user_2".to_string(), Uint128::new(20000)),
+            ],
+            referrer: Some("ref_1".to_string()),
+            min_usteak_out: Some(Uint128::new(29269)),
         },
-    );
+    )
+    .unwrap_err();
     assert_eq!(
-        res,
-        vec![batches[1].clone(), batches[2].clone(), batches[3].clone()]
+        err,
+        StdError::generic_err("bond would mint 29268 usteak, below the minimum of 29269")
     );
 
-    let res: Vec<Batch> = query_helper(
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("payroll", &[Coin::new(30000, "uxyz")]),
+        ExecuteMsg::BondMany {
+            receivers: vec![
+                ("user_1".to_string(), Uint128::new(10000)),
+                ("user_2".to_string(), Uint128::new(20000)),
+            ],
+            referrer: Some("ref_1".to_string()),
+            min_usteak_out: Some(Uint128::new(29268)),
+        },
+    )
+    .unwrap();
+    let res: ReferralStatsResponse = query_helper(
         deps.as_ref(),
-        QueryMsg::PreviousBatches {
-            start_after: Some(4),
-            limit: None,
+        QueryMsg::ReferralStats {
+            referrer: "ref_1".to_string(),
         },
     );
-    assert_eq!(res, vec![]);
-
-    // Query multiple batches, indexed by whether it has been reconciled
-    let res = state
-        .previous_batches
-        .idx
-        .reconciled
-        .prefix(true.into())
-        .range(deps.as_ref().storage, None, None, Order::Ascending)
-        .map(|item| {
-            let (_, v) = item.unwrap();
-            v
-        })
-        .collect::<Vec<_>>();
-
-    assert_eq!(res, vec![batches[1].clone(), batches[3].clone()]);
-
-    let res = state
-        .previous_batches
-        .idx
-        .reconciled
-        .prefix(false.into())
-        .range(deps.as_ref().storage, None, None, Order::Ascending)
-        .map(|item| {
-            let (_, v) = item.unwrap();
-            v
-        })
-        .collect::<Vec<_>>();
-
-    assert_eq!(res, vec![batches[0].clone(), batches[2].clone()]);
+    assert_eq!(res.volume, Uint128::new(30000));
 }
 
 #[test]
-fn querying_unbond_requests() {
-    let mut deps = mock_dependencies();
+fn restaking_through_adapter() {
+    let mut deps = setup_test();
     let state = State::default();
 
-    let unbond_requests = vec![
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("alice"),
-            shares: Uint128::new(123),
-        },
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("bob"),
-            shares: Uint128::new(234),
-        },
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("charlie"),
-            shares: Uint128::new(345),
-        },
-        UnbondRequest {
-            id: 2,
-            user: Addr::unchecked("alice"),
-            shares: Uint128::new(456),
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetRestakingAdapter {
+            adapter: Some(RestakingAdapter {
+                contract: "restaker".to_string(),
+                max_restaked_bps: 10001,
+            }),
         },
-    ];
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("max restaked share can't be more than 10000 bps")
+    );
 
-    for unbond_request in &unbond_requests {
-        state
-            .unbond_requests
-            .save(
-                deps.as_mut().storage,
-                (
-                    unbond_request.id,
-                    &Addr::unchecked(unbond_request.user.clone()),
-                ),
-                unbond_request,
-            )
-            .unwrap();
-    }
+    let adapter = RestakingAdapter {
+        contract: "restaker".to_string(),
+        max_restaked_bps: 2000,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetRestakingAdapter {
+            adapter: Some(adapter.clone()),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.restaking_adapter, Some(adapter));
 
-    let res: Vec<UnbondRequestsByBatchResponseItem> = query_helper(
-        deps.as_ref(),
-        QueryMsg::UnbondRequestsByBatch {
-            id: 1,
-            start_after: None,
-            limit: None,
+    // Bonding tells the adapter the new bonded amount: 1,025,000 + 12,345 = 1,037,345
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
         },
-    );
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
     assert_eq!(
-        res,
-        vec![
-            unbond_requests[0].clone().into(),
-            unbond_requests[1].clone().into(),
-            unbond_requests[2].clone().into(),
-        ]
+        res.messages[2],
+        SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: "restaker".to_string(),
+                msg: to_binary(&RestakingHookMsg::BondedAmountChanged {
+                    total_bonded: Uint128::new(1037345),
+                    max_restaked: Uint128::new(207469),
+                })
+                .unwrap(),
+                funds: vec![],
+            },
+            2
+        )
     );
 
-    let res: Vec<UnbondRequestsByBatchResponseItem> = query_helper(
-        deps.as_ref(),
-        QueryMsg::UnbondRequestsByBatch {
+    // A failing adapter doesn't hold the hub up
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
             id: 2,
-            start_after: None,
-            limit: None,
+            result: cosmwasm_std::SubMsgResult::Err("out of gas".to_string()),
         },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/restaking_hook_failed").add_attribute("error", "out of gas")]
     );
-    assert_eq!(res, vec![unbond_requests[3].clone().into()]);
 
-    let res: Vec<UnbondRequestsByUserResponseItem> = query_helper(
-        deps.as_ref(),
-        QueryMsg::UnbondRequestsByUser {
-            user: "alice".to_string(),
-            start_after: None,
-            limit: None,
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::ReportRestaked {
+            amount: Uint128::new(1000),
         },
-    );
+    )
+    .unwrap_err();
     assert_eq!(
-        res,
-        vec![
-            unbond_requests[0].clone().into(),
-            unbond_requests[3].clone().into()
-        ]
+        err,
+        StdError::generic_err("unauthorized: sender is not the restaking adapter")
     );
 
-    let res: Vec<UnbondRequestsByUserResponseItem> = query_helper(
-        deps.as_ref(),
-        QueryMsg::UnbondRequestsByUser {
-            user: "alice".to_string(),
-            start_after: Some(2),
-            limit: None,
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("restaker", &[]),
+        ExecuteMsg::ReportRestaked {
+            amount: Uint128::new(205001),
         },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("restaked amount 205001 is above the cap of 205000")
     );
-    assert_eq!(res, vec![unbond_requests[3].clone().into()]);
-}
-
-//--------------------------------------------------------------------------------------------------
-// Delegations
-//--------------------------------------------------------------------------------------------------
 
-#[test]
-fn computing_undelegations() {
-    let current_delegations = vec![
-        Delegation::new("alice", 400, "uxyz"),
-        Delegation::new("bob", 300, "uxyz"),
-        Delegation::new("charlie", 200, "uxyz"),
-    ];
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("restaker", &[]),
+        ExecuteMsg::ReportRestaked {
+            amount: Uint128::new(205000),
+        },
+    )
+    .unwrap();
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(res.restaked_native, Uint128::new(205000));
 
-    // Target: (400 + 300 + 200 - 451) / 3 = 149
-    // Remainder: 2
-    // Alice:   400 - (149 + 1) = 250
-    // Bob:     300 - (149 + 1) = 150
-    // Charlie: 200 - (149 + 0) = 51
-    let new_undelegations = compute_undelegations(Uint128::new(451), &current_delegations, "uxyz");
-    let expected = vec![
-        Undelegation::new("alice", 250, "uxyz"),
-        Undelegation::new("bob", 150, "uxyz"),
-        Undelegation::new("charlie", 51, "uxyz"),
-    ];
-    assert_eq!(new_undelegations, expected);
+    // Restaked stake isn't available for unbonding: 1,025,000 - 900,000 < 205,000
+    state
+        .pending_batches
+        .save(
+            deps.as_mut().storage,
+            "uxyz",
+            &PendingBatch {
+                id: 1,
+                usteak_to_burn: Uint128::new(900000),
+                est_unbond_start_time: 269200,
+                rollovers: 0,
+            },
+        )
+        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unbonding 922500 would leave less bonded than the 205000 restaked")
+    );
 }
 
 #[test]
-fn computing_redelegations_for_removal() {
-    let current_delegations = vec![
-        Delegation::new("alice", 13000, "uxyz"),
-        Delegation::new("bob", 12000, "uxyz"),
-        Delegation::new("charlie", 11000, "uxyz"),
-        Delegation::new("dave", 10000, "uxyz"),
-    ];
+fn bonding_on_behalf() {
+    let mut deps = setup_test();
+    let mut env = mock_env();
+    env.contract.address = Addr::unchecked("osmo1hub");
 
-    // Suppose Dave will be removed
-    // native_token_per_validator = (13000 + 12000 + 11000 + 10000) / 3 = 15333
-    // remainder = 1
-    // to Alice:   15333 + 1 - 13000 = 2334
-    // to Bob:     15333 + 0 - 12000 = 3333
-    // to Charlie: 15333 + 0 - 11000 = 4333
-    let expected = vec![
-        Redelegation::new("dave", "alice", 2334, "uxyz"),
-        Redelegation::new("dave", "bob", 3333, "uxyz"),
-        Redelegation::new("dave", "charlie", 4333, "uxyz"),
-    ];
+    // The sender `ibc_hooks` derives for cosmos1depositor over channel-0
+    let hooks_sender = "osmo1wkwn2p6mart3p9q6eyqq66t288z6csx0dyhpa00e4fqlkj3u6xfq2uuahj";
+    let msg = ExecuteMsg::BondOnBehalf {
+        original_sender: "cosmos1depositor".to_string(),
+    };
 
+    // Bonding through IBC is disabled until the ics20 contract is set
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(hooks_sender, &[Coin::new(1000000, "uxyz")]),
+        msg.clone(),
+    )
+    .unwrap_err();
     assert_eq!(
-        compute_redelegations_for_removal(
-            &current_delegations[3],
-            &current_delegations[..3],
-            "uxyz"
-        ),
-        expected,
+        err,
+        StdError::generic_err("bonding through IBC is not enabled")
     );
-}
 
-#[test]
-fn computing_redelegations_for_rebalancing() {
-    let current_delegations = vec![
-        Delegation::new("alice", 69420, "uxyz"),
-        Delegation::new("bob", 1234, "uxyz"),
-        Delegation::new("charlie", 88888, "uxyz"),
-        Delegation::new("dave", 40471, "uxyz"),
-        Delegation::new("evan", 2345, "uxyz"),
-    ];
-    let active_validators: Vec<String> = vec![
-        "alice".to_string(),
-        "bob".to_string(),
-        "charlie".to_string(),
-        "dave".to_string(),
-        "evan".to_string(),
-    ];
-    // native_token_per_validator = (69420 + 88888 + 1234 + 40471 + 2345) / 4 = 40471
-    // remainer = 3
-    // src_delegations:
-    //  - alice:   69420 - (40471 + 1) = 28948
-    //  - charlie: 88888 - (40471 + 1) = 48416
-    // dst_delegations:
-    //  - bob:     (40471 + 1) - 1234  = 39238
-    //  - evan:    (40471 + 0) - 2345  = 38126
-    //
-    // Round 1: alice --(28948)--> bob
-    // src_delegations:
-    //  - charlie: 48416
-    // dst_delegations:
-    //  - bob:     39238 - 28948 = 10290
-    //  - evan:    38126
-    //
-    // Round 2: charlie --(10290)--> bob
-    // src_delegations:
-    //  - charlie: 48416 - 10290 = 38126
-    // dst_delegations:
-    //  - evan:    38126
-    //
-    // Round 3: charlie --(38126)--> evan
-    // Queues are emptied
-    let expected = vec![
-        Redelegation::new("alice", "bob", 28948, "uxyz"),
-        Redelegation::new("charlie", "bob", 10290, "uxyz"),
-        Redelegation::new("charlie", "evan", 38126, "uxyz"),
-    ];
+    // Only the owner can set the ics20 contract, along with the channel
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetIcs20Contract {
+            ics20_contract: Some("ics20".to_string()),
+            channel: Some("channel-0".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
 
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetIcs20Contract {
+            ics20_contract: Some("ics20".to_string()),
+            channel: None,
+        },
+    )
+    .unwrap_err();
     assert_eq!(
-        compute_redelegations_for_rebalancing(
-            active_validators,
-            &current_delegations,
-            Uint128::from(10_u64),
-            // mock the same mining power on every validator
-            |_| Ok(40471_u128.into())
-        )
-        .unwrap(),
-        expected,
+        err,
+        StdError::generic_err("ics20 contract and channel must be set together")
     );
 
-    let partially_active = vec![
-        "alice".to_string(),
-        "charlie".to_string(),
-        "dave".to_string(),
-        "evan".to_string(),
-    ];
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetIcs20Contract {
+            ics20_contract: Some("ics20".to_string()),
+            channel: Some("channel-0".to_string()),
+        },
+    )
+    .unwrap();
 
-    let partially_expected = vec![
-        Redelegation::new("alice", "dave", 10118, "uxyz"),
-        Redelegation::new("alice", "evan", 8712, "uxyz"),
-        Redelegation::new("charlie", "evan", 38299, "uxyz"),
-    ];
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(hooks_sender, &[Coin::new(1000000, "uxyz")]),
+        ExecuteMsg::BondOnBehalf {
+            original_sender: "".to_string(),
+        },
+    )
+    .unwrap_err();
     assert_eq!(
-        compute_redelegations_for_rebalancing(
-            partially_active.clone(),
-            &current_delegations,
-            Uint128::from(10_u64),
-            // mock the same mining power on every validator
-            |_| Ok(50589_u128.into())
-        )
-        .unwrap(),
-        partially_expected,
+        err,
+        StdError::generic_err("original sender must be provided")
     );
 
-    let partially_expected_minimums = vec![
-        Redelegation::new("alice", "evan", 18830, "uxyz"),
-        Redelegation::new("charlie", "evan", 29414, "uxyz"),
-    ];
+    // Anyone else claiming to bond for the depositor is refused
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("jake", &[Coin::new(1000000, "uxyz")]),
+        msg.clone(),
+    )
+    .unwrap_err();
     assert_eq!(
-        compute_redelegations_for_rebalancing(
-            partially_active,
-            &current_delegations,
-            Uint128::from(15_000_u64),
-            // mock the same mining power on every validator
-            |d| Ok(50589u128.into())
+        err,
+        StdError::generic_err(
+            "sender jake is not the IBC hooks sender for cosmos1depositor over channel-0"
         )
-        .unwrap(),
-        partially_expected_minimums,
     );
-}
-
-#[test]
-fn computing_redelegations_for_rebalancing_with_mining() {
-    let current_delegations = vec![
-        Delegation::new("alice", 69420, "uxyz"),
-        Delegation::new("bob", 1234, "uxyz"),
-        Delegation::new("charlie", 88888, "uxyz"),
-        Delegation::new("dave", 40471, "uxyz"),
-        Delegation::new("evan", 2345, "uxyz"),
-    ];
-    let total_delegated_amount = current_delegations.iter().map(|d| d.amount).sum::<u128>();
-    let active_validators: Vec<String> = vec![
-        "alice".to_string(),
-        "bob".to_string(),
-        "charlie".to_string(),
-        "dave".to_string(),
-        "evan".to_string(),
-        // add steve to ensure still works for validators with no mining power
-        "steve".to_string(),
-    ];
-    let mining_powers_by_validator = vec![
-        ("alice".to_string(), 1002_u128),
-        ("bob".to_string(), 3214_u128),
-        ("charlie".to_string(), 881_u128),
-        ("dave".to_string(), 5471_u128),
-        ("evan".to_string(), 9285_u128),
-    ];
-    let total_mining_power = mining_powers_by_validator
-        .iter()
-        .map(|(_, power)| power)
-        .sum::<u128>();
 
-    // total delegated amount: 69420 + 1234 + 88888 + 40471 + 2345 = 202358
-    // total mining power:         1002 + 3214 + 881 + 5471 + 9285 = 19853
+    // The Steak is minted to the hub, then sent back over the channel through the ics20 contract
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(hooks_sender, &[Coin::new(1000000, "uxyz")]),
+        msg.clone(),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "osmo1hub".to_string(),
+                amount: Uint128::new(1000000)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: "ics20".to_string(),
+                amount: Uint128::new(1000000),
+                msg: to_binary(&Ics20TransferMsg {
+                    channel: "channel-0".to_string(),
+                    remote_address: "cosmos1depositor".to_string(),
+                    timeout: None,
+                })
+                .unwrap(),
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.ics20_contract, Some("ics20".to_string()));
+    assert_eq!(res.ics20_channel, Some("channel-0".to_string()));
+
+    // Deposits through IBC hooks count towards the rate limit of the derived sender
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetBondRateLimit {
+            limit: Some(BondRateLimit {
+                window_secs: 3600,
+                max_bond_per_window: Uint128::new(1500000),
+            }),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(hooks_sender, &[Coin::new(1000000, "uxyz")]),
+        msg.clone(),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info(hooks_sender, &[Coin::new(1000000, "uxyz")]),
+        msg,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "{} would have 2000000 bonded in the window, above the limit of 1500000",
+            hooks_sender
+        ))
+    );
+}
+
+#[test]
+fn bonding_tokenized_shares() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    // Only share tokens of an active validator are accepted
+    for (denom, expected) in [
+        ("uxyz", "uxyz is not a tokenized shares denom"),
+        ("alice/seven", "alice/seven is not a tokenized shares denom"),
+        ("/7", "/7 is not a tokenized shares denom"),
+        ("dave/7", "validator dave is not active"),
+    ] {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("user_1", &[Coin::new(1000, denom)]),
+            ExecuteMsg::BondTokenizedShares { receiver: None },
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err(expected));
+    }
+
+    // The shares are redeemed first; nothing is minted until the delegation is known
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000, "alice/7")]),
+        ExecuteMsg::BondTokenizedShares {
+            receiver: Some("user_2".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_success(
+            SharesRedemption::new(1000, "alice/7")
+                .to_cosmos_msg(MOCK_CONTRACT_ADDR.to_string(), &StakingProtoUrls::default())
+                .unwrap(),
+            1
+        )
+    );
+    assert_eq!(
+        state.reply_contexts.load(deps.as_ref().storage, 1).unwrap(),
+        ReplyContext::RedeemTokenizedShares {
+            receiver: Addr::unchecked("user_2"),
+            validator: "alice".to_string(),
+            delegation_before: Uint128::new(341667),
+        }
+    );
+
+    // The 1000 shares turn out to be worth 1025 Native Token, which mints 1000 Steak at the
+    // exchange rate from before the redemption
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 342692, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    let reply_msg = Reply {
+        id: 1,
+        result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "user_2".to_string(),
+                amount: Uint128::new(1000)
+            })
+            .unwrap(),
+            funds: vec![]
+        })
+    );
+    assert_eq!(
+        from_binary::<BondReceipt>(&res.data.unwrap()).unwrap(),
+        BondReceipt {
+            receiver: "user_2".to_string(),
+            native_bonded: Uint128::new(1025),
+            usteak_minted: Uint128::new(1000),
+            exchange_rate: Decimal::from_ratio(1025000u128, 1000000u128),
+        }
+    );
+    assert!(state
+        .reply_contexts
+        .may_load(deps.as_ref().storage, 1)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn bonding_tokenized_shares_after_suspension() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 342692, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    // Alice was suspended while her shares were being redeemed, and the suspension has since run
+    // out without anything lifting it
+    state
+        .reply_contexts
+        .save(
+            deps.as_mut().storage,
+            1,
+            &ReplyContext::RedeemTokenizedShares {
+                receiver: Addr::unchecked("user_1"),
+                validator: "alice".to_string(),
+                delegation_before: Uint128::new(341667),
+            },
+        )
+        .unwrap();
+    state
+        .validators_active
+        .save(
+            deps.as_mut().storage,
+            &vec!["bob".to_string(), "charlie".to_string()],
+        )
+        .unwrap();
+    state
+        .validator_suspensions
+        .save(deps.as_mut().storage, "alice".to_string(), &20000)
+        .unwrap();
+
+    let reply_msg = Reply {
+        id: 1,
+        result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    let res = reply(deps.as_mut(), mock_env_at_timestamp(20000), reply_msg).unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "user_1".to_string(),
+                amount: Uint128::new(1000)
+            })
+            .unwrap(),
+            funds: vec![]
+        })
+    );
+
+    // The suspension is lifted along the way
+    let validators_active = state.validators_active.load(deps.as_ref().storage).unwrap();
+    assert!(validators_active.contains(&"alice".to_string()));
+    assert!(!state
+        .validator_suspensions
+        .has(deps.as_ref().storage, "alice".to_string()));
+}
+
+#[test]
+fn refusing_stale_exchange_rate() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMaxHarvestStaleness {
+            max_harvest_staleness: Some(0),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(
+            "max harvest staleness must be positive; use none to remove the limit"
+        )
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMaxHarvestStaleness {
+            max_harvest_staleness: Some(3600),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.max_harvest_staleness, Some(3600));
+
+    state
+        .last_harvest_time
+        .save(deps.as_mut().storage, &10000)
+        .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(13600),
+        mock_info("user_1", &[Coin::new(1000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap();
+
+    let stale_err = StdError::generic_err(
+        "exchange rate is stale: last harvest at 10000 is more than 3600 seconds ago",
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(13601),
+        mock_info("user_1", &[Coin::new(1000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, stale_err);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(13601),
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, stale_err);
+
+    // Lifting the limit lets deposits through again
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMaxHarvestStaleness {
+            max_harvest_staleness: None,
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(13601),
+        mock_info("user_1", &[Coin::new(1000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn referring_bonds() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetReferralBps { referral_bps: 2000 },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetReferralBps {
+            referral_bps: 10001,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("referral bps must not exceed 10000")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetReferralBps { referral_bps: 2000 },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.referral_bps, 2000);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(250000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: Some("user_1".to_string()),
+            min_usteak_out: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("cannot refer yourself"));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(250000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: Some("ref_1".to_string()),
+            min_usteak_out: None,
+        },
+    )
+    .unwrap();
+
+    let res: ReferralStatsResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ReferralStats {
+            referrer: "ref_1".to_string(),
+        },
+    );
+    assert_eq!(
+        res,
+        ReferralStatsResponse {
+            referrer: "ref_1".to_string(),
+            volume: Uint128::new(250000),
+            earned: Uint128::zero(),
+            unclaimed: Uint128::zero(),
+        }
+    );
+
+    // Of the 1000 protocol fee on 10000 rewards, a fifth was earned on the referred stake, and 20%
+    // of that is kicked back to the referrer
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 583333, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1250000);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::zero())
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(10000u128, "uxyz")]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(BankMsg::Send {
+            to_address: "the_fee_man".to_string(),
+            amount: vec![Coin::new(960, "uxyz")],
+        })
+    );
+    assert!(res.events[0]
+        .attributes
+        .contains(&attr("referral_kickback", "40")));
+
+    let res: ReferralStatsResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ReferralStats {
+            referrer: "ref_1".to_string(),
+        },
+    );
+    assert_eq!(res.earned, Uint128::new(40));
+    assert_eq!(res.unclaimed, Uint128::new(40));
+    assert_eq!(
+        state
+            .referral_unclaimed
+            .load(deps.as_ref().storage)
+            .unwrap(),
+        Uint128::new(40)
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::ClaimReferralRewards {},
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("no referral rewards to claim"));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("ref_1", &[]),
+        ExecuteMsg::ClaimReferralRewards {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "ref_1".to_string(),
+            amount: vec![Coin::new(40, "uxyz")],
+        })]
+    );
+
+    let res: ReferralStatsResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ReferralStats {
+            referrer: "ref_1".to_string(),
+        },
+    );
+    assert_eq!(res.earned, Uint128::new(40));
+    assert_eq!(res.unclaimed, Uint128::zero());
+    assert_eq!(
+        state
+            .referral_unclaimed
+            .load(deps.as_ref().storage)
+            .unwrap(),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn vesting_fees() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetFeeVesting {
+            fee_vesting: Some(FeeVesting {
+                cliff: 1001,
+                duration: 1000,
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(
+            "fee vesting duration must be positive and not shorter than the cliff"
+        )
+    );
+
+    let fee_vesting = FeeVesting {
+        cliff: 100,
+        duration: 1000,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetFeeVesting {
+            fee_vesting: Some(fee_vesting),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.fee_vesting, Some(fee_vesting));
+
+    // The 1000 fee is delegated along with the rest of the rewards, and the fee account is minted
+    // as much Steak as 1000 is worth once the other 9000 are bonded
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::zero())
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(10000u128, "uxyz")]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::new(991),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert!(res.events[0]
+        .attributes
+        .contains(&attr("denom_bonded", "10000")));
+    assert!(res.events[0]
+        .attributes
+        .contains(&attr("usteak_vesting", "991")));
+
+    let vested_fees = |deps: &OwnedDeps<_, _, _>, time| -> VestedFeesResponse {
+        from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env_at_timestamp(time),
+                QueryMsg::VestedFees {
+                    account: "the_fee_man".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        vested_fees(&deps, 10050),
+        VestedFeesResponse {
+            account: "the_fee_man".to_string(),
+            unclaimed: Uint128::new(991),
+            claimable: Uint128::zero(),
+        }
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10050),
+        mock_info("the_fee_man", &[]),
+        ExecuteMsg::ClaimVestedFees {},
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("no vested fees to claim"));
+
+    // Past the cliff, the fee vests linearly from the reinvest
+    assert_eq!(vested_fees(&deps, 10500).claimable, Uint128::new(495));
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10500),
+        mock_info("the_fee_man", &[]),
+        ExecuteMsg::ClaimVestedFees {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "the_fee_man".to_string(),
+                amount: Uint128::new(495),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+    assert_eq!(vested_fees(&deps, 10500).unclaimed, Uint128::new(496));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("the_fee_man", &[]),
+        ExecuteMsg::ClaimVestedFees {},
+    )
+    .unwrap();
+    assert!(res.events[0]
+        .attributes
+        .contains(&attr("usteak_claimed", "496")));
+    assert_eq!(
+        vested_fees(&deps, 20000),
+        VestedFeesResponse {
+            account: "the_fee_man".to_string(),
+            unclaimed: Uint128::zero(),
+            claimable: Uint128::zero(),
+        }
+    );
+}
+#[test]
+fn burning_fees() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::zero())
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(10000u128, "uxyz")]);
+
+    apply_change(
+        &mut deps,
+        mock_env(),
+        "larry",
+        ConfigChange::FeeAccount {
+            fee_account_type: FeeType::Burn,
+            new_fee_account: "the_fee_man".to_string(),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.fee_type, "Burn");
+
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("bob", 9000, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(BankMsg::Burn {
+            amount: vec![Coin::new(1000, "uxyz")],
+        })
+    );
+
+    // A fee taken in Steak is delegated with the rewards, and no Steak is minted for it
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetFeeVesting {
+            fee_vesting: Some(FeeVesting {
+                cliff: 100,
+                duration: 1000,
+            }),
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("bob", 10000, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    assert!(res.events[0]
+        .attributes
+        .contains(&attr("fees_deducted", "1000")));
+}
+
+#[test]
+fn exempting_holders_from_fees() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::UpdateFeeExemptions {
+            add: vec!["pol".to_string()],
+            remove: vec![],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::UpdateFeeExemptions {
+            add: (0..=MAX_FEE_EXEMPTIONS)
+                .map(|i| format!("holder_{}", i))
+                .collect(),
+            remove: vec![],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("at most 20 addresses can be exempt from the fee")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::UpdateFeeExemptions {
+            add: vec!["pol".to_string(), "dao".to_string()],
+            remove: vec![],
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::UpdateFeeExemptions {
+            add: vec![],
+            remove: vec!["dao".to_string()],
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.fee_exemptions, vec!["pol".to_string()]);
+
+    // A quarter of the Steak is exempt. The fee on 10000 rewards is 1000, of which the quarter
+    // earned by the exempt holder's stake is rebated, leaving 750 for the fee account
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    deps.querier.set_cw20_balance("steak_token", "pol", 250000);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::zero())
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(10000u128, "uxyz")]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert!(res.events[0]
+        .attributes
+        .contains(&attr("fees_deducted", "750")));
+    assert!(res.events[0]
+        .attributes
+        .contains(&attr("denom_bonded", "9250")));
+    assert!(res.events[0]
+        .attributes
+        .contains(&attr("fee_rebated", "250")));
+
+    // The rebate is delegated with the rewards and minted back as Steak at the exchange rate the
+    // rest of the rewards bring the hub to: 1,000,000 * 250 / (1,000,000 + 9,000) = 247
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "pol".to_string(),
+                amount: Uint128::new(247)
+            })
+            .unwrap(),
+            funds: vec![]
+        })
+    );
+    assert_eq!(
+        res.events[1],
+        Event::new("steakhub/fee_rebated")
+            .add_attribute("holder", "pol")
+            .add_attribute("native_rebated", "250")
+            .add_attribute("usteak_minted", "247")
+    );
+}
+
+#[test]
+fn harvesting() {
+    let mut deps = setup_test();
+
+    // Assume users have bonded a total of 1,000,000 native_token and minted the same amount of usteak
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let harvest_env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        harvest_env.clone(),
+        mock_info(&harvest_env.contract.address.to_string(), &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_success(
+            RewardWithdrawal {
+                validator: "alice".to_string(),
+            }
+            .to_cosmos_msg(
+                harvest_env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap(),
+            1,
+        )
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::reply_on_success(
+            RewardWithdrawal {
+                validator: "bob".to_string(),
+            }
+            .to_cosmos_msg(
+                harvest_env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap(),
+            2,
+        )
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::reply_on_success(
+            RewardWithdrawal {
+                validator: "charlie".to_string(),
+            }
+            .to_cosmos_msg(
+                harvest_env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap(),
+            3,
+        )
+    );
+    assert_eq!(
+        res.messages[3],
+        SubMsg {
+            id: 0,
+            msg: CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                msg: to_binary(&ExecuteMsg::Callback(CallbackMsg::Reinvest {})).unwrap(),
+                funds: vec![]
+            }),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        }
+    );
+}
+#[test]
+fn harvesting_skips_validators_without_rewards() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    deps.querier.pending_rewards.insert("alice".to_string(), 0);
+    deps.querier.pending_rewards.insert("bob".to_string(), 5);
+    deps.querier
+        .pending_rewards
+        .insert("charlie".to_string(), 250);
+
+    let withdrawal = |validator: &str, id: u64| {
+        SubMsg::reply_on_success(
+            RewardWithdrawal {
+                validator: validator.to_string(),
+            }
+            .to_cosmos_msg(MOCK_CONTRACT_ADDR.to_string(), &StakingProtoUrls::default())
+            .unwrap(),
+            id,
+        )
+    };
+
+    // Alice has nothing pending
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(res.messages[0], withdrawal("bob", 1));
+    assert_eq!(res.messages[1], withdrawal("charlie", 2));
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("validators_skipped", "1"),
+            attr("sender", MOCK_CONTRACT_ADDR),
+            attr("action", "steakhub/harvest")
+        ]
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMinHarvestReward {
+            min_reward: Uint128::new(100),
+        },
+    )
+    .unwrap();
+
+    // Bob's 5 aren't worth withdrawing either
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[0], withdrawal("charlie", 3));
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("validators_skipped", "2"),
+            attr("sender", MOCK_CONTRACT_ADDR),
+            attr("action", "steakhub/harvest")
+        ]
+    );
+}
+
+#[test]
+fn harvesting_through_authz() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations_of(
+        "intermediate",
+        &[
+            Delegation::new("alice", 341667, "uxyz"),
+            Delegation::new("bob", 341667, "uxyz"),
+        ],
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetHarvestGranter {
+            granter: Some("intermediate".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetHarvestGranter {
+            granter: Some("intermediate".to_string()),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.harvest_granter, Some("intermediate".to_string()));
+
+    // The granter's rewards are withdrawn by the hub, each withdrawal wrapped in a `MsgExec`
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    for (submsg, validator) in res.messages.iter().zip(["alice", "bob"]) {
+        assert_eq!(
+            submsg.msg,
+            RewardWithdrawal::new(validator)
+                .to_authz_exec_msg(
+                    "intermediate".to_string(),
+                    MOCK_CONTRACT_ADDR.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap()
+        );
+    }
+
+    let (type_url, value) = match &res.messages[0].msg {
+        CosmosMsg::Stargate { type_url, value } => (type_url, value),
+        msg => panic!("unexpected message {:?}", msg),
+    };
+    assert_eq!(type_url, "/cosmos.authz.v1beta1.MsgExec");
+    let exec: MsgExec = prost::Message::decode(value.as_slice()).unwrap();
+    assert_eq!(exec.grantee, MOCK_CONTRACT_ADDR);
+    assert_eq!(exec.msgs.len(), 1);
+    assert_eq!(
+        exec.msgs[0].type_url,
+        StakingProtoUrls::default().withdraw_rewards
+    );
+    let withdrawal: MsgWithdrawDelegatorReward =
+        prost::Message::decode(exec.msgs[0].value.as_slice()).unwrap();
+    assert_eq!(withdrawal.delegator_address, "intermediate");
+    assert_eq!(withdrawal.validator_address, "alice");
+}
+
+#[test]
+fn harvesting_with_balance_diff() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    deps.querier
+        .set_bank_balances(&[Coin::new(100, "uxyz"), Coin::new(5, "ukrw")]);
+
+    // Only the owner can change the harvest mode
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetHarvestMode {
+            mode: HarvestMode::BalanceDiff,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetHarvestMode {
+            mode: HarvestMode::BalanceDiff,
+        },
+    )
+    .unwrap();
+
+    // The withdrawals need no reply, and the balances are snapshotted instead
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(
+            RewardWithdrawal {
+                validator: "alice".to_string(),
+            }
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+        )
+    );
+    assert_eq!(
+        state.harvest_balances.load(deps.as_ref().storage).unwrap(),
+        vec![Coin::new(100, "uxyz"), Coin::new(5, "ukrw")]
+    );
+
+    // The callback registers whatever the balances grew by
+    deps.querier
+        .set_bank_balances(&[Coin::new(334, "uxyz"), Coin::new(128, "ukrw")]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("charlie", 234 - 23, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    assert_eq!(
+        state.unlocked_coins.load(deps.as_ref().storage).unwrap(),
+        vec![Coin::new(123, "ukrw")]
+    );
+    assert!(state
+        .harvest_balances
+        .may_load(deps.as_ref().storage)
+        .unwrap()
+        .is_none());
+}
+#[test]
+fn recording_operation_triggers() {
+    let mut deps = setup_test();
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let mut env = mock_env();
+    env.block.height = 20000;
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&attr("sender", MOCK_CONTRACT_ADDR)));
+
+    env.block.height = 20005;
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("jake", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+    assert_eq!(res.events[0].attributes[0], attr("sender", "jake"));
+
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(
+        res.last_harvester,
+        Some(OperationRecord {
+            address: MOCK_CONTRACT_ADDR.to_string(),
+            height: 20000,
+        })
+    );
+    assert_eq!(
+        res.last_reconciler,
+        Some(OperationRecord {
+            address: "jake".to_string(),
+            height: 20005,
+        })
+    );
+    assert_eq!(res.last_batch_submitter, None);
+}
+
+#[test]
+fn registering_unlocked_coins() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // After withdrawing staking rewards, we parse the `coin_received` event to find the received amounts
+    let event = Event::new("coin_received")
+        .add_attribute("receiver", MOCK_CONTRACT_ADDR.to_string())
+        .add_attribute("amount", "123ukrw,234uxyz,345uusd,69420ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B");
+
+    // Replies are only accepted for IDs that were registered
+    let reply_msg = Reply {
+        id: 1,
+        result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+            events: vec![event],
+            data: None,
+        }),
+    };
+    let err = reply(deps.as_mut(), mock_env(), reply_msg.clone()).unwrap_err();
+    assert_eq!(err, StdError::generic_err("invalid reply id: 1"));
+
+    let id = state
+        .register_reply(deps.as_mut().storage, ReplyContext::RegisterReceivedCoins)
+        .unwrap();
+    assert_eq!(id, 1);
+    reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+
+    // The context is consumed by the reply
+    assert!(state
+        .reply_contexts
+        .may_load(deps.as_ref().storage, 1)
+        .unwrap()
+        .is_none());
+
+    // Unlocked coins in contract state should have been updated
+    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        unlocked_coins,
+        vec![
+            Coin::new(123, "ukrw"),
+            Coin::new(234, "uxyz"),
+            Coin::new(345, "uusd"),
+            Coin::new(
+                69420,
+                "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn reinvesting() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::from(0_u32))
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
+
+    // After the swaps, `unlocked_coins` should contain only uxyz and unknown denoms
+    state
+        .unlocked_coins
+        .save(
+            deps.as_mut().storage,
+            &vec![
+                Coin::new(234, "uxyz"),
+                Coin::new(
+                    69420,
+                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
+                ),
+            ],
+        )
+        .unwrap();
+
+    let modifier = 1_000_000_000_000_000_000_u128;
+
+    state
+        .total_mining_power
+        .save(deps.as_mut().storage, &Uint128::from(15_u128.mul(modifier)))
+        .unwrap();
+
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "alice".to_string(),
+            &5_u128.mul(modifier).into(),
+        )
+        .unwrap();
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "bob".to_string(),
+            &5_u128.mul(modifier).into(),
+        )
+        .unwrap();
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "charlie".to_string(),
+            &5_u128.mul(modifier).into(),
+        )
+        .unwrap();
+
+    let env = mock_env();
+    // Bob has the smallest amount of delegations, so all proceeds go to him
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    // decode first message as to MsgUndelegate
+    let decoded_message =
+        if let CosmosMsg::Stargate { type_url, value } = res.messages[0].msg.clone() {
+            // assert_eq!(type_url, "/liquidstaking.staking.v1beta1.MsgDelegate");
+            let msg_decoded: MsgDelegate = prost::Message::decode(value.as_slice()).unwrap();
+            // assert_eq!(msg_decoded.validator_address, "bob");
+            Some(msg_decoded)
+        } else {
+            None
+        };
+    // decode all messages to MsgUndelegate and transpose as result
+    let decoded_messages = res
+        .messages
+        .iter()
+        .map(|msg| {
+            if let CosmosMsg::Stargate { type_url, value } = msg.msg.clone() {
+                // assert_eq!(type_url, "/liquidstaking.staking.v1beta1.MsgDelegate");
+                let msg_decoded: MsgDelegate = prost::Message::decode(value.as_slice()).unwrap();
+                // assert_eq!(msg_decoded.validator_address, "bob");
+                Some(msg_decoded)
+            } else {
+                None
+            }
+        })
+        .filter(Option::is_some)
+        .collect::<Option<Vec<MsgDelegate>>>()
+        .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg {
+            id: 0,
+            msg: Delegation::new("bob", 234 - 23, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        },
+        "bob"
+    );
+    let send_msg = BankMsg::Send {
+        to_address: "the_fee_man".into(),
+        amount: vec![Coin::new(23u128, "uxyz")],
+    };
+    assert_eq!(
+        res.messages[1],
+        SubMsg {
+            id: 0,
+            msg: CosmosMsg::Bank(send_msg),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        },
+        "fee"
+    );
+
+    // The summary should report the epoch in full, unswapped rewards included
+    assert_eq!(
+        res.events[1],
+        Event::new("steakhub/reinvest_summary")
+            .add_attribute("time", env.block.time.seconds().to_string())
+            .add_attribute("height", env.block.height.to_string())
+            .add_attribute(
+                "gross_rewards",
+                "234uxyz,69420ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
+            )
+            .add_attribute("fee", "23")
+            .add_attribute("net_delegated", "211")
+            .add_attribute("validators", "bob")
+            .add_attribute("exchange_rate", "1")
+    );
+
+    // Storage should have been updated
+    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        unlocked_coins,
+        vec![Coin::new(
+            69420,
+            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
+        )],
+        "unlocked_coins"
+    );
+
+    // The fee payout should have been recorded in the ledger
+    let res: Vec<FeeLedgerEntry> = query_helper(
+        deps.as_ref(),
+        QueryMsg::FeeLedger {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![FeeLedgerEntry {
+            id: 0,
+            source: "reinvest".to_string(),
+            amount: Uint128::new(23),
+            denom: "uxyz".to_string(),
+            destination: Addr::unchecked("the_fee_man"),
+            fee_type: FeeType::Wallet,
+            height: env.block.height,
+        }]
+    );
+
+    // Running totals should have been updated
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(res.last_reinvest_amount, Uint128::new(211));
+    assert_eq!(res.total_fees_collected, Uint128::new(23));
+    assert_eq!(res.total_rewards_compounded, Uint128::new(211));
+}
+
+#[test]
+fn recording_fees() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    for i in 0..(FEE_LEDGER_CAPACITY + 5) {
+        state
+            .record_fee(
+                deps.as_mut().storage,
+                FeeLedgerEntry {
+                    id: 0,
+                    source: "reinvest".to_string(),
+                    amount: Uint128::new(i as u128),
+                    denom: "uxyz".to_string(),
+                    destination: Addr::unchecked("the_fee_man"),
+                    fee_type: FeeType::Wallet,
+                    height: i,
+                },
+            )
+            .unwrap();
+    }
+
+    // Only the most recent entries are kept
+    let ids = state
+        .fee_ledger
+        .keys(deps.as_ref().storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(ids, (5..(FEE_LEDGER_CAPACITY + 5)).collect::<Vec<_>>());
+
+    let res: Vec<FeeLedgerEntry> = query_helper(
+        deps.as_ref(),
+        QueryMsg::FeeLedger {
+            start_after: Some(FEE_LEDGER_CAPACITY + 2),
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res.iter().map(|entry| entry.id).collect::<Vec<_>>(),
+        vec![FEE_LEDGER_CAPACITY + 3, FEE_LEDGER_CAPACITY + 4]
+    );
+    assert_eq!(res[1].amount, Uint128::new(FEE_LEDGER_CAPACITY as u128 + 4));
+}
+
+#[test]
+fn reinvesting_with_mining() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::from(0_u32))
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
+
+    // After the swaps, `unlocked_coins` should contain only uxyz and unknown denoms
+    state
+        .unlocked_coins
+        .save(
+            deps.as_mut().storage,
+            &vec![
+                Coin::new(234, "uxyz"),
+                Coin::new(
+                    69420,
+                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
+                ),
+            ],
+        )
+        .unwrap();
+
+    let modifier = 1_000_000_000_000_000_000_u128;
+
+    state
+        .total_mining_power
+        .save(deps.as_mut().storage, &Uint128::from(15_u128.mul(modifier)))
+        .unwrap();
+
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "alice".to_string(),
+            &4_u128.mul(modifier).into(),
+        )
+        .unwrap();
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "bob".to_string(),
+            &4_u128.mul(modifier).into(),
+        )
+        .unwrap();
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "charlie".to_string(),
+            &7_u128.mul(modifier).into(),
+        )
+        .unwrap();
+
+    let env = mock_env();
+    // Bob has the smallest amount of delegations, so all proceeds go to him
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    // decode first message as to MsgUndelegate
+    let decoded_message =
+        if let CosmosMsg::Stargate { type_url, value } = res.messages[0].msg.clone() {
+            // assert_eq!(type_url, "/liquidstaking.staking.v1beta1.MsgDelegate");
+            let msg_decoded: MsgDelegate = prost::Message::decode(value.as_slice()).unwrap();
+            // assert_eq!(msg_decoded.validator_address, "bob");
+            Some(msg_decoded)
+        } else {
+            None
+        };
+    // decode all messages to MsgUndelegate and transpose as result
+    let decoded_messages = res
+        .messages
+        .iter()
+        .map(|msg| {
+            if let CosmosMsg::Stargate { type_url, value } = msg.msg.clone() {
+                // assert_eq!(type_url, "/liquidstaking.staking.v1beta1.MsgDelegate");
+                let msg_decoded: MsgDelegate = prost::Message::decode(value.as_slice()).unwrap();
+                // assert_eq!(msg_decoded.validator_address, "bob");
+                Some(msg_decoded)
+            } else {
+                None
+            }
+        })
+        .filter(Option::is_some)
+        .collect::<Option<Vec<MsgDelegate>>>()
+        .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg {
+            id: 0,
+            msg: Delegation::new("charlie", 234 - 23, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        },
+        "charlie"
+    );
+    let send_msg = BankMsg::Send {
+        to_address: "the_fee_man".into(),
+        amount: vec![Coin::new(23u128, "uxyz")],
+    };
+    assert_eq!(
+        res.messages[1],
+        SubMsg {
+            id: 0,
+            msg: CosmosMsg::Bank(send_msg),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        },
+        "fee"
+    );
+
+    // Storage should have been updated
+    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        unlocked_coins,
+        vec![Coin::new(
+            69420,
+            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
+        )],
+        "unlocked_coins"
+    );
+}
+
+#[test]
+fn reinvesting_fee_split() {
+    let mut deps = setup_test_fee_split();
+    let state = State::default();
+    let env = mock_env();
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::from(0_u32))
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
+
+    // After the swaps, `unlocked_coins` should contain only uxyz and unknown denoms
+    state
+        .unlocked_coins
+        .save(
+            deps.as_mut().storage,
+            &vec![
+                Coin::new(234, "uxyz"),
+                Coin::new(
+                    69420,
+                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
+                ),
+            ],
+        )
+        .unwrap();
+
+    let modifier = 1_000_000_000_000_000_000_u128;
+
+    state
+        .total_mining_power
+        .save(deps.as_mut().storage, &Uint128::from(15_u128.mul(modifier)))
+        .unwrap();
+
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "alice".to_string(),
+            &1_u128.mul(modifier).into(),
+        )
+        .unwrap();
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "bob".to_string(),
+            &12_u128.mul(modifier).into(),
+        )
+        .unwrap();
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "charlie".to_string(),
+            &2_u128.mul(modifier).into(),
+        )
+        .unwrap();
+
+    // Bob has the smallest amount of delegations, so all proceeds go to him
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg {
+            id: 0,
+            msg: Delegation::new("bob", 234 - 23, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        }
+    );
+    let send_msg = pfc_fee_split::fee_split_msg::ExecuteMsg::Deposit { flush: false };
+
+    assert_eq!(
+        res.messages[1],
+        SubMsg::reply_always(
+            send_msg
+                .into_cosmos_msg("fee_split_contract", vec![Coin::new(23u128, "uxyz")])
+                .unwrap(),
+            1
+        )
+    );
+
+    // A fee-split contract rejecting the deposit doesn't hold up compounding; the fees are kept
+    // in escrow
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: cosmwasm_std::SubMsgResult::Err("contract is paused".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/fee_deposit_failed")
+            .add_attribute("amount", "23")
+            .add_attribute("fee_escrow", "23")
+            .add_attribute("error", "contract is paused")]
+    );
+    let fee_escrow = state.fee_escrow.load(deps.as_ref().storage).unwrap();
+    assert_eq!(fee_escrow, Uint128::new(23));
+
+    // Storage should have been updated
+    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        unlocked_coins,
+        vec![Coin::new(
+            69420,
+            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
+        )],
+    );
+
+    // Once the fee-split contract is upgraded, the owner switches the hub to the new interface
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetFeeSplitInterface {
+            interface: FeeSplitInterface::V2,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetFeeSplitInterface {
+            interface: FeeSplitInterface::V2,
+        },
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.fee_split_interface, FeeSplitInterface::V2);
+
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(234, "uxyz")])
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::reply_always(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "fee_split_contract".to_string(),
+                msg: to_binary(&FeeSplitV2ExecuteMsg::Deposit {}).unwrap(),
+                funds: vec![Coin::new(23u128, "uxyz")],
+            }),
+            1
+        )
+    );
+
+    // Once the fee-split contract accepts deposits again, anyone can release the escrow to it
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::ReleaseFeeEscrow {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "fee_split_contract".to_string(),
+            msg: to_binary(&FeeSplitV2ExecuteMsg::Deposit {}).unwrap(),
+            funds: vec![Coin::new(23u128, "uxyz")],
+        }))]
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::ReleaseFeeEscrow {},
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("no fees in escrow"));
+}
+
+#[test]
+fn reinvesting_below_minimum() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::zero())
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(234, "uxyz")])
+        .unwrap();
+    state
+        .total_mining_power
+        .save(deps.as_mut().storage, &Uint128::new(3))
+        .unwrap();
+    for validator in ["alice", "bob", "charlie"] {
+        state
+            .validator_mining_powers
+            .save(
+                deps.as_mut().storage,
+                validator.to_string(),
+                &Uint128::new(1),
+            )
+            .unwrap();
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetMinReinvestAmount {
+            min_reinvest_amount: Uint128::new(1000),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMinReinvestAmount {
+            min_reinvest_amount: Uint128::new(1000),
+        },
+    )
+    .unwrap();
+
+    // 234 - 10% fee = 211 is below the minimum; nothing is delegated and nothing is paid out
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
+    assert_eq!(unlocked_coins, vec![Coin::new(234, "uxyz")]);
+    let carry = state.reinvest_carry.load(deps.as_ref().storage).unwrap();
+    assert_eq!(carry, Uint128::new(234));
+
+    // The next harvest brings in another 1000; the carried amount is reinvested with it
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::new(234))
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(1234u128, "uxyz")]);
+
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("bob", 1234 - 123, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+
+    let carry = state.reinvest_carry.load(deps.as_ref().storage).unwrap();
+    assert_eq!(carry, Uint128::zero());
+    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
+    assert_eq!(unlocked_coins, vec![]);
+}
+#[test]
+fn publishing_exchange_rate() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::zero())
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(234, "uxyz")])
+        .unwrap();
+    state
+        .total_mining_power
+        .save(deps.as_mut().storage, &Uint128::new(3))
+        .unwrap();
+    for validator in ["alice", "bob", "charlie"] {
+        state
+            .validator_mining_powers
+            .save(
+                deps.as_mut().storage,
+                validator.to_string(),
+                &Uint128::new(1),
+            )
+            .unwrap();
+    }
+
+    let wormhole = RatePublisher {
+        protocol: BridgeProtocol::Wormhole {
+            core_bridge: "wormhole_core".to_string(),
+        },
+        fee: Uint128::new(34),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetRatePublisher {
+            rate_publisher: Some(wormhole.clone()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetRatePublisher {
+            rate_publisher: Some(RatePublisher {
+                protocol: BridgeProtocol::Axelar {
+                    channel: "channel-4".to_string(),
+                    destination_chain: "ethereum".to_string(),
+                    destination_address: "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string(),
+                },
+                fee: Uint128::zero(),
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("axelar needs a non-zero fee"));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetRatePublisher {
+            rate_publisher: Some(wormhole.clone()),
+        },
+    )
+    .unwrap();
+
+    // The bridge's fee comes out of the rewards: 234 - 34 = 200, minus a 10% fee is 180 delegated
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("bob", 180, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    let payload = RatePayload {
+        exchange_rate: Decimal::from_ratio(1000180u128, 1000000u128),
+        supply: Uint128::new(1000000),
+        total_bonded: Uint128::new(1000180),
+        time: env.block.time.seconds(),
+    };
+    assert_eq!(
+        res.messages[2].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "wormhole_core".to_string(),
+            msg: to_binary(&WormholeExecuteMsg::PostMessage {
+                message: Binary(payload.abi_encode()),
+                nonce: env.block.height as u32,
+            })
+            .unwrap(),
+            funds: vec![Coin::new(34, "uxyz")],
+        })
+    );
+    assert_eq!(
+        res.events[0].attributes.last().unwrap(),
+        &attr("publication_fee", "34")
+    );
+
+    // Over Axelar, the message rides on an IBC transfer paying the fee
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetRatePublisher {
+            rate_publisher: Some(RatePublisher {
+                protocol: BridgeProtocol::Axelar {
+                    channel: "channel-4".to_string(),
+                    destination_chain: "ethereum".to_string(),
+                    destination_address: "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string(),
+                },
+                fee: Uint128::new(34),
+            }),
+        },
+    )
+    .unwrap();
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(234, "uxyz")])
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    let (type_url, value) = match &res.messages[2].msg {
+        CosmosMsg::Stargate { type_url, value } => (type_url.clone(), value.clone()),
+        msg => panic!("unexpected message {:?}", msg),
+    };
+    assert_eq!(type_url, "/ibc.applications.transfer.v1.MsgTransfer");
+    let transfer: MsgTransfer = prost::Message::decode(value.as_slice()).unwrap();
+    assert_eq!(transfer.source_channel, "channel-4");
+    assert_eq!(transfer.sender, MOCK_CONTRACT_ADDR);
+    assert_eq!(
+        transfer.receiver,
+        "axelar1dv4u5k73pzqrxlzujxg3qp8kvc3pje7jtdvu72npnt5zhq05ejcsn5qme5"
+    );
+    assert_eq!(transfer.token.unwrap().amount, "34");
+    let memo: AxelarGmpMemo = from_slice(transfer.memo.as_bytes()).unwrap();
+    assert_eq!(memo.destination_chain, "ethereum");
+    assert_eq!(memo.ty, 1);
+    assert_eq!(memo.payload, payload.abi_encode());
+
+    // Rewards that can't pay the fee are reinvested without publishing
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(30, "uxyz")])
+        .unwrap();
+    deps.querier.set_bank_balances(&[Coin::new(30u128, "uxyz")]);
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    let config: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert!(config.rate_publisher.is_some());
+}
+
+#[test]
+fn encoding_rate_payload() {
+    let payload = RatePayload {
+        exchange_rate: Decimal::percent(150),
+        supply: Uint128::new(258),
+        total_bonded: Uint128::new(387),
+        time: 1,
+    };
+    let bytes = payload.abi_encode();
+    assert_eq!(bytes.len(), 128);
+    assert_eq!(
+        hex::encode(&bytes[..32]),
+        "00000000000000000000000000000000000000000000000014d1120d7b160000"
+    );
+    assert_eq!(hex::encode(&bytes[32..64]), format!("{:064x}", 258));
+    assert_eq!(hex::encode(&bytes[64..96]), format!("{:064x}", 387));
+    assert_eq!(hex::encode(&bytes[96..]), format!("{:064x}", 1));
+}
+#[test]
+fn providing_protocol_owned_liquidity() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::zero())
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(234, "uxyz")])
+        .unwrap();
+    state
+        .total_mining_power
+        .save(deps.as_mut().storage, &Uint128::new(3))
+        .unwrap();
+    for validator in ["alice", "bob", "charlie"] {
+        state
+            .validator_mining_powers
+            .save(
+                deps.as_mut().storage,
+                validator.to_string(),
+                &Uint128::new(1),
+            )
+            .unwrap();
+    }
+
+    let treasury = TreasuryConfig {
+        pair: "steak_pair".to_string(),
+        lp_token: "steak_lp".to_string(),
+        fee_share: Decimal::percent(50),
+        slippage_tolerance: Decimal::percent(1),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetTreasury {
+            treasury: Some(treasury.clone()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetTreasury {
+            treasury: Some(TreasuryConfig {
+                fee_share: Decimal::percent(150),
+                ..treasury.clone()
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("treasury fee share can't be more than 100%")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetTreasury {
+            treasury: Some(treasury.clone()),
+        },
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ProvideLiquidity {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("treasury holds 0 of Native Token, too little to provide liquidity")
+    );
+
+    // Half of the 23 fee is set aside; the fee account gets the rest
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "the_fee_man".to_string(),
+            amount: vec![Coin::new(12, "uxyz")],
+        })
+    );
+    assert_eq!(
+        state.treasury_escrow.load(deps.as_ref().storage).unwrap(),
+        Uint128::new(11)
+    );
+
+    // Only the treasury's Native Token is left once the rewards are delegated
+    deps.querier.set_bank_balances(&[Coin::new(11u128, "uxyz")]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ProvideLiquidity {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("bob", 5, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::new(5),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(
+        res.messages[2].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                spender: "steak_pair".to_string(),
+                amount: Uint128::new(5),
+                expires: None,
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(
+        res.messages[3].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_pair".to_string(),
+            msg: to_binary(&PairExecuteMsg::ProvideLiquidity {
+                assets: vec![
+                    Asset {
+                        info: AssetInfo::Token {
+                            contract_addr: "steak_token".to_string()
+                        },
+                        amount: Uint128::new(5),
+                    },
+                    Asset {
+                        info: AssetInfo::NativeToken {
+                            denom: "uxyz".to_string()
+                        },
+                        amount: Uint128::new(6),
+                    },
+                ],
+                slippage_tolerance: Some(Decimal::percent(1)),
+            })
+            .unwrap(),
+            funds: vec![Coin::new(6, "uxyz")],
+        })
+    );
+
+    deps.querier
+        .set_cw20_balance("steak_lp", MOCK_CONTRACT_ADDR, 42);
+    let res: TreasuryResponse = query_helper(deps.as_ref(), QueryMsg::Treasury {});
+    assert_eq!(
+        res,
+        TreasuryResponse {
+            treasury: Some(treasury),
+            native_escrow: Uint128::zero(),
+            native_provided: Uint128::new(6),
+            usteak_provided: Uint128::new(5),
+            lp_tokens: Uint128::new(42),
+        }
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("larry", &[]),
+        ExecuteMsg::TransferTreasuryLp {
+            recipient: "dao".to_string(),
+            amount: Uint128::new(42),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_lp".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "dao".to_string(),
+                amount: Uint128::new(42),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+}
+
+#[test]
+fn reinvesting_reward_campaigns() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    state
+        .total_mining_power
+        .save(deps.as_mut().storage, &Uint128::new(3))
+        .unwrap();
+    for validator in ["alice", "bob", "charlie"] {
+        state
+            .validator_mining_powers
+            .save(
+                deps.as_mut().storage,
+                validator.to_string(),
+                &Uint128::new(1),
+            )
+            .unwrap();
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("jake", &[Coin::new(10000, "uxyz")]),
+        ExecuteMsg::FundRewardCampaign {
+            start: 9000,
+            end: 11000,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("reward campaign can't start in the past")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("jake", &[Coin::new(10000, "uxyz")]),
+        ExecuteMsg::FundRewardCampaign {
+            start: 11000,
+            end: 11000,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("reward campaign must end after it starts")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("jake", &[Coin::new(10000, "uxyz")]),
+        ExecuteMsg::FundRewardCampaign {
+            start: 10100,
+            end: 11100,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let campaign = RewardCampaign {
+        id: 1,
+        funder: Addr::unchecked("jake"),
+        amount: Uint128::new(10000),
+        released: Uint128::zero(),
+        start: 10100,
+        end: 11100,
+    };
+    let res: Vec<RewardCampaign> = query_helper(
+        deps.as_ref(),
+        QueryMsg::RewardCampaigns {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![campaign.clone()]);
+
+    // The funds sit in the contract's balance from before the harvest started, so they aren't
+    // mistaken for rewards. Halfway through the window, half of them are delegated, without a fee
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::new(10000))
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(10000u128, "uxyz")]);
+
+    let env = mock_env_at_timestamp(10600);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("bob", 5000, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+
+    let res: Vec<RewardCampaign> = query_helper(
+        deps.as_ref(),
+        QueryMsg::RewardCampaigns {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![RewardCampaign {
+            released: Uint128::new(5000),
+            ..campaign
+        }]
+    );
+    assert_eq!(
+        state
+            .reward_campaign_escrow
+            .load(deps.as_ref().storage)
+            .unwrap(),
+        Uint128::new(5000)
+    );
+
+    // Once the window is over, the rest is delegated and the campaign is dropped
+    deps.querier
+        .set_bank_balances(&[Coin::new(5000u128, "uxyz")]);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::new(5000))
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    let res: Vec<RewardCampaign> = query_helper(
+        deps.as_ref(),
+        QueryMsg::RewardCampaigns {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![]);
+    assert_eq!(
+        state
+            .reward_campaign_escrow
+            .load(deps.as_ref().storage)
+            .unwrap(),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn reinvesting_with_rebase_limit() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::zero())
+        .unwrap();
+    deps.querier
+        .set_bank_balances(&[Coin::new(234u128, "uxyz")]);
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(234, "uxyz")])
+        .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetMaxRebaseBps {
+            max_rebase_bps: Some(1),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMaxRebaseBps {
+            max_rebase_bps: Some(0),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("max rebase must be positive; use none to remove the limit")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMaxRebaseBps {
+            max_rebase_bps: Some(1),
+        },
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.max_rebase_bps, Some(1));
+
+    // 1 bps of the 1,000,000 bonded is 100; the other 134 are held back
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("bob", 100 - 10, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "the_fee_man".to_string(),
+            amount: vec![Coin::new(10, "uxyz")],
+        })
+    );
+    assert_eq!(
+        res.events[0].attributes.last().unwrap(),
+        &attr("amount_deferred", "134")
+    );
+    let carry = state.reinvest_carry.load(deps.as_ref().storage).unwrap();
+    assert_eq!(carry, Uint128::new(134));
+
+    // Without new rewards, the held back amount drips out over the following reinvests
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::new(234))
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    let carry = state.reinvest_carry.load(deps.as_ref().storage).unwrap();
+    assert_eq!(carry, Uint128::new(34));
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("bob", 34 - 3, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    let carry = state.reinvest_carry.load(deps.as_ref().storage).unwrap();
+    assert_eq!(carry, Uint128::zero());
+}
+
+#[test]
+fn queuing_unbond() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // Only Steak token is accepted for unbonding requests
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("random_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "hacker".to_string(),
+            amount: Uint128::new(69420),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::generic_err("token random_token is not accepted")
+    );
+
+    // User 1 creates an unbonding request before `est_unbond_start_time` is reached. The unbond
+    // request is saved, but not the pending batch is not submitted for unbonding
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345), // est_unbond_start_time = 269200
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(23456),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 0);
+    assert_eq!(
+        from_binary::<UnbondReceipt>(&res.data.unwrap()).unwrap(),
+        UnbondReceipt {
+            batch_id: 1,
+            receiver: "user_1".to_string(),
+            usteak_to_burn: Uint128::new(23456),
+            request_shares: Uint128::new(23456),
+            est_unbond_start_time: 269200,
+        }
+    );
+
+    // User 2 creates an unbonding request after `est_unbond_start_time` is reached. The unbond
+    // request is saved, and the pending is automatically submitted for unbonding
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201), // est_unbond_start_time = 269200
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_2".to_string(),
+            amount: Uint128::new(69420),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: Some("user_3".to_string()),
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg {
+            id: 0,
+            msg: CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                msg: to_binary(&ExecuteMsg::SubmitBatch { exclude: vec![] }).unwrap(),
+                funds: vec![]
+            }),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        }
+    );
+
+    // The users' unbonding requests should have been saved
+    let ubr1 = state
+        .load_unbond_request(deps.as_ref().storage, 1, &Addr::unchecked("user_1"))
+        .unwrap()
+        .unwrap();
+    let ubr2 = state
+        .load_unbond_request(deps.as_ref().storage, 1, &Addr::unchecked("user_3"))
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        ubr1,
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(23456)
+        }
+    );
+    assert_eq!(
+        ubr2,
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_3"),
+            shares: Uint128::new(69420)
+        }
+    );
+
+    // Pending batch should have been updated
+    let pending_batch = state
+        .pending_batches
+        .load(deps.as_ref().storage, "uxyz")
+        .unwrap();
+    assert_eq!(
+        pending_batch,
+        PendingBatch {
+            id: 1,
+            usteak_to_burn: Uint128::new(92876), // 23,456 + 69,420
+            est_unbond_start_time: 269200,
+            rollovers: 0,
+        }
+    );
+
+    // The sender of each request should be queryable, even when it differs from the receiver
+    let res: Vec<UnbondRequestsBySenderResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsBySender {
+            sender: "user_2".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![UnbondRequestsBySenderResponseItem {
+            id: 1,
+            receiver: "user_3".to_string(),
+            shares: Uint128::new(69420)
+        }]
+    );
+
+    let res: Vec<UnbondRequestsBySenderResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsBySender {
+            sender: "user_2".to_string(),
+            start_after: Some((1, "user_3".to_string())),
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![]);
+
+    let res: Vec<UnbondRequestsBySenderResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsBySender {
+            sender: "user_3".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![]);
+}
+#[test]
+fn recording_queue_time_exchange_rates() {
+    let mut deps = setup_test();
+
+    // native_token per ustake: 1.025
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let queue_unbond = |deps: DepsMut, amount: u128| {
+        execute(
+            deps,
+            mock_env_at_timestamp(12345),
+            mock_info("steak_token", &[]),
+            ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: "user_1".to_string(),
+                amount: Uint128::new(amount),
+                msg: to_binary(&ReceiveMsg::QueueUnbond {
+                    receiver: None,
+                    min_native_out: None,
+                })
+                .unwrap(),
+            }),
+        )
+        .unwrap()
+    };
+    queue_unbond(deps.as_mut(), 100);
+
+    let res: Vec<UnbondRequestsByUserResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByUser {
+            user: "user_1".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![UnbondRequestsByUserResponseItem {
+            id: 1,
+            shares: Uint128::new(100),
+            queued_exchange_rate: Some(Decimal::from_ratio(1025u128, 1000u128)),
+        }]
+    );
+
+    // After a slash the rate is down to 1.0. The request averages the two calls by their shares:
+    // 1.025 * 100 / 400 + 1.0 * 300 / 400 = 1.00625
+    deps.querier.set_cw20_total_supply("steak_token", 1025000);
+    queue_unbond(deps.as_mut(), 300);
+
+    let res: Vec<UnbondRequestsByBatchResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByBatch {
+            id: 1,
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![UnbondRequestsByBatchResponseItem {
+            user: "user_1".to_string(),
+            shares: Uint128::new(400),
+            queued_exchange_rate: Some(Decimal::from_str("1.00625").unwrap()),
+        }]
+    );
+}
+
+#[test]
+fn recording_unbond_calls() {
+    let mut deps = setup_test();
+
+    let queue = |deps: &mut OwnedDeps<_, _, _>, time: u64, sender: &str, amount: u128| {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(time),
+            mock_info("steak_token", &[]),
+            ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: sender.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_binary(&ReceiveMsg::QueueUnbond {
+                    receiver: Some("user_1".to_string()),
+                    min_native_out: None,
+                })
+                .unwrap(),
+            }),
+        )
+        .unwrap()
+    };
+
+    // Each call gets its own record, and the event carries the running total of the request
+    let res = queue(&mut deps, 10000, "user_1", 100);
+    assert_eq!(res.events[0].attributes[6].value, "0");
+    assert_eq!(res.events[0].attributes[7].value, "100");
+    let res = queue(&mut deps, 10001, "user_2", 50);
+    assert_eq!(res.events[0].attributes[6].value, "1");
+    assert_eq!(res.events[0].attributes[7].value, "150");
+
+    let res: Vec<UnbondCall> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestCalls {
+            id: 1,
+            user: "user_1".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![
+            UnbondCall {
+                index: 0,
+                sender: Addr::unchecked("user_1"),
+                shares: Uint128::new(100),
+                time: 10000,
+                calls: 1
+            },
+            UnbondCall {
+                index: 1,
+                sender: Addr::unchecked("user_2"),
+                shares: Uint128::new(50),
+                time: 10001,
+                calls: 1
+            }
+        ]
+    );
+
+    // Once the request is full, further calls are merged into the last record
+    for i in 2..(MAX_UNBOND_CALLS_PER_REQUEST as u64 + 2) {
+        queue(&mut deps, 10000 + i, "user_1", 1);
+    }
+    let state = State::default();
+    let calls = state
+        .unbond_calls
+        .prefix((1u64, &Addr::unchecked("user_1")))
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .count();
+    assert_eq!(calls, MAX_UNBOND_CALLS_PER_REQUEST as usize);
+
+    let res: Vec<UnbondCall> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestCalls {
+            id: 1,
+            user: "user_1".to_string(),
+            start_after: Some(MAX_UNBOND_CALLS_PER_REQUEST - 2),
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![UnbondCall {
+            index: MAX_UNBOND_CALLS_PER_REQUEST - 1,
+            sender: Addr::unchecked("user_1"),
+            shares: Uint128::new(3),
+            time: 10000 + MAX_UNBOND_CALLS_PER_REQUEST as u64 + 1,
+            calls: 3
+        }]
+    );
+
+    let ubr = state
+        .load_unbond_request(deps.as_ref().storage, 1, &Addr::unchecked("user_1"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        ubr.shares,
+        Uint128::new(150 + MAX_UNBOND_CALLS_PER_REQUEST as u128)
+    );
+}
+
+#[test]
+fn guarding_exchange_rate() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let bond = |deps: &mut OwnedDeps<_, _, _>| {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+            ExecuteMsg::Bond {
+                receiver: None,
+                referrer: None,
+                min_usteak_out: None,
+            },
+        )
+    };
+
+    // The first bond records the exchange rate
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    bond(&mut deps).unwrap();
+    assert_eq!(
+        state
+            .last_exchange_rate
+            .load(deps.as_ref().storage)
+            .unwrap(),
+        Decimal::from_ratio(1025000u128, 1000000u128)
+    );
+
+    // A validator leaving the active set takes nothing off the rate, its stake still counts
+    let validators_active = state.validators_active.load(deps.as_ref().storage).unwrap();
+    state
+        .validators_active
+        .save(
+            deps.as_mut().storage,
+            &vec!["alice".to_string(), "bob".to_string()],
+        )
+        .unwrap();
+    bond(&mut deps).unwrap();
+    assert_eq!(
+        state
+            .last_exchange_rate
+            .load(deps.as_ref().storage)
+            .unwrap(),
+        Decimal::from_ratio(1025000u128, 1000000u128)
+    );
+    state
+        .validators_active
+        .save(deps.as_mut().storage, &validators_active)
+        .unwrap();
+
+    // A drop within the epsilon is let through
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341000, "uxyz"),
+    ]);
+    bond(&mut deps).unwrap();
+
+    // A larger drop is refused until it is acknowledged
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 300000, "uxyz"),
+    ]);
+    let err = bond(&mut deps).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(
+            "exchange rate dropped from 1.024334 to 0.983334; a guardian must acknowledge the slash"
+        )
+    );
+
+    // Only the owner can set the guardian, and once set, only the guardian can acknowledge
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetGuardian {
+            guardian: Some("guardian".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetGuardian {
+            guardian: Some("guardian".to_string()),
+        },
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcknowledgeSlash { validator: None },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not guardian")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::AcknowledgeSlash { validator: None },
+    )
+    .unwrap();
+
+    // The acknowledged drop goes through and becomes the new reference
+    bond(&mut deps).unwrap();
+    let res: ExchangeRateFloorResponse =
+        query_helper(deps.as_ref(), QueryMsg::ExchangeRateFloor {});
+    assert_eq!(
+        res,
+        ExchangeRateFloorResponse {
+            last_exchange_rate: Some(Decimal::from_ratio(983334u128, 1000000u128)),
+            epsilon: Decimal::permille(1),
+            guardian: Some("guardian".to_string()),
+            slash_acknowledged: false,
+        }
+    );
+
+    // The owner can widen the epsilon, but not to 100% or more
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetExchangeRateEpsilon {
+            epsilon: Decimal::one(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("epsilon must be less than 1"));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetExchangeRateEpsilon {
+            epsilon: Decimal::percent(10),
+        },
+    )
+    .unwrap();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 250000, "uxyz"),
+    ]);
+    bond(&mut deps).unwrap();
+}
+
+#[test]
+fn penalizing_repeat_offenders() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetSlashLimit {
+            slash_limit: Some(0),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(
+            "slash limit must be positive; use none to never deactivate validators"
+        )
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetSlashLimit {
+            slash_limit: Some(2),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.slash_limit, Some(2));
+
+    // With no guardian set, the owner acknowledges slashes
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcknowledgeSlash {
+            validator: Some("dave".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("validator dave is not whitelisted")
+    );
+
+    // The first incident is only recorded
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcknowledgeSlash {
+            validator: Some("bob".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.events.len(), 1);
+    assert_eq!(
+        res.events[0],
+        Event::new("steak/slash_acknowledged")
+            .add_attribute("guardian", "larry")
+            .add_attribute("validator", "bob")
+            .add_attribute("slash_incidents", "1")
+    );
+    let validators_active = state.validators_active.load(deps.as_ref().storage).unwrap();
+    assert!(validators_active.contains(&"bob".to_string()));
+
+    // The second one puts the validator in the penalty box
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcknowledgeSlash {
+            validator: Some("bob".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events[0],
+        Event::new("steak/validator_penalized")
+            .add_attribute("validator", "bob")
+            .add_attribute("slash_incidents", "2")
+    );
+    let validators_active = state.validators_active.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        validators_active,
+        vec!["alice".to_string(), "charlie".to_string()]
+    );
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert!(validators.contains(&"bob".to_string()));
+
+    // Only the owner may reinstate it, which clears its record
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::ReinstateValidator {
+            validator: "bob".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ReinstateValidator {
+            validator: "bob".to_string(),
+        },
+    )
+    .unwrap();
+    let validators_active = state.validators_active.load(deps.as_ref().storage).unwrap();
+    assert!(validators_active.contains(&"bob".to_string()));
+    let slash_incidents = state
+        .slash_incidents
+        .may_load(deps.as_ref().storage, "bob".to_string())
+        .unwrap();
+    assert_eq!(slash_incidents, None);
+}
+
+#[test]
+fn suspending_validator() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("larry", &[]),
+        ExecuteMsg::SuspendValidator {
+            validator: "charlie".to_string(),
+            until: 10000,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("suspension must end after the current time 10000")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("larry", &[]),
+        ExecuteMsg::SuspendValidator {
+            validator: "charlie".to_string(),
+            until: 20000,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steak/suspend_validator")
+            .add_attribute("validator", "charlie")
+            .add_attribute("until", "20000")]
+    );
+    let validators_active = state.validators_active.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        validators_active,
+        vec!["alice".to_string(), "bob".to_string()]
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("larry", &[]),
+        ExecuteMsg::SuspendValidator {
+            validator: "dave".to_string(),
+            until: 20000,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("validator dave is not active"));
+
+    // Charlie has the smallest delegation, but is skipped while suspended
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(19999),
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("alice", 12345, "uxyz")
+            .to_cosmos_msg(MOCK_CONTRACT_ADDR.to_string(), &StakingProtoUrls::default())
+            .unwrap()
+    );
+
+    let res: ValidatorSummaryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env_at_timestamp(19999),
+            QueryMsg::ValidatorSummary {
+                validator: "charlie".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(!res.active);
+    assert_eq!(res.suspended_until, Some(20000));
+
+    // Once the suspension ends, Charlie is back without the owner stepping in
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("charlie", 12345, "uxyz")
+            .to_cosmos_msg(MOCK_CONTRACT_ADDR.to_string(), &StakingProtoUrls::default())
+            .unwrap()
+    );
+    let validators_active = state.validators_active.load(deps.as_ref().storage).unwrap();
+    assert!(validators_active.contains(&"charlie".to_string()));
+    assert!(!state
+        .validator_suspensions
+        .has(deps.as_ref().storage, "charlie".to_string()));
+
+    // Pausing a suspended validator clears the suspension, and it stays out once it would have ended
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("larry", &[]),
+        ExecuteMsg::SuspendValidator {
+            validator: "charlie".to_string(),
+            until: 30000,
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("larry", &[]),
+        ExecuteMsg::PauseValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap();
+    assert!(!state
+        .validator_suspensions
+        .has(deps.as_ref().storage, "charlie".to_string()));
+    let res: ValidatorSummaryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env_at_timestamp(35000),
+            QueryMsg::ValidatorSummary {
+                validator: "charlie".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(!res.active);
+    assert_eq!(res.suspended_until, None);
+
+    // A validator penalized while suspended isn't shown as active once the suspension ends
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetSlashLimit {
+            slash_limit: Some(1),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("larry", &[]),
+        ExecuteMsg::SuspendValidator {
+            validator: "bob".to_string(),
+            until: 30000,
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcknowledgeSlash {
+            validator: Some("bob".to_string()),
+        },
+    )
+    .unwrap();
+    let res: ValidatorSummaryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env_at_timestamp(35000),
+            QueryMsg::ValidatorSummary {
+                validator: "bob".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(!res.active);
+    assert_eq!(res.suspended_until, None);
+}
+
+#[test]
+fn accepting_tokens() {
+    let mut deps = setup_test();
+
+    // The Steak token is registered when it is instantiated
+    let res: Vec<AcceptedTokenResponse> = query_helper(
+        deps.as_ref(),
+        QueryMsg::AcceptedTokens {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![AcceptedTokenResponse {
+            token: "steak_token".to_string(),
+            receive_msgs: vec![ReceiveMsgKind::QueueUnbond],
+        }]
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetAcceptedToken {
+            token: "voucher_token".to_string(),
+            receive_msgs: vec![ReceiveMsgKind::QueueUnbond],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    // Listing another token doesn't let it stand in for Steak
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetAcceptedToken {
+            token: "voucher_token".to_string(),
+            receive_msgs: vec![ReceiveMsgKind::QueueUnbond],
+        },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("voucher_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(23456),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("expecting Steak token, received voucher_token")
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetAcceptedToken {
+            token: "voucher_token".to_string(),
+            receive_msgs: vec![],
+        },
+    )
+    .unwrap();
+
+    // Once delisted, the Steak token can no longer be used to queue unbonds
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetAcceptedToken {
+            token: "steak_token".to_string(),
+            receive_msgs: vec![],
+        },
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(23456),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("token steak_token is not accepted")
+    );
+
+    let res: Vec<AcceptedTokenResponse> = query_helper(
+        deps.as_ref(),
+        QueryMsg::AcceptedTokens {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![]);
+}
+
+#[test]
+fn migrating_token() {
+    let mut deps = setup_test();
+    let state = State::default();
+    let usteak_denom = "factory/cosmos2contract/steak";
+
+    // The hub holds 1000 usteak in escrow for unbond orders
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334, "uxyz"),
+        Delegation::new("bob", 333333, "uxyz"),
+        Delegation::new("charlie", 333333, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    deps.querier
+        .set_cw20_balance("steak_token", MOCK_CONTRACT_ADDR, 1000);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::MigrateToken {
+            new_backend: TokenBackend::TokenFactory {
+                subdenom: "steak".to_string(),
+            },
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::MigrateToken {
+            new_backend: TokenBackend::Cw20,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("the token already uses the cw20 backend")
+    );
+
+    // The denom is created, and the hub's escrow swapped straight away
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::MigrateToken {
+            new_backend: TokenBackend::TokenFactory {
+                subdenom: "steak".to_string(),
+            },
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(create_denom_msg(MOCK_CONTRACT_ADDR.to_string(), "steak".to_string()).unwrap())
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(1000)
+            })
+            .unwrap(),
+            funds: vec![],
+        }))
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(
+            mint_msg(
+                MOCK_CONTRACT_ADDR.to_string(),
+                usteak_denom,
+                Uint128::new(1000)
+            )
+            .unwrap()
+        )
+    );
+    deps.querier.set_cw20_total_supply("steak_token", 999000);
+    deps.querier
+        .set_cw20_balance("steak_token", MOCK_CONTRACT_ADDR, 0);
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.usteak_denom, Some(usteak_denom.to_string()));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::MigrateToken {
+            new_backend: TokenBackend::TokenFactory {
+                subdenom: "steak2".to_string(),
+            },
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("the token has already been migrated")
+    );
+
+    // Bonding mints the denom, counting both forms towards the supply
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(
+            mint_msg(
+                MOCK_CONTRACT_ADDR.to_string(),
+                usteak_denom,
+                Uint128::new(1000)
+            )
+            .unwrap()
+        )
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(1000, usteak_denom)],
+        }))
+    );
+
+    // Holders swap their cw20 at their own pace
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_2".to_string(),
+            amount: Uint128::new(500),
+            msg: to_binary(&ReceiveMsg::SwapToken {}).unwrap(),
+        }),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_2".to_string(),
+            amount: vec![Coin::new(500, usteak_denom)],
+        }))
+    );
+
+    // Any other token is refused, even if listed for swaps
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetAcceptedToken {
+            token: "voucher_token".to_string(),
+            receive_msgs: vec![ReceiveMsgKind::SwapToken],
+        },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("voucher_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_2".to_string(),
+            amount: Uint128::new(500),
+            msg: to_binary(&ReceiveMsg::SwapToken {}).unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("expecting Steak token, received voucher_token")
+    );
+
+    // The denom is unbonded by sending it along; cw20 sent to unbond is swapped first
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_1", &[Coin::new(1000, "steak_token")]),
+        ExecuteMsg::QueueUnbond {
+            receiver: None,
+            min_native_out: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "expected {} deposit, received steak_token",
+            usteak_denom
+        ))
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_1", &[Coin::new(1000, usteak_denom)]),
+        ExecuteMsg::QueueUnbond {
+            receiver: None,
+            min_native_out: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_3".to_string(),
+            amount: Uint128::new(200),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(
+            mint_msg(
+                MOCK_CONTRACT_ADDR.to_string(),
+                usteak_denom,
+                Uint128::new(200)
+            )
+            .unwrap()
+        )
+    );
+
+    let pending_batch = state
+        .pending_batches
+        .load(deps.as_ref().storage, "uxyz")
+        .unwrap();
+    assert_eq!(pending_batch.usteak_to_burn, Uint128::new(1200));
+    let usteak_denom_supply = state
+        .usteak_denom_supply
+        .load(deps.as_ref().storage)
+        .unwrap();
+    assert_eq!(usteak_denom_supply, Uint128::new(2700));
+
+    // The batch is burned in the denom
+    deps.querier.set_cw20_total_supply("steak_token", 998300);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 334000, "uxyz"),
+        Delegation::new("bob", 334000, "uxyz"),
+        Delegation::new("charlie", 333000, "uxyz"),
+    ]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info("jake", &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages.last().unwrap(),
+        &SubMsg::new(
+            burn_msg(
+                MOCK_CONTRACT_ADDR.to_string(),
+                usteak_denom,
+                Uint128::new(1200)
+            )
+            .unwrap()
+        )
+    );
+    let usteak_denom_supply = state
+        .usteak_denom_supply
+        .load(deps.as_ref().storage)
+        .unwrap();
+    assert_eq!(usteak_denom_supply, Uint128::new(1500));
+}
+
+#[test]
+fn administering_token_contract() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::UpdateTokenAdmin {
+            admin: "jake".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::UpdateTokenAdmin {
+            admin: "multisig".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::UpdateAdmin {
+            contract_addr: "steak_token".to_string(),
+            admin: "multisig".to_string(),
+        })]
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::MigrateTokenContract {
+            new_code_id: 420,
+            msg: to_binary(&Empty {}).unwrap(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::MigrateTokenContract {
+            new_code_id: 420,
+            msg: to_binary(&Empty {}).unwrap(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Migrate {
+            contract_addr: "steak_token".to_string(),
+            new_code_id: 420,
+            msg: to_binary(&Empty {}).unwrap(),
+        })]
+    );
+}
+
+#[test]
+fn capping_unbond_orders() {
+    let mut deps = setup_test();
+
+    let create_order = ExecuteMsg::CreateUnbondOrder {
+        shares_per_epoch: Uint128::new(1000),
+        epochs: 2,
+    };
+    for i in 0..MAX_UNBOND_ORDERS {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(&format!("user_{}", i), &[]),
+            create_order.clone(),
+        )
+        .unwrap();
+    }
+
+    // Every batch submission goes through all orders, so their number is bounded
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        create_order.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "no more than {} unbond orders may be open at once",
+            MAX_UNBOND_ORDERS
+        ))
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_0", &[]),
+        ExecuteMsg::CancelUnbondOrder {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        create_order,
+    )
+    .unwrap();
+}
+
+#[test]
+fn streaming_unbond_orders_into_rolled_over_batch() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782, "uxyz"),
+        Delegation::new("bob", 345782, "uxyz"),
+        Delegation::new("charlie", 345781, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetBatchRollover {
+            batch_rollover: Some(BatchRollover {
+                min_shares: Uint128::new(5000),
+                max_rollovers: 2,
+            }),
+        },
+    )
+    .unwrap();
+    state
+        .unbond_orders
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("user_1"),
+            &UnbondOrder {
+                user: Addr::unchecked("user_1"),
+                shares_per_epoch: Uint128::new(1000),
+                epochs_remaining: 3,
+                last_batch_id: None,
+            },
+        )
+        .unwrap();
+
+    // The batch is too small and rolls over twice, but the order only adds its slice once
+    for time in [269201, 528401] {
+        let res = execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(time),
+            mock_info(MOCK_CONTRACT_ADDR, &[]),
+            ExecuteMsg::SubmitBatch { exclude: vec![] },
+        )
+        .unwrap();
+        assert_eq!(res.events[0].ty, "steakhub/batch_rolled_over");
+
+        let pending_batch = state
+            .pending_batches
+            .load(deps.as_ref().storage, "uxyz")
+            .unwrap();
+        assert_eq!(pending_batch.id, 1);
+        assert_eq!(pending_batch.usteak_to_burn, Uint128::new(1000));
+        let order = state
+            .unbond_orders
+            .load(deps.as_ref().storage, &Addr::unchecked("user_1"))
+            .unwrap();
+        assert_eq!(order.epochs_remaining, 2);
+        assert_eq!(order.last_batch_id, Some(1));
+    }
+
+    // Out of rollovers, the batch goes out with the one slice; the next batch gets the next one
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(787601),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap();
+    assert_eq!(batch.total_shares, Uint128::new(1000));
+    let request = state
+        .load_unbond_request(deps.as_ref().storage, 1, &Addr::unchecked("user_1"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(request.shares, Uint128::new(1000));
+}
+
+#[test]
+fn streaming_unbond_orders() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782, "uxyz"),
+        Delegation::new("bob", 345782, "uxyz"),
+        Delegation::new("charlie", 345781, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CreateUnbondOrder {
+            shares_per_epoch: Uint128::new(1000),
+            epochs: 0,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("shares per epoch and epochs must be greater than zero")
+    );
+
+    // Slices are held to the minimum of `QueueUnbond`
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMinUnbondShares {
+            min_unbond_shares: Uint128::new(500),
+        },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CreateUnbondOrder {
+            shares_per_epoch: Uint128::new(499),
+            epochs: 2,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("shares per epoch 499 is below the minimum of 500")
+    );
+
+    // The full amount is escrowed using the allowance granted to the hub
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CreateUnbondOrder {
+            shares_per_epoch: Uint128::new(1000),
+            epochs: 2,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: "user_1".to_string(),
+                recipient: MOCK_CONTRACT_ADDR.to_string(),
+                amount: Uint128::new(2000),
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CreateUnbondOrder {
+            shares_per_epoch: Uint128::new(1000),
+            epochs: 2,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("user already has an unbond order")
+    );
+
+    // A slice is queued into the batch as it is submitted
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap();
+    assert_eq!(batch.total_shares, Uint128::new(1000));
+    let request = state
+        .load_unbond_request(deps.as_ref().storage, 1, &Addr::unchecked("user_1"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(request.shares, Uint128::new(1000));
+
+    let res: Option<UnbondOrder> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondOrder {
+            user: "user_1".to_string(),
+        },
+    );
+    assert_eq!(
+        res,
+        Some(UnbondOrder {
+            user: Addr::unchecked("user_1"),
+            shares_per_epoch: Uint128::new(1000),
+            epochs_remaining: 1,
+            last_batch_id: Some(1),
+        })
+    );
+
+    // Cancelling returns what is left in escrow
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CancelUnbondOrder {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "steak_token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "user_1".to_string(),
+                amount: Uint128::new(1000),
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    let res: Option<UnbondOrder> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondOrder {
+            user: "user_1".to_string(),
+        },
+    );
+    assert_eq!(res, None);
+}
+
+#[test]
+fn submitting_batch() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // native_token bonded: 1,037,345
+    // usteak supply: 1,012,043
+    // native_token per ustake: 1.025
+    DelegationSetBuilder::new()
+        .split(&["alice", "bob", "charlie"], 1037345)
+        .apply(&mut deps.querier);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+
+    // We continue from the contract state at the end of the last test
+    for (user, shares) in [("user_1", 23456), ("user_3", 69420)] {
+        UnbondRequestBuilder::new(1, user)
+            .shares(shares)
+            .save(deps.as_mut().storage)
+            .unwrap();
+    }
+
+    state
+        .pending_batches
+        .save(
+            deps.as_mut().storage,
+            "uxyz",
+            &PendingBatch {
+                id: 1,
+                usteak_to_burn: Uint128::new(92876), // 23,456 + 69,420
+                est_unbond_start_time: 269200,
+                rollovers: 0,
+            },
+        )
+        .unwrap();
+
+    // Anyone can invoke `submit_batch`. Here we continue from the previous test and assume it is
+    // invoked automatically as user 2 submits the unbonding request
+    //
+    // usteak to burn: 23,456 + 69,420 = 92,876
+    // native_token to unbond: 1,037,345 * 92,876 / 1,012,043 = 95,197
+    //
+    // Target: (1,037,345 - 95,197) / 3 = 314,049
+    // Remainer: 1
+    // Alice:   345,782 - (314,049 + 1) = 31,732
+    // Bob:     345,782 - (314,049 + 0) = 31,733
+    // Charlie: 345,781 - (314,049 + 0) = 31,732
+    let env_at_ts = mock_env_at_timestamp(269201);
+    let res = execute(
+        deps.as_mut(),
+        env_at_ts.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_success(
+            Undelegation::new("alice", 31732, "uxyz")
+                .to_cosmos_msg(
+                    env_at_ts.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            1
+        )
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::reply_on_success(
+            Undelegation::new("bob", 31733, "uxyz")
+                .to_cosmos_msg(
+                    env_at_ts.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            2
+        )
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::reply_on_success(
+            Undelegation::new("charlie", 31732, "uxyz")
+                .to_cosmos_msg(
+                    env_at_ts.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            3
+        )
+    );
+    assert_eq!(
+        res.messages[3],
+        SubMsg {
+            id: 0,
+            msg: CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "steak_token".to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::new(92876)
+                })
+                .unwrap(),
+                funds: vec![]
+            }),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        }
+    );
+
+    // A new pending batch should have been created
+    let pending_batch = state
+        .pending_batches
+        .load(deps.as_ref().storage, "uxyz")
+        .unwrap();
+    assert_eq!(
+        pending_batch,
+        PendingBatch {
+            id: 2,
+            usteak_to_burn: Uint128::zero(),
+            est_unbond_start_time: 528401, // 269,201 + 259,200
+            rollovers: 0,
+        }
+    );
+
+    // Previous batch should have been updated
+    let previous_batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap();
+    assert_eq!(
+        previous_batch,
+        Batch {
+            id: 1,
+            reconciled: false,
+            total_shares: Uint128::new(92876),
+            amount_unclaimed: Uint128::new(95197),
+            est_unbond_end_time: 2083601, // 269,201 + 1,814,400
+            fee_rate: Some(Decimal::from_ratio(10_u128, 100_u128)),
+            exchange_rate: Some(Decimal::from_ratio(1037345u128, 1012043u128)),
+            submitter: Some(Addr::unchecked(MOCK_CONTRACT_ADDR)),
+            shortfalls: vec![],
+            backfilled: false,
+        }
+    );
+
+    // The staking module reports when each undelegation actually completes, e.g. after the chain
+    // shortened its unbonding time. The latest of them replaces the estimate
+    let undelegated = |id: u64, events: Vec<Event>| Reply {
+        id,
+        result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse { events, data: None }),
+    };
+    let unbond_event = |validator: &str, completion_time: &str| {
+        Event::new("unbond")
+            .add_attribute("validator", validator)
+            .add_attribute("amount", "31732uxyz")
+            .add_attribute("completion_time", completion_time)
+    };
+
+    // 1970-01-21T00:00:00Z = 20 days = 1,728,000
+    let res = reply(
+        deps.as_mut(),
+        env_at_ts.clone(),
+        undelegated(
+            1,
+            vec![unbond_event("alice", "1970-01-21T00:00:00.123456Z")],
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/unbond_completion_recorded")
+            .add_attribute("id", "1")
+            .add_attribute("validator", "alice")
+            .add_attribute("completion_time", "1728000")
+            .add_attribute("est_unbond_end_time", "1728000")]
+    );
+
+    reply(
+        deps.as_mut(),
+        env_at_ts.clone(),
+        undelegated(2, vec![unbond_event("bob", "1970-01-21T02:00:00+01:00")]),
+    )
+    .unwrap();
+    reply(deps.as_mut(), env_at_ts, undelegated(3, vec![])).unwrap();
+
+    let previous_batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap();
+    assert_eq!(previous_batch.est_unbond_end_time, 1731600);
+
+    let completion_times = state
+        .unbond_completion_times
+        .prefix(1)
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(
+        completion_times,
+        vec![("alice".to_string(), 1728000), ("bob".to_string(), 1731600)]
+    );
+
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(
+        res.last_batch_submitter,
+        Some(OperationRecord {
+            address: MOCK_CONTRACT_ADDR.to_string(),
+            height: 12345,
+        })
+    );
+}
+#[test]
+fn submitting_batch_with_exclusions() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782, "uxyz"),
+        Delegation::new("bob", 345782, "uxyz"),
+        Delegation::new("charlie", 345781, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+
+    state
+        .save_unbond_request(
+            deps.as_mut().storage,
+            &UnbondRequest {
+                id: 1,
+                user: Addr::unchecked("user_1"),
+                shares: Uint128::new(92876),
+            },
+        )
+        .unwrap();
+    state
+        .pending_batches
+        .save(
+            deps.as_mut().storage,
+            "uxyz",
+            &PendingBatch {
+                id: 1,
+                usteak_to_burn: Uint128::new(92876),
+                est_unbond_start_time: 269200,
+                rollovers: 0,
+            },
+        )
+        .unwrap();
+
+    // No exclusions are allowed until the owner sets a bound
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info("keeper", &[]),
+        ExecuteMsg::SubmitBatch {
+            exclude: vec!["bob".to_string()],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("can't exclude more than 0 validators from a batch")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("keeper", &[]),
+        ExecuteMsg::SetMaxBatchExclusions { max_exclusions: 1 },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMaxBatchExclusions { max_exclusions: 1 },
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info("keeper", &[]),
+        ExecuteMsg::SubmitBatch {
+            exclude: vec!["bob".to_string(), "charlie".to_string()],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("can't exclude more than 1 validators from a batch")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info("keeper", &[]),
+        ExecuteMsg::SubmitBatch {
+            exclude: vec!["dave".to_string()],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("validator dave is not whitelisted")
+    );
+
+    // native_token to unbond: 1,037,345 * 92,876 / 1,012,043 = 95,197
+    //
+    // Bob is left out, so the rest is spread over alice and charlie
+    // Target: (345,782 + 345,781 - 95,197) / 2 = 298,183
+    // Alice:   345,782 - 298,183 = 47,599
+    // Charlie: 345,781 - 298,183 = 47,598
+    let env = mock_env_at_timestamp(269201);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("keeper", &[]),
+        ExecuteMsg::SubmitBatch {
+            exclude: vec!["bob".to_string()],
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0].msg,
+        Undelegation::new("alice", 47599, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    assert_eq!(
+        res.messages[1].msg,
+        Undelegation::new("charlie", 47598, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    assert!(res.events[0]
+        .attributes
+        .contains(&attr("validators_excluded", "bob")));
+}
+
+#[test]
+fn counting_activity() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    let res: Counters = query_helper(deps.as_ref(), QueryMsg::Counters {});
+    assert_eq!(res, Counters::default());
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(12345, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap();
+
+    let harvest_env = mock_env();
+    execute(
+        deps.as_mut(),
+        harvest_env.clone(),
+        mock_info(&harvest_env.contract.address.to_string(), &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+
+    state
+        .pending_batches
+        .save(
+            deps.as_mut().storage,
+            "uxyz",
+            &PendingBatch {
+                id: 1,
+                usteak_to_burn: Uint128::new(92876),
+                est_unbond_start_time: 269200,
+                rollovers: 0,
+            },
+        )
+        .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+
+    // 92,876 usteak at 1.025 native_token per usteak: 95,197 native_token unbonded
+    let res: Counters = query_helper(deps.as_ref(), QueryMsg::Counters {});
+    assert_eq!(
+        res,
+        Counters {
+            total_bonded: Uint128::new(12345),
+            total_unbonded: Uint128::new(95197),
+            total_harvests: 1,
+        }
+    );
+
+    // Migrating carries them over untouched, and refuses to if they no longer read
+    migrate(deps.as_mut(), mock_env(), Empty {}).unwrap();
+    assert_eq!(state.load_counters(deps.as_ref().storage).unwrap(), res);
+
+    deps.as_mut().storage.set(b"counters", b"[1,2,3]");
+    let err = migrate(deps.as_mut(), mock_env(), Empty {}).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("stored counters don't match this version's schema"));
+}
+
+#[test]
+fn holding_over_unbond_below_minimum() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // native_token bonded: 1,037,345
+    // usteak supply: 1,012,043
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782, "uxyz"),
+        Delegation::new("bob", 345782, "uxyz"),
+        Delegation::new("charlie", 345781, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+
+    // 23,456 usteak are worth 24,042 native_token, and 69,420 usteak 71,155
+    for (user, shares, min_native_out) in
+        [("user_1", 23456u128, 24000u128), ("user_3", 69420, 72000)]
+    {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(12345),
+            mock_info("steak_token", &[]),
+            ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: user.to_string(),
+                amount: Uint128::new(shares),
+                msg: to_binary(&ReceiveMsg::QueueUnbond {
+                    receiver: None,
+                    min_native_out: Some(Uint128::new(min_native_out)),
+                })
+                .unwrap(),
+            }),
+        )
+        .unwrap();
+    }
+
+    // User 3's request would fall short of their minimum, so it moves on to batch 2
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    assert!(res.events.contains(
+        &Event::new("steakhub/unbond_held_over")
+            .add_attribute("id", "1")
+            .add_attribute("user", "user_3")
+            .add_attribute("shares", "69420")
+            .add_attribute("native_out", "71155")
+            .add_attribute("min_native_out", "72000")
+    ));
+
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1))
+        .unwrap();
+    assert_eq!(batch.total_shares, Uint128::new(23456));
+    assert_eq!(batch.amount_unclaimed, Uint128::new(24042));
+
+    let pending_batch = state
+        .pending_batches
+        .load(deps.as_ref().storage, "uxyz")
+        .unwrap();
+    assert_eq!(pending_batch.id, 2);
+    assert_eq!(pending_batch.usteak_to_burn, Uint128::new(69420));
+
+    let user_3 = Addr::unchecked("user_3");
+    assert_eq!(
+        state
+            .load_unbond_request(deps.as_ref().storage, 1, &user_3)
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        state
+            .load_unbond_request(deps.as_ref().storage, 2, &user_3)
+            .unwrap(),
+        Some(UnbondRequest {
+            id: 2,
+            user: user_3.clone(),
+            shares: Uint128::new(69420),
+        })
+    );
+    assert_eq!(
+        state
+            .unbond_min_outs
+            .load(deps.as_ref().storage, (2, &user_3))
+            .unwrap(),
+        Uint128::new(72000)
+    );
+    assert!(!state
+        .unbond_min_outs
+        .has(deps.as_ref().storage, (1, &Addr::unchecked("user_1"))));
+
+    // With nothing else in the batch, the batch as a whole waits for the next epoch
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(528401),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "steakhub/hold_over_batch")]
+    );
+
+    let pending_batch = state
+        .pending_batches
+        .load(deps.as_ref().storage, "uxyz")
+        .unwrap();
+    assert_eq!(pending_batch.id, 2);
+    assert_eq!(pending_batch.est_unbond_start_time, 528401 + 259200);
+}
+
+#[test]
+fn recording_supply_checkpoints() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782, "uxyz"),
+        Delegation::new("bob", 345782, "uxyz"),
+        Delegation::new("charlie", 345781, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+    state
+        .pending_batches
+        .save(
+            deps.as_mut().storage,
+            "uxyz",
+            &PendingBatch {
+                id: 1,
+                usteak_to_burn: Uint128::new(92876),
+                est_unbond_start_time: 269200,
+                rollovers: 0,
+            },
+        )
+        .unwrap();
+
+    // Submitting the batch unbonds 95,197 native_token and burns 92,876 usteak, at height 12,345
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+
+    let checkpoint = SupplyCheckpoint {
+        id: 0,
+        height: 12345,
+        time: 269201,
+        source: "batch".to_string(),
+        usteak_supply: Uint128::new(919167),
+        native_bonded: Uint128::new(942148),
+        exchange_rate: Decimal::from_ratio(942148u128, 919167u128),
+    };
+    let res: Vec<SupplyCheckpoint> = query_helper(
+        deps.as_ref(),
+        QueryMsg::SupplyCheckpoints {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![checkpoint.clone()]);
+
+    let res: Option<SupplyCheckpoint> = query_helper(
+        deps.as_ref(),
+        QueryMsg::SupplyCheckpointAt { height: 12344 },
+    );
+    assert_eq!(res, None);
+    let res: Option<SupplyCheckpoint> = query_helper(
+        deps.as_ref(),
+        QueryMsg::SupplyCheckpointAt { height: 20000 },
+    );
+    assert_eq!(res, Some(checkpoint.clone()));
+
+    // Only the last `SUPPLY_CHECKPOINT_CAPACITY` are kept, one every 10 blocks from here on
+    for i in 1..=SUPPLY_CHECKPOINT_CAPACITY {
+        state
+            .record_supply_checkpoint(
+                deps.as_mut().storage,
+                SupplyCheckpoint {
+                    height: 20000 + i * 10,
+                    ..checkpoint.clone()
+                },
+            )
+            .unwrap();
+    }
+    assert!(!state.supply_checkpoints.has(deps.as_ref().storage, 0));
+
+    let res: Option<SupplyCheckpoint> = query_helper(
+        deps.as_ref(),
+        QueryMsg::SupplyCheckpointAt { height: 20000 },
+    );
+    assert_eq!(res, None);
+    let res: Option<SupplyCheckpoint> = query_helper(
+        deps.as_ref(),
+        QueryMsg::SupplyCheckpointAt { height: 25555 },
+    );
+    assert_eq!(res.map(|c| (c.id, c.height)), Some((555, 25550)));
+    let res: Option<SupplyCheckpoint> = query_helper(
+        deps.as_ref(),
+        QueryMsg::SupplyCheckpointAt { height: u64::MAX },
+    );
+    assert_eq!(res.map(|c| c.id), Some(SUPPLY_CHECKPOINT_CAPACITY));
+}
+
+#[test]
+fn submitting_batch_past_deadline() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782, "uxyz"),
+        Delegation::new("bob", 345782, "uxyz"),
+        Delegation::new("charlie", 345781, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+    let pending_batch = PendingBatch {
+        id: 1,
+        usteak_to_burn: Uint128::new(92876),
+        est_unbond_start_time: 269200,
+        rollovers: 0,
+    };
+    state
+        .pending_batches
+        .save(deps.as_mut().storage, "uxyz", &pending_batch)
+        .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetSubmitDeadlineBuffer {
+            submit_deadline_buffer: Some(3600),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetGuardian {
+            guardian: Some("guardian".to_string()),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.submit_deadline_buffer, Some(3600));
+
+    // Once the window has closed, keepers are turned away
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(272801),
+        mock_info("jake", &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not guardian")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(272801),
+        mock_info("guardian", &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events.last().unwrap(),
+        &Event::new("steakhub/submit_window_scheduled")
+            .add_attribute("id", "2")
+            .add_attribute("window_start", "532001") // 272,801 + 259,200
+            .add_attribute("window_end", "535601")
+    );
+
+    // Within the window, anyone can submit
+    state
+        .pending_batches
+        .save(deps.as_mut().storage, "uxyz", &pending_batch)
+        .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(272800),
+        mock_info("jake", &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+}
+#[test]
+fn unbonding_over_time() {
+    let mut deps = setup_test();
+    DelegationSetBuilder::new()
+        .split(&["alice", "bob", "charlie"], 1000000)
+        .apply(&mut deps.querier);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+
+    // The first batch opens for submission 3 days after instantiation, and finishes unbonding 21
+    // days after it is submitted
+    let mut scenario = Scenario::new(deps, 10000);
+    scenario.run(vec![
+        Step::Advance(100),
+        Step::Execute(
+            "steak_token",
+            ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: "user_1".to_string(),
+                amount: Uint128::new(1000),
+                msg: to_binary(&ReceiveMsg::QueueUnbond {
+                    receiver: None,
+                    min_native_out: None,
+                })
+                .unwrap(),
+            }),
+        ),
+        Step::Advance(259200),
+        Step::Execute("worker", ExecuteMsg::SubmitBatch { exclude: vec![] }),
+    ]);
+
+    let res: PendingBatch = scenario.query(QueryMsg::PendingBatch {});
+    assert_eq!(res.id, 2);
+    let batch: Batch = scenario.query(QueryMsg::PreviousBatch(1));
+    assert!(!batch.reconciled);
+    assert_eq!(batch.total_shares, Uint128::new(1000));
+    assert_eq!(batch.est_unbond_end_time, 269300 + 1814400);
+    assert_eq!(batch.submitter, Some(Addr::unchecked("worker")));
+
+    // Too early for the unbonded Native Token to have arrived
+    scenario.advance(1814399);
+    scenario
+        .execute("worker", ExecuteMsg::Reconcile {})
+        .unwrap();
+    let batch: Batch = scenario.query(QueryMsg::PreviousBatch(1));
+    assert!(!batch.reconciled);
+
+    scenario
+        .deps
+        .querier
+        .set_bank_balances(&[Coin::new(batch.amount_unclaimed.u128(), "uxyz")]);
+    scenario.run(vec![
+        Step::Advance(2),
+        Step::Execute("worker", ExecuteMsg::Reconcile {}),
+    ]);
+    let batch: Batch = scenario.query(QueryMsg::PreviousBatch(1));
+    assert!(batch.reconciled);
+    assert!(batch.shortfalls.is_empty());
+}
+
+#[test]
+fn estimating_unbond_eta() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let unbond_eta = |deps: &OwnedDeps<_, _, _>, timestamp: u64| -> UnbondEtaResponse {
+        from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env_at_timestamp(timestamp),
+                QueryMsg::UnbondEta {
+                    user: "user_1".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(23456),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    // Without the chain's unbonding time, the configured period is used
+    assert_eq!(
+        unbond_eta(&deps, 12345),
+        UnbondEtaResponse {
+            unbond_time: 1814400,
+            batches: vec![UnbondEtaResponseItem {
+                id: 1,
+                shares: Uint128::new(23456),
+                submitted: false,
+                est_unbond_end_time: 269200 + 1814400,
+            }],
+        }
+    );
+
+    // The chain takes a day longer; an overdue batch starts no earlier than now
+    deps.querier.unbonding_time = Some(1900800);
+    let res = unbond_eta(&deps, 12345);
+    assert_eq!(res.unbond_time, 1900800);
+    assert_eq!(res.batches[0].est_unbond_end_time, 269200 + 1900800);
+    let res = unbond_eta(&deps, 300000);
+    assert_eq!(res.batches[0].est_unbond_end_time, 300000 + 1900800);
+
+    // Submitting the batch caches the unbonding time, so it no longer needs to be queried
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782, "uxyz"),
+        Delegation::new("bob", 345782, "uxyz"),
+        Delegation::new("charlie", 345781, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info("jake", &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    let chain_unbonding_time = state
+        .chain_unbonding_time
+        .load(deps.as_ref().storage)
+        .unwrap();
+    assert_eq!(chain_unbonding_time, 1900800);
+    deps.querier.unbonding_time = None;
+
+    // The submitted batch was estimated with the configured period and is late by the difference
+    assert_eq!(
+        unbond_eta(&deps, 269201),
+        UnbondEtaResponse {
+            unbond_time: 1900800,
+            batches: vec![UnbondEtaResponseItem {
+                id: 1,
+                shares: Uint128::new(23456),
+                submitted: true,
+                est_unbond_end_time: 269201 + 1900800,
+            }],
+        }
+    );
+}
+
+#[test]
+fn rolling_over_small_batch() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782, "uxyz"),
+        Delegation::new("bob", 345782, "uxyz"),
+        Delegation::new("charlie", 345781, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetBatchRollover {
+            batch_rollover: Some(BatchRollover {
+                min_shares: Uint128::new(1000),
+                max_rollovers: 0,
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("batch rollover needs a positive minimum and number of rollovers")
+    );
+
+    let batch_rollover = BatchRollover {
+        min_shares: Uint128::new(1000),
+        max_rollovers: 2,
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetBatchRollover {
+            batch_rollover: Some(batch_rollover),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.batch_rollover, Some(batch_rollover));
+
+    state
+        .pending_batches
+        .save(
+            deps.as_mut().storage,
+            "uxyz",
+            &PendingBatch {
+                id: 1,
+                usteak_to_burn: Uint128::new(500),
+                est_unbond_start_time: 269200,
+                rollovers: 0,
+            },
+        )
+        .unwrap();
+
+    // Too small: the batch stays open for another epoch, keeping its ID and requests
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(
+        res.events,
+        vec![
+            Event::new("steakhub/batch_rolled_over")
+                .add_attribute("id", "1")
+                .add_attribute("usteak_to_burn", "500")
+                .add_attribute("min_shares", "1000")
+                .add_attribute("rollovers", "1"),
+            Event::new("steakhub/submit_window_scheduled")
+                .add_attribute("id", "1")
+                .add_attribute("window_start", "528401"),
+        ]
+    );
+    let pending_batch: PendingBatch = query_helper(deps.as_ref(), QueryMsg::PendingBatch {});
+    assert_eq!(
+        pending_batch,
+        PendingBatch {
+            id: 1,
+            usteak_to_burn: Uint128::new(500),
+            est_unbond_start_time: 528401, // 269,201 + 259,200
+            rollovers: 1,
+        }
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(528401),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    let pending_batch = state
+        .pending_batches
+        .load(deps.as_ref().storage, "uxyz")
+        .unwrap();
+    assert_eq!(pending_batch.rollovers, 2);
+    assert_eq!(pending_batch.est_unbond_start_time, 787601);
+
+    // Out of rollovers, the batch is submitted however small it is
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(787601),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    assert_eq!(res.attributes, vec![attr("action", "steakhub/unbond")]);
+    let previous_batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap();
+    assert_eq!(previous_batch.total_shares, Uint128::new(500));
+    let pending_batch = state
+        .pending_batches
+        .load(deps.as_ref().storage, "uxyz")
+        .unwrap();
+    assert_eq!(
+        pending_batch,
+        PendingBatch {
+            id: 2,
+            usteak_to_burn: Uint128::zero(),
+            est_unbond_start_time: 1046801,
+            rollovers: 0,
+        }
+    );
+}
+
+#[test]
+fn unbonding_with_priority() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782, "uxyz"),
+        Delegation::new("bob", 345782, "uxyz"),
+        Delegation::new("charlie", 345781, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1012043);
+
+    for msg in [
+        ExecuteMsg::SetBatchRollover {
+            batch_rollover: Some(BatchRollover {
+                min_shares: Uint128::new(1000),
+                max_rollovers: 2,
+            }),
+        },
+        ExecuteMsg::SetMinUnbondShares {
+            min_unbond_shares: Uint128::new(500),
+        },
+    ] {
+        execute(deps.as_mut(), mock_env(), mock_info("larry", &[]), msg).unwrap();
+    }
+
+    let set_priority_unbonders = ExecuteMsg::SetPriorityUnbonders {
+        addresses: vec!["insurance".to_string(), "insurance".to_string()],
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        set_priority_unbonders.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_priority_unbonders,
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steak/set_priority_unbonders").add_attribute("addresses", "insurance")]
+    );
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.priority_unbonders, vec!["insurance".to_string()]);
+
+    let queue_unbond = |sender: &str, receiver: Option<String>| {
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::new(100),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver,
+                min_native_out: None,
+            })
+            .unwrap(),
+        })
+    };
+
+    // Queued for a priority unbonder by someone else, a request is held to the usual minimum
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("steak_token", &[]),
+        queue_unbond("user_1", Some("insurance".to_string())),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unbond amount 100 is below the minimum of 500")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("steak_token", &[]),
+        queue_unbond("insurance", None),
+    )
+    .unwrap();
+    assert!(res.events[0].attributes.contains(&attr("priority", "true")));
+
+    // The batch is below the rollover minimum, yet submitted right away
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch { exclude: vec![] },
+    )
+    .unwrap();
+    assert_eq!(res.attributes, vec![attr("action", "steakhub/unbond")]);
+    let previous_batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap();
+    assert_eq!(previous_batch.total_shares, Uint128::new(100));
+    let pending_batch = state
+        .pending_batches
+        .load(deps.as_ref().storage, "uxyz")
+        .unwrap();
+    assert_eq!(pending_batch.id, 2);
+    assert_eq!(pending_batch.rollovers, 0);
+}
+
+#[test]
+fn reconciling() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let previous_batches = vec![
+        BatchBuilder::new(1)
+            .reconciled()
+            .shares(92876)
+            .unclaimed(95197) // 1.025 Native Token per Steak
+            .unbond_end_time(10000)
+            .build(),
+        BatchBuilder::new(2)
+            .shares(1345)
+            .unclaimed(1385) // 1.030 Native Token per Steak
+            .unbond_end_time(20000)
+            .build(),
+        BatchBuilder::new(3)
+            .shares(1456)
+            .unclaimed(1506) // 1.035 Native Token per Steak
+            .unbond_end_time(30000)
+            .build(),
+        BatchBuilder::new(4)
+            .shares(1567)
+            .unclaimed(1629) // 1.040 Native Token per Steak
+            .unbond_end_time(40000) // not yet finished unbonding, ignored
+            .build(),
+    ];
+
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(
+                deps.as_mut().storage,
+                ("uxyz", previous_batch.id),
+                previous_batch,
+            )
+            .unwrap();
+    }
+
+    state
+        .unlocked_coins
+        .save(
+            deps.as_mut().storage,
+            &vec![
+                Coin::new(10000, "uxyz"),
+                Coin::new(234, "ukrw"),
+                Coin::new(345, "uusd"),
+                Coin::new(
+                    69420,
+                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
+                ),
+            ],
+        )
+        .unwrap();
+    state
+        .reinvest_carry
+        .save(deps.as_mut().storage, &Uint128::new(100))
+        .unwrap();
+
+    deps.querier.set_bank_balances(&[
+        Coin::new(95197 + 12445, "uxyz"),
+        Coin::new(234, "ukrw"),
+        Coin::new(345, "uusd"),
+        Coin::new(
+            69420,
+            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
+        ),
+    ]);
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(35000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+
+    // Expected reconciled, not yet withdrawn: batch 1 = 95197
+    // Expected received: batch 2 + batch 3 = 1385 + 1506 = 2891
+    // Expected unlocked: 10000
+    // Expected carried over by reinvest: 100
+    // Expected: 108188
+    // Actual: 107642
+    // Shortfall: 108188 - 107642 = 546
+    //
+    // native_token per batch: 546 / 2 = 273
+    // remainder: 0
+    // batch 2: 1385 - 273 = 1112
+    // batch 3: 1506 - 273 = 1233
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 2u64))
+        .unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            id: 2,
+            reconciled: true,
+            total_shares: Uint128::new(1345),
+            amount_unclaimed: Uint128::new(1112), // 1385 - 273
+            est_unbond_end_time: 20000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![BatchShortfall {
+                time: 35000,
+                amount: Uint128::new(273),
+            }],
+            backfilled: false,
+        }
+    );
+
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 3u64))
+        .unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            id: 3,
+            reconciled: true,
+            total_shares: Uint128::new(1456),
+            amount_unclaimed: Uint128::new(1233), // 1506 - 273
+            est_unbond_end_time: 30000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![BatchShortfall {
+                time: 35000,
+                amount: Uint128::new(273),
+            }],
+            backfilled: false,
+        }
+    );
+
+    // Batches 1 and 4 should not have changed
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap();
+    assert_eq!(batch, previous_batches[0]);
+
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 4u64))
+        .unwrap();
+    assert_eq!(batch, previous_batches[3]);
+}
+#[test]
+fn reconciling_by_unbonding_entries() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let previous_batches = vec![
+        BatchBuilder::new(2)
+            .shares(1345)
+            .unclaimed(1385)
+            .unbond_end_time(20000),
+        BatchBuilder::new(3)
+            .shares(1456)
+            .unclaimed(1506)
+            .unbond_end_time(30000),
+    ]
+    .into_iter()
+    .map(|batch| batch.save(deps.as_mut().storage).unwrap())
+    .collect::<Vec<_>>();
+    for (id, validator, completion_time) in
+        [(2, "alice", 20000), (3, "alice", 30000), (3, "bob", 29000)]
+    {
+        state
+            .unbond_completion_times
+            .save(deps.as_mut().storage, (id, validator), &completion_time)
+            .unwrap();
+    }
+
+    // Batch 3 is past its estimated end, but the chain still holds one of its undelegations. Only
+    // batch 2's Native Token has been received
+    deps.querier.set_bank_balances(&[Coin::new(1385, "uxyz")]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetReconcileMode {
+            mode: ReconcileMode::UnbondingEntries,
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.reconcile_mode, ReconcileMode::UnbondingEntries);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(35000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("chain doesn't allow querying unbonding delegations")
+    );
+
+    deps.querier.unbonding_entries = Some(vec![("alice".to_string(), 30000)]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(35000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/reconciled")
+            .add_attribute("sender", "worker")
+            .add_attribute("ids", "2")
+            .add_attribute("native_deducted", "0")]
+    );
+
+    // Batch 2 is reconciled in full, rather than made to cover batch 3's Native Token
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 2u64))
+        .unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            reconciled: true,
+            ..previous_batches[0].clone()
+        }
+    );
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 3u64))
+        .unwrap();
+    assert_eq!(batch, previous_batches[1]);
+}
+#[test]
+fn classifying_parked_funds() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 1000, "uxyz"),
+        Delegation::new("bob", 500, "uxyz"),
+        Delegation::new("charlie", 1000, "uxyz"),
+    ]);
+
+    // Someone sends Native Token to the hub directly. Reconciling parks it rather than letting it
+    // cover a future shortfall
+    deps.querier.set_bank_balances(&[Coin::new(1000, "uxyz")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/reconciled")
+            .add_attribute("sender", "worker")
+            .add_attribute("ids", "")
+            .add_attribute("native_deducted", "0")
+            .add_attribute("native_parked", "1000")]
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/reconciled")
+            .add_attribute("sender", "worker")
+            .add_attribute("ids", "")
+            .add_attribute("native_deducted", "0")]
+    );
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(res.donations, Uint128::new(1000));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::ClassifyFunds {
+            amount: Uint128::new(100),
+            classification: FundsClassification::AsRewards,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ClassifyFunds {
+            amount: Uint128::new(1001),
+            classification: FundsClassification::AsRewards,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(
+            "1000 of Native Token is awaiting classification, can't classify 1001"
+        )
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ClassifyFunds {
+            amount: Uint128::new(300),
+            classification: FundsClassification::Refund {
+                recipient: "jake".to_string(),
+            },
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "jake".to_string(),
+            amount: vec![Coin::new(300, "uxyz")],
+        })]
+    );
+
+    // Rewards are picked up by the next reinvest
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ClassifyFunds {
+            amount: Uint128::new(200),
+            classification: FundsClassification::AsRewards,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        state.reinvest_carry.load(deps.as_ref().storage).unwrap(),
+        Uint128::new(200)
+    );
+
+    // A donation is delegated right away, to the validator with the smallest delegation
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ClassifyFunds {
+            amount: Uint128::new(500),
+            classification: FundsClassification::AsDonation,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0].msg,
+        Delegation::new("bob", 500, "uxyz")
+            .to_cosmos_msg(MOCK_CONTRACT_ADDR.to_string(), &StakingProtoUrls::default())
+            .unwrap()
+    );
+
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(res.donations, Uint128::zero());
+}
+
+#[test]
+fn parking_funds_of_early_unbondings() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // Batch 1 finished unbonding ahead of its estimated end time, as the staking module reported;
+    // its funds aren't unexpected. Batch 2 is still unbonding and owed nothing yet, so a transfer
+    // smaller than it is parked all the same
+    for (id, completion_time) in [(1u64, 9000u64), (2, 20000)] {
+        let batch = BatchBuilder::new(id)
+            .shares(1000)
+            .unclaimed(1025)
+            .unbond_end_time(20000)
+            .build();
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, ("uxyz", id), &batch)
+            .unwrap();
+        state
+            .unbond_completion_times
+            .save(deps.as_mut().storage, (id, "alice"), &completion_time)
+            .unwrap();
+    }
+
+    deps.querier.set_bank_balances(&[Coin::new(1500, "uxyz")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/reconciled")
+            .add_attribute("sender", "worker")
+            .add_attribute("ids", "")
+            .add_attribute("native_deducted", "0")
+            .add_attribute("native_parked", "475")]
+    );
+    assert_eq!(
+        state.donations.load(deps.as_ref().storage).unwrap(),
+        Uint128::new(475)
+    );
+}
+
+#[test]
+fn backfilling_legacy_batches() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // A batch stored before fee, exchange rate and submitter snapshots existed
+    #[derive(Serialize, Deserialize)]
+    struct LegacyBatch {
+        id: u64,
+        reconciled: bool,
+        total_shares: Uint128,
+        amount_unclaimed: Uint128,
+        est_unbond_end_time: u64,
+    }
+    Map::<u64, LegacyBatch>::new(BATCH_KEY_V101)
+        .save(
+            deps.as_mut().storage,
+            1,
+            &LegacyBatch {
+                id: 1,
+                reconciled: false,
+                total_shares: Uint128::new(92876),
+                amount_unclaimed: Uint128::new(95197),
+                est_unbond_end_time: 2083601,
+            },
+        )
+        .unwrap();
+    let batch = Batch {
+        id: 2,
+        reconciled: false,
+        total_shares: Uint128::new(1345),
+        amount_unclaimed: Uint128::new(1385),
+        est_unbond_end_time: 2342801,
+        fee_rate: Some(Decimal::percent(5)),
+        exchange_rate: Some(Decimal::from_ratio(1385u128, 1345u128)),
+        submitter: Some(Addr::unchecked("worker")),
+        shortfalls: vec![],
+        backfilled: false,
+    };
+    state
+        .previous_batches
+        .save(deps.as_mut().storage, ("uxyz", 2), &batch)
+        .unwrap();
+
+    // The legacy batch still loads, with what it lacks left out
+    let legacy = Map::<u64, Batch>::new(BATCH_KEY_V101)
+        .load(deps.as_ref().storage, 1)
+        .unwrap();
+    assert_eq!(legacy.fee_rate, None);
+    assert_eq!(legacy.submitter, None);
+    assert!(!legacy.backfilled);
+
+    let res = migrate(deps.as_mut(), mock_env(), Empty {}).unwrap();
+    assert!(res.attributes.contains(&attr("batches_rekeyed", "1")));
+    assert!(res.attributes.contains(&attr("batches_backfilled", "1")));
+
+    assert_eq!(
+        state
+            .previous_batches
+            .load(deps.as_ref().storage, ("uxyz", 1))
+            .unwrap(),
+        Batch {
+            id: 1,
+            reconciled: false,
+            total_shares: Uint128::new(92876),
+            amount_unclaimed: Uint128::new(95197),
+            est_unbond_end_time: 2083601,
+            fee_rate: Some(Decimal::from_ratio(10u128, 100u128)),
+            exchange_rate: Some(Decimal::from_ratio(95197u128, 92876u128)),
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: true,
+        }
+    );
+    assert_eq!(
+        state
+            .previous_batches
+            .load(deps.as_ref().storage, ("uxyz", 2))
+            .unwrap(),
+        batch
+    );
+
+    // Migrating again leaves the backfilled batch alone
+    let res = migrate(deps.as_mut(), mock_env(), Empty {}).unwrap();
+    assert!(res.attributes.contains(&attr("batches_rekeyed", "0")));
+    assert!(res.attributes.contains(&attr("batches_backfilled", "0")));
+}
+
+#[test]
+fn keying_batches_by_denom() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // The pending batch as stored before batches were keyed by denom
+    let pending_batch = PendingBatch {
+        id: 3,
+        usteak_to_burn: Uint128::new(2500),
+        est_unbond_start_time: 269200,
+        rollovers: 0,
+    };
+    state.pending_batches.remove(deps.as_mut().storage, "uxyz");
+    Item::<PendingBatch>::new(PENDING_BATCH_KEY_V101)
+        .save(deps.as_mut().storage, &pending_batch)
+        .unwrap();
+    Map::<u64, Batch>::new(BATCH_KEY_V101)
+        .save(
+            deps.as_mut().storage,
+            2,
+            &BatchBuilder::new(2).shares(1000).unclaimed(1010).build(),
+        )
+        .unwrap();
+
+    // A batch of another denom, which the hub's queries leave out
+    state
+        .previous_batches
+        .save(
+            deps.as_mut().storage,
+            ("uabc", 1),
+            &BatchBuilder::new(1).shares(500).unclaimed(505).build(),
+        )
+        .unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), Empty {}).unwrap();
+    assert!(res.attributes.contains(&attr("batches_rekeyed", "1")));
+
+    let res: PendingBatch = query_helper(deps.as_ref(), QueryMsg::PendingBatch {});
+    assert_eq!(res, pending_batch);
+    assert_eq!(
+        Item::<PendingBatch>::new(PENDING_BATCH_KEY_V101)
+            .may_load(deps.as_ref().storage)
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        Map::<u64, Batch>::new(BATCH_KEY_V101)
+            .may_load(deps.as_ref().storage, 2)
+            .unwrap(),
+        None
+    );
+
+    let res: Vec<Batch> = query_helper(
+        deps.as_ref(),
+        QueryMsg::PreviousBatches {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res.iter().map(|batch| batch.id).collect::<Vec<_>>(),
+        vec![2]
+    );
+    let err = query(deps.as_ref(), mock_env(), QueryMsg::PreviousBatch(1)).unwrap_err();
+    assert_eq!(err, StdError::not_found("pfc_steak_api::hub::Batch"));
+}
+
+#[test]
+fn ticking() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // Nothing is due yet
+    let res = sudo(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        SudoMsg::Tick {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/tick").add_attribute("actions", "")]
+    );
+
+    // A batch finished unbonding, and the pending batch has requests and is due for submission
+    state
+        .previous_batches
+        .save(
+            deps.as_mut().storage,
+            ("uxyz", 1u64),
+            &Batch {
+                id: 1,
+                reconciled: false,
+                total_shares: Uint128::new(1000),
+                amount_unclaimed: Uint128::new(1000),
+                est_unbond_end_time: 200000,
+                fee_rate: None,
+                exchange_rate: None,
+                submitter: None,
+                shortfalls: vec![],
+                backfilled: false,
+            },
+        )
+        .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("steak_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(23456),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    // Mining is enabled, so harvests are left to the proofs
+    let res = sudo(
+        deps.as_mut(),
+        mock_env_at_timestamp(269200),
+        SudoMsg::Tick {},
+    )
+    .unwrap();
+    let msgs: Vec<(CosmosMsg, ReplyOn)> = res
+        .messages
+        .iter()
+        .map(|submsg| (submsg.msg.clone(), submsg.reply_on.clone()))
+        .collect();
+    let self_msg = |msg: ExecuteMsg| {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&msg).unwrap(),
+            funds: vec![],
+        })
+    };
+    assert_eq!(
+        msgs,
+        vec![
+            (self_msg(ExecuteMsg::Reconcile {}), ReplyOn::Always),
+            (
+                self_msg(ExecuteMsg::SubmitBatch { exclude: vec![] }),
+                ReplyOn::Always
+            ),
+        ]
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/tick").add_attribute("actions", "reconcile,submit_batch")]
+    );
+
+    // With mining disabled, the tick harvests once per epoch period
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMiningParams {
+            difficulty: None,
+            entropy: None,
+            enabled: Some(false),
+            proof_cooldown_blocks: None,
+            power_normalization: None,
+        },
+    )
+    .unwrap();
+    state
+        .last_harvest_time
+        .save(deps.as_mut().storage, &10000)
+        .unwrap();
+
+    let res = sudo(
+        deps.as_mut(),
+        mock_env_at_timestamp(269199),
+        SudoMsg::Tick {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/tick").add_attribute("actions", "reconcile")]
+    );
+
+    let res = sudo(
+        deps.as_mut(),
+        mock_env_at_timestamp(269200),
+        SudoMsg::Tick {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(res.messages[2].msg, self_msg(ExecuteMsg::Harvest {}));
+
+    // A failed action is recorded, without failing the tick
+    let res = reply(
+        deps.as_mut(),
+        mock_env_at_timestamp(269200),
+        Reply {
+            id: res.messages[2].id,
+            result: cosmwasm_std::SubMsgResult::Err("no rewards".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/tick_failed")
+            .add_attribute("tick_action", "harvest")
+            .add_attribute("error", "no rewards")]
+    );
+}
+
+#[test]
+fn withdrawing_unbonded() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // We simulate a most general case:
+    // - batches 1 and 2 have finished unbonding and have been reconciled
+    // - batch 3 has finished unbonding, but has not been reconciled
+    // - batch 4 has been reconciled, but has not finished unbonding
+    let unbond_requests = vec![
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(23456),
+        },
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_3"),
+            shares: Uint128::new(69420),
+        },
+        UnbondRequest {
+            id: 2,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(34567),
+        },
+        UnbondRequest {
+            id: 3,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(45678),
+        },
+        UnbondRequest {
+            id: 4,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(56789),
+        },
+    ];
+
+    // These requests were written before the compact layout, and move over as they are withdrawn
+    for unbond_request in &unbond_requests {
+        state
+            .legacy_unbond_requests
+            .save(
+                deps.as_mut().storage,
+                (
+                    unbond_request.id,
+                    &Addr::unchecked(unbond_request.user.clone()),
+                ),
+                unbond_request,
+            )
+            .unwrap();
+    }
+
+    let previous_batches = vec![
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(92876),
+            amount_unclaimed: Uint128::new(95197), // 1.025 Native Token per Steak
+            est_unbond_end_time: 10000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: false,
+        },
+        Batch {
+            id: 2,
+            reconciled: true,
+            total_shares: Uint128::new(34567),
+            amount_unclaimed: Uint128::new(35604), // 1.030 Native Token per Steak
+            est_unbond_end_time: 20000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: false,
+        },
+        Batch {
+            id: 3,
+            reconciled: false, // finished unbonding, but not reconciled; reconciled on withdrawal
+            total_shares: Uint128::new(45678),
+            amount_unclaimed: Uint128::new(47276), // 1.035 Native Token per Steak
+            est_unbond_end_time: 20000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: false,
+        },
+        Batch {
+            id: 4,
+            reconciled: true,
+            total_shares: Uint128::new(56789),
+            amount_unclaimed: Uint128::new(59060), // 1.040 Native Token per Steak
+            est_unbond_end_time: 30000, // reconciled, but not yet finished unbonding; ignored
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: false,
+        },
+    ];
+
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(
+                deps.as_mut().storage,
+                ("uxyz", previous_batch.id),
+                previous_batch,
+            )
+            .unwrap();
+    }
+
+    state
+        .pending_batches
+        .save(
+            deps.as_mut().storage,
+            "uxyz",
+            &PendingBatch {
+                id: 4,
+                usteak_to_burn: Uint128::new(56789),
+                est_unbond_start_time: 100000,
+                rollovers: 0,
+            },
+        )
+        .unwrap();
+
+    // The hub holds the native for every batch that is reconciled or has finished unbonding
+    deps.querier
+        .set_bank_balances(&[Coin::new(95197 + 35604 + 47276 + 59060, "uxyz")]);
+
+    // Attempt to withdraw before any batch has completed unbonding. Should error
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(5000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            amount: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, StdError::generic_err("withdrawable amount is zero"));
+
+    // Attempt to withdraw once batches 1, 2 and 3 have finished unbonding. Batch 3 is reconciled
+    // first; the hub's balance covers it, so nothing is deducted
+    //
+    // Withdrawable from batch 1: 95,197 * 23,456 / 92,876 = 24,042
+    // Withdrawable from batch 2: 35,604
+    // Withdrawable from batch 3: 47,276
+    // Total withdrawable: 24,042 + 35,604 + 47,276 = 106,922
+    //
+    // Batch 1 should be updated:
+    // Total shares: 92,876 - 23,456 = 69,420
+    // Unclaimed native_token: 95,197 - 24,042 = 71,155
+    //
+    // Batches 2 and 3 are completely withdrawn, should be purged from storage
+    let res: WithdrawableAmountResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env_at_timestamp(5000),
+            QueryMsg::WithdrawableAmount {
+                user: "user_1".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        WithdrawableAmountResponse {
+            amount: Uint128::zero(),
+            ids: vec![]
+        }
+    );
+
+    let res: WithdrawableAmountResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env_at_timestamp(25000),
+            QueryMsg::WithdrawableAmount {
+                user: "user_1".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        WithdrawableAmountResponse {
+            amount: Uint128::new(106922),
+            ids: vec![1, 2, 3]
+        }
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            amount: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.events[0].ty, "steakhub/reconciled");
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg {
+            id: 0,
+            msg: CosmosMsg::Bank(BankMsg::Send {
+                to_address: "user_1".to_string(),
+                amount: vec![Coin::new(106922, "uxyz")]
+            }),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        }
+    );
+    assert_eq!(
+        from_binary::<WithdrawReceipt>(&res.data.unwrap()).unwrap(),
+        WithdrawReceipt {
+            claim_id: 0,
+            receiver: "user_1".to_string(),
+            native_withdrawn: Uint128::new(106922),
+            batch_ids: vec![1, 2, 3],
+        }
+    );
+
+    // A retry finds nothing left to withdraw, and points at the claim that went through
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            amount: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(
+            "withdrawable amount is zero; claim 0 already withdrew 106922 at height 12345"
+        )
+    );
+    let res: Vec<RecentClaim> = query_helper(
+        deps.as_ref(),
+        QueryMsg::RecentClaims {
+            user: "user_1".to_string(),
+        },
+    );
+    assert_eq!(
+        res,
+        vec![RecentClaim {
+            id: 0,
+            receiver: "user_1".to_string(),
+            amount: Uint128::new(106922),
+            height: 12345,
+        }]
+    );
+
+    // Previous batches should have been updated
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(69420),
+            amount_unclaimed: Uint128::new(71155),
+            est_unbond_end_time: 10000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: false,
+        }
+    );
+
+    let err = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 2u64))
+        .unwrap_err();
+    assert_eq!(err, StdError::not_found("pfc_steak_api::hub::Batch"));
+
+    let err = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 3u64))
+        .unwrap_err();
+    assert_eq!(err, StdError::not_found("pfc_steak_api::hub::Batch"));
+
+    // User 1's unbond requests in batches 1 and 2 should have been deleted
+    for id in [1u64, 2] {
+        let ubr = state
+            .load_unbond_request(deps.as_ref().storage, id, &Addr::unchecked("user_1"))
+            .unwrap();
+        assert_eq!(ubr, None);
+    }
+    // User 3 attempt to withdraw; also specifying a receiver
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_3", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: Some("user_2".to_string()),
+            amount: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg {
+            id: 0,
+            msg: CosmosMsg::Bank(BankMsg::Send {
+                to_address: "user_2".to_string(),
+                amount: vec![Coin::new(71155, "uxyz")]
+            }),
+            gas_limit: None,
+            reply_on: ReplyOn::Never
+        }
+    );
+
+    // Batch 1 and user 2's unbonding request should have been purged from storage
+    let err = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap_err();
+    assert_eq!(err, StdError::not_found("pfc_steak_api::hub::Batch"));
+
+    let ubr = state
+        .load_unbond_request(deps.as_ref().storage, 1, &Addr::unchecked("user_3"))
+        .unwrap();
+    assert_eq!(ubr, None);
+    assert!(state
+        .legacy_unbond_requests
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .next()
+        .is_none());
+}
+
+#[test]
+fn withdrawing_unbonded_reconciles_own_batches_only() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // Batches 1 and 2 have both finished unbonding, but only batch 1 is user 1's
+    for (id, user) in [(1u64, "user_1"), (2, "user_2")] {
+        state
+            .save_unbond_request(
+                deps.as_mut().storage,
+                &UnbondRequest {
+                    id,
+                    user: Addr::unchecked(user),
+                    shares: Uint128::new(10000),
+                },
+            )
+            .unwrap();
+        state
+            .previous_batches
+            .save(
+                deps.as_mut().storage,
+                ("uxyz", id),
+                &Batch {
+                    id,
+                    reconciled: false,
+                    total_shares: Uint128::new(10000),
+                    amount_unclaimed: Uint128::new(10000),
+                    est_unbond_end_time: 20000,
+                    fee_rate: None,
+                    exchange_rate: None,
+                    submitter: None,
+                    shortfalls: vec![],
+                    backfilled: false,
+                },
+            )
+            .unwrap();
+    }
+
+    // Batches saved as they were before the hub kept count of them
+    count_previous_batches(deps.as_mut().storage).unwrap();
+    let res: MetricsResponse = query_helper(deps.as_ref(), QueryMsg::Metrics {});
+    assert_eq!(res.num_previous_batches, 2);
+    assert_eq!(res.native_unclaimed, Uint128::new(20000));
+
+    // The hub is 200 short of what both batches are due; the shortfall is spread over both
+    deps.querier
+        .set_bank_balances(&[Coin::new(20000 - 200, "uxyz")]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            amount: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events[0],
+        Event::new("steakhub/reconciled")
+            .add_attribute("sender", "user_1")
+            .add_attribute("ids", "1")
+            .add_attribute("native_deducted", "100")
+    );
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(9900, "uxyz")]
+        })
+    );
+
+    // User 2's batch is left for `Reconcile` or user 2's own withdrawal
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 2u64))
+        .unwrap();
+    assert!(!batch.reconciled);
+    assert_eq!(batch.amount_unclaimed, Uint128::new(10000));
+
+    // User 1's batch is gone with the withdrawal
+    let res: MetricsResponse = query_helper(deps.as_ref(), QueryMsg::Metrics {});
+    assert_eq!(res.num_previous_batches, 1);
+    assert_eq!(res.native_unclaimed, Uint128::new(10000));
+
+    // Reconciling later deducts the rest of the shortfall from batch 2, the same share batch 1 took
+    deps.querier
+        .set_bank_balances(&[Coin::new(20000 - 200 - 9900, "uxyz")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(26000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.events[0],
+        Event::new("steakhub/reconciled")
+            .add_attribute("sender", "worker")
+            .add_attribute("ids", "2")
+            .add_attribute("native_deducted", "100")
+    );
+    let batch = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 2u64))
+        .unwrap();
+    assert!(batch.reconciled);
+    assert_eq!(batch.amount_unclaimed, Uint128::new(9900));
+}
+
+#[test]
+fn keeping_recent_claims() {
+    let mut deps = mock_dependencies();
+    let state = State::default();
+    let user = Addr::unchecked("user_1");
+
+    for height in 0..(RECENT_CLAIMS_CAPACITY as u64 + 2) {
+        state
+            .record_claim(
+                deps.as_mut().storage,
+                &user,
+                RecentClaim {
+                    id: 0,
+                    receiver: "user_1".to_string(),
+                    amount: Uint128::new(100),
+                    height,
+                },
+            )
+            .unwrap();
+    }
+    // Another user's claims neither take up nor push out user 1's
+    let id = state
+        .record_claim(
+            deps.as_mut().storage,
+            &Addr::unchecked("user_2"),
+            RecentClaim {
+                id: 0,
+                receiver: "user_2".to_string(),
+                amount: Uint128::new(100),
+                height: 10,
+            },
+        )
+        .unwrap();
+    assert_eq!(id, RECENT_CLAIMS_CAPACITY as u64 + 2);
+
+    let res: Vec<RecentClaim> = query_helper(
+        deps.as_ref(),
+        QueryMsg::RecentClaims {
+            user: "user_1".to_string(),
+        },
+    );
+    assert_eq!(
+        res.iter().map(|c| c.id).collect::<Vec<_>>(),
+        (2..(RECENT_CLAIMS_CAPACITY as u64 + 2)).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn withdrawing_unbonded_partially() {
+    let state = State::default();
+    let setup_batches = || {
+        let mut deps = setup_test();
+
+        let unbond_requests = vec![
+            UnbondRequest {
+                id: 1,
+                user: Addr::unchecked("user_1"),
+                shares: Uint128::new(400),
+            },
+            UnbondRequest {
+                id: 1,
+                user: Addr::unchecked("user_2"),
+                shares: Uint128::new(600),
+            },
+            UnbondRequest {
+                id: 2,
+                user: Addr::unchecked("user_1"),
+                shares: Uint128::new(500),
+            },
+        ];
+        for unbond_request in &unbond_requests {
+            state
+                .save_unbond_request(deps.as_mut().storage, unbond_request)
+                .unwrap();
+        }
+
+        let previous_batches = vec![
+            Batch {
+                id: 1,
+                reconciled: true,
+                total_shares: Uint128::new(1000),
+                amount_unclaimed: Uint128::new(1100),
+                est_unbond_end_time: 10000,
+                fee_rate: None,
+                exchange_rate: None,
+                submitter: None,
+                shortfalls: vec![],
+                backfilled: false,
+            },
+            Batch {
+                id: 2,
+                reconciled: true,
+                total_shares: Uint128::new(500),
+                amount_unclaimed: Uint128::new(600),
+                est_unbond_end_time: 20000,
+                fee_rate: None,
+                exchange_rate: None,
+                submitter: None,
+                shortfalls: vec![],
+                backfilled: false,
+            },
+        ];
+        for previous_batch in &previous_batches {
+            state
+                .previous_batches
+                .save(
+                    deps.as_mut().storage,
+                    ("uxyz", previous_batch.id),
+                    previous_batch,
+                )
+                .unwrap();
+        }
+
+        deps
+    };
+
+    // User 1 can withdraw 400 * 1100 / 1000 + 600 = 1,040 in total
+    let mut deps = setup_batches();
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            amount: Some(Uint128::new(2000)),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("withdrawable amount is 1040, less than the 2000 requested")
+    );
+
+    // Withdraw 700: batch 1 is claimed in full (440), batch 2 only in part
+    let mut deps = setup_batches();
+    //
+    // Shares claimed from batch 2: 500 * 260 / 600 = 216
+    // Native claimed from batch 2: 600 * 216 / 500 = 259
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            amount: Some(Uint128::new(700)),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(440 + 259, "uxyz")]
+        })
+    );
+
+    let batch1 = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 1u64))
+        .unwrap();
+    assert_eq!(batch1.total_shares, Uint128::new(600));
+    assert_eq!(batch1.amount_unclaimed, Uint128::new(660));
+    let batch2 = state
+        .previous_batches
+        .load(deps.as_ref().storage, ("uxyz", 2u64))
+        .unwrap();
+    assert_eq!(batch2.total_shares, Uint128::new(284));
+    assert_eq!(batch2.amount_unclaimed, Uint128::new(341));
+
+    let res: Vec<UnbondRequestsByUserResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByUser {
+            user: "user_1".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![UnbondRequestsByUserResponseItem {
+            id: 2,
+            shares: Uint128::new(284),
+            queued_exchange_rate: None,
+        }]
+    );
+
+    // The rest is withdrawn later, and batch 2 is purged
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+            amount: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(341, "uxyz")]
+        })
+    );
+    assert!(state
+        .previous_batches
+        .may_load(deps.as_ref().storage, ("uxyz", 2u64))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn adding_validator() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "alice".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::generic_err("validator is already whitelisted")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 0);
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        validators,
+        vec![
+            String::from("alice"),
+            String::from("bob"),
+            String::from("charlie"),
+            String::from("dave")
+        ],
+    );
+}
+#[test]
+fn requiring_self_delegation() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let alice = "junovaloper190vqdjtlpcq27xslcveglfmr4ynfwg7gf5drvk";
+    let bob = "junovaloper1sxmr0k8u6trd5c6eu6trzyapzux7090ycnpepk";
+    let carol = "junovaloper1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5kde26e";
+    assert_eq!(
+        operator_account_address(alice).unwrap(),
+        "juno190vqdjtlpcq27xslcveglfmr4ynfwg7gkfmvh0"
+    );
+    assert_eq!(
+        operator_account_address(bob).unwrap(),
+        "juno1sxmr0k8u6trd5c6eu6trzyapzux7090y8whk60"
+    );
+
+    // Alice's operator self-delegates; Bob's doesn't
+    deps.querier.set_staking_delegations_of(
+        "juno190vqdjtlpcq27xslcveglfmr4ynfwg7gkfmvh0",
+        &[Delegation::new(alice, 1500, "uxyz")],
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::CheckValidators {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("no minimum self-delegation is set")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetMinSelfDelegation {
+            min_self_delegation: Uint128::new(1000),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMinSelfDelegation {
+            min_self_delegation: Uint128::new(1000),
+        },
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("dave is not a validator operator address")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AddValidator {
+            validator: bob.to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "validator {} self-delegates 0, below the minimum of 1000",
+            bob
+        ))
+    );
+
+    // Nor can a validator short of the minimum come in as a replacement
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ReplaceValidator {
+            old: "charlie".to_string(),
+            new: bob.to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "validator {} self-delegates 0, below the minimum of 1000",
+            bob
+        ))
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::AddValidator {
+            validator: alice.to_string(),
+        },
+    )
+    .unwrap();
+
+    // Bob was whitelisted before the minimum was raised, and so was Carol, who is suspended
+    state
+        .validators_active
+        .save(
+            deps.as_mut().storage,
+            &vec![alice.to_string(), bob.to_string()],
+        )
+        .unwrap();
+    state
+        .validator_suspensions
+        .save(
+            deps.as_mut().storage,
+            carol.to_string(),
+            &(mock_env().block.time.seconds() + 10000),
+        )
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::CheckValidators {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steakhub/validators_checked")
+            .add_attribute("min_self_delegation", "1000")
+            .add_attribute("deactivated", format!("{},{}", bob, carol))]
+    );
+    assert_eq!(
+        state.validators_active.load(deps.as_ref().storage).unwrap(),
+        vec![alice.to_string()]
+    );
+    // Carol's suspension is cleared, so that she doesn't come back once it would have ended
+    assert!(!state
+        .validator_suspensions
+        .has(deps.as_ref().storage, carol.to_string()));
+
+    let config: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(config.min_self_delegation, Uint128::new(1000));
+}
+
+#[test]
+fn removing_validator() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    state
+        .validator_mining_powers
+        .save(deps.as_mut().storage, "alice".to_string(), &Uint128::new(1))
+        .unwrap();
+    state
+        .validator_mining_powers
+        .save(
+            deps.as_mut().storage,
+            "charlie".to_string(),
+            &Uint128::new(2),
+        )
+        .unwrap();
+    state
+        .total_mining_power
+        .save(deps.as_mut().storage, &Uint128::new(3))
+        .unwrap();
+
+    let err = apply_change(
+        &mut deps,
+        mock_env(),
+        "jake",
+        ConfigChange::RemoveValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = apply_change(
+        &mut deps,
+        mock_env(),
+        "larry",
+        ConfigChange::RemoveValidator {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::generic_err("validator is not already whitelisted")
+    );
+
+    // Target: (341667 + 341667 + 341666) / 2 = 512500
+    // Remainder: 0
+    // Alice:   512500 + 0 - 341667 = 170833
+    // Bob:     512500 + 0 - 341667 = 170833
+    let env = mock_env();
+    let res = apply_change(
+        &mut deps,
+        env.clone(),
+        "larry",
+        ConfigChange::RemoveValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_success(
+            Redelegation::new("charlie", "alice", 170833, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            1
+        ),
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::reply_on_success(
+            Redelegation::new("charlie", "bob", 170833, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            2
+        ),
+    );
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(validators, vec![String::from("alice"), String::from("bob")],);
+
+    // The removed validator is no longer active, and its mining power is purged
+    assert_eq!(
+        res.events[0],
+        Event::new("steak/validator_removed")
+            .add_attribute("validator", "charlie")
+            .add_attribute("mining_power_removed", "2")
+            .add_attribute("total_mining_power", "1")
+    );
+    let validators_active = state.validators_active.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        validators_active,
+        vec![String::from("alice"), String::from("bob")],
+    );
+    let mining_power = state
+        .validator_mining_powers
+        .may_load(deps.as_ref().storage, "charlie".to_string())
+        .unwrap();
+    assert_eq!(mining_power, None);
+    let total_mining_power = state
+        .total_mining_power
+        .load(deps.as_ref().storage)
+        .unwrap();
+    assert_eq!(total_mining_power, Uint128::new(1));
+
+    // Proofs can no longer target it
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("miner", &[]),
+        ExecuteMsg::SubmitProof {
+            nonce: Uint64::new(1),
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("validator is not whitelisted or is paused")
+    );
+}
+#[test]
+fn capping_messages_per_execute() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1025000);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetMaxMessages {
+            max_messages: Some(1),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMaxMessages {
+            max_messages: Some(0),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("maximum message count must be at least 1")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMaxMessages {
+            max_messages: Some(1),
+        },
+    )
+    .unwrap();
+
+    // Bonding delegates and mints, one message too many
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("execute would send 2 messages, more than the maximum of 1")
+    );
+
+    // Removing a validator sends one redelegation and leaves the other to `ContinueRebalance`
+    let env = mock_env();
+    let res = apply_change(
+        &mut deps,
+        env.clone(),
+        "larry",
+        ConfigChange::RemoveValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0].msg,
+        Redelegation::new("charlie", "alice", 170833, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    assert_eq!(
+        res.events[0].attributes.last().unwrap(),
+        &attr("redelegations_pending", "1")
+    );
+    assert_eq!(
+        state.pending_rebalance.load(deps.as_ref().storage).unwrap(),
+        vec![Redelegation::new("charlie", "bob", 170833, "uxyz")]
+    );
+
+    // Until it's moved, what charlie still holds is counted as bonded
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 512500, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 170833, "uxyz"),
+    ]);
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(res.total_native, Uint128::new(1025000));
+    assert_eq!(res.exchange_rate, Decimal::one());
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("jake", &[]),
+        ExecuteMsg::ContinueRebalance {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0].msg,
+        Redelegation::new("charlie", "bob", 170833, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+    assert!(state
+        .pending_rebalance
+        .may_load(deps.as_ref().storage)
+        .unwrap()
+        .is_none());
+
+    let config: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(config.max_messages, Some(1));
+}
+
+#[test]
+fn replacing_validator() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    state
+        .validator_mining_powers
+        .save(deps.as_mut().storage, "bob".to_string(), &Uint128::new(5))
+        .unwrap();
+    state
+        .validator_caps
+        .save(
+            deps.as_mut().storage,
+            "bob".to_string(),
+            &Uint128::new(400000),
+        )
+        .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ReplaceValidator {
+            old: "bob".to_string(),
+            new: "charlie".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("validator is already whitelisted")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ReplaceValidator {
+            old: "dave".to_string(),
+            new: "bobby".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("validator is not already whitelisted")
+    );
+
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ReplaceValidator {
+            old: "bob".to_string(),
+            new: "bobby".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_success(
+            Redelegation::new("bob", "bobby", 341667, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap(),
+            1
+        )]
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("steak/validator_replaced")
+            .add_attribute("old", "bob")
+            .add_attribute("new", "bobby")
+            .add_attribute("redelegated", "341667")
+            .add_attribute("mining_power", "5")]
+    );
+
+    // The new address takes the old one's place and everything recorded against it
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(validators, vec!["alice", "bobby", "charlie"]);
+    let validators_active = state.validators_active.load(deps.as_ref().storage).unwrap();
+    assert_eq!(validators_active, vec!["alice", "bobby", "charlie"]);
+    let mining_power = state
+        .validator_mining_powers
+        .may_load(deps.as_ref().storage, "bobby".to_string())
+        .unwrap();
+    assert_eq!(mining_power, Some(Uint128::new(5)));
+    let cap = state
+        .validator_caps
+        .may_load(deps.as_ref().storage, "bobby".to_string())
+        .unwrap();
+    assert_eq!(cap, Some(Uint128::new(400000)));
+    assert!(!state
+        .validator_caps
+        .has(deps.as_ref().storage, "bob".to_string()));
+}
+
+#[test]
+fn replacing_validator_mid_rebalance() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    state
+        .pending_rebalance
+        .save(
+            deps.as_mut().storage,
+            &vec![
+                Redelegation::new("bob", "alice", 1000, "uxyz"),
+                Redelegation::new("charlie", "bob", 2000, "uxyz"),
+                Redelegation::new("alice", "charlie", 500, "uxyz"),
+            ],
+        )
+        .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::ReplaceValidator {
+            old: "bob".to_string(),
+            new: "bobby".to_string(),
+        },
+    )
+    .unwrap();
+
+    // Bob's part in the rebalance goes to Bobby, along with Bob's stake
+    let pending_rebalance = state.pending_rebalance.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        pending_rebalance,
+        vec![
+            Redelegation::new("bobby", "alice", 1000, "uxyz"),
+            Redelegation::new("charlie", "bobby", 2000, "uxyz"),
+            Redelegation::new("alice", "charlie", 500, "uxyz"),
+        ]
+    );
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bobby", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("jake", &[]),
+        ExecuteMsg::ContinueRebalance {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0].msg,
+        Redelegation::new("bobby", "alice", 1000, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default()
+            )
+            .unwrap()
+    );
+}
+
+#[test]
+fn rebalancing_in_chunks() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // One validator holds everything; the other eleven should get 100000 each
+    let validators: Vec<String> = (0..12).map(|i| format!("validator{}", i)).collect();
+    state
+        .validators
+        .save(deps.as_mut().storage, &validators)
+        .unwrap();
+    state
+        .validators_active
+        .save(deps.as_mut().storage, &validators)
+        .unwrap();
+    deps.querier
+        .set_staking_delegations(&[Delegation::new("validator0", 1200000, "uxyz")]);
+
+    let env = mock_env();
+    let redelegation_msg = |dst: &str, amount: u128| {
+        Redelegation::new("validator0", dst, amount, "uxyz")
+            .to_cosmos_msg(
+                env.contract.address.to_string(),
+                &StakingProtoUrls::default(),
+            )
+            .unwrap()
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("jake", &[]),
+        ExecuteMsg::ContinueRebalance {},
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("no rebalance in progress"));
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Rebalance { minimum: None },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), MAX_REDELEGATIONS_PER_TX);
+    for (i, submsg) in res.messages.iter().enumerate() {
+        assert_eq!(
+            submsg.msg,
+            redelegation_msg(&format!("validator{}", i + 1), 100000)
+        );
+    }
+    assert_eq!(
+        res.events[0],
+        Event::new("steakhub/rebalanced")
+            .add_attribute("amount_moved", "1000000")
+            .add_attribute("redelegations_pending", "1")
+    );
+    let pending_rebalance = state.pending_rebalance.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        pending_rebalance,
+        vec![Redelegation::new(
+            "validator0",
+            "validator11",
+            100000,
+            "uxyz"
+        )]
+    );
+
+    // By the time the rest is sent, validator0 has less left than planned, e.g. after a batch was
+    // submitted; the redelegation is capped at what it has
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("validator0", 60000, "uxyz"),
+        Delegation::new("validator1", 100000, "uxyz"),
+    ]);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("jake", &[]),
+        ExecuteMsg::ContinueRebalance {},
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0].msg, redelegation_msg("validator11", 60000));
+    assert_eq!(
+        res.events[0],
+        Event::new("steakhub/rebalanced")
+            .add_attribute("amount_moved", "60000")
+            .add_attribute("redelegations_pending", "0")
+    );
+    let pending_rebalance = state
+        .pending_rebalance
+        .may_load(deps.as_ref().storage)
+        .unwrap();
+    assert_eq!(pending_rebalance, None);
+
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info("jake", &[]),
+        ExecuteMsg::ContinueRebalance {},
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("no rebalance in progress"));
+}
+#[test]
+fn rebalancing_above_minimum() {
+    let mut deps = setup_test();
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 1000, "uxyz"),
+        Delegation::new("bob", 900, "uxyz"),
+        Delegation::new("charlie", 1100, "uxyz"),
+    ]);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetMinRedelegation {
+            min_redelegation: Uint128::new(100),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMinRedelegation {
+            min_redelegation: Uint128::new(100),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.min_redelegation, Uint128::new(100));
+
+    // Bob and Charlie are each 100 from their target, which isn't enough to move. Callers can't
+    // ask for a lower minimum than the owner set
+    for minimum in [None, Some(Uint128::new(50))] {
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("jake", &[]),
+            ExecuteMsg::Rebalance { minimum },
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 0);
+    }
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMinRedelegation {
+            min_redelegation: Uint128::new(50),
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Rebalance { minimum: None },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0].msg,
+        Redelegation::new("charlie", "bob", 100, "uxyz")
+            .to_cosmos_msg(MOCK_CONTRACT_ADDR.to_string(), &StakingProtoUrls::default())
+            .unwrap()
+    );
+
+    // Unless they ask for a higher one
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Rebalance {
+            minimum: Some(Uint128::new(100)),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+}
+
+#[test]
+fn transferring_ownership() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::TransferOwnership {
+            new_owner: "jake".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::TransferOwnership {
+            new_owner: "jake".to_string(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 0);
+
+    let owner = state.owner.load(deps.as_ref().storage).unwrap();
+    assert_eq!(owner, Addr::unchecked("larry"));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("pumpkin", &[]),
+        ExecuteMsg::AcceptOwnership {},
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not new owner")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::AcceptOwnership {},
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 0);
+
+    let owner = state.owner.load(deps.as_ref().storage).unwrap();
+    assert_eq!(owner, Addr::unchecked("jake"));
+}
+
+#[test]
+fn splitting_fees() {
+    let mut deps = setup_test();
+
+    let err = apply_change(
+        &mut deps,
+        mock_env(),
+        "jake",
+        ConfigChange::FeeAccount {
+            fee_account_type: FeeType::Wallet,
+            new_fee_account: "charlie".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    // Fee types keep the strings they were given before being typed; anything else is refused
+    // when the message is parsed
+    let change: ConfigChange =
+        from_slice(br#"{"fee_account":{"fee_account_type":"Wallet","new_fee_account":"charlie"}}"#)
+            .unwrap();
+    assert_eq!(
+        change,
+        ConfigChange::FeeAccount {
+            fee_account_type: FeeType::Wallet,
+            new_fee_account: "charlie".to_string(),
+        }
+    );
+    from_slice::<ConfigChange>(
+        br#"{"fee_account":{"fee_account_type":"xxxx","new_fee_account":"charlie"}}"#,
+    )
+    .unwrap_err();
+
+    apply_change(
+        &mut deps,
+        mock_env(),
+        "larry",
+        ConfigChange::FeeAccount {
+            fee_account_type: FeeType::Wallet,
+            new_fee_account: "charlie".to_string(),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(
+        res,
+        ConfigResponse {
+            owner: "larry".to_string(),
+            new_owner: None,
+            steak_token: "steak_token".to_string(),
+            usteak_denom: None,
+            epoch_period: 259200,
+            unbond_period: 1814400,
+            denom: "uxyz".to_string(),
+            fee_type: "Wallet".to_string(),
+            fee_account: "charlie".to_string(),
+            fee_rate: Decimal::from_ratio(10_u128, 100_u128),
+            max_fee_rate: Decimal::from_ratio(20_u128, 100_u128),
+            fee_exemptions: vec![],
+            validators: vec![
+                "alice".to_string(),
+                "bob".to_string(),
+                "charlie".to_string()
+            ],
+            min_reinvest_amount: Uint128::zero(),
+            min_bond_amount: Uint128::zero(),
+            min_unbond_shares: Uint128::zero(),
+            max_rebase_bps: None,
+            max_harvest_staleness: None,
+            submit_deadline_buffer: None,
+            harvest_granter: None,
+            ics20_contract: None,
+            ics20_channel: None,
+            harvest_mode: HarvestMode::CoinReceivedEvents,
+            remainder_policy: RemainderPolicy::FirstValidators,
+            fee_split_interface: FeeSplitInterface::V1,
+            slash_limit: None,
+            referral_bps: 0,
+            fee_vesting: None,
+            batch_rollover: None,
+            restaking_adapter: None,
+            measure_deposits: false,
+            min_delegation_per_validator: Uint128::zero(),
+            bond_rate_limit: None,
+            min_harvest_reward: Uint128::zero(),
+            min_self_delegation: Uint128::zero(),
+            max_batch_exclusions: 0,
+            forbidden_deposit_denoms: vec![],
+            token_admin: MOCK_CONTRACT_ADDR.to_string(),
+            capabilities: Capabilities::default(),
+            reconcile_mode: ReconcileMode::BalanceDiff,
+            rate_publisher: None,
+            max_messages: None,
+            min_redelegation: Uint128::zero(),
+            priority_unbonders: vec![],
+            change_timelock: ChangeTimelock::default(),
+        }
+    );
+
+    apply_change(
+        &mut deps,
+        mock_env(),
+        "larry",
+        ConfigChange::FeeAccount {
+            fee_account_type: FeeType::FeeSplit,
+            new_fee_account: "contract".to_string(),
+        },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(
+        res,
+        ConfigResponse {
+            owner: "larry".to_string(),
+            new_owner: None,
+            steak_token: "steak_token".to_string(),
+            usteak_denom: None,
+            epoch_period: 259200,
+            unbond_period: 1814400,
+            denom: "uxyz".to_string(),
+            fee_type: "FeeSplit".to_string(),
+            fee_account: "contract".to_string(),
+            fee_rate: Decimal::from_ratio(10_u128, 100_u128),
+            max_fee_rate: Decimal::from_ratio(20_u128, 100_u128),
+            fee_exemptions: vec![],
+            validators: vec![
+                "alice".to_string(),
+                "bob".to_string(),
+                "charlie".to_string()
+            ],
+            min_reinvest_amount: Uint128::zero(),
+            min_bond_amount: Uint128::zero(),
+            min_unbond_shares: Uint128::zero(),
+            max_rebase_bps: None,
+            max_harvest_staleness: None,
+            submit_deadline_buffer: None,
+            harvest_granter: None,
+            ics20_contract: None,
+            ics20_channel: None,
+            harvest_mode: HarvestMode::CoinReceivedEvents,
+            remainder_policy: RemainderPolicy::FirstValidators,
+            fee_split_interface: FeeSplitInterface::V1,
+            slash_limit: None,
+            referral_bps: 0,
+            fee_vesting: None,
+            batch_rollover: None,
+            restaking_adapter: None,
+            measure_deposits: false,
+            min_delegation_per_validator: Uint128::zero(),
+            bond_rate_limit: None,
+            min_harvest_reward: Uint128::zero(),
+            min_self_delegation: Uint128::zero(),
+            max_batch_exclusions: 0,
+            forbidden_deposit_denoms: vec![],
+            token_admin: MOCK_CONTRACT_ADDR.to_string(),
+            capabilities: Capabilities::default(),
+            reconcile_mode: ReconcileMode::BalanceDiff,
+            rate_publisher: None,
+            max_messages: None,
+            min_redelegation: Uint128::zero(),
+            priority_unbonders: vec![],
+            change_timelock: ChangeTimelock::default(),
+        }
+    );
+}
+
+#[test]
+fn submit_proof() {
+    let mut deps = setup_test();
+    let state = State::default();
+    let miner_entropy =
+        "df5c2d1c1e799c13e81ef0d24acdb338e9da760af9afcd1bfbde40d61fed8996".to_string();
+    let miner_address = "joe1gh9nds8amsy33ewpt97gj4n99436hftz2zl79q".to_string();
+    let nonce = Uint64::from(121063160u64);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    state
+        .miner_entropy
+        .save(deps.as_mut().storage, &miner_entropy)
+        .unwrap();
+    state
+        .miner_difficulty
+        .save(deps.as_mut().storage, &Uint64::new(5))
+        .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMiningParams {
+            difficulty: None,
+            entropy: None,
+            enabled: None,
+            proof_cooldown_blocks: Some(10),
+            power_normalization: None,
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&miner_address.to_string(), &[]),
+        ExecuteMsg::SubmitProof {
+            nonce,
+            validator: "alice".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        state
+            .last_proof_height
+            .load(deps.as_ref().storage, &Addr::unchecked(&miner_address))
+            .unwrap(),
+        mock_env().block.height
+    );
+
+    // The miner is held back until the cooldown is over; the proof itself is only checked then
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&miner_address.to_string(), &[]),
+        ExecuteMsg::SubmitProof {
+            nonce,
+            validator: "alice".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(format!(
+            "miner must wait until block {} to submit another proof",
+            mock_env().block.height + 10
+        ))
+    );
+
+    let mut env = mock_env();
+    env.block.height += 10;
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info(&miner_address.to_string(), &[]),
+        ExecuteMsg::SubmitProof {
+            nonce,
+            validator: "alice".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("block hash does not meet difficulty requirement")
+    );
+}
+
+#[test]
+fn mining_with_validator_difficulty() {
+    let mut deps = setup_test();
+    let state = State::default();
+    let miner_entropy =
+        "df5c2d1c1e799c13e81ef0d24acdb338e9da760af9afcd1bfbde40d61fed8996".to_string();
+    let miner_address = "joe1gh9nds8amsy33ewpt97gj4n99436hftz2zl79q".to_string();
+    let nonce = Uint64::from(121063160u64);
+    DelegationSetBuilder::new()
+        .split(&["alice", "bob", "charlie"], 1025000)
+        .apply(&mut deps.querier);
+    state
+        .miner_entropy
+        .save(deps.as_mut().storage, &miner_entropy)
+        .unwrap();
+    state
+        .miner_difficulty
+        .save(deps.as_mut().storage, &Uint64::new(5))
+        .unwrap();
+
+    let set_validator_difficulty =
+        |validator: &str, difficulty: Option<u64>| ExecuteMsg::SetValidatorDifficulty {
+            validator: validator.to_string(),
+            difficulty: difficulty.map(Uint64::new),
+        };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        set_validator_difficulty("alice", Some(64)),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_validator_difficulty("dave", Some(64)),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("validator is not already whitelisted")
+    );
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_validator_difficulty("alice", Some(65)),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("difficulty must be between 1 and 64")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_validator_difficulty("alice", Some(64)),
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steak/set_validator_difficulty")
+            .add_attribute("validator", "alice")
+            .add_attribute("difficulty", "64")]
+    );
+
+    // The proof meets the global difficulty, but not alice's
+    let submit_proof = |validator: &str| ExecuteMsg::SubmitProof {
+        nonce,
+        validator: validator.to_string(),
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&miner_address, &[]),
+        submit_proof("alice"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("block hash does not meet difficulty requirement")
+    );
+
+    // An override may also be easier than the global difficulty
+    state
+        .miner_difficulty
+        .save(deps.as_mut().storage, &Uint64::new(64))
+        .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_validator_difficulty("alice", Some(5)),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&miner_address, &[]),
+        submit_proof("bob"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("block hash does not meet difficulty requirement")
+    );
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(&miner_address, &[]),
+        submit_proof("alice"),
+    )
+    .unwrap();
+
+    let res: Vec<ValidatorDifficultyResponse> = query_helper(
+        deps.as_ref(),
+        QueryMsg::ValidatorDifficulties {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![ValidatorDifficultyResponse {
+            validator: "alice".to_string(),
+            difficulty: Uint64::new(5),
+        }]
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_validator_difficulty("alice", None),
+    )
+    .unwrap();
+    let res: Vec<ValidatorDifficultyResponse> = query_helper(
+        deps.as_ref(),
+        QueryMsg::ValidatorDifficulties {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert!(res.is_empty());
+
+    // A validator's override goes along with it
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_validator_difficulty("bob", Some(7)),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::RemoveValidatorEx {
+            validator: "bob".to_string(),
+        },
+    )
+    .unwrap();
+    let res: Vec<ValidatorDifficultyResponse> = query_helper(
+        deps.as_ref(),
+        QueryMsg::ValidatorDifficulties {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert!(res.is_empty());
+}
+
+//--------------------------------------------------------------------------------------------------
+// Queries
+//--------------------------------------------------------------------------------------------------
+
+#[test]
+fn querying_multi() {
+    let deps = setup_test();
+
+    let res: Vec<Binary> = query_helper(
+        deps.as_ref(),
+        QueryMsg::Multi {
+            queries: vec![QueryMsg::Config {}, QueryMsg::PendingBatch {}],
+        },
+    );
+    assert_eq!(res.len(), 2);
+    let config: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(from_binary::<ConfigResponse>(&res[0]).unwrap(), config);
+    assert_eq!(
+        from_binary::<PendingBatch>(&res[1]).unwrap(),
+        PendingBatch {
+            id: 1,
+            usteak_to_burn: Uint128::zero(),
+            est_unbond_start_time: 269200,
+            rollovers: 0,
+        }
+    );
+
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Multi {
+            queries: vec![QueryMsg::Config {}, QueryMsg::Multi { queries: vec![] }],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("multi queries cannot be nested"));
+}
+
+#[test]
+fn querying_info() {
+    let mut deps = setup_test();
+
+    let res: InfoResponse = query_helper(deps.as_ref(), QueryMsg::Info {});
+    assert_eq!(res.contract, "steak-hub");
+    assert_eq!(res.version, CONTRACT_VERSION);
+    assert_eq!(res.git_commit.as_deref(), option_env!("GIT_COMMIT"));
+    assert_eq!(res.token_backend, "cw20");
+    assert_eq!(res.usteak, "steak_token");
+    assert_eq!(
+        res.chain_profile,
+        ChainProfile {
+            chain_id: "cosmos-testnet-14002".to_string(),
+            denom: "uxyz".to_string(),
+            unbond_period: 1814400,
+            staking_proto_urls: StakingProtoUrls::default(),
+            preset: None,
+        }
+    );
+
+    State::default()
+        .usteak_denom
+        .save(
+            deps.as_mut().storage,
+            &"factory/cosmos2contract/steak".to_string(),
+        )
+        .unwrap();
+    let res: InfoResponse = query_helper(deps.as_ref(), QueryMsg::Info {});
+    assert_eq!(res.token_backend, "token_factory");
+    assert_eq!(res.usteak, "factory/cosmos2contract/steak");
+}
+
+#[test]
+fn querying_schedule() {
+    let mut deps = setup_test();
+    let state = State::default();
+    let env = mock_env_at_timestamp(10000);
+
+    let res: ScheduleResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Schedule {}).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        ScheduleResponse {
+            pending_batch_id: 1,
+            batch_submission_time: 269200,
+            batch_submission_deadline: None,
+            reconciliation_time: None,
+            harvest_deadline: None,
+            suspension_end: None,
+            config_change_expiry: None,
+        }
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetSubmitDeadlineBuffer {
+            submit_deadline_buffer: Some(3600),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMaxHarvestStaleness {
+            max_harvest_staleness: Some(86400),
+        },
+    )
+    .unwrap();
+    state
+        .last_harvest_time
+        .save(deps.as_mut().storage, &5000)
+        .unwrap();
+    for (id, reconciled, est_unbond_end_time) in
+        [(1, true, 8000), (2, false, 30000), (3, false, 20000)]
+    {
+        state
+            .previous_batches
+            .save(
+                deps.as_mut().storage,
+                ("uxyz", id),
+                &Batch {
+                    id,
+                    reconciled,
+                    total_shares: Uint128::new(100),
+                    amount_unclaimed: Uint128::new(100),
+                    est_unbond_end_time,
+                    fee_rate: None,
+                    exchange_rate: None,
+                    submitter: None,
+                    shortfalls: vec![],
+                    backfilled: false,
+                },
+            )
+            .unwrap();
+    }
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SuspendValidator {
+            validator: "bob".to_string(),
+            until: 50000,
+        },
+    )
+    .unwrap();
+    state
+        .pending_changes
+        .save(
+            deps.as_mut().storage,
+            1,
+            &PendingChange {
+                id: 1,
+                change: ConfigChange::FeeRate {
+                    new_fee: Decimal::from_ratio(5u128, 100u128),
+                },
+                proposed_at: 10000,
+                accept_after: 10000,
+                expires_at: 269200,
+            },
+        )
+        .unwrap();
+
+    let res: ScheduleResponse =
+        from_binary(&query(deps.as_ref(), env, QueryMsg::Schedule {}).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        ScheduleResponse {
+            pending_batch_id: 1,
+            batch_submission_time: 269200,
+            batch_submission_deadline: Some(272800),
+            reconciliation_time: Some(20000),
+            harvest_deadline: Some(91400),
+            suspension_end: Some(50000),
+            config_change_expiry: Some(269200),
+        }
+    );
+}
+
+#[test]
+fn querying_replay_batch() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    state
+        .previous_batches
+        .save(
+            deps.as_mut().storage,
+            ("uxyz", 1),
+            &Batch {
+                id: 1,
+                reconciled: true,
+                total_shares: Uint128::new(300),
+                amount_unclaimed: Uint128::new(1000),
+                est_unbond_end_time: 10000,
+                fee_rate: None,
+                exchange_rate: None,
+                submitter: None,
+                shortfalls: vec![],
+                backfilled: false,
+            },
+        )
+        .unwrap();
+    for (user, shares) in [("user_1", 100u128), ("user_2", 200)] {
+        state
+            .save_unbond_request(
+                deps.as_mut().storage,
+                &UnbondRequest {
+                    id: 1,
+                    user: Addr::unchecked(user),
+                    shares: Uint128::new(shares),
+                },
+            )
+            .unwrap();
+    }
+
+    let res: ReplayBatchResponse = query_helper(deps.as_ref(), QueryMsg::ReplayBatch { id: 1 });
+    assert_eq!(
+        res,
+        ReplayBatchResponse {
+            id: 1,
+            total_shares: Uint128::new(300),
+            amount_unclaimed: Uint128::new(1000),
+            request_shares: Uint128::new(300),
+            amount_entitled: Uint128::new(999),
+            entitlements: vec![
+                UnbondEntitlement {
+                    user: "user_1".to_string(),
+                    shares: Uint128::new(100),
+                    amount: Uint128::new(333),
+                },
+                UnbondEntitlement {
+                    user: "user_2".to_string(),
+                    shares: Uint128::new(200),
+                    amount: Uint128::new(666),
+                },
+            ],
+            mismatches: vec![],
+        }
+    );
+
+    // A request that went missing shows up as a mismatch
+    state
+        .remove_unbond_request(deps.as_mut().storage, 1, &Addr::unchecked("user_2"))
+        .unwrap();
+    let res: ReplayBatchResponse = query_helper(deps.as_ref(), QueryMsg::ReplayBatch { id: 1 });
+    assert_eq!(
+        res.mismatches,
+        vec!["requests hold 100 shares but the batch records 300".to_string()]
+    );
+
+    let err = query(deps.as_ref(), mock_env(), QueryMsg::ReplayBatch { id: 2 }).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("batch 2 was not submitted or has been fully withdrawn")
+    );
+}
+
+#[test]
+fn querying_previous_batches() {
+    let mut deps = mock_dependencies();
+
+    let batches = vec![
+        Batch {
+            id: 1,
+            reconciled: false,
+            total_shares: Uint128::new(123),
+            amount_unclaimed: Uint128::new(678),
+            est_unbond_end_time: 10000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: false,
+        },
+        Batch {
+            id: 2,
+            reconciled: true,
+            total_shares: Uint128::new(234),
+            amount_unclaimed: Uint128::new(789),
+            est_unbond_end_time: 15000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: false,
+        },
+        Batch {
+            id: 3,
+            reconciled: false,
+            total_shares: Uint128::new(345),
+            amount_unclaimed: Uint128::new(890),
+            est_unbond_end_time: 20000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: false,
+        },
+        Batch {
+            id: 4,
+            reconciled: true,
+            total_shares: Uint128::new(456),
+            amount_unclaimed: Uint128::new(999),
+            est_unbond_end_time: 25000,
+            fee_rate: None,
+            exchange_rate: None,
+            submitter: None,
+            shortfalls: vec![],
+            backfilled: false,
+        },
+    ];
+
+    let state = State::default();
+    state
+        .denom
+        .save(deps.as_mut().storage, &"uxyz".to_string())
+        .unwrap();
+    for batch in &batches {
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, ("uxyz", batch.id), batch)
+            .unwrap();
+    }
+
+    // Querying a single batch
+    let res: Batch = query_helper(deps.as_ref(), QueryMsg::PreviousBatch(1));
+    assert_eq!(res, batches[0].clone());
+
+    let res: Batch = query_helper(deps.as_ref(), QueryMsg::PreviousBatch(2));
+    assert_eq!(res, batches[1].clone());
+
+    // Query multiple batches
+    let res: Vec<Batch> = query_helper(
+        deps.as_ref(),
+        QueryMsg::PreviousBatches {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, batches);
+
+    let res: Vec<Batch> = query_helper(
+        deps.as_ref(),
+        QueryMsg::PreviousBatches {
+            start_after: Some(1),
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![batches[1].clone(), batches[2].clone(), batches[3].clone()]
+    );
+
+    let res: Vec<Batch> = query_helper(
+        deps.as_ref(),
+        QueryMsg::PreviousBatches {
+            start_after: Some(4),
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![]);
+
+    // Query multiple batches, indexed by whether it has been reconciled
+    let res = state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(true.into())
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (_, v) = item.unwrap();
+            v
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(res, vec![batches[1].clone(), batches[3].clone()]);
+
+    let res = state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(false.into())
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (_, v) = item.unwrap();
+            v
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(res, vec![batches[0].clone(), batches[2].clone()]);
+}
+
+#[test]
+fn querying_unbond_requests() {
+    let mut deps = mock_dependencies();
+    let state = State::default();
+
+    let unbond_requests = vec![
+        UnbondRequestBuilder::new(1, "alice").shares(123).build(),
+        UnbondRequestBuilder::new(1, "bob").shares(234).build(),
+        UnbondRequestBuilder::new(1, "charlie").shares(345).build(),
+        UnbondRequestBuilder::new(2, "alice").shares(456).build(),
+    ];
+
+    // Requests in the legacy layout are found alongside the compact ones
+    for (i, unbond_request) in unbond_requests.iter().enumerate() {
+        if i % 2 == 0 {
+            state
+                .save_unbond_request(deps.as_mut().storage, unbond_request)
+                .unwrap();
+        } else {
+            state
+                .legacy_unbond_requests
+                .save(
+                    deps.as_mut().storage,
+                    (unbond_request.id, &unbond_request.user),
+                    unbond_request,
+                )
+                .unwrap();
+        }
+    }
+
+    let res: Vec<UnbondRequestsByBatchResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByBatch {
+            id: 1,
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![
+            unbond_requests[0].clone().into(),
+            unbond_requests[1].clone().into(),
+            unbond_requests[2].clone().into(),
+        ]
+    );
+
+    let res: Vec<UnbondRequestsByBatchResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByBatch {
+            id: 2,
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![unbond_requests[3].clone().into()]);
+
+    let res: Vec<UnbondRequestsByUserResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByUser {
+            user: "alice".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![
+            unbond_requests[0].clone().into(),
+            unbond_requests[3].clone().into()
+        ]
+    );
+
+    let res: Vec<UnbondRequestsByUserResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByUser {
+            user: "alice".to_string(),
+            start_after: Some(2),
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![unbond_requests[3].clone().into()]);
+}
+
+#[test]
+fn querying_validator_summary() {
+    let mut deps = setup_test();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 400, "uxyz"),
+        Delegation::new("bob", 300, "uxyz"),
+        Delegation::new("charlie", 200, "uxyz"),
+    ]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetValidatorCap {
+            validator: "bob".to_string(),
+            cap: Some(Uint128::new(250)),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::PauseValidator {
+            validator: "bob".to_string(),
+        },
+    )
+    .unwrap();
+
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ValidatorSummary {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("validator dave is not whitelisted")
+    );
+
+    // Without the Stargate query, the total tokens are unknown. Two validators remain active, so
+    // the target is 900 / 2, capped at 250
+    let res: ValidatorSummaryResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ValidatorSummary {
+            validator: "bob".to_string(),
+        },
+    );
+    assert_eq!(
+        res,
+        ValidatorSummaryResponse {
+            validator: "bob".to_string(),
+            active: false,
+            delegation: Uint128::new(300),
+            target_delegation: Uint128::new(250),
+            cap: Some(Uint128::new(250)),
+            mining_power: Uint128::zero(),
+            commission: Some(Decimal::zero()),
+            max_commission: Some(Decimal::zero()),
+            total_tokens: None,
+            jailed: None,
+            slash_incidents: 0,
+            suspended_until: None,
+        }
+    );
+
+    deps.querier
+        .validator_tokens
+        .insert("alice".to_string(), 123456);
+    let res: ValidatorSummaryResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ValidatorSummary {
+            validator: "alice".to_string(),
+        },
+    );
+    assert!(res.active);
+    assert_eq!(res.delegation, Uint128::new(400));
+    assert_eq!(res.target_delegation, Uint128::new(450));
+    assert_eq!(res.cap, None);
+    assert_eq!(res.total_tokens, Some(Uint128::new(123456)));
+    assert_eq!(res.jailed, Some(false));
+}
+
+#[test]
+fn querying_validator_net_apr() {
+    let mut deps = setup_test();
+
+    deps.querier
+        .commissions
+        .insert("alice".to_string(), Decimal::percent(10));
+    DelegationSetBuilder::new()
+        .split(&["alice", "bob", "charlie"], 900)
+        .apply(&mut deps.querier);
+
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::ValidatorNetApr {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("validator dave is not whitelisted")
+    );
+
+    // Without the Stargate queries, only the commission is known
+    let res: ValidatorNetAprResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ValidatorNetApr {
+            validator: "alice".to_string(),
+        },
+    );
+    assert_eq!(
+        res,
+        ValidatorNetAprResponse {
+            validator: "alice".to_string(),
+            commission: Some(Decimal::percent(10)),
+            staking_apr: None,
+            net_apr: None,
+        }
+    );
+
+    // 10,000,000 minted a year, of which 98% goes to 49,000,000 bonded tokens: 20%
+    deps.querier.staking_economics = Some((10000000, Decimal::percent(2), 49000000));
+    let res: ValidatorNetAprResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ValidatorNetApr {
+            validator: "alice".to_string(),
+        },
+    );
+    assert_eq!(res.staking_apr, Some(Decimal::percent(20)));
+    assert_eq!(res.net_apr, Some(Decimal::percent(18)));
+
+    let res: ValidatorNetAprResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ValidatorNetApr {
+            validator: "bob".to_string(),
+        },
+    );
+    assert_eq!(res.commission, Some(Decimal::zero()));
+    assert_eq!(res.net_apr, Some(Decimal::percent(20)));
+}
+
+//--------------------------------------------------------------------------------------------------
+// Delegations
+//--------------------------------------------------------------------------------------------------
+
+#[test]
+fn computing_undelegations() {
+    let current_delegations = vec![
+        Delegation::new("alice", 400, "uxyz"),
+        Delegation::new("bob", 300, "uxyz"),
+        Delegation::new("charlie", 200, "uxyz"),
+    ];
+
+    // Target: (400 + 300 + 200 - 451) / 3 = 149
+    // Remainder: 2
+    // Alice:   400 - (149 + 1) = 250
+    // Bob:     300 - (149 + 1) = 150
+    // Charlie: 200 - (149 + 0) = 51
+    let new_undelegations =
+        compute_undelegations(Uint128::new(451), &current_delegations, "uxyz", 0);
+    let expected = vec![
+        Undelegation::new("alice", 250, "uxyz"),
+        Undelegation::new("bob", 150, "uxyz"),
+        Undelegation::new("charlie", 51, "uxyz"),
+    ];
+    assert_eq!(new_undelegations, expected);
+
+    // With an offset of 2, the remainder goes to Charlie, then wraps around to Alice
+    // Alice:   400 - (149 + 1) = 250
+    // Bob:     300 - (149 + 0) = 151
+    // Charlie: 200 - (149 + 1) = 50
+    let new_undelegations =
+        compute_undelegations(Uint128::new(451), &current_delegations, "uxyz", 2);
+    let expected = vec![
+        Undelegation::new("alice", 250, "uxyz"),
+        Undelegation::new("bob", 151, "uxyz"),
+        Undelegation::new("charlie", 50, "uxyz"),
+    ];
+    assert_eq!(new_undelegations, expected);
+}
+#[test]
+fn computing_undelegations_with_floor() {
+    let current_delegations = vec![
+        Delegation::new("alice", 1000, "uxyz"),
+        Delegation::new("bob", 1000, "uxyz"),
+        Delegation::new("charlie", 100, "uxyz"),
+    ];
+
+    // Without a floor, Alice is brought down to the target of 367, and Bob makes up the rest
+    let new_undelegations = compute_undelegations_with_floor(
+        Uint128::new(1000),
+        &current_delegations,
+        "uxyz",
+        0,
+        Uint128::zero(),
+    );
+    let expected = vec![
+        Undelegation::new("alice", 633, "uxyz"),
+        Undelegation::new("bob", 367, "uxyz"),
+    ];
+    assert_eq!(new_undelegations, expected);
+
+    // With a floor of 500, the 133 that would take Alice below it spill over to Bob. Charlie is
+    // below the floor already, and left alone
+    let new_undelegations = compute_undelegations_with_floor(
+        Uint128::new(1000),
+        &current_delegations,
+        "uxyz",
+        0,
+        Uint128::new(500),
+    );
+    let expected = vec![
+        Undelegation::new("alice", 500, "uxyz"),
+        Undelegation::new("bob", 500, "uxyz"),
+    ];
+    assert_eq!(new_undelegations, expected);
+
+    // With a floor of 600, Bob can only take 33 more while staying above it. The remaining 200 go
+    // below the floor, from Bob who has the most left
+    let new_undelegations = compute_undelegations_with_floor(
+        Uint128::new(1000),
+        &current_delegations,
+        "uxyz",
+        0,
+        Uint128::new(600),
+    );
+    let expected = vec![
+        Undelegation::new("alice", 400, "uxyz"),
+        Undelegation::new("bob", 600, "uxyz"),
+    ];
+    assert_eq!(new_undelegations, expected);
+}
+
+#[test]
+fn rotating_remainder() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetRemainderPolicy {
+            policy: RemainderPolicy::RoundRobin,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    // By default, the remainder always goes to the first validators
+    assert_eq!(
+        state.next_remainder_offset(deps.as_mut().storage).unwrap(),
+        0
+    );
+    assert_eq!(
+        state.next_remainder_offset(deps.as_mut().storage).unwrap(),
+        0
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetRemainderPolicy {
+            policy: RemainderPolicy::RoundRobin,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("steak/set_remainder_policy").add_attribute("policy", "RoundRobin")]
+    );
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.remainder_policy, RemainderPolicy::RoundRobin);
+
+    // Each operation starts the remainder one validator further
+    assert_eq!(
+        state.next_remainder_offset(deps.as_mut().storage).unwrap(),
+        0
+    );
+    assert_eq!(
+        state.next_remainder_offset(deps.as_mut().storage).unwrap(),
+        1
+    );
+    assert_eq!(
+        state.next_remainder_offset(deps.as_mut().storage).unwrap(),
+        2
+    );
+}
+
+#[test]
+fn computing_redelegations_for_removal() {
+    let current_delegations = vec![
+        Delegation::new("alice", 13000, "uxyz"),
+        Delegation::new("bob", 12000, "uxyz"),
+        Delegation::new("charlie", 11000, "uxyz"),
+        Delegation::new("dave", 10000, "uxyz"),
+    ];
+
+    // Suppose Dave will be removed
+    // native_token_per_validator = (13000 + 12000 + 11000 + 10000) / 3 = 15333
+    // remainder = 1
+    // to Alice:   15333 + 1 - 13000 = 2334
+    // to Bob:     15333 + 0 - 12000 = 3333
+    // to Charlie: 15333 + 0 - 11000 = 4333
+    let expected = vec![
+        Redelegation::new("dave", "alice", 2334, "uxyz"),
+        Redelegation::new("dave", "bob", 3333, "uxyz"),
+        Redelegation::new("dave", "charlie", 4333, "uxyz"),
+    ];
+
+    assert_eq!(
+        compute_redelegations_for_removal(
+            &current_delegations[3],
+            &current_delegations[..3],
+            "uxyz",
+            0
+        ),
+        expected,
+    );
+}
+
+#[test]
+fn computing_redelegations_for_rebalancing() {
+    let current_delegations = vec![
+        Delegation::new("alice", 69420, "uxyz"),
+        Delegation::new("bob", 1234, "uxyz"),
+        Delegation::new("charlie", 88888, "uxyz"),
+        Delegation::new("dave", 40471, "uxyz"),
+        Delegation::new("evan", 2345, "uxyz"),
+    ];
+    let active_validators: Vec<String> = vec![
+        "alice".to_string(),
+        "bob".to_string(),
+        "charlie".to_string(),
+        "dave".to_string(),
+        "evan".to_string(),
+    ];
+    // native_token_per_validator = (69420 + 88888 + 1234 + 40471 + 2345) / 4 = 40471
+    // remainer = 3
+    // src_delegations:
+    //  - alice:   69420 - (40471 + 1) = 28948
+    //  - charlie: 88888 - (40471 + 1) = 48416
+    // dst_delegations:
+    //  - bob:     (40471 + 1) - 1234  = 39238
+    //  - evan:    (40471 + 0) - 2345  = 38126
+    //
+    // Round 1: alice --(28948)--> bob
+    // src_delegations:
+    //  - charlie: 48416
+    // dst_delegations:
+    //  - bob:     39238 - 28948 = 10290
+    //  - evan:    38126
+    //
+    // Round 2: charlie --(10290)--> bob
+    // src_delegations:
+    //  - charlie: 48416 - 10290 = 38126
+    // dst_delegations:
+    //  - evan:    38126
+    //
+    // Round 3: charlie --(38126)--> evan
+    // Queues are emptied
+    let expected = vec![
+        Redelegation::new("alice", "bob", 28948, "uxyz"),
+        Redelegation::new("charlie", "bob", 10290, "uxyz"),
+        Redelegation::new("charlie", "evan", 38126, "uxyz"),
+    ];
+
+    assert_eq!(
+        compute_redelegations_for_rebalancing(
+            active_validators,
+            &current_delegations,
+            Uint128::from(10_u64),
+            0,
+            // mock the same mining power on every validator
+            |_| Ok(40471_u128.into())
+        )
+        .unwrap(),
+        expected,
+    );
+
+    let partially_active = vec![
+        "alice".to_string(),
+        "charlie".to_string(),
+        "dave".to_string(),
+        "evan".to_string(),
+    ];
+
+    let partially_expected = vec![
+        Redelegation::new("alice", "dave", 10118, "uxyz"),
+        Redelegation::new("alice", "evan", 8712, "uxyz"),
+        Redelegation::new("charlie", "evan", 38299, "uxyz"),
+    ];
+    assert_eq!(
+        compute_redelegations_for_rebalancing(
+            partially_active.clone(),
+            &current_delegations,
+            Uint128::from(10_u64),
+            0,
+            // mock the same mining power on every validator
+            |_| Ok(50589_u128.into())
+        )
+        .unwrap(),
+        partially_expected,
+    );
+
+    let partially_expected_minimums = vec![
+        Redelegation::new("alice", "evan", 18830, "uxyz"),
+        Redelegation::new("charlie", "evan", 29414, "uxyz"),
+    ];
+    assert_eq!(
+        compute_redelegations_for_rebalancing(
+            partially_active,
+            &current_delegations,
+            Uint128::from(15_000_u64),
+            0,
+            // mock the same mining power on every validator
+            |d| Ok(50589u128.into())
+        )
+        .unwrap(),
+        partially_expected_minimums,
+    );
+}
+
+#[test]
+fn computing_redelegations_for_rebalancing_with_mining() {
+    let current_delegations = vec![
+        Delegation::new("alice", 69420, "uxyz"),
+        Delegation::new("bob", 1234, "uxyz"),
+        Delegation::new("charlie", 88888, "uxyz"),
+        Delegation::new("dave", 40471, "uxyz"),
+        Delegation::new("evan", 2345, "uxyz"),
+    ];
+    let total_delegated_amount = current_delegations.iter().map(|d| d.amount).sum::<u128>();
+    let active_validators: Vec<String> = vec![
+        "alice".to_string(),
+        "bob".to_string(),
+        "charlie".to_string(),
+        "dave".to_string(),
+        "evan".to_string(),
+        // add steve to ensure still works for validators with no mining power
+        "steve".to_string(),
+    ];
+    let mining_powers_by_validator = vec![
+        ("alice".to_string(), 1002_u128),
+        ("bob".to_string(), 3214_u128),
+        ("charlie".to_string(), 881_u128),
+        ("dave".to_string(), 5471_u128),
+        ("evan".to_string(), 9285_u128),
+    ];
+    let total_mining_power = mining_powers_by_validator
+        .iter()
+        .map(|(_, power)| power)
+        .sum::<u128>();
+
+    // total delegated amount: 69420 + 1234 + 88888 + 40471 + 2345 = 202358
+    // total mining power:         1002 + 3214 + 881 + 5471 + 9285 = 19853
     // remainder = 3
     //
     // alice target:                          202358 * 1002 / 19853 = 10213 + remainder 1 = 10214
@@ -2250,180 +11562,904 @@ fn computing_redelegations_for_rebalancing_with_mining() {
     // alice -> evan: 12387 (alice now has delta 0)
     // charlie -> evan: 79908 (charlie now has delta 0)
 
-    let expected = vec![
-        Redelegation::new("alice", "bob", 31526, "uxyz"),
-        Redelegation::new("alice", "dave", 15293, "uxyz"),
-        Redelegation::new("alice", "evan", 12387, "uxyz"),
-        Redelegation::new("charlie", "evan", 79908, "uxyz"),
-    ];
+    let expected = vec![
+        Redelegation::new("alice", "bob", 31526, "uxyz"),
+        Redelegation::new("alice", "dave", 15293, "uxyz"),
+        Redelegation::new("alice", "evan", 12387, "uxyz"),
+        Redelegation::new("charlie", "evan", 79908, "uxyz"),
+    ];
+
+    assert_eq!(
+        compute_redelegations_for_rebalancing(
+            active_validators,
+            &current_delegations,
+            Uint128::from(10_u64),
+            0,
+            // mock the same mining power on every validator
+            |d| compute_target_delegation_from_mining_power(
+                total_delegated_amount.into(),
+                mining_powers_by_validator
+                    .iter()
+                    .find(|(v, _)| v == &d.validator)
+                    .unwrap()
+                    .1
+                    .into(),
+                total_mining_power.into()
+            )
+            .into()
+        )
+        .unwrap(),
+        expected,
+        "round one mining weighted rebalancing"
+    );
+
+    let partially_active = vec![
+        "alice".to_string(),
+        "charlie".to_string(),
+        "dave".to_string(),
+        "evan".to_string(),
+    ];
+
+    let partially_expected = vec![
+        Redelegation::new("alice", "dave", 10118, "uxyz"),
+        Redelegation::new("alice", "evan", 8712, "uxyz"),
+        Redelegation::new("charlie", "evan", 38299, "uxyz"),
+    ];
+    assert_eq!(
+        compute_redelegations_for_rebalancing(
+            partially_active.clone(),
+            &current_delegations,
+            Uint128::from(10_u64),
+            0,
+            // mock the same mining power on every validator
+            |_| Ok(50589_u128.into())
+        )
+        .unwrap(),
+        partially_expected,
+        "round 2 mining weighted rebalancing"
+    );
+
+    let partially_expected_minimums = vec![
+        Redelegation::new("alice", "evan", 18830, "uxyz"),
+        Redelegation::new("charlie", "evan", 29414, "uxyz"),
+    ];
+    assert_eq!(
+        compute_redelegations_for_rebalancing(
+            partially_active,
+            &current_delegations,
+            Uint128::from(15_000_u64),
+            0,
+            // mock the same mining power on every validator
+            |d| Ok(50589u128.into())
+        )
+        .unwrap(),
+        partially_expected_minimums,
+        "round 2 mining weighted rebalancing with minimums"
+    );
+}
+
+//--------------------------------------------------------------------------------------------------
+// Coins
+//--------------------------------------------------------------------------------------------------
+
+#[test]
+fn parsing_coin() {
+    let coin = parse_coin("12345uatom").unwrap();
+    assert_eq!(coin, Coin::new(12345, "uatom"));
+
+    let coin =
+        parse_coin("23456ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B")
+            .unwrap();
+    assert_eq!(
+        coin,
+        Coin::new(
+            23456,
+            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
+        )
+    );
+
+    let err = parse_coin("69420").unwrap_err();
+    assert_eq!(err, StdError::generic_err("failed to parse coin: 69420"));
+
+    let err = parse_coin("ngmi").unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("Parsing u128: cannot parse integer from empty string")
+    );
+}
+
+#[test]
+fn parsing_rfc3339_timestamp() {
+    assert_eq!(parse_rfc3339_seconds("1970-01-01T00:00:00Z").unwrap(), 0);
+    assert_eq!(
+        parse_rfc3339_seconds("2022-08-01T12:34:56.789Z").unwrap(),
+        1659357296
+    );
+    assert_eq!(
+        parse_rfc3339_seconds("2024-02-29T23:59:59-05:00").unwrap(),
+        1709269199
+    );
+
+    let err = parse_rfc3339_seconds("2022-13-01T00:00:00Z").unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("failed to parse timestamp: 2022-13-01T00:00:00Z")
+    );
+    let err = parse_rfc3339_seconds("1659357296").unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("failed to parse timestamp: 1659357296")
+    );
+}
+
+#[test]
+fn parsing_coins() {
+    let coins = Coins::from_str("").unwrap();
+    assert_eq!(coins.0, vec![]);
+
+    let coins = Coins::from_str("12345uatom").unwrap();
+    assert_eq!(coins.0, vec![Coin::new(12345, "uatom")]);
+
+    let coins = Coins::from_str("12345uatom,23456uxyz").unwrap();
+    assert_eq!(
+        coins.0,
+        vec![Coin::new(12345, "uatom"), Coin::new(23456, "uxyz")]
+    );
+}
+
+#[test]
+fn adding_coins() {
+    let mut coins = Coins(vec![]);
+
+    coins.add(&Coin::new(12345, "uatom")).unwrap();
+    assert_eq!(coins.0, vec![Coin::new(12345, "uatom")]);
+
+    coins.add(&Coin::new(23456, "uxyz")).unwrap();
+    assert_eq!(
+        coins.0,
+        vec![Coin::new(12345, "uatom"), Coin::new(23456, "uxyz")]
+    );
+
+    coins
+        .add_many(&Coins::from_str("76543uatom,69420uusd").unwrap())
+        .unwrap();
+    assert_eq!(
+        coins.0,
+        vec![
+            Coin::new(88888, "uatom"),
+            Coin::new(23456, "uxyz"),
+            Coin::new(69420, "uusd")
+        ]
+    );
+}
+#[test]
+fn subtracting_coins() {
+    let mut coins = Coins::from_str("12345uatom,23456uxyz").unwrap();
+    assert_eq!(coins.get("uatom"), Uint128::new(12345));
+    assert_eq!(coins.get("uusd"), Uint128::zero());
 
+    let err = coins.sub(&Coin::new(12346, "uatom")).unwrap_err();
     assert_eq!(
-        compute_redelegations_for_rebalancing(
-            active_validators,
-            &current_delegations,
-            Uint128::from(10_u64),
-            // mock the same mining power on every validator
-            |d| compute_target_delegation_from_mining_power(
-                total_delegated_amount.into(),
-                mining_powers_by_validator
-                    .iter()
-                    .find(|(v, _)| v == &d.validator)
-                    .unwrap()
-                    .1
-                    .into(),
-                total_mining_power.into()
+        err,
+        StdError::overflow(OverflowError::new(
+            OverflowOperation::Sub,
+            Uint128::new(12345),
+            Uint128::new(12346)
+        ))
+    );
+    assert!(coins.sub(&Coin::new(1, "uusd")).is_err());
+
+    coins.sub(&Coin::new(12340, "uatom")).unwrap();
+    assert_eq!(
+        coins.0,
+        vec![Coin::new(5, "uatom"), Coin::new(23456, "uxyz")]
+    );
+
+    // A denom that runs out is dropped
+    coins.sub(&Coin::new(5, "uatom")).unwrap();
+    assert_eq!(coins.0, vec![Coin::new(23456, "uxyz")]);
+
+    coins.saturating_sub_many(&Coins::from_str("456uxyz,69420uusd").unwrap());
+    assert_eq!(coins.0, vec![Coin::new(23000, "uxyz")]);
+    coins.saturating_sub(&Coin::new(99999, "uxyz"));
+    assert_eq!(coins.0, vec![]);
+}
+
+#[test]
+fn comparing_coins() {
+    let coins = Coins::from_str("12345uatom,23456uxyz").unwrap();
+
+    assert!(coins.is_superset_of(&Coins(vec![])));
+    assert!(coins.is_superset_of(&Coins::from_str("12345uatom").unwrap()));
+    assert!(coins.is_superset_of(&Coins::from_str("23456uxyz,12345uatom").unwrap()));
+    assert!(!coins.is_superset_of(&Coins::from_str("12346uatom").unwrap()));
+    assert!(!coins.is_superset_of(&Coins::from_str("1uusd").unwrap()));
+    assert!(!Coins(vec![]).is_superset_of(&coins));
+}
+
+#[test]
+fn receiving_funds() {
+    let err = parse_received_fund(&[], "uxyz").unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("must deposit exactly one coin; received 0")
+    );
+
+    let err = parse_received_fund(
+        &[Coin::new(12345, "uatom"), Coin::new(23456, "uxyz")],
+        "uxyz",
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("must deposit exactly one coin; received 2")
+    );
+
+    let err = parse_received_fund(&[Coin::new(12345, "uatom")], "uxyz").unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("expected uxyz deposit, received uatom")
+    );
+
+    let err = parse_received_fund(&[Coin::new(0, "uxyz")], "uxyz").unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("deposit amount must be non-zero")
+    );
+
+    let amount = parse_received_fund(&[Coin::new(69420, "uxyz")], "uxyz").unwrap();
+    assert_eq!(amount, Uint128::new(69420));
+}
+
+#[test]
+fn setting_mining_params() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetMiningParams {
+            difficulty: Some(Uint64::new(3)),
+            entropy: None,
+            enabled: None,
+            proof_cooldown_blocks: None,
+            power_normalization: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMiningParams {
+            difficulty: Some(Uint64::new(65)),
+            entropy: None,
+            enabled: None,
+            proof_cooldown_blocks: None,
+            power_normalization: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("difficulty must be between 1 and 64")
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMiningParams {
+            difficulty: Some(Uint64::new(3)),
+            entropy: Some("new_entropy".to_string()),
+            enabled: None,
+            proof_cooldown_blocks: None,
+            power_normalization: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.events.len(), 2);
+    assert_eq!(res.events[0].ty, "steak/difficulty_retargeted");
+    assert_eq!(res.events[1].ty, "steak/entropy_rotated");
+
+    // The entropy only goes into the draft; miners keep working against the current one until
+    // the next proof is accepted
+    let mut hasher = Sha256::new();
+    hasher.update(MOCK_CONTRACT_ADDR);
+    hasher.update("new_entropy");
+    let draft = hex::encode(hasher.finalize());
+    assert_eq!(
+        res.events[1],
+        Event::new("steak/entropy_rotated").add_attribute("miner_entropy_draft", &draft)
+    );
+    let state = State::default();
+    assert_eq!(
+        state
+            .miner_entropy_draft
+            .load(deps.as_ref().storage)
+            .unwrap(),
+        draft
+    );
+
+    let res: MinerParamsResponse = query_helper(deps.as_ref(), QueryMsg::MinerParams {});
+    assert_eq!(
+        res,
+        MinerParamsResponse {
+            entropy: MOCK_CONTRACT_ADDR.to_string(),
+            difficulty: Uint64::new(3),
+            enabled: true,
+            proof_cooldown_blocks: 0,
+            power_normalization: MiningPowerNormalization::Disabled,
+        }
+    );
+
+    // With mining disabled, proofs are rejected and anyone can harvest
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetMiningParams {
+            difficulty: None,
+            entropy: None,
+            enabled: Some(false),
+            proof_cooldown_blocks: None,
+            power_normalization: None,
+        },
+    )
+    .unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("miner", &[]),
+        ExecuteMsg::SubmitProof {
+            nonce: Uint64::new(1),
+            validator: "alice".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("mining is disabled"));
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 4);
+}
+
+#[test]
+fn normalizing_mining_power() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667, "uxyz"),
+        Delegation::new("bob", 341667, "uxyz"),
+        Delegation::new("charlie", 341666, "uxyz"),
+    ]);
+    deps.querier.set_cw20_total_supply("steak_token", 1000000);
+    for (validator, mining_power) in [("alice", 10u128), ("bob", 21)] {
+        state
+            .validator_mining_powers
+            .save(
+                deps.as_mut().storage,
+                validator.to_string(),
+                &Uint128::new(mining_power),
             )
-            .into()
+            .unwrap();
+    }
+    state
+        .total_mining_power
+        .save(deps.as_mut().storage, &Uint128::new(31))
+        .unwrap();
+
+    let set_normalization = |normalization| ExecuteMsg::SetMiningParams {
+        difficulty: None,
+        entropy: None,
+        enabled: None,
+        proof_cooldown_blocks: None,
+        power_normalization: Some(normalization),
+    };
+    for factor in [Decimal::zero(), Decimal::one()] {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("larry", &[]),
+            set_normalization(MiningPowerNormalization::Scale { factor }),
         )
-        .unwrap(),
-        expected,
-        "round one mining weighted rebalancing"
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("mining power scale factor must be between 0 and 1, exclusive")
+        );
+    }
+
+    let normalization = MiningPowerNormalization::Scale {
+        factor: Decimal::percent(50),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_normalization(normalization.clone()),
+    )
+    .unwrap();
+    let res: MinerParamsResponse = query_helper(deps.as_ref(), QueryMsg::MinerParams {});
+    assert_eq!(res.power_normalization, normalization);
+
+    let submit_batch = |deps: &mut OwnedDeps<_, _, _>, time: u64| {
+        let mut pending_batch = state
+            .pending_batches
+            .load(deps.as_ref().storage, "uxyz")
+            .unwrap();
+        pending_batch.usteak_to_burn = Uint128::new(1000);
+        state
+            .pending_batches
+            .save(deps.as_mut().storage, "uxyz", &pending_batch)
+            .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(time),
+            mock_info(MOCK_CONTRACT_ADDR, &[]),
+            ExecuteMsg::SubmitBatch { exclude: vec![] },
+        )
+        .unwrap()
+    };
+    let mining_power = |deps: &OwnedDeps<_, _, _>, validator: &str| {
+        state
+            .validator_mining_powers
+            .may_load(deps.as_ref().storage, validator.to_string())
+            .unwrap()
+    };
+
+    // Scaling rounds each validator down
+    let res = submit_batch(&mut deps, 269201);
+    assert_eq!(
+        res.events[1],
+        Event::new("steakhub/mining_power_normalized")
+            .add_attribute("previous_total_mining_power", "31")
+            .add_attribute("total_mining_power", "15")
     );
+    assert_eq!(mining_power(&deps, "alice"), Some(Uint128::new(5)));
+    assert_eq!(mining_power(&deps, "bob"), Some(Uint128::new(10)));
+    assert_eq!(mining_power(&deps, "charlie"), None);
 
-    let partially_active = vec![
-        "alice".to_string(),
-        "charlie".to_string(),
-        "dave".to_string(),
-        "evan".to_string(),
-    ];
+    // Resetting puts every whitelisted validator on the baseline
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_normalization(MiningPowerNormalization::Reset {
+            baseline: Uint128::new(4),
+        }),
+    )
+    .unwrap();
+    let res = submit_batch(&mut deps, 269201 + 259200);
+    assert_eq!(
+        res.events[1],
+        Event::new("steakhub/mining_power_normalized")
+            .add_attribute("previous_total_mining_power", "15")
+            .add_attribute("total_mining_power", "12")
+    );
+    for validator in ["alice", "bob", "charlie"] {
+        assert_eq!(mining_power(&deps, validator), Some(Uint128::new(4)));
+    }
+
+    // Once disabled, mining power is left alone
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        set_normalization(MiningPowerNormalization::Disabled),
+    )
+    .unwrap();
+    let res = submit_batch(&mut deps, 269201 + 2 * 259200);
+    assert!(res
+        .events
+        .iter()
+        .all(|event| event.ty != "steakhub/mining_power_normalized"));
+    assert_eq!(
+        state
+            .total_mining_power
+            .load(deps.as_ref().storage)
+            .unwrap(),
+        Uint128::new(12)
+    );
+}
+
+#[test]
+fn proposing_changes() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("jake", &[]),
+        ExecuteMsg::ProposeChange {
+            change: ConfigChange::FeeRate {
+                new_fee: Decimal::from_ratio(5u128, 100u128),
+            },
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
+    );
+
+    for change in [
+        ConfigChange::FeeRate {
+            new_fee: Decimal::from_ratio(5u128, 100u128),
+        },
+        ConfigChange::FeeAccount {
+            fee_account_type: FeeType::Wallet,
+            new_fee_account: "new_fee_account".to_string(),
+        },
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(10000),
+            mock_info("larry", &[]),
+            ExecuteMsg::ProposeChange { change },
+        )
+        .unwrap();
+    }
 
-    let partially_expected = vec![
-        Redelegation::new("alice", "dave", 10118, "uxyz"),
-        Redelegation::new("alice", "evan", 8712, "uxyz"),
-        Redelegation::new("charlie", "evan", 38299, "uxyz"),
-    ];
-    assert_eq!(
-        compute_redelegations_for_rebalancing(
-            partially_active.clone(),
-            &current_delegations,
-            Uint128::from(10_u64),
-            // mock the same mining power on every validator
-            |_| Ok(50589_u128.into())
+    // Nothing changes until the proposal is accepted
+    let fee_rate = state.fee_rate.load(deps.as_ref().storage).unwrap();
+    assert_eq!(fee_rate, Decimal::from_ratio(10u128, 100u128));
+
+    let res: Vec<PendingChange> = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env_at_timestamp(10000),
+            QueryMsg::PendingChanges {
+                start_after: None,
+                limit: None,
+            },
         )
         .unwrap(),
-        partially_expected,
-        "round 2 mining weighted rebalancing"
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        vec![
+            PendingChange {
+                id: 1,
+                change: ConfigChange::FeeRate {
+                    new_fee: Decimal::from_ratio(5u128, 100u128),
+                },
+                proposed_at: 10000,
+                accept_after: 96400,
+                expires_at: 355600,
+            },
+            PendingChange {
+                id: 2,
+                change: ConfigChange::FeeAccount {
+                    fee_account_type: FeeType::Wallet,
+                    new_fee_account: "new_fee_account".to_string(),
+                },
+                proposed_at: 10000,
+                accept_after: 96400,
+                expires_at: 355600,
+            },
+        ]
     );
 
-    let partially_expected_minimums = vec![
-        Redelegation::new("alice", "evan", 18830, "uxyz"),
-        Redelegation::new("charlie", "evan", 29414, "uxyz"),
-    ];
+    // The owner proposes and accepts, so changes must wait out the delay
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(96399),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcceptChange { id: 1 },
+    )
+    .unwrap_err();
     assert_eq!(
-        compute_redelegations_for_rebalancing(
-            partially_active,
-            &current_delegations,
-            Uint128::from(15_000_u64),
-            // mock the same mining power on every validator
-            |d| Ok(50589u128.into())
-        )
-        .unwrap(),
-        partially_expected_minimums,
-        "round 2 mining weighted rebalancing with minimums"
+        err,
+        StdError::generic_err("change 1 can only be accepted after 96400")
     );
-}
 
-//--------------------------------------------------------------------------------------------------
-// Coins
-//--------------------------------------------------------------------------------------------------
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(96400),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcceptChange { id: 1 },
+    )
+    .unwrap();
+    let fee_rate = state.fee_rate.load(deps.as_ref().storage).unwrap();
+    assert_eq!(fee_rate, Decimal::from_ratio(5u128, 100u128));
 
-#[test]
-fn parsing_coin() {
-    let coin = parse_coin("12345uatom").unwrap();
-    assert_eq!(coin, Coin::new(12345, "uatom"));
+    // An accepted change cannot be accepted again
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(96400),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcceptChange { id: 1 },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("change 1 not found"));
 
-    let coin =
-        parse_coin("23456ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B")
-            .unwrap();
-    assert_eq!(
-        coin,
-        Coin::new(
-            23456,
-            "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"
+    // Once expired, the change can no longer be accepted and is no longer listed
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(355601),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcceptChange { id: 2 },
+    )
+    .unwrap_err();
+    assert_eq!(err, StdError::generic_err("change 2 has expired"));
+
+    let res: Vec<PendingChange> = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env_at_timestamp(355601),
+            QueryMsg::PendingChanges {
+                start_after: None,
+                limit: None,
+            },
         )
-    );
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res, vec![]);
 
-    let err = parse_coin("69420").unwrap_err();
-    assert_eq!(err, StdError::generic_err("failed to parse coin: 69420"));
+    let fee_account = state.fee_account.load(deps.as_ref().storage).unwrap();
+    assert_ne!(fee_account, Addr::unchecked("new_fee_account"));
 
-    let err = parse_coin("ngmi").unwrap_err();
+    // The timelock itself changes through a proposal, and expired changes are dropped as the next
+    // one is proposed
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(355601),
+        mock_info("larry", &[]),
+        ExecuteMsg::ProposeChange {
+            change: ConfigChange::Timelock {
+                timelock: ChangeTimelock {
+                    delay: 3600,
+                    expiry: 7200,
+                },
+            },
+        },
+    )
+    .unwrap();
     assert_eq!(
-        err,
-        StdError::generic_err("Parsing u128: cannot parse integer from empty string")
+        res.events,
+        vec![Event::new("steakhub/change_proposed")
+            .add_attribute("id", "3")
+            .add_attribute(
+                "change",
+                "Timelock { timelock: ChangeTimelock { delay: 3600, expiry: 7200 } }"
+            )
+            .add_attribute("accept_after", "442001")
+            .add_attribute("expires_at", "701201")
+            .add_attribute("expired_dropped", "1")]
     );
-}
-
-#[test]
-fn parsing_coins() {
-    let coins = Coins::from_str("").unwrap();
-    assert_eq!(coins.0, vec![]);
-
-    let coins = Coins::from_str("12345uatom").unwrap();
-    assert_eq!(coins.0, vec![Coin::new(12345, "uatom")]);
+    assert!(!state.pending_changes.has(deps.as_ref().storage, 2));
 
-    let coins = Coins::from_str("12345uatom,23456uxyz").unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(442001),
+        mock_info("larry", &[]),
+        ExecuteMsg::AcceptChange { id: 3 },
+    )
+    .unwrap();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
     assert_eq!(
-        coins.0,
-        vec![Coin::new(12345, "uatom"), Coin::new(23456, "uxyz")]
+        res.change_timelock,
+        ChangeTimelock {
+            delay: 3600,
+            expiry: 7200,
+        }
     );
 }
 
 #[test]
-fn adding_coins() {
-    let mut coins = Coins(vec![]);
+fn setting_staking_proto_urls() {
+    let mut deps = setup_test();
 
-    coins.add(&Coin::new(12345, "uatom")).unwrap();
-    assert_eq!(coins.0, vec![Coin::new(12345, "uatom")]);
+    let urls = StakingProtoUrls {
+        delegate: "/cosmos.staking.v1beta1.MsgDelegate".to_string(),
+        undelegate: "/cosmos.staking.v1beta1.MsgUndelegate".to_string(),
+        redelegate: "/cosmos.staking.v1beta1.MsgBeginRedelegate".to_string(),
+        withdraw_rewards: "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward".to_string(),
+        redeem_tokens: "/cosmos.staking.v1beta1.MsgRedeemTokensForShares".to_string(),
+        authz_exec: "/cosmos.authz.v1beta1.MsgExec".to_string(),
+    };
 
-    coins.add(&Coin::new(23456, "uxyz")).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::SetStakingProtoUrls { urls: urls.clone() },
+    )
+    .unwrap_err();
     assert_eq!(
-        coins.0,
-        vec![Coin::new(12345, "uatom"), Coin::new(23456, "uxyz")]
+        err,
+        StdError::generic_err("unauthorized: sender is not owner")
     );
 
-    coins
-        .add_many(&Coins::from_str("76543uatom,69420uusd").unwrap())
-        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetStakingProtoUrls {
+            urls: StakingProtoUrls {
+                delegate: "cosmos.staking.v1beta1.MsgDelegate".to_string(),
+                ..urls.clone()
+            },
+        },
+    )
+    .unwrap_err();
     assert_eq!(
-        coins.0,
-        vec![
-            Coin::new(88888, "uatom"),
-            Coin::new(23456, "uxyz"),
-            Coin::new(69420, "uusd")
-        ]
+        err,
+        StdError::generic_err(
+            "invalid type url `cosmos.staking.v1beta1.MsgDelegate`: must start with `/`"
+        )
     );
-}
 
-#[test]
-fn receiving_funds() {
-    let err = parse_received_fund(&[], "uxyz").unwrap_err();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetStakingProtoUrls { urls: urls.clone() },
+    )
+    .unwrap();
+
+    let res: StakingProtoUrls = query_helper(deps.as_ref(), QueryMsg::StakingProtoUrls {});
+    assert_eq!(res, urls);
+
+    // Messages are built with the new type URLs
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user_1", &[Coin::new(1000000, "uxyz")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+            referrer: None,
+            min_usteak_out: None,
+        },
+    )
+    .unwrap();
     assert_eq!(
-        err,
-        StdError::generic_err("must deposit exactly one coin; received 0")
+        res.messages[0],
+        SubMsg::reply_on_success(
+            Delegation::new("alice", 1000000, "uxyz")
+                .to_cosmos_msg(env.contract.address.to_string(), &urls)
+                .unwrap(),
+            1
+        )
     );
+    match &res.messages[0].msg {
+        CosmosMsg::Stargate { type_url, .. } => {
+            assert_eq!(type_url, "/cosmos.staking.v1beta1.MsgDelegate")
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
 
-    let err = parse_received_fund(
-        &[Coin::new(12345, "uatom"), Coin::new(23456, "uxyz")],
-        "uxyz",
+#[test]
+fn reinvesting_respects_caps() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // Charlie has the smallest delegation, but has reached its cap
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 100000, "uxyz"),
+        Delegation::new("bob", 90000, "uxyz"),
+        Delegation::new("charlie", 50000, "uxyz"),
+    ]);
+    deps.querier
+        .set_bank_balances(&[Coin::new(1000u128, "uxyz")]);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetValidatorCap {
+            validator: "dave".to_string(),
+            cap: Some(Uint128::new(50000)),
+        },
     )
     .unwrap_err();
     assert_eq!(
         err,
-        StdError::generic_err("must deposit exactly one coin; received 2")
+        StdError::generic_err("validator is not already whitelisted")
     );
 
-    let err = parse_received_fund(&[Coin::new(12345, "uatom")], "uxyz").unwrap_err();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::SetValidatorCap {
+            validator: "charlie".to_string(),
+            cap: Some(Uint128::new(50000)),
+        },
+    )
+    .unwrap();
+
+    let res: Vec<ValidatorCapResponse> = query_helper(
+        deps.as_ref(),
+        QueryMsg::ValidatorCaps {
+            start_after: None,
+            limit: None,
+        },
+    );
     assert_eq!(
-        err,
-        StdError::generic_err("expected uxyz deposit, received uatom")
+        res,
+        vec![ValidatorCapResponse {
+            validator: "charlie".to_string(),
+            cap: Uint128::new(50000),
+        }]
     );
 
-    let err = parse_received_fund(&[Coin::new(0, "uxyz")], "uxyz").unwrap_err();
+    // No mining power has been recorded, so each validator targets an even share (80,000), and
+    // Charlie's target is limited to its cap. Bob is the furthest below target
+    let env = mock_env();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
     assert_eq!(
-        err,
-        StdError::generic_err("deposit amount must be non-zero")
+        res.messages[0],
+        SubMsg::new(
+            Delegation::new("bob", 900, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap()
+        )
     );
 
-    let amount = parse_received_fund(&[Coin::new(69420, "uxyz")], "uxyz").unwrap();
-    assert_eq!(amount, Uint128::new(69420));
+    // A paused validator receives nothing, even when furthest below target
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("larry", &[]),
+        ExecuteMsg::PauseValidator {
+            validator: "bob".to_string(),
+        },
+    )
+    .unwrap();
+    state
+        .prev_denom
+        .save(deps.as_mut().storage, &Uint128::zero())
+        .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(
+            Delegation::new("alice", 900, "uxyz")
+                .to_cosmos_msg(
+                    env.contract.address.to_string(),
+                    &StakingProtoUrls::default()
+                )
+                .unwrap()
+        )
+    );
 }
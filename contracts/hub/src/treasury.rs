@@ -0,0 +1,219 @@
+//! Protocol-owned liquidity. A slice of the fees is set aside at each reinvest; the owner then
+//! bonds half of it and deposits the Steak minted with the other half into a Steak/Native Token
+//! pair, so that the secondary market stays liquid without relying on third-party LPs.
+
+use cosmwasm_std::{
+    to_binary, Addr, Coin, CosmosMsg, Decimal, DepsMut, Env, Event, Response, StdError, StdResult,
+    Storage, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+
+use pfc_steak::hub::TreasuryConfig;
+
+use crate::execute::bond_internal;
+use crate::state::State;
+use crate::types::{provide_liquidity_msg, Asset, AssetInfo};
+
+pub fn set_treasury(
+    deps: DepsMut,
+    sender: Addr,
+    treasury: Option<TreasuryConfig>,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let event = match treasury {
+        Some(treasury) => {
+            deps.api.addr_validate(&treasury.pair)?;
+            deps.api.addr_validate(&treasury.lp_token)?;
+            if treasury.fee_share > Decimal::one() {
+                return Err(StdError::generic_err(
+                    "treasury fee share can't be more than 100%",
+                ));
+            }
+            if treasury.slippage_tolerance > Decimal::one() {
+                return Err(StdError::generic_err(
+                    "slippage tolerance can't be more than 100%",
+                ));
+            }
+            state.treasury.save(deps.storage, &treasury)?;
+            Event::new("steak/set_treasury")
+                .add_attribute("pair", treasury.pair)
+                .add_attribute("lp_token", treasury.lp_token)
+                .add_attribute("fee_share", treasury.fee_share.to_string())
+                .add_attribute(
+                    "slippage_tolerance",
+                    treasury.slippage_tolerance.to_string(),
+                )
+        }
+        None => {
+            state.treasury.remove(deps.storage);
+            // What was set aside is reinvested rather than left stranded
+            let escrow = state
+                .treasury_escrow
+                .may_load(deps.storage)?
+                .unwrap_or_default();
+            state.treasury_escrow.remove(deps.storage);
+            let carry = state
+                .reinvest_carry
+                .may_load(deps.storage)?
+                .unwrap_or_default();
+            state.reinvest_carry.save(deps.storage, &(carry + escrow))?;
+            Event::new("steak/set_treasury")
+                .add_attribute("pair", "none")
+                .add_attribute("native_released", escrow)
+        }
+    };
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "steakhub/set_treasury"))
+}
+
+/// Set aside the treasury's share of `fee_amount`, a fee a reinvest takes. Returns the amount set
+/// aside, which is no longer paid to the fee account
+pub(crate) fn accrue_treasury_share(
+    storage: &mut dyn Storage,
+    fee_amount: Uint128,
+) -> StdResult<Uint128> {
+    let state = State::default();
+
+    let share = match state.treasury.may_load(storage)? {
+        Some(treasury) => fee_amount * treasury.fee_share,
+        None => return Ok(Uint128::zero()),
+    };
+    if !share.is_zero() {
+        let escrow = state.treasury_escrow.may_load(storage)?.unwrap_or_default();
+        state.treasury_escrow.save(storage, &(escrow + share))?;
+    }
+    Ok(share)
+}
+
+pub fn provide_liquidity(mut deps: DepsMut, env: Env, sender: Addr) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let treasury = state
+        .treasury
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("no treasury is set"))?;
+    let escrow = state
+        .treasury_escrow
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let native_to_bond = escrow.multiply_ratio(1u128, 2u128);
+    let native_to_pair = escrow - native_to_bond;
+    if native_to_bond.is_zero() {
+        return Err(StdError::generic_err(format!(
+            "treasury holds {} of Native Token, too little to provide liquidity",
+            escrow
+        )));
+    }
+
+    // Released first, so that bonding finds it among the Native Token the hub doesn't owe anyone
+    state.treasury_escrow.save(deps.storage, &Uint128::zero())?;
+    let denom = state.denom.load(deps.storage)?;
+    let (response, usteak_minted) = bond_internal(
+        deps.branch(),
+        env.clone(),
+        env.contract.address.clone(),
+        None,
+        vec![Coin::new(native_to_bond.u128(), &denom)],
+        None,
+    )?;
+
+    let mut msgs = vec![];
+    let usteak_info = match state.usteak_denom.may_load(deps.storage)? {
+        Some(usteak_denom) => AssetInfo::NativeToken {
+            denom: usteak_denom,
+        },
+        None => {
+            let steak_token = state.steak_token.load(deps.storage)?;
+            msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: steak_token.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                    spender: treasury.pair.clone(),
+                    amount: usteak_minted,
+                    expires: None,
+                })?,
+                funds: vec![],
+            }));
+            AssetInfo::Token {
+                contract_addr: steak_token.into(),
+            }
+        }
+    };
+    msgs.push(provide_liquidity_msg(
+        treasury.pair.clone(),
+        vec![
+            Asset {
+                info: usteak_info,
+                amount: usteak_minted,
+            },
+            Asset {
+                info: AssetInfo::NativeToken { denom },
+                amount: native_to_pair,
+            },
+        ],
+        treasury.slippage_tolerance,
+    )?);
+
+    let native_provided = state
+        .treasury_native_provided
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    state
+        .treasury_native_provided
+        .save(deps.storage, &(native_provided + native_to_pair))?;
+    let usteak_provided = state
+        .treasury_usteak_provided
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    state
+        .treasury_usteak_provided
+        .save(deps.storage, &(usteak_provided + usteak_minted))?;
+
+    let event = Event::new("steakhub/liquidity_provided")
+        .add_attribute("time", env.block.time.seconds().to_string())
+        .add_attribute("height", env.block.height.to_string())
+        .add_attribute("pair", treasury.pair)
+        .add_attribute("native_bonded", native_to_bond)
+        .add_attribute("native_provided", native_to_pair)
+        .add_attribute("usteak_provided", usteak_minted);
+
+    Ok(response.add_messages(msgs).add_event(event))
+}
+
+pub fn transfer_treasury_lp(
+    deps: DepsMut,
+    sender: Addr,
+    recipient: String,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+    let treasury = state
+        .treasury
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("no treasury is set"))?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: treasury.lp_token,
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    });
+
+    let event = Event::new("steakhub/treasury_lp_transferred")
+        .add_attribute("recipient", recipient)
+        .add_attribute("amount", amount);
+
+    Ok(Response::new()
+        .add_message(msg)
+        .add_event(event)
+        .add_attribute("action", "steakhub/transfer_treasury_lp"))
+}
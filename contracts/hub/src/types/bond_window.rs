@@ -0,0 +1,26 @@
+use cosmwasm_std::Uint128;
+use serde::{Deserialize, Serialize};
+
+/// Native Token an address had bonded for it in the rate limit window that started at `start`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BondWindow {
+    /// Time of the first bond in the window, in seconds
+    pub start: u64,
+    /// Native Token bonded in the window so far
+    pub bonded: Uint128,
+}
+
+impl BondWindow {
+    /// The window in effect at `time`: this one if it is `window_secs` old at most, otherwise a
+    /// fresh one starting at `time`
+    pub fn at(self, time: u64, window_secs: u64) -> Self {
+        if time < self.start + window_secs {
+            self
+        } else {
+            Self {
+                start: time,
+                bonded: Uint128::zero(),
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use cosmos_sdk_proto::cosmos::base::v1beta1::Coin as SdkCoin;
+use cosmwasm_std::{
+    to_binary, to_vec, Binary, Coin, CosmosMsg, Decimal, StdError, StdResult, Timestamp, Uint128,
+    WasmMsg,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use pfc_steak::hub::{BridgeProtocol, RatePublisher};
+
+use crate::helpers::proto_encode;
+
+pub const MSG_TRANSFER_TYPE_URL: &str = "/ibc.applications.transfer.v1.MsgTransfer";
+
+/// Account on Axelar that picks up general message passing memos from IBC transfers
+pub const AXELAR_GMP_ACCOUNT: &str =
+    "axelar1dv4u5k73pzqrxlzujxg3qp8kvc3pje7jtdvu72npnt5zhq05ejcsn5qme5";
+
+/// Account on Axelar collecting the gas paid for relaying a general message
+pub const AXELAR_GAS_SERVICE_ACCOUNT: &str = "axelar1zl3rxpp70lmte2xr6c4lgske2fyuj3hupcsvcd";
+
+/// How long the IBC transfer carrying a message to Axelar may take to be relayed, in seconds
+pub const AXELAR_TRANSFER_TIMEOUT: u64 = 3600;
+
+/// What a remote market needs to price bridged Steak: the Native Token each Steak is worth, and
+/// how much Steak there is
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RatePayload {
+    pub exchange_rate: Decimal,
+    pub supply: Uint128,
+    pub total_bonded: Uint128,
+    pub time: u64,
+}
+
+impl RatePayload {
+    /// The payload as Solidity's `abi.encode(uint256, uint256, uint256, uint64)` would produce it,
+    /// the exchange rate scaled by 10^18, so that EVM contracts can decode it without a library
+    pub fn abi_encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(128);
+        for word in [
+            self.exchange_rate.atomics().u128(),
+            self.supply.u128(),
+            self.total_bonded.u128(),
+            self.time as u128,
+        ] {
+            bytes.extend_from_slice(&[0u8; 16]);
+            bytes.extend_from_slice(&word.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+/// `ExecuteMsg` of Wormhole's core bridge contract, publishing `message` for the guardians to sign
+///
+/// https://github.com/wormhole-foundation/wormhole/blob/main/cosmwasm/contracts/wormhole/src/msg.rs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WormholeExecuteMsg {
+    PostMessage { message: Binary, nonce: u32 },
+}
+
+/// Memo of an IBC transfer to Axelar, asking it to call `destination_address` on
+/// `destination_chain` with `payload`
+///
+/// https://docs.axelar.dev/dev/cosmos-gmp
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AxelarGmpMemo {
+    pub destination_chain: String,
+    pub destination_address: String,
+    pub payload: Vec<u8>,
+    /// 1 for a message without tokens
+    #[serde(rename = "type")]
+    pub ty: u8,
+    pub fee: Option<AxelarGmpFee>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AxelarGmpFee {
+    pub amount: String,
+    pub recipient: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Height {
+    #[prost(uint64, tag = "1")]
+    pub revision_number: u64,
+    #[prost(uint64, tag = "2")]
+    pub revision_height: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgTransfer {
+    #[prost(string, tag = "1")]
+    pub source_port: String,
+    #[prost(string, tag = "2")]
+    pub source_channel: String,
+    #[prost(message, optional, tag = "3")]
+    pub token: Option<SdkCoin>,
+    #[prost(string, tag = "4")]
+    pub sender: String,
+    #[prost(string, tag = "5")]
+    pub receiver: String,
+    #[prost(message, optional, tag = "6")]
+    pub timeout_height: Option<Height>,
+    #[prost(uint64, tag = "7")]
+    pub timeout_timestamp: u64,
+    #[prost(string, tag = "8")]
+    pub memo: String,
+}
+
+/// Message carrying `payload` off the chain through `publisher`'s bridge, paying its fee in `denom`
+pub fn rate_publication_msg(
+    publisher: &RatePublisher,
+    sender: String,
+    denom: &str,
+    payload: &RatePayload,
+    time: Timestamp,
+    height: u64,
+) -> StdResult<CosmosMsg> {
+    match &publisher.protocol {
+        BridgeProtocol::Wormhole { core_bridge } => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: core_bridge.clone(),
+            msg: to_binary(&WormholeExecuteMsg::PostMessage {
+                message: Binary(payload.abi_encode()),
+                nonce: height as u32,
+            })?,
+            funds: if publisher.fee.is_zero() {
+                vec![]
+            } else {
+                vec![Coin::new(publisher.fee.u128(), denom)]
+            },
+        })),
+        BridgeProtocol::Axelar {
+            channel,
+            destination_chain,
+            destination_address,
+        } => {
+            let memo = AxelarGmpMemo {
+                destination_chain: destination_chain.clone(),
+                destination_address: destination_address.clone(),
+                payload: payload.abi_encode(),
+                ty: 1,
+                fee: Some(AxelarGmpFee {
+                    amount: publisher.fee.to_string(),
+                    recipient: AXELAR_GAS_SERVICE_ACCOUNT.to_string(),
+                }),
+            };
+            proto_encode(
+                MsgTransfer {
+                    source_port: "transfer".to_string(),
+                    source_channel: channel.clone(),
+                    token: Some(SdkCoin {
+                        denom: denom.to_string(),
+                        amount: publisher.fee.to_string(),
+                    }),
+                    sender,
+                    receiver: AXELAR_GMP_ACCOUNT.to_string(),
+                    timeout_height: None,
+                    timeout_timestamp: time.plus_seconds(AXELAR_TRANSFER_TIMEOUT).nanos(),
+                    memo: String::from_utf8(to_vec(&memo)?)
+                        .map_err(|err| StdError::generic_err(err.to_string()))?,
+                },
+                MSG_TRANSFER_TYPE_URL.to_string(),
+            )
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use cosmwasm_std::{Coin, StdError, StdResult};
+use cosmwasm_std::{Coin, StdError, StdResult, Uint128};
 
 use crate::helpers::parse_coin;
 
@@ -56,4 +56,49 @@ impl Coins {
             .find(|coin| coin.denom == denom)
             .unwrap_or_else(|| Coin::new(0, denom))
     }
+
+    /// Amount of `denom` held; zero if there is none
+    pub fn get(&self, denom: &str) -> Uint128 {
+        self.find(denom).amount
+    }
+
+    /// Take `coin_to_sub` out, failing if less than its amount is held. A denom that runs out is
+    /// removed, so that the list keeps matching what the bank module would report
+    pub fn sub(&mut self, coin_to_sub: &Coin) -> StdResult<()> {
+        let amount = self
+            .get(&coin_to_sub.denom)
+            .checked_sub(coin_to_sub.amount)?;
+        self.set(&coin_to_sub.denom, amount);
+        Ok(())
+    }
+
+    /// Take `coin_to_sub` out, or as much of it as is held
+    pub fn saturating_sub(&mut self, coin_to_sub: &Coin) {
+        let amount = self
+            .get(&coin_to_sub.denom)
+            .saturating_sub(coin_to_sub.amount);
+        self.set(&coin_to_sub.denom, amount);
+    }
+
+    pub fn saturating_sub_many(&mut self, coins_to_sub: &Coins) {
+        for coin_to_sub in &coins_to_sub.0 {
+            self.saturating_sub(coin_to_sub);
+        }
+    }
+
+    /// Whether at least as much of every denom in `other` is held
+    pub fn is_superset_of(&self, other: &Coins) -> bool {
+        other
+            .0
+            .iter()
+            .all(|coin| self.get(&coin.denom) >= coin.amount)
+    }
+
+    fn set(&mut self, denom: &str, amount: Uint128) {
+        if amount.is_zero() {
+            self.0.retain(|coin| coin.denom != denom);
+        } else if let Some(coin) = self.0.iter_mut().find(|coin| coin.denom == denom) {
+            coin.amount = amount;
+        }
+    }
 }
@@ -0,0 +1,33 @@
+use cosmwasm_std::{to_binary, Coin, CosmosMsg, StdResult, WasmMsg};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use pfc_steak::hub::FeeSplitInterface;
+
+/// `ExecuteMsg` of the planned fee-split interface, where deposits no longer carry a `flush` flag
+/// and the fee-split contract decides by itself when to distribute
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeSplitV2ExecuteMsg {
+    Deposit {},
+}
+
+/// Message depositing `funds` into the fee-split contract at `contract_addr`, in whichever
+/// interface that contract speaks
+pub fn fee_split_deposit_msg(
+    interface: FeeSplitInterface,
+    contract_addr: String,
+    funds: Vec<Coin>,
+) -> StdResult<CosmosMsg> {
+    let msg = match interface {
+        FeeSplitInterface::V1 => {
+            to_binary(&pfc_fee_split::fee_split_msg::ExecuteMsg::Deposit { flush: false })?
+        }
+        FeeSplitInterface::V2 => to_binary(&FeeSplitV2ExecuteMsg::Deposit {})?,
+    };
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr,
+        msg,
+        funds,
+    }))
+}
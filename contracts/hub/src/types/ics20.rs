@@ -0,0 +1,16 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Message expected by the cw20-ics20 contract in a `Cw20ExecuteMsg::Send`, transferring the sent
+/// tokens to `remote_address` over `channel`
+///
+/// https://github.com/CosmWasm/cw-plus/blob/v0.13.4/contracts/cw20-ics20/src/msg.rs#L43-L52
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Ics20TransferMsg {
+    /// The local channel to send the packets on
+    pub channel: String,
+    /// The remote address to send to
+    pub remote_address: String,
+    /// How long the packet lives in seconds. If not specified, the contract's default is used
+    pub timeout: Option<u64>,
+}
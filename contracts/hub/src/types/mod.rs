@@ -1,7 +1,31 @@
+mod bond_window;
+mod bridge;
 mod coins;
+mod fee_split;
+mod ics20;
 mod keys;
+mod pair;
+mod referral;
+mod reply;
 mod staking;
+mod swap;
+mod token_factory;
+mod vesting;
 
+pub use bond_window::BondWindow;
+pub use bridge::{
+    rate_publication_msg, AxelarGmpFee, AxelarGmpMemo, MsgTransfer, RatePayload, WormholeExecuteMsg,
+};
 pub use coins::Coins;
+pub use fee_split::{fee_split_deposit_msg, FeeSplitV2ExecuteMsg};
+pub use ics20::Ics20TransferMsg;
 pub use keys::BooleanKey;
-pub use staking::{Delegation, Redelegation, RewardWithdrawal, Undelegation};
+pub use pair::{provide_liquidity_msg, Asset, AssetInfo, PairExecuteMsg};
+pub use referral::Referrer;
+pub use reply::ReplyContext;
+pub use staking::{
+    Delegation, Redelegation, RewardWithdrawal, SharesRedemption, ToCosmosMsg, Undelegation,
+};
+pub use swap::swap_exact_amount_in_msg;
+pub use token_factory::{burn_msg, create_denom_msg, mint_msg, token_factory_denom};
+pub use vesting::VestingTranche;
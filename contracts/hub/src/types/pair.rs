@@ -0,0 +1,55 @@
+use cosmwasm_std::{to_binary, Coin, CosmosMsg, Decimal, StdResult, Uint128, WasmMsg};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// An asset as Astroport and Terraswap style pair contracts describe it
+///
+/// https://github.com/astroport-fi/astroport-core/blob/main/packages/astroport/src/asset.rs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetInfo {
+    Token { contract_addr: String },
+    NativeToken { denom: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Asset {
+    pub info: AssetInfo,
+    pub amount: Uint128,
+}
+
+/// The part of a pair contract's `ExecuteMsg` the treasury uses
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PairExecuteMsg {
+    ProvideLiquidity {
+        assets: Vec<Asset>,
+        slippage_tolerance: Option<Decimal>,
+    },
+}
+
+/// Message depositing `assets` into the pair at `contract_addr`. Native assets are sent along;
+/// the pair must already be allowed to take CW20 ones
+pub fn provide_liquidity_msg(
+    contract_addr: String,
+    assets: Vec<Asset>,
+    slippage_tolerance: Decimal,
+) -> StdResult<CosmosMsg> {
+    let mut funds = assets
+        .iter()
+        .filter_map(|asset| match &asset.info {
+            AssetInfo::NativeToken { denom } => Some(Coin::new(asset.amount.u128(), denom)),
+            AssetInfo::Token { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    funds.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr,
+        msg: to_binary(&PairExecuteMsg::ProvideLiquidity {
+            assets,
+            slippage_tolerance: Some(slippage_tolerance),
+        })?,
+        funds,
+    }))
+}
@@ -0,0 +1,26 @@
+use cosmwasm_std::{Decimal, Uint128};
+use serde::{Deserialize, Serialize};
+
+/// Referral record of an address. Kickbacks accrue to all referrers at once through a global
+/// index, in proportion to their volume, and are credited to each referrer when it is settled
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Referrer {
+    /// Native Token bonded with this referrer
+    pub volume: Uint128,
+    /// Value of the global kickback index at the last settlement
+    pub index: Decimal,
+    /// Kickbacks credited so far, claimed or not
+    pub earned: Uint128,
+    /// Kickbacks credited but not claimed yet
+    pub unclaimed: Uint128,
+}
+
+impl Referrer {
+    /// Credit the kickbacks accrued since the last settlement, given the current global index
+    pub fn settle(&mut self, index: Decimal) {
+        let accrued = self.volume * (index - self.index);
+        self.earned += accrued;
+        self.unclaimed += accrued;
+        self.index = index;
+    }
+}
@@ -0,0 +1,30 @@
+use cosmwasm_std::{Addr, Uint128};
+use serde::{Deserialize, Serialize};
+
+/// What a reply is for, stored under the reply ID it was registered with
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyContext {
+    /// The Steak token was instantiated; its address is to be saved
+    InstantiateToken,
+    /// Coins may have been received, e.g. rewards withdrawn by a (re/un)delegation
+    RegisterReceivedCoins,
+    /// Batch `batch_id` was undelegated from `validator`; the completion time reported by the
+    /// staking module is to be recorded, and coins may have been received
+    Undelegate { batch_id: u64, validator: String },
+    /// One of the actions sent by `SudoMsg::Tick` finished, successfully or not
+    Tick { action: String },
+    /// The restaking adapter was notified of a change to the bonded amount, successfully or not
+    RestakingHook,
+    /// Tokenized shares were redeemed into a delegation to `validator`, which stood at
+    /// `delegation_before`; Steak is to be minted to `receiver` for the difference
+    RedeemTokenizedShares {
+        receiver: Addr,
+        validator: String,
+        delegation_before: Uint128,
+    },
+    /// `amount` of Native Token was deposited to the fee-split contract, successfully or not
+    FeeSplitDeposit { amount: Uint128 },
+    /// `contract` was notified of an event through a registered hook, successfully or not
+    Hook { contract: Addr },
+}
@@ -1,27 +1,28 @@
+use cosmos_sdk_proto::cosmos::authz::v1beta1::MsgExec;
 use cosmos_sdk_proto::cosmos::distribution::v1beta1::MsgWithdrawDelegatorReward;
 use cosmos_sdk_proto::cosmos::staking::v1beta1::{MsgBeginRedelegate, MsgDelegate};
 use cosmos_sdk_proto::cosmos::{base::v1beta1::Coin as SdkCoin, staking::v1beta1::MsgUndelegate};
-use cosmwasm_std::{CosmosMsg, StdResult};
+use cosmwasm_std::{CosmosMsg, StdError, StdResult};
+use pfc_steak::hub::StakingProtoUrls;
+use prost_types::Any;
 
-#[derive(Clone)]
-#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
-pub struct Delegation {
-    pub validator: String,
-    pub amount: u128,
-    pub denom: String,
-}
-// "/liquidstaking.staking.v1beta1.MsgDelegate"
+pub use pfc_steak::planning::{Delegation, Redelegation, Undelegation};
 
-impl Delegation {
-    pub fn new(validator: &str, amount: u128, denom: &str) -> Self {
-        Self {
-            validator: validator.to_string(),
-            amount,
-            denom: denom.to_string(),
-        }
-    }
+/// A move planned by `pfc_steak::planning`, as the staking module message that carries it out
+pub trait ToCosmosMsg {
+    fn to_cosmos_msg(
+        &self,
+        delegator_address: String,
+        urls: &StakingProtoUrls,
+    ) -> StdResult<CosmosMsg>;
+}
 
-    pub fn to_cosmos_msg(&self, delegator_address: String) -> StdResult<CosmosMsg> {
+impl ToCosmosMsg for Delegation {
+    fn to_cosmos_msg(
+        &self,
+        delegator_address: String,
+        urls: &StakingProtoUrls,
+    ) -> StdResult<CosmosMsg> {
         crate::helpers::proto_encode(
             MsgDelegate {
                 amount: Some(SdkCoin {
@@ -31,29 +32,17 @@ impl Delegation {
                 delegator_address,
                 validator_address: self.validator.clone(),
             },
-            "/liquidstaking.staking.v1beta1.MsgDelegate".to_string(),
+            urls.delegate.clone(),
         )
     }
 }
 
-#[derive(Clone)]
-#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
-pub struct Undelegation {
-    pub validator: String,
-    pub amount: u128,
-    pub denom: String,
-}
-
-impl Undelegation {
-    pub fn new(validator: &str, amount: u128, denom: &str) -> Self {
-        Self {
-            validator: validator.to_string(),
-            amount,
-            denom: denom.to_string(),
-        }
-    }
-
-    pub fn to_cosmos_msg(&self, delegator_address: String) -> StdResult<CosmosMsg> {
+impl ToCosmosMsg for Undelegation {
+    fn to_cosmos_msg(
+        &self,
+        delegator_address: String,
+        urls: &StakingProtoUrls,
+    ) -> StdResult<CosmosMsg> {
         crate::helpers::proto_encode(
             MsgUndelegate {
                 amount: Some(SdkCoin {
@@ -63,31 +52,17 @@ impl Undelegation {
                 delegator_address,
                 validator_address: self.validator.clone(),
             },
-            "/liquidstaking.staking.v1beta1.MsgUndelegate".to_string(),
+            urls.undelegate.clone(),
         )
     }
 }
 
-#[derive(Clone)]
-#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
-pub struct Redelegation {
-    pub src: String,
-    pub dst: String,
-    pub amount: u128,
-    pub denom: String,
-}
-
-impl Redelegation {
-    pub fn new(src: &str, dst: &str, amount: u128, denom: &str) -> Self {
-        Self {
-            src: src.to_string(),
-            dst: dst.to_string(),
-            amount,
-            denom: denom.into(),
-        }
-    }
-
-    pub fn to_cosmos_msg(&self, delegator_address: String) -> StdResult<CosmosMsg> {
+impl ToCosmosMsg for Redelegation {
+    fn to_cosmos_msg(
+        &self,
+        delegator_address: String,
+        urls: &StakingProtoUrls,
+    ) -> StdResult<CosmosMsg> {
         crate::helpers::proto_encode(
             MsgBeginRedelegate {
                 amount: Some(SdkCoin {
@@ -98,7 +73,7 @@ impl Redelegation {
                 validator_src_address: self.src.clone(),
                 validator_dst_address: self.dst.clone(),
             },
-            "/liquidstaking.staking.v1beta1.MsgBeginRedelegate".to_string(),
+            urls.redelegate.clone(),
         )
     }
 }
@@ -116,13 +91,89 @@ impl RewardWithdrawal {
         }
     }
 
-    pub fn to_cosmos_msg(&self, delegator_address: String) -> StdResult<CosmosMsg> {
+    pub fn to_cosmos_msg(
+        &self,
+        delegator_address: String,
+        urls: &StakingProtoUrls,
+    ) -> StdResult<CosmosMsg> {
         crate::helpers::proto_encode(
             MsgWithdrawDelegatorReward {
                 delegator_address,
                 validator_address: self.validator.clone(),
             },
-            "/liquidstaking.distribution.v1beta1.MsgWithdrawDelegatorReward".to_string(),
+            urls.withdraw_rewards.clone(),
+        )
+    }
+
+    /// Withdraw the rewards of `granter`'s delegation, as `grantee` under an authz grant
+    pub fn to_authz_exec_msg(
+        &self,
+        granter: String,
+        grantee: String,
+        urls: &StakingProtoUrls,
+    ) -> StdResult<CosmosMsg> {
+        let mut value = Vec::new();
+        prost::Message::encode(
+            &MsgWithdrawDelegatorReward {
+                delegator_address: granter,
+                validator_address: self.validator.clone(),
+            },
+            &mut value,
+        )
+        .map_err(|_e| StdError::generic_err("Message encoding must be infallible"))?;
+
+        crate::helpers::proto_encode(
+            MsgExec {
+                grantee,
+                msgs: vec![Any {
+                    type_url: urls.withdraw_rewards.clone(),
+                    value,
+                }],
+            },
+            urls.authz_exec.clone(),
+        )
+    }
+}
+
+/// `MsgRedeemTokensForShares` of the liquid staking module, which `cosmos-sdk-proto` doesn't have
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgRedeemTokensForShares {
+    #[prost(string, tag = "1")]
+    pub delegator_address: String,
+    #[prost(message, optional, tag = "2")]
+    pub amount: Option<SdkCoin>,
+}
+
+/// Share tokens of a tokenization record, to be redeemed into a delegation from the hub
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub struct SharesRedemption {
+    pub amount: u128,
+    pub denom: String,
+}
+
+impl SharesRedemption {
+    pub fn new(amount: u128, denom: &str) -> Self {
+        Self {
+            amount,
+            denom: denom.to_string(),
+        }
+    }
+
+    pub fn to_cosmos_msg(
+        &self,
+        delegator_address: String,
+        urls: &StakingProtoUrls,
+    ) -> StdResult<CosmosMsg> {
+        crate::helpers::proto_encode(
+            MsgRedeemTokensForShares {
+                delegator_address,
+                amount: Some(SdkCoin {
+                    denom: self.denom.clone(),
+                    amount: self.amount.to_string(),
+                }),
+            },
+            urls.redeem_tokens.clone(),
         )
     }
 }
@@ -0,0 +1,56 @@
+use cosmos_sdk_proto::cosmos::base::v1beta1::Coin as SdkCoin;
+use cosmwasm_std::{Coin, CosmosMsg, StdResult, Uint128};
+
+use pfc_steak::hub::SwapHop;
+
+use crate::helpers::proto_encode;
+
+pub const MSG_SWAP_EXACT_AMOUNT_IN_TYPE_URL: &str =
+    "/osmosis.poolmanager.v1beta1.MsgSwapExactAmountIn";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SwapAmountInRoute {
+    #[prost(uint64, tag = "1")]
+    pub pool_id: u64,
+    #[prost(string, tag = "2")]
+    pub token_out_denom: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgSwapExactAmountIn {
+    #[prost(string, tag = "1")]
+    pub sender: String,
+    #[prost(message, repeated, tag = "2")]
+    pub routes: Vec<SwapAmountInRoute>,
+    #[prost(message, optional, tag = "3")]
+    pub token_in: Option<SdkCoin>,
+    #[prost(string, tag = "4")]
+    pub token_out_min_amount: String,
+}
+
+/// Swap all of `token_in` along `hops`, failing if less than `token_out_min_amount` comes out
+pub fn swap_exact_amount_in_msg(
+    sender: String,
+    hops: &[SwapHop],
+    token_in: &Coin,
+    token_out_min_amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    proto_encode(
+        MsgSwapExactAmountIn {
+            sender,
+            routes: hops
+                .iter()
+                .map(|hop| SwapAmountInRoute {
+                    pool_id: hop.pool_id,
+                    token_out_denom: hop.token_out_denom.clone(),
+                })
+                .collect(),
+            token_in: Some(SdkCoin {
+                denom: token_in.denom.clone(),
+                amount: token_in.amount.to_string(),
+            }),
+            token_out_min_amount: token_out_min_amount.to_string(),
+        },
+        MSG_SWAP_EXACT_AMOUNT_IN_TYPE_URL.to_string(),
+    )
+}
@@ -0,0 +1,72 @@
+use cosmos_sdk_proto::cosmos::base::v1beta1::Coin as SdkCoin;
+use cosmwasm_std::{CosmosMsg, StdResult, Uint128};
+
+use crate::helpers::proto_encode;
+
+pub const MSG_CREATE_DENOM_TYPE_URL: &str = "/osmosis.tokenfactory.v1beta1.MsgCreateDenom";
+pub const MSG_MINT_TYPE_URL: &str = "/osmosis.tokenfactory.v1beta1.MsgMint";
+pub const MSG_BURN_TYPE_URL: &str = "/osmosis.tokenfactory.v1beta1.MsgBurn";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgCreateDenom {
+    #[prost(string, tag = "1")]
+    pub sender: String,
+    #[prost(string, tag = "2")]
+    pub subdenom: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgMint {
+    #[prost(string, tag = "1")]
+    pub sender: String,
+    #[prost(message, optional, tag = "2")]
+    pub amount: Option<SdkCoin>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MsgBurn {
+    #[prost(string, tag = "1")]
+    pub sender: String,
+    #[prost(message, optional, tag = "2")]
+    pub amount: Option<SdkCoin>,
+}
+
+/// Denom of the token factory token `creator` gets for `subdenom`
+pub fn token_factory_denom(creator: &str, subdenom: &str) -> String {
+    format!("factory/{}/{}", creator, subdenom)
+}
+
+pub fn create_denom_msg(sender: String, subdenom: String) -> StdResult<CosmosMsg> {
+    proto_encode(
+        MsgCreateDenom { sender, subdenom },
+        MSG_CREATE_DENOM_TYPE_URL.to_string(),
+    )
+}
+
+/// Mint `amount` of `denom` to `sender`, which must be the denom's admin
+pub fn mint_msg(sender: String, denom: &str, amount: Uint128) -> StdResult<CosmosMsg> {
+    proto_encode(
+        MsgMint {
+            sender,
+            amount: Some(SdkCoin {
+                denom: denom.to_string(),
+                amount: amount.to_string(),
+            }),
+        },
+        MSG_MINT_TYPE_URL.to_string(),
+    )
+}
+
+/// Burn `amount` of `denom` from the balance of `sender`, which must be the denom's admin
+pub fn burn_msg(sender: String, denom: &str, amount: Uint128) -> StdResult<CosmosMsg> {
+    proto_encode(
+        MsgBurn {
+            sender,
+            amount: Some(SdkCoin {
+                denom: denom.to_string(),
+                amount: amount.to_string(),
+            }),
+        },
+        MSG_BURN_TYPE_URL.to_string(),
+    )
+}
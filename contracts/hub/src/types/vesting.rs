@@ -0,0 +1,37 @@
+use cosmwasm_std::Uint128;
+use serde::{Deserialize, Serialize};
+
+/// Steak taken as a reinvest fee, vesting to the fee account of the time. The schedule is copied
+/// from the config when the fee is taken, so changing it doesn't affect fees already vesting
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VestingTranche {
+    /// Steak minted for the fee
+    pub amount: Uint128,
+    /// Steak claimed so far
+    pub claimed: Uint128,
+    /// Time the fee was taken, in seconds
+    pub start: u64,
+    /// Time before which nothing can be claimed, in seconds
+    pub cliff_end: u64,
+    /// Time from which everything can be claimed, in seconds
+    pub end: u64,
+}
+
+impl VestingTranche {
+    /// Steak vested at `time`, claimed or not
+    pub fn vested(&self, time: u64) -> Uint128 {
+        if time < self.cliff_end {
+            Uint128::zero()
+        } else if time >= self.end {
+            self.amount
+        } else {
+            self.amount
+                .multiply_ratio(time - self.start, self.end - self.start)
+        }
+    }
+
+    /// Steak vested at `time` that has not been claimed yet
+    pub fn claimable(&self, time: u64) -> Uint128 {
+        self.vested(time).saturating_sub(self.claimed)
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::{Context, Ok, Result};
 use async_std::io::ReadExt;
 use futures::future;
-use pfc_steak::hub::MinerParamsResponse;
+use pfc_steak_api::hub::{MinerParamsResponse, MiningPowerNormalization};
 use rand::distributions::Alphanumeric;
 use rand::Rng;
 use rayon::prelude::*;
@@ -28,6 +28,9 @@ async fn main() {
             entropy: "0x0000000000000000000000000000000000000000000000000000000000000000"
                 .to_string(),
             difficulty: 0_u64.into(),
+            enabled: true,
+            proof_cooldown_blocks: 0,
+            power_normalization: MiningPowerNormalization::Disabled,
         },
         tx_in_flight: false,
         miner_params_loaded: false,
@@ -91,7 +94,7 @@ async fn main() {
 }
 
 pub fn poll_miner_params() -> Result<MinerParamsResponse> {
-    let miner_params_query = pfc_steak::hub::QueryMsg::MinerParams {};
+    let miner_params_query = pfc_steak_api::hub::QueryMsg::MinerParams {};
     let joed_cosmwasm_query = Command::new("joed")
         .arg("q")
         .arg("wasm")
@@ -126,7 +129,7 @@ pub fn poll_miner_params() -> Result<MinerParamsResponse> {
         "stdout: {}",
         String::from_utf8_lossy(&joed_cosmwasm_query.stdout)
     );
-    let query_response: pfc_steak::hub::MinerParamsResponse = serde_json::from_value(
+    let query_response: pfc_steak_api::hub::MinerParamsResponse = serde_json::from_value(
         parsed_json
             .get("data")
             .context("getting data field from miner params json")?
@@ -145,7 +148,7 @@ pub fn submit_proof(proof: MinedProof) -> Result<()> {
         .arg("execute")
         .arg(get_contract_address())
         .arg(
-            serde_json::to_string(&pfc_steak::hub::ExecuteMsg::SubmitProof {
+            serde_json::to_string(&pfc_steak_api::hub::ExecuteMsg::SubmitProof {
                 nonce: proof.nonce.into(),
                 validator: get_validator_address(),
             })
@@ -202,7 +205,7 @@ pub fn update_entropy() -> Result<String, anyhow::Error> {
         .arg("execute")
         .arg(get_contract_address())
         .arg(
-            serde_json::to_string(&pfc_steak::hub::ExecuteMsg::UpdateEntropy {
+            serde_json::to_string(&pfc_steak_api::hub::ExecuteMsg::UpdateEntropy {
                 entropy: entropy.clone(),
             })
             .context("serializing UpdateEntropy message")?,
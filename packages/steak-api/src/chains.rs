@@ -0,0 +1,103 @@
+//! Settings of the chains the hub is commonly deployed to. Passing a preset in
+//! `InstantiateMsg::chain` makes the hub check the denom, unbonding time and validator addresses
+//! it is instantiated with against them, and talk to the chain's staking module through the type
+//! URLs it uses.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::hub::StakingProtoUrls;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainPreset {
+    Juno,
+    Terra,
+    Migaloo,
+}
+
+/// Gas limits that transactions sending the hub's messages typically fit in, as a starting point
+/// for keepers and deployment scripts
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GasLimits {
+    pub bond: u64,
+    pub harvest: u64,
+    pub submit_batch: u64,
+    pub reconcile: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ChainConfig {
+    /// Chain ID of the mainnet
+    pub chain_id: &'static str,
+    /// Denomination of the Native Token
+    pub denom: &'static str,
+    /// Bech32 prefix of account addresses
+    pub account_prefix: &'static str,
+    /// Bech32 prefix of validator operator addresses
+    pub valoper_prefix: &'static str,
+    /// The staking module's unbonding time, in seconds
+    pub unbond_period: u64,
+    /// Gas price, e.g. for `--gas-prices`
+    pub gas_prices: &'static str,
+    pub gas_limits: GasLimits,
+}
+
+const GAS_LIMITS: GasLimits = GasLimits {
+    bond: 400_000,
+    harvest: 1_500_000,
+    submit_batch: 1_000_000,
+    reconcile: 500_000,
+};
+
+impl ChainPreset {
+    pub fn config(self) -> ChainConfig {
+        match self {
+            ChainPreset::Juno => ChainConfig {
+                chain_id: "juno-1",
+                denom: "ujuno",
+                account_prefix: "juno",
+                valoper_prefix: "junovaloper",
+                unbond_period: 2419200, // 28 days
+                gas_prices: "0.075ujuno",
+                gas_limits: GAS_LIMITS,
+            },
+            ChainPreset::Terra => ChainConfig {
+                chain_id: "phoenix-1",
+                denom: "uluna",
+                account_prefix: "terra",
+                valoper_prefix: "terravaloper",
+                unbond_period: 1814400, // 21 days
+                gas_prices: "0.015uluna",
+                gas_limits: GAS_LIMITS,
+            },
+            ChainPreset::Migaloo => ChainConfig {
+                chain_id: "migaloo-1",
+                denom: "uwhale",
+                account_prefix: "migaloo",
+                valoper_prefix: "migaloovaloper",
+                unbond_period: 1814400, // 21 days
+                gas_prices: "1uwhale",
+                gas_limits: GAS_LIMITS,
+            },
+        }
+    }
+
+    /// These chains run the Cosmos SDK's own staking and distribution modules
+    pub fn staking_proto_urls(self) -> StakingProtoUrls {
+        StakingProtoUrls {
+            delegate: "/cosmos.staking.v1beta1.MsgDelegate".to_string(),
+            undelegate: "/cosmos.staking.v1beta1.MsgUndelegate".to_string(),
+            redelegate: "/cosmos.staking.v1beta1.MsgBeginRedelegate".to_string(),
+            withdraw_rewards: "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward".to_string(),
+            ..StakingProtoUrls::default()
+        }
+    }
+
+    /// Whether `validator` is a validator operator address on this chain
+    pub fn is_valoper_address(self, validator: &str) -> bool {
+        validator
+            .strip_prefix(self.config().valoper_prefix)
+            .map_or(false, |rest| rest.starts_with('1'))
+    }
+}
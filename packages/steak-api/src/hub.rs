@@ -0,0 +1,1955 @@
+use cosmwasm_schema::QueryResponses;
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Coin, CosmosMsg, Decimal, Empty, StdResult, Uint128, Uint64, WasmMsg,
+};
+use cw20::{Cw20ReceiveMsg, Logo};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::chains::ChainPreset;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    /// Code ID of the CW20 token contract
+    pub cw20_code_id: u64,
+    /// Account who can call certain privileged functions
+    pub owner: String,
+    /// Name of the liquid staking token
+    pub name: String,
+    /// Symbol of the liquid staking token
+    pub symbol: String,
+    /// Number of decimals of the liquid staking token
+    pub decimals: u8,
+    /// Number of decimals of the bond denom, which amounts of it are shown in. Defaults to 6
+    #[serde(default)]
+    pub denom_decimals: Option<u8>,
+    /// How often the unbonding queue is to be executed, in seconds
+    pub epoch_period: u64,
+    /// The staking module's unbonding time, in seconds
+    pub unbond_period: u64,
+    /// Initial set of validators who will receive the delegations
+    pub validators: Vec<String>,
+    /// denomination of coins to steak (uXXXX)
+    pub denom: String,
+    /// type of fee account
+    pub fee_account_type: FeeType,
+    /// Fee Account to send fees too
+    pub fee_account: String,
+    /// Fee "1.00 = 100%"
+    pub fee_amount: Decimal,
+    /// Max Fee "1.00 = 100%"
+    pub max_fee_amount: Decimal,
+    /// label for the CW20 token we create. Defaults to `steak_token`; leading and trailing
+    /// whitespace is trimmed
+    pub label: Option<String>,
+    /// Wasm admin of the CW20 token we create. Defaults to the hub, which lets the owner upgrade it
+    /// through `MigrateTokenContract`
+    #[serde(default)]
+    pub token_admin: Option<String>,
+    /// Marketing info for the CW20 we create
+    pub marketing: Option<MarketingInfo>,
+    /// Chain the hub is deployed to. The denom, unbonding time and validators are checked against
+    /// its preset, and its staking module's type URLs are used
+    #[serde(default)]
+    pub chain: Option<ChainPreset>,
+}
+
+/// Marketing info for the CW20 token, passed as is to the token contract. Mirrors the one in
+/// `cw20-base`, so the API doesn't depend on the token contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarketingInfo {
+    pub project: Option<String>,
+    pub description: Option<String>,
+    pub marketing: Option<String>,
+    pub logo: Option<Logo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    /// Implements the Cw20 receiver interface
+    Receive(Cw20ReceiveMsg),
+    /// Bond specified amount of Native Token. The amount bonded counts towards the referral volume
+    /// of `referrer`, if any. With `min_usteak_out`, the bond fails rather than mint less Steak
+    /// than that
+    Bond {
+        receiver: Option<String>,
+        referrer: Option<String>,
+        min_usteak_out: Option<Uint128>,
+    },
+    /// Bond a single deposit of Native Token, minting Steak to each receiver in proportion to the
+    /// amount of Native Token set against it. The amounts must add up to the deposit. `referrer`
+    /// and `min_usteak_out` work as for `Bond`, the latter against the Steak minted in total
+    BondMany {
+        receivers: Vec<(String, Uint128)>,
+        referrer: Option<String>,
+        min_usteak_out: Option<Uint128>,
+    },
+    /// Send the Steak token's instantiation again after it failed. Callable by the owner
+    RetryTokenInstantiate {},
+    /// Withdraw the referral kickbacks accrued to the sender
+    ClaimReferralRewards {},
+    /// Withdraw the protocol fees, taken in Steak, that have vested for the sender
+    ClaimVestedFees {},
+    /// Pay the fees held in escrow after the fee-split contract rejected them to the fee account
+    /// now configured
+    ReleaseFeeEscrow {},
+    /// Settle `amount` of the Native Token parked as received outside of bonds and rewards, e.g.
+    /// through a bank transfer to the hub (see `StateResponse::donations`). Callable by the owner
+    ClassifyFunds {
+        amount: Uint128,
+        classification: FundsClassification,
+    },
+    /// Bond Native Token that arrived through an ICS-20 transfer with an `ibc_hooks` wasm memo, and
+    /// send the minted Steak back over the IBC channel to `original_sender` on the origin chain.
+    /// Only callable by the sender `ibc_hooks` derives for `original_sender` over that channel
+    BondOnBehalf { original_sender: String },
+    /// Bond delegations tokenized through the liquid staking module: the share tokens sent along
+    /// (denom `{validator}/{record_id}`) are redeemed into a delegation from the hub, and Steak is
+    /// minted for the Native Token the delegation is worth. The validator must be active
+    BondTokenizedShares { receiver: Option<String> },
+    /// Withdraw Native Token that have finished unbonding in previous batches. With `amount` set,
+    /// only that much is withdrawn, oldest batches first, and the rest can be withdrawn later
+    WithdrawUnbonded {
+        receiver: Option<String>,
+        amount: Option<Uint128>,
+    },
+    /// Withdraw Native Token that has finished unbonding in previous batches, for given address
+    WithdrawUnbondedAdmin { address: String },
+    /// Escrow `shares_per_epoch * epochs` usteak, transferred from the sender using an allowance
+    /// granted to the hub, and queue `shares_per_epoch` of it into each of the next `epochs`
+    /// batches as they are submitted. Each user may have one order at a time, and a limited number
+    /// of orders may be open across users. `shares_per_epoch` is held to `min_unbond_shares`
+    CreateUnbondOrder {
+        shares_per_epoch: Uint128,
+        epochs: u32,
+    },
+    /// Cancel the sender's unbond order, returning the usteak still in escrow
+    CancelUnbondOrder {},
+    /// Submit an unbonding request for the usteak sent along, once the Steak token has moved to
+    /// the token factory. Holders of the cw20 token use `ReceiveMsg::QueueUnbond` instead.
+    /// With `min_native_out`, the request is held over to the next batch rather than submitted
+    /// for less Native Token than that
+    QueueUnbond {
+        receiver: Option<String>,
+        min_native_out: Option<Uint128>,
+    },
+    /// Move the Steak token off cw20. The hub stops minting the cw20 token, swaps the cw20 it holds
+    /// in escrow, and from then on mints and burns the new denom; holders swap their cw20 through
+    /// `ReceiveMsg::SwapToken` at their own pace. Callable by the owner
+    MigrateToken { new_backend: TokenBackend },
+    /// Hand the admin rights of the cw20 Steak token, which the hub holds, to `admin`. Callable by
+    /// the owner
+    UpdateTokenAdmin { admin: String },
+    /// Upgrade the cw20 Steak token contract to `new_code_id`, migrating it with `msg`. Callable by
+    /// the owner
+    MigrateTokenContract { new_code_id: u64, msg: Binary },
+    /// Add a validator to the whitelist; callable by the owner. Refused if the validator's operator
+    /// self-delegates less than `min_self_delegation`
+    AddValidator { validator: String },
+    /// Remove a validator from the whitelist; callable by the owner. Does not undelegate. use for typos
+    RemoveValidatorEx { validator: String },
+    /// Swap validator `old` for `new`, e.g. after it rotated to a new operator address: `new`
+    /// takes over `old`'s place in the whitelist, its mining power, cap and record, and the hub's
+    /// delegation is redelegated from one to the other. Callable by the owner
+    ReplaceValidator { old: String, new: String },
+
+    /// Pause a validator from accepting new delegations
+    PauseValidator { validator: String },
+    /// Unpause a validator from accepting new delegations
+    UnPauseValidator { validator: String },
+    /// Pause a validator until the block time `until`, in seconds, e.g. over a known maintenance
+    /// window. It re-enters the active set on its own afterwards; unpausing it lifts the suspension
+    /// early. Callable by the owner
+    SuspendValidator { validator: String, until: u64 },
+    /// Clear the slashing incidents of a validator and let it receive new delegations again, e.g.
+    /// after it was put in the penalty box. Callable by the owner
+    ReinstateValidator { validator: String },
+    /// Set the number of slashing incidents after which a validator is deactivated automatically;
+    /// `None` never deactivates. Callable by the owner
+    SetSlashLimit { slash_limit: Option<u32> },
+    /// Limit the amount of native delegated to a validator; `None` removes the limit. Reinvesting
+    /// skips validators at their cap, and rebalancing moves any excess away. Callable by the owner
+    SetValidatorCap {
+        validator: String,
+        cap: Option<Uint128>,
+    },
+
+    /// Transfer ownership to another account; will not take effect unless the new owner accepts
+    TransferOwnership { new_owner: String },
+    /// Accept an ownership transfer
+    AcceptOwnership {},
+    /// Propose a config change, to be applied once accepted by the owner in a separate transaction
+    /// after the timelock's delay. Changing the fee, the fee account and removing validators only
+    /// go through here. Callable by the owner
+    ProposeChange { change: ConfigChange },
+    /// Apply a proposed change whose delay has passed and that has not expired yet. Callable by
+    /// the owner
+    AcceptChange { id: u64 },
+    /// Discard a proposed change. Callable by the owner
+    CancelChange { id: u64 },
+    /// Claim staking rewards, swap all for Native Token, and restake
+    Harvest {},
+    /// Fund a reward campaign with the Native Token sent along. It is delegated for the benefit of
+    /// all holders, a share at each harvest, in proportion to how much of the `start` to `end`
+    /// window (in seconds) has gone by
+    FundRewardCampaign { start: u64, end: u64 },
+    /// Swap the `denom` rewards held in `unlocked_coins` for Native Token through the chain's swap
+    /// module, along the route set for it. The next reinvest delegates the output. The swap gets
+    /// at least the route's `min_rate`, or `min_out` if that is higher
+    SwapRewards {
+        denom: String,
+        min_out: Option<Uint128>,
+    },
+    /// Use redelegations to balance the amounts of Native Token delegated to validators. Validators
+    /// no further than `min_redelegation` from their target, or `minimum` if that is higher, are
+    /// left as they are. Large
+    /// plans are sent in chunks; the remaining redelegations are sent by `ContinueRebalance`
+    Rebalance { minimum: Option<Uint128> },
+    /// Send the next chunk of redelegations left over by the last `Rebalance`
+    ContinueRebalance {},
+    /// Update Native Token amounts in unbonding batches to reflect any slashing or rounding errors
+    Reconcile {},
+    /// Submit the current pending batch of unbonding requests to be unbonded. Validators in
+    /// `exclude`, e.g. ones at their unbonding entry limit or under maintenance, are left out of
+    /// the undelegations, up to the number set by `SetMaxBatchExclusions`
+    SubmitBatch {
+        #[serde(default)]
+        exclude: Vec<String>,
+    },
+    /// Set unbond period
+    SetUnbondPeriod { unbond_period: u64 },
+    /// Set the minimum amount of harvested native (after fees) worth delegating; smaller amounts
+    /// are carried over to the next reinvest
+    SetMinReinvestAmount { min_reinvest_amount: Uint128 },
+    /// Set the smallest deposit `Bond` accepts. Callable by the owner
+    SetMinBondAmount { min_bond_amount: Uint128 },
+    /// Set the most messages a single execute may send, so that no operation grows past the block
+    /// gas limit; `None` removes the cap. Callable by the owner
+    SetMaxMessages { max_messages: Option<u32> },
+    /// Set how far from its target a validator's delegation may be before `Rebalance` moves it.
+    /// Callable by the owner
+    SetMinRedelegation { min_redelegation: Uint128 },
+    /// Set the largest increase of the bonded amount a single reinvest may make, in basis points;
+    /// larger rewards are bonded over several reinvests. `None` removes the limit. Callable by the
+    /// owner
+    SetMaxRebaseBps { max_rebase_bps: Option<u64> },
+    /// Set how long after the last harvest, in seconds, `Bond` and `QueueUnbond` are still
+    /// accepted; past it they are refused until rewards are harvested, as the exchange rate
+    /// understates the stake. `None` removes the limit. Callable by the owner
+    SetMaxHarvestStaleness { max_harvest_staleness: Option<u64> },
+    /// Set how long after its estimated start time, in seconds, anyone may submit the pending
+    /// batch; past it only the guardian may. `None` lets anyone submit at any time. Callable by
+    /// the owner
+    SetSubmitDeadlineBuffer { submit_deadline_buffer: Option<u64> },
+    /// Harvest the rewards of `granter`'s delegations instead of the hub's own, through an authz
+    /// grant of `MsgWithdrawDelegatorReward` to the hub, for when the hub stakes through an
+    /// intermediate account. The granter's withdraw address must be the hub. `None` goes back to
+    /// harvesting the hub's own delegations. Callable by the owner
+    SetHarvestGranter { granter: Option<String> },
+    /// Set the share of the protocol fee paid back to referrers, in basis points. Callable by the
+    /// owner
+    SetReferralBps { referral_bps: u64 },
+    /// Take the reinvest fee in Steak, vesting to the fee account over a schedule, rather than in
+    /// Native Token paid out right away. `None` goes back to paying out Native Token. Callable by
+    /// the owner
+    SetFeeVesting { fee_vesting: Option<FeeVesting> },
+    /// Exempt holders, e.g. protocol-owned liquidity or the DAO treasury, from the protocol fee on
+    /// the rewards their Steak earns, or end their exemption. The fee on their share is delegated
+    /// along with the rest of the rewards and minted to them as Steak; everyone else pays the full
+    /// fee. Callable by the owner
+    UpdateFeeExemptions {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Set the smallest amount of Steak `QueueUnbond` accepts. Callable by the owner
+    SetMinUnbondShares { min_unbond_shares: Uint128 },
+    /// Hold pending batches with too little Steak over to the next epoch instead of submitting
+    /// them; `None` always submits. Callable by the owner
+    SetBatchRollover {
+        batch_rollover: Option<BatchRollover>,
+    },
+    /// Set the accounts, e.g. an insurance fund, whose unbonding requests for themselves skip
+    /// `min_unbond_shares` and are never held over by `SetBatchRollover`, so that they go out in
+    /// the earliest batch. Replaces the current list. Callable by the owner
+    SetPriorityUnbonders { addresses: Vec<String> },
+    /// Notify a restaking adapter contract whenever the bonded amount changes, letting it restake
+    /// part of the hub's stake to secure other services; `None` opts out. Callable by the owner
+    SetRestakingAdapter { adapter: Option<RestakingAdapter> },
+    /// Report how much of the hub's stake is currently restaked. Callable by the restaking adapter
+    ReportRestaked { amount: Uint128 },
+    /// Notify `contract` with a `SteakHookMsg` whenever an event of `event_kind` happens. A
+    /// contract failing to handle it doesn't hold up the hub. Callable by the owner
+    RegisterHook {
+        event_kind: HookEventKind,
+        contract: String,
+    },
+    /// Stop notifying `contract` of events of `event_kind`. Callable by the owner
+    DeregisterHook {
+        event_kind: HookEventKind,
+        contract: String,
+    },
+    /// Credit bonders with the increase of the contract's balance rather than the amount in
+    /// `info.funds`, for denoms where a transfer may be taxed on the way in. Callable by the owner
+    SetMeasureDeposits { enabled: bool },
+    /// Set the least Native Token unbonding leaves delegated to a validator, for chains where a
+    /// validator with too little stake drops out of the active set. What would take a validator
+    /// below it is unbonded from the others instead. Callable by the owner
+    SetMinDelegationPerValidator { min_delegation: Uint128 },
+    /// Limit how much Native Token may be bonded for a single receiver per window; `None` lifts the
+    /// limit. Callable by the owner
+    SetBondRateLimit { limit: Option<BondRateLimit> },
+    /// Set the pending rewards, in Native Token, a validator must have above for a harvest to
+    /// withdraw from it. Only applies on chains allowing the distribution module's rewards query.
+    /// Callable by the owner
+    SetMinHarvestReward { min_reward: Uint128 },
+    /// Set the least Native Token a validator's operator must delegate to it for it to be added to
+    /// the whitelist and stay active; zero means no requirement. Callable by the owner
+    SetMinSelfDelegation { min_self_delegation: Uint128 },
+    /// Stop delegating to the active validators whose operators self-delegate less than
+    /// `min_self_delegation`, as `PauseValidator` would. Callable by anyone
+    CheckValidators {},
+    /// Set how many validators `SubmitBatch` may be asked to leave out of a batch. Callable by the
+    /// owner
+    SetMaxBatchExclusions { max_exclusions: u32 },
+    /// Set the denoms of liquid staking derivatives, e.g. of other hubs, that `Bond` refuses with a
+    /// dedicated error. The hub's own Steak is always refused. Callable by the owner
+    SetForbiddenDepositDenoms { denoms: Vec<String> },
+    /// Hand the Steak token's wasm admin role to `admin`. Only possible while the hub holds it, and
+    /// `MigrateTokenContract` stops working once it doesn't. Callable by the owner
+    SetTokenAdmin { admin: String },
+    /// Take in Native Token delegated to a validator in the hub's name from outside the hub, e.g. a
+    /// community pool spend delegated directly, minting the matching Steak to the owner at the rate
+    /// excluding it. Refused if the hub's delegation to the validator doesn't cover `amount`, or if
+    /// leaving it out would put the exchange rate below the last one observed. Callable by the owner
+    AbsorbDelegation { validator: String, amount: Uint128 },
+    /// Set the swap module route converting `denom` rewards to Native Token; `None` removes it.
+    /// Callable by the owner
+    SetSwapRoute {
+        denom: String,
+        route: Option<SwapRoute>,
+    },
+    /// Set the number of decimals of the bond denom. Callable by the owner
+    SetDenomDecimals { decimals: u8 },
+    /// Set the type URLs of the Stargate messages sent to the staking and distribution modules.
+    /// Callable by the owner
+    SetStakingProtoUrls { urls: StakingProtoUrls },
+    /// Set the cw20-ics20 contract used to send Steak back to IBC depositors, and the channel
+    /// deposits are accepted from and sent back over; `None` for both disables `BondOnBehalf`.
+    /// Callable by the owner
+    SetIcs20Contract {
+        ics20_contract: Option<String>,
+        channel: Option<String>,
+    },
+    /// Set the bridge each reinvest publishes the exchange rate and Steak supply through, for
+    /// markets of bridged Steak on other chains; `None` stops publishing. Callable by the owner
+    SetRatePublisher {
+        rate_publisher: Option<RatePublisher>,
+    },
+    /// Set the treasury building protocol-owned Steak/Native Token liquidity out of the fees;
+    /// `None` stops it, handing the Native Token it set aside to the next reinvest. Callable by
+    /// the owner
+    SetTreasury { treasury: Option<TreasuryConfig> },
+    /// Bond half of the Native Token set aside by the treasury, and deposit the Steak minted with
+    /// the other half into the treasury's pair. Callable by the owner
+    ProvideLiquidity {},
+    /// Send LP tokens held by the treasury to `recipient`. Callable by the owner
+    TransferTreasuryLp { recipient: String, amount: Uint128 },
+    /// Set which `ReceiveMsg` variants a CW20 token may be sent with; an empty list removes the
+    /// token from the registry. Callable by the owner
+    SetAcceptedToken {
+        token: String,
+        receive_msgs: Vec<ReceiveMsgKind>,
+    },
+    /// Set how the rewards received by a harvest are found. Callable by the owner
+    SetHarvestMode { mode: HarvestMode },
+    /// Set how `Reconcile` finds the batches whose unbonded Native Token has been received.
+    /// Callable by the owner
+    SetReconcileMode { mode: ReconcileMode },
+    /// Set which validators receive the remainder when an amount can't be split evenly among
+    /// them. Callable by the owner
+    SetRemainderPolicy { policy: RemainderPolicy },
+    /// Set the interface spoken by the fee-split contract, so it can be upgraded without migrating
+    /// the hub at the same time. Callable by the owner
+    SetFeeSplitInterface { interface: FeeSplitInterface },
+    /// Set the account allowed to acknowledge slashes; `None` leaves it to the owner. Callable by
+    /// the owner
+    SetGuardian { guardian: Option<String> },
+    /// Halt or resume bonding, unbonding, withdrawals, harvests and batch submissions independently
+    /// of each other. Operations left out stay enabled. Callable by the owner
+    SetCapabilities { capabilities: Capabilities },
+    /// Set by how much the exchange rate may drop between two operations before they are refused.
+    /// Callable by the owner
+    SetExchangeRateEpsilon { epsilon: Decimal },
+    /// Allow the next operation to proceed even though the exchange rate dropped by more than the
+    /// epsilon, e.g. after a validator got slashed. Naming the slashed validator records an
+    /// incident against it, which may put it in the penalty box. Callable by the guardian
+    AcknowledgeSlash { validator: Option<String> },
+
+    /// Update entropy
+    UpdateEntropy { entropy: String },
+    /// Submit mined proof
+    SubmitProof { nonce: Uint64, validator: String },
+    /// Override the mining difficulty, mix entropy into the draft that the next accepted proof
+    /// turns into the current entropy, turn the mining subsystem on or
+    /// off, set the number of blocks a miner must wait between accepted proofs, or set how mining
+    /// power is normalized at each batch submission. With mining disabled, anyone may harvest and
+    /// rewards are delegated to the validator with the smallest delegation. Callable by the owner
+    SetMiningParams {
+        difficulty: Option<Uint64>,
+        entropy: Option<String>,
+        enabled: Option<bool>,
+        proof_cooldown_blocks: Option<u64>,
+        power_normalization: Option<MiningPowerNormalization>,
+    },
+    /// Require proofs for a validator to meet `difficulty` instead of the global difficulty, e.g.
+    /// to make mining power harder to shift towards validators that already have much of the
+    /// stake. The override isn't retargeted along with the global difficulty; `None` removes it.
+    /// Callable by the owner
+    SetValidatorDifficulty {
+        validator: String,
+        difficulty: Option<Uint64>,
+    },
+    /// Callbacks; can only be invoked by the contract itself
+    Callback(CallbackMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Submit an unbonding request to the current unbonding queue; automatically invokes `unbond`
+    /// if `epoch_time` has elapsed since when the last unbonding queue was executed.
+    /// With `min_native_out`, the request is held over to the next batch rather than submitted
+    /// for less Native Token than that
+    QueueUnbond {
+        receiver: Option<String>,
+        min_native_out: Option<Uint128>,
+    },
+    /// Swap the cw20 Steak token sent along for the same amount of the token factory denom, once
+    /// the token has been migrated
+    SwapToken {},
+}
+
+/// Discriminant of `ReceiveMsg`, used to register which variants each CW20 token may be sent with
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsgKind {
+    QueueUnbond,
+    SwapToken,
+}
+
+/// How the Steak token is minted and burned
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenBackend {
+    /// A cw20 contract instantiated by the hub, which is its minter
+    Cw20,
+    /// A token factory denom created by the hub, `factory/{hub}/{subdenom}`
+    TokenFactory { subdenom: String },
+}
+
+/// How the rewards received by a harvest are found
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HarvestMode {
+    /// Parse the `coin_received` events emitted by the reward withdrawals
+    CoinReceivedEvents,
+    /// Snapshot the hub's bank balances before the withdrawals, and compare them in the `Reinvest`
+    /// callback. For chains that do not emit `coin_received` for reward withdrawals
+    BalanceDiff,
+}
+
+impl Default for HarvestMode {
+    fn default() -> Self {
+        HarvestMode::CoinReceivedEvents
+    }
+}
+
+/// What Native Token parked as received outside of bonds and rewards turns out to be
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FundsClassification {
+    /// Rewards, reinvested at the next harvest, which takes the fee
+    AsRewards,
+    /// A donation to holders, delegated right away without a fee
+    AsDonation,
+    /// Sent by mistake, and returned to `recipient`
+    Refund { recipient: String },
+}
+
+/// How `Reconcile` finds the batches whose unbonded Native Token has been received
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileMode {
+    /// Every batch past its estimated unbonding end is taken as received, and any Native Token
+    /// missing from the hub's balance is deducted from them
+    BalanceDiff,
+    /// Only batches whose undelegations no longer show among the hub's unbonding delegation entries
+    /// in the staking module are taken as received. For chains where the unbonding time may differ
+    /// from the estimate. Requires the chain to allow the Stargate query
+    UnbondingEntries,
+}
+
+impl Default for ReconcileMode {
+    fn default() -> Self {
+        ReconcileMode::BalanceDiff
+    }
+}
+
+/// What happens to the validators' mining power at each batch submission, so that it reflects
+/// recent work rather than growing forever
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MiningPowerNormalization {
+    /// Mining power keeps accumulating
+    Disabled,
+    /// Every validator's mining power is multiplied by `factor`, which is below 1
+    Scale { factor: Decimal },
+    /// Every whitelisted validator's mining power is set to `baseline`
+    Reset { baseline: Uint128 },
+}
+
+impl Default for MiningPowerNormalization {
+    fn default() -> Self {
+        MiningPowerNormalization::Disabled
+    }
+}
+
+/// Which validators receive the remainder when an amount to delegate, undelegate or redelegate
+/// can't be split evenly among them
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RemainderPolicy {
+    /// The remainder always goes to the first validators in the list
+    FirstValidators,
+    /// The remainder goes to the validators after the ones that received it in the previous
+    /// operation, so it is spread evenly over time
+    RoundRobin,
+}
+
+impl Default for RemainderPolicy {
+    fn default() -> Self {
+        RemainderPolicy::FirstValidators
+    }
+}
+
+impl ReceiveMsg {
+    pub fn kind(&self) -> ReceiveMsgKind {
+        match self {
+            ReceiveMsg::QueueUnbond { .. } => ReceiveMsgKind::QueueUnbond,
+            ReceiveMsg::SwapToken {} => ReceiveMsgKind::SwapToken,
+        }
+    }
+}
+
+/// Messages sent by the chain itself, e.g. by the x/clock or cron module
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoMsg {
+    /// Send whichever of `Reconcile`, `SubmitBatch` and `Harvest` are due. Each runs as its own
+    /// submessage, so one failing does not hold up the others; they all remain callable by anyone
+    /// in case the chain stops ticking
+    Tick {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CallbackMsg {
+    /// Following the swaps, stake the Native Token acquired to the whitelisted validators
+    Reinvest {},
+}
+
+impl CallbackMsg {
+    pub fn into_cosmos_msg(&self, contract_addr: &Addr) -> StdResult<CosmosMsg> {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&ExecuteMsg::Callback(self.clone()))?,
+            funds: vec![],
+        }))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema, QueryResponses)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    /// The contract's configurations. Response: `ConfigResponse`
+    #[returns(ConfigResponse)]
+    Config {},
+    /// The contract's current state. Response: `StateResponse`
+    #[returns(StateResponse)]
+    State {},
+    /// The current batch on unbonding requests pending submission. Response: `PendingBatch`
+    #[returns(PendingBatch)]
+    PendingBatch {},
+    /// Query an individual batch that has previously been submitted for unbonding but have not yet
+    /// fully withdrawn. Response: `Batch`
+    #[returns(Batch)]
+    PreviousBatch(u64),
+    /// Enumerate all previous batches that have previously been submitted for unbonding but have not
+    /// yet fully withdrawn. Response: `Vec<Batch>`
+    #[returns(Vec<Batch>)]
+    PreviousBatches {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Recompute what each user is entitled to from a previous batch's unbonding requests, and
+    /// check it against what the batch has left unclaimed. Meant for audits after an incident; all
+    /// requests of the batch are read at once. Response: `ReplayBatchResponse`
+    #[returns(ReplayBatchResponse)]
+    ReplayBatch { id: u64 },
+    /// Enumerate all outstanding unbonding requests in a given batch. Response: `Vec<UnbondRequestsResponseByBatchItem>`
+    #[returns(Vec<UnbondRequestsByBatchResponseItem>)]
+    UnbondRequestsByBatch {
+        id: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Enumreate all outstanding unbonding requests from given a user. Response: `Vec<UnbondRequestsByUserResponseItem>`
+    #[returns(Vec<UnbondRequestsByUserResponseItem>)]
+    UnbondRequestsByUser {
+        user: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Enumerate outstanding unbonding requests queued by a sender, whichever receivers they were
+    /// queued for. Response: `Vec<UnbondRequestsBySenderResponseItem>`
+    #[returns(Vec<UnbondRequestsBySenderResponseItem>)]
+    UnbondRequestsBySender {
+        sender: String,
+        start_after: Option<(u64, String)>,
+        limit: Option<u32>,
+    },
+    /// Enumerate the individual calls that make up a user's unbonding request in a batch.
+    /// Response: `Vec<UnbondCall>`
+    #[returns(Vec<UnbondCall>)]
+    UnbondRequestCalls {
+        id: u64,
+        user: String,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    /// The amount of native a user would receive from `WithdrawUnbonded` right now, including from
+    /// batches it would reconcile first. Response: `WithdrawableAmountResponse`
+    #[returns(WithdrawableAmountResponse)]
+    WithdrawableAmount { user: String },
+    /// The last withdrawals of unbonded native made by a user, oldest first. Response:
+    /// `Vec<RecentClaim>`
+    #[returns(Vec<RecentClaim>)]
+    RecentClaims { user: String },
+    /// When each outstanding unbonding request of a user is expected to be withdrawable, taking
+    /// the chain's actual unbonding time into account. Response: `UnbondEtaResponse`
+    #[returns(UnbondEtaResponse)]
+    UnbondEta { user: String },
+    /// The recurring unbond order of a user, if any. Response: `Option<UnbondOrder>`
+    #[returns(Option<UnbondOrder>)]
+    UnbondOrder { user: String },
+    /// Load entropy and difficulty for the current epoch. Response: `MinerParamsResponse`
+    #[returns(MinerParamsResponse)]
+    MinerParams {},
+    /// Validator Mining Powers
+    /// Response: `Vec<ValidatorMiningPower>`
+    #[returns(Vec<ValidatorMiningPower>)]
+    ValidatorMiningPowers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Everything the hub knows about a whitelisted validator, along with its commission and total
+    /// bonded tokens. Response: `ValidatorSummaryResponse`
+    #[returns(ValidatorSummaryResponse)]
+    ValidatorSummary { validator: String },
+    /// Estimated yield of delegating to a whitelisted validator, from the chain's inflation net of
+    /// the validator's commission, for comparing validators. Response: `ValidatorNetAprResponse`
+    #[returns(ValidatorNetAprResponse)]
+    ValidatorNetApr { validator: String },
+    /// Enumerate validators with a delegation cap. Response: `Vec<ValidatorCapResponse>`
+    #[returns(Vec<ValidatorCapResponse>)]
+    ValidatorCaps {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Enumerate validators with their own mining difficulty.
+    /// Response: `Vec<ValidatorDifficultyResponse>`
+    #[returns(Vec<ValidatorDifficultyResponse>)]
+    ValidatorDifficulties {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Enumerate CW20 tokens accepted by the `Receive` hook. Response: `Vec<AcceptedTokenResponse>`
+    #[returns(Vec<AcceptedTokenResponse>)]
+    AcceptedTokens {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Enumerate reward campaigns that have not been paid out in full yet. Response:
+    /// `Vec<RewardCampaign>`
+    #[returns(Vec<RewardCampaign>)]
+    RewardCampaigns {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Contracts notified of events of `event_kind`. Response: `Vec<String>`
+    #[returns(Vec<String>)]
+    Hooks { event_kind: HookEventKind },
+    /// Enumerate reward denoms with a swap route. Response: `Vec<SwapRouteResponse>`
+    #[returns(Vec<SwapRouteResponse>)]
+    SwapRoutes {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Enumerate proposed config changes that have not expired. Response: `Vec<PendingChange>`
+    #[returns(Vec<PendingChange>)]
+    PendingChanges {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Type URLs of the Stargate messages sent by the hub. Response: `StakingProtoUrls`
+    #[returns(StakingProtoUrls)]
+    StakingProtoUrls {},
+    /// Running totals of the hub's activity, which never decrease. Response: `Counters`
+    #[returns(Counters)]
+    Counters {},
+    /// Aggregated figures for analytics. Response: `MetricsResponse`
+    #[returns(MetricsResponse)]
+    Metrics {},
+    /// The bond denom and its decimals. Response: `DenomMetadataResponse`
+    #[returns(DenomMetadataResponse)]
+    DenomMetadata {},
+    /// The exchange rate floor and who can lift it. Response: `ExchangeRateFloorResponse`
+    #[returns(ExchangeRateFloorResponse)]
+    ExchangeRateFloor {},
+    /// Enumerate the most recent fee payouts, oldest first. Response: `Vec<FeeLedgerEntry>`
+    #[returns(Vec<FeeLedgerEntry>)]
+    FeeLedger {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Enumerate the most recent supply checkpoints, oldest first. Response:
+    /// `Vec<SupplyCheckpoint>`
+    #[returns(Vec<SupplyCheckpoint>)]
+    SupplyCheckpoints {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// The last supply checkpoint at or before `height`, e.g. to value Steak holdings at a
+    /// snapshot height without an archive node; `None` if it is older than those kept. Response:
+    /// `Option<SupplyCheckpoint>`
+    #[returns(Option<SupplyCheckpoint>)]
+    SupplyCheckpointAt { height: u64 },
+    /// The volume brought in by a referrer and the kickbacks it earned. Response:
+    /// `ReferralStatsResponse`
+    #[returns(ReferralStatsResponse)]
+    ReferralStats { referrer: String },
+    /// The Steak fees vesting to an account, and how much of them can be claimed. Response:
+    /// `VestedFeesResponse`
+    #[returns(VestedFeesResponse)]
+    VestedFees { account: String },
+    /// What code this deployment runs and what it is set up for. Response: `InfoResponse`
+    #[returns(InfoResponse)]
+    Info {},
+    /// When the next time-bound actions fall due, for keepers to schedule themselves by.
+    /// Response: `ScheduleResponse`
+    #[returns(ScheduleResponse)]
+    Schedule {},
+    /// The treasury's settings and the liquidity it has provided. Response: `TreasuryResponse`
+    #[returns(TreasuryResponse)]
+    Treasury {},
+    /// Evaluate several queries at once, e.g. everything a dashboard shows on a page. `Multi`
+    /// queries can't be nested. Response: `Vec<Binary>`, the response of each query in order
+    #[returns(Vec<Binary>)]
+    Multi { queries: Vec<QueryMsg> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    /// Account who can call certain privileged functions
+    pub owner: String,
+    /// Pending ownership transfer, awaiting acceptance by the new owner
+    pub new_owner: Option<String>,
+    /// Address of the Steak token
+    pub steak_token: String,
+    /// Token factory denom of the Steak token, once it has been migrated off cw20
+    pub usteak_denom: Option<String>,
+    /// How often the unbonding queue is to be executed, in seconds
+    pub epoch_period: u64,
+    /// The staking module's unbonding time, in seconds
+    pub unbond_period: u64,
+    /// denomination of coins to steak (uXXXX)
+    pub denom: String,
+    /// type of account to send the fees too
+    pub fee_type: String,
+    /// Fee Account to send fees too
+    pub fee_account: String,
+    /// Fee "1.00 = 100%"
+    pub fee_rate: Decimal,
+    /// Max Fee "1.00 = 100%"
+    pub max_fee_rate: Decimal,
+    /// Holders whose share of the rewards bears no protocol fee
+    pub fee_exemptions: Vec<String>,
+    /// Initial set of validators who will receive the delegations
+    pub validators: Vec<String>,
+    /// Minimum amount of harvested native (after fees) that will be delegated by a reinvest
+    pub min_reinvest_amount: Uint128,
+    /// Smallest deposit accepted by `Bond`
+    pub min_bond_amount: Uint128,
+    /// Smallest amount of Steak accepted by `QueueUnbond`
+    pub min_unbond_shares: Uint128,
+    /// Largest increase of the bonded amount a single reinvest may make, in basis points
+    pub max_rebase_bps: Option<u64>,
+    /// Seconds after the last harvest past which `Bond` and `QueueUnbond` are refused
+    pub max_harvest_staleness: Option<u64>,
+    /// Seconds after the pending batch's estimated start time during which anyone may submit it
+    pub submit_deadline_buffer: Option<u64>,
+    /// Account whose delegations are harvested through an authz grant, if not the hub itself
+    pub harvest_granter: Option<String>,
+    /// cw20-ics20 contract used to send Steak back to IBC depositors
+    pub ics20_contract: Option<String>,
+    /// IBC channel `BondOnBehalf` deposits are accepted from and sent back over
+    pub ics20_channel: Option<String>,
+    /// How the rewards received by a harvest are found
+    pub harvest_mode: HarvestMode,
+    /// Which validators receive the remainder when an amount can't be split evenly among them
+    pub remainder_policy: RemainderPolicy,
+    /// Interface of the fee-split contract, when fees go to one
+    pub fee_split_interface: FeeSplitInterface,
+    /// Number of slashing incidents after which a validator is deactivated
+    pub slash_limit: Option<u32>,
+    /// Share of the protocol fee paid back to referrers, in basis points
+    pub referral_bps: u64,
+    /// Vesting schedule of the reinvest fee, when it is taken in Steak
+    pub fee_vesting: Option<FeeVesting>,
+    /// When pending batches too small to be worth submitting are held over to the next epoch
+    pub batch_rollover: Option<BatchRollover>,
+    /// Contract notified of changes to the bonded amount, which may restake part of it
+    pub restaking_adapter: Option<RestakingAdapter>,
+    /// Whether deposits are measured from the contract's balance rather than taken from the funds
+    /// sent along
+    pub measure_deposits: bool,
+    /// Least Native Token unbonding leaves delegated to a validator
+    pub min_delegation_per_validator: Uint128,
+    /// How much Native Token may be bonded for a single receiver per window
+    pub bond_rate_limit: Option<BondRateLimit>,
+    /// Pending rewards a validator must have above for a harvest to withdraw from it
+    pub min_harvest_reward: Uint128,
+    /// Least Native Token a validator's operator must delegate to it
+    pub min_self_delegation: Uint128,
+    /// How many validators `SubmitBatch` may be asked to leave out of a batch
+    pub max_batch_exclusions: u32,
+    /// Denoms of liquid staking derivatives `Bond` refuses
+    pub forbidden_deposit_denoms: Vec<String>,
+    /// Wasm admin of the Steak token
+    pub token_admin: String,
+    /// Operations currently allowed
+    pub capabilities: Capabilities,
+    /// How `Reconcile` finds the batches whose unbonded Native Token has been received
+    pub reconcile_mode: ReconcileMode,
+    /// Bridge the exchange rate is published through on each reinvest
+    pub rate_publisher: Option<RatePublisher>,
+    /// Most messages a single execute may send
+    pub max_messages: Option<u32>,
+    /// How far from its target a validator's delegation may be before `Rebalance` moves it
+    pub min_redelegation: Uint128,
+    /// Accounts whose unbonding requests for themselves are never held back by size limits
+    pub priority_unbonders: Vec<String>,
+    /// How long proposed changes wait before they can be accepted, and for how long after
+    pub change_timelock: ChangeTimelock,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct InfoResponse {
+    /// Contract name, as recorded by cw2
+    pub contract: String,
+    /// Contract version, as recorded by cw2 at instantiation or the last migration
+    pub version: String,
+    /// Git commit the code was built from, if it was given at build time
+    pub git_commit: Option<String>,
+    /// Cargo features the code was built with
+    pub features: Vec<String>,
+    /// How the Steak token is minted and burned: `cw20` or `token_factory`
+    pub token_backend: String,
+    /// Address of the cw20 Steak token, or the token factory denom once migrated
+    pub usteak: String,
+    pub chain_profile: ChainProfile,
+}
+
+/// Upcoming times, in seconds, at which the hub's time-bound actions fall due
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ScheduleResponse {
+    /// ID of the pending batch
+    pub pending_batch_id: u64,
+    /// When the pending batch can be submitted
+    pub batch_submission_time: u64,
+    /// When anyone but the guardian can no longer submit the pending batch, if there is a deadline
+    pub batch_submission_deadline: Option<u64>,
+    /// Earliest estimated end of the unbonding of an unreconciled batch. `Reconcile` can be
+    /// invoked once it has passed
+    pub reconciliation_time: Option<u64>,
+    /// When the exchange rate goes stale unless rewards are harvested, if staleness is limited
+    pub harvest_deadline: Option<u64>,
+    /// When the next validator suspension ends
+    pub suspension_end: Option<u64>,
+    /// When the next proposed config change expires
+    pub config_change_expiry: Option<u64>,
+}
+
+/// The chain a deployment runs on, and how it talks to its staking module
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ChainProfile {
+    pub chain_id: String,
+    /// Denomination of the Native Token
+    pub denom: String,
+    /// The staking module's unbonding time, in seconds
+    pub unbond_period: u64,
+    pub staking_proto_urls: StakingProtoUrls,
+    /// Preset the hub was instantiated with, if any
+    pub preset: Option<ChainPreset>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ReferralStatsResponse {
+    pub referrer: String,
+    /// Native Token bonded with this referrer
+    pub volume: Uint128,
+    /// Kickbacks earned so far, claimed or not
+    pub earned: Uint128,
+    /// Kickbacks that can be claimed with `ClaimReferralRewards`
+    pub unclaimed: Uint128,
+}
+
+/// Schedule over which a reinvest fee taken in Steak vests to the fee account, counted from the
+/// reinvest: nothing can be claimed before the cliff, then the fee vests linearly until `duration`
+/// has passed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+pub struct FeeVesting {
+    /// Seconds before any of the fee can be claimed
+    pub cliff: u64,
+    /// Seconds until all of the fee can be claimed
+    pub duration: u64,
+}
+
+/// A pending batch with less than `min_shares` of Steak to burn when it is submitted is rolled over:
+/// it stays open for another epoch and collects more requests. After `max_rollovers` epochs it is
+/// submitted however small it is, so that dust can't hold its requests back indefinitely
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+pub struct BatchRollover {
+    pub min_shares: Uint128,
+    pub max_rollovers: u32,
+}
+
+/// No more than `max_bond_per_window` of Native Token may be bonded for a single receiver within
+/// `window_secs` of the first bond of its current window, e.g. during a guarded launch. Once the
+/// window is over, the next bond starts a new one
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+pub struct BondRateLimit {
+    pub max_bond_per_window: Uint128,
+    pub window_secs: u64,
+}
+
+/// Operations the owner may halt one by one, e.g. to stop deposits during an incident while users
+/// remain free to exit. Everything is enabled unless halted
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+pub struct Capabilities {
+    /// `Bond`, `BondMany`, `BondOnBehalf` and `BondTokenizedShares`
+    #[serde(default = "enabled")]
+    pub bond: bool,
+    /// `QueueUnbond` and `CreateUnbondOrder`. Orders already placed keep being executed
+    #[serde(default = "enabled")]
+    pub unbond: bool,
+    /// `WithdrawUnbonded`
+    #[serde(default = "enabled")]
+    pub withdraw: bool,
+    /// `Harvest`
+    #[serde(default = "enabled")]
+    pub harvest: bool,
+    /// `SubmitBatch`
+    #[serde(default = "enabled")]
+    pub submit_batch: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            bond: true,
+            unbond: true,
+            withdraw: true,
+            harvest: true,
+            submit_batch: true,
+        }
+    }
+}
+
+/// A contract restaking part of the hub's stake to secure additional services. The stake stays
+/// delegated by the hub; the adapter only reports how much of it is restaked, which may not exceed
+/// `max_restaked_bps` of the bonded amount
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct RestakingAdapter {
+    pub contract: String,
+    pub max_restaked_bps: u64,
+}
+
+/// A pool of the chain's swap module (e.g. Osmosis' poolmanager) to swap through, and the denom
+/// coming out of it
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct SwapHop {
+    pub pool_id: u64,
+    pub token_out_denom: String,
+}
+
+/// Pools a reward denom is swapped through to get Native Token. `min_rate` is the least Native
+/// Token accepted per unit of the reward denom, guarding swaps anyone may trigger
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct SwapRoute {
+    pub hops: Vec<SwapHop>,
+    pub min_rate: Decimal,
+}
+
+/// Cross-chain messaging protocol the exchange rate is published through
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeProtocol {
+    /// Posted to Wormhole's core bridge contract, for its guardians to sign and anyone to deliver
+    Wormhole { core_bridge: String },
+    /// Sent over IBC `channel` to Axelar, which calls `destination_address` on
+    /// `destination_chain` (an Axelar chain name, e.g. "ethereum")
+    Axelar {
+        channel: String,
+        destination_chain: String,
+        destination_address: String,
+    },
+}
+
+/// Where reinvests publish the exchange rate. `fee` is the Native Token the bridge charges per
+/// message, paid out of the rewards being reinvested
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct RatePublisher {
+    pub protocol: BridgeProtocol,
+    pub fee: Uint128,
+}
+
+/// Protocol-owned liquidity. `fee_share` of the fee each reinvest takes is set aside, to be paired
+/// with Steak in `pair`, an Astroport or Terraswap style pair contract whose LP token is
+/// `lp_token`. `slippage_tolerance` is passed along with each deposit
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct TreasuryConfig {
+    pub pair: String,
+    pub lp_token: String,
+    pub fee_share: Decimal,
+    pub slippage_tolerance: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct TreasuryResponse {
+    pub treasury: Option<TreasuryConfig>,
+    /// Native Token set aside from fees, not yet provided as liquidity
+    pub native_escrow: Uint128,
+    /// Native Token deposited into the pair so far, not counting what was bonded for the Steak
+    pub native_provided: Uint128,
+    /// Steak deposited into the pair so far
+    pub usteak_provided: Uint128,
+    /// LP tokens the hub holds
+    pub lp_tokens: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct SwapRouteResponse {
+    /// Reward denom
+    pub denom: String,
+    pub route: SwapRoute,
+}
+
+/// Events contracts can register to be notified of with `ExecuteMsg::RegisterHook`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEventKind {
+    Bond,
+    BatchSubmission,
+    Reconciliation,
+}
+
+impl HookEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEventKind::Bond => "bond",
+            HookEventKind::BatchSubmission => "batch_submission",
+            HookEventKind::Reconciliation => "reconciliation",
+        }
+    }
+}
+
+/// Messages the hub sends to contracts registered with `ExecuteMsg::RegisterHook`, which must
+/// accept them in their `ExecuteMsg`
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SteakHookMsg {
+    /// Native Token was bonded; see `BondReceipt`
+    Bonded { receipt: BondReceipt },
+    /// Batch `id` was submitted, unbonding `native_unbonded` until `est_unbond_end_time`
+    BatchSubmitted {
+        id: u64,
+        native_unbonded: Uint128,
+        usteak_burned: Uint128,
+        est_unbond_end_time: u64,
+    },
+    /// Batches `ids` finished unbonding and were reconciled with what was received
+    BatchesReconciled { ids: Vec<u64> },
+}
+
+/// Messages the hub sends to its restaking adapter, which must accept them in its `ExecuteMsg`
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RestakingHookMsg {
+    /// The amount bonded by the hub changed to `total_bonded`; no more than `max_restaked` of it
+    /// may be restaked
+    BondedAmountChanged {
+        total_bonded: Uint128,
+        max_restaked: Uint128,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct VestedFeesResponse {
+    pub account: String,
+    /// Steak taken as fees for this account that has not been claimed yet, vested or not
+    pub unclaimed: Uint128,
+    /// Steak that can be claimed with `ClaimVestedFees`
+    pub claimable: Uint128,
+}
+
+// entropy response
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct MinerParamsResponse {
+    // entropy to hash
+    pub entropy: String,
+    // mining difficulty
+    pub difficulty: Uint64,
+    // whether proofs are currently accepted
+    pub enabled: bool,
+    // blocks a miner must wait after an accepted proof before submitting another
+    pub proof_cooldown_blocks: u64,
+    // how mining power is normalized at each batch submission
+    pub power_normalization: MiningPowerNormalization,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ValidatorCapResponse {
+    pub validator: String,
+    /// Maximum amount of native to be delegated to the validator
+    pub cap: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ValidatorDifficultyResponse {
+    pub validator: String,
+    /// Mining difficulty proofs for the validator must meet, in place of the global one
+    pub difficulty: Uint64,
+}
+
+/// Set as the `data` of the response to `Bond`, so calling contracts can read the outcome from
+/// their reply instead of parsing events
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct BondReceipt {
+    pub receiver: String,
+    pub native_bonded: Uint128,
+    pub usteak_minted: Uint128,
+    /// Amount of native per Steak the deposit was minted at
+    pub exchange_rate: Decimal,
+}
+
+/// Set as the `data` of the response to `QueueUnbond`. The Steak token's `Send` does not pass it
+/// on, so it is only visible to the token contract and in the transaction result
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondReceipt {
+    /// ID of the pending batch the request was added to
+    pub batch_id: u64,
+    pub receiver: String,
+    pub usteak_to_burn: Uint128,
+    /// Total shares of the receiver's request in the batch, including earlier calls
+    pub request_shares: Uint128,
+    /// Time after which the batch may be submitted
+    pub est_unbond_start_time: u64,
+}
+
+/// Set as the `data` of the response to `WithdrawUnbonded`
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct WithdrawReceipt {
+    /// ID of the claim, as listed by `RecentClaims`
+    pub claim_id: u64,
+    pub receiver: String,
+    pub native_withdrawn: Uint128,
+    /// IDs of the batches the native was claimed from
+    pub batch_ids: Vec<u64>,
+}
+
+/// A withdrawal of unbonded native, kept for a while so that a retried `WithdrawUnbonded` can be
+/// told apart from one that never went through
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct RecentClaim {
+    /// ID of this claim; IDs keep increasing across users
+    pub id: u64,
+    pub receiver: String,
+    pub amount: Uint128,
+    /// Block height of the withdrawal
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ValidatorNetAprResponse {
+    pub validator: String,
+    /// Commission rate, if the staking module knows the validator
+    pub commission: Option<Decimal>,
+    /// Staking rewards per year per token bonded, before commission: the annual provisions left
+    /// after the community tax, over the chain's bonded tokens. Fees are left out. `None` if the
+    /// chain doesn't allow the hub's Stargate queries
+    pub staking_apr: Option<Decimal>,
+    /// `staking_apr` net of the validator's commission
+    pub net_apr: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ValidatorSummaryResponse {
+    pub validator: String,
+    /// Whether the validator receives new delegations
+    pub active: bool,
+    /// Amount of native delegated to the validator by the hub
+    pub delegation: Uint128,
+    /// Amount of native that rebalancing works towards for the validator
+    pub target_delegation: Uint128,
+    /// Maximum amount of native to be delegated to the validator, if any
+    pub cap: Option<Uint128>,
+    pub mining_power: Uint128,
+    /// Commission rate, if the staking module knows the validator
+    pub commission: Option<Decimal>,
+    pub max_commission: Option<Decimal>,
+    /// Amount of native bonded to the validator by all delegators. `None` if the chain doesn't
+    /// allow the hub's Stargate query
+    pub total_tokens: Option<Uint128>,
+    /// `None` if the chain doesn't allow the hub's Stargate query
+    pub jailed: Option<bool>,
+    /// Slashing incidents acknowledged against the validator since it was last reinstated
+    pub slash_incidents: u32,
+    /// When the validator's suspension ends, if it is suspended
+    pub suspended_until: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ExchangeRateFloorResponse {
+    /// Exchange rate observed by the last bond, reinvest or batch submission
+    pub last_exchange_rate: Option<Decimal>,
+    /// Largest relative drop from `last_exchange_rate` that is let through
+    pub epsilon: Decimal,
+    /// Account allowed to acknowledge slashes; the owner if not set
+    pub guardian: Option<String>,
+    /// Whether the next drop beyond the epsilon has been acknowledged
+    pub slash_acknowledged: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct StateResponse {
+    /// Total supply to the Steak token
+    pub total_usteak: Uint128,
+    /// Total amount of native staked
+    pub total_native: Uint128,
+    /// The exchange rate between usteak and native, in terms of native per usteak
+    pub exchange_rate: Decimal,
+    /// Staking rewards currently held by the contract that are ready to be reinvested
+    pub unlocked_coins: Vec<Coin>,
+    /// Time of the last harvest, in seconds
+    pub last_harvest_time: Option<u64>,
+    /// Native Token delegated by the last reinvest
+    pub last_reinvest_amount: Uint128,
+    /// Protocol fees taken from rewards so far, in Native Token
+    pub total_fees_collected: Uint128,
+    /// Rewards delegated back so far, net of fees
+    pub total_rewards_compounded: Uint128,
+    /// Part of `total_native` the restaking adapter reports as restaked. It can't be unbonded until
+    /// the adapter releases it
+    pub restaked_native: Uint128,
+    /// Native Token delegated to the hub from outside and absorbed by the owner so far
+    pub absorbed_native: Uint128,
+    /// Fees in Native Token held after the fee-split contract rejected them
+    pub fee_escrow: Uint128,
+    /// Native Token received outside of bonds and rewards, parked until the owner classifies it
+    pub donations: Uint128,
+    /// Account that triggered the last harvest
+    pub last_harvester: Option<OperationRecord>,
+    /// Account that triggered the last reconciliation
+    pub last_reconciler: Option<OperationRecord>,
+    /// Account that triggered the last batch submission
+    pub last_batch_submitter: Option<OperationRecord>,
+}
+
+/// Who triggered a permissionless operation, and at which block height
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct OperationRecord {
+    pub address: String,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct DenomMetadataResponse {
+    /// Denomination of coins being staked
+    pub denom: String,
+    /// Number of decimals of the denom
+    pub decimals: u8,
+    /// Amount of `denom` making up one whole token, i.e. `10^decimals`
+    pub unit: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MetricsResponse {
+    /// Total value locked, i.e. total amount of native staked
+    pub total_native: Uint128,
+    /// Total supply to the Steak token
+    pub total_usteak: Uint128,
+    /// Estimated number of stakers: accounts that were minted Steak and have not fully exited
+    pub num_stakers: u64,
+    /// Number of whitelisted validators
+    pub num_validators: u64,
+    /// ID of the pending batch
+    pub pending_batch_id: u64,
+    /// Amount of `usteak` queued for unbonding in the pending batch
+    pub pending_usteak_to_burn: Uint128,
+    /// Estimated time when the pending batch will be submitted for unbonding
+    pub pending_est_unbond_start_time: u64,
+    /// Number of submitted batches that have not been fully withdrawn
+    pub num_previous_batches: u64,
+    /// Amount of native in submitted batches that has not been withdrawn yet
+    pub native_unclaimed: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct PendingBatch {
+    /// ID of this batch
+    pub id: u64,
+    /// Total amount of `usteak` to be burned in this batch
+    pub usteak_to_burn: Uint128,
+    /// Estimated time when this batch will be submitted for unbonding
+    pub est_unbond_start_time: u64,
+    /// Number of epochs this batch was held over for being too small to submit
+    #[serde(default)]
+    pub rollovers: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct Batch {
+    /// ID of this batch
+    pub id: u64,
+    /// Whether this batch has already been reconciled
+    pub reconciled: bool,
+    /// Total amount of shares remaining this batch. Each `usteak` burned = 1 share
+    pub total_shares: Uint128,
+    /// Amount of `denom` in this batch that have not been claimed
+    pub amount_unclaimed: Uint128,
+    /// Estimated time when this batch will finish unbonding
+    pub est_unbond_end_time: u64,
+    /// Fee rate in effect when this batch was submitted. `None` for batches submitted before
+    /// fee snapshotting was introduced
+    pub fee_rate: Option<Decimal>,
+    /// Exchange rate (native per usteak) at which this batch was submitted. `None` for batches
+    /// submitted before exchange rate snapshotting was introduced
+    pub exchange_rate: Option<Decimal>,
+    /// Account that submitted this batch. `None` for batches submitted before submitters were
+    /// recorded
+    pub submitter: Option<Addr>,
+    /// Native Token deducted from this batch when it was reconciled, e.g. after a slash
+    #[serde(default)]
+    pub shortfalls: Vec<BatchShortfall>,
+    /// Whether `fee_rate` and `exchange_rate` were filled in by a migration, as the batch was
+    /// submitted before they were snapshotted, rather than recorded at submission
+    #[serde(default)]
+    pub backfilled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct BatchShortfall {
+    /// Time of the reconciliation, in seconds
+    pub time: u64,
+    /// Native Token deducted from the batch
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondRequest {
+    /// ID of the batch
+    pub id: u64,
+    /// The user's address
+    pub user: Addr,
+    /// The user's share in the batch
+    pub shares: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ReplayBatchResponse {
+    /// ID of the batch
+    pub id: u64,
+    /// Shares the batch records as not yet withdrawn
+    pub total_shares: Uint128,
+    /// Native Token the batch records as not yet withdrawn
+    pub amount_unclaimed: Uint128,
+    /// Shares of the batch's unbonding requests, added up
+    pub request_shares: Uint128,
+    /// Native Token the requests are entitled to, added up
+    pub amount_entitled: Uint128,
+    pub entitlements: Vec<UnbondEntitlement>,
+    /// Inconsistencies found between the batch and its requests; empty if there are none
+    pub mismatches: Vec<String>,
+}
+
+/// What a user can withdraw from a batch, as recomputed by `QueryMsg::ReplayBatch`
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondEntitlement {
+    pub user: String,
+    pub shares: Uint128,
+    pub amount: Uint128,
+}
+
+/// Running totals of the hub's activity since it was instantiated. They only ever grow: an
+/// overflow errors instead of wrapping around, and a migration may not set them back
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq, JsonSchema)]
+pub struct Counters {
+    /// Native Token bonded so far, excluding reinvested rewards
+    pub total_bonded: Uint128,
+    /// Native Token undelegated so far by submitted batches
+    pub total_unbonded: Uint128,
+    /// Number of harvests so far
+    pub total_harvests: u64,
+}
+
+/// Type URLs of the Stargate messages the hub sends to the chain's staking and distribution
+/// modules, so that a chain renaming these modules only needs a config update
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct StakingProtoUrls {
+    pub delegate: String,
+    pub undelegate: String,
+    pub redelegate: String,
+    pub withdraw_rewards: String,
+    /// Redeems liquid staking module share tokens; see `ExecuteMsg::BondTokenizedShares`
+    #[serde(default = "default_redeem_tokens_url")]
+    pub redeem_tokens: String,
+    /// Wraps reward withdrawals made on behalf of the harvest granter; see
+    /// `ExecuteMsg::SetHarvestGranter`
+    #[serde(default = "default_authz_exec_url")]
+    pub authz_exec: String,
+}
+
+fn default_redeem_tokens_url() -> String {
+    "/liquidstaking.staking.v1beta1.MsgRedeemTokensForShares".to_string()
+}
+
+fn default_authz_exec_url() -> String {
+    "/cosmos.authz.v1beta1.MsgExec".to_string()
+}
+
+impl Default for StakingProtoUrls {
+    fn default() -> Self {
+        Self {
+            delegate: "/liquidstaking.staking.v1beta1.MsgDelegate".to_string(),
+            undelegate: "/liquidstaking.staking.v1beta1.MsgUndelegate".to_string(),
+            redelegate: "/liquidstaking.staking.v1beta1.MsgBeginRedelegate".to_string(),
+            withdraw_rewards: "/liquidstaking.distribution.v1beta1.MsgWithdrawDelegatorReward"
+                .to_string(),
+            redeem_tokens: default_redeem_tokens_url(),
+            authz_exec: default_authz_exec_url(),
+        }
+    }
+}
+
+/// A config change that goes through the propose/accept flow
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigChange {
+    /// Transfer the fee collection account to another account
+    FeeAccount {
+        fee_account_type: FeeType,
+        new_fee_account: String,
+    },
+    /// Update the fee rate
+    FeeRate { new_fee: Decimal },
+    /// Remove a validator from the whitelist, redelegating its stake to the others
+    RemoveValidator { validator: String },
+    /// Set the timelock of changes proposed from then on
+    Timelock { timelock: ChangeTimelock },
+}
+
+/// Proposed changes can be accepted `delay` seconds after being proposed, for `expiry` seconds.
+/// As the owner both proposes and accepts, the delay is what gives holders time to react
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ChangeTimelock {
+    pub delay: u64,
+    pub expiry: u64,
+}
+
+impl Default for ChangeTimelock {
+    fn default() -> Self {
+        Self {
+            delay: 86400,
+            expiry: 259200,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingChange {
+    /// ID of this change
+    pub id: u64,
+    /// The change to apply once accepted
+    pub change: ConfigChange,
+    /// Time when the change was proposed
+    pub proposed_at: u64,
+    /// Time from which the change can be accepted
+    #[serde(default)]
+    pub accept_after: u64,
+    /// Time after which the change can no longer be accepted
+    pub expires_at: u64,
+}
+
+/// A fee paid out by the hub
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct FeeLedgerEntry {
+    /// ID of this entry; IDs keep increasing as older entries are dropped from the ledger
+    pub id: u64,
+    /// What the fee was charged on, e.g. `reinvest`
+    pub source: String,
+    /// Amount paid
+    pub amount: Uint128,
+    /// Denom paid in
+    pub denom: String,
+    /// Account the fee was sent to
+    pub destination: Addr,
+    /// How the fee was sent to `destination`
+    pub fee_type: FeeType,
+    /// Block height of the payout
+    pub height: u64,
+}
+
+/// Native Token a third party set aside to be delegated for holders over a window of time
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct RewardCampaign {
+    pub id: u64,
+    /// Account that funded the campaign
+    pub funder: Addr,
+    /// Native Token funding the campaign
+    pub amount: Uint128,
+    /// Part of `amount` delegated by harvests so far
+    pub released: Uint128,
+    /// Time the campaign starts, in seconds
+    pub start: u64,
+    /// Time by which all of `amount` is released, in seconds
+    pub end: u64,
+}
+
+impl RewardCampaign {
+    /// Part of `amount` due for release by `time`, released or not
+    pub fn vested(&self, time: u64) -> Uint128 {
+        if time <= self.start {
+            Uint128::zero()
+        } else if time >= self.end {
+            self.amount
+        } else {
+            self.amount
+                .multiply_ratio(time - self.start, self.end - self.start)
+        }
+    }
+}
+
+/// Steak supply and exchange rate right after a harvest or batch submission changed them. Bonds
+/// mint at the exchange rate in effect, so until the next checkpoint only the supply moves, and
+/// the rate only through slashes
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct SupplyCheckpoint {
+    /// ID of this checkpoint; IDs keep increasing as older checkpoints are dropped
+    pub id: u64,
+    /// Block height the checkpoint was recorded at
+    pub height: u64,
+    /// Block time the checkpoint was recorded at, in seconds
+    pub time: u64,
+    /// What changed the supply or rate: `harvest` or `batch`
+    pub source: String,
+    /// Total supply of Steak
+    pub usteak_supply: Uint128,
+    /// Native Token bonded by the hub
+    pub native_bonded: Uint128,
+    /// Native Token per Steak
+    pub exchange_rate: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondOrder {
+    /// The user's address
+    pub user: Addr,
+    /// Amount of usteak queued for unbonding in each batch
+    pub shares_per_epoch: Uint128,
+    /// Number of batches the order has yet to be queued into
+    pub epochs_remaining: u32,
+    /// ID of the batch the last slice was queued into, so that a batch held over to the next epoch
+    /// doesn't take another one
+    #[serde(default)]
+    pub last_batch_id: Option<u64>,
+}
+
+/// The part of an `UnbondRequest` that was queued by a given sender. A request can aggregate shares
+/// queued by several senders for the same receiver
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondRequestSource {
+    /// ID of the batch
+    pub id: u64,
+    /// The account that sent the usteak
+    pub sender: Addr,
+    /// The account the unbonding request belongs to
+    pub receiver: Addr,
+    /// The shares queued by `sender`
+    pub shares: Uint128,
+}
+
+/// A single call that added shares to an `UnbondRequest`. The request itself holds the running
+/// total; once a request has `MAX_UNBOND_CALLS_PER_REQUEST` records, further calls are folded into
+/// the last one, so storage stays bounded while the events still carry every call
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondCall {
+    /// Position of this record within the request, starting at 0
+    pub index: u32,
+    /// The account that sent the usteak (the most recent one, if several calls were folded in)
+    pub sender: Addr,
+    /// The shares queued
+    pub shares: Uint128,
+    /// Timestamp of the most recent call recorded here
+    pub time: u64,
+    /// Number of calls recorded here; more than 1 only for the last record of a full request
+    pub calls: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondEtaResponse {
+    /// Unbonding time the estimates are based on, in seconds: the longer of the hub's configured
+    /// unbond period and the staking module's unbonding time
+    pub unbond_time: u64,
+    pub batches: Vec<UnbondEtaResponseItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondEtaResponseItem {
+    /// ID of the batch
+    pub id: u64,
+    /// The user's share in the batch
+    pub shares: Uint128,
+    /// Whether the batch has been submitted for unbonding yet
+    pub submitted: bool,
+    /// Estimated time the batch finishes unbonding, in seconds
+    pub est_unbond_end_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct WithdrawableAmountResponse {
+    /// Amount of native that can be withdrawn
+    pub amount: Uint128,
+    /// IDs of the batches it would be withdrawn from
+    pub ids: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondRequestsByBatchResponseItem {
+    /// The user's address
+    pub user: String,
+    /// The user's share in the batch
+    pub shares: Uint128,
+    /// Native Token per Steak when the shares were queued, averaged over the calls making up the
+    /// request; `None` for requests queued before it was recorded
+    pub queued_exchange_rate: Option<Decimal>,
+}
+
+impl From<UnbondRequest> for UnbondRequestsByBatchResponseItem {
+    fn from(s: UnbondRequest) -> Self {
+        Self {
+            user: s.user.into(),
+            shares: s.shares,
+            queued_exchange_rate: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondRequestsByUserResponseItem {
+    /// ID of the batch
+    pub id: u64,
+    /// The user's share in the batch
+    pub shares: Uint128,
+    /// Native Token per Steak when the shares were queued, averaged over the calls making up the
+    /// request; `None` for requests queued before it was recorded
+    pub queued_exchange_rate: Option<Decimal>,
+}
+
+impl From<UnbondRequest> for UnbondRequestsByUserResponseItem {
+    fn from(s: UnbondRequest) -> Self {
+        Self {
+            id: s.id,
+            shares: s.shares,
+            queued_exchange_rate: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct UnbondRequestsBySenderResponseItem {
+    /// ID of the batch
+    pub id: u64,
+    /// The account the unbonding request belongs to
+    pub receiver: String,
+    /// The shares queued by the sender
+    pub shares: Uint128,
+}
+
+impl From<UnbondRequestSource> for UnbondRequestsBySenderResponseItem {
+    fn from(s: UnbondRequestSource) -> Self {
+        Self {
+            id: s.id,
+            receiver: s.receiver.into(),
+            shares: s.shares,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct ValidatorMiningPower {
+    /// Validator address
+    pub address: String,
+    /// Mining power
+    pub mining_power: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, JsonSchema)]
+pub struct AcceptedTokenResponse {
+    /// CW20 token address
+    pub token: String,
+    /// `ReceiveMsg` variants the token may be sent with
+    pub receive_msgs: Vec<ReceiveMsgKind>,
+}
+
+pub type MigrateMsg = Empty;
+
+/// Where fees are sent. Serialized as `"Wallet"`, `"FeeSplit"` or `"Burn"`, the strings these
+/// fields took before they were typed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Copy, JsonSchema)]
+pub enum FeeType {
+    /// Fees are sent to the fee account with a bank transfer
+    Wallet,
+    /// Fees are deposited into the fee-split contract at the fee account
+    FeeSplit,
+    /// Fees are burned, for deflationary tokenomics; the fee account receives nothing. A fee taken
+    /// in Steak is burned by never minting it, leaving its Native Token to all holders
+    Burn,
+}
+/// Interface of the fee-split contract that fees are deposited into, with `FeeType::FeeSplit`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeSplitInterface {
+    /// `Deposit { flush: false }`, as spoken by `pfc-fee-split` 0.1
+    V1,
+    /// `Deposit {}`, leaving it to the fee-split contract to decide when to distribute
+    V2,
+}
+
+impl Default for FeeSplitInterface {
+    fn default() -> Self {
+        FeeSplitInterface::V1
+    }
+}
+
+impl FromStr for FeeType {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Wallet" => Ok(FeeType::Wallet),
+            "FeeSplit" => Ok(FeeType::FeeSplit),
+            "Burn" => Ok(FeeType::Burn),
+            _ => Err(()),
+        }
+    }
+}
+impl ToString for FeeType {
+    fn to_string(&self) -> String {
+        match &self {
+            FeeType::Wallet => String::from("Wallet"),
+            FeeType::FeeSplit => String::from("FeeSplit"),
+            FeeType::Burn => String::from("Burn"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{from_slice, to_vec};
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+
+    /// `json` is what a frontend sends or receives; it must parse into `expected`, and `expected`
+    /// must survive a round trip
+    fn assert_round_trip<T>(json: &str, expected: T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        assert_eq!(from_slice::<T>(json.as_bytes()).unwrap(), expected);
+        assert_eq!(
+            from_slice::<T>(&to_vec(&expected).unwrap()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn execute_msgs() {
+        // Fields added since a message was introduced are optional
+        assert_round_trip(
+            r#"{"bond":{"receiver":null}}"#,
+            ExecuteMsg::Bond {
+                receiver: None,
+                referrer: None,
+                min_usteak_out: None,
+            },
+        );
+        assert_round_trip(
+            r#"{"bond":{"receiver":"alice","referrer":"bob","min_usteak_out":"1000"}}"#,
+            ExecuteMsg::Bond {
+                receiver: Some("alice".to_string()),
+                referrer: Some("bob".to_string()),
+                min_usteak_out: Some(Uint128::new(1000)),
+            },
+        );
+        assert_round_trip(
+            r#"{"migrate_token":{"new_backend":{"token_factory":{"subdenom":"steak"}}}}"#,
+            ExecuteMsg::MigrateToken {
+                new_backend: TokenBackend::TokenFactory {
+                    subdenom: "steak".to_string(),
+                },
+            },
+        );
+        assert_round_trip(
+            r#"{"callback":{"reinvest":{}}}"#,
+            ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+        );
+        assert_round_trip(
+            r#"{"propose_change":{"change":{"fee_account":{"fee_account_type":"FeeSplit","new_fee_account":"fees"}}}}"#,
+            ExecuteMsg::ProposeChange {
+                change: ConfigChange::FeeAccount {
+                    fee_account_type: FeeType::FeeSplit,
+                    new_fee_account: "fees".to_string(),
+                },
+            },
+        );
+        assert_round_trip(
+            r#"{"propose_change":{"change":{"fee_account":{"fee_account_type":"Burn","new_fee_account":"fees"}}}}"#,
+            ExecuteMsg::ProposeChange {
+                change: ConfigChange::FeeAccount {
+                    fee_account_type: FeeType::Burn,
+                    new_fee_account: "fees".to_string(),
+                },
+            },
+        );
+        assert_round_trip(
+            r#"{"set_staking_proto_urls":{"urls":{
+                "delegate":"/cosmos.staking.v1beta1.MsgDelegate",
+                "undelegate":"/cosmos.staking.v1beta1.MsgUndelegate",
+                "redelegate":"/cosmos.staking.v1beta1.MsgBeginRedelegate",
+                "withdraw_rewards":"/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward"
+            }}}"#,
+            ExecuteMsg::SetStakingProtoUrls {
+                urls: StakingProtoUrls {
+                    delegate: "/cosmos.staking.v1beta1.MsgDelegate".to_string(),
+                    undelegate: "/cosmos.staking.v1beta1.MsgUndelegate".to_string(),
+                    redelegate: "/cosmos.staking.v1beta1.MsgBeginRedelegate".to_string(),
+                    withdraw_rewards: "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward"
+                        .to_string(),
+                    redeem_tokens: StakingProtoUrls::default().redeem_tokens,
+                    authz_exec: StakingProtoUrls::default().authz_exec,
+                },
+            },
+        );
+        assert_round_trip(
+            r#"{"rebalance":{"minimum":"1000"}}"#,
+            ExecuteMsg::Rebalance {
+                minimum: Some(Uint128::new(1000)),
+            },
+        );
+        assert_round_trip(
+            r#"{"rebalance":{}}"#,
+            ExecuteMsg::Rebalance { minimum: None },
+        );
+        // Halting one operation leaves the others enabled
+        assert_round_trip(
+            r#"{"set_capabilities":{"capabilities":{"bond":false}}}"#,
+            ExecuteMsg::SetCapabilities {
+                capabilities: Capabilities {
+                    bond: false,
+                    ..Capabilities::default()
+                },
+            },
+        );
+    }
+
+    #[test]
+    fn receive_and_sudo_msgs() {
+        assert_round_trip(
+            r#"{"queue_unbond":{"receiver":null}}"#,
+            ReceiveMsg::QueueUnbond {
+                receiver: None,
+                min_native_out: None,
+            },
+        );
+        assert_round_trip(r#"{"swap_token":{}}"#, ReceiveMsg::SwapToken {});
+        assert_round_trip(r#""queue_unbond""#, ReceiveMsgKind::QueueUnbond);
+        assert_round_trip(r#"{"tick":{}}"#, SudoMsg::Tick {});
+    }
+
+    #[test]
+    fn query_msgs() {
+        assert_round_trip(r#"{"previous_batch":7}"#, QueryMsg::PreviousBatch(7));
+        assert_round_trip(
+            r#"{"unbond_requests_by_sender":{"sender":"alice","start_after":[1,"bob"],"limit":null}}"#,
+            QueryMsg::UnbondRequestsBySender {
+                sender: "alice".to_string(),
+                start_after: Some((1, "bob".to_string())),
+                limit: None,
+            },
+        );
+        assert_round_trip(
+            r#"{"multi":{"queries":[{"config":{}},{"state":{}}]}}"#,
+            QueryMsg::Multi {
+                queries: vec![QueryMsg::Config {}, QueryMsg::State {}],
+            },
+        );
+    }
+
+    #[test]
+    fn responses() {
+        assert_round_trip(
+            r#"{"id":2,"usteak_to_burn":"100","est_unbond_start_time":20000}"#,
+            PendingBatch {
+                id: 2,
+                usteak_to_burn: Uint128::new(100),
+                est_unbond_start_time: 20000,
+                rollovers: 0,
+            },
+        );
+        assert_round_trip(
+            r#"{"receiver":"alice","native_bonded":"1025","usteak_minted":"1000","exchange_rate":"1.025"}"#,
+            BondReceipt {
+                receiver: "alice".to_string(),
+                native_bonded: Uint128::new(1025),
+                usteak_minted: Uint128::new(1000),
+                exchange_rate: Decimal::from_ratio(1025u128, 1000u128),
+            },
+        );
+        assert_round_trip(
+            r#"{"referrer":"bob","volume":"250000","earned":"40","unclaimed":"0"}"#,
+            ReferralStatsResponse {
+                referrer: "bob".to_string(),
+                volume: Uint128::new(250000),
+                earned: Uint128::new(40),
+                unclaimed: Uint128::zero(),
+            },
+        );
+    }
+}
@@ -0,0 +1,2 @@
+pub mod chains;
+pub mod hub;
@@ -0,0 +1,95 @@
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+
+use pfc_steak_api::hub::{ExecuteMsg, QueryMsg};
+
+/// Talks to the hub through the chain's CLI binary, the same way `dpow-miner-cli` does
+pub struct Chain {
+    pub binary: String,
+    pub chain_id: String,
+    pub rpc_url: String,
+    pub contract_address: String,
+    pub keeper_address: String,
+    pub gas_prices: String,
+}
+
+impl Chain {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            binary: std::env::var("CHAIN_BINARY").unwrap_or_else(|_| "joed".to_string()),
+            chain_id: std::env::var("CHAIN_ID").unwrap_or_else(|_| "joe-1".to_string()),
+            rpc_url: std::env::var("RPC_URL").context("RPC_URL is not set")?,
+            contract_address: std::env::var("CONTRACT_ADDRESS")
+                .context("CONTRACT_ADDRESS is not set")?,
+            keeper_address: std::env::var("KEEPER_ADDRESS").context("KEEPER_ADDRESS is not set")?,
+            gas_prices: std::env::var("GAS_PRICES").unwrap_or_else(|_| "0.025ujoe".to_string()),
+        })
+    }
+
+    /// Run a smart query against the hub and parse the `data` field of the response
+    pub fn query<T: DeserializeOwned>(&self, msg: &QueryMsg) -> Result<T> {
+        let output = Command::new(&self.binary)
+            .arg("q")
+            .arg("wasm")
+            .arg("contract-state")
+            .arg("smart")
+            .arg(&self.contract_address)
+            .arg(serde_json::to_string(msg).context("serializing query")?)
+            .arg("--node")
+            .arg(&self.rpc_url)
+            .arg("--chain-id")
+            .arg(&self.chain_id)
+            .arg("--output")
+            .arg("json")
+            .output()
+            .context("executing query")?;
+        if !output.status.success() {
+            bail!("query failed: {}", String::from_utf8_lossy(&output.stderr));
+        }
+
+        let parsed_json: serde_json::Value =
+            serde_json::from_slice(&output.stdout).context("parsing json from query")?;
+        serde_json::from_value(
+            parsed_json
+                .get("data")
+                .context("getting data field from query response")?
+                .to_owned(),
+        )
+        .context("parsing query response")
+    }
+
+    /// Broadcast an execute message to the hub from the keeper's account, returning the CLI output
+    pub fn execute(&self, msg: &ExecuteMsg) -> Result<String> {
+        let output = Command::new(&self.binary)
+            .arg("tx")
+            .arg("wasm")
+            .arg("execute")
+            .arg(&self.contract_address)
+            .arg(serde_json::to_string(msg).context("serializing execute message")?)
+            .arg("--from")
+            .arg(&self.keeper_address)
+            .arg("--node")
+            .arg(&self.rpc_url)
+            .arg("--chain-id")
+            .arg(&self.chain_id)
+            .arg("--gas")
+            .arg("auto")
+            .arg("--gas-adjustment")
+            .arg("1.5")
+            .arg("--gas-prices")
+            .arg(&self.gas_prices)
+            .arg("--broadcast-mode")
+            .arg("block")
+            .arg("-y")
+            .arg("--output")
+            .arg("json")
+            .output()
+            .context("executing tx")?;
+        if !output.status.success() {
+            bail!("tx failed: {}", String::from_utf8_lossy(&output.stderr));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
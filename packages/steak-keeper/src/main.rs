@@ -0,0 +1,111 @@
+//! Keeper for the Steak hub: polls the hub and sends `Reconcile`, `SubmitBatch` and `Harvest`
+//! when they are due, so operators don't need to hand-roll scripts.
+//!
+//! Configured through environment variables:
+//! - `RPC_URL`, `CONTRACT_ADDRESS`, `KEEPER_ADDRESS` (the key name or address to sign with)
+//! - `CHAIN_BINARY` (default `joed`), `CHAIN_ID` (default `joe-1`), `GAS_PRICES` (default `0.025ujoe`)
+//! - `POLL_INTERVAL_SECONDS` (default 60), `HARVEST_INTERVAL_SECONDS` (default 86400), used only
+//!   while the hub's mining subsystem is disabled
+//! - `DRY_RUN`: if set, print the planned transactions instead of sending them
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+use pfc_steak_api::hub::{Batch, QueryMsg};
+
+mod chain;
+mod plan;
+
+use chain::Chain;
+use plan::{plan, Action, Schedule, Snapshot};
+
+/// Page size when enumerating previous batches; the hub's maximum
+const PAGE_LIMIT: u32 = 30;
+
+fn main() -> Result<()> {
+    let chain = Chain::from_env()?;
+    let poll_interval = env_seconds("POLL_INTERVAL_SECONDS", 60)?;
+    let schedule = Schedule {
+        harvest_interval: env_seconds("HARVEST_INTERVAL_SECONDS", 86400)?,
+    };
+    let dry_run = std::env::var("DRY_RUN").is_ok();
+
+    let mut last_harvest = None;
+    loop {
+        match run_once(&chain, &schedule, last_harvest, dry_run) {
+            Ok(Some(time)) => last_harvest = Some(time),
+            Ok(None) => {}
+            Err(e) => println!("Error: {:#}", e),
+        }
+        std::thread::sleep(Duration::from_secs(poll_interval));
+    }
+}
+
+/// Take a snapshot of the hub and send whatever is due, returning the time of the harvest if one
+/// was sent
+fn run_once(
+    chain: &Chain,
+    schedule: &Schedule,
+    last_harvest: Option<u64>,
+    dry_run: bool,
+) -> Result<Option<u64>> {
+    let snapshot = Snapshot {
+        now: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("reading system time")?
+            .as_secs(),
+        pending_batch: chain.query(&QueryMsg::PendingBatch {})?,
+        previous_batches: query_previous_batches(chain)?,
+        state: chain.query(&QueryMsg::State {})?,
+        exchange_rate_floor: chain.query(&QueryMsg::ExchangeRateFloor {})?,
+        miner_params: chain.query(&QueryMsg::MinerParams {})?,
+        last_harvest,
+    };
+
+    let plan = plan(&snapshot, schedule);
+    for warning in &plan.warnings {
+        println!("Warning: {}", warning);
+    }
+
+    let mut harvested_at = None;
+    for action in plan.actions {
+        let msg = action.to_msg();
+        if dry_run {
+            println!("Would send: {}", serde_json::to_string(&msg)?);
+            continue;
+        }
+        println!("Sending {:?}", action);
+        let output = chain
+            .execute(&msg)
+            .with_context(|| format!("sending {:?}", action))?;
+        println!("{}", output);
+        if action == Action::Harvest {
+            harvested_at = Some(snapshot.now);
+        }
+    }
+    Ok(harvested_at)
+}
+
+fn query_previous_batches(chain: &Chain) -> Result<Vec<Batch>> {
+    let mut batches: Vec<Batch> = vec![];
+    loop {
+        let page: Vec<Batch> = chain.query(&QueryMsg::PreviousBatches {
+            start_after: batches.last().map(|batch| batch.id),
+            limit: Some(PAGE_LIMIT),
+        })?;
+        let done = page.len() < PAGE_LIMIT as usize;
+        batches.extend(page);
+        if done {
+            return Ok(batches);
+        }
+    }
+}
+
+fn env_seconds(key: &str, default: u64) -> Result<u64> {
+    match std::env::var(key) {
+        Ok(value) => value
+            .parse()
+            .with_context(|| format!("parsing {} as seconds", key)),
+        Err(_) => Ok(default),
+    }
+}
@@ -0,0 +1,219 @@
+use cosmwasm_std::Decimal;
+
+use pfc_steak_api::hub::{
+    Batch, ExchangeRateFloorResponse, ExecuteMsg, MinerParamsResponse, PendingBatch, StateResponse,
+};
+
+/// Everything the keeper reads from the hub before deciding what to do
+pub struct Snapshot {
+    /// Current time, in seconds
+    pub now: u64,
+    pub pending_batch: PendingBatch,
+    pub previous_batches: Vec<Batch>,
+    pub state: StateResponse,
+    pub exchange_rate_floor: ExchangeRateFloorResponse,
+    pub miner_params: MinerParamsResponse,
+    /// Time of the last harvest sent by this keeper, if any
+    pub last_harvest: Option<u64>,
+}
+
+/// How often the keeper harvests, when it is allowed to
+pub struct Schedule {
+    pub harvest_interval: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+    Reconcile,
+    SubmitBatch,
+    Harvest,
+}
+
+impl Action {
+    pub fn to_msg(self) -> ExecuteMsg {
+        match self {
+            Action::Reconcile => ExecuteMsg::Reconcile {},
+            Action::SubmitBatch => ExecuteMsg::SubmitBatch { exclude: vec![] },
+            Action::Harvest => ExecuteMsg::Harvest {},
+        }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Plan {
+    /// Transactions to send, in order
+    pub actions: Vec<Action>,
+    /// Problems found by the health checks, for the operator to look into
+    pub warnings: Vec<String>,
+}
+
+/// Decide which transactions are due. Reconciling goes first, as it only touches batches that
+/// finished unbonding; submitting and harvesting are held back while the exchange rate is below the
+/// hub's floor, as the hub would refuse them until a guardian acknowledges the slash
+pub fn plan(snapshot: &Snapshot, schedule: &Schedule) -> Plan {
+    let mut plan = Plan::default();
+
+    if snapshot
+        .previous_batches
+        .iter()
+        .any(|batch| !batch.reconciled && batch.est_unbond_end_time < snapshot.now)
+    {
+        plan.actions.push(Action::Reconcile);
+    }
+
+    let floor = &snapshot.exchange_rate_floor;
+    if let Some(last_exchange_rate) = floor.last_exchange_rate {
+        let min_exchange_rate = last_exchange_rate * (Decimal::one() - floor.epsilon);
+        if snapshot.state.exchange_rate < min_exchange_rate && !floor.slash_acknowledged {
+            plan.warnings.push(format!(
+                "exchange rate dropped from {} to {}; waiting for the guardian to acknowledge the slash",
+                last_exchange_rate, snapshot.state.exchange_rate
+            ));
+            return plan;
+        }
+    }
+
+    // An empty batch would have nothing to burn. Slices of unbond orders are only added at
+    // submission, so a batch made up of those alone has to be submitted by hand
+    let pending_batch = &snapshot.pending_batch;
+    if snapshot.now >= pending_batch.est_unbond_start_time
+        && !pending_batch.usteak_to_burn.is_zero()
+    {
+        plan.actions.push(Action::SubmitBatch);
+    }
+
+    // While mining is enabled, harvests are triggered by the proofs submitted by miners
+    if !snapshot.miner_params.enabled {
+        let harvest_due = snapshot.last_harvest.map_or(true, |last| {
+            snapshot.now >= last + schedule.harvest_interval
+        });
+        if harvest_due {
+            plan.actions.push(Action::Harvest);
+        }
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{Uint128, Uint64};
+    use pfc_steak_api::hub::MiningPowerNormalization;
+
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            now: 10000,
+            pending_batch: PendingBatch {
+                id: 2,
+                usteak_to_burn: Uint128::new(100),
+                est_unbond_start_time: 20000,
+                rollovers: 0,
+            },
+            previous_batches: vec![Batch {
+                id: 1,
+                reconciled: false,
+                total_shares: Uint128::new(100),
+                amount_unclaimed: Uint128::new(105),
+                est_unbond_end_time: 15000,
+                fee_rate: None,
+                exchange_rate: None,
+                submitter: None,
+                shortfalls: vec![],
+                backfilled: false,
+            }],
+            state: StateResponse {
+                total_usteak: Uint128::new(1000),
+                total_native: Uint128::new(1050),
+                exchange_rate: Decimal::from_ratio(105u128, 100u128),
+                unlocked_coins: vec![],
+                last_harvest_time: None,
+                last_reinvest_amount: Uint128::zero(),
+                total_fees_collected: Uint128::zero(),
+                total_rewards_compounded: Uint128::zero(),
+                restaked_native: Uint128::zero(),
+                absorbed_native: Uint128::zero(),
+                fee_escrow: Uint128::zero(),
+                donations: Uint128::zero(),
+                last_harvester: None,
+                last_reconciler: None,
+                last_batch_submitter: None,
+            },
+            exchange_rate_floor: ExchangeRateFloorResponse {
+                last_exchange_rate: Some(Decimal::from_ratio(105u128, 100u128)),
+                epsilon: Decimal::permille(1),
+                guardian: None,
+                slash_acknowledged: false,
+            },
+            miner_params: MinerParamsResponse {
+                entropy: "entropy".to_string(),
+                difficulty: Uint64::new(4),
+                enabled: true,
+                proof_cooldown_blocks: 0,
+                power_normalization: MiningPowerNormalization::Disabled,
+            },
+            last_harvest: None,
+        }
+    }
+
+    const SCHEDULE: Schedule = Schedule {
+        harvest_interval: 3600,
+    };
+
+    #[test]
+    fn nothing_due() {
+        assert_eq!(plan(&snapshot(), &SCHEDULE), Plan::default());
+    }
+
+    #[test]
+    fn submitting_and_reconciling() {
+        let mut snapshot = snapshot();
+        snapshot.now = 20000;
+
+        assert_eq!(
+            plan(&snapshot, &SCHEDULE).actions,
+            vec![Action::Reconcile, Action::SubmitBatch]
+        );
+    }
+
+    #[test]
+    fn skipping_empty_batch() {
+        let mut snapshot = snapshot();
+        snapshot.now = 20000;
+        snapshot.pending_batch.usteak_to_burn = Uint128::zero();
+
+        assert_eq!(plan(&snapshot, &SCHEDULE).actions, vec![Action::Reconcile]);
+    }
+
+    #[test]
+    fn harvesting_without_mining() {
+        let mut snapshot = snapshot();
+        snapshot.miner_params.enabled = false;
+        assert_eq!(plan(&snapshot, &SCHEDULE).actions, vec![Action::Harvest]);
+
+        snapshot.last_harvest = Some(7000);
+        assert_eq!(plan(&snapshot, &SCHEDULE).actions, vec![]);
+
+        snapshot.last_harvest = Some(6400);
+        assert_eq!(plan(&snapshot, &SCHEDULE).actions, vec![Action::Harvest]);
+    }
+
+    #[test]
+    fn holding_back_below_floor() {
+        let mut snapshot = snapshot();
+        snapshot.now = 20000;
+        snapshot.miner_params.enabled = false;
+        snapshot.state.exchange_rate = Decimal::one();
+
+        let res = plan(&snapshot, &SCHEDULE);
+        assert_eq!(res.actions, vec![Action::Reconcile]);
+        assert_eq!(res.warnings.len(), 1);
+
+        snapshot.exchange_rate_floor.slash_acknowledged = true;
+        assert_eq!(
+            plan(&snapshot, &SCHEDULE).actions,
+            vec![Action::Reconcile, Action::SubmitBatch, Action::Harvest]
+        );
+    }
+}
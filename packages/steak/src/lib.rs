@@ -1,4 +1,7 @@
-pub mod hub;
+pub use pfc_steak_api::{chains, hub};
+
+#[cfg(feature = "planning")]
+pub mod planning;
 
 // this was copied from eris-staking's branch of STEAK.
 //
@@ -0,0 +1,504 @@
+//! The math the hub uses to plan its moves: how much Steak to mint or Native Token to unbond, and
+//! how to spread (un/re)delegations across validators.
+//!
+//! The hub calls these very functions, so keepers and simulations built on this module reach the
+//! same numbers as the chain does. Enabled by the `planning` feature.
+
+use std::{cmp, cmp::Ordering, ops::Mul};
+
+use cosmwasm_std::{Decimal, StdError, StdResult, Uint128};
+use serde::{Deserialize, Serialize};
+
+use crate::hub::Batch;
+
+//--------------------------------------------------------------------------------------------------
+// Moves
+//--------------------------------------------------------------------------------------------------
+
+/// An amount of `denom` delegated to `validator`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Delegation {
+    pub validator: String,
+    pub amount: u128,
+    pub denom: String,
+}
+
+impl Delegation {
+    pub fn new(validator: &str, amount: u128, denom: &str) -> Self {
+        Self {
+            validator: validator.to_string(),
+            amount,
+            denom: denom.to_string(),
+        }
+    }
+}
+
+/// An amount of `denom` to undelegate from `validator`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Undelegation {
+    pub validator: String,
+    pub amount: u128,
+    pub denom: String,
+}
+
+impl Undelegation {
+    pub fn new(validator: &str, amount: u128, denom: &str) -> Self {
+        Self {
+            validator: validator.to_string(),
+            amount,
+            denom: denom.to_string(),
+        }
+    }
+}
+
+/// An amount of `denom` to redelegate from validator `src` to validator `dst`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Redelegation {
+    pub src: String,
+    pub dst: String,
+    pub amount: u128,
+    pub denom: String,
+}
+
+impl Redelegation {
+    pub fn new(src: &str, dst: &str, amount: u128, denom: &str) -> Self {
+        Self {
+            src: src.to_string(),
+            dst: dst.to_string(),
+            amount,
+            denom: denom.into(),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Minting/burning logics
+//--------------------------------------------------------------------------------------------------
+
+/// Compute the amount of Steak token to mint for a specific Native Token stake amount. If current total
+/// staked amount is zero, we use 1 usteak = 1 native; otherwise, we calculate base on the current
+/// native per ustake ratio.
+pub fn compute_mint_amount(
+    usteak_supply: Uint128,
+    native_to_bond: Uint128,
+    current_delegations: &[Delegation],
+) -> Uint128 {
+    let native_bonded: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    if native_bonded == 0 {
+        native_to_bond
+    } else {
+        usteak_supply.multiply_ratio(native_to_bond, native_bonded)
+    }
+}
+
+/// Compute the amount of `native` to unbond for a specific `usteak` burn amount
+///
+/// There is no way `usteak` total supply is zero when the user is senting a non-zero amount of `usteak`
+/// to burn, so we don't need to handle division-by-zero here
+pub fn compute_unbond_amount(
+    usteak_supply: Uint128,
+    usteak_to_burn: Uint128,
+    current_delegations: &[Delegation],
+) -> Uint128 {
+    let native_bonded: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    Uint128::new(native_bonded).multiply_ratio(usteak_to_burn, usteak_supply)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Delegation logics
+//--------------------------------------------------------------------------------------------------
+
+/// Whether the validator at position `i` gets one unit of the `remainder` left over from splitting
+/// an amount evenly among `validator_count` validators. The remainder goes to the validators from
+/// position `remainder_offset` onwards, wrapping around
+fn gets_remainder(
+    i: usize,
+    validator_count: usize,
+    remainder: u128,
+    remainder_offset: usize,
+) -> bool {
+    let position = (i + validator_count - remainder_offset % validator_count) % validator_count;
+    (position as u128) < remainder
+}
+
+/// Given the current delegations made to validators, and a specific amount of `native` to unstake,
+/// compute the undelegations to make such that the delegated amount to each validator is as even
+/// as possible.
+///
+/// This function is based on Lido's implementation:
+/// https://github.com/lidofinance/lido-terra-contracts/blob/v1.0.2/contracts/lido_terra_validators_registry/src/common.rs#L55-102
+pub fn compute_undelegations(
+    native_to_unbond: Uint128,
+    current_delegations: &[Delegation],
+    denom: &str,
+    remainder_offset: usize,
+) -> Vec<Undelegation> {
+    let native_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    let validator_count = current_delegations.len() as u128;
+
+    let native_to_distribute = native_staked - native_to_unbond.u128();
+    let native_per_validator = native_to_distribute / validator_count;
+    let remainder = native_to_distribute % validator_count;
+
+    let mut new_undelegations: Vec<Undelegation> = vec![];
+    let mut native_available = native_to_unbond.u128();
+    for (i, d) in current_delegations.iter().enumerate() {
+        let remainder_for_validator: u128 = u128::from(gets_remainder(
+            i,
+            current_delegations.len(),
+            remainder,
+            remainder_offset,
+        ));
+        let native_for_validator = native_per_validator + remainder_for_validator;
+
+        let mut native_to_undelegate = if d.amount < native_for_validator {
+            0
+        } else {
+            d.amount - native_for_validator
+        };
+
+        native_to_undelegate = cmp::min(native_to_undelegate, native_available);
+        native_available -= native_to_undelegate;
+
+        if native_to_undelegate > 0 {
+            new_undelegations.push(Undelegation::new(&d.validator, native_to_undelegate, denom));
+        }
+
+        if native_available == 0 {
+            break;
+        }
+    }
+
+    new_undelegations
+}
+
+/// Same as `compute_undelegations`, except that no validator is left with less than
+/// `min_delegation`: whatever would take a validator below the floor is undelegated from the
+/// validators with the most to spare instead. A validator already below the floor isn't
+/// undelegated from at all. Only if the others can't make up the amount does the unbond go below
+/// the floor, again starting with the largest delegations
+pub fn compute_undelegations_with_floor(
+    native_to_unbond: Uint128,
+    current_delegations: &[Delegation],
+    denom: &str,
+    remainder_offset: usize,
+    min_delegation: Uint128,
+) -> Vec<Undelegation> {
+    let undelegations = compute_undelegations(
+        native_to_unbond,
+        current_delegations,
+        denom,
+        remainder_offset,
+    );
+    let min_delegation = min_delegation.u128();
+    if min_delegation == 0 {
+        return undelegations;
+    }
+
+    let mut amounts: Vec<u128> = current_delegations
+        .iter()
+        .map(|d| {
+            undelegations
+                .iter()
+                .find(|u| u.validator == d.validator)
+                .map_or(0, |u| u.amount)
+        })
+        .collect();
+
+    let mut native_to_spill = 0;
+    for (d, amount) in current_delegations.iter().zip(amounts.iter_mut()) {
+        let max_amount = d.amount.saturating_sub(min_delegation);
+        if *amount > max_amount {
+            native_to_spill += *amount - max_amount;
+            *amount = max_amount;
+        }
+    }
+
+    // Largest remaining delegations first, keeping the original order between equals
+    let mut order: Vec<usize> = (0..current_delegations.len()).collect();
+    order.sort_by_key(|&i| cmp::Reverse(current_delegations[i].amount - amounts[i]));
+
+    for floor in [min_delegation, 0] {
+        for &i in &order {
+            if native_to_spill == 0 {
+                break;
+            }
+            let spare = (current_delegations[i].amount - amounts[i]).saturating_sub(floor);
+            let amount = cmp::min(spare, native_to_spill);
+            amounts[i] += amount;
+            native_to_spill -= amount;
+        }
+    }
+
+    current_delegations
+        .iter()
+        .zip(amounts)
+        .filter(|(_, amount)| *amount > 0)
+        .map(|(d, amount)| Undelegation::new(&d.validator, amount, denom))
+        .collect()
+}
+
+/// Given a validator who is to be removed from the whitelist, and current delegations made to other
+/// validators, compute the new delegations to make such that the delegated amount to each validator
+// is as even as possible.
+///
+/// This function is based on Lido's implementation:
+/// https://github.com/lidofinance/lido-terra-contracts/blob/v1.0.2/contracts/lido_terra_validators_registry/src/common.rs#L19-L53
+pub fn compute_redelegations_for_removal(
+    delegation_to_remove: &Delegation,
+    current_delegations: &[Delegation],
+    denom: &str,
+    remainder_offset: usize,
+) -> Vec<Redelegation> {
+    let native_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    let validator_count = current_delegations.len() as u128;
+
+    let native_to_distribute = native_staked + delegation_to_remove.amount;
+    let native_per_validator = native_to_distribute / validator_count;
+    let remainder = native_to_distribute % validator_count;
+
+    let mut new_redelegations: Vec<Redelegation> = vec![];
+    let mut native_available = delegation_to_remove.amount;
+    for (i, d) in current_delegations.iter().enumerate() {
+        let remainder_for_validator: u128 = u128::from(gets_remainder(
+            i,
+            current_delegations.len(),
+            remainder,
+            remainder_offset,
+        ));
+        let native_for_validator = native_per_validator + remainder_for_validator;
+
+        let mut native_to_redelegate = if d.amount > native_for_validator {
+            0
+        } else {
+            native_for_validator - d.amount
+        };
+
+        native_to_redelegate = cmp::min(native_to_redelegate, native_available);
+        native_available -= native_to_redelegate;
+
+        if native_to_redelegate > 0 {
+            new_redelegations.push(Redelegation::new(
+                &delegation_to_remove.validator,
+                &d.validator,
+                native_to_redelegate,
+                denom,
+            ));
+        }
+
+        if native_available == 0 {
+            break;
+        }
+    }
+
+    new_redelegations
+}
+
+/// The share of `total_delegated_amount` a validator is targeted to hold, in proportion to its
+/// mining power
+pub fn compute_target_delegation_from_mining_power(
+    total_delegated_amount: Uint128,
+    validator_mining_power: Uint128,
+    total_mining_power: Uint128,
+) -> StdResult<Uint128> {
+    if validator_mining_power > total_mining_power {
+        return Err(StdError::generic_err(
+            "validator mining power cannot be greater than total mining power",
+        ));
+    }
+    let expected_delegated_amount =
+        Decimal::from_ratio(validator_mining_power, total_mining_power).mul(total_delegated_amount);
+    Ok(expected_delegated_amount)
+}
+
+/// Pick the validator that should receive a deposit: the one furthest below its target delegation.
+/// If every validator has reached its target, the one with the smallest delegation among those that
+/// have not reached their cap is picked instead.
+pub fn select_validator_for_deposit(
+    current_delegations: &[Delegation],
+    load_target_delegation: impl Fn(&Delegation) -> StdResult<Uint128>,
+    load_cap: impl Fn(&Delegation) -> StdResult<Option<Uint128>>,
+) -> StdResult<String> {
+    let mut furthest_below: Option<(&Delegation, u128)> = None;
+    let mut smallest_uncapped: Option<&Delegation> = None;
+    for d in current_delegations {
+        let target = load_target_delegation(d)?.u128();
+        if target > d.amount {
+            let gap = target - d.amount;
+            if furthest_below.map_or(true, |(_, largest_gap)| gap > largest_gap) {
+                furthest_below = Some((d, gap));
+            }
+        }
+        let below_cap = load_cap(d)?.map_or(true, |cap| d.amount < cap.u128());
+        if below_cap && smallest_uncapped.map_or(true, |smallest| d.amount < smallest.amount) {
+            smallest_uncapped = Some(d);
+        }
+    }
+
+    furthest_below
+        .map(|(d, _)| d)
+        .or(smallest_uncapped)
+        .map(|d| d.validator.clone())
+        .ok_or_else(|| StdError::generic_err("all active validators have reached their cap"))
+}
+
+/// Compute redelegation moves that will make each validator's delegation the targeted amount (hopefully
+/// this sentence makes sense)
+///
+/// This algorithm does not guarantee the minimal number of moves, but is the best I can some up with...
+///
+/// Rewrite to compute moves off-chain and verify them on-chain?
+pub fn compute_redelegations_for_rebalancing(
+    validators_active: Vec<String>,
+    current_delegations: &[Delegation],
+    min_difference: Uint128,
+    remainder_offset: usize,
+    load_target_delegation: impl Fn(&Delegation) -> StdResult<Uint128>,
+) -> StdResult<Vec<Redelegation>> {
+    let native_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    let validator_count = validators_active.len() as u128;
+
+    let remainder = native_staked % validator_count;
+
+    // If a validator's current delegated amount is greater than the target amount, native will be
+    // redelegated _from_ them. They will be put in `src_validators` vector
+    // If a validator's current delegated amount is smaller than the target amount, native will be
+    // redelegated _to_ them. They will be put in `dst_validators` vector
+    let mut src_delegations: Vec<Delegation> = vec![];
+    let mut dst_delegations: Vec<Delegation> = vec![];
+    for (i, d) in current_delegations.iter().enumerate() {
+        let remainder_for_validator: u128 = u128::from(gets_remainder(
+            i,
+            current_delegations.len(),
+            remainder,
+            remainder_offset,
+        ));
+        let native_for_validator = load_target_delegation(d)?.u128() + remainder_for_validator;
+        // eprintln!("{} amount ={} native={} min={}", d.validator, d.amount, native_for_validator, min_difference);
+        match d.amount.cmp(&native_for_validator) {
+            Ordering::Greater => {
+                if d.amount - native_for_validator > min_difference.u128() {
+                    src_delegations.push(Delegation::new(
+                        &d.validator,
+                        d.amount - native_for_validator,
+                        &d.denom,
+                    ));
+                }
+            }
+            Ordering::Less => {
+                if validators_active.contains(&d.validator)
+                    && native_for_validator - d.amount > min_difference.u128()
+                {
+                    dst_delegations.push(Delegation::new(
+                        &d.validator,
+                        native_for_validator - d.amount,
+                        &d.denom,
+                    ));
+                }
+            }
+            Ordering::Equal => (),
+        }
+    }
+
+    let mut new_redelegations: Vec<Redelegation> = vec![];
+    while !src_delegations.is_empty() && !dst_delegations.is_empty() {
+        let src_delegation = src_delegations[0].clone();
+        let dst_delegation = dst_delegations[0].clone();
+        let native_to_redelegate = cmp::min(src_delegation.amount, dst_delegation.amount);
+
+        if src_delegation.amount == native_to_redelegate {
+            src_delegations.remove(0);
+        } else {
+            src_delegations[0].amount -= native_to_redelegate;
+        }
+
+        if dst_delegation.amount == native_to_redelegate {
+            dst_delegations.remove(0);
+        } else {
+            dst_delegations[0].amount -= native_to_redelegate;
+        }
+        new_redelegations.push(Redelegation::new(
+            &src_delegation.validator,
+            &dst_delegation.validator,
+            native_to_redelegate,
+            &src_delegation.denom,
+        ));
+    }
+    // eprintln!("new redelegations ={:?}", new_redelegations);
+
+    Ok(new_redelegations)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Batch logics
+//--------------------------------------------------------------------------------------------------
+
+/// If the received native amount after the unbonding period is less than expected, e.g. due to rounding
+/// error or the validator(s) being slashed, then deduct the difference in amount evenly from each
+/// unreconciled batch.
+///
+/// The idea of "reconciling" is based on Stader's implementation:
+/// https://github.com/stader-labs/stader-liquid-token/blob/v0.2.1/contracts/staking/src/contract.rs#L968-L1048
+pub fn reconcile_batches(batches: &mut [Batch], native_to_deduct: Uint128) {
+    let batch_count = batches.len() as u128;
+    let native_per_batch = native_to_deduct.u128() / batch_count;
+    let remainder = native_to_deduct.u128() % batch_count;
+
+    for (i, batch) in batches.iter_mut().enumerate() {
+        let remainder_for_batch: u128 = u128::from((i + 1) as u128 <= remainder) as u128;
+        let native_for_batch = native_per_batch + remainder_for_batch;
+
+        batch.amount_unclaimed -= Uint128::new(native_for_batch);
+        batch.reconciled = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_target_delegation_from_mining_power() {
+        let total_delegated_amount = Uint128::from(1_000_000u128);
+        let validator_mining_power = Uint128::from(100_000u128);
+        let total_mining_power = Uint128::from(1_000_000u128);
+        let expected_delegated_amount = Uint128::from(100_000u128);
+        assert_eq!(
+            compute_target_delegation_from_mining_power(
+                total_delegated_amount,
+                validator_mining_power,
+                total_mining_power
+            )
+            .unwrap(),
+            expected_delegated_amount
+        );
+
+        let total_delegated_amount = Uint128::from(1_000_000u128);
+        let validator_mining_power = Uint128::from(5_000_000u128);
+        let total_mining_power = Uint128::from(15_000_000u128);
+        let expected_delegated_amount = Uint128::from(333_333u128);
+        assert_eq!(
+            compute_target_delegation_from_mining_power(
+                total_delegated_amount,
+                validator_mining_power,
+                total_mining_power
+            )
+            .unwrap(),
+            expected_delegated_amount
+        );
+
+        let total_delegated_amount = Uint128::from(1_000_000u128);
+        let total_mining_power = Uint128::from(5_000_000u128);
+        let validator_mining_power = Uint128::from(1_000_000u128);
+        let expected_delegated_amount = Uint128::from(200_000u128);
+        assert_eq!(
+            compute_target_delegation_from_mining_power(
+                total_delegated_amount,
+                validator_mining_power,
+                total_mining_power
+            )
+            .unwrap(),
+            expected_delegated_amount
+        );
+    }
+}